@@ -0,0 +1,128 @@
+//! The code scan API, which runs the security detectors of the service on uploaded source code.
+
+use std::time::Duration;
+
+use amzn_codewhisperer_client::types::Origin::Cli;
+use amzn_codewhisperer_client::types::{
+    ArtifactType,
+    CodeAnalysisFindingsSchema,
+    CodeAnalysisScope,
+    CodeAnalysisStatus,
+    ContentChecksumType,
+    ProgrammingLanguage,
+    UploadIntent,
+};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tracing::debug;
+
+use super::{
+    ApiClient,
+    ApiClientError,
+};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+impl ApiClient {
+    /// Uploads `zip`, an archive of source files, and scans it for security issues. Returns the
+    /// JSON of every page of findings, in the `codeanalysis/findings/1.0` schema.
+    ///
+    /// `language` is the main language of the files, such as `python`.
+    pub async fn scan_code(
+        &self,
+        zip: Vec<u8>,
+        language: &str,
+        timeout: Duration,
+    ) -> Result<Vec<String>, ApiClientError> {
+        let profile_arn = self.profile.as_ref().map(|p| p.arn.clone());
+
+        let upload = self
+            .client
+            .create_upload_url()
+            .content_checksum(BASE64.encode(Sha256::digest(&zip)))
+            .content_checksum_type(ContentChecksumType::Sha256)
+            .content_length(zip.len() as i64)
+            .artifact_type(ArtifactType::SourceCode)
+            .upload_intent(UploadIntent::FullProjectSecurityScan)
+            .set_profile_arn(profile_arn.clone())
+            .send()
+            .await?;
+
+        let mut request = crate::request::new_client()
+            .map_err(|err| ApiClientError::ArtifactUpload(err.to_string()))?
+            .put(upload.upload_url())
+            .body(zip);
+        for (name, value) in upload.request_headers().into_iter().flatten() {
+            request = request.header(name, value);
+        }
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| ApiClientError::ArtifactUpload(err.to_string()))?;
+
+        let job = self
+            .client
+            .start_code_analysis()
+            .artifacts(ArtifactType::SourceCode, upload.upload_id())
+            .programming_language(ProgrammingLanguage::builder().language_name(language).build()?)
+            .scope(CodeAnalysisScope::Project)
+            .client_type(Cli)
+            .set_profile_arn(profile_arn.clone())
+            .send()
+            .await?;
+        let job_id = job.job_id().to_string();
+        debug!(%job_id, "started a code scan");
+
+        let started = std::time::Instant::now();
+        let mut status = job.status().clone();
+        let mut error_message = job.error_message().map(str::to_string);
+        while status == CodeAnalysisStatus::Pending {
+            if started.elapsed() > timeout {
+                return Err(ApiClientError::CodeScanFailed(format!(
+                    "the scan did not finish within {} seconds",
+                    timeout.as_secs()
+                )));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let output = self
+                .client
+                .get_code_analysis()
+                .job_id(&job_id)
+                .set_profile_arn(profile_arn.clone())
+                .send()
+                .await?;
+            status = output.status().clone();
+            error_message = output.error_message().map(str::to_string);
+        }
+        if status != CodeAnalysisStatus::Completed {
+            return Err(ApiClientError::CodeScanFailed(
+                error_message.unwrap_or_else(|| status.as_str().to_string()),
+            ));
+        }
+
+        let mut pages = Vec::new();
+        let mut next_token = None;
+        loop {
+            let output = self
+                .client
+                .list_code_analysis_findings()
+                .job_id(&job_id)
+                .code_analysis_findings_schema(CodeAnalysisFindingsSchema::CodeanalysisFindingsV10)
+                .set_next_token(next_token)
+                .set_profile_arn(profile_arn.clone())
+                .send()
+                .await?;
+            pages.push(output.code_analysis_findings().to_string());
+            next_token = output.next_token().map(str::to_string);
+            if next_token.is_none() {
+                break;
+            }
+        }
+        Ok(pages)
+    }
+}
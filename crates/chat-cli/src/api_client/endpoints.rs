@@ -4,8 +4,11 @@ use aws_config::Region;
 use serde_json::Value;
 use tracing::error;
 
-use crate::database::Database;
 use crate::database::settings::Setting;
+use crate::database::{
+    AuthProfile,
+    Database,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Endpoint {
@@ -25,39 +28,56 @@ impl Endpoint {
     };
 
     pub fn configured_value(database: &Database) -> Self {
-        let (endpoint, region) = if let Some(Value::Object(o)) = database.settings.get(Setting::ApiCodeWhispererService)
-        {
+        if let Some(Value::Object(o)) = database.settings.get(Setting::ApiCodeWhispererService) {
             // The following branch is evaluated in case the user has set their own endpoint.
-            (
-                o.get("endpoint").and_then(|v| v.as_str()).map(|v| v.to_owned()),
-                o.get("region").and_then(|v| v.as_str()).map(|v| v.to_owned()),
-            )
-        } else if let Ok(Some(profile)) = database.get_auth_profile() {
-            // The following branch is evaluated in the case of user profile being set.
-            let region = profile.arn.split(':').nth(3).unwrap_or_default().to_owned();
-            match Self::CODEWHISPERER_ENDPOINTS
-                .iter()
-                .find(|e| e.region().as_ref() == region)
-            {
-                Some(endpoint) => (Some(endpoint.url().to_owned()), Some(region)),
-                None => {
-                    error!("Failed to find endpoint for region: {region}");
-                    (None, None)
+            let endpoint = o.get("endpoint").and_then(|v| v.as_str());
+            let region = o.get("region").and_then(|v| v.as_str());
+            return match (endpoint, region) {
+                (Some(endpoint), Some(region)) => Self {
+                    url: endpoint.to_owned().into(),
+                    region: Region::new(region.to_owned()),
                 },
-            }
-        } else {
-            (None, None)
+                _ => Endpoint::DEFAULT_ENDPOINT,
+            };
+        }
+
+        let Ok(Some(profile)) = database.get_auth_profile() else {
+            return Endpoint::DEFAULT_ENDPOINT;
         };
 
-        match (endpoint, region) {
-            (Some(endpoint), Some(region)) => Self {
-                url: endpoint.clone().into(),
-                region: Region::new(region.clone()),
+        // The following is evaluated in the case of user profile being set.
+        let region = Self::pinned_region(database, &profile)
+            .unwrap_or_else(|| profile.arn.split(':').nth(3).unwrap_or_default().to_owned());
+        match Self::for_region(&region) {
+            Some(endpoint) => endpoint,
+            None => {
+                error!("Failed to find endpoint for region: {region}");
+                Endpoint::DEFAULT_ENDPOINT
             },
-            _ => Endpoint::DEFAULT_ENDPOINT,
         }
     }
 
+    /// Returns the endpoint serving `region`, if any.
+    pub fn for_region(region: &str) -> Option<Self> {
+        Self::CODEWHISPERER_ENDPOINTS
+            .iter()
+            .find(|e| e.region().as_ref() == region)
+            .cloned()
+    }
+
+    /// Returns the region pinned for `profile` in [Setting::ApiProfileRegions], matching on the
+    /// profile ARN first and the profile name second.
+    fn pinned_region(database: &Database, profile: &AuthProfile) -> Option<String> {
+        let Some(Value::Object(pins)) = database.settings.get(Setting::ApiProfileRegions) else {
+            return None;
+        };
+
+        [profile.arn.as_str(), profile.profile_name.as_str()]
+            .into_iter()
+            .find_map(|key| pins.get(key).and_then(|v| v.as_str()))
+            .map(|region| region.to_owned())
+    }
+
     pub(crate) fn url(&self) -> &str {
         &self.url
     }
@@ -88,4 +108,42 @@ mod tests {
         Url::parse(custom.url()).unwrap();
         assert_eq!(custom.region(), &Region::new("us-west-2"));
     }
+
+    #[tokio::test]
+    async fn test_profile_region_pinning() {
+        let mut database = Database::new().await.unwrap();
+        database
+            .set_auth_profile(&AuthProfile {
+                arn: "arn:aws:codewhisperer:us-east-1:123456789012:profile/ABC".into(),
+                profile_name: "team".into(),
+            })
+            .unwrap();
+        assert_eq!(Endpoint::configured_value(&database), Endpoint::DEFAULT_ENDPOINT);
+
+        database
+            .settings
+            .set(
+                Setting::ApiProfileRegions,
+                serde_json::json!({ "team": "eu-central-1" }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(Endpoint::configured_value(&database), Endpoint::FRA_ENDPOINT);
+
+        database
+            .settings
+            .set(
+                Setting::ApiProfileRegions,
+                serde_json::json!({
+                    "team": "eu-central-1",
+                    "arn:aws:codewhisperer:us-east-1:123456789012:profile/ABC": "us-east-1",
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(Endpoint::configured_value(&database), Endpoint::DEFAULT_ENDPOINT);
+
+        assert_eq!(Endpoint::for_region("eu-central-1"), Some(Endpoint::FRA_ENDPOINT));
+        assert_eq!(Endpoint::for_region("us-west-2"), None);
+    }
 }
@@ -1,10 +1,14 @@
 use amzn_codewhisperer_client::operation::create_subscription_token::CreateSubscriptionTokenError;
+use amzn_codewhisperer_client::operation::create_upload_url::CreateUploadUrlError;
 use amzn_codewhisperer_client::operation::generate_completions::GenerateCompletionsError;
+use amzn_codewhisperer_client::operation::get_code_analysis::GetCodeAnalysisError;
 use amzn_codewhisperer_client::operation::get_profile::GetProfileError;
 use amzn_codewhisperer_client::operation::list_available_customizations::ListAvailableCustomizationsError;
 use amzn_codewhisperer_client::operation::list_available_models::ListAvailableModelsError;
 use amzn_codewhisperer_client::operation::list_available_profiles::ListAvailableProfilesError;
+use amzn_codewhisperer_client::operation::list_code_analysis_findings::ListCodeAnalysisFindingsError;
 use amzn_codewhisperer_client::operation::send_telemetry_event::SendTelemetryEventError;
+use amzn_codewhisperer_client::operation::start_code_analysis::StartCodeAnalysisError;
 pub use amzn_codewhisperer_streaming_client::operation::generate_assistant_response::GenerateAssistantResponseError;
 use amzn_codewhisperer_streaming_client::types::error::ChatResponseStreamError as CodewhispererChatResponseStreamError;
 use amzn_consolas_client::operation::generate_recommendations::GenerateRecommendationsError;
@@ -104,6 +108,23 @@ pub enum ApiClientError {
 
     #[error(transparent)]
     GetProfileError(#[from] SdkError<GetProfileError, HttpResponse>),
+
+    // Code scan errors
+    #[error("{}", SdkErrorDisplay(.0))]
+    CreateUploadUrl(#[from] SdkError<CreateUploadUrlError, HttpResponse>),
+    #[error("failed to upload the code to scan: {}", .0)]
+    ArtifactUpload(String),
+    #[error("{}", SdkErrorDisplay(.0))]
+    StartCodeAnalysis(#[from] SdkError<StartCodeAnalysisError, HttpResponse>),
+    #[error("{}", SdkErrorDisplay(.0))]
+    GetCodeAnalysis(#[from] SdkError<GetCodeAnalysisError, HttpResponse>),
+    #[error("{}", SdkErrorDisplay(.0))]
+    ListCodeAnalysisFindings(#[from] SdkError<ListCodeAnalysisFindingsError, HttpResponse>),
+    #[error("the code scan failed: {}", .0)]
+    CodeScanFailed(String),
+
+    #[error(transparent)]
+    Request(#[from] crate::request::RequestError),
 }
 
 impl ApiClientError {
@@ -130,6 +151,56 @@ impl ApiClientError {
             Self::ListAvailableModelsError(e) => sdk_status_code(e),
             Self::DefaultModelNotFound => None,
             Self::GetProfileError(e) => sdk_status_code(e),
+            Self::CreateUploadUrl(e) => sdk_status_code(e),
+            Self::StartCodeAnalysis(e) => sdk_status_code(e),
+            Self::GetCodeAnalysis(e) => sdk_status_code(e),
+            Self::ListCodeAnalysisFindings(e) => sdk_status_code(e),
+            Self::ArtifactUpload(_) | Self::CodeScanFailed(_) | Self::Request(_) => None,
+        }
+    }
+
+    /// Whether the request failed before a response was received, e.g. due to a DNS failure,
+    /// connection reset, or timeout.
+    pub fn is_connectivity_error(&self) -> bool {
+        match self {
+            Self::GenerateCompletions(e) => sdk_is_connectivity_error(e),
+            Self::GenerateRecommendations(e) => sdk_is_connectivity_error(e),
+            Self::ListAvailableCustomizations(e) => sdk_is_connectivity_error(e),
+            Self::ListAvailableServices(e) => sdk_is_connectivity_error(e),
+            Self::CodewhispererGenerateAssistantResponse(e) => sdk_is_connectivity_error(e),
+            Self::QDeveloperSendMessage(e) => sdk_is_connectivity_error(e),
+            Self::CodewhispererChatResponseStream(e) => sdk_is_connectivity_error(e),
+            Self::QDeveloperChatResponseStream(e) => sdk_is_connectivity_error(e),
+            Self::ListAvailableProfilesError(e) => sdk_is_connectivity_error(e),
+            Self::SendTelemetryEvent(e) => sdk_is_connectivity_error(e),
+            Self::CreateSubscriptionToken(e) => sdk_is_connectivity_error(e),
+            Self::ListAvailableModelsError(e) => sdk_is_connectivity_error(e),
+            Self::GetProfileError(e) => sdk_is_connectivity_error(e),
+            Self::CreateUploadUrl(e) => sdk_is_connectivity_error(e),
+            Self::StartCodeAnalysis(e) => sdk_is_connectivity_error(e),
+            Self::GetCodeAnalysis(e) => sdk_is_connectivity_error(e),
+            Self::ListCodeAnalysisFindings(e) => sdk_is_connectivity_error(e),
+            Self::QuotaBreach { .. }
+            | Self::ContextWindowOverflow { .. }
+            | Self::SmithyBuild(_)
+            | Self::AuthError(_)
+            | Self::ModelOverloadedError { .. }
+            | Self::MonthlyLimitReached { .. }
+            | Self::Credentials(_)
+            | Self::DefaultModelNotFound
+            | Self::ArtifactUpload(_)
+            | Self::CodeScanFailed(_)
+            | Self::Request(_) => false,
+        }
+    }
+
+    /// Whether a response stream broke off for a reason likely to go away, such as a dropped
+    /// connection or throttling, so that the request is worth sending again.
+    pub fn is_interrupted_stream(&self) -> bool {
+        match self {
+            Self::CodewhispererChatResponseStream(e) => sdk_is_transient_error(e),
+            Self::QDeveloperChatResponseStream(e) => sdk_is_transient_error(e),
+            _ => false,
         }
     }
 }
@@ -158,6 +229,13 @@ impl ReasonCode for ApiClientError {
             Self::ListAvailableModelsError(e) => sdk_error_code(e),
             Self::DefaultModelNotFound => "DefaultModelNotFound".to_string(),
             Self::GetProfileError(e) => sdk_error_code(e),
+            Self::CreateUploadUrl(e) => sdk_error_code(e),
+            Self::ArtifactUpload(_) => "ArtifactUploadError".to_string(),
+            Self::StartCodeAnalysis(e) => sdk_error_code(e),
+            Self::GetCodeAnalysis(e) => sdk_error_code(e),
+            Self::ListCodeAnalysisFindings(e) => sdk_error_code(e),
+            Self::CodeScanFailed(_) => "CodeScanFailed".to_string(),
+            Self::Request(_) => "RequestError".to_string(),
         }
     }
 }
@@ -168,6 +246,23 @@ fn sdk_error_code<T: ProvideErrorMetadata, R>(e: &SdkError<T, R>) -> String {
         .unwrap_or_else(|| e.to_string())
 }
 
+fn sdk_is_connectivity_error<E, R>(e: &SdkError<E, R>) -> bool {
+    matches!(e, SdkError::DispatchFailure(_) | SdkError::TimeoutError(_))
+}
+
+fn sdk_is_transient_error<E: ProvideErrorMetadata, R>(e: &SdkError<E, R>) -> bool {
+    match e {
+        SdkError::DispatchFailure(_) | SdkError::TimeoutError(_) | SdkError::ResponseError(_) => true,
+        SdkError::ServiceError(_) => e
+            .as_service_error()
+            .and_then(|se| se.meta().code())
+            .is_some_and(|code| {
+                code.contains("Throttling") || matches!(code, "InternalServerException" | "ServiceUnavailableException")
+            }),
+        _ => false,
+    }
+}
+
 fn sdk_status_code<E>(e: &SdkError<E, Response>) -> Option<u16> {
     e.raw_response().map(|res| res.status().as_u16())
 }
@@ -238,6 +333,12 @@ mod tests {
                 raw_message(),
             )),
             ApiClientError::SmithyBuild(aws_smithy_types::error::operation::BuildError::other("<other>")),
+            ApiClientError::StartCodeAnalysis(SdkError::service_error(
+                StartCodeAnalysisError::unhandled("<unhandled>"),
+                response(),
+            )),
+            ApiClientError::ArtifactUpload("<upload>".to_string()),
+            ApiClientError::Request(crate::request::RequestError::TlsCaBundle("<bundle>".to_string())),
         ]
     }
 
@@ -248,4 +349,14 @@ mod tests {
             println!("{error} {error:?}");
         }
     }
+
+    #[test]
+    fn test_is_connectivity_error() {
+        for error in all_errors() {
+            assert!(!error.is_connectivity_error(), "{error:?}");
+        }
+
+        let error = ApiClientError::GenerateCompletions(SdkError::timeout_error("<timeout>"));
+        assert!(error.is_connectivity_error());
+    }
 }
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::interceptors::Intercept;
+use aws_smithy_runtime_api::client::interceptors::context::BeforeTransmitInterceptorContextMut;
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_runtime_api::http::Headers;
+use aws_smithy_types::config_bag::ConfigBag;
+use parking_lot::Mutex;
+use ring::{
+    digest,
+    hmac,
+};
+use tracing::{
+    error,
+    warn,
+};
+
+use crate::database::Database;
+use crate::database::settings::Setting;
+
+pub const X_AMZN_Q_GATEWAY_TIMESTAMP_HEADER: &str = "x-amzn-q-gateway-timestamp";
+pub const X_AMZN_Q_GATEWAY_SIGNATURE_HEADER: &str = "x-amzn-q-gateway-signature";
+
+/// How long the output of [Setting::ApiGatewayHeadersCommand] is reused before the command is
+/// run again.
+const HEADERS_COMMAND_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Hash used for request bodies that can't be read up front, e.g. streams.
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Adds the headers and signature required by corporate API gateways to every request.
+///
+/// Configured with:
+/// - [Setting::ApiGatewayHeaders] - static headers, as an object of header names to values.
+/// - [Setting::ApiGatewayHeadersCommand] - a command printing `Name: value` lines, for headers that
+///   change over time such as attestation tokens.
+/// - [Setting::ApiGatewaySigningKeyEnv] - the name of an environment variable holding a secret used
+///   to sign requests with HMAC-SHA256.
+#[derive(Debug, Clone)]
+pub struct GatewayInterceptor {
+    static_headers: Vec<(String, String)>,
+    headers_command: Option<String>,
+    command_headers: Arc<Mutex<Option<(Instant, Vec<(String, String)>)>>>,
+    signing_key: Option<hmac::Key>,
+}
+
+impl GatewayInterceptor {
+    pub fn new(database: &Database) -> Self {
+        let static_headers = match database.settings.get(Setting::ApiGatewayHeaders) {
+            Some(value) => match serde_json::from_value::<HashMap<String, String>>(value.clone()) {
+                Ok(headers) => headers.into_iter().collect(),
+                Err(err) => {
+                    warn!(?err, "ignoring invalid {}", Setting::ApiGatewayHeaders.as_ref());
+                    Vec::new()
+                },
+            },
+            None => Vec::new(),
+        };
+
+        let signing_key = database
+            .settings
+            .get_string(Setting::ApiGatewaySigningKeyEnv)
+            .and_then(|var| match std::env::var(&var) {
+                Ok(secret) => Some(hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())),
+                Err(err) => {
+                    warn!(?err, %var, "request signing is configured but the signing key is not set");
+                    None
+                },
+            });
+
+        Self {
+            static_headers,
+            headers_command: database.settings.get_string(Setting::ApiGatewayHeadersCommand),
+            command_headers: Arc::new(Mutex::new(None)),
+            signing_key,
+        }
+    }
+
+    /// Returns the headers printed by the headers command, rerunning it once the cached output
+    /// is older than [HEADERS_COMMAND_TTL].
+    fn command_headers(&self, command: &str) -> Result<Vec<(String, String)>, BoxError> {
+        if let Some((fetched_at, headers)) = self.command_headers.lock().as_ref() {
+            if fetched_at.elapsed() < HEADERS_COMMAND_TTL {
+                return Ok(headers.clone());
+            }
+        }
+
+        // Interceptors can't be async, so the command blocks this thread. On a multi-threaded
+        // runtime, the worker's other tasks are handed off first rather than stalled with it.
+        let headers = match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| run_headers_command(command))?
+            },
+            _ => run_headers_command(command)?,
+        };
+        *self.command_headers.lock() = Some((Instant::now(), headers.clone()));
+        Ok(headers)
+    }
+}
+
+impl Intercept for GatewayInterceptor {
+    fn name(&self) -> &'static str {
+        "GatewayInterceptor"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let command_headers = match &self.headers_command {
+            Some(command) => self.command_headers(command)?,
+            None => Vec::new(),
+        };
+
+        insert_headers(
+            context.request_mut().headers_mut(),
+            self.static_headers.iter().chain(command_headers.iter()),
+        )
+    }
+
+    fn modify_before_transmit(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let Some(key) = &self.signing_key else {
+            return Ok(());
+        };
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs().to_string();
+        let request = context.request_mut();
+        let path = request_path(request.uri()).to_string();
+        let payload_hash = request.body().bytes().map_or_else(
+            || UNSIGNED_PAYLOAD.to_string(),
+            |bytes| hex::encode(digest::digest(&digest::SHA256, bytes)),
+        );
+
+        let signature = sign(key, request.method(), &path, &timestamp, &payload_hash);
+        let headers = request.headers_mut();
+        headers.insert(X_AMZN_Q_GATEWAY_TIMESTAMP_HEADER, timestamp);
+        headers.insert(X_AMZN_Q_GATEWAY_SIGNATURE_HEADER, signature);
+
+        Ok(())
+    }
+}
+
+/// Inserts the configured headers, failing the request rather than panicking on a name or value
+/// that isn't a valid header.
+fn insert_headers<'a>(
+    headers: &mut Headers,
+    configured: impl Iterator<Item = &'a (String, String)>,
+) -> Result<(), BoxError> {
+    for (name, value) in configured {
+        headers
+            .try_insert(name.clone(), value.clone())
+            .map_err(|err| format!("invalid gateway header {name:?}: {err}"))?;
+    }
+    Ok(())
+}
+
+/// Signs the canonical form of a request: the method, path, timestamp, and payload hash joined by
+/// newlines.
+fn sign(key: &hmac::Key, method: &str, path: &str, timestamp: &str, payload_hash: &str) -> String {
+    let canonical_request = format!("{method}\n{path}\n{timestamp}\n{payload_hash}");
+    hex::encode(hmac::sign(key, canonical_request.as_bytes()))
+}
+
+/// Returns the path and query of `uri`.
+fn request_path(uri: &str) -> &str {
+    match uri.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+        None => uri,
+    }
+}
+
+fn run_headers_command(command: &str) -> Result<Vec<(String, String)>, BoxError> {
+    #[cfg(unix)]
+    let output = Command::new("bash").arg("-c").arg(command).output();
+    #[cfg(windows)]
+    let output = Command::new("cmd").arg("/C").arg(command).output();
+
+    let output = output.map_err(|err| {
+        error!(?err, "failed to run the gateway headers command");
+        err
+    })?;
+    if !output.status.success() {
+        return Err(format!(
+            "gateway headers command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+
+    Ok(parse_headers(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses `Name: value` lines, skipping blank and malformed lines.
+fn parse_headers(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_headers() {
+        let headers = parse_headers("x-attestation: abc:123\n\nnot a header\n x-team : platform \n: empty\n");
+        assert_eq!(headers, vec![
+            ("x-attestation".to_string(), "abc:123".to_string()),
+            ("x-team".to_string(), "platform".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_insert_headers() {
+        let mut headers = Headers::new();
+        let valid = vec![("x-team".to_string(), "platform".to_string())];
+        insert_headers(&mut headers, valid.iter()).unwrap();
+        assert_eq!(headers.get("x-team"), Some("platform"));
+
+        let invalid = vec![("x-token".to_string(), "line\nbreak".to_string())];
+        let err = insert_headers(&mut headers, invalid.iter()).unwrap_err();
+        assert!(err.to_string().contains("x-token"));
+        assert_eq!(headers.get("x-token"), None);
+    }
+
+    #[test]
+    fn test_request_path() {
+        assert_eq!(request_path("https://q.us-east-1.amazonaws.com/"), "/");
+        assert_eq!(request_path("https://q.us-east-1.amazonaws.com"), "/");
+        assert_eq!(request_path("https://gateway.corp/q/models?x=1"), "/q/models?x=1");
+    }
+
+    #[test]
+    fn test_sign() {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, b"secret");
+        let signature = sign(&key, "POST", "/", "1700000000", UNSIGNED_PAYLOAD);
+        assert_eq!(signature.len(), 64);
+        assert_eq!(signature, sign(&key, "POST", "/", "1700000000", UNSIGNED_PAYLOAD));
+        assert_ne!(signature, sign(&key, "POST", "/", "1700000001", UNSIGNED_PAYLOAD));
+    }
+}
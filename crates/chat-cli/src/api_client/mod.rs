@@ -1,13 +1,16 @@
+pub mod code_scan;
 mod credentials;
 pub mod customization;
 mod delay_interceptor;
 mod endpoints;
 mod error;
+mod gateway;
 pub mod model;
 mod opt_out;
 pub mod profile;
 mod retry_classifier;
 pub mod send_message_output;
+pub mod usage_limits;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -44,13 +47,19 @@ use tracing::{
 
 use crate::api_client::credentials::CredentialsChain;
 use crate::api_client::delay_interceptor::DelayTrackingInterceptor;
+use crate::api_client::gateway::GatewayInterceptor;
 use crate::api_client::model::{
     ChatResponseStream,
     ConversationState,
 };
 use crate::api_client::opt_out::OptOutInterceptor;
 use crate::api_client::send_message_output::SendMessageOutput;
+use crate::api_client::usage_limits::{
+    ReportedUsage,
+    UsageLimitsInterceptor,
+};
 use crate::auth::builder_id::BearerResolver;
+use crate::aws_common::endpoint_overrides::endpoint_overrides;
 use crate::aws_common::{
     UserAgentOverrideInterceptor,
     app_name,
@@ -93,9 +102,10 @@ pub struct ApiClient {
     client: CodewhispererClient,
     streaming_client: Option<CodewhispererStreamingClient>,
     sigv4_streaming_client: Option<QDeveloperStreamingClient>,
-    mock_client: Option<Arc<Mutex<std::vec::IntoIter<Vec<ChatResponseStream>>>>>,
+    mock_client: Option<Arc<Mutex<std::vec::IntoIter<Result<Vec<ChatResponseStream>, ApiClientError>>>>>,
     profile: Option<AuthProfile>,
     model_cache: ModelCache,
+    reported_usage: Arc<Mutex<Option<ReportedUsage>>>,
 }
 
 impl ApiClient {
@@ -119,9 +129,10 @@ impl ApiClient {
 
         let client = CodewhispererClient::from_conf(
             amzn_codewhisperer_client::config::Builder::from(&bearer_sdk_config)
-                .http_client(crate::aws_common::http_client::client())
+                .http_client(crate::aws_common::http_client::q_client()?)
                 .interceptor(OptOutInterceptor::new(database))
                 .interceptor(UserAgentOverrideInterceptor::new())
+                .interceptor(GatewayInterceptor::new(database))
                 .bearer_token_resolver(BearerResolver)
                 .app_name(app_name())
                 .endpoint_url(endpoint.url())
@@ -136,6 +147,7 @@ impl ApiClient {
                 mock_client: None,
                 profile: None,
                 model_cache: Arc::new(RwLock::new(None)),
+                reported_usage: Arc::new(Mutex::new(None)),
             };
 
             if let Ok(json) = env.get("Q_MOCK_CHAT_RESPONSE") {
@@ -145,6 +157,14 @@ impl ApiClient {
             return Ok(this);
         }
 
+        // Allow pointing only the streaming clients at e.g. a VPC endpoint.
+        let streaming_endpoint_url = endpoint_overrides()
+            .streaming
+            .clone()
+            .unwrap_or_else(|| endpoint.url().to_string());
+
+        let reported_usage = Arc::new(Mutex::new(None));
+
         // If SIGV4_AUTH_ENABLED is true, use Q developer client
         let mut streaming_client = None;
         let mut sigv4_streaming_client = None;
@@ -165,12 +185,14 @@ impl ApiClient {
                             .load()
                             .await,
                     )
-                    .http_client(crate::aws_common::http_client::client())
+                    .http_client(crate::aws_common::http_client::q_client()?)
                     .interceptor(OptOutInterceptor::new(database))
                     .interceptor(UserAgentOverrideInterceptor::new())
+                    .interceptor(GatewayInterceptor::new(database))
                     .interceptor(DelayTrackingInterceptor::new())
+                    .interceptor(UsageLimitsInterceptor::new(reported_usage.clone()))
                     .app_name(app_name())
-                    .endpoint_url(endpoint.url())
+                    .endpoint_url(&streaming_endpoint_url)
                     .retry_classifier(retry_classifier::QCliRetryClassifier::new())
                     .stalled_stream_protection(stalled_stream_protection_config())
                     .build(),
@@ -179,13 +201,15 @@ impl ApiClient {
             false => {
                 streaming_client = Some(CodewhispererStreamingClient::from_conf(
                     amzn_codewhisperer_streaming_client::config::Builder::from(&bearer_sdk_config)
-                        .http_client(crate::aws_common::http_client::client())
+                        .http_client(crate::aws_common::http_client::q_client()?)
                         .interceptor(OptOutInterceptor::new(database))
                         .interceptor(UserAgentOverrideInterceptor::new())
+                        .interceptor(GatewayInterceptor::new(database))
                         .interceptor(DelayTrackingInterceptor::new())
+                        .interceptor(UsageLimitsInterceptor::new(reported_usage.clone()))
                         .bearer_token_resolver(BearerResolver)
                         .app_name(app_name())
-                        .endpoint_url(endpoint.url())
+                        .endpoint_url(&streaming_endpoint_url)
                         .retry_classifier(retry_classifier::QCliRetryClassifier::new())
                         .stalled_stream_protection(stalled_stream_protection_config())
                         .build(),
@@ -215,6 +239,7 @@ impl ApiClient {
             mock_client: None,
             profile,
             model_cache: Arc::new(RwLock::new(None)),
+            reported_usage,
         })
     }
 
@@ -573,7 +598,7 @@ impl ApiClient {
                 },
             }
         } else if let Some(client) = &self.mock_client {
-            let mut new_events = client.lock().next().unwrap_or_default().clone();
+            let mut new_events = client.lock().next().transpose()?.unwrap_or_default();
             new_events.reverse();
 
             return Ok(SendMessageOutput::Mock(new_events));
@@ -582,10 +607,25 @@ impl ApiClient {
         }
     }
 
+    /// Returns the monthly request usage most recently reported by the service, if any.
+    pub fn reported_usage(&self) -> Option<ReportedUsage> {
+        *self.reported_usage.lock()
+    }
+
     /// Only meant for testing. Do not use outside of testing responses.
+    ///
+    /// Each response is an array of events, or the string `"ModelOverloadedError"` to fail that
+    /// request with [ApiClientError::ModelOverloadedError].
     pub fn set_mock_output(&mut self, json: serde_json::Value) {
         let mut mock = Vec::new();
         for response in json.as_array().unwrap() {
+            if response.as_str() == Some("ModelOverloadedError") {
+                mock.push(Err(ApiClientError::ModelOverloadedError {
+                    request_id: None,
+                    status_code: Some(429),
+                }));
+                continue;
+            }
             let mut stream = Vec::new();
             for event in response.as_array().unwrap() {
                 match event {
@@ -600,7 +640,7 @@ impl ApiClient {
                     other => panic!("Unexpected value: {:?}", other),
                 }
             }
-            mock.push(stream);
+            mock.push(Ok(stream));
         }
 
         self.mock_client = Some(Arc::new(Mutex::new(mock.into_iter())));
@@ -719,7 +759,7 @@ mod tests {
             .unwrap();
 
         client.mock_client = Some(Arc::new(Mutex::new(
-            vec![vec![
+            vec![Ok(vec![
                 ChatResponseStream::AssistantResponseEvent {
                     content: "Hello!".to_owned(),
                 },
@@ -729,7 +769,7 @@ mod tests {
                 ChatResponseStream::AssistantResponseEvent {
                     content: " assist you today?".to_owned(),
                 },
-            ]]
+            ])]
             .into_iter(),
         )));
 
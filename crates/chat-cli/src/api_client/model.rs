@@ -563,6 +563,13 @@ pub enum ChatResponseStream {
         conversation_id: Option<String>,
         utterance_id: Option<String>,
     },
+    MetadataEvent {
+        token_usage: Option<TokenUsage>,
+    },
+    /// Streaming reasoning of the model, before its answer. Empty for redacted reasoning.
+    ReasoningContentEvent {
+        text: String,
+    },
     SupplementaryWebLinksEvent(()),
     ToolUseEvent {
         tool_use_id: String,
@@ -609,6 +616,11 @@ impl From<amzn_codewhisperer_streaming_client::types::ChatResponseStream> for Ch
                 conversation_id,
                 utterance_id,
             },
+            amzn_codewhisperer_streaming_client::types::ChatResponseStream::MetadataEvent(
+                amzn_codewhisperer_streaming_client::types::MetadataEvent { token_usage, .. },
+            ) => ChatResponseStream::MetadataEvent {
+                token_usage: token_usage.map(Into::into),
+            },
             amzn_codewhisperer_streaming_client::types::ChatResponseStream::ToolUseEvent(
                 amzn_codewhisperer_streaming_client::types::ToolUseEvent {
                     tool_use_id,
@@ -623,6 +635,11 @@ impl From<amzn_codewhisperer_streaming_client::types::ChatResponseStream> for Ch
                 input,
                 stop,
             },
+            amzn_codewhisperer_streaming_client::types::ChatResponseStream::ReasoningContentEvent(
+                amzn_codewhisperer_streaming_client::types::ReasoningContentEvent { text, .. },
+            ) => ChatResponseStream::ReasoningContentEvent {
+                text: text.unwrap_or_default(),
+            },
             amzn_codewhisperer_streaming_client::types::ChatResponseStream::SupplementaryWebLinksEvent(_) => {
                 ChatResponseStream::SupplementaryWebLinksEvent(())
             },
@@ -665,6 +682,11 @@ impl From<amzn_qdeveloper_streaming_client::types::ChatResponseStream> for ChatR
                 conversation_id,
                 utterance_id,
             },
+            amzn_qdeveloper_streaming_client::types::ChatResponseStream::MetadataEvent(
+                amzn_qdeveloper_streaming_client::types::MetadataEvent { token_usage, .. },
+            ) => ChatResponseStream::MetadataEvent {
+                token_usage: token_usage.map(Into::into),
+            },
             amzn_qdeveloper_streaming_client::types::ChatResponseStream::ToolUseEvent(
                 amzn_qdeveloper_streaming_client::types::ToolUseEvent {
                     tool_use_id,
@@ -679,6 +701,11 @@ impl From<amzn_qdeveloper_streaming_client::types::ChatResponseStream> for ChatR
                 input,
                 stop,
             },
+            amzn_qdeveloper_streaming_client::types::ChatResponseStream::ReasoningContentEvent(
+                amzn_qdeveloper_streaming_client::types::ReasoningContentEvent { text, .. },
+            ) => ChatResponseStream::ReasoningContentEvent {
+                text: text.unwrap_or_default(),
+            },
             amzn_qdeveloper_streaming_client::types::ChatResponseStream::SupplementaryWebLinksEvent(_) => {
                 ChatResponseStream::SupplementaryWebLinksEvent(())
             },
@@ -687,6 +714,38 @@ impl From<amzn_qdeveloper_streaming_client::types::ChatResponseStream> for ChatR
     }
 }
 
+/// Token usage reported by the backend for a single request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub uncached_input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub cache_write_input_tokens: u64,
+}
+
+impl From<amzn_codewhisperer_streaming_client::types::TokenUsage> for TokenUsage {
+    fn from(value: amzn_codewhisperer_streaming_client::types::TokenUsage) -> Self {
+        Self {
+            uncached_input_tokens: value.uncached_input_tokens.max(0) as u64,
+            output_tokens: value.output_tokens.max(0) as u64,
+            cache_read_input_tokens: value.cache_read_input_tokens.unwrap_or_default().max(0) as u64,
+            cache_write_input_tokens: value.cache_write_input_tokens.unwrap_or_default().max(0) as u64,
+        }
+    }
+}
+
+impl From<amzn_qdeveloper_streaming_client::types::TokenUsage> for TokenUsage {
+    fn from(value: amzn_qdeveloper_streaming_client::types::TokenUsage) -> Self {
+        Self {
+            uncached_input_tokens: value.uncached_input_tokens.max(0) as u64,
+            output_tokens: value.output_tokens.max(0) as u64,
+            cache_read_input_tokens: value.cache_read_input_tokens.unwrap_or_default().max(0) as u64,
+            cache_write_input_tokens: value.cache_write_input_tokens.unwrap_or_default().max(0) as u64,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct EnvState {
     pub operating_system: Option<String>,
@@ -1173,6 +1232,31 @@ mod tests {
             }
         );
 
+        let user_input_event = amzn_codewhisperer_streaming_client::types::ChatResponseStream::MetadataEvent(
+            amzn_codewhisperer_streaming_client::types::MetadataEvent::builder()
+                .token_usage(
+                    amzn_codewhisperer_streaming_client::types::TokenUsage::builder()
+                        .uncached_input_tokens(100)
+                        .output_tokens(20)
+                        .total_tokens(170)
+                        .cache_read_input_tokens(50)
+                        .build()
+                        .unwrap(),
+                )
+                .build(),
+        );
+        assert_eq!(
+            ChatResponseStream::from(user_input_event),
+            ChatResponseStream::MetadataEvent {
+                token_usage: Some(TokenUsage {
+                    uncached_input_tokens: 100,
+                    output_tokens: 20,
+                    cache_read_input_tokens: 50,
+                    cache_write_input_tokens: 0,
+                })
+            }
+        );
+
         let user_input_event =
             amzn_codewhisperer_streaming_client::types::ChatResponseStream::SupplementaryWebLinksEvent(
                 amzn_codewhisperer_streaming_client::types::SupplementaryWebLinksEvent::builder().build(),
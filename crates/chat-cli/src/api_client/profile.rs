@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use crate::api_client::endpoints::Endpoint;
 use crate::api_client::{
     ApiClient,
@@ -12,17 +14,38 @@ use crate::os::{
     Fs,
 };
 
+const PROFILES_CACHE_KEY: &str = "profiles";
+
+/// How long the profiles listed by one invocation are reused by the following ones.
+const PROFILES_TTL: Duration = Duration::from_secs(60 * 60);
+
 pub async fn list_available_profiles(
     env: &Env,
     fs: &Fs,
     database: &mut Database,
 ) -> Result<Vec<AuthProfile>, ApiClientError> {
+    if let Some(profiles) = database.get_cached(PROFILES_CACHE_KEY, PROFILES_TTL) {
+        tracing::debug!("Using the profiles cached by an earlier invocation");
+        return Ok(profiles);
+    }
+
     let mut profiles = vec![];
+    let mut complete = true;
     for endpoint in Endpoint::CODEWHISPERER_ENDPOINTS {
         let client = ApiClient::new(env, fs, database, Some(endpoint.clone())).await?;
         match client.list_available_profiles().await {
             Ok(mut p) => profiles.append(&mut p),
-            Err(e) => tracing::error!("Failed to list profiles from endpoint {:?}: {:?}", endpoint, e),
+            Err(e) => {
+                tracing::error!("Failed to list profiles from endpoint {:?}: {:?}", endpoint, e);
+                complete = false;
+            },
+        }
+    }
+
+    // A partial list would hide profiles until the cache expires.
+    if complete {
+        if let Err(err) = database.set_cached(PROFILES_CACHE_KEY, &profiles) {
+            tracing::warn!(?err, "Failed to cache the profiles");
         }
     }
 
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use aws_smithy_runtime_api::box_error::BoxError;
+use aws_smithy_runtime_api::client::interceptors::Intercept;
+use aws_smithy_runtime_api::client::interceptors::context::BeforeDeserializationInterceptorContextRef;
+use aws_smithy_runtime_api::client::runtime_components::RuntimeComponents;
+use aws_smithy_types::config_bag::ConfigBag;
+use parking_lot::Mutex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+pub const X_AMZN_Q_MONTHLY_REQUESTS_USED_HEADER: &str = "x-amzn-q-monthly-requests-used";
+pub const X_AMZN_Q_MONTHLY_REQUESTS_LIMIT_HEADER: &str = "x-amzn-q-monthly-requests-limit";
+
+/// Monthly request usage as reported by the service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportedUsage {
+    pub used: u64,
+    pub limit: u64,
+}
+
+/// Records the monthly request usage from the response headers, when the service includes them.
+#[derive(Debug, Clone)]
+pub struct UsageLimitsInterceptor {
+    reported: Arc<Mutex<Option<ReportedUsage>>>,
+}
+
+impl UsageLimitsInterceptor {
+    pub fn new(reported: Arc<Mutex<Option<ReportedUsage>>>) -> Self {
+        Self { reported }
+    }
+}
+
+impl Intercept for UsageLimitsInterceptor {
+    fn name(&self) -> &'static str {
+        "UsageLimitsInterceptor"
+    }
+
+    fn read_before_deserialization(
+        &self,
+        context: &BeforeDeserializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let headers = context.response().headers();
+        if let Some(usage) = parse_usage(
+            headers.get(X_AMZN_Q_MONTHLY_REQUESTS_USED_HEADER),
+            headers.get(X_AMZN_Q_MONTHLY_REQUESTS_LIMIT_HEADER),
+        ) {
+            *self.reported.lock() = Some(usage);
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_usage(used: Option<&str>, limit: Option<&str>) -> Option<ReportedUsage> {
+    Some(ReportedUsage {
+        used: used?.trim().parse().ok()?,
+        limit: limit?.trim().parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_usage() {
+        assert_eq!(
+            parse_usage(Some("12"), Some(" 50 ")),
+            Some(ReportedUsage { used: 12, limit: 50 })
+        );
+        assert_eq!(parse_usage(Some("12"), None), None);
+        assert_eq!(parse_usage(Some("twelve"), Some("50")), None);
+    }
+}
@@ -59,6 +59,7 @@ use crate::auth::AuthError;
 use crate::auth::consts::*;
 use crate::auth::scope::is_scopes;
 use crate::aws_common::app_name;
+use crate::aws_common::endpoint_overrides::endpoint_overrides;
 use crate::database::{
     Database,
     Secret,
@@ -80,13 +81,16 @@ fn is_expired(expiration_time: &OffsetDateTime) -> bool {
 }
 
 pub(crate) fn oidc_url(region: &Region) -> String {
+    if let Some(endpoint) = &endpoint_overrides().auth {
+        return endpoint.trim_end_matches('/').to_string();
+    }
     format!("https://oidc.{region}.amazonaws.com")
 }
 
-pub fn client(region: Region) -> Client {
-    Client::new(
+pub fn client(region: Region) -> Result<Client, AuthError> {
+    Ok(Client::new(
         &aws_types::SdkConfig::builder()
-            .http_client(crate::aws_common::http_client::client())
+            .http_client(crate::aws_common::http_client::client()?)
             .behavior_version(BehaviorVersion::v2025_01_17())
             .endpoint_url(oidc_url(&region))
             .region(region)
@@ -95,7 +99,7 @@ pub fn client(region: Region) -> Client {
             .stalled_stream_protection(stalled_stream_protection_config())
             .app_name(app_name())
             .build(),
-    )
+    ))
 }
 
 /// Represents an OIDC registered client, resulting from the "register client" API call.
@@ -239,7 +243,7 @@ pub async fn start_device_authorization(
     region: Option<String>,
 ) -> Result<StartDeviceAuthorizationResponse, AuthError> {
     let region = region.clone().map_or(OIDC_BUILDER_ID_REGION, Region::new);
-    let client = client(region.clone());
+    let client = client(region.clone())?;
 
     let DeviceRegistration {
         client_id,
@@ -324,7 +328,7 @@ impl BuilderIdToken {
                 match token {
                     Some(token) => {
                         let region = token.region.clone().map_or(OIDC_BUILDER_ID_REGION, Region::new);
-                        let client = client(region.clone());
+                        let client = client(region.clone())?;
 
                         if token.is_expired() {
                             trace!("token is expired, refreshing");
@@ -500,7 +504,10 @@ pub async fn poll_create_token(
     region: Option<String>,
 ) -> PollCreateToken {
     let region = region.clone().map_or(OIDC_BUILDER_ID_REGION, Region::new);
-    let client = client(region.clone());
+    let client = match client(region.clone()) {
+        Ok(client) => client,
+        Err(err) => return PollCreateToken::Error(err),
+    };
 
     let DeviceRegistration {
         client_id,
@@ -574,6 +581,10 @@ pub async fn logout(database: &mut Database) -> Result<(), AuthError> {
     );
 
     let profile_res = database.unset_auth_profile();
+    // The cached profiles and models belong to the user logging out.
+    if let Err(err) = database.clear_all_cached() {
+        warn!(?err, "failed to clear the cached values");
+    }
 
     builder_res?;
     device_res?;
@@ -48,6 +48,8 @@ pub enum AuthError {
     OAuthCustomError(String),
     #[error(transparent)]
     DatabaseError(#[from] crate::database::DatabaseError),
+    #[error(transparent)]
+    Request(#[from] crate::request::RequestError),
 }
 
 impl From<aws_sdk_ssooidc::Error> for AuthError {
@@ -68,7 +68,7 @@ pub async fn start_pkce_authorization(
 ) -> Result<(Client, PkceRegistration), AuthError> {
     let issuer_url = start_url.as_deref().unwrap_or(START_URL);
     let region = region.clone().map_or(OIDC_BUILDER_ID_REGION, Region::new);
-    let client = client(region.clone());
+    let client = client(region.clone())?;
     let registration = PkceRegistration::register(&client, region, issuer_url.to_string(), None).await?;
     Ok((client, registration))
 }
@@ -513,7 +513,7 @@ mod tests {
 
         let start_url = "https://amzn.awsapps.com/start".to_string();
         let region = Region::new("us-east-1");
-        let client = client(region.clone());
+        let client = client(region.clone()).unwrap();
         let registration = PkceRegistration::register(&client, region.clone(), start_url, None)
             .await
             .unwrap();
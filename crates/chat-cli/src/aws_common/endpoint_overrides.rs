@@ -0,0 +1,138 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use anstream::eprintln;
+use crossterm::style::Stylize;
+use tracing::warn;
+
+use crate::database::Database;
+use crate::database::settings::Setting;
+
+static ENDPOINT_OVERRIDES: OnceLock<EndpointOverrides> = OnceLock::new();
+
+/// Endpoint and TLS overrides for private VPC endpoints (PrivateLink) and pre-prod stacks.
+///
+/// These are read from the settings once at startup with [init], since the HTTP clients that use
+/// them are created in places without access to the [Database].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EndpointOverrides {
+    /// Overrides the endpoint of the streaming (chat) clients.
+    pub streaming: Option<String>,
+    /// Overrides the OIDC endpoint used for authentication.
+    pub auth: Option<String>,
+    /// Overrides the telemetry endpoint.
+    pub telemetry: Option<String>,
+    /// Hostname the server certificate of the Q API is validated against instead of the hostname
+    /// of the request URL, e.g. when connecting through a VPC endpoint DNS name.
+    pub tls_server_name: Option<String>,
+    /// Path to a PEM bundle of additional trusted root certificates for the Q API.
+    pub tls_ca_bundle: Option<PathBuf>,
+}
+
+impl EndpointOverrides {
+    pub fn from_database(database: &Database) -> Self {
+        let get = |setting: Setting| {
+            database
+                .settings
+                .get_string(setting)
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        Self {
+            streaming: get(Setting::ApiStreamingEndpoint),
+            auth: get(Setting::ApiAuthEndpoint),
+            telemetry: get(Setting::ApiTelemetryEndpoint),
+            tls_server_name: get(Setting::ApiTlsServerName),
+            tls_ca_bundle: get(Setting::ApiTlsCaBundle).map(PathBuf::from),
+        }
+    }
+}
+
+/// Loads the overrides from the settings. Only the first call has any effect.
+///
+/// TLS overrides that can't be used are ignored with a warning rather than failing every command,
+/// including the `q settings` needed to fix them.
+pub fn init(database: &Database) {
+    let mut overrides = EndpointOverrides::from_database(database);
+    if let Err(err) = without_invalid_tls(&mut overrides) {
+        warn!(%err, "Ignoring the TLS overrides");
+        eprintln!(
+            "{} {err}, ignoring api.tls.serverName and api.tls.caBundle",
+            "warning:".bold().yellow()
+        );
+    }
+    let _ = ENDPOINT_OVERRIDES.set(overrides);
+}
+
+/// Clears the TLS overrides of `overrides` if they can't be used, returning why.
+fn without_invalid_tls(overrides: &mut EndpointOverrides) -> Result<(), crate::request::RequestError> {
+    crate::request::check_tls_overrides(overrides).inspect_err(|_| {
+        overrides.tls_server_name = None;
+        overrides.tls_ca_bundle = None;
+    })
+}
+
+/// Returns the configured overrides, or no overrides if [init] has not been called.
+pub fn endpoint_overrides() -> &'static EndpointOverrides {
+    static NONE: EndpointOverrides = EndpointOverrides {
+        streaming: None,
+        auth: None,
+        telemetry: None,
+        tls_server_name: None,
+        tls_ca_bundle: None,
+    };
+    ENDPOINT_OVERRIDES.get().unwrap_or(&NONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_from_database() {
+        let mut database = Database::new().await.unwrap();
+        assert_eq!(
+            EndpointOverrides::from_database(&database),
+            EndpointOverrides::default()
+        );
+
+        database
+            .settings
+            .set(
+                Setting::ApiStreamingEndpoint,
+                "https://vpce-123.q.us-east-1.vpce.amazonaws.com",
+            )
+            .await
+            .unwrap();
+        database
+            .settings
+            .set(Setting::ApiTlsServerName, " q.us-east-1.amazonaws.com ")
+            .await
+            .unwrap();
+        database.settings.set(Setting::ApiAuthEndpoint, "").await.unwrap();
+
+        let overrides = EndpointOverrides::from_database(&database);
+        assert_eq!(
+            overrides.streaming.as_deref(),
+            Some("https://vpce-123.q.us-east-1.vpce.amazonaws.com")
+        );
+        assert_eq!(overrides.tls_server_name.as_deref(), Some("q.us-east-1.amazonaws.com"));
+        assert_eq!(overrides.auth, None);
+    }
+
+    #[test]
+    fn test_invalid_tls_overrides_ignored() {
+        let mut overrides = EndpointOverrides {
+            streaming: Some("https://vpce-123.q.us-east-1.vpce.amazonaws.com".into()),
+            tls_ca_bundle: Some("/nonexistent/ca-bundle.pem".into()),
+            ..Default::default()
+        };
+        assert!(without_invalid_tls(&mut overrides).is_err());
+        assert_eq!(overrides.tls_ca_bundle, None);
+        assert_eq!(
+            overrides.streaming.as_deref(),
+            Some("https://vpce-123.q.us-east-1.vpce.amazonaws.com")
+        );
+    }
+}
@@ -13,11 +13,18 @@ use aws_smithy_runtime_api::http::Request;
 use aws_smithy_types::body::SdkBody;
 use reqwest::Client as ReqwestClient;
 
+use crate::request::RequestError;
+
 /// Returns a wrapper around the global [fig_request::client] that implements
 /// [HttpClient].
-pub fn client() -> Client {
-    let client = crate::request::new_client().expect("failed to create http client");
-    Client::new(client.clone())
+pub fn client() -> Result<Client, RequestError> {
+    Ok(Client::new(crate::request::new_client()?))
+}
+
+/// Like [client], but with the TLS overrides of the Q service clients applied, see
+/// [crate::request::new_q_client_builder].
+pub fn q_client() -> Result<Client, RequestError> {
+    Ok(Client::new(crate::request::new_q_client_builder()?.build()?))
 }
 
 /// A wrapper around [reqwest::Client] that implements [HttpClient].
@@ -0,0 +1,103 @@
+//! What a running session shares with IDE extensions over the control channel (see
+//! [super::ipc]): selections sent from the editor to attach to the next prompt, the transcript
+//! of the conversation, and the events the extension can follow to stay in sync.
+
+use parking_lot::Mutex;
+use tokio::sync::broadcast;
+
+use super::protocol::{
+    Selection,
+    SessionEvent,
+};
+
+/// How many events a slow subscriber can fall behind before missing some.
+const EVENT_CAPACITY: usize = 64;
+
+#[derive(Debug)]
+pub struct IdeBridge {
+    selections: Mutex<Vec<Selection>>,
+    transcript: Mutex<Vec<String>>,
+    events: broadcast::Sender<SessionEvent>,
+}
+
+impl Default for IdeBridge {
+    fn default() -> Self {
+        Self {
+            selections: Mutex::default(),
+            transcript: Mutex::default(),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        }
+    }
+}
+
+impl IdeBridge {
+    pub fn add_selection(&self, selection: Selection) {
+        self.selections.lock().push(selection);
+    }
+
+    /// The selections received since the last call, oldest first.
+    pub fn take_selections(&self) -> Vec<Selection> {
+        std::mem::take(&mut *self.selections.lock())
+    }
+
+    pub fn set_transcript(&self, transcript: Vec<String>) {
+        *self.transcript.lock() = transcript;
+    }
+
+    pub fn transcript(&self) -> Vec<String> {
+        self.transcript.lock().clone()
+    }
+
+    /// Sends `event` to the subscribed connections, if any.
+    pub fn notify(&self, event: SessionEvent) {
+        self.events.send(event).ok();
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
+    }
+}
+
+/// `input` with the selections prepended, the way the model sees them.
+pub fn attach_selections(selections: &[Selection], input: &str) -> String {
+    let mut message = String::new();
+    for selection in selections {
+        let location = match (selection.start_line, selection.end_line) {
+            (Some(start), Some(end)) if end > start => format!("{}:{start}-{end}", selection.path.display()),
+            (Some(start), _) => format!("{}:{start}", selection.path.display()),
+            (None, _) => selection.path.display().to_string(),
+        };
+        message.push_str(&format!(
+            "--- Selected in the editor: {location} ---\n```\n{}\n```\n\n",
+            selection.text.trim_end_matches('\n')
+        ));
+    }
+    message.push_str(input);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn test_attach_selections() {
+        let bridge = IdeBridge::default();
+        bridge.add_selection(Selection {
+            path: PathBuf::from("src/lib.rs"),
+            start_line: Some(10),
+            end_line: Some(12),
+            text: "fn a() {}\n".to_string(),
+        });
+        let selections = bridge.take_selections();
+        assert!(bridge.take_selections().is_empty());
+
+        assert_eq!(
+            attach_selections(&selections, "why is this slow?"),
+            "--- Selected in the editor: src/lib.rs:10-12 ---\n```\nfn a() {}\n```\n\nwhy is this slow?"
+        );
+        assert_eq!(attach_selections(&[], "hello"), "hello");
+    }
+}
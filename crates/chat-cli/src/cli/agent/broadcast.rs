@@ -0,0 +1,141 @@
+//! Sending the same message to several running sessions, for `q agent send --all`.
+
+use std::io::Write;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+
+use super::ipc::ControlClient;
+use super::protocol::Capability;
+use super::registry::RegistryEntry;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Prompt { text: String, wait: bool },
+    Shutdown,
+}
+
+/// What a session did with the message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ack {
+    /// The prompt was queued.
+    Sent,
+    /// The turn started by the prompt finished with this response.
+    Completed(String),
+    /// The session is exiting.
+    ShuttingDown,
+}
+
+/// Sends `message` to every session of `targets` concurrently. The results are in the order of
+/// `targets`.
+pub async fn send_all(targets: &[RegistryEntry], message: &Message) -> Vec<Result<Ack>> {
+    futures::future::join_all(targets.iter().map(|entry| send(entry.pid, message))).await
+}
+
+async fn send(pid: u32, message: &Message) -> Result<Ack> {
+    let mut client = ControlClient::connect(pid).await?;
+    match message {
+        Message::Prompt { text, wait } => {
+            if *wait && !client.supports(Capability::PromptResult) {
+                bail!("waiting for a response is not supported");
+            }
+            match client.prompt(text.clone(), *wait).await? {
+                Some(response) => Ok(Ack::Completed(response)),
+                None => Ok(Ack::Sent),
+            }
+        },
+        Message::Shutdown => {
+            if !client.supports(Capability::Shutdown) {
+                bail!("shutting down is not supported");
+            }
+            client.shutdown().await?;
+            Ok(Ack::ShuttingDown)
+        },
+    }
+}
+
+/// Prints a table with the outcome for each session, followed by the responses of the sessions
+/// that were waited for.
+pub fn print_results(output: &mut impl Write, targets: &[RegistryEntry], results: &[Result<Ack>]) -> Result<()> {
+    let agent_width = targets
+        .iter()
+        .map(|entry| entry.agent.len())
+        .max()
+        .unwrap_or(0)
+        .max("agent".len());
+
+    queue!(
+        output,
+        style::SetAttribute(style::Attribute::Bold),
+        style::Print(format!(
+            "{:<8}  {:<agent_width$}  {:<14}  cwd\n",
+            "pid", "agent", "status"
+        )),
+        style::SetAttribute(style::Attribute::Reset),
+    )?;
+    for (entry, result) in targets.iter().zip(results) {
+        let (status, color) = match result {
+            Ok(Ack::Sent) => ("sent".to_string(), Color::Green),
+            Ok(Ack::Completed(_)) => ("done".to_string(), Color::Green),
+            Ok(Ack::ShuttingDown) => ("shutting down".to_string(), Color::Yellow),
+            Err(err) => (format!("failed: {err}"), Color::Red),
+        };
+        queue!(
+            output,
+            style::Print(format!("{:<8}  {:<agent_width$}  ", entry.pid, entry.agent)),
+            style::SetForegroundColor(color),
+            style::Print(format!("{status:<14}")),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(format!("  {}\n", entry.cwd.display())),
+        )?;
+    }
+
+    for (entry, result) in targets.iter().zip(results) {
+        if let Ok(Ack::Completed(response)) = result {
+            queue!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("\n── {} (pid {}) ──\n", entry.agent, entry.pid)),
+                style::SetForegroundColor(Color::Reset),
+                style::Print(format!("{}\n", response.trim_end())),
+            )?;
+        }
+    }
+    output.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_print_results() {
+        let entry = |pid: u32, agent: &str| RegistryEntry {
+            pid,
+            agent: agent.to_string(),
+            conversation_id: String::new(),
+            cwd: "/work".into(),
+            started_at: chrono::Utc::now(),
+            process_start_time: None,
+            heartbeat_at: chrono::Utc::now(),
+            children: Vec::new(),
+        };
+        let targets = [entry(1, "default"), entry(2, "reviewer")];
+        let results = [Ok(Ack::Completed("All good".to_string())), Err(eyre::eyre!("refused"))];
+
+        let mut output = Vec::new();
+        print_results(&mut output, &targets, &results).unwrap();
+        let output = String::from_utf8(strip_ansi_escapes::strip(output)).unwrap();
+        assert!(output.contains("reviewer  failed: refused"));
+        assert!(output.contains("── default (pid 1) ──\nAll good\n"));
+    }
+}
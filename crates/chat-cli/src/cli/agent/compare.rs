@@ -0,0 +1,623 @@
+//! `q agent compare`: runs the same task with several models, each in its own git worktree, and
+//! compares what they did.
+
+use std::ffi::OsStr;
+use std::io::{
+    IsTerminal,
+    Read,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::{
+    ExitCode,
+    Stdio,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::style::{
+    self,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::warn;
+
+use super::registry;
+use crate::os::Os;
+use crate::util::time::format_duration;
+use crate::util::{
+    directories,
+    shutdown,
+};
+
+/// Describes the comparison in progress, for `q agent compare attach`.
+const MANIFEST_FILE_NAME: &str = "compare.json";
+
+/// How often `q agent compare attach` checks the log for new output.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_long_help = "Each model works in a git worktree checked out at HEAD, so uncommitted changes of the current
+worktree are not visible to them. The output of each run is written to a log file, which can be
+followed from another terminal with q agent compare attach. Once every run finished, the changes of
+the chosen run are applied to the current worktree without being committed."
+)]
+pub struct CompareArgs {
+    #[command(subcommand)]
+    pub cmd: Option<CompareSubcommand>,
+    /// Models to compare, as listed by /model, separated by commas
+    #[arg(long, short, required = true, value_delimiter = ',')]
+    pub models: Vec<String>,
+    /// Agent used by every run
+    #[arg(long)]
+    pub agent: Option<String>,
+    /// Shell command run in each worktree once the task finished, e.g. "cargo test". A run passes
+    /// its tests when the command exits successfully
+    #[arg(long)]
+    pub test: Option<String>,
+    /// Apply the changes of the run with this number, as shown in the comparison, rather than
+    /// asking
+    #[arg(long)]
+    pub pick: Option<usize>,
+    /// Keep the worktrees once done
+    #[arg(long)]
+    pub keep: bool,
+    /// Allow every run to use any tool without asking. Otherwise runs can only use the tools the
+    /// agent trusts, and stop at the first one that needs approval
+    #[arg(long)]
+    pub trust_all_tools: bool,
+    /// The task given to every model
+    #[arg(required = true, num_args = 1..)]
+    pub task: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum CompareSubcommand {
+    /// Follow the output of a run of the comparison in progress in this repository, until it
+    /// finishes
+    Attach {
+        /// Model of the run, or its number
+        run: String,
+    },
+}
+
+/// The comparison in progress in a repository.
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    /// Pid of the `q agent compare` process.
+    pid: u32,
+    task: String,
+    runs: Vec<Run>,
+}
+
+/// A single model working on the task.
+#[derive(Debug, Serialize, Deserialize)]
+struct Run {
+    /// 1-based number shown to the user.
+    number: usize,
+    model: String,
+    worktree: PathBuf,
+    log: PathBuf,
+}
+
+impl Run {
+    /// Created once the run finished, including its tests.
+    fn finished_marker(&self) -> PathBuf {
+        self.log.with_extension("done")
+    }
+}
+
+/// What a run did.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Outcome {
+    /// Whether the chat session exited successfully.
+    completed: bool,
+    files_changed: usize,
+    insertions: usize,
+    deletions: usize,
+    /// Whether the test command passed, if there is one.
+    tests_passed: Option<bool>,
+    tokens: Option<u64>,
+    duration: Duration,
+}
+
+impl CompareArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        if let Some(CompareSubcommand::Attach { run }) = self.cmd {
+            return attach(os, &run).await;
+        }
+
+        let mut stderr = std::io::stderr();
+        let root = repository_root(os).await?;
+        let dir = directories::agent_compare_dir(&root)?;
+        // Only the latest comparison of a repository is kept.
+        remove_worktrees(&root, &dir).await;
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        std::fs::create_dir_all(&dir)?;
+
+        let mut runs = Vec::new();
+        for (index, model) in self.models.iter().enumerate() {
+            let name = format!("{}-{}", index + 1, sanitize(model));
+            let worktree = dir.join(&name);
+            git(&root, [
+                OsStr::new("worktree"),
+                OsStr::new("add"),
+                OsStr::new("--detach"),
+                worktree.as_os_str(),
+                OsStr::new("HEAD"),
+            ])
+            .await?;
+            let log = dir.join(format!("{name}.log"));
+            // Created upfront so that runs can be attached to right away.
+            std::fs::File::create(&log)?;
+            runs.push(Run {
+                number: index + 1,
+                model: model.clone(),
+                // Conversations are saved under the canonical path of the directory chat runs in.
+                worktree: worktree.canonicalize()?,
+                log,
+            });
+        }
+
+        let manifest = Manifest {
+            pid: std::process::id(),
+            task: self.task.join(" "),
+            runs,
+        };
+        std::fs::write(dir.join(MANIFEST_FILE_NAME), serde_json::to_vec_pretty(&manifest)?)?;
+        let Manifest { task, runs, .. } = manifest;
+
+        execute!(
+            stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "Running the task with {} models, logs are in {}\nFollow a run with: q agent compare attach <model>\n\n",
+                runs.len(),
+                dir.display()
+            )),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+
+        let exe = std::env::current_exe()?;
+        let (exe, task, agent, test) = (&exe, &task, self.agent.as_deref(), self.test.as_deref());
+        let trust_all_tools = self.trust_all_tools;
+        let results = futures::future::join_all(runs.iter().map(|run| async move {
+            let result = execute_run(run, exe, agent, task, test, trust_all_tools).await;
+            if let Err(err) = std::fs::write(run.finished_marker(), "") {
+                warn!(?err, model = run.model, "failed to mark a compare run as finished");
+            }
+            result
+        }))
+        .await;
+
+        let mut outcomes = Vec::new();
+        for (run, result) in runs.iter().zip(results) {
+            let mut outcome = match result {
+                Ok(outcome) => outcome,
+                Err(err) => {
+                    warn!(?err, model = run.model, "compare run failed");
+                    Outcome::default()
+                },
+            };
+            outcome.tokens = match os.database.get_conversation_by_path(&run.worktree) {
+                Ok(Some(conversation)) => {
+                    let usage = conversation.history_usage().total();
+                    Some(
+                        usage.input_tokens
+                            + usage.output_tokens
+                            + usage.cache_read_input_tokens
+                            + usage.cache_write_input_tokens,
+                    )
+                },
+                _ => None,
+            };
+            outcomes.push(outcome);
+        }
+
+        print_comparison(&mut stderr, &runs, &outcomes)?;
+
+        let pick = match self.pick {
+            Some(pick) => Some(pick),
+            None if std::io::stdin().is_terminal() => ask_pick(&mut stderr, runs.len())?,
+            None => None,
+        };
+        if let Some(pick) = pick {
+            let Some(run) = runs.iter().find(|run| run.number == pick) else {
+                bail!("There is no run {pick}, pick one of 1 to {}", runs.len());
+            };
+            apply(&root, &dir, run).await?;
+            execute!(
+                stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!(
+                    "✓ Applied the changes of {} to {}\n",
+                    run.model,
+                    root.display()
+                )),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+
+        if self.keep {
+            writeln!(stderr, "The worktrees are kept in {}", dir.display())?;
+        } else {
+            remove_worktrees(&root, &dir).await;
+        }
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Prints the log of a run of the comparison in progress as it is written.
+async fn attach(os: &Os, run: &str) -> Result<ExitCode> {
+    let root = repository_root(os).await?;
+    let manifest_path = directories::agent_compare_dir(&root)?.join(MANIFEST_FILE_NAME);
+    let manifest = match std::fs::read(&manifest_path) {
+        Ok(manifest) => serde_json::from_slice::<Manifest>(&manifest)?,
+        Err(_) => bail!("No comparison was started in {}", root.display()),
+    };
+    let Some(run) = manifest
+        .runs
+        .iter()
+        .find(|r| r.model == run || r.number.to_string() == run)
+    else {
+        let models = manifest.runs.iter().map(|r| r.model.as_str()).collect::<Vec<_>>();
+        bail!("No run uses the model '{run}', pick one of: {}", models.join(", "));
+    };
+
+    let mut stderr = std::io::stderr();
+    execute!(
+        stderr,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!(
+            "Following run {} ({}): {}\n\n",
+            run.number, run.model, manifest.task
+        )),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+
+    let mut stdout = std::io::stdout();
+    let mut log = std::fs::File::open(&run.log)?;
+    let mut buf = Vec::new();
+    let finished = loop {
+        // Checked before reading so that the output written right before finishing isn't missed.
+        let finished = run.finished_marker().exists();
+        let stopped = !registry::is_process_running(manifest.pid);
+
+        buf.clear();
+        log.read_to_end(&mut buf)?;
+        stdout.write_all(&buf)?;
+        stdout.flush()?;
+
+        if finished || stopped {
+            break finished;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(FOLLOW_INTERVAL) => {},
+            _ = tokio::signal::ctrl_c() => return Ok(ExitCode::SUCCESS),
+        }
+    };
+
+    let message = if finished {
+        format!("\nRun {} ({}) finished\n", run.number, run.model)
+    } else {
+        format!(
+            "\nThe comparison stopped before run {} ({}) finished\n",
+            run.number, run.model
+        )
+    };
+    execute!(
+        stderr,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(message),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn repository_root(os: &Os) -> Result<PathBuf> {
+    let cwd = os.env.current_dir()?;
+    Ok(PathBuf::from(git(&cwd, ["rev-parse", "--show-toplevel"]).await?.trim()))
+}
+
+/// Runs the task in the worktree of `run`, then measures its changes and runs the tests.
+async fn execute_run(
+    run: &Run,
+    exe: &Path,
+    agent: Option<&str>,
+    task: &str,
+    test: Option<&str>,
+    trust_all_tools: bool,
+) -> Result<Outcome> {
+    let log = std::fs::File::create(&run.log)?;
+    let mut cmd = tokio::process::Command::new(exe);
+    cmd.args(["chat", "--no-interactive", "--model", &run.model]);
+    if trust_all_tools {
+        cmd.arg("--trust-all-tools");
+    }
+    if let Some(agent) = agent {
+        cmd.args(["--agent", agent]);
+    }
+    cmd.arg(task)
+        .current_dir(&run.worktree)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    #[cfg(not(windows))]
+    cmd.process_group(0);
+
+    let start = Instant::now();
+    let mut child = cmd.spawn()?;
+    let _child_guard = child
+        .id()
+        .map(|pid| shutdown::track_child(pid, format!("compare run {}", run.model)));
+    let completed = child.wait().await?.success();
+    let duration = start.elapsed();
+
+    // Staging makes new files show up in the diff.
+    git(&run.worktree, ["add", "--all"]).await?;
+    let (files_changed, insertions, deletions) =
+        parse_numstat(&git(&run.worktree, ["diff", "--cached", "--numstat", "HEAD"]).await?);
+
+    let tests_passed = match test {
+        Some(test) => Some(run_test(run, test).await?),
+        None => None,
+    };
+
+    Ok(Outcome {
+        completed,
+        files_changed,
+        insertions,
+        deletions,
+        tests_passed,
+        tokens: None,
+        duration,
+    })
+}
+
+/// Runs the test command in the worktree of `run`, appending its output to the log.
+async fn run_test(run: &Run, test: &str) -> Result<bool> {
+    let log = std::fs::OpenOptions::new().append(true).open(&run.log)?;
+    #[cfg(windows)]
+    let mut cmd = {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.args(["/C", test]);
+        cmd
+    };
+    #[cfg(not(windows))]
+    let mut cmd = {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", test]);
+        cmd
+    };
+    cmd.current_dir(&run.worktree)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+
+    let mut child = cmd.spawn()?;
+    let _child_guard = child
+        .id()
+        .map(|pid| shutdown::track_child(pid, format!("compare tests {}", run.model)));
+    Ok(child.wait().await?.success())
+}
+
+/// Applies the changes of `run` to the worktree of the repository.
+async fn apply(root: &Path, dir: &Path, run: &Run) -> Result<()> {
+    let patch = git(&run.worktree, ["diff", "--cached", "--binary", "HEAD"]).await?;
+    if patch.is_empty() {
+        return Ok(());
+    }
+    let patch_path = dir.join(format!("{}.patch", run.number));
+    std::fs::write(&patch_path, patch)?;
+    git(root, [OsStr::new("apply"), patch_path.as_os_str()]).await?;
+    Ok(())
+}
+
+/// Removes the worktrees of a comparison, leaving the logs.
+async fn remove_worktrees(root: &Path, dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if let Err(err) = git(root, [
+                OsStr::new("worktree"),
+                OsStr::new("remove"),
+                OsStr::new("--force"),
+                path.as_os_str(),
+            ])
+            .await
+            {
+                warn!(?err, ?path, "failed to remove a compare worktree");
+            }
+        }
+    }
+    git(root, ["worktree", "prune"]).await.ok();
+}
+
+fn print_comparison(output: &mut impl Write, runs: &[Run], outcomes: &[Outcome]) -> Result<()> {
+    let best = best_run(outcomes);
+    let model_width = runs
+        .iter()
+        .map(|run| run.model.len())
+        .max()
+        .unwrap_or(0)
+        .max("model".len());
+
+    queue!(
+        output,
+        style::SetAttribute(style::Attribute::Bold),
+        style::Print(format!(
+            "  {:<3} {:<model_width$}  {:<8}  {:>5}  {:>13}  {:<6}  {:>9}  {:>7}\n",
+            "#", "model", "status", "files", "lines", "tests", "tokens", "time"
+        )),
+        style::SetAttribute(style::Attribute::Reset),
+    )?;
+    for (index, (run, outcome)) in runs.iter().zip(outcomes).enumerate() {
+        let tests = match outcome.tests_passed {
+            Some(true) => "pass",
+            Some(false) => "fail",
+            None => "-",
+        };
+        let tokens = outcome.tokens.map_or("-".to_string(), |tokens| tokens.to_string());
+        queue!(
+            output,
+            style::SetForegroundColor(Color::Green),
+            style::Print(if best == Some(index) { "★ " } else { "  " }),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(format!(
+                "{:<3} {:<model_width$}  {:<8}  {:>5}  {:>13}  {:<6}  {:>9}  {:>7}\n",
+                run.number,
+                run.model,
+                if outcome.completed { "done" } else { "failed" },
+                outcome.files_changed,
+                format!("+{} -{}", outcome.insertions, outcome.deletions),
+                tests,
+                tokens,
+                format_duration(outcome.duration),
+            )),
+        )?;
+    }
+    queue!(output, style::Print("\n"))?;
+    output.flush()?;
+    Ok(())
+}
+
+fn ask_pick(output: &mut impl Write, runs: usize) -> Result<Option<usize>> {
+    queue!(
+        output,
+        style::Print(format!("Apply the changes of which run? [1-{runs}, empty to skip]: ")),
+    )?;
+    output.flush()?;
+
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    match input.parse() {
+        Ok(pick) => Ok(Some(pick)),
+        Err(_) => bail!("Invalid run number '{input}'"),
+    }
+}
+
+/// The index of the run to recommend: one that completed and passed its tests, changing
+/// something, with the fewest tokens used as a tie-breaker.
+fn best_run(outcomes: &[Outcome]) -> Option<usize> {
+    outcomes
+        .iter()
+        .enumerate()
+        .filter(|(_, outcome)| outcome.completed && outcome.tests_passed != Some(false) && outcome.files_changed > 0)
+        .min_by_key(|(_, outcome)| outcome.tokens.unwrap_or(u64::MAX))
+        .map(|(index, _)| index)
+}
+
+/// Sums the output of `git diff --numstat` into files changed, insertions, and deletions. Binary
+/// files count as changed without any lines.
+fn parse_numstat(numstat: &str) -> (usize, usize, usize) {
+    let mut totals = (0, 0, 0);
+    for line in numstat.lines().filter(|line| !line.trim().is_empty()) {
+        let mut fields = line.split('\t');
+        totals.0 += 1;
+        totals.1 += fields.next().and_then(|n| n.parse::<usize>().ok()).unwrap_or(0);
+        totals.2 += fields.next().and_then(|n| n.parse::<usize>().ok()).unwrap_or(0);
+    }
+    totals
+}
+
+/// A model id usable as a directory name.
+fn sanitize(model: &str) -> String {
+    model
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Runs git in `dir`, returning its output.
+async fn git<I, S>(dir: &Path, args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = tokio::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!("git failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_numstat() {
+        assert_eq!(parse_numstat(""), (0, 0, 0));
+        assert_eq!(
+            parse_numstat("10\t2\tsrc/main.rs\n-\t-\tassets/logo.png\n3\t0\tREADME.md\n"),
+            (3, 13, 2)
+        );
+    }
+
+    #[test]
+    fn test_best_run() {
+        let outcome = |completed, files_changed, tests_passed, tokens| Outcome {
+            completed,
+            files_changed,
+            tests_passed,
+            tokens,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            best_run(&[
+                outcome(true, 2, Some(false), Some(100)),
+                outcome(true, 3, Some(true), Some(5000)),
+                outcome(true, 1, Some(true), Some(2000)),
+                outcome(false, 1, Some(true), Some(10)),
+            ]),
+            Some(2)
+        );
+        assert_eq!(best_run(&[outcome(true, 0, None, Some(10))]), None);
+    }
+}
@@ -23,6 +23,8 @@ pub enum HookTrigger {
     PostToolUse,
     /// Triggered when the assistant finishes responding
     Stop,
+    /// Triggered once when the chat session ends
+    SessionEnd,
 }
 
 impl Display for HookTrigger {
@@ -33,6 +35,7 @@ impl Display for HookTrigger {
             HookTrigger::PreToolUse => write!(f, "preToolUse"),
             HookTrigger::PostToolUse => write!(f, "postToolUse"),
             HookTrigger::Stop => write!(f, "stop"),
+            HookTrigger::SessionEnd => write!(f, "sessionEnd"),
         }
     }
 }
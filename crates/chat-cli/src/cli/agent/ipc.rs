@@ -0,0 +1,665 @@
+//! Control channel used to reach a running chat session, e.g. from `q agent send`.
+//!
+//! Every registered session listens on an endpoint derived from its pid: a unix domain socket in
+//! the agent registry directory, or a named pipe on Windows. The messages exchanged over it are
+//! defined in [super::protocol].
+
+use std::io;
+use std::sync::Arc;
+
+use eyre::{
+    Result,
+    bail,
+};
+use parking_lot::Mutex;
+use tokio::io::{
+    AsyncRead,
+    AsyncWrite,
+};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{
+    broadcast,
+    mpsc,
+    oneshot,
+};
+use tracing::{
+    debug,
+    warn,
+};
+
+use super::bridge::IdeBridge;
+use super::protocol::{
+    self,
+    Capability,
+    Connection,
+    ErrorCode,
+    PROTOCOL_VERSION,
+    Request,
+    Response,
+    Selection,
+    SessionEvent,
+    SessionStatus,
+};
+use super::registry::RegistryEntry;
+use crate::util::shutdown;
+
+/// A connection to or from a session's control endpoint.
+pub trait ControlStream: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+
+impl<T> ControlStream for T where T: AsyncRead + AsyncWrite + Unpin + Send + 'static {}
+
+/// The platform specific transport behind the control channel.
+#[async_trait::async_trait]
+pub trait ControlListener: Sized + Send + 'static {
+    /// The server side of a connection.
+    type Stream: ControlStream;
+    /// The client side of a connection.
+    type Client: ControlStream;
+
+    /// Starts listening on the endpoint of the session with the given pid.
+    fn bind(pid: u32) -> io::Result<Self>;
+
+    /// Waits for the next connection.
+    async fn accept(&mut self) -> io::Result<Self::Stream>;
+
+    /// Connects to the endpoint of the session with the given pid.
+    async fn connect(pid: u32) -> io::Result<Self::Client>;
+}
+
+#[cfg(unix)]
+pub type PlatformListener = unix::UnixSocketListener;
+#[cfg(windows)]
+pub type PlatformListener = windows::NamedPipeListener;
+
+pub type PlatformClient = <PlatformListener as ControlListener>::Client;
+
+/// A prompt received over the control channel, to be handled by the session like user input.
+#[derive(Debug)]
+pub struct IncomingPrompt {
+    pub text: String,
+    /// Set when the client waits for the final response of the turn, or for the reason it failed.
+    pub reply: Option<oneshot::Sender<Result<String, String>>>,
+}
+
+/// Accepts connections on `listener` until the receiver of `prompts` is dropped. Status requests
+/// are answered from `entry`, prompts are forwarded to `prompts`, and what IDEs send or follow
+/// goes through `bridge`.
+pub async fn serve<L: ControlListener>(
+    mut listener: L,
+    entry: Arc<Mutex<RegistryEntry>>,
+    bridge: Arc<IdeBridge>,
+    prompts: mpsc::UnboundedSender<IncomingPrompt>,
+) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!(?err, "failed to accept a control connection");
+                return;
+            },
+        };
+        if prompts.is_closed() {
+            return;
+        }
+
+        let (entry, bridge, prompts) = (Arc::clone(&entry), Arc::clone(&bridge), prompts.clone());
+        tokio::spawn(async move {
+            let mut connection = protocol::framed(stream);
+            if let Err(err) = handle_connection(&mut connection, &entry, &bridge, &prompts).await {
+                debug!(?err, "control connection failed");
+            }
+        });
+    }
+}
+
+async fn handle_connection<S: ControlStream>(
+    connection: &mut Connection<S>,
+    entry: &Mutex<RegistryEntry>,
+    bridge: &IdeBridge,
+    prompts: &mpsc::UnboundedSender<IncomingPrompt>,
+) -> Result<()> {
+    let capabilities = match protocol::read::<_, Request>(connection).await {
+        Ok(Some(Request::Hello { version, capabilities })) => {
+            if version != PROTOCOL_VERSION {
+                let message = format!("protocol version {version} is not supported, expected {PROTOCOL_VERSION}");
+                return protocol::write(connection, &Response::error(ErrorCode::UnsupportedVersion, message)).await;
+            }
+            let capabilities = protocol::negotiate(&capabilities);
+            protocol::write(connection, &Response::Hello {
+                version: PROTOCOL_VERSION,
+                capabilities: capabilities.clone(),
+            })
+            .await?;
+            capabilities
+        },
+        Ok(Some(_)) => {
+            let response = Response::error(ErrorCode::InvalidRequest, "expected a hello request");
+            return protocol::write(connection, &response).await;
+        },
+        Ok(None) => return Ok(()),
+        Err(err) => {
+            let response = Response::error(ErrorCode::InvalidRequest, err.to_string());
+            return protocol::write(connection, &response).await;
+        },
+    };
+
+    loop {
+        let response = match protocol::read::<_, Request>(connection).await {
+            Ok(Some(Request::Subscribe)) => match check_capability(&capabilities, Capability::Events) {
+                Some(response) => response,
+                None => {
+                    let events = bridge.subscribe();
+                    protocol::write(connection, &Response::Accepted).await?;
+                    return stream_events(connection, events).await;
+                },
+            },
+            Ok(Some(request)) => handle_request(request, &capabilities, entry, bridge, prompts).await,
+            Ok(None) => return Ok(()),
+            Err(err) => Response::error(ErrorCode::InvalidRequest, err.to_string()),
+        };
+        protocol::write(connection, &response).await?;
+    }
+}
+
+/// Sends the events of the session until either side goes away.
+async fn stream_events<S: ControlStream>(
+    connection: &mut Connection<S>,
+    mut events: broadcast::Receiver<SessionEvent>,
+) -> Result<()> {
+    loop {
+        match events.recv().await {
+            Ok(event) => protocol::write(connection, &Response::Event(event)).await?,
+            Err(RecvError::Lagged(missed)) => debug!(missed, "a control connection fell behind on events"),
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+async fn handle_request(
+    request: Request,
+    capabilities: &[Capability],
+    entry: &Mutex<RegistryEntry>,
+    bridge: &IdeBridge,
+    prompts: &mpsc::UnboundedSender<IncomingPrompt>,
+) -> Response {
+    match request {
+        Request::Hello { .. } => Response::error(ErrorCode::InvalidRequest, "the connection was already set up"),
+        Request::Status => {
+            if let Some(response) = check_capability(capabilities, Capability::Status) {
+                return response;
+            }
+
+            let entry = entry.lock();
+            Response::Status(SessionStatus {
+                pid: entry.pid,
+                agent: entry.agent.clone(),
+                conversation_id: entry.conversation_id.clone(),
+                cwd: entry.cwd.clone(),
+            })
+        },
+        Request::Prompt { text, wait } => {
+            let required = if wait {
+                Capability::PromptResult
+            } else {
+                Capability::Prompt
+            };
+            if let Some(response) = check_capability(capabilities, required) {
+                return response;
+            }
+            if text.trim().is_empty() {
+                return Response::error(ErrorCode::InvalidRequest, "the prompt is empty");
+            }
+
+            let (reply, result) = match wait {
+                true => {
+                    let (tx, rx) = oneshot::channel();
+                    (Some(tx), Some(rx))
+                },
+                false => (None, None),
+            };
+            if prompts.send(IncomingPrompt { text, reply }).is_err() {
+                return Response::error(ErrorCode::Unavailable, "the session is exiting");
+            }
+
+            match result {
+                None => Response::Accepted,
+                Some(result) => match result.await {
+                    Ok(Ok(response)) => Response::Completed { response },
+                    Ok(Err(message)) => Response::error(ErrorCode::Failed, message),
+                    Err(_) => Response::error(ErrorCode::Unavailable, "the session exited before the turn finished"),
+                },
+            }
+        },
+        Request::Shutdown => {
+            if let Some(response) = check_capability(capabilities, Capability::Shutdown) {
+                return response;
+            }
+
+            shutdown::request();
+            Response::Accepted
+        },
+        Request::AddContext { selection } => {
+            if let Some(response) = check_capability(capabilities, Capability::Context) {
+                return response;
+            }
+            if selection.text.trim().is_empty() {
+                return Response::error(ErrorCode::InvalidRequest, "the selection is empty");
+            }
+
+            bridge.add_selection(selection);
+            Response::Accepted
+        },
+        Request::Transcript => {
+            if let Some(response) = check_capability(capabilities, Capability::Transcript) {
+                return response;
+            }
+
+            Response::Transcript {
+                messages: bridge.transcript(),
+            }
+        },
+        Request::Subscribe => Response::error(ErrorCode::InvalidRequest, "subscribing is handled per connection"),
+    }
+}
+
+/// Returns an error response if `capability` was not negotiated.
+fn check_capability(capabilities: &[Capability], capability: Capability) -> Option<Response> {
+    (!capabilities.contains(&capability)).then(|| {
+        Response::error(
+            ErrorCode::Unsupported,
+            format!("the {capability:?} capability was not negotiated"),
+        )
+    })
+}
+
+/// A client connection to the control channel of a running session.
+#[derive(Debug)]
+pub struct ControlClient<S: ControlStream = PlatformClient> {
+    connection: Connection<S>,
+    capabilities: Vec<Capability>,
+}
+
+impl ControlClient {
+    /// Connects to the session with the given pid.
+    pub async fn connect(pid: u32) -> Result<Self> {
+        Self::handshake(PlatformListener::connect(pid).await?).await
+    }
+}
+
+impl<S: ControlStream> ControlClient<S> {
+    async fn handshake(stream: S) -> Result<Self> {
+        let mut connection = protocol::framed(stream);
+        protocol::write(&mut connection, &Request::Hello {
+            version: PROTOCOL_VERSION,
+            capabilities: Capability::ALL.to_vec(),
+        })
+        .await?;
+
+        match protocol::read::<_, Response>(&mut connection).await? {
+            Some(Response::Hello { capabilities, .. }) => Ok(Self {
+                connection,
+                capabilities,
+            }),
+            Some(Response::Error { message, .. }) => bail!("the session refused the connection: {message}"),
+            Some(other) => bail!("unexpected response from the session: {other:?}"),
+            None => bail!("the session closed the connection"),
+        }
+    }
+
+    /// Whether the session supports `capability`.
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Sends `request` and returns its response. Error responses are returned as errors.
+    pub async fn request(&mut self, request: &Request) -> Result<Response> {
+        protocol::write(&mut self.connection, request).await?;
+        match protocol::read::<_, Response>(&mut self.connection).await? {
+            Some(Response::Error { message, .. }) => bail!("{message}"),
+            Some(response) => Ok(response),
+            None => bail!("the session closed the connection"),
+        }
+    }
+
+    pub async fn status(&mut self) -> Result<SessionStatus> {
+        match self.request(&Request::Status).await? {
+            Response::Status(status) => Ok(status),
+            other => bail!("unexpected response from the session: {other:?}"),
+        }
+    }
+
+    /// Submits a prompt. With `wait`, returns the final response of the turn it started.
+    pub async fn prompt(&mut self, text: impl Into<String>, wait: bool) -> Result<Option<String>> {
+        let request = Request::Prompt {
+            text: text.into(),
+            wait,
+        };
+        match self.request(&request).await? {
+            Response::Accepted if !wait => Ok(None),
+            Response::Completed { response } if wait => Ok(Some(response)),
+            other => bail!("unexpected response from the session: {other:?}"),
+        }
+    }
+
+    /// Asks the session to exit.
+    pub async fn shutdown(&mut self) -> Result<()> {
+        match self.request(&Request::Shutdown).await? {
+            Response::Accepted => Ok(()),
+            other => bail!("unexpected response from the session: {other:?}"),
+        }
+    }
+
+    /// Attaches `selection` to the next prompt of the session.
+    pub async fn add_context(&mut self, selection: Selection) -> Result<()> {
+        match self.request(&Request::AddContext { selection }).await? {
+            Response::Accepted => Ok(()),
+            other => bail!("unexpected response from the session: {other:?}"),
+        }
+    }
+
+    pub async fn transcript(&mut self) -> Result<Vec<String>> {
+        match self.request(&Request::Transcript).await? {
+            Response::Transcript { messages } => Ok(messages),
+            other => bail!("unexpected response from the session: {other:?}"),
+        }
+    }
+
+    /// Turns the connection into one receiving the events of the session.
+    pub async fn subscribe(mut self) -> Result<EventStream<S>> {
+        match self.request(&Request::Subscribe).await? {
+            Response::Accepted => Ok(EventStream {
+                connection: self.connection,
+            }),
+            other => bail!("unexpected response from the session: {other:?}"),
+        }
+    }
+}
+
+/// A connection subscribed to the events of a session.
+#[derive(Debug)]
+pub struct EventStream<S: ControlStream = PlatformClient> {
+    connection: Connection<S>,
+}
+
+impl<S: ControlStream> EventStream<S> {
+    /// The next event, or `None` once the session closed the connection.
+    pub async fn next(&mut self) -> Result<Option<SessionEvent>> {
+        match protocol::read::<_, Response>(&mut self.connection).await? {
+            Some(Response::Event(event)) => Ok(Some(event)),
+            Some(other) => bail!("unexpected message from the session: {other:?}"),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::io;
+    use std::path::{
+        Path,
+        PathBuf,
+    };
+
+    use tokio::net::{
+        UnixListener,
+        UnixStream,
+    };
+
+    use super::ControlListener;
+    use crate::util::directories::agent_registry_dir;
+    use crate::util::shutdown;
+
+    /// Listens on `<agent registry dir>/<pid>.sock`.
+    #[derive(Debug)]
+    pub struct UnixSocketListener {
+        listener: UnixListener,
+        path: PathBuf,
+    }
+
+    impl UnixSocketListener {
+        pub(super) fn bind_in(dir: &Path, pid: u32) -> io::Result<Self> {
+            std::fs::create_dir_all(dir)?;
+            let path = socket_path(dir, pid);
+            // A socket left behind by an earlier process with the same pid.
+            match std::fs::remove_file(&path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {},
+            }
+            let listener = UnixListener::bind(&path)?;
+            shutdown::register_path(&path);
+            Ok(Self { listener, path })
+        }
+    }
+
+    fn socket_path(dir: &Path, pid: u32) -> PathBuf {
+        dir.join(format!("{pid}.sock"))
+    }
+
+    fn registry_dir() -> io::Result<PathBuf> {
+        agent_registry_dir().map_err(io::Error::other)
+    }
+
+    #[async_trait::async_trait]
+    impl ControlListener for UnixSocketListener {
+        type Client = UnixStream;
+        type Stream = UnixStream;
+
+        fn bind(pid: u32) -> io::Result<Self> {
+            Self::bind_in(&registry_dir()?, pid)
+        }
+
+        async fn accept(&mut self) -> io::Result<Self::Stream> {
+            self.listener.accept().await.map(|(stream, _)| stream)
+        }
+
+        async fn connect(pid: u32) -> io::Result<Self::Client> {
+            UnixStream::connect(socket_path(&registry_dir()?, pid)).await
+        }
+    }
+
+    impl Drop for UnixSocketListener {
+        fn drop(&mut self) {
+            std::fs::remove_file(&self.path).ok();
+            shutdown::unregister_path(&self.path);
+        }
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+    use std::time::Duration;
+
+    use tokio::net::windows::named_pipe::{
+        ClientOptions,
+        NamedPipeClient,
+        NamedPipeServer,
+        ServerOptions,
+    };
+
+    use super::ControlListener;
+
+    /// Returned when every instance of the pipe is busy.
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    /// How many times to retry connecting to a busy pipe.
+    const CONNECT_ATTEMPTS: usize = 20;
+
+    /// Listens on `\\.\pipe\amazon-q-agent-<pid>`.
+    #[derive(Debug)]
+    pub struct NamedPipeListener {
+        name: String,
+        /// The instance waiting for the next client.
+        server: NamedPipeServer,
+    }
+
+    fn pipe_name(pid: u32) -> String {
+        format!(r"\\.\pipe\amazon-q-agent-{pid}")
+    }
+
+    #[async_trait::async_trait]
+    impl ControlListener for NamedPipeListener {
+        type Client = NamedPipeClient;
+        type Stream = NamedPipeServer;
+
+        fn bind(pid: u32) -> io::Result<Self> {
+            let name = pipe_name(pid);
+            let server = ServerOptions::new().first_pipe_instance(true).create(&name)?;
+            Ok(Self { name, server })
+        }
+
+        async fn accept(&mut self) -> io::Result<Self::Stream> {
+            self.server.connect().await?;
+            // A new instance has to exist before the connected one is handed out, otherwise
+            // clients connecting in between would fail.
+            let next = ServerOptions::new().create(&self.name)?;
+            Ok(std::mem::replace(&mut self.server, next))
+        }
+
+        async fn connect(pid: u32) -> io::Result<Self::Client> {
+            let name = pipe_name(pid);
+            let mut attempts = 0;
+            loop {
+                match ClientOptions::new().open(&name) {
+                    Err(err) if err.raw_os_error() == Some(ERROR_PIPE_BUSY) && attempts < CONNECT_ATTEMPTS => {
+                        attempts += 1;
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                    },
+                    result => return result,
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_serve_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let listener = unix::UnixSocketListener::bind_in(dir.path(), 1).unwrap();
+        let entry = Arc::new(Mutex::new(RegistryEntry {
+            pid: 1,
+            agent: "dev".to_string(),
+            conversation_id: "conversation".to_string(),
+            cwd: dir.path().to_path_buf(),
+            started_at: Utc::now(),
+            process_start_time: None,
+            heartbeat_at: Utc::now(),
+            children: Vec::new(),
+        }));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tokio::spawn(serve(listener, entry, Arc::default(), tx));
+
+        let stream = tokio::net::UnixStream::connect(dir.path().join("1.sock"))
+            .await
+            .unwrap();
+        let mut client = ControlClient::handshake(stream).await.unwrap();
+        assert!(Capability::ALL.iter().all(|capability| client.supports(*capability)));
+        assert_eq!(client.status().await.unwrap().agent, "dev");
+
+        assert_eq!(client.prompt("hello", false).await.unwrap(), None);
+        let prompt = rx.recv().await.unwrap();
+        assert_eq!(prompt.text, "hello");
+        assert!(prompt.reply.is_none());
+
+        tokio::spawn(async move {
+            let prompt = rx.recv().await.unwrap();
+            prompt
+                .reply
+                .unwrap()
+                .send(Ok(format!("answered {}", prompt.text)))
+                .unwrap();
+        });
+        assert_eq!(
+            client.prompt("question", true).await.unwrap(),
+            Some("answered question".to_string())
+        );
+        assert!(client.prompt("  ", false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_ide_bridge() {
+        let entry = Arc::new(Mutex::new(RegistryEntry {
+            pid: 1,
+            agent: "dev".to_string(),
+            conversation_id: "conversation".to_string(),
+            cwd: Default::default(),
+            started_at: Utc::now(),
+            process_start_time: None,
+            heartbeat_at: Utc::now(),
+            children: Vec::new(),
+        }));
+        let bridge = Arc::new(IdeBridge::default());
+        bridge.set_transcript(vec!["> hi".to_string(), "hello".to_string()]);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let connect = || {
+            let (client, server) = tokio::io::duplex(1024);
+            let (entry, bridge, tx) = (Arc::clone(&entry), Arc::clone(&bridge), tx.clone());
+            tokio::spawn(async move {
+                let mut connection = protocol::framed(server);
+                handle_connection(&mut connection, &entry, &bridge, &tx).await
+            });
+            ControlClient::handshake(client)
+        };
+
+        let mut client = connect().await.unwrap();
+        assert_eq!(client.transcript().await.unwrap(), vec!["> hi", "hello"]);
+        let selection = Selection {
+            path: "src/main.rs".into(),
+            start_line: Some(1),
+            end_line: Some(1),
+            text: "fn main() {}".to_string(),
+        };
+        client.add_context(selection.clone()).await.unwrap();
+        assert_eq!(bridge.take_selections(), vec![selection]);
+
+        let mut events = connect().await.unwrap().subscribe().await.unwrap();
+        let event = SessionEvent::EditApplied {
+            path: "src/main.rs".into(),
+            tool_use_id: "tooluse_1".to_string(),
+        };
+        bridge.notify(event.clone());
+        assert_eq!(events.next().await.unwrap(), Some(event));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_version() {
+        let (client, server) = tokio::io::duplex(1024);
+        let entry = Arc::new(Mutex::new(RegistryEntry {
+            pid: 1,
+            agent: "dev".to_string(),
+            conversation_id: "conversation".to_string(),
+            cwd: Default::default(),
+            started_at: Utc::now(),
+            process_start_time: None,
+            heartbeat_at: Utc::now(),
+            children: Vec::new(),
+        }));
+        let (tx, _rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut connection = protocol::framed(server);
+            handle_connection(&mut connection, &entry, &IdeBridge::default(), &tx).await
+        });
+
+        let mut client = protocol::framed(client);
+        protocol::write(&mut client, &Request::Hello {
+            version: PROTOCOL_VERSION + 1,
+            capabilities: Vec::new(),
+        })
+        .await
+        .unwrap();
+        assert!(matches!(
+            protocol::read::<_, Response>(&mut client).await.unwrap(),
+            Some(Response::Error {
+                code: ErrorCode::UnsupportedVersion,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::time::Duration;
+
+use regex::RegexSet;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use thiserror::Error;
+use tracing::warn;
+
+use super::AgentConfigError;
+use crate::cli::chat::tools::Tool;
+use crate::os::Os;
+use crate::util::directories;
+use crate::util::tool_permission_checker::is_tool_in_allowlist;
+
+/// How long fetching the policy from its endpoint may take before the file is used instead.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shell constructs that would run a second command after an allowed one, or redirect its output.
+const CHAINING_PATTERNS: &[&str] = &[";", "&", "|", "`", "$(", "<(", ">", "\n", "\r"];
+
+/// The policy an administrator installs at [directories::managed_policy_path] to govern which
+/// tools q chat may use on the machine.
+///
+/// It is evaluated before the permissions of the agent, and neither the agent config,
+/// `/tools trust` nor --trust-all-tools can loosen it. When it sets `url`, the policy served at
+/// that endpoint is used instead, and the file is the fallback if it can't be fetched.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ManagedPolicy {
+    /// Tools that are never run, named as in the allowedTools of an agent config
+    #[serde(default)]
+    pub denied_tools: HashSet<String>,
+    /// If set, execute_bash only runs commands matching one of these regular expressions, and no
+    /// command that chains another
+    #[serde(default)]
+    pub allowed_commands: Option<Vec<String>>,
+    /// Whether MCP servers are disabled altogether
+    #[serde(default)]
+    pub disable_mcp: bool,
+    /// Classes of actions the user is asked about every time, whatever the agent trusts
+    #[serde(default)]
+    pub require_approval: Vec<ApprovalClass>,
+    /// The endpoint to fetch the policy from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Where the policy was read from
+    #[serde(skip)]
+    pub source: String,
+    /// allowedCommands, compiled by [Self::parse]
+    #[serde(skip)]
+    allowed_command_patterns: Option<RegexSet>,
+}
+
+#[derive(Debug, Error)]
+pub enum ManagedPolicyError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("invalid allowedCommands pattern: {0}")]
+    Pattern(#[from] regex::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum ApprovalClass {
+    /// Tools that change files, or run commands or AWS operations that aren't read-only
+    Mutating,
+    /// execute_bash
+    Commands,
+    /// use_aws
+    Aws,
+    /// CloudFormation, CDK and SAM deployments
+    Infrastructure,
+    /// Tools of MCP servers
+    Mcp,
+}
+
+impl ApprovalClass {
+    fn includes(&self, tool: &Tool) -> bool {
+        match self {
+            Self::Mutating => tool.is_mutating(),
+            Self::Commands => matches!(tool, Tool::ExecuteCommand(_)),
+            Self::Aws => matches!(tool, Tool::UseAws(_)),
+            Self::Infrastructure => tool.infra_change().is_some(),
+            Self::Mcp => matches!(tool, Tool::Custom(_)),
+        }
+    }
+}
+
+/// What the managed policy makes of a tool use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PolicyEvalResult {
+    /// The policy has no say, the permissions of the agent decide.
+    Unrestricted,
+    /// The user must accept the tool use, even if the agent trusts it.
+    RequireApproval(ApprovalClass),
+    /// The tool use is rejected, with the rule that rejects it.
+    Deny(String),
+}
+
+impl Display for PolicyEvalResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unrestricted => write!(f, "the managed policy doesn't restrict it"),
+            Self::RequireApproval(class) => write!(f, "the managed policy requires approval of {class} tools"),
+            Self::Deny(rule) => write!(f, "the managed policy {rule}"),
+        }
+    }
+}
+
+impl ManagedPolicy {
+    /// Reads the policy installed on the machine, `None` if there is none.
+    pub async fn load(os: &Os) -> Result<Option<Self>, AgentConfigError> {
+        let path = directories::managed_policy_path(os)?;
+        if !os.fs.exists(&path) {
+            return Ok(None);
+        }
+
+        let content = os.fs.read_to_string(&path).await?;
+        let mut policy = Self::parse(&content).map_err(|error| AgentConfigError::InvalidManagedPolicy {
+            error,
+            path: path.clone(),
+        })?;
+        policy.source = path.display().to_string();
+
+        if let Some(url) = policy.url.clone() {
+            match fetch(&url).await {
+                Ok(mut fetched) => {
+                    fetched.url = Some(url.clone());
+                    fetched.source = url;
+                    policy = fetched;
+                },
+                Err(err) => warn!(%err, %url, "Failed to fetch the managed policy, using {}", policy.source),
+            }
+        }
+
+        Ok(Some(policy))
+    }
+
+    /// Parses a policy, compiling its allowedCommands so that an invalid pattern is an error rather
+    /// than a rule that is silently dropped.
+    pub fn parse(content: &str) -> Result<Self, ManagedPolicyError> {
+        let mut policy = serde_json::from_str::<Self>(content)?;
+        if let Some(allowed_commands) = &policy.allowed_commands {
+            policy.allowed_command_patterns = Some(RegexSet::new(
+                allowed_commands.iter().map(|allowed| format!(r"\A(?:{allowed})\z")),
+            )?);
+        }
+        Ok(policy)
+    }
+
+    /// Whether the policy allows using MCP servers at all.
+    pub fn mcp_enabled(policy: Option<&Self>) -> bool {
+        policy.is_none_or(|policy| !policy.disable_mcp)
+    }
+
+    /// What the policy makes of a use of `tool`, before the agent is asked.
+    pub fn eval(&self, tool: &Tool) -> PolicyEvalResult {
+        let denied = match tool {
+            Tool::Custom(custom_tool) => self.eval_mcp(&custom_tool.server_name, &custom_tool.name),
+            Tool::ExecuteCommand(execute) => self
+                .eval_native(&tool.display_name())
+                .or_else(|| self.eval_command(&execute.command)),
+            Tool::Thinking(_) => self.eval_native("thinking"),
+            _ => self.eval_native(&tool.display_name()),
+        };
+        if let Some(rule) = denied {
+            return PolicyEvalResult::Deny(rule);
+        }
+
+        match self.require_approval.iter().find(|class| class.includes(tool)) {
+            Some(class) => PolicyEvalResult::RequireApproval(*class),
+            None => PolicyEvalResult::Unrestricted,
+        }
+    }
+
+    /// The rule denying the tool `tool_name` of the MCP server `server`, if one does. Approval
+    /// classes need the tool itself, see [Self::eval].
+    pub fn eval_mcp(&self, server: &str, tool_name: &str) -> Option<String> {
+        if self.disable_mcp {
+            return Some("disables MCP".to_string());
+        }
+        self.denied_tools
+            .iter()
+            .find(|pattern| is_tool_in_allowlist(&HashSet::from([(*pattern).clone()]), tool_name, Some(server)))
+            .map(|pattern| format!("denies \"{pattern}\""))
+    }
+
+    fn eval_native(&self, tool_name: &str) -> Option<String> {
+        self.denied_tools
+            .iter()
+            .find(|pattern| is_tool_in_allowlist(&HashSet::from([(*pattern).clone()]), tool_name, None))
+            .map(|pattern| format!("denies \"{pattern}\""))
+    }
+
+    fn eval_command(&self, command: &str) -> Option<String> {
+        self.allowed_commands.as_ref()?;
+        let chains = CHAINING_PATTERNS.iter().any(|pattern| command.contains(pattern));
+        // A policy that wasn't parsed with [Self::parse] allows no command.
+        let allowed = !chains
+            && self
+                .allowed_command_patterns
+                .as_ref()
+                .is_some_and(|patterns| patterns.is_match(command.trim()));
+        (!allowed).then(|| "only allows the commands of allowedCommands".to_string())
+    }
+}
+
+async fn fetch(url: &str) -> eyre::Result<ManagedPolicy> {
+    let response = crate::request::new_client()?
+        .get(url)
+        .timeout(FETCH_TIMEOUT)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(ManagedPolicy::parse(&response.text().await?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::tools::execute::ExecuteCommand;
+    use crate::cli::chat::tools::fs_read::FsRead;
+
+    const POLICY: &str = r#"{
+        "deniedTools": ["fs_write", "@github/create_*"],
+        "allowedCommands": ["git (status|log|diff).*", "cargo (build|test).*"],
+        "requireApproval": ["aws", "commands"]
+    }"#;
+
+    fn execute(command: &str) -> Tool {
+        Tool::ExecuteCommand(ExecuteCommand {
+            command: command.to_string(),
+            summary: None,
+            notice: None,
+        })
+    }
+
+    #[test]
+    fn test_eval() {
+        let policy = ManagedPolicy::parse(POLICY).unwrap();
+
+        let read = serde_json::from_value::<FsRead>(serde_json::json!({
+            "operations": [{ "mode": "Line", "path": "README.md" }]
+        }))
+        .unwrap();
+        assert_eq!(policy.eval(&Tool::FsRead(read)), PolicyEvalResult::Unrestricted);
+
+        assert_eq!(
+            policy.eval(&execute("cargo test --workspace")),
+            PolicyEvalResult::RequireApproval(ApprovalClass::Commands)
+        );
+        assert_eq!(
+            policy.eval(&execute("rm -rf target")),
+            PolicyEvalResult::Deny("only allows the commands of allowedCommands".to_string())
+        );
+        assert!(matches!(
+            policy.eval(&execute("git status; rm -rf target")),
+            PolicyEvalResult::Deny(_)
+        ));
+
+        assert_eq!(
+            policy.eval_mcp("github", "create_pr"),
+            Some("denies \"@github/create_*\"".to_string())
+        );
+        assert_eq!(policy.eval_mcp("github", "list_prs"), None);
+    }
+
+    #[test]
+    fn test_disable_mcp() {
+        let policy = ManagedPolicy::parse(r#"{ "disableMcp": true }"#).unwrap();
+        assert!(!ManagedPolicy::mcp_enabled(Some(&policy)));
+        assert!(ManagedPolicy::mcp_enabled(None));
+        assert_eq!(policy.eval_mcp("git", "git_status"), Some("disables MCP".to_string()));
+        assert_eq!(policy.eval(&execute("rm -rf target")), PolicyEvalResult::Unrestricted);
+    }
+
+    #[tokio::test]
+    async fn test_load() {
+        let os = Os::new().await.unwrap();
+        assert!(ManagedPolicy::load(&os).await.unwrap().is_none());
+
+        let path = directories::managed_policy_path(&os).unwrap();
+        os.fs.create_dir_all(path.parent().unwrap()).await.unwrap();
+        os.fs.write(&path, POLICY).await.unwrap();
+        let policy = ManagedPolicy::load(&os).await.unwrap().unwrap();
+        assert_eq!(policy.source, path.display().to_string());
+        assert_eq!(policy.eval_command("git status"), None);
+
+        os.fs
+            .write(&path, r#"{ "allowedCommands": ["git (status"] }"#)
+            .await
+            .unwrap();
+        assert!(matches!(
+            ManagedPolicy::load(&os).await,
+            Err(AgentConfigError::InvalidManagedPolicy { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            ManagedPolicy::parse(r#"{ "deniedTool": ["fs_write"] }"#),
+            Err(ManagedPolicyError::Json(_))
+        ));
+        assert!(matches!(
+            ManagedPolicy::parse(r#"{ "allowedCommands": ["git (status"] }"#),
+            Err(ManagedPolicyError::Pattern(_))
+        ));
+    }
+}
@@ -1,7 +1,15 @@
+pub mod bridge;
+mod broadcast;
+mod compare;
 pub mod hook;
+pub mod ipc;
 mod legacy;
+mod managed_policy;
 mod mcp_config;
+pub mod protocol;
+pub mod registry;
 mod root_command_args;
+mod workspace_config;
 mod wrapper_types;
 
 use std::borrow::Borrow;
@@ -29,6 +37,11 @@ use crossterm::{
     style,
 };
 use eyre::bail;
+pub use managed_policy::{
+    ApprovalClass,
+    ManagedPolicy,
+    PolicyEvalResult,
+};
 pub use mcp_config::McpServerConfig;
 pub use root_command_args::*;
 use schemars::{
@@ -46,6 +59,7 @@ use tracing::{
     info,
     warn,
 };
+pub use workspace_config::WorkspaceConfig;
 use wrapper_types::ResourcePath;
 pub use wrapper_types::{
     OriginalToolName,
@@ -54,6 +68,7 @@ pub use wrapper_types::{
     tool_settings_schema,
 };
 
+use super::chat::tools::execute::EXECUTE_TOOL_NAME;
 use super::chat::tools::{
     DEFAULT_APPROVE,
     NATIVE_TOOLS,
@@ -90,6 +105,15 @@ pub enum AgentConfigError {
     Io(#[from] std::io::Error),
     #[error("Failed to parse legacy mcp config: {0}")]
     BadLegacyMcpConfig(#[from] eyre::Report),
+    #[error("Workspace config at {} is invalid: {}", path.display(), error)]
+    InvalidToml { error: toml::de::Error, path: PathBuf },
+    #[error("Managed policy at {} is invalid: {}", path.display(), error)]
+    InvalidManagedPolicy {
+        error: managed_policy::ManagedPolicyError,
+        path: PathBuf,
+    },
+    #[error("Encountered database error: {0}")]
+    Database(#[from] crate::database::DatabaseError),
 }
 
 /// An [Agent] is a declarative way of configuring a given instance of q chat. Currently, it is
@@ -145,7 +169,7 @@ pub struct Agent {
     /// List of tools the agent is explicitly allowed to use
     #[serde(default)]
     pub allowed_tools: HashSet<String>,
-    /// Files to include in the agent's context
+    /// Files to include in the agent's context, and `@git` for the state of the git repository
     #[serde(default)]
     pub resources: Vec<ResourcePath>,
     /// Commands to run when a chat session is created
@@ -257,14 +281,16 @@ impl Agent {
     }
 
     pub fn print_overridden_permissions(&self, output: &mut impl Write) -> Result<(), AgentConfigError> {
-        let execute_name = if cfg!(windows) { "execute_cmd" } else { "execute_bash" };
         for allowed_tool in &self.allowed_tools {
             if let Some(settings) = self.tools_settings.get(allowed_tool.as_str()) {
-                // currently we only have four native tools that offers tool settings
+                // currently we only have five native tools that offers tool settings
                 let overridden_settings_key = match allowed_tool.as_str() {
                     "fs_read" | "fs_write" => Some("allowedPaths"),
                     "use_aws" => Some("allowedServices"),
-                    name if name == execute_name => Some("allowedCommands"),
+                    "cloudwatch_logs" => Some("allowedLogGroups"),
+                    name if name == EXECUTE_TOOL_NAME || (cfg!(windows) && name == "execute_cmd") => {
+                        Some("allowedCommands")
+                    },
                     _ => None,
                 };
 
@@ -393,6 +419,10 @@ pub struct Agents {
     /// Agent name.
     pub active_idx: String,
     pub trust_all_tools: bool,
+    /// The `.amazonq/config.toml` of the workspace, if it has one.
+    pub workspace_config: Option<WorkspaceConfig>,
+    /// The policy installed by an administrator, which no agent can loosen.
+    pub managed_policy: Option<ManagedPolicy>,
 }
 
 impl Agents {
@@ -414,6 +444,16 @@ impl Agents {
         }
     }
 
+    /// Adds the context, MCP servers, trusted tools and model of the workspace config to every
+    /// agent. This is only done for chat sessions, so that they are never saved into agent configs.
+    pub fn apply_workspace_config(&mut self, mcp_enabled: bool) {
+        if let Some(config) = &self.workspace_config {
+            for agent in self.agents.values_mut() {
+                config.apply(agent, mcp_enabled);
+            }
+        }
+    }
+
     pub fn get_active(&self) -> Option<&Agent> {
         self.agents.get(&self.active_idx)
     }
@@ -643,11 +683,30 @@ impl Agents {
         local_agents.append(&mut global_agents);
         let mut all_agents = local_agents;
 
+        // Only a config the user trusted is used, see [WorkspaceConfig::confirm_trust].
+        let workspace_config = match WorkspaceConfig::load(os).await {
+            Ok(Some(config)) if config.is_trusted(os).await => Some(config),
+            Ok(_) => None,
+            Err(e) => {
+                let _ = queue!(
+                    output,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print("Error: "),
+                    style::ResetColor,
+                    style::Print(e),
+                    style::Print("\n"),
+                );
+                None
+            },
+        };
+
         // Assume agent in the following order of priority:
         // 1. The agent name specified by the start command via --agent (this is the agent_name that's
         //    passed in)
-        // 2. If the above is missing or invalid, assume one that is specified by chat.defaultAgent
-        // 3. If the above is missing or invalid, assume the in-memory default
+        // 2. If the above is missing or invalid, assume one that is specified by the defaultAgent of the
+        //    workspace config
+        // 3. If the above is missing or invalid, assume one that is specified by chat.defaultAgent
+        // 4. If the above is missing or invalid, assume the in-memory default
         let active_idx = 'active_idx: {
             if let Some(name) = agent_name {
                 if all_agents.iter().any(|a| a.name.as_str() == name) {
@@ -667,6 +726,24 @@ impl Agents {
                 );
             }
 
+            if let Some(workspace_default) = workspace_config.as_ref().and_then(|c| c.default_agent.as_ref()) {
+                if all_agents.iter().any(|a| &a.name == workspace_default) {
+                    break 'active_idx workspace_default.clone();
+                }
+                let _ = queue!(
+                    output,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print("Error"),
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(format!(
+                        ": workspace default {} not found. Falling back to user specified default",
+                        workspace_default
+                    )),
+                    style::Print("\n"),
+                    style::SetForegroundColor(Color::Reset)
+                );
+            }
+
             if let Some(user_set_default) = os.database.settings.get_string(Setting::ChatDefaultAgent) {
                 if all_agents.iter().any(|a| a.name == user_set_default) {
                     break 'active_idx user_set_default;
@@ -768,6 +845,7 @@ impl Agents {
             Self {
                 agents,
                 active_idx,
+                workspace_config,
                 ..Default::default()
             },
             load_metadata,
@@ -802,12 +880,18 @@ impl Agents {
             #[cfg(not(windows))]
             "execute_bash" => "not trusted".dark_grey(),
             #[cfg(windows)]
-            "execute_cmd" => "not trusted".dark_grey(),
+            "execute_powershell" | "execute_cmd" => "not trusted".dark_grey(),
             "use_aws" => "trust read-only commands".dark_grey(),
-            "report_issue" => "trusted".dark_green().bold(),
+            "cloudwatch_logs" => "not trusted".dark_grey(),
+            "git" => "trust read-only commands".dark_grey(),
+            "issue_tracker" => "trusted".dark_green().bold(),
             "introspect" => "trusted".dark_green().bold(),
             "thinking" => "trusted (prerelease)".dark_green().bold(),
             "todo_list" => "trusted".dark_green().bold(),
+            "update_plan" => "trusted".dark_green().bold(),
+            "dependency_report" => "trusted".dark_green().bold(),
+            "retrieve_output" => "trusted".dark_green().bold(),
+            "semantic_search" => "trusted".dark_green().bold(),
             _ if self.trust_all_tools => "trusted".dark_grey().bold(),
             _ => "not trusted".dark_grey(),
         };
@@ -0,0 +1,288 @@
+//! Messages exchanged over the control channel of a running chat session (see [super::ipc]).
+//!
+//! Every message is a JSON object sent as a length-delimited frame. A connection starts with the
+//! client sending [Request::Hello] with the protocol version and the capabilities it wants to
+//! use, to which the session answers with [Response::Hello] listing the capabilities both sides
+//! support. Every following request gets exactly one response, or [Response::Error], except
+//! for [Request::Subscribe]: once it is accepted, the session only sends [Response::Event] on
+//! that connection until it is closed.
+//!
+//! See `docs/ide-bridge.md` for how IDE extensions use the protocol.
+
+use std::path::PathBuf;
+
+use bytes::Bytes;
+use futures::{
+    SinkExt,
+    StreamExt,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio_util::codec::{
+    Framed,
+    LengthDelimitedCodec,
+};
+
+use super::ipc::ControlStream;
+
+/// Version of the protocol implemented by this build.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Upper bound on the size of a single message.
+const MAX_FRAME_LENGTH: usize = 1024 * 1024;
+
+/// Optional parts of the protocol, negotiated when connecting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Capability {
+    /// [Request::Status]
+    Status,
+    /// [Request::Prompt]
+    Prompt,
+    /// [Request::Prompt] with `wait` set, answered with [Response::Completed].
+    PromptResult,
+    /// [Request::Shutdown]
+    Shutdown,
+    /// [Request::AddContext]
+    Context,
+    /// [Request::Transcript]
+    Transcript,
+    /// [Request::Subscribe]
+    Events,
+}
+
+impl Capability {
+    /// The capabilities supported by this build.
+    pub const ALL: &[Capability] = &[
+        Capability::Status,
+        Capability::Prompt,
+        Capability::PromptResult,
+        Capability::Shutdown,
+        Capability::Context,
+        Capability::Transcript,
+        Capability::Events,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Request {
+    /// Must be the first request of a connection.
+    Hello {
+        version: u32,
+        capabilities: Vec<Capability>,
+    },
+    /// Describe the session.
+    Status,
+    /// Submit a prompt as if it was typed by the user. The session picks it up the next time it
+    /// waits for input.
+    Prompt {
+        text: String,
+        /// Answer with [Response::Completed] once the turn finished, rather than with
+        /// [Response::Accepted] once the prompt was queued.
+        #[serde(default)]
+        wait: bool,
+    },
+    /// Exit the session as if it received SIGTERM, saving the conversation. Answered with
+    /// [Response::Accepted].
+    Shutdown,
+    /// Attach a selection, e.g. from an editor, to the next prompt of the session. Answered with
+    /// [Response::Accepted].
+    AddContext { selection: Selection },
+    /// The conversation as of the last time the session waited for input. Answered with
+    /// [Response::Transcript].
+    Transcript,
+    /// Receive the [SessionEvent]s of the session on this connection. Answered with
+    /// [Response::Accepted], after which no more requests are read.
+    Subscribe,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Response {
+    Hello {
+        version: u32,
+        capabilities: Vec<Capability>,
+    },
+    Status(SessionStatus),
+    /// The prompt was queued, or the shutdown started.
+    Accepted,
+    /// The turn started by the prompt finished.
+    Completed {
+        /// The final response of the assistant.
+        response: String,
+    },
+    Transcript {
+        /// The prompts, prefixed with `> `, and the responses of the conversation, oldest first.
+        messages: Vec<String>,
+    },
+    /// Sent on a connection after [Request::Subscribe].
+    Event(SessionEvent),
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+}
+
+impl Response {
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::Error {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    /// The message could not be parsed.
+    InvalidRequest,
+    /// The protocol version of the client is not supported.
+    UnsupportedVersion,
+    /// The request needs a capability that was not negotiated.
+    Unsupported,
+    /// The session is exiting.
+    Unavailable,
+    /// The turn started by the prompt failed.
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionStatus {
+    pub pid: u32,
+    pub agent: String,
+    pub conversation_id: String,
+    pub cwd: PathBuf,
+}
+
+/// Text sent from an editor to be attached to the next prompt.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Selection {
+    pub path: PathBuf,
+    /// The first line of the selection, starting at 1.
+    #[serde(default)]
+    pub start_line: Option<usize>,
+    /// The last line of the selection, inclusive.
+    #[serde(default)]
+    pub end_line: Option<usize>,
+    pub text: String,
+}
+
+/// Something that happened in the session, sent to subscribed connections.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum SessionEvent {
+    /// A file was written by `fs_write`.
+    #[serde(rename_all = "camelCase")]
+    EditApplied { path: PathBuf, tool_use_id: String },
+}
+
+/// A control channel connection that sends and receives whole messages.
+pub type Connection<S> = Framed<S, LengthDelimitedCodec>;
+
+pub fn framed<S: ControlStream>(stream: S) -> Connection<S> {
+    LengthDelimitedCodec::builder()
+        .max_frame_length(MAX_FRAME_LENGTH)
+        .new_framed(stream)
+}
+
+/// Sends a message.
+pub async fn write<S: ControlStream>(connection: &mut Connection<S>, message: &impl Serialize) -> eyre::Result<()> {
+    connection.send(Bytes::from(serde_json::to_vec(message)?)).await?;
+    Ok(())
+}
+
+/// Receives a message, or `None` if the other side closed the connection.
+pub async fn read<S: ControlStream, T: for<'de> Deserialize<'de>>(
+    connection: &mut Connection<S>,
+) -> eyre::Result<Option<T>> {
+    match connection.next().await {
+        Some(frame) => Ok(Some(serde_json::from_slice(&frame?)?)),
+        None => Ok(None),
+    }
+}
+
+/// The capabilities of `offered` that this build supports.
+pub fn negotiate(offered: &[Capability]) -> Vec<Capability> {
+    offered
+        .iter()
+        .copied()
+        .filter(|capability| Capability::ALL.contains(capability))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialization() {
+        assert_eq!(
+            serde_json::to_value(Request::Prompt {
+                text: "hello".to_string(),
+                wait: true
+            })
+            .unwrap(),
+            serde_json::json!({ "type": "prompt", "text": "hello", "wait": true })
+        );
+        assert_eq!(
+            serde_json::from_value::<Request>(serde_json::json!({ "type": "prompt", "text": "hello" })).unwrap(),
+            Request::Prompt {
+                text: "hello".to_string(),
+                wait: false
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(Request::Shutdown).unwrap(),
+            serde_json::json!({ "type": "shutdown" })
+        );
+        assert_eq!(
+            serde_json::from_value::<Request>(serde_json::json!({
+                "type": "addContext",
+                "selection": { "path": "src/main.rs", "startLine": 3, "text": "fn main() {}" }
+            }))
+            .unwrap(),
+            Request::AddContext {
+                selection: Selection {
+                    path: PathBuf::from("src/main.rs"),
+                    start_line: Some(3),
+                    end_line: None,
+                    text: "fn main() {}".to_string(),
+                }
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(Response::Event(SessionEvent::EditApplied {
+                path: PathBuf::from("src/main.rs"),
+                tool_use_id: "tooluse_1".to_string(),
+            }))
+            .unwrap(),
+            serde_json::json!({ "type": "event", "event": "editApplied", "path": "src/main.rs", "toolUseId": "tooluse_1" })
+        );
+        assert_eq!(
+            serde_json::to_value(Response::error(ErrorCode::Unsupported, "nope")).unwrap(),
+            serde_json::json!({ "type": "error", "code": "unsupported", "message": "nope" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_framing() {
+        let (client, server) = tokio::io::duplex(64);
+        let (mut client, mut server) = (framed(client), framed(server));
+
+        let request = Request::Hello {
+            version: PROTOCOL_VERSION,
+            capabilities: vec![Capability::Prompt],
+        };
+        write(&mut client, &request).await.unwrap();
+        assert_eq!(read::<_, Request>(&mut server).await.unwrap(), Some(request));
+
+        drop(client);
+        assert_eq!(read::<_, Request>(&mut server).await.unwrap(), None);
+    }
+}
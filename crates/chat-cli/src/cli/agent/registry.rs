@@ -0,0 +1,559 @@
+//! Registry of the chat sessions running on this machine.
+//!
+//! Each session writes its own entry under [agent_registry_dir] on startup, refreshes its heartbeat
+//! while running, and removes the entry on exit. Entries whose heartbeat stopped, e.g. because the
+//! process was killed, are removed the next time the registry is read. The child processes a
+//! session recorded in its entry, like MCP servers, are killed at that point if they outlived it,
+//! and the temporary directories of sessions that are no longer running are removed along with
+//! them.
+//!
+//! Registered sessions also listen on a control channel (see [super::ipc]) so that other processes
+//! can reach them, e.g. with `q agent send`.
+
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use eyre::Result;
+use parking_lot::Mutex;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sysinfo::{
+    Pid,
+    ProcessesToUpdate,
+    System,
+};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{
+    debug,
+    warn,
+};
+
+use super::bridge::IdeBridge;
+use super::ipc::{
+    self,
+    ControlListener,
+    IncomingPrompt,
+    PlatformListener,
+};
+use crate::util::directories::{
+    agent_registry_dir,
+    session_temp_root,
+};
+use crate::util::shutdown;
+
+/// How often a running session refreshes its entry.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often a running session checks whether its child processes changed.
+const CHILDREN_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Entries without a heartbeat for this long belong to sessions that exited without deregistering.
+const STALE_AFTER: Duration = Duration::from_secs(90);
+
+/// A chat session registered as running.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegistryEntry {
+    pub pid: u32,
+    pub agent: String,
+    pub conversation_id: String,
+    pub cwd: PathBuf,
+    pub started_at: DateTime<Utc>,
+    /// Start time of the process in seconds since the epoch, to tell it apart from a later one
+    /// reusing its pid. Not recorded by older versions.
+    #[serde(default)]
+    pub process_start_time: Option<u64>,
+    pub heartbeat_at: DateTime<Utc>,
+    /// Child processes to kill if the session dies without cleaning them up.
+    #[serde(default)]
+    pub children: Vec<ChildProcess>,
+}
+
+impl RegistryEntry {
+    /// Whether the process that registered this entry is still running, rather than another
+    /// process that reused its pid.
+    fn is_running(&self, processes: &HashMap<u32, u64>) -> bool {
+        processes
+            .get(&self.pid)
+            .is_some_and(|start_time| match self.process_start_time {
+                Some(process_start_time) => *start_time == process_start_time,
+                // Without the start time, a process that started after the entry was written can
+                // still be told apart.
+                None => *start_time <= self.started_at.timestamp().max(0) as u64,
+            })
+    }
+
+    fn is_stale(&self, now: DateTime<Utc>, processes: &HashMap<u32, u64>) -> bool {
+        let since_heartbeat = (now - self.heartbeat_at).to_std().unwrap_or_default();
+        since_heartbeat > STALE_AFTER || !self.is_running(processes)
+    }
+}
+
+/// A child process of a registered session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildProcess {
+    pub pid: u32,
+    pub name: String,
+    /// Start time in seconds since the epoch, to tell the process apart from a later one reusing
+    /// its pid.
+    pub start_time: u64,
+}
+
+/// What [collect_garbage] cleaned up.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Reclaimed {
+    /// Child processes that outlived their session.
+    pub processes: Vec<ChildProcess>,
+    /// Control sockets of sessions that are no longer running.
+    pub sockets: usize,
+    /// Temporary directories of sessions that are no longer running.
+    pub temp_dirs: usize,
+}
+
+impl Reclaimed {
+    pub fn is_empty(&self) -> bool {
+        self.processes.is_empty() && self.sockets == 0 && self.temp_dirs == 0
+    }
+}
+
+/// The registration of the current process, removed from the registry when dropped.
+#[derive(Debug)]
+pub struct AgentRegistration {
+    path: PathBuf,
+    entry: Arc<Mutex<RegistryEntry>>,
+    heartbeat: JoinHandle<()>,
+    bridge: Arc<IdeBridge>,
+    /// Receives the prompts sent over the control channel, if it could be opened.
+    control: Option<(JoinHandle<()>, mpsc::UnboundedReceiver<IncomingPrompt>)>,
+}
+
+impl AgentRegistration {
+    /// Registers the current process as running `agent`. Must be called from within a tokio
+    /// runtime.
+    pub fn register(agent: &str, conversation_id: &str) -> Result<Self> {
+        let mut registration = Self::register_in(&agent_registry_dir()?, agent, conversation_id)?;
+        match PlatformListener::bind(std::process::id()) {
+            Ok(listener) => {
+                let (tx, rx) = mpsc::unbounded_channel();
+                let server = tokio::spawn(ipc::serve(
+                    listener,
+                    Arc::clone(&registration.entry),
+                    Arc::clone(&registration.bridge),
+                    tx,
+                ));
+                registration.control = Some((server, rx));
+            },
+            Err(err) => warn!(?err, "failed to open the agent control channel"),
+        }
+        Ok(registration)
+    }
+
+    fn register_in(dir: &Path, agent: &str, conversation_id: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let now = Utc::now();
+        let pid = std::process::id();
+        let path = dir.join(format!("{pid}.json"));
+        let entry = RegistryEntry {
+            pid,
+            agent: agent.to_string(),
+            conversation_id: conversation_id.to_string(),
+            cwd: std::env::current_dir().unwrap_or_default(),
+            started_at: now,
+            process_start_time: process_start_times([pid]).get(&pid).copied(),
+            heartbeat_at: now,
+            children: current_children(),
+        };
+        write_entry(&path, &entry)?;
+        shutdown::register_path(&path);
+
+        let entry = Arc::new(Mutex::new(entry));
+        let heartbeat = tokio::spawn({
+            let path = path.clone();
+            let entry = Arc::clone(&entry);
+            async move {
+                let mut interval = tokio::time::interval(CHILDREN_POLL_INTERVAL);
+                interval.tick().await;
+                loop {
+                    interval.tick().await;
+                    let children = current_children();
+                    let entry = {
+                        let mut entry = entry.lock();
+                        let now = Utc::now();
+                        let since_heartbeat = (now - entry.heartbeat_at).to_std().unwrap_or_default();
+                        if entry.children == children && since_heartbeat < HEARTBEAT_INTERVAL {
+                            continue;
+                        }
+                        entry.children = children;
+                        entry.heartbeat_at = now;
+                        entry.clone()
+                    };
+                    if let Err(err) = write_entry(&path, &entry) {
+                        warn!(?err, "failed to refresh the agent registry heartbeat");
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            path,
+            entry,
+            heartbeat,
+            bridge: Arc::default(),
+            control: None,
+        })
+    }
+
+    /// Returns the next prompt received over the control channel, if any.
+    pub fn next_prompt(&mut self) -> Option<IncomingPrompt> {
+        self.control.as_mut().and_then(|(_, prompts)| prompts.try_recv().ok())
+    }
+
+    /// What is shared with IDE extensions connected to the control channel.
+    pub fn bridge(&self) -> &IdeBridge {
+        &self.bridge
+    }
+
+    /// Updates the working directory of the registered session after `/cd`.
+    pub fn set_cwd(&self, cwd: &Path) {
+        let entry = {
+            let mut entry = self.entry.lock();
+            entry.cwd = cwd.to_path_buf();
+            entry.clone()
+        };
+        if let Err(err) = write_entry(&self.path, &entry) {
+            warn!(?err, "failed to update the agent registry");
+        }
+    }
+
+    /// Updates the agent of the registered session, e.g. after `/agent swap`.
+    pub fn set_agent(&self, agent: &str) {
+        let entry = {
+            let mut entry = self.entry.lock();
+            if entry.agent == agent {
+                return;
+            }
+            entry.agent = agent.to_string();
+            entry.clone()
+        };
+        if let Err(err) = write_entry(&self.path, &entry) {
+            warn!(?err, "failed to update the agent registry");
+        }
+    }
+}
+
+impl Drop for AgentRegistration {
+    fn drop(&mut self) {
+        self.heartbeat.abort();
+        if let Some((server, _)) = self.control.take() {
+            server.abort();
+        }
+        if let Err(err) = std::fs::remove_file(&self.path) {
+            debug!(?err, "failed to deregister from the agent registry");
+        }
+        shutdown::unregister_path(&self.path);
+    }
+}
+
+/// Returns the running chat sessions, oldest first.
+pub fn running_agents() -> Result<Vec<RegistryEntry>> {
+    Ok(scan(&agent_registry_dir()?)?.0)
+}
+
+/// Removes what sessions that didn't exit cleanly left behind: their registry entries, their
+/// control sockets, their temporary directories, and child processes like MCP servers that are
+/// still running.
+pub fn collect_garbage() -> Result<Reclaimed> {
+    let mut reclaimed = scan(&agent_registry_dir()?)?.1;
+    if let Ok(root) = session_temp_root() {
+        reclaimed.temp_dirs = remove_stale_temp_dirs(&root);
+    }
+    Ok(reclaimed)
+}
+
+/// Removes the temporary directories of sessions that are no longer running, returning how many
+/// were removed. Directories are named after the pid of their session and when they were created.
+fn remove_stale_temp_dirs(root: &Path) -> usize {
+    let Ok(read_dir) = std::fs::read_dir(root) else {
+        return 0;
+    };
+    let dirs = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name();
+            let (pid, created_at) = name.to_str()?.split_once('-')?;
+            Some((entry.path(), pid.parse::<u32>().ok()?, created_at.parse::<u64>().ok()?))
+        })
+        .collect::<Vec<_>>();
+
+    let processes = process_start_times(dirs.iter().map(|(_, pid, _)| *pid));
+    dirs.into_iter()
+        .filter(|(path, pid, created_at)| {
+            // A process that started after the directory was created only reused its pid.
+            let running = processes.get(pid).is_some_and(|start_time| start_time <= created_at);
+            !running && std::fs::remove_dir_all(path).is_ok()
+        })
+        .inspect(|(path, ..)| debug!(?path, "removed stale session temp dir"))
+        .count()
+}
+
+/// Reads the registry, cleaning up after the sessions that are no longer running.
+fn scan(dir: &Path) -> Result<(Vec<RegistryEntry>, Reclaimed)> {
+    let read_dir = match std::fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), Reclaimed::default())),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut parsed = Vec::new();
+    let mut sockets = Vec::new();
+    for file in read_dir.flatten() {
+        let path = file.path();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let entry = std::fs::read(&path)
+                    .ok()
+                    .and_then(|bytes| serde_json::from_slice::<RegistryEntry>(&bytes).ok());
+                parsed.push((path, entry));
+            },
+            Some("sock") => {
+                if let Some(pid) = path.file_stem().and_then(|stem| stem.to_str()?.parse::<u32>().ok()) {
+                    sockets.push((path, pid));
+                }
+            },
+            _ => {},
+        }
+    }
+
+    let pids = parsed
+        .iter()
+        .filter_map(|(_, entry)| entry.as_ref())
+        .flat_map(|entry| std::iter::once(entry.pid).chain(entry.children.iter().map(|child| child.pid)))
+        .chain(sockets.iter().map(|(_, pid)| *pid));
+    let processes = process_start_times(pids);
+
+    let now = Utc::now();
+    let mut entries = Vec::new();
+    let mut reclaimed = Reclaimed::default();
+    for (path, entry) in parsed {
+        match entry {
+            Some(entry) if !entry.is_stale(now, &processes) => entries.push(entry),
+            entry => {
+                debug!(?path, "removing stale agent registry entry");
+                // A session that is only slow to refresh its heartbeat still owns its children.
+                if let Some(entry) = entry.filter(|entry| !entry.is_running(&processes)) {
+                    for child in entry.children {
+                        if processes.get(&child.pid) == Some(&child.start_time) {
+                            debug!(pid = child.pid, name = child.name, "killing orphaned process");
+                            shutdown::kill_process(child.pid);
+                            reclaimed.processes.push(child);
+                        }
+                    }
+                }
+                std::fs::remove_file(&path).ok();
+            },
+        }
+    }
+
+    for (path, pid) in sockets {
+        if !processes.contains_key(&pid) && std::fs::remove_file(&path).is_ok() {
+            debug!(?path, "removed stale control socket");
+            reclaimed.sockets += 1;
+        }
+    }
+
+    entries.sort_by_key(|entry| entry.started_at);
+    Ok((entries, reclaimed))
+}
+
+/// Writes `entry` through a temporary file so readers never see a partial entry.
+fn write_entry(path: &Path, entry: &RegistryEntry) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(entry)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+pub fn is_process_running(pid: u32) -> bool {
+    process_start_times([pid]).contains_key(&pid)
+}
+
+/// Returns the start times of those of the given pids that belong to running processes.
+fn process_start_times(pids: impl IntoIterator<Item = u32>) -> HashMap<u32, u64> {
+    let pids = pids.into_iter().map(Pid::from_u32).collect::<Vec<_>>();
+    if pids.is_empty() {
+        return HashMap::new();
+    }
+
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&pids), true);
+    system
+        .processes()
+        .iter()
+        .map(|(pid, process)| (pid.as_u32(), process.start_time()))
+        .collect()
+}
+
+/// The child processes of the current process registered for cleanup on shutdown.
+fn current_children() -> Vec<ChildProcess> {
+    let children = shutdown::children();
+    let start_times = process_start_times(children.iter().map(|(pid, _)| *pid));
+    let mut children = children
+        .into_iter()
+        .filter_map(|(pid, name)| {
+            start_times.get(&pid).map(|start_time| ChildProcess {
+                pid,
+                name,
+                start_time: *start_time,
+            })
+        })
+        .collect::<Vec<_>>();
+    children.sort_by_key(|child| child.pid);
+    children
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_and_list() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let registration = AgentRegistration::register_in(dir.path(), "dev", "conversation").unwrap();
+        let running = scan(dir.path()).unwrap().0;
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].pid, std::process::id());
+        assert_eq!(running[0].agent, "dev");
+
+        registration.set_agent("reviewer");
+        assert_eq!(scan(dir.path()).unwrap().0[0].agent, "reviewer");
+
+        drop(registration);
+        assert!(scan(dir.path()).unwrap().0.is_empty());
+    }
+
+    #[test]
+    fn test_stale_entries_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let stale = RegistryEntry {
+            pid: std::process::id(),
+            agent: "dev".to_string(),
+            conversation_id: "conversation".to_string(),
+            cwd: PathBuf::new(),
+            started_at: Utc::now() - chrono::Duration::hours(1),
+            process_start_time: None,
+            heartbeat_at: Utc::now() - chrono::Duration::hours(1),
+            children: Vec::new(),
+        };
+        let path = dir.path().join("1.json");
+        write_entry(&path, &stale).unwrap();
+        std::fs::write(dir.path().join("2.json"), "not json").unwrap();
+
+        assert!(scan(dir.path()).unwrap().0.is_empty());
+        assert!(!path.exists());
+        assert!(!dir.path().join("2.json").exists());
+    }
+
+    #[test]
+    fn test_is_running() {
+        let pid = std::process::id();
+        let start_time = process_start_times([pid])[&pid];
+        let processes = HashMap::from([(pid, start_time)]);
+        let entry = |process_start_time| RegistryEntry {
+            pid,
+            agent: "dev".to_string(),
+            conversation_id: "conversation".to_string(),
+            cwd: PathBuf::new(),
+            started_at: Utc::now(),
+            process_start_time,
+            heartbeat_at: Utc::now(),
+            children: Vec::new(),
+        };
+
+        assert!(entry(Some(start_time)).is_running(&processes));
+        assert!(entry(None).is_running(&processes));
+        // The pid was reused by a process other than the one that registered.
+        assert!(!entry(Some(start_time - 1)).is_running(&processes));
+        assert!(!entry(Some(start_time)).is_running(&HashMap::new()));
+    }
+
+    #[test]
+    fn test_process_start_times() {
+        let pid = std::process::id();
+        assert!(process_start_times([pid]).contains_key(&pid));
+        assert!(process_start_times(std::iter::empty()).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_orphaned_children() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut orphan = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let child = ChildProcess {
+            pid: orphan.id(),
+            name: "MCP server test".to_string(),
+            start_time: process_start_times([orphan.id()])[&orphan.id()],
+        };
+        // Belongs to a process that no longer exists.
+        let entry = RegistryEntry {
+            pid: u32::MAX,
+            agent: "dev".to_string(),
+            conversation_id: "conversation".to_string(),
+            cwd: PathBuf::new(),
+            started_at: Utc::now(),
+            process_start_time: None,
+            heartbeat_at: Utc::now(),
+            children: vec![child.clone()],
+        };
+        write_entry(&dir.path().join(format!("{}.json", u32::MAX)), &entry).unwrap();
+        std::fs::write(dir.path().join(format!("{}.sock", u32::MAX)), "").unwrap();
+
+        let (running, reclaimed) = scan(dir.path()).unwrap();
+        assert!(running.is_empty());
+        assert_eq!(reclaimed, Reclaimed {
+            processes: vec![child],
+            sockets: 1,
+            temp_dirs: 0,
+        });
+        assert!(!orphan.wait().unwrap().success());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_remove_stale_temp_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let now = Utc::now().timestamp() as u64;
+        let running = root.path().join(format!("{}-{now}", std::process::id()));
+        let stale = root.path().join(format!("{}-{now}", u32::MAX));
+        let unrelated = root.path().join("unrelated");
+        for dir in [&running, &stale, &unrelated] {
+            std::fs::create_dir(dir).unwrap();
+        }
+
+        assert_eq!(remove_stale_temp_dirs(root.path()), 1);
+        assert!(running.exists());
+        assert!(!stale.exists());
+        assert!(unrelated.exists());
+        assert_eq!(remove_stale_temp_dirs(&root.path().join("missing")), 0);
+    }
+
+    #[test]
+    fn test_missing_registry_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(scan(&dir.path().join("missing")).unwrap().0.is_empty());
+    }
+}
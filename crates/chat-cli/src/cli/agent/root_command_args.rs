@@ -21,11 +21,17 @@ use super::{
     Agent,
     Agents,
     McpServerConfig,
+    broadcast,
+    compare,
+    ipc,
     legacy,
+    protocol,
+    registry,
 };
 use crate::database::settings::Setting;
 use crate::os::Os;
 use crate::util::directories;
+use crate::util::time::humanize;
 
 #[derive(Clone, Debug, Subcommand, PartialEq, Eq)]
 pub enum AgentSubcommands {
@@ -69,6 +75,35 @@ pub enum AgentSubcommands {
         #[arg(long, short)]
         name: String,
     },
+    /// Send a prompt to a running chat session. The session picks it up the next time it waits
+    /// for input
+    Send {
+        /// Pid or agent name of the running session, as shown by `q agent list`. Omitted with
+        /// --all, in which case every word is part of the prompt
+        #[arg(required_unless_present = "all")]
+        target: Option<String>,
+        /// Send to every running session, or to those matching --agent and --cwd
+        #[arg(long)]
+        all: bool,
+        /// With --all, only send to sessions using this agent. Can be repeated
+        #[arg(long, requires = "all")]
+        agent: Vec<String>,
+        /// With --all, only send to sessions working in this directory or below it
+        #[arg(long, requires = "all")]
+        cwd: Option<PathBuf>,
+        /// Ask the sessions to exit instead of sending a prompt
+        #[arg(long, conflicts_with = "wait")]
+        shutdown: bool,
+        /// Wait for the session to finish responding and print its final response
+        #[arg(long)]
+        wait: bool,
+        /// The prompt to send
+        #[arg(num_args = 1..)]
+        prompt: Vec<String>,
+    },
+    /// Run the same task with several models, each in its own git worktree, and compare what
+    /// they changed
+    Compare(compare::CompareArgs),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Args)]
@@ -112,6 +147,25 @@ impl AgentArgs {
                     .join("\n");
 
                 writeln!(stderr, "{}", output_str)?;
+
+                match registry::running_agents() {
+                    Ok(running) if !running.is_empty() => {
+                        writeln!(stderr, "\nRunning sessions:")?;
+                        for entry in running {
+                            writeln!(
+                                stderr,
+                                "{:<width$}    pid {:<8}    {}    started {}",
+                                entry.agent,
+                                entry.pid,
+                                entry.cwd.display(),
+                                humanize(&entry.started_at),
+                                width = max_name_length
+                            )?;
+                        }
+                    },
+                    Ok(_) => {},
+                    Err(err) => tracing::warn!(?err, "Failed to read the agent registry"),
+                }
             },
             Some(AgentSubcommands::Create { name, directory, from }) => {
                 let mut agents = Agents::load(os, None, true, &mut stderr, mcp_enabled).await.0;
@@ -324,6 +378,97 @@ impl AgentArgs {
                     },
                 }
             },
+            Some(AgentSubcommands::Send {
+                target,
+                all: true,
+                agent,
+                cwd,
+                shutdown,
+                wait,
+                prompt,
+            }) => {
+                let cwd = cwd.map(|cwd| cwd.canonicalize().unwrap_or(cwd));
+                let targets = registry::running_agents()?
+                    .into_iter()
+                    .filter(|entry| agent.is_empty() || agent.contains(&entry.agent))
+                    .filter(|entry| cwd.as_ref().is_none_or(|cwd| entry.cwd.starts_with(cwd)))
+                    .collect::<Vec<_>>();
+                if targets.is_empty() {
+                    bail!("No running session matches");
+                }
+
+                let message = match shutdown {
+                    true => broadcast::Message::Shutdown,
+                    false => {
+                        // The first word of the prompt is parsed as the target.
+                        let prompt = target.into_iter().chain(prompt).collect::<Vec<_>>().join(" ");
+                        if prompt.trim().is_empty() {
+                            bail!("A prompt is required unless --shutdown is given");
+                        }
+                        broadcast::Message::Prompt { text: prompt, wait }
+                    },
+                };
+                let results = broadcast::send_all(&targets, &message).await;
+                broadcast::print_results(&mut stderr, &targets, &results)?;
+                if results.iter().any(Result::is_err) {
+                    return Ok(ExitCode::FAILURE);
+                }
+            },
+            Some(AgentSubcommands::Send {
+                target: Some(target),
+                shutdown,
+                wait,
+                prompt,
+                ..
+            }) => {
+                let running = registry::running_agents()?;
+                let pid = match target.parse::<u32>() {
+                    Ok(pid) => pid,
+                    Err(_) => {
+                        let matches = running.iter().filter(|entry| entry.agent == target).collect::<Vec<_>>();
+                        match matches.as_slice() {
+                            [entry] => entry.pid,
+                            [] => bail!("No running session uses the agent '{target}'"),
+                            _ => bail!("Several running sessions use the agent '{target}', specify a pid instead"),
+                        }
+                    },
+                };
+                if !running.iter().any(|entry| entry.pid == pid) {
+                    bail!("No running session with pid {pid}");
+                }
+
+                let mut client = ipc::ControlClient::connect(pid).await?;
+                if shutdown {
+                    if !client.supports(protocol::Capability::Shutdown) {
+                        bail!("The session with pid {pid} does not support being shut down");
+                    }
+                    client.shutdown().await?;
+                    queue!(
+                        stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("✓ The session with pid {pid} is shutting down\n")),
+                        style::ResetColor,
+                    )?;
+                    return Ok(ExitCode::SUCCESS);
+                }
+                if prompt.is_empty() {
+                    bail!("A prompt is required unless --shutdown is given");
+                }
+                if wait && !client.supports(protocol::Capability::PromptResult) {
+                    bail!("The session with pid {pid} does not support waiting for a response");
+                }
+                match client.prompt(prompt.join(" "), wait).await? {
+                    Some(response) => println!("{response}"),
+                    None => queue!(
+                        stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(format!("✓ Sent to the session with pid {pid}\n")),
+                        style::ResetColor,
+                    )?,
+                }
+            },
+            Some(AgentSubcommands::Send { target: None, .. }) => bail!("A target is required unless --all is given"),
+            Some(AgentSubcommands::Compare(args)) => return args.execute(os).await,
         }
 
         Ok(ExitCode::SUCCESS)
@@ -425,6 +570,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_agent_subcommand_send() {
+        assert_parse!(
+            ["agent", "send", "1234", "run", "the tests"],
+            RootSubcommand::Agent(AgentArgs {
+                cmd: Some(AgentSubcommands::Send {
+                    target: Some("1234".to_string()),
+                    all: false,
+                    agent: Vec::new(),
+                    cwd: None,
+                    shutdown: false,
+                    wait: false,
+                    prompt: vec!["run".to_string(), "the tests".to_string()],
+                })
+            })
+        );
+        assert_parse!(
+            [
+                "agent",
+                "send",
+                "--all",
+                "--agent",
+                "reviewer",
+                "summarize",
+                "your progress"
+            ],
+            RootSubcommand::Agent(AgentArgs {
+                cmd: Some(AgentSubcommands::Send {
+                    target: Some("summarize".to_string()),
+                    all: true,
+                    agent: vec!["reviewer".to_string()],
+                    cwd: None,
+                    shutdown: false,
+                    wait: false,
+                    prompt: vec!["your progress".to_string()],
+                })
+            })
+        );
+    }
+
     #[test]
     fn test_agent_subcommand_edit() {
         assert_parse!(
@@ -444,4 +629,53 @@ mod tests {
             })
         );
     }
+
+    #[test]
+    fn test_agent_subcommand_compare() {
+        assert_parse!(
+            [
+                "agent",
+                "compare",
+                "--models",
+                "claude-sonnet-4,claude-3.7-sonnet",
+                "--test",
+                "cargo test",
+                "--pick",
+                "2",
+                "--trust-all-tools",
+                "fix",
+                "the",
+                "bug"
+            ],
+            RootSubcommand::Agent(AgentArgs {
+                cmd: Some(AgentSubcommands::Compare(compare::CompareArgs {
+                    cmd: None,
+                    models: vec!["claude-sonnet-4".to_string(), "claude-3.7-sonnet".to_string()],
+                    agent: None,
+                    test: Some("cargo test".to_string()),
+                    pick: Some(2),
+                    keep: false,
+                    trust_all_tools: true,
+                    task: vec!["fix".to_string(), "the".to_string(), "bug".to_string()],
+                }))
+            })
+        );
+        assert_parse!(
+            ["agent", "compare", "attach", "claude-sonnet-4"],
+            RootSubcommand::Agent(AgentArgs {
+                cmd: Some(AgentSubcommands::Compare(compare::CompareArgs {
+                    cmd: Some(compare::CompareSubcommand::Attach {
+                        run: "claude-sonnet-4".to_string()
+                    }),
+                    models: Vec::new(),
+                    agent: None,
+                    test: None,
+                    pick: None,
+                    keep: false,
+                    trust_all_tools: false,
+                    task: Vec::new(),
+                }))
+            })
+        );
+    }
 }
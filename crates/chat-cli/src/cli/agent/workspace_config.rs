@@ -0,0 +1,336 @@
+use std::collections::{
+    BTreeMap,
+    HashSet,
+};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use super::{
+    Agent,
+    AgentConfigError,
+};
+use crate::cli::chat::tools::custom_tool::CustomToolConfig;
+use crate::os::Os;
+use crate::util::consts::MCP_SERVER_TOOL_DELIMITER;
+use crate::util::{
+    dialoguer_theme,
+    directories,
+};
+
+/// The checked-in configuration of a workspace, `.amazonq/config.toml`, with which a team shares
+/// its agent setup through git.
+///
+/// It is read when q chat starts in the workspace, and its context, MCP servers, trusted tools and
+/// model are added to every agent, where the agent doesn't configure them itself. As it comes with
+/// the repository, it is only used once the user trusted it, see [Self::confirm_trust], and it can
+/// only trust tools of the MCP servers it adds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct WorkspaceConfig {
+    /// The agent to use when none is given with --agent, over chat.defaultAgent
+    #[serde(default)]
+    pub default_agent: Option<String>,
+    /// The model to use when the agent doesn't specify one
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Paths or glob patterns of files to include in the context
+    #[serde(default)]
+    pub context: Vec<String>,
+    /// MCP servers, by name, configured as in an agent config
+    #[serde(default)]
+    pub mcp_servers: BTreeMap<String, CustomToolConfig>,
+    /// Tools of its own MCP servers trusted without asking, as in the allowedTools of an agent
+    /// config
+    #[serde(default)]
+    pub allowed_tools: HashSet<String>,
+    #[serde(skip)]
+    pub path: PathBuf,
+    /// Digest of the content, so that a change must be trusted again
+    #[serde(skip)]
+    pub digest: String,
+}
+
+impl WorkspaceConfig {
+    /// Reads the config of the current directory, `None` if it has none.
+    pub async fn load(os: &Os) -> Result<Option<Self>, AgentConfigError> {
+        let path = directories::chat_workspace_config_path(os)?;
+        if !os.fs.exists(&path) {
+            return Ok(None);
+        }
+
+        let content = os.fs.read_to_string(&path).await?;
+        let mut config = Self::parse(&content).map_err(|error| AgentConfigError::InvalidToml {
+            error,
+            path: path.clone(),
+        })?;
+        config.path = path;
+        config.digest = hex::encode(Sha256::digest(content.as_bytes()));
+        Ok(Some(config))
+    }
+
+    /// Whether the user trusted the config, as it is now, in this workspace.
+    pub async fn is_trusted(&self, os: &Os) -> bool {
+        let path = self.canonical_path(os).await;
+        os.database
+            .get_trusted_workspace_config(&path)
+            .ok()
+            .flatten()
+            .is_some_and(|digest| digest == self.digest)
+    }
+
+    /// Asks the user whether to use the config, unless they already trusted it as it is. Returns
+    /// whether it is trusted. When `interactive` is false, an untrusted config is ignored.
+    pub async fn confirm_trust(
+        &self,
+        os: &Os,
+        output: &mut impl Write,
+        interactive: bool,
+    ) -> Result<bool, AgentConfigError> {
+        if self.is_trusted(os).await {
+            return Ok(true);
+        }
+
+        execute!(
+            output,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print("This workspace has a .amazonq/config.toml that changes your agents:\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        self.print(output)?;
+        if !interactive {
+            execute!(
+                output,
+                style::Print("It is ignored until you trust it in an interactive q chat session\n\n")
+            )?;
+            return Ok(false);
+        }
+
+        let trusted = dialoguer::Confirm::with_theme(&dialoguer_theme())
+            .with_prompt("Trust it in this workspace?")
+            .default(false)
+            .interact()
+            .unwrap_or(false);
+        execute!(output, style::Print("\n"))?;
+        if trusted {
+            let path = self.canonical_path(os).await;
+            os.database.set_trusted_workspace_config(&path, &self.digest)?;
+        }
+        Ok(trusted)
+    }
+
+    async fn canonical_path(&self, os: &Os) -> String {
+        os.fs
+            .canonicalize(&self.path)
+            .await
+            .unwrap_or_else(|_| self.path.clone())
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Prints what the config adds to the agents.
+    pub fn print(&self, output: &mut impl Write) -> Result<(), std::io::Error> {
+        execute!(
+            output,
+            style::SetAttribute(Attribute::Bold),
+            style::SetForegroundColor(Color::Magenta),
+            style::Print("🗂️  Workspace (.amazonq/config.toml):\n"),
+            style::SetAttribute(Attribute::Reset),
+        )?;
+
+        let mcp_servers = self.mcp_servers.keys().map(String::as_str).collect::<Vec<_>>();
+        let mut allowed_tools = self.trusted_tools().map(String::as_str).collect::<Vec<_>>();
+        allowed_tools.sort();
+        let entries = [
+            ("default agent", self.default_agent.clone().unwrap_or_default()),
+            ("model", self.model.clone().unwrap_or_default()),
+            ("context", self.context.join(", ")),
+            ("mcp servers", mcp_servers.join(", ")),
+            ("trusted tools", allowed_tools.join(", ")),
+        ];
+        let entries = entries
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .collect::<Vec<_>>();
+        if entries.is_empty() {
+            execute!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("    <empty>\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        }
+        for (name, value) in entries {
+            execute!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("    {name}: ")),
+                style::SetForegroundColor(Color::Reset),
+                style::Print(format!("{value}\n")),
+            )?;
+        }
+        execute!(output, style::Print("\n"))?;
+        Ok(())
+    }
+
+    /// The allowedTools that name tools of the config's own MCP servers, the only ones it can
+    /// trust.
+    pub fn trusted_tools(&self) -> impl Iterator<Item = &String> {
+        self.allowed_tools
+            .iter()
+            .filter(|pattern| server_of(pattern).is_some_and(|server| self.mcp_servers.contains_key(server)))
+    }
+
+    fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// Adds what the config declares to `agent`, without overriding what it configures itself.
+    pub fn apply(&self, agent: &mut Agent, mcp_enabled: bool) {
+        for pattern in &self.context {
+            let resource = format!("file://{pattern}");
+            if !agent.resources.iter().any(|r| **r == resource) {
+                agent.resources.push(resource.into());
+            }
+        }
+
+        if agent.model.is_none() {
+            agent.model = self.model.clone();
+        }
+
+        if mcp_enabled {
+            for (name, server) in &self.mcp_servers {
+                if agent.mcp_servers.mcp_servers.contains_key(name) {
+                    continue;
+                }
+                agent.mcp_servers.mcp_servers.insert(name.clone(), server.clone());
+
+                // The config can't widen what agents trust, only trust the servers it adds.
+                agent.allowed_tools.extend(
+                    self.trusted_tools()
+                        .filter(|pattern| server_of(pattern) == Some(name.as_str()))
+                        .cloned(),
+                );
+
+                // Agents that list their tools wouldn't see the server's otherwise.
+                let tools = format!("@{name}");
+                if !agent.tools.iter().any(|t| t == "*" || *t == tools) {
+                    agent.tools.push(tools);
+                }
+            }
+        }
+    }
+}
+
+/// The MCP server an allowedTools pattern like `@git` or `@git/git_status` names, if any.
+fn server_of(pattern: &str) -> Option<&str> {
+    let pattern = pattern.strip_prefix('@')?;
+    pattern.split(MCP_SERVER_TOOL_DELIMITER).next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+defaultAgent = "backend"
+model = "claude-sonnet-4"
+context = ["README.md", "docs/**/*.md"]
+allowedTools = ["execute_bash", "@git", "@github/list_prs"]
+
+[mcpServers.git]
+command = "uvx"
+args = ["mcp-server-git"]
+"#;
+
+    #[test]
+    fn test_parse() {
+        let config = WorkspaceConfig::parse(CONFIG).unwrap();
+        assert_eq!(config.default_agent.as_deref(), Some("backend"));
+        assert_eq!(config.context, vec!["README.md", "docs/**/*.md"]);
+        assert_eq!(config.mcp_servers["git"].command, "uvx");
+        assert!(config.allowed_tools.contains("@git"));
+        assert_eq!(config.trusted_tools().collect::<Vec<_>>(), vec!["@git"]);
+
+        assert_eq!(WorkspaceConfig::parse("").unwrap(), WorkspaceConfig::default());
+        assert!(WorkspaceConfig::parse("defaultAgent = 1").is_err());
+        assert!(WorkspaceConfig::parse("unknown = true").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let config = WorkspaceConfig::parse(CONFIG).unwrap();
+
+        let mut agent = Agent::default();
+        config.apply(&mut agent, true);
+        assert!(agent.resources.iter().any(|r| **r == "file://docs/**/*.md"));
+        assert!(agent.allowed_tools.contains("@git"));
+        assert!(!agent.allowed_tools.contains("execute_bash"));
+        assert!(!agent.allowed_tools.contains("@github/list_prs"));
+        assert_eq!(agent.model.as_deref(), Some("claude-sonnet-4"));
+        assert!(agent.mcp_servers.mcp_servers.contains_key("git"));
+        assert_eq!(agent.tools, vec!["*"]);
+
+        // Applying twice doesn't duplicate the context.
+        let resources = agent.resources.len();
+        config.apply(&mut agent, true);
+        assert_eq!(agent.resources.len(), resources);
+
+        let mut agent = Agent {
+            tools: vec!["fs_read".to_string()],
+            model: Some("claude-opus-4".to_string()),
+            ..Default::default()
+        };
+        config.apply(&mut agent, false);
+        assert_eq!(agent.model.as_deref(), Some("claude-opus-4"));
+        assert!(agent.mcp_servers.mcp_servers.is_empty());
+        assert_eq!(agent.tools, vec!["fs_read"]);
+
+        config.apply(&mut agent, true);
+        assert_eq!(agent.tools, vec!["fs_read", "@git"]);
+
+        // Tools of a server the agent configures itself aren't trusted.
+        let mut agent = Agent::default();
+        agent
+            .mcp_servers
+            .mcp_servers
+            .insert("git".to_string(), config.mcp_servers["git"].clone());
+        config.apply(&mut agent, true);
+        assert!(agent.allowed_tools.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_trust() {
+        let os = Os::new().await.unwrap();
+        let mut config = WorkspaceConfig::parse(CONFIG).unwrap();
+        config.path = PathBuf::from("/workspace/.amazonq/config.toml");
+        config.digest = hex::encode(Sha256::digest(CONFIG.as_bytes()));
+        assert!(!config.is_trusted(&os).await);
+
+        let mut output = Vec::new();
+        assert!(!config.confirm_trust(&os, &mut output, false).await.unwrap());
+        assert!(!config.is_trusted(&os).await);
+
+        let path = config.canonical_path(&os).await;
+        os.database.set_trusted_workspace_config(&path, &config.digest).unwrap();
+        assert!(config.is_trusted(&os).await);
+
+        // A change must be trusted again.
+        config.digest = hex::encode(Sha256::digest(b"allowedTools = [\"*\"]"));
+        assert!(!config.is_trusted(&os).await);
+    }
+}
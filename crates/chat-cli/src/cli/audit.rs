@@ -0,0 +1,243 @@
+//! Reviewing what the agent actually did, from the audit log of the tools it used.
+
+use std::fmt::Write as _;
+use std::process::ExitCode;
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use clap::{
+    Args,
+    Subcommand,
+};
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+
+use super::OutputFormat;
+use crate::cli::chat::audit::{
+    self,
+    AuditEntry,
+};
+use crate::os::Os;
+use crate::util::directories;
+use crate::util::time::{
+    format_timestamp,
+    parse_duration,
+};
+
+/// How many characters of an id `q audit list` prints, enough for `q audit show`.
+const SHORT_ID_LEN: usize = 8;
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum AuditSubcommand {
+    /// List the tools the agent used, oldest first
+    List(ListArgs),
+    /// Show everything recorded about a tool use
+    Show(ShowArgs),
+}
+
+impl AuditSubcommand {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let entries = audit::read_all(&directories::audit_log_path()?).await?;
+        match self {
+            Self::List(args) => args.execute(os, entries),
+            Self::Show(args) => args.execute(os, entries),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Args)]
+pub struct ListArgs {
+    /// Only the tool uses of this conversation
+    #[arg(long)]
+    pub conversation: Option<String>,
+    /// Only the uses of this tool
+    #[arg(long)]
+    pub tool: Option<String>,
+    /// Only the tool uses of this long ago or less, such as 12h or 7d
+    #[arg(long, value_parser = parse_duration)]
+    pub since: Option<std::time::Duration>,
+    /// How many tool uses to list, the most recent ones
+    #[arg(long, default_value_t = 50)]
+    pub last: usize,
+    /// The format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl ListArgs {
+    pub fn execute(self, _os: &mut Os, entries: Vec<AuditEntry>) -> Result<ExitCode> {
+        let since = self.since.map(cutoff).transpose()?;
+        let mut entries = entries
+            .into_iter()
+            .filter(|entry| {
+                self.conversation
+                    .as_ref()
+                    .is_none_or(|conversation| &entry.conversation_id == conversation)
+                    && self.tool.as_ref().is_none_or(|tool| &entry.tool == tool)
+                    && since.is_none_or(|since| entry.recorded_at >= since)
+            })
+            .collect::<Vec<_>>();
+        entries.drain(..entries.len().saturating_sub(self.last));
+
+        self.format.print(|| format_list(&entries), || &entries);
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+#[derive(Debug, PartialEq, Args)]
+pub struct ShowArgs {
+    /// The id of the tool use, or its beginning as printed by q audit list
+    pub id: String,
+    /// The format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl ShowArgs {
+    pub fn execute(self, _os: &mut Os, entries: Vec<AuditEntry>) -> Result<ExitCode> {
+        let matches = entries
+            .iter()
+            .filter(|entry| !entry.id.is_empty() && entry.id.starts_with(&self.id))
+            .collect::<Vec<_>>();
+        let entry = match matches.as_slice() {
+            [entry] => *entry,
+            [] => bail!("No tool use has the id {}", self.id),
+            _ => bail!(
+                "{} tool uses have ids starting with {}, give more of it",
+                matches.len(),
+                self.id
+            ),
+        };
+        self.format.print(|| format_entry(entry), || entry);
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// The time `since` ago, or an error if that is before the earliest time that can be represented.
+fn cutoff(since: std::time::Duration) -> Result<DateTime<Utc>> {
+    chrono::Duration::from_std(since)
+        .ok()
+        .and_then(|since| Utc::now().checked_sub_signed(since))
+        .ok_or_else(|| eyre!("--since is too far in the past"))
+}
+
+fn format_list(entries: &[AuditEntry]) -> String {
+    if entries.is_empty() {
+        return "No tool uses were recorded".to_string();
+    }
+    let mut out = String::new();
+    for entry in entries {
+        let outcome = match (entry.success, entry.exit_status) {
+            (Some(true), Some(status)) if status != 0 => format!("exit {status}"),
+            (Some(true), _) => "ok".to_string(),
+            (Some(false), _) => "failed".to_string(),
+            (None, _) => "not run".to_string(),
+        };
+        let _ = write!(
+            out,
+            "{}  {}  {:<18} {:<9} {outcome}",
+            entry.id.get(..SHORT_ID_LEN).unwrap_or("-"),
+            format_timestamp(&entry.recorded_at),
+            entry.tool,
+            entry.decision.to_string(),
+        );
+        if let Some(action) = &entry.action {
+            let _ = write!(out, "  {action}");
+            if let Some(target) = &entry.target {
+                let _ = write!(out, " {target}");
+            }
+        }
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+fn format_entry(entry: &AuditEntry) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let mut out = String::new();
+    let _ = writeln!(out, "id: {}", entry.id);
+    let _ = writeln!(out, "recorded: {}", format_timestamp(&entry.recorded_at));
+    let _ = writeln!(out, "conversation: {}", entry.conversation_id);
+    let _ = writeln!(out, "tool: {}", entry.tool);
+    let _ = writeln!(out, "arguments hash: {}", optional(entry.arguments_hash.clone()));
+    let _ = writeln!(out, "decision: {}", entry.decision);
+    if let Some(action) = &entry.action {
+        let _ = writeln!(out, "action: {action}");
+        let _ = writeln!(out, "target: {}", optional(entry.target.clone()));
+    }
+    let success = entry.success.map(|success| match success {
+        true => "succeeded".to_string(),
+        false => "failed".to_string(),
+    });
+    let _ = writeln!(out, "result: {}", success.unwrap_or_else(|| "not run".to_string()));
+    let _ = writeln!(
+        out,
+        "exit status: {}",
+        optional(entry.exit_status.map(|status| status.to_string()))
+    );
+    let _ = write!(
+        out,
+        "duration: {}",
+        optional(entry.duration_ms.map(|ms| format!("{ms}ms")))
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::audit::Decision;
+
+    fn entry(id: &str, decision: Decision, success: Option<bool>) -> AuditEntry {
+        AuditEntry {
+            id: id.to_string(),
+            recorded_at: Utc::now(),
+            conversation_id: "conversation".to_string(),
+            tool: "execute_bash".to_string(),
+            arguments_hash: Some("abc".to_string()),
+            decision,
+            action: None,
+            target: None,
+            success,
+            exit_status: success.map(|_| 1),
+            duration_ms: success.map(|_| 20),
+        }
+    }
+
+    #[test]
+    fn test_cutoff() {
+        let hour_ago = cutoff(std::time::Duration::from_secs(3600)).unwrap();
+        assert!(hour_ago < Utc::now() - chrono::Duration::minutes(59));
+        assert!(cutoff(std::time::Duration::from_secs(u64::MAX)).is_err());
+        assert!(cutoff(std::time::Duration::from_secs(400_000 * 365 * 24 * 3600)).is_err());
+    }
+
+    #[test]
+    fn test_format_list() {
+        assert_eq!(format_list(&[]), "No tool uses were recorded");
+        let out = format_list(&[
+            entry("0123456789abcdef", Decision::Trusted, Some(true)),
+            entry("fedcba9876543210", Decision::Denied, None),
+        ]);
+        let lines = out.lines().collect::<Vec<_>>();
+        assert!(lines[0].starts_with("01234567  "));
+        assert!(lines[0].contains("execute_bash"));
+        assert!(lines[0].contains("trusted"));
+        assert!(lines[0].ends_with("exit 1"));
+        assert!(lines[1].contains("denied"));
+        assert!(lines[1].ends_with("not run"));
+    }
+
+    #[test]
+    fn test_format_entry() {
+        let out = format_entry(&entry("0123456789abcdef", Decision::Accepted, Some(false)));
+        assert!(out.contains("decision: accepted\n"));
+        assert!(out.contains("result: failed\n"));
+        assert!(out.ends_with("duration: 20ms"));
+    }
+}
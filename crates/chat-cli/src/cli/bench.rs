@@ -0,0 +1,423 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use clap::Args;
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use winnow::Partial;
+use winnow::stream::Offset;
+
+use super::OutputFormat;
+use crate::cli::chat::checkpoint::{
+    CheckpointManager,
+    is_git_installed,
+};
+use crate::cli::chat::parse::{
+    ParseState,
+    interpret_markdown,
+};
+use crate::cli::chat::tools::fs_read::{
+    FsLine,
+    FsSearch,
+};
+use crate::os::Os;
+
+/// Iterations run before measuring, to warm up the file system cache.
+const WARM_UP_ITERATIONS: usize = 3;
+
+/// Lines read by each of the fs_read window benchmarks.
+const WINDOW_LINES: i32 = 200;
+
+/// Sections in the markdown rendered by the markdown benchmark, about 90 KB.
+const MARKDOWN_SECTIONS: usize = 200;
+
+/// Runs the built-in tools against synthetic workspaces and reports how long they take, so
+/// performance regressions are caught before a release.
+#[derive(Debug, PartialEq, Args)]
+pub struct BenchArgs {
+    /// Only run benchmarks whose name contains this string
+    filter: Option<String>,
+    /// Number of measured iterations of each benchmark
+    #[arg(long, default_value_t = 50)]
+    samples: usize,
+    /// Number of lines in the file searched and read
+    #[arg(long, default_value_t = 10_000)]
+    lines: usize,
+    /// Number of files in the workspace that is checkpointed
+    #[arg(long, default_value_t = 200)]
+    files: usize,
+    /// Results of a previous run, saved with --save, to compare against
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+    /// Save the results to this path to use as a baseline later
+    #[arg(long)]
+    save: Option<PathBuf>,
+    /// Slowdown of the mean time against the baseline, in percent, that is reported as a
+    /// regression
+    #[arg(long, default_value_t = 10.0)]
+    threshold: f64,
+    /// Output format to use
+    #[arg(long, short, value_enum, default_value_t)]
+    format: OutputFormat,
+}
+
+/// Timing statistics of a single benchmark, in nanoseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchResult {
+    pub name: String,
+    pub samples: usize,
+    pub mean_ns: f64,
+    pub std_dev_ns: f64,
+    pub median_ns: f64,
+    pub min_ns: f64,
+    pub max_ns: f64,
+    /// Change of the mean against the baseline, in percent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_percent: Option<f64>,
+}
+
+impl BenchResult {
+    fn from_samples(name: impl Into<String>, samples: &[Duration]) -> Self {
+        let mut nanos: Vec<f64> = samples.iter().map(|d| d.as_nanos() as f64).collect();
+        nanos.sort_by(f64::total_cmp);
+
+        let n = nanos.len().max(1) as f64;
+        let mean = nanos.iter().sum::<f64>() / n;
+        let variance = nanos.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+
+        Self {
+            name: name.into(),
+            samples: nanos.len(),
+            mean_ns: mean,
+            std_dev_ns: variance.sqrt(),
+            median_ns: nanos.get(nanos.len() / 2).copied().unwrap_or_default(),
+            min_ns: nanos.first().copied().unwrap_or_default(),
+            max_ns: nanos.last().copied().unwrap_or_default(),
+            change_percent: None,
+        }
+    }
+
+    /// Bounds of the 95% confidence interval of the mean.
+    fn confidence_interval(&self) -> (f64, f64) {
+        let margin = 1.96 * self.std_dev_ns / (self.samples.max(1) as f64).sqrt();
+        ((self.mean_ns - margin).max(0.0), self.mean_ns + margin)
+    }
+
+    /// Formats the result the way criterion does, e.g.
+    /// `fs_search/10000_lines  time: [1.2012 ms 1.2104 ms 1.2203 ms]`.
+    fn criterion_style(&self, threshold: f64) -> String {
+        let (lower, upper) = self.confidence_interval();
+        let mut out = format!(
+            "{:<32}time:   [{} {} {}]",
+            self.name,
+            format_nanos(lower),
+            format_nanos(self.mean_ns),
+            format_nanos(upper)
+        );
+        if let Some(change) = self.change_percent {
+            let verdict = if change > threshold {
+                "Performance has regressed."
+            } else if change < -threshold {
+                "Performance has improved."
+            } else {
+                "No change in performance detected."
+            };
+            let _ = write!(out, "\n{:<32}change: [{change:+.2}%] {verdict}", "");
+        }
+        out
+    }
+}
+
+impl BenchArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let workspace = tempfile::tempdir()?;
+        let mut results = Vec::new();
+
+        if self.matches("fs_search") || self.matches("fs_read") {
+            let path = workspace.path().join("large.rs");
+            os.fs.write(&path, synthetic_source(self.lines)).await?;
+
+            if self.matches("fs_search") {
+                results.push(self.bench_fs_search(os, &path).await?);
+            }
+            for (name, start_line) in [
+                ("fs_read/window_head", 1),
+                ("fs_read/window_middle", (self.lines / 2) as i32),
+                ("fs_read/window_tail", -WINDOW_LINES),
+            ] {
+                if self.matches(name) {
+                    results.push(self.bench_fs_read_window(os, name, &path, start_line).await?);
+                }
+            }
+        }
+
+        if self.matches("markdown") {
+            results.push(self.bench_markdown()?);
+        }
+
+        if self.matches("checkpoint") {
+            if is_git_installed() {
+                results.push(self.bench_checkpoint(os, workspace.path()).await?);
+            } else {
+                eprintln!("Skipping checkpoint benchmarks: git is not installed");
+            }
+        }
+
+        if results.is_empty() {
+            bail!("No benchmarks match the filter");
+        }
+
+        if let Some(baseline) = &self.baseline {
+            let baseline: Vec<BenchResult> = serde_json::from_slice(&os.fs.read(baseline).await?)?;
+            for result in &mut results {
+                if let Some(base) = baseline.iter().find(|b| b.name == result.name && b.mean_ns > 0.0) {
+                    result.change_percent = Some((result.mean_ns - base.mean_ns) / base.mean_ns * 100.0);
+                }
+            }
+        }
+
+        if let Some(save) = &self.save {
+            os.fs.write(save, serde_json::to_string_pretty(&results)?).await?;
+        }
+
+        self.format.print(
+            || {
+                results
+                    .iter()
+                    .map(|result| result.criterion_style(self.threshold))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            },
+            || &results,
+        );
+
+        let regressed = results
+            .iter()
+            .any(|result| result.change_percent.is_some_and(|change| change > self.threshold));
+        Ok(if regressed {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        })
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| name.contains(filter.as_str()) || filter.contains(name))
+    }
+
+    async fn bench_fs_search(&self, os: &Os, path: &Path) -> Result<BenchResult> {
+        let search = FsSearch {
+            path: path.to_string_lossy().to_string(),
+            pattern: "needle".to_string(),
+            context_lines: None,
+        };
+
+        let mut samples = Vec::with_capacity(self.samples);
+        for i in 0..WARM_UP_ITERATIONS + self.samples {
+            let start = Instant::now();
+            search.invoke(os, &mut std::io::sink()).await?;
+            if i >= WARM_UP_ITERATIONS {
+                samples.push(start.elapsed());
+            }
+        }
+
+        Ok(BenchResult::from_samples(
+            format!("fs_search/{}_lines", self.lines),
+            &samples,
+        ))
+    }
+
+    async fn bench_fs_read_window(&self, os: &Os, name: &str, path: &Path, start_line: i32) -> Result<BenchResult> {
+        let read = FsLine {
+            path: path.to_string_lossy().to_string(),
+            start_line: Some(start_line),
+            end_line: Some(if start_line < 0 {
+                -1
+            } else {
+                start_line + WINDOW_LINES - 1
+            }),
+        };
+
+        let mut samples = Vec::with_capacity(self.samples);
+        for i in 0..WARM_UP_ITERATIONS + self.samples {
+            let start = Instant::now();
+            read.invoke(os, &mut std::io::sink()).await?;
+            if i >= WARM_UP_ITERATIONS {
+                samples.push(start.elapsed());
+            }
+        }
+
+        Ok(BenchResult::from_samples(name, &samples))
+    }
+
+    fn bench_markdown(&self) -> Result<BenchResult> {
+        let markdown = synthetic_markdown(MARKDOWN_SECTIONS);
+
+        let mut samples = Vec::with_capacity(self.samples);
+        for i in 0..WARM_UP_ITERATIONS + self.samples {
+            let start = Instant::now();
+            render_markdown(&markdown)?;
+            if i >= WARM_UP_ITERATIONS {
+                samples.push(start.elapsed());
+            }
+        }
+
+        Ok(BenchResult::from_samples(
+            format!("markdown/render_{}_kb", markdown.len() / 1024),
+            &samples,
+        ))
+    }
+
+    async fn bench_checkpoint(&self, os: &Os, workspace: &Path) -> Result<BenchResult> {
+        let work_tree = workspace.join("project");
+        for i in 0..self.files {
+            let dir = work_tree.join(format!("module_{}", i % 10));
+            os.fs.create_dir_all(&dir).await?;
+            os.fs
+                .write(dir.join(format!("file_{i}.rs")), synthetic_source(200))
+                .await?;
+        }
+
+        let mut manager =
+            CheckpointManager::init_with_work_tree(os, workspace.join("shadow"), work_tree.clone(), &VecDeque::new())
+                .await?;
+
+        let mut samples = Vec::with_capacity(self.samples);
+        for i in 0..WARM_UP_ITERATIONS + self.samples {
+            // Each checkpoint follows an edit, like one created after a tool use.
+            let edited = work_tree.join(format!("module_{}/file_{}.rs", i % 10, i % self.files.max(1)));
+            os.fs
+                .write(&edited, format!("// edit {i}\n{}", synthetic_source(200)))
+                .await?;
+
+            let start = Instant::now();
+            manager.create_checkpoint(&format!("{}", i + 1), "bench", &VecDeque::new(), true, None)?;
+            if i >= WARM_UP_ITERATIONS {
+                samples.push(start.elapsed());
+            }
+        }
+
+        Ok(BenchResult::from_samples(
+            format!("checkpoint/create_{}_files", self.files),
+            &samples,
+        ))
+    }
+}
+
+/// Renders `markdown` the way streamed responses are rendered in chat, discarding the output.
+fn render_markdown(markdown: &str) -> Result<()> {
+    let mut state = ParseState::new(Some(120), Some(false));
+    state.ended = true;
+    let mut output = std::io::sink();
+    let mut offset = 0;
+
+    loop {
+        let input = Partial::new(&markdown[offset..]);
+        match interpret_markdown(input, &mut output, &mut state) {
+            Ok(parsed) => {
+                offset += parsed.offset_from(&input);
+                state.newline = state.set_newline;
+                state.set_newline = false;
+            },
+            Err(err) => match err.into_inner() {
+                Some(err) => bail!("{err}"),
+                None => break, // Data was incomplete
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Rust-like source with a line containing "needle" every 100 lines.
+fn synthetic_source(lines: usize) -> String {
+    let mut out = String::with_capacity(lines * 48);
+    for i in 0..lines {
+        match i % 100 {
+            0 => writeln!(out, "fn function_{i}() -> usize {{"),
+            50 => writeln!(out, "    let needle = lookup(\"haystack_{i}\");"),
+            99 => writeln!(out, "}}"),
+            _ => writeln!(out, "    let value_{i} = compute({i}, \"some string\");"),
+        }
+        .expect("writing to a string cannot fail");
+    }
+    out
+}
+
+/// A markdown response with `sections` sections of headings, prose, lists, and code blocks.
+fn synthetic_markdown(sections: usize) -> String {
+    let mut out = String::new();
+    for i in 0..sections.max(1) {
+        write!(
+            out,
+            "## Section {i}\n\n\
+            This paragraph has **bold**, *italic*, `inline code`, and a [link](https://example.com/{i}) \
+            that wraps across the terminal width because it is long enough to need wrapping.\n\n\
+            - First item with `code`\n\
+            - Second item\n  - Nested item\n\
+            1. Numbered item\n\n\
+            > A quoted line\n\n\
+            ```rust\nfn section_{i}() {{\n    println!(\"{i}\");\n}}\n```\n\n"
+        )
+        .expect("writing to a string cannot fail");
+    }
+    out
+}
+
+fn format_nanos(nanos: f64) -> String {
+    match nanos {
+        n if n < 1_000.0 => format!("{n:.2} ns"),
+        n if n < 1_000_000.0 => format!("{:.4} µs", n / 1_000.0),
+        n if n < 1_000_000_000.0 => format!("{:.4} ms", n / 1_000_000.0),
+        n => format!("{:.4} s", n / 1_000_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_result_from_samples() {
+        let samples = [3, 1, 2].map(Duration::from_micros);
+        let result = BenchResult::from_samples("fs_search/10_lines", &samples);
+        assert_eq!(result.samples, 3);
+        assert_eq!(result.mean_ns, 2_000.0);
+        assert_eq!(result.median_ns, 2_000.0);
+        assert_eq!(result.min_ns, 1_000.0);
+        assert_eq!(result.max_ns, 3_000.0);
+
+        let (lower, upper) = result.confidence_interval();
+        assert!(lower < result.mean_ns && result.mean_ns < upper);
+    }
+
+    #[test]
+    fn test_render_synthetic_markdown() {
+        render_markdown(&synthetic_markdown(3)).unwrap();
+    }
+
+    #[test]
+    fn test_synthetic_source() {
+        let source = synthetic_source(250);
+        assert_eq!(source.lines().count(), 250);
+        assert_eq!(source.matches("needle").count(), 3);
+    }
+}
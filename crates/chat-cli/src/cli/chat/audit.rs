@@ -0,0 +1,265 @@
+//! The audit log of every tool the agent used: when, in which conversation, with which arguments,
+//! whether it was trusted, accepted or denied, and how it ended. `q audit` reads it.
+//!
+//! The log is append-only. Past [MAX_LOG_SIZE] it is rotated to `audit.1.jsonl`, and so on up to
+//! [MAX_ROTATED_LOGS] files, the oldest of which is dropped.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::Duration;
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+
+use super::tools::{
+    InvokeOutput,
+    OutputKind,
+    QueuedTool,
+};
+use crate::util::directories;
+
+/// The size past which the log is rotated.
+pub const MAX_LOG_SIZE: u64 = 10 * 1024 * 1024;
+
+/// How many rotated logs are kept besides the current one.
+pub const MAX_ROTATED_LOGS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Decision {
+    /// Run without asking, as the tool is trusted.
+    Trusted,
+    /// Run after the user accepted it.
+    Accepted,
+    /// Not run, as the user denied it.
+    Denied,
+    /// Not run, as it matched the denied list of the agent.
+    Blocked,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    #[serde(default)]
+    pub id: String,
+    pub recorded_at: DateTime<Utc>,
+    pub conversation_id: String,
+    pub tool: String,
+    /// The SHA-256 of the arguments of the tool, which are not kept themselves as they may hold
+    /// secrets.
+    #[serde(default)]
+    pub arguments_hash: Option<String>,
+    pub decision: Decision,
+    /// For infrastructure changes, what the tool did, such as `cloudformation deploy`.
+    #[serde(default)]
+    pub action: Option<String>,
+    /// What the infrastructure change was made to, such as the name of a stack.
+    #[serde(default)]
+    pub target: Option<String>,
+    /// Whether the tool succeeded, none if it was not run.
+    #[serde(default)]
+    pub success: Option<bool>,
+    /// The exit status of the commands of `execute_bash` and `use_aws`.
+    #[serde(default)]
+    pub exit_status: Option<i32>,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+}
+
+impl AuditEntry {
+    pub fn new(conversation_id: &str, tool: &QueuedTool, decision: Decision) -> Self {
+        let change = tool.tool.infra_change();
+        Self {
+            id: uuid::Uuid::new_v4().simple().to_string(),
+            recorded_at: Utc::now(),
+            conversation_id: conversation_id.to_string(),
+            tool: tool.name.clone(),
+            arguments_hash: Some(arguments_hash(&tool.tool_input)),
+            decision,
+            action: change.as_ref().map(|change| change.action.clone()),
+            target: change.and_then(|change| change.stack),
+            success: None,
+            exit_status: None,
+            duration_ms: None,
+        }
+    }
+
+    /// The entry of a tool that was run, with how it ended.
+    pub fn with_result(mut self, result: &eyre::Result<InvokeOutput>, duration: Duration) -> Self {
+        self.success = Some(result.is_ok());
+        self.exit_status = result.as_ref().ok().and_then(exit_status);
+        self.duration_ms = Some(duration.as_millis() as u64);
+        self
+    }
+}
+
+fn arguments_hash(arguments: &serde_json::Value) -> String {
+    format!("{:x}", Sha256::digest(arguments.to_string().as_bytes()))
+}
+
+fn exit_status(output: &InvokeOutput) -> Option<i32> {
+    match &output.output {
+        OutputKind::Json(json) => json.get("exit_status")?.as_str()?.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Appends `entry` to the audit log of the user, logging rather than returning a failure.
+pub async fn log(entry: &AuditEntry) {
+    // Tests shouldn't log to the user's data directory.
+    if cfg!(test) {
+        return;
+    }
+    let Ok(path) = directories::audit_log_path() else {
+        return;
+    };
+    if let Err(err) = record(&path, entry).await {
+        warn!(%err, "Failed to record an audit log entry");
+    }
+}
+
+/// Appends `entry` to the log at `path`, rotating the log first if it is full. Entries are never
+/// rewritten.
+pub async fn record(path: &Path, entry: &AuditEntry) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    if tokio::fs::metadata(path)
+        .await
+        .is_ok_and(|metadata| metadata.len() >= MAX_LOG_SIZE)
+    {
+        rotate(path).await?;
+    }
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    file.write_all(format!("{}\n", serde_json::to_string(entry)?).as_bytes())
+        .await?;
+    file.flush().await
+}
+
+/// Moves each rotated log one place up, dropping the last one, and the log to the first place.
+async fn rotate(path: &Path) -> std::io::Result<()> {
+    for n in (1..MAX_ROTATED_LOGS).rev() {
+        let from = rotated_path(path, n);
+        if tokio::fs::try_exists(&from).await? {
+            tokio::fs::rename(&from, rotated_path(path, n + 1)).await?;
+        }
+    }
+    tokio::fs::rename(path, rotated_path(path, 1)).await
+}
+
+/// `audit.<n>.jsonl` next to `audit.jsonl`.
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    path.with_extension(format!("{n}.jsonl"))
+}
+
+/// The entries of the log at `path` and its rotated logs, oldest first.
+pub async fn read_all(path: &Path) -> std::io::Result<Vec<AuditEntry>> {
+    let mut paths = (1..=MAX_ROTATED_LOGS)
+        .rev()
+        .map(|n| rotated_path(path, n))
+        .collect::<Vec<_>>();
+    paths.push(path.to_path_buf());
+
+    let mut entries = Vec::new();
+    for path in paths {
+        let content = match tokio::fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        entries.extend(
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<AuditEntry>(line).ok()),
+        );
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::tools::Tool;
+    use crate::cli::chat::tools::execute::ExecuteCommand;
+
+    fn queued(command: &str) -> QueuedTool {
+        QueuedTool {
+            id: "tool-use".to_string(),
+            name: "execute_bash".to_string(),
+            accepted: true,
+            tool: Tool::ExecuteCommand(ExecuteCommand {
+                command: command.to_string(),
+                summary: None,
+                notice: None,
+            }),
+            tool_input: serde_json::json!({ "command": command }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_and_read_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        assert!(read_all(&path).await.unwrap().is_empty());
+
+        let output = Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::json!({ "exit_status": "2" })),
+        });
+        let entry = AuditEntry::new("conversation", &queued("cdk deploy Api"), Decision::Accepted)
+            .with_result(&output, Duration::from_millis(1500));
+        record(&path, &entry).await.unwrap();
+        record(&path, &AuditEntry::new("conversation", &queued("ls"), Decision::Denied))
+            .await
+            .unwrap();
+
+        let entries = read_all(&path).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], entry);
+        assert_eq!(entries[0].action.as_deref(), Some("cdk deploy"));
+        assert_eq!(entries[0].exit_status, Some(2));
+        assert_eq!(entries[0].duration_ms, Some(1500));
+        assert_eq!(entries[1].decision, Decision::Denied);
+        assert_eq!(entries[1].success, None);
+        assert_ne!(entries[0].arguments_hash, entries[1].arguments_hash);
+    }
+
+    #[tokio::test]
+    async fn test_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        for n in 0..=MAX_ROTATED_LOGS + 1 {
+            let entry = AuditEntry::new(&format!("conversation{n}"), &queued("ls"), Decision::Trusted);
+            record(&path, &entry).await.unwrap();
+            rotate(&path).await.unwrap();
+        }
+
+        assert!(!path.exists());
+        assert!(!rotated_path(&path, MAX_ROTATED_LOGS + 1).exists());
+        let entries = read_all(&path).await.unwrap();
+        assert_eq!(entries.len(), MAX_ROTATED_LOGS);
+        assert_eq!(entries[0].conversation_id, "conversation2");
+        assert_eq!(
+            entries[MAX_ROTATED_LOGS - 1].conversation_id,
+            format!("conversation{}", MAX_ROTATED_LOGS + 1)
+        );
+    }
+}
@@ -3,6 +3,7 @@ use std::collections::{
     VecDeque,
 };
 use std::path::{
+    Component,
     Path,
     PathBuf,
 };
@@ -25,11 +26,30 @@ use serde::{
     Deserialize,
     Serialize,
 };
-use tracing::debug;
+use tracing::{
+    debug,
+    warn,
+};
 
 use crate::cli::ConversationState;
 use crate::cli::chat::conversation::HistoryEntry;
+use crate::database::settings::Setting;
 use crate::os::Os;
+use crate::util::directories::{
+    legacy_shadow_repos_dir,
+    shadow_repos_dir,
+};
+
+/// Default limit on the total size of all shadow repositories, in MB.
+const DEFAULT_SHADOW_REPOS_MAX_SIZE_MB: u64 = 1024;
+
+/// The file marking a directory as a shadow repository, so that only those are ever removed from
+/// the configurable `chat.checkpoint.dir`.
+const SHADOW_REPO_MARKER: &str = ".q-shadow-repo";
+
+/// How long a shadow repository in the legacy location is left alone, in case a session of an
+/// older version still uses it.
+const LEGACY_SHADOW_REPO_GRACE: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
 
 /// Manages a shadow git repository for tracking and restoring workspace changes
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -104,14 +124,24 @@ impl CheckpointManager {
         path: impl AsRef<Path>,
         current_history: &VecDeque<HistoryEntry>,
     ) -> Result<Self> {
-        let path = path.as_ref();
-        os.fs.create_dir_all(path).await?;
-
         let work_tree_path =
             std::env::current_dir().map_err(|e| eyre!("Failed to get current working directory: {}", e))?;
+        Self::init_with_work_tree(os, path, work_tree_path, current_history).await
+    }
+
+    /// Initialize checkpoint manager tracking `work_tree_path` instead of the current directory
+    pub async fn init_with_work_tree(
+        os: &Os,
+        path: impl AsRef<Path>,
+        work_tree_path: PathBuf,
+        current_history: &VecDeque<HistoryEntry>,
+    ) -> Result<Self> {
+        let path = path.as_ref();
+        os.fs.create_dir_all(path).await?;
 
         // Initialize bare repository
         run_git(path, None, &["init", "--bare", &path.to_string_lossy()])?;
+        os.fs.write(path.join(SHADOW_REPO_MARKER), "").await?;
 
         // Configure git
         configure_git(&path.to_string_lossy())?;
@@ -242,6 +272,180 @@ impl CheckpointManager {
         Ok(())
     }
 
+    /// Restore only `paths` to their state in a specific checkpoint, leaving the rest of the
+    /// work-tree and the conversation history untouched. Paths that did not exist in the
+    /// checkpoint are removed.
+    ///
+    /// Unless `force` is set, fails without changing anything if any of the paths were modified
+    /// since the most recent checkpoint, since those changes would otherwise be lost.
+    pub fn restore_paths(&self, tag: &str, paths: &[String], force: bool) -> Result<Vec<String>> {
+        self.get_checkpoint(tag)?;
+        let paths = paths
+            .iter()
+            .map(|path| self.work_tree_relative_path(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        if !force {
+            let conflicts = self.changed_since_last_checkpoint(&paths)?;
+            if !conflicts.is_empty() {
+                bail!(
+                    "The following files were modified since the last checkpoint: {}. Use --force to overwrite them.",
+                    conflicts.join(", ")
+                );
+            }
+        }
+
+        for path in &paths {
+            if self.tag_has_path(tag, path)? {
+                run_git(&self.shadow_repo_path, Some(&self.work_tree_path), &[
+                    "checkout", tag, "--", path,
+                ])?;
+            } else {
+                let full_path = self.work_tree_path.join(path);
+                if full_path.is_dir() {
+                    std::fs::remove_dir_all(&full_path)?;
+                } else if full_path.exists() {
+                    std::fs::remove_file(&full_path)?;
+                } else {
+                    bail!("'{}' does not exist in checkpoint {}", path, tag);
+                }
+            }
+        }
+
+        Ok(paths)
+    }
+
+    /// Returns the turn checkpoint of the most recent turn that changed files, if any.
+    pub fn last_turn_checkpoint(&self) -> Option<&Checkpoint> {
+        let turn = self.last_turn()?;
+        self.get_checkpoint(&turn.to_string()).ok()
+    }
+
+    /// Reverts the file changes of the most recent turn that changed files and removes its
+    /// checkpoints, so that the next turn reuses its number. Returns the restored paths.
+    ///
+    /// Unless `force` is set, fails without changing anything if any of those files were
+    /// modified since the most recent checkpoint.
+    pub fn undo_last_turn(&mut self, force: bool) -> Result<Vec<String>> {
+        let Some(turn) = self.last_turn() else {
+            bail!("No turn has changed any files");
+        };
+        let previous = (turn - 1).to_string();
+        let output = run_git(&self.shadow_repo_path, None, &[
+            "diff",
+            "--name-only",
+            "--no-renames",
+            &previous,
+            &turn.to_string(),
+        ])?;
+        let paths = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
+
+        let restored = if paths.is_empty() {
+            paths
+        } else {
+            self.restore_paths(&previous, &paths, force)?
+        };
+
+        self.checkpoints
+            .retain(|checkpoint| turn_of_tag(&checkpoint.tag) != Some(turn));
+        self.tag_index = self
+            .checkpoints
+            .iter()
+            .enumerate()
+            .map(|(i, checkpoint)| (checkpoint.tag.clone(), i))
+            .collect();
+        self.file_stats_cache.retain(|tag, _| turn_of_tag(tag) != Some(turn));
+        self.current_turn = turn - 1;
+        self.tools_in_turn = 0;
+        self.pending_user_message = None;
+        self.message_locked = false;
+
+        Ok(restored)
+    }
+
+    /// The number of the most recent turn with checkpoints, excluding the initial state.
+    fn last_turn(&self) -> Option<usize> {
+        self.checkpoints
+            .iter()
+            .filter_map(|checkpoint| turn_of_tag(&checkpoint.tag))
+            .max()
+            .filter(|turn| *turn > 0)
+    }
+
+    /// Returns the paths among `paths` whose contents differ from the most recent checkpoint.
+    fn changed_since_last_checkpoint(&self, paths: &[String]) -> Result<Vec<String>> {
+        let Some(latest) = self.checkpoints.last() else {
+            return Ok(Vec::new());
+        };
+
+        let mut args = vec!["diff", "--name-only", latest.tag.as_str(), "--"];
+        args.extend(paths.iter().map(|p| p.as_str()));
+        let output = run_git(&self.shadow_repo_path, Some(&self.work_tree_path), &args)?;
+        let mut changed = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
+
+        // Files created after the last checkpoint aren't known to `git diff`.
+        for path in paths {
+            if self.work_tree_path.join(path).exists()
+                && !self.tag_has_path(&latest.tag, path)?
+                && !changed.contains(path)
+            {
+                changed.push(path.clone());
+            }
+        }
+
+        Ok(changed)
+    }
+
+    /// Converts `path` to a path relative to the work-tree. Paths that could point outside of it,
+    /// i.e. ones with `..` components, and paths naming the work-tree itself are rejected.
+    fn work_tree_relative_path(&self, path: &str) -> Result<String> {
+        let path = Path::new(path);
+        let relative = if path.is_absolute() {
+            path.strip_prefix(&self.work_tree_path)
+                .map_err(|_err| eyre!("'{}' is outside of {}", path.display(), self.work_tree_path.display()))?
+        } else {
+            path
+        };
+
+        let mut components = Vec::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(name) => components.push(name.to_string_lossy()),
+                Component::CurDir => {},
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    bail!("'{}' is outside of {}", path.display(), self.work_tree_path.display())
+                },
+            }
+        }
+        if components.is_empty() {
+            bail!(
+                "'{}' does not name a file in {}",
+                path.display(),
+                self.work_tree_path.display()
+            );
+        }
+        Ok(components.join("/"))
+    }
+
+    /// Return true iff `path` is tracked in the given tag/tree.
+    fn tag_has_path(&self, tag: &str, path: &str) -> Result<bool> {
+        let out = run_git(&self.shadow_repo_path, None, &[
+            "ls-tree",
+            "-r",
+            "--name-only",
+            tag,
+            "--",
+            path,
+        ])?;
+        Ok(!out.stdout.is_empty())
+    }
+
     /// Return true iff the given tag/tree has any tracked paths.
     fn tag_has_any_paths(&self, tag: &str) -> eyre::Result<bool> {
         // Use `git ls-tree -r --name-only <tag>` to check if the tree is empty
@@ -284,12 +488,15 @@ impl CheckpointManager {
         Ok(stats)
     }
 
-    /// Generate detailed diff between checkpoints
-    pub fn diff(&self, from: &str, to: &str) -> Result<String> {
+    /// Generate a colorized diff between two checkpoints, or between a checkpoint and the working
+    /// tree if `to` is [None].
+    ///
+    /// With `stat_only`, only the changed files and the diffstat are included.
+    pub fn diff(&self, from: &str, to: Option<&str>, stat_only: bool) -> Result<String> {
         let mut result = String::new();
 
         // Get file changes
-        let output = run_git(&self.shadow_repo_path, None, &["diff", "--name-status", from, to])?;
+        let output = self.run_diff(from, to, &["--name-status"])?;
 
         for line in String::from_utf8_lossy(&output.stdout).lines() {
             if let Some((status, file)) = line.split_once('\t') {
@@ -304,22 +511,45 @@ impl CheckpointManager {
         }
 
         // Add statistics
-        let stat_output = run_git(&self.shadow_repo_path, None, &[
-            "diff",
-            from,
-            to,
-            "--stat",
-            "--color=always",
-        ])?;
+        let stat_output = self.run_diff(from, to, &["--stat", "--color=always"])?;
 
         if stat_output.status.success() {
             result.push('\n');
             result.push_str(&String::from_utf8_lossy(&stat_output.stdout));
         }
 
+        if !stat_only {
+            let patch_output = self.run_diff(from, to, &["--color=always"])?;
+            if !patch_output.stdout.is_empty() {
+                result.push('\n');
+                result.push_str(&String::from_utf8_lossy(&patch_output.stdout));
+            }
+        }
+
         Ok(result)
     }
 
+    /// Runs `git diff` with `args` between `from` and `to`, or between `from` and the working tree
+    /// if `to` is [None].
+    fn run_diff(&self, from: &str, to: Option<&str>, args: &[&str]) -> Result<Output> {
+        match to {
+            Some(to) => {
+                let mut diff_args = vec!["diff"];
+                diff_args.extend_from_slice(args);
+                diff_args.extend([from, to]);
+                run_git(&self.shadow_repo_path, None, &diff_args)
+            },
+            None => {
+                // Stage the working tree in the shadow index so that new files are included.
+                run_git(&self.shadow_repo_path, Some(&self.work_tree_path), &["add", "-A"])?;
+                let mut diff_args = vec!["diff", "--cached"];
+                diff_args.extend_from_slice(args);
+                diff_args.push(from);
+                run_git(&self.shadow_repo_path, Some(&self.work_tree_path), &diff_args)
+            },
+        }
+    }
+
     /// Check for uncommitted changes
     pub fn has_changes(&self) -> Result<bool> {
         let output = run_git(&self.shadow_repo_path, Some(&self.work_tree_path), &[
@@ -362,6 +592,144 @@ impl Drop for CheckpointManager {
     }
 }
 
+/// Removes the least recently modified shadow repositories until the total size of all shadow
+/// repositories is under the limit set by `chat.checkpoint.maxSizeMb`. The repository at `keep`
+/// is never removed.
+///
+/// Shadow repositories are normally removed when the session ends, this cleans up the ones left
+/// behind by sessions that did not exit cleanly, including those in the legacy location.
+pub fn gc_shadow_repos(os: &Os, keep: &Path) {
+    let max_mb = match os.database.settings.get_int(Setting::ChatCheckpointMaxSizeMb) {
+        Some(mb) => u64::try_from(mb).ok().filter(|mb| *mb > 0).unwrap_or_else(|| {
+            warn!(
+                mb,
+                "chat.checkpoint.maxSizeMb must be a positive number, using the default"
+            );
+            DEFAULT_SHADOW_REPOS_MAX_SIZE_MB
+        }),
+        None => DEFAULT_SHADOW_REPOS_MAX_SIZE_MB,
+    };
+    let max_bytes = max_mb.saturating_mul(1024 * 1024);
+
+    match legacy_shadow_repos_dir(os).map(|root| remove_legacy_repos(&root)) {
+        Ok(Ok(removed)) if !removed.is_empty() => debug!(?removed, "removed legacy shadow repos"),
+        Ok(Ok(_)) => (),
+        Ok(Err(err)) => warn!(?err, "failed to remove legacy shadow repos"),
+        Err(err) => warn!(?err, "failed to get the legacy shadow repos directory"),
+    }
+
+    let root = match shadow_repos_dir(os) {
+        Ok(root) => root,
+        Err(err) => {
+            warn!(?err, "failed to get the shadow repos directory");
+            return;
+        },
+    };
+
+    match remove_oldest_repos(&root, keep, max_bytes) {
+        Ok(removed) if !removed.is_empty() => debug!(?removed, "removed old shadow repos"),
+        Ok(_) => (),
+        Err(err) => warn!(?err, "failed to garbage collect shadow repos"),
+    }
+}
+
+/// Whether `path` is a shadow repository created by [CheckpointManager].
+pub fn is_shadow_repo(path: &Path) -> bool {
+    path.join(SHADOW_REPO_MARKER).is_file()
+}
+
+/// Shadow repositories are laid out as `<root>/<workspace>/<conversation id>`. Directories
+/// without the [SHADOW_REPO_MARKER] are not shadow repositories and are left alone.
+fn remove_oldest_repos(root: &Path, keep: &Path, max_bytes: u64) -> Result<Vec<PathBuf>> {
+    let mut repos = Vec::new();
+    if !root.exists() {
+        return Ok(repos);
+    }
+
+    for workspace in std::fs::read_dir(root)?.flatten() {
+        if !workspace.path().is_dir() {
+            continue;
+        }
+        for repo in std::fs::read_dir(workspace.path())?.flatten() {
+            let path = repo.path();
+            if !is_shadow_repo(&path) {
+                continue;
+            }
+            let (size, modified) = dir_size_and_modified(&path);
+            repos.push((path, size, modified));
+        }
+    }
+
+    let mut total: u64 = repos.iter().map(|(_, size, _)| size).sum();
+    repos.sort_by_key(|(_, _, modified)| *modified);
+
+    let mut removed = Vec::new();
+    for (path, size, _) in repos {
+        if total <= max_bytes {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+        std::fs::remove_dir_all(&path)?;
+        total = total.saturating_sub(size);
+        if let Some(workspace) = path.parent() {
+            // Only succeeds once the workspace has no repos left.
+            let _ = std::fs::remove_dir(workspace);
+        }
+        removed.push(path);
+    }
+
+    Ok(removed)
+}
+
+/// Removes the shadow repositories older versions left in the legacy location,
+/// `<legacy root>/<conversation id>`, once they have not been used for a day. That location is
+/// not configurable, and only bare git repositories in it are removed.
+fn remove_legacy_repos(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    if !root.exists() {
+        return Ok(removed);
+    }
+
+    for repo in std::fs::read_dir(root)?.flatten() {
+        let path = repo.path();
+        if !path.join("HEAD").is_file() || !path.join("objects").is_dir() {
+            continue;
+        }
+        let (_, modified) = dir_size_and_modified(&path);
+        if modified
+            .elapsed()
+            .is_ok_and(|elapsed| elapsed > LEGACY_SHADOW_REPO_GRACE)
+        {
+            std::fs::remove_dir_all(&path)?;
+            removed.push(path);
+        }
+    }
+    // Only succeeds once no repos are left.
+    let _ = std::fs::remove_dir(root);
+
+    Ok(removed)
+}
+
+/// Total size of the files in `dir` and the most recent modification time among them.
+pub fn dir_size_and_modified(dir: &Path) -> (u64, std::time::SystemTime) {
+    let mut size = 0;
+    let mut modified = std::time::SystemTime::UNIX_EPOCH;
+    for entry in walkdir::WalkDir::new(dir).into_iter().flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_file() {
+            size += metadata.len();
+            if let Ok(time) = metadata.modified() {
+                modified = modified.max(time);
+            }
+        }
+    }
+    (size, modified)
+}
+
 // Helper functions
 
 /// Truncate message for display
@@ -380,7 +748,7 @@ pub fn truncate_message(s: &str, max_len: usize) -> String {
 
 pub const CHECKPOINT_MESSAGE_MAX_LENGTH: usize = 60;
 
-fn is_git_installed() -> bool {
+pub fn is_git_installed() -> bool {
     Command::new("git")
         .arg("--version")
         .output()
@@ -467,6 +835,11 @@ fn run_git(dir: &Path, work_tree: Option<&Path>, args: &[&str]) -> Result<Output
     Ok(output)
 }
 
+/// Parses the turn number of a tag, e.g. 3 for both "3" and "3.1".
+fn turn_of_tag(tag: &str) -> Option<usize> {
+    tag.split_once('.').map_or(tag, |(turn, _)| turn).parse().ok()
+}
+
 fn get_previous_tag(tag: &str) -> String {
     // Parse turn.tool format
     if let Some((turn_str, tool_str)) = tag.split_once('.') {
@@ -486,3 +859,219 @@ fn get_previous_tag(tag: &str) -> String {
 
     "0".to_string()
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::time::{
+        Duration,
+        SystemTime,
+    };
+
+    use super::*;
+
+    fn write_aged(file: &Path, size: usize, age_secs: u64) {
+        std::fs::write(file, vec![0; size]).unwrap();
+        File::options()
+            .write(true)
+            .open(file)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(age_secs))
+            .unwrap();
+    }
+
+    fn create_repo(root: &Path, workspace: &str, conversation_id: &str, size: usize, age_secs: u64) -> PathBuf {
+        let repo = root.join(workspace).join(conversation_id);
+        std::fs::create_dir_all(&repo).unwrap();
+        write_aged(&repo.join(SHADOW_REPO_MARKER), 0, age_secs);
+        write_aged(&repo.join("objects"), size, age_secs);
+        repo
+    }
+
+    #[test]
+    fn test_remove_oldest_repos() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let oldest = create_repo(root, "a-1234", "1", 100, 300);
+        let kept = create_repo(root, "b-5678", "2", 100, 200);
+        let newest = create_repo(root, "b-5678", "3", 100, 100);
+        // Directories that are not shadow repos are never removed.
+        let unrelated = root.join("c-9012").join("4");
+        std::fs::create_dir_all(&unrelated).unwrap();
+        write_aged(&unrelated.join("data"), 1000, 400);
+
+        // Nothing to do when under the limit.
+        assert!(remove_oldest_repos(root, &kept, 300).unwrap().is_empty());
+
+        // The current repo is never removed, even if it is old.
+        let removed = remove_oldest_repos(root, &kept, 100).unwrap();
+        assert_eq!(removed, vec![oldest.clone(), newest.clone()]);
+        assert!(!oldest.exists());
+        assert!(!root.join("a-1234").exists());
+        assert!(kept.exists());
+        assert!(!newest.exists());
+        assert!(unrelated.exists());
+    }
+
+    #[test]
+    fn test_remove_legacy_repos() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let legacy_repo = |name: &str, age_secs: u64| {
+            let repo = root.join(name);
+            std::fs::create_dir_all(repo.join("objects")).unwrap();
+            write_aged(&repo.join("HEAD"), 10, age_secs);
+            repo
+        };
+        let stale = legacy_repo("1", 2 * 24 * 60 * 60);
+        let recent = legacy_repo("2", 60);
+        let unrelated = root.join("notes");
+        std::fs::create_dir_all(&unrelated).unwrap();
+
+        assert_eq!(remove_legacy_repos(root).unwrap(), vec![stale.clone()]);
+        assert!(!stale.exists());
+        assert!(recent.exists());
+        assert!(unrelated.exists());
+    }
+
+    fn checkpoint(tag: &str) -> Checkpoint {
+        Checkpoint {
+            tag: tag.to_string(),
+            timestamp: Local::now(),
+            description: String::new(),
+            history_snapshot: VecDeque::new(),
+            is_turn: true,
+            tool_name: None,
+        }
+    }
+
+    #[test]
+    fn test_restore_paths() {
+        if !is_git_installed() {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let shadow = dir.path().join("shadow");
+        let work_tree = dir.path().join("work");
+        std::fs::create_dir_all(&work_tree).unwrap();
+        run_git(&shadow, None, &["init", "--bare", &shadow.to_string_lossy()]).unwrap();
+        configure_git(&shadow.to_string_lossy()).unwrap();
+
+        std::fs::write(work_tree.join("a.txt"), "a0").unwrap();
+        std::fs::write(work_tree.join("b.txt"), "b0").unwrap();
+        stage_commit_tag(&shadow.to_string_lossy(), &work_tree, "0", "0").unwrap();
+        std::fs::write(work_tree.join("a.txt"), "a1").unwrap();
+        std::fs::write(work_tree.join("b.txt"), "b1").unwrap();
+        std::fs::write(work_tree.join("c.txt"), "c1").unwrap();
+        stage_commit_tag(&shadow.to_string_lossy(), &work_tree, "1", "1").unwrap();
+
+        let manager = CheckpointManager {
+            shadow_repo_path: shadow,
+            work_tree_path: work_tree.clone(),
+            checkpoints: vec![checkpoint("0"), checkpoint("1")],
+            tag_index: HashMap::from([("0".to_string(), 0), ("1".to_string(), 1)]),
+            current_turn: 1,
+            tools_in_turn: 0,
+            pending_user_message: None,
+            message_locked: false,
+            file_stats_cache: HashMap::new(),
+        };
+
+        // Only the requested files are restored, and files newer than the checkpoint are removed.
+        let restored = manager
+            .restore_paths("0", &["a.txt".to_string(), "./c.txt".to_string()], false)
+            .unwrap();
+        assert_eq!(restored, vec!["a.txt".to_string(), "c.txt".to_string()]);
+        assert_eq!(std::fs::read_to_string(work_tree.join("a.txt")).unwrap(), "a0");
+        assert_eq!(std::fs::read_to_string(work_tree.join("b.txt")).unwrap(), "b1");
+        assert!(!work_tree.join("c.txt").exists());
+
+        // Changes made since the last checkpoint are detected as conflicts.
+        std::fs::write(work_tree.join("b.txt"), "b2").unwrap();
+        let err = manager.restore_paths("0", &["b.txt".to_string()], false).unwrap_err();
+        assert!(err.to_string().contains("b.txt"));
+        assert_eq!(std::fs::read_to_string(work_tree.join("b.txt")).unwrap(), "b2");
+
+        manager.restore_paths("0", &["b.txt".to_string()], true).unwrap();
+        assert_eq!(std::fs::read_to_string(work_tree.join("b.txt")).unwrap(), "b0");
+
+        // Paths outside of the work-tree, or naming all of it, are rejected.
+        for path in ["../a.txt", "sub/../../a.txt", "", ".", "./"] {
+            assert!(
+                manager.restore_paths("0", &[path.to_string()], true).is_err(),
+                "{path:?}"
+            );
+        }
+        let outside = dir.path().join("a.txt").to_string_lossy().to_string();
+        assert!(manager.restore_paths("0", &[outside], true).is_err());
+        assert_eq!(
+            manager.work_tree_relative_path("./sub/./a.txt").unwrap(),
+            "sub/a.txt".to_string()
+        );
+    }
+
+    #[test]
+    fn test_undo_last_turn() {
+        if !is_git_installed() {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let shadow = dir.path().join("shadow");
+        let work_tree = dir.path().join("work");
+        std::fs::create_dir_all(&work_tree).unwrap();
+        run_git(&shadow, None, &["init", "--bare", &shadow.to_string_lossy()]).unwrap();
+        configure_git(&shadow.to_string_lossy()).unwrap();
+
+        std::fs::write(work_tree.join("a.txt"), "a0").unwrap();
+        std::fs::write(work_tree.join("b.txt"), "b0").unwrap();
+        stage_commit_tag(&shadow.to_string_lossy(), &work_tree, "0", "0").unwrap();
+        std::fs::write(work_tree.join("a.txt"), "a1").unwrap();
+        stage_commit_tag(&shadow.to_string_lossy(), &work_tree, "1", "1").unwrap();
+        std::fs::write(work_tree.join("a.txt"), "a2").unwrap();
+        std::fs::remove_file(work_tree.join("b.txt")).unwrap();
+        stage_commit_tag(&shadow.to_string_lossy(), &work_tree, "2.1", "2.1").unwrap();
+        std::fs::write(work_tree.join("c.txt"), "c2").unwrap();
+        stage_commit_tag(&shadow.to_string_lossy(), &work_tree, "2", "2").unwrap();
+
+        let mut manager = CheckpointManager {
+            shadow_repo_path: shadow,
+            work_tree_path: work_tree.clone(),
+            checkpoints: vec![checkpoint("0"), checkpoint("1"), checkpoint("2.1"), checkpoint("2")],
+            tag_index: HashMap::from([
+                ("0".to_string(), 0),
+                ("1".to_string(), 1),
+                ("2.1".to_string(), 2),
+                ("2".to_string(), 3),
+            ]),
+            current_turn: 2,
+            tools_in_turn: 0,
+            pending_user_message: None,
+            message_locked: false,
+            file_stats_cache: HashMap::new(),
+        };
+        assert_eq!(manager.last_turn_checkpoint().unwrap().tag, "2");
+
+        let mut restored = manager.undo_last_turn(false).unwrap();
+        restored.sort();
+        assert_eq!(restored, vec!["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(std::fs::read_to_string(work_tree.join("a.txt")).unwrap(), "a1");
+        assert_eq!(std::fs::read_to_string(work_tree.join("b.txt")).unwrap(), "b0");
+        assert!(!work_tree.join("c.txt").exists());
+
+        // The turn's checkpoints are forgotten so the next turn reuses its number.
+        assert_eq!(manager.current_turn, 1);
+        assert_eq!(manager.checkpoints.len(), 2);
+        assert_eq!(manager.tag_index.get("1"), Some(&1));
+        assert_eq!(manager.last_turn_checkpoint().unwrap().tag, "1");
+    }
+
+    #[test]
+    fn test_turn_of_tag() {
+        assert_eq!(turn_of_tag("0"), Some(0));
+        assert_eq!(turn_of_tag("3"), Some(3));
+        assert_eq!(turn_of_tag("3.12"), Some(3));
+        assert_eq!(turn_of_tag("abc"), None);
+    }
+}
@@ -0,0 +1,57 @@
+use clap::Args;
+use crossterm::style::Color;
+use crossterm::{
+    execute,
+    style,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+use crate::telemetry::core::CompactionFeedbackArgs;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "/bad reports that the last response was poor, for example because the assistant forgot
+something it was told earlier in the conversation.
+
+Shortly after the conversation has been compacted, this is used to measure how well the summary
+preserved the context. No conversation content is sent."
+)]
+pub struct BadArgs {}
+
+impl BadArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        if let Some(compaction) = session.recent_compaction.take() {
+            os.telemetry
+                .send_compaction_feedback(
+                    &os.database,
+                    session.conversation.conversation_id().to_string(),
+                    CompactionFeedbackArgs {
+                        request_id: compaction.request_id,
+                        prompt_variant: compaction.prompt_variant,
+                        turns_since_compaction: compaction.turns as i64,
+                    },
+                )
+                .await
+                .ok();
+        }
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print("\nThanks for the feedback!"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(" Use /issue to tell us more about what went wrong.\n\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
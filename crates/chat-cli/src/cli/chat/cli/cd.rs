@@ -0,0 +1,157 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use clap::Args;
+use crossterm::style::{
+    self,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use tracing::warn;
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::directories::canonicalizes_path;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "The working directory of a chat session is pinned when it starts: changing directories
+in another shell has no effect on it. /cd changes it for the rest of the session, which affects
+the commands run by execute_bash, how paths are displayed, and where context files are looked up.
+
+Only the directory chat was started in and the directories listed in the chat.cdAllowedRoots
+setting, along with their subdirectories, can be changed to. The conversation is still saved
+under the directory chat was started in."
+)]
+pub struct CdArgs {
+    /// Directory to change to. Prints the working directory if omitted
+    pub path: Option<String>,
+}
+
+impl CdArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let Some(path) = self.path else {
+            let cwd = os.env.current_dir()?;
+            queue!(session.stderr, style::Print(format!("\n{}\n", cwd.display())))?;
+            if let Some(pinned_dir) = session.conversation.pinned_dir.as_ref().filter(|dir| **dir != cwd) {
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("Started in {}\n", pinned_dir.display())),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            }
+            execute!(session.stderr, style::Print("\n"))?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        let target = resolve_target(os, &path, session.conversation.pinned_dir.as_deref())
+            .map_err(|err| ChatError::Custom(err.into()))?;
+        os.env.set_current_dir(&target)?;
+        if let Some(registration) = &session.agent_registration {
+            registration.set_cwd(&target);
+        }
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("\n✓ Working directory: {}\n\n", target.display())),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Resolves `path` against the working directory, and checks that it is a directory within one of
+/// the allowed roots.
+fn resolve_target(os: &Os, path: &str, pinned_dir: Option<&Path>) -> Result<PathBuf, String> {
+    let target = canonicalizes_path(os, path)
+        .map(PathBuf::from)
+        .map_err(|err| format!("Invalid path '{path}': {err}"))?;
+    if !target.is_dir() {
+        return Err(format!("{} is not a directory", target.display()));
+    }
+
+    let roots = allowed_roots(os, pinned_dir);
+    if !roots.iter().any(|root| target.starts_with(root)) {
+        return Err(format!(
+            "{} is outside of the allowed directories. Add it to {} to allow changing to it.",
+            target.display(),
+            Setting::ChatCdAllowedRoots.as_ref()
+        ));
+    }
+
+    Ok(target)
+}
+
+fn allowed_roots(os: &Os, pinned_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut roots = pinned_dir
+        .and_then(|dir| dir.canonicalize().ok())
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    if let Some(value) = os.database.settings.get(Setting::ChatCdAllowedRoots) {
+        match serde_json::from_value::<Vec<String>>(value.clone()) {
+            Ok(paths) => roots.extend(
+                paths
+                    .iter()
+                    .filter_map(|path| canonicalizes_path(os, path).ok())
+                    .map(PathBuf::from),
+            ),
+            Err(err) => warn!(?err, "ignoring invalid {}", Setting::ChatCdAllowedRoots.as_ref()),
+        }
+    }
+
+    roots
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve_target() {
+        let mut os = Os::new().await.unwrap();
+        let pinned = tempfile::tempdir().unwrap();
+        let other = tempfile::tempdir().unwrap();
+        std::fs::create_dir(pinned.path().join("sub")).unwrap();
+        std::fs::write(pinned.path().join("file"), "").unwrap();
+        let pinned_dir = pinned.path().canonicalize().unwrap();
+        let other_dir = other.path().canonicalize().unwrap();
+
+        let sub = pinned_dir.join("sub");
+        assert_eq!(
+            resolve_target(&os, &sub.to_string_lossy(), Some(&pinned_dir)).unwrap(),
+            sub
+        );
+        assert!(resolve_target(&os, &pinned_dir.join("file").to_string_lossy(), Some(&pinned_dir)).is_err());
+        assert!(resolve_target(&os, &pinned_dir.join("missing").to_string_lossy(), Some(&pinned_dir)).is_err());
+        assert!(resolve_target(&os, &other_dir.to_string_lossy(), Some(&pinned_dir)).is_err());
+
+        os.database
+            .settings
+            .set(Setting::ChatCdAllowedRoots, serde_json::json!([other_dir]))
+            .await
+            .unwrap();
+        assert_eq!(
+            resolve_target(&os, &other_dir.to_string_lossy(), Some(&pinned_dir)).unwrap(),
+            other_dir
+        );
+    }
+}
@@ -17,6 +17,7 @@ use crate::cli::chat::checkpoint::{
     Checkpoint,
     CheckpointManager,
     FileStats,
+    gc_shadow_repos,
 };
 use crate::cli::chat::{
     ChatError,
@@ -29,6 +30,10 @@ use crate::cli::experiment::experiment_manager::{
 };
 use crate::os::Os;
 use crate::util::directories::get_shadow_repo_dir;
+use crate::util::time::{
+    format_timestamp,
+    humanize,
+};
 
 #[derive(Debug, PartialEq, Subcommand)]
 pub enum CheckpointSubcommand {
@@ -46,15 +51,28 @@ Default mode:
 
 With --hard:
   • Exactly matches the checkpoint state
-  • Removes files created after the checkpoint"#
+  • Removes files created after the checkpoint
+
+With --path <file>:
+  • Only restores the given files, e.g. /checkpoint restore 3 --path src/main.rs
+  • Keeps the conversation history and all other files as they are
+  • Refuses to overwrite files modified since the last checkpoint unless --force is given"#
     )]
     Restore {
         /// Checkpoint tag (e.g., 3 or 3.1). Leave empty to select interactively.
         tag: Option<String>,
 
         /// Exactly match checkpoint state (removes newer files)
-        #[arg(long)]
+        #[arg(long, conflicts_with = "paths")]
         hard: bool,
+
+        /// Only restore these files (can be repeated)
+        #[arg(long = "path", value_name = "FILE")]
+        paths: Vec<String>,
+
+        /// Overwrite files modified since the last checkpoint when restoring with --path
+        #[arg(long, requires = "paths")]
+        force: bool,
     },
 
     /// List all checkpoints
@@ -74,13 +92,25 @@ With --hard:
     },
 
     /// Show differences between checkpoints
+    #[command(
+        about = "Show differences between checkpoints",
+        long_about = r#"Show a colorized diff between two checkpoints, e.g. /checkpoint diff 2 5.
+If <tag2> is omitted, <tag1> is compared against the current working tree.
+
+With --stat:
+  • Only lists the changed files and the diffstat"#
+    )]
     Diff {
         /// First checkpoint tag
         tag1: String,
 
-        /// Second checkpoint tag (defaults to current state)
+        /// Second checkpoint tag (defaults to the current working tree)
         #[arg(required = false)]
         tag2: Option<String>,
+
+        /// Only show the changed files and the diffstat
+        #[arg(long)]
+        stat: bool,
     },
 }
 
@@ -115,11 +145,26 @@ impl CheckpointSubcommand {
         }
         match self {
             Self::Init => self.handle_init(os, session).await,
-            Self::Restore { ref tag, hard } => self.handle_restore(session, tag.clone(), hard).await,
+            Self::Restore {
+                ref tag,
+                hard,
+                ref paths,
+                force,
+            } => {
+                if paths.is_empty() {
+                    self.handle_restore(session, tag.clone(), hard).await
+                } else {
+                    Self::handle_restore_paths(session, tag.clone(), paths, force)
+                }
+            },
             Self::List { limit } => Self::handle_list(session, limit),
             Self::Clean => self.handle_clean(os, session).await,
             Self::Expand { ref tag } => Self::handle_expand(session, tag.clone()),
-            Self::Diff { ref tag1, ref tag2 } => Self::handle_diff(session, tag1.clone(), tag2.clone()),
+            Self::Diff {
+                ref tag1,
+                ref tag2,
+                stat,
+            } => Self::handle_diff(session, tag1.clone(), tag2.clone(), stat),
         }
     }
 
@@ -136,6 +181,7 @@ impl CheckpointSubcommand {
         } else {
             let path = get_shadow_repo_dir(os, session.conversation.conversation_id().to_string())
                 .map_err(|e| ChatError::Custom(e.to_string().into()))?;
+            gc_shadow_repos(os, &path);
 
             let start = std::time::Instant::now();
             session.conversation.checkpoint_manager = Some(
@@ -233,6 +279,61 @@ impl CheckpointSubcommand {
         })
     }
 
+    fn handle_restore_paths(
+        session: &mut ChatSession,
+        tag: Option<String>,
+        paths: &[String],
+        force: bool,
+    ) -> Result<ChatState, ChatError> {
+        let Some(manager) = session.conversation.checkpoint_manager.as_ref() else {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("⚠️ Checkpoints not enabled. Use '/checkpoint init' to enable.\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        let tag = match tag {
+            Some(tag) => tag,
+            None => {
+                let entries = gather_turn_checkpoints(manager)
+                    .map_err(|e| ChatError::Custom(format!("Failed to gather checkpoints: {}", e).into()))?;
+                match select_checkpoint(&entries, "Select checkpoint to restore files from:") {
+                    Some(idx) => entries[idx].tag.clone(),
+                    None => {
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                }
+            },
+        };
+
+        let restored = manager
+            .restore_paths(&tag, paths, force)
+            .map_err(|e| ChatError::Custom(format!("Failed to restore: {}", e).into()))?;
+
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Blue),
+            style::SetAttribute(Attribute::Bold),
+            style::Print(format!("✓ Restored from checkpoint {}:\n", tag)),
+            style::SetForegroundColor(Color::Reset),
+            style::SetAttribute(Attribute::Reset),
+        )?;
+        for path in restored {
+            execute!(session.stderr, style::Print(format!("  {}\n", path)))?;
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
     fn handle_list(session: &mut ChatSession, limit: Option<usize>) -> Result<ChatState, ChatError> {
         let Some(manager) = session.conversation.checkpoint_manager.as_ref() else {
             execute!(
@@ -314,7 +415,12 @@ impl CheckpointSubcommand {
         })
     }
 
-    fn handle_diff(session: &mut ChatSession, tag1: String, tag2: Option<String>) -> Result<ChatState, ChatError> {
+    fn handle_diff(
+        session: &mut ChatSession,
+        tag1: String,
+        tag2: Option<String>,
+        stat: bool,
+    ) -> Result<ChatState, ChatError> {
         let Some(manager) = session.conversation.checkpoint_manager.as_ref() else {
             execute!(
                 session.stderr,
@@ -327,8 +433,6 @@ impl CheckpointSubcommand {
             });
         };
 
-        let tag2 = tag2.unwrap_or_else(|| "HEAD".to_string());
-
         // Validate tags exist
         if tag1 != "HEAD" && !manager.tag_index.contains_key(&tag1) {
             execute!(
@@ -345,7 +449,10 @@ impl CheckpointSubcommand {
             });
         }
 
-        if tag2 != "HEAD" && !manager.tag_index.contains_key(&tag2) {
+        if let Some(tag2) = tag2
+            .as_ref()
+            .filter(|tag| *tag != "HEAD" && !manager.tag_index.contains_key(*tag))
+        {
             execute!(
                 session.stderr,
                 style::SetForegroundColor(Color::Yellow),
@@ -360,10 +467,9 @@ impl CheckpointSubcommand {
             });
         }
 
-        let header = if tag2 == "HEAD" {
-            format!("Changes since checkpoint {}:\n", tag1)
-        } else {
-            format!("Changes from {} to {}:\n", tag1, tag2)
+        let header = match &tag2 {
+            Some(tag2) => format!("Changes from {} to {}:\n", tag1, tag2),
+            None => format!("Changes since checkpoint {} in the working tree:\n", tag1),
         };
 
         execute!(
@@ -373,7 +479,7 @@ impl CheckpointSubcommand {
             style::SetForegroundColor(Color::Reset),
         )?;
 
-        match manager.diff(&tag1, &tag2) {
+        match manager.diff(&tag1, tag2.as_deref(), stat) {
             Ok(diff) => {
                 if diff.trim().is_empty() {
                     execute!(
@@ -416,8 +522,9 @@ impl CheckpointDisplay {
             // Turn checkpoint: show timestamp and description
             parts.push(
                 format!(
-                    "{} - {}",
-                    checkpoint.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                    "{} ({}) - {}",
+                    format_timestamp(&checkpoint.timestamp),
+                    humanize(&checkpoint.timestamp),
                     checkpoint.description
                 )
                 .reset(),
@@ -0,0 +1,105 @@
+use clap::Args;
+use crossterm::style::{
+    self,
+    Color,
+};
+use crossterm::{
+    cursor,
+    execute,
+    terminal,
+};
+use spinners::{
+    Spinner,
+    Spinners,
+};
+
+use crate::cli::chat::cli::editor::open_editor;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::cli::generate;
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Writes a conventional commit message for the staged changes with the model of the session,
+opens it in $EDITOR to review, and commits with it. Saving an empty message cancels the commit.
+
+The message is put into the chat.git.commitTemplate setting and given the Co-authored-by trailer
+of the chat.git.coAuthoredBy setting, when they are set. Outside of chat, q generate
+commit-message does the same."
+)]
+pub struct CommitArgs {
+    /// Commit without opening the message in $EDITOR first
+    #[arg(long)]
+    pub no_edit: bool,
+    /// Only show the message, without committing
+    #[arg(long, conflicts_with = "no_edit")]
+    pub dry_run: bool,
+}
+
+impl CommitArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let dir = os.env.current_dir()?;
+        let custom = |err: eyre::Report| ChatError::Custom(err.to_string().into());
+        let Some(diff) = generate::staged_diff(&dir).await.map_err(custom)? else {
+            return Err(ChatError::Custom(
+                "There are no staged changes, stage them with git add first".into(),
+            ));
+        };
+        let model_id = match session.conversation.model.clone() {
+            Some(model_id) => model_id,
+            None => generate::model_id(os, None).await.map_err(custom)?,
+        };
+
+        execute!(session.stderr, cursor::Hide, style::Print("\n"))?;
+        let spinner = Spinner::new(Spinners::Dots, "Writing a commit message...".to_string());
+        let message = generate::generate_commit_message(os, &model_id, &dir, &diff).await;
+        drop(spinner);
+        execute!(
+            session.stderr,
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            cursor::MoveToColumn(0),
+            cursor::Show
+        )?;
+        let mut message = message.map_err(custom)?;
+
+        if self.dry_run {
+            execute!(session.stderr, style::Print(format!("{message}\n\n")))?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+        if !self.no_edit {
+            message = open_editor(Some(message))?;
+        }
+        if message.is_empty() {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("The commit message is empty, nothing was committed.\n\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let output = generate::commit(os, &dir, &message).await.map_err(custom)?;
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print("✓ Committed\n"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("{}\n\n", output.trim_end())),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
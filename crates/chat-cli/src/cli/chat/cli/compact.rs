@@ -1,4 +1,9 @@
 use clap::Args;
+use strum::{
+    Display,
+    EnumString,
+};
+use tracing::warn;
 
 use crate::cli::chat::consts::MAX_USER_MESSAGE_SIZE;
 use crate::cli::chat::message::UserMessageContent;
@@ -7,8 +12,12 @@ use crate::cli::chat::{
     ChatSession,
     ChatState,
 };
+use crate::database::settings::Setting;
 use crate::os::Os;
 
+/// Number of user turns after a compaction during which `/bad` is reported as compaction feedback.
+pub const COMPACTION_FEEDBACK_TURNS: usize = 3;
+
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
 #[command(
@@ -99,3 +108,36 @@ impl Default for CompactStrategy {
         }
     }
 }
+
+/// The summarization prompt used for compaction, selected with
+/// [Setting::ChatCompactionPromptVariant] so that prompt changes can be compared with telemetry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumString, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum CompactionPromptVariant {
+    #[default]
+    Default,
+    /// Prioritizes the state of the current task over the topics that were discussed.
+    TaskFocused,
+}
+
+impl CompactionPromptVariant {
+    pub fn from_settings(os: &Os) -> Self {
+        match os.database.settings.get_string(Setting::ChatCompactionPromptVariant) {
+            Some(value) => value.parse().unwrap_or_else(|_| {
+                warn!(%value, "unknown compaction prompt variant, using the default");
+                Self::default()
+            }),
+            None => Self::default(),
+        }
+    }
+}
+
+/// The most recent compaction, kept for [COMPACTION_FEEDBACK_TURNS] user turns to detect whether
+/// the model lost context.
+#[derive(Debug, Clone)]
+pub struct RecentCompaction {
+    pub request_id: Option<String>,
+    pub prompt_variant: String,
+    /// User turns since the compaction.
+    pub turns: usize,
+}
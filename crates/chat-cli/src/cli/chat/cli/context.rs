@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::io::Write;
 
 use clap::Subcommand;
 use crossterm::style::{
@@ -13,7 +14,10 @@ use crossterm::{
 use crate::cli::chat::consts::AGENT_FORMAT_HOOKS_DOC_URL;
 use crate::cli::chat::context::{
     ContextFilePath,
-    calc_max_context_files_size,
+    ContextManager,
+    calc_context_files_budget,
+    max_file_tokens,
+    truncate_head_tail,
 };
 use crate::cli::chat::token_counter::TokenCounter;
 use crate::cli::chat::util::drop_matched_context_files;
@@ -43,11 +47,17 @@ pub enum ContextSubcommand {
         #[arg(long)]
         expand: bool,
     },
-    /// Add context rules (filenames or glob patterns)
+    /// Add context rules (filenames, directories, or glob patterns such as src/**/*.rs)
     Add {
         /// Include even if matched files exceed size limits
         #[arg(short, long)]
         force: bool,
+        /// Maximum number of tokens the files matched by each rule may use
+        #[arg(short, long, value_name = "TOKENS")]
+        budget: Option<usize>,
+        /// Report when the set of files matched by the rules changes
+        #[arg(short, long)]
+        watch: bool,
         #[arg(required = true)]
         /// Paths or glob patterns to remove from context rules
         paths: Vec<String>,
@@ -92,6 +102,10 @@ impl ContextSubcommand {
                     .iter()
                     .partition::<Vec<_>, _>(|p| matches!(**p, ContextFilePath::Agent(_)));
 
+                if let Some(config) = &session.conversation.agents.workspace_config {
+                    config.print(&mut session.stderr)?;
+                }
+
                 execute!(
                     session.stderr,
                     style::SetAttribute(Attribute::Bold),
@@ -124,6 +138,7 @@ impl ContextSubcommand {
                                 )),
                                 style::SetForegroundColor(Color::Reset)
                             )?;
+                            print_rule_annotations(&mut session.stderr, context_manager, path.get_path_as_str())?;
                             profile_context_files
                                 .extend(context_files.into_iter().map(|(path, content)| (path, content, false)));
                         }
@@ -164,6 +179,7 @@ impl ContextSubcommand {
                                 )),
                                 style::SetForegroundColor(Color::Reset)
                             )?;
+                            print_rule_annotations(&mut session.stderr, context_manager, path.get_path_as_str())?;
                             profile_context_files
                                 .extend(context_files.into_iter().map(|(path, content)| (path, content, true)));
                         }
@@ -198,6 +214,7 @@ impl ContextSubcommand {
                         style::SetAttribute(Attribute::Reset)
                     )?;
 
+                    let file_token_limit = max_file_tokens(os);
                     for (filename, content, is_temporary) in &profile_context_files {
                         let est_tokens = TokenCounter::count_tokens(content);
                         let icon = if *is_temporary { "💬" } else { "👤" };
@@ -205,8 +222,19 @@ impl ContextSubcommand {
                             session.stderr,
                             style::Print(format!("{} {} ", icon, filename)),
                             style::SetForegroundColor(Color::DarkGrey),
-                            style::Print(format!("(~{} tkns)\n", est_tokens)),
+                            style::Print(format!("(~{} tkns)", est_tokens)),
+                        )?;
+                        if let Some(limit) = file_token_limit.filter(|limit| est_tokens > *limit) {
+                            execute!(
+                                session.stderr,
+                                style::SetForegroundColor(Color::DarkYellow),
+                                style::Print(format!(" truncated to ~{} tkns", limit)),
+                            )?;
+                        }
+                        execute!(
+                            session.stderr,
                             style::SetForegroundColor(Color::Reset),
+                            style::Print("\n")
                         )?;
                         if expand {
                             execute!(
@@ -222,16 +250,23 @@ impl ContextSubcommand {
                         execute!(session.stderr, style::Print(format!("{}\n\n", "▔".repeat(3))),)?;
                     }
 
-                    let context_files_max_size = calc_max_context_files_size(session.conversation.model_info.as_ref());
+                    let context_files_max_size =
+                        calc_context_files_budget(os, session.conversation.model_info.as_ref());
                     let mut files_as_vec = profile_context_files
                         .iter()
-                        .map(|(path, content, _)| (path.clone(), content.clone()))
+                        .map(|(path, content, _)| match file_token_limit {
+                            Some(limit) => (path.clone(), truncate_head_tail(content, limit)),
+                            None => (path.clone(), content.clone()),
+                        })
                         .collect::<Vec<_>>();
                     let dropped_files = drop_matched_context_files(&mut files_as_vec, context_files_max_size).ok();
 
                     execute!(
                         session.stderr,
-                        style::Print(format!("\nTotal: ~{} tokens\n\n", total_tokens))
+                        style::Print(format!("\nTotal: ~{} tokens", total_tokens)),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!(" (budget: ~{} tokens)\n\n", context_files_max_size)),
+                        style::SetForegroundColor(Color::Reset),
                     )?;
 
                     if let Some(dropped_files) = dropped_files {
@@ -294,15 +329,42 @@ impl ContextSubcommand {
                     }
                 }
             },
-            Self::Add { force, paths } => match context_manager.add_paths(os, paths.clone(), force).await {
+            Self::Add {
+                force,
+                budget,
+                watch,
+                paths,
+            } => match context_manager.add_paths(os, paths.clone(), force).await {
                 Ok(_) => {
+                    for path in &paths {
+                        if let Some(budget) = budget {
+                            context_manager
+                                .set_path_budget(path, budget)
+                                .map_err(|e| ChatError::Custom(e.to_string().into()))?;
+                        }
+                        if watch {
+                            context_manager
+                                .watch_path(os, path)
+                                .await
+                                .map_err(|e| ChatError::Custom(e.to_string().into()))?;
+                        }
+                    }
                     execute!(
                         session.stderr,
                         style::SetForegroundColor(Color::Green),
                         style::Print(format!("\nAdded {} path(s) to context.\n", paths.len())),
-                        style::Print("Note: Context modifications via slash command is temporary.\n\n"),
+                        style::Print("Note: Context modifications via slash command is temporary.\n"),
                         style::SetForegroundColor(Color::Reset)
                     )?;
+                    if watch {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print("Matches are re-resolved on every message. Changes will be reported.\n"),
+                            style::SetForegroundColor(Color::Reset)
+                        )?;
+                    }
+                    execute!(session.stderr, style::Print("\n"))?;
                 },
                 Err(e) => {
                     execute!(
@@ -372,3 +434,27 @@ impl ContextSubcommand {
         }
     }
 }
+
+/// Prints the token budget and watch status of a context rule, if any.
+fn print_rule_annotations(
+    output: &mut impl Write,
+    context_manager: &ContextManager,
+    path: &str,
+) -> Result<(), ChatError> {
+    let mut annotations = Vec::new();
+    if let Some(budget) = context_manager.path_budgets.get(path) {
+        annotations.push(format!("budget: ~{} tkns", budget));
+    }
+    if context_manager.is_watched(path) {
+        annotations.push("watching".to_string());
+    }
+    if !annotations.is_empty() {
+        execute!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(" [{}]", annotations.join(", "))),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+    }
+    Ok(())
+}
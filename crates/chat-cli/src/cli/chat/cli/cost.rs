@@ -0,0 +1,34 @@
+use clap::Args;
+use crossterm::execute;
+
+use crate::cli::chat::cost::{
+    price_table,
+    print_usage_summary,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Arguments for the cost command that displays the tokens reported by the backend over the
+/// current session, broken down by model, along with an estimated cost.
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct CostArgs;
+
+impl CostArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        print_usage_summary(
+            &mut session.stderr,
+            &session.conversation.session_usage,
+            &price_table(os),
+        )?;
+        execute!(session.stderr)?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
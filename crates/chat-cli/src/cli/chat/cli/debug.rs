@@ -0,0 +1,68 @@
+use clap::Subcommand;
+use crossterm::execute;
+use crossterm::style::{
+    self,
+    Color,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::logging::{
+    get_log_level,
+    session_log_path,
+    set_log_level,
+};
+
+/// Subcommands for inspecting and changing how the session is logged.
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum DebugSubcommand {
+    /// Show or change the log level, such as debug or chat_cli=trace, until the session ends
+    Level {
+        /// The new log level
+        level: Option<String>,
+    },
+}
+
+impl DebugSubcommand {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self {
+            Self::Level { level: None } => {
+                let log_file = session_log_path()
+                    .map(|path| format!(", logging to {}", path.display()))
+                    .unwrap_or_default();
+                execute!(
+                    session.stderr,
+                    style::Print(format!("\nThe log level is {}{log_file}\n\n", get_log_level())),
+                )?;
+            },
+            Self::Level { level: Some(level) } => match set_log_level(level.clone()) {
+                Ok(old_level) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\nChanged the log level from {old_level} to {level}\n\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?,
+                Err(err) => execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!("\n{err}\n\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?,
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Level { .. } => "level",
+        }
+    }
+}
@@ -7,6 +7,7 @@ use crossterm::style::{
 };
 use uuid::Uuid;
 
+use crate::cli::chat::error::CategorizedError;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -91,10 +92,12 @@ fn launch_editor(file_path: &std::path::Path) -> Result<(), ChatError> {
 
     // Parse the editor command to handle arguments
     let mut parts =
-        shlex::split(&editor_cmd).ok_or_else(|| ChatError::Custom("Failed to parse EDITOR command".into()))?;
+        shlex::split(&editor_cmd).ok_or_else(|| CategorizedError::config_invalid("Failed to parse EDITOR command"))?;
 
     if parts.is_empty() {
-        return Err(ChatError::Custom("EDITOR environment variable is empty".into()));
+        return Err(CategorizedError::config_invalid("EDITOR environment variable is empty")
+            .with_remediation("Set the EDITOR environment variable to your preferred editor, e.g. `export EDITOR=vim`.")
+            .into());
     }
 
     let editor_bin = parts.remove(0);
@@ -7,7 +7,14 @@ use std::time::{
 };
 
 use bstr::ByteSlice;
-use clap::Args;
+use chrono::{
+    DateTime,
+    Utc,
+};
+use clap::{
+    Args,
+    Subcommand,
+};
 use crossterm::style::{
     self,
     Attribute,
@@ -48,6 +55,10 @@ use crate::cli::chat::{
 use crate::constants::help_text::hooks_long_help;
 use crate::util::MCP_SERVER_TOOL_DELIMITER;
 use crate::util::pattern_matching::matches_any_pattern;
+use crate::util::time::{
+    format_duration,
+    humanize,
+};
 
 /// Hook execution result: (exit_code, output)
 /// Output is stdout if exit_code is 0, stderr otherwise.
@@ -85,6 +96,24 @@ fn hook_matches_tool(hook: &Hook, tool_name: &str) -> bool {
     }
 }
 
+/// What the postToolUse hooks of a tool add to its result for the model: the STDOUT of the hooks
+/// that succeeded, and the STDERR of those that exited with code 2.
+pub fn post_tool_use_context(results: &[((HookTrigger, Hook), HookOutput)]) -> Option<String> {
+    let context = results
+        .iter()
+        .filter_map(|((_, hook), (exit_code, output))| match exit_code {
+            0 if !output.trim().is_empty() => Some(format!("PostToolUse hook output: {}", output.trim_end())),
+            2 => Some(format!(
+                "PostToolUse hook \"{}\" reported: {}",
+                hook.command,
+                output.trim_end()
+            )),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    (!context.is_empty()).then(|| context.join("\n"))
+}
+
 #[derive(Debug, Clone)]
 pub struct ToolContext {
     pub tool_name: String,
@@ -98,15 +127,28 @@ pub struct CachedHook {
     expiry: Option<Instant>,
 }
 
+/// How the runs of a hook went, for `/hooks status`
+#[derive(Debug, Clone)]
+pub struct HookRunStats {
+    pub runs: usize,
+    pub failures: usize,
+    pub last_run: DateTime<Utc>,
+    pub last_duration: Duration,
+    /// Why the last run failed, if it did
+    pub last_error: Option<String>,
+}
+
 /// Maps a hook name to a [`CachedHook`]
 #[derive(Debug, Clone, Default)]
 pub struct HookExecutor {
     pub cache: HashMap<(HookTrigger, Hook), CachedHook>,
+    /// How the runs of each hook went, cached results aside
+    pub stats: HashMap<(HookTrigger, Hook), HookRunStats>,
 }
 
 impl HookExecutor {
     pub fn new() -> Self {
-        Self { cache: HashMap::new() }
+        Self::default()
     }
 
     /// Run and cache [`Hook`]s. Any hooks that are already cached will be returned without
@@ -116,6 +158,10 @@ impl HookExecutor {
     /// If `updates` is `Some`, progress on hook execution will be written to it.
     /// Errors encountered with write operations to `updates` are ignored.
     ///
+    /// Hooks run in parallel, each for at most its own timeout. Hooks still running at
+    /// `deadline` are stopped and reported as failed, so that one slow hook can't hold up the
+    /// others.
+    ///
     /// Note: [`HookTrigger::AgentSpawn`] hooks never leave the cache.
     pub async fn run_hooks(
         &mut self,
@@ -124,8 +170,10 @@ impl HookExecutor {
         cwd: &str,
         prompt: Option<&str>,
         tool_context: Option<ToolContext>,
+        deadline: Option<Instant>,
     ) -> Result<Vec<((HookTrigger, Hook), HookOutput)>, ChatError> {
         let mut cached = vec![];
+        let mut pending = vec![];
         let mut futures = FuturesUnordered::new();
         for hook in hooks
             .into_iter()
@@ -143,6 +191,7 @@ impl HookExecutor {
                 cached.push((hook.clone(), (0, cache)));
                 continue;
             }
+            pending.push(hook.clone());
             futures.push(self.run_hook(hook, cwd, prompt, tool_context.clone()));
         }
 
@@ -163,8 +212,23 @@ impl HookExecutor {
 
         // Process results as they complete
         let mut results = vec![];
+        let mut runs = vec![];
         let start_time = Instant::now();
-        while let Some((hook, result, duration)) = futures.next().await {
+        loop {
+            let next = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline.into(), futures.next()).await {
+                    Ok(next) => next,
+                    Err(_) => break,
+                },
+                None => futures.next().await,
+            };
+            let Some((hook, result, duration)) = next else {
+                break;
+            };
+            if let Some(i) = pending.iter().position(|p| p == &hook) {
+                pending.swap_remove(i);
+            }
+
             // If output is enabled, handle that first
             if let Some(spinner) = spinner.as_mut() {
                 spinner.stop();
@@ -194,6 +258,12 @@ impl HookExecutor {
                 )?;
             }
 
+            runs.push((hook.clone(), duration, match &result {
+                Ok((0, _)) => None,
+                Ok((exit_code, hook_output)) => Some(format!("exit code {}: {}", exit_code, hook_output.trim_end())),
+                Err(err) => Some(err.to_string()),
+            }));
+
             // Process results regardless of output enabled
             if let Ok((exit_code, hook_output)) = &result {
                 // Print warning if exit code is not 0
@@ -241,8 +311,56 @@ impl HookExecutor {
                 spinner = Some(Spinner::new(Spinners::Dots, spinner_text(complete, total)));
             }
         }
+        // Hooks still running are past the deadline. Dropping them kills their processes.
         drop(futures);
 
+        if !pending.is_empty() {
+            if let Some(spinner) = spinner.as_mut() {
+                spinner.stop();
+                execute!(
+                    output,
+                    cursor::MoveToColumn(0),
+                    terminal::Clear(terminal::ClearType::CurrentLine),
+                    cursor::Hide,
+                )?;
+            }
+            let elapsed = start_time.elapsed();
+            for hook in pending {
+                queue!(
+                    output,
+                    style::SetForegroundColor(style::Color::Red),
+                    style::Print("✗ "),
+                    style::ResetColor,
+                    style::Print(format!("{} \"", hook.0)),
+                    style::Print(&hook.1.command),
+                    style::Print("\""),
+                    style::SetForegroundColor(style::Color::Red),
+                    style::Print(format!(
+                        " was stopped after {:.2} s, at the deadline of hooks\n",
+                        elapsed.as_secs_f32()
+                    )),
+                    style::ResetColor,
+                )?;
+                runs.push((hook, elapsed, Some("stopped at the deadline of hooks".to_string())));
+            }
+            queue!(
+                output,
+                style::SetForegroundColor(Color::Blue),
+                style::Print(format!(
+                    "{} {} in ",
+                    "✗".to_string().red(),
+                    spinner_text(complete, total)
+                )),
+                style::SetForegroundColor(style::Color::Yellow),
+                style::Print(format!("{:.2} s\n", elapsed.as_secs_f32())),
+                style::ResetColor,
+            )?;
+        }
+
+        for (hook, duration, error) in runs {
+            self.record_run(hook, duration, error);
+        }
+
         // Fill cache with executed results, skipping what was already from cache
         for ((trigger, hook), (exit_code, output)) in &results {
             if *exit_code != 0 {
@@ -256,6 +374,7 @@ impl HookExecutor {
                     HookTrigger::PreToolUse => Some(Instant::now() + Duration::from_secs(hook.cache_ttl_seconds)),
                     HookTrigger::PostToolUse => Some(Instant::now() + Duration::from_secs(hook.cache_ttl_seconds)),
                     HookTrigger::Stop => Some(Instant::now() + Duration::from_secs(hook.cache_ttl_seconds)),
+                    HookTrigger::SessionEnd => Some(Instant::now() + Duration::from_secs(hook.cache_ttl_seconds)),
                 },
             });
         }
@@ -265,6 +384,21 @@ impl HookExecutor {
         Ok(results)
     }
 
+    fn record_run(&mut self, hook: (HookTrigger, Hook), duration: Duration, error: Option<String>) {
+        let stats = self.stats.entry(hook).or_insert_with(|| HookRunStats {
+            runs: 0,
+            failures: 0,
+            last_run: Utc::now(),
+            last_duration: duration,
+            last_error: None,
+        });
+        stats.runs += 1;
+        stats.failures += usize::from(error.is_some());
+        stats.last_run = Utc::now();
+        stats.last_duration = duration;
+        stats.last_error = error;
+    }
+
     async fn run_hook(
         &self,
         hook: (HookTrigger, Hook),
@@ -280,6 +414,7 @@ impl HookExecutor {
         let mut cmd = tokio::process::Command::new("bash");
         #[cfg(unix)]
         let cmd = cmd
+            .kill_on_drop(true)
             .arg("-c")
             .arg(command)
             .stdin(Stdio::piped())
@@ -290,6 +425,7 @@ impl HookExecutor {
         let mut cmd = tokio::process::Command::new("cmd");
         #[cfg(windows)]
         let cmd = cmd
+            .kill_on_drop(true)
             .arg("/C")
             .arg(command)
             .stdin(Stdio::piped())
@@ -392,7 +528,19 @@ fn sanitize_user_prompt(input: &str) -> String {
     before_long_help = hooks_long_help()
 )]
 /// Arguments for the hooks command that displays configured context hooks
-pub struct HooksArgs;
+pub struct HooksArgs {
+    /// What to show, the configured hooks by default
+    #[command(subcommand)]
+    subcommand: Option<HooksSubcommand>,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+/// Subcommands of the hooks command
+pub enum HooksSubcommand {
+    /// Show when each hook last ran, how long it took and how often it failed
+    Status,
+}
 
 impl HooksArgs {
     pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
@@ -402,6 +550,51 @@ impl HooksArgs {
             });
         };
 
+        if let Some(HooksSubcommand::Status) = self.subcommand {
+            let mut stats = context_manager.hook_executor.stats.iter().collect::<Vec<_>>();
+            if stats.is_empty() {
+                queue!(session.stderr, style::Print("No hooks have run in this session.\n"))?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            }
+            stats.sort_by(|(a, _), (b, _)| (a.0.to_string(), &a.1.command).cmp(&(b.0.to_string(), &b.1.command)));
+            for ((trigger, hook), stats) in stats {
+                let status = match stats.last_error {
+                    Some(_) => "✗".to_string().red(),
+                    None => "✓".to_string().green(),
+                };
+                queue!(
+                    session.stderr,
+                    style::Print(format!("{status} {trigger} ")),
+                    style::SetForegroundColor(Color::Blue),
+                    style::Print(&hook.command),
+                    style::ResetColor,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!(
+                        "\n  last ran {}, took {} (timeout {}) • {} runs, {} failed\n",
+                        humanize(&stats.last_run),
+                        format_duration(stats.last_duration),
+                        format_duration(Duration::from_millis(hook.timeout_ms)),
+                        stats.runs,
+                        stats.failures,
+                    )),
+                    style::ResetColor,
+                )?;
+                if let Some(error) = &stats.last_error {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("  {}\n", error.lines().next().unwrap_or_default())),
+                        style::ResetColor,
+                    )?;
+                }
+            }
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
         let mut out = Vec::new();
         for (trigger, hooks) in &context_manager.hooks {
             writeln!(&mut out, "{trigger}:")?;
@@ -446,6 +639,7 @@ mod tests {
     use crate::cli::agent::hook::{
         Hook,
         HookTrigger,
+        Source,
     };
 
     #[test]
@@ -590,7 +784,7 @@ mod tests {
 
         // Run the hook
         let result = executor
-            .run_hooks(hooks, &mut output, ".", None, Some(tool_context))
+            .run_hooks(hooks, &mut output, ".", None, Some(tool_context), None)
             .await;
 
         assert!(result.is_ok());
@@ -638,6 +832,7 @@ mod tests {
                 ".",  // cwd - using current directory for now
                 None, // prompt - no user prompt for this test
                 Some(tool_context),
+                None, // deadline
             )
             .await;
 
@@ -689,6 +884,7 @@ mod tests {
                 ".",  // cwd
                 None, // prompt
                 Some(tool_context),
+                None, // deadline
             )
             .await
             .unwrap();
@@ -702,6 +898,27 @@ mod tests {
         assert!(hook_output.contains("Tool execution blocked by security policy"));
     }
 
+    #[test]
+    fn test_post_tool_use_context() {
+        let hook = |command: &str| (HookTrigger::PostToolUse, Hook::new(command.to_string(), Source::Agent));
+        assert_eq!(post_tool_use_context(&[]), None);
+        assert_eq!(
+            post_tool_use_context(&[
+                (hook("fmt"), (0, "\n".to_string())),
+                (hook("notify"), (1, "oops".to_string()))
+            ]),
+            None
+        );
+        assert_eq!(
+            post_tool_use_context(&[
+                (hook("cargo check"), (0, "1 warning\n".to_string())),
+                (hook("lint"), (2, "line too long\n".to_string())),
+            ])
+            .unwrap(),
+            "PostToolUse hook output: 1 warning\nPostToolUse hook \"lint\" reported: line too long"
+        );
+    }
+
     #[tokio::test]
     async fn test_stop_hook() {
         let mut executor = HookExecutor::new();
@@ -731,6 +948,7 @@ mod tests {
                 ".",  // cwd
                 None, // prompt
                 None, // tool_context - Stop doesn't have tool context
+                None, // deadline
             )
             .await
             .unwrap();
@@ -743,4 +961,39 @@ mod tests {
         assert_eq!(*exit_code, 0);
         assert!(hook_output.contains("Turn completed successfully"));
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_deadline_stops_slow_hooks() {
+        let mut executor = HookExecutor::new();
+        let mut output = Vec::new();
+        let fast = Hook::new("echo fast".to_string(), Source::Agent);
+        let slow = Hook::new("sleep 10".to_string(), Source::Agent);
+        let hooks = HashMap::from([(HookTrigger::Stop, vec![fast.clone(), slow.clone()])]);
+
+        let start = Instant::now();
+        let results = executor
+            .run_hooks(
+                hooks,
+                &mut output,
+                ".",
+                None,
+                None,
+                Some(Instant::now() + Duration::from_millis(500)),
+            )
+            .await
+            .unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        // The hook that finished in time is kept
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.1, fast);
+        assert_eq!(results[0].1.1.trim(), "fast");
+
+        let fast_stats = &executor.stats[&(HookTrigger::Stop, fast)];
+        assert_eq!((fast_stats.runs, fast_stats.failures), (1, 0));
+        let slow_stats = &executor.stats[&(HookTrigger::Stop, slow)];
+        assert_eq!((slow_stats.runs, slow_stats.failures), (1, 1));
+        assert!(slow_stats.last_error.as_ref().unwrap().contains("deadline"));
+    }
 }
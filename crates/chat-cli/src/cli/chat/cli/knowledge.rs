@@ -20,7 +20,9 @@ use crate::cli::experiment::experiment_manager::{
     ExperimentName,
 };
 use crate::os::Os;
+use crate::util::knowledge_documents;
 use crate::util::knowledge_store::KnowledgeStore;
+use crate::util::time::format_duration;
 
 /// Knowledge base management commands
 #[derive(Clone, Debug, PartialEq, Eq, Subcommand)]
@@ -32,7 +34,7 @@ pub enum KnowledgeSubcommand {
         /// Name for the knowledge base entry
         #[arg(long, short = 'n')]
         name: String,
-        /// Path to file or directory to add
+        /// Path to file or directory to add, or the URL of a web page or PDF
         #[arg(long, short = 'p')]
         path: String,
         /// Include patterns (e.g., `**/*.ts`, `**/*.md`)
@@ -45,10 +47,15 @@ pub enum KnowledgeSubcommand {
         #[arg(long)]
         index_type: Option<String>,
     },
+    /// Index the workspace for semantic search, or re-index the files that changed since
+    Index {
+        /// Directory to index (defaults to the current directory)
+        path: Option<String>,
+    },
     /// Remove specified knowledge base entry by path
     #[command(alias = "rm")]
     Remove { path: String },
-    /// Update a file or directory in knowledge base
+    /// Update a file or directory in knowledge base, or fetch a web page or PDF again
     Update { path: String },
     /// Remove all knowledge base entries
     Clear,
@@ -122,6 +129,7 @@ impl KnowledgeSubcommand {
                 exclude,
                 index_type,
             } => Self::handle_add(os, session, name, path, include, exclude, index_type).await,
+            KnowledgeSubcommand::Index { path } => Self::handle_index(os, session, path.as_deref()).await,
             KnowledgeSubcommand::Remove { path } => Self::handle_remove(os, session, path).await,
             KnowledgeSubcommand::Update { path } => Self::handle_update(os, session, path).await,
             KnowledgeSubcommand::Clear => Self::handle_clear(os, session).await,
@@ -212,7 +220,15 @@ impl KnowledgeSubcommand {
             )?;
 
             // Path line if available (matching operation format)
-            if let Some(source_path) = &ctx.source_path {
+            if let Some(source) = &ctx.source {
+                queue!(
+                    session.stderr,
+                    style::Print(format!("{}   ", indent)),
+                    style::SetForegroundColor(Color::Grey),
+                    style::Print(format!("{}\n", source)),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+            } else if let Some(source_path) = &ctx.source_path {
                 queue!(
                     session.stderr,
                     style::Print(format!("{}   ", indent)),
@@ -311,9 +327,39 @@ impl KnowledgeSubcommand {
         }
     }
 
+    /// Handle index operation
+    async fn handle_index(os: &Os, session: &ChatSession, path: Option<&str>) -> OperationResult {
+        let sanitized_path = match Self::validate_and_sanitize_path(os, path.unwrap_or(".")) {
+            Ok(sanitized_path) => sanitized_path,
+            Err(e) => return OperationResult::Error(format!("Invalid path: {}", e)),
+        };
+        let name = std::path::Path::new(&sanitized_path)
+            .canonicalize()
+            .ok()
+            .and_then(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "workspace".to_string());
+
+        let agent = Self::get_agent(session);
+        let async_knowledge_store = match KnowledgeStore::get_async_instance(os, agent).await {
+            Ok(store) => store,
+            Err(e) => return OperationResult::Error(format!("Error accessing knowledge base: {}", e)),
+        };
+        let mut store = async_knowledge_store.lock().await;
+
+        let options = crate::util::knowledge_store::AddOptions::with_db_defaults(os);
+        match store.add_or_update(&name, &sanitized_path, options).await {
+            Ok(message) => OperationResult::Info(message),
+            Err(e) => OperationResult::Error(format!("Failed to index: {}", e)),
+        }
+    }
+
     /// Handle remove operation
     async fn handle_remove(os: &Os, session: &ChatSession, path: &str) -> OperationResult {
-        let sanitized_path = sanitize_path_tool_arg(os, path);
+        let sanitized_path = if knowledge_documents::is_url(path) {
+            path.to_string()
+        } else {
+            sanitize_path_tool_arg(os, path).to_string_lossy().to_string()
+        };
         let agent = Self::get_agent(session);
 
         let async_knowledge_store = match KnowledgeStore::get_async_instance(os, agent).await {
@@ -325,7 +371,7 @@ impl KnowledgeSubcommand {
         let scope_desc = "agent";
 
         // Try path first, then name
-        if store.remove_by_path(&sanitized_path.to_string_lossy()).await.is_ok() {
+        if store.remove_by_path(&sanitized_path).await.is_ok() {
             OperationResult::Success(format!(
                 "Removed {} knowledge base entry with path '{}'",
                 scope_desc, path
@@ -447,7 +493,7 @@ impl KnowledgeSubcommand {
             } else if Self::should_show_progress_bar(op.current, op.total) {
                 let percentage = (op.current as f64 / op.total as f64 * 100.0) as u8;
                 if let Some(eta) = op.eta {
-                    output.push_str(&format!("       {}% • ETA: {}s\n", percentage, eta.as_secs()));
+                    output.push_str(&format!("       {}% • ETA: {}\n", percentage, format_duration(eta)));
                 } else {
                     output.push_str(&format!("       {}%\n", percentage));
                 }
@@ -481,7 +527,7 @@ impl KnowledgeSubcommand {
 
     /// Validate and sanitize path
     fn validate_and_sanitize_path(os: &Os, path: &str) -> Result<String, String> {
-        if path.contains('\n') {
+        if path.contains('\n') || knowledge_documents::is_url(path) {
             return Ok(path.to_string());
         }
 
@@ -597,6 +643,17 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_index_path_is_optional() {
+        let cli = TestCli::try_parse_from(["test", "index"]).unwrap();
+        assert_eq!(cli.knowledge, KnowledgeSubcommand::Index { path: None });
+
+        let cli = TestCli::try_parse_from(["test", "index", "src"]).unwrap();
+        assert_eq!(cli.knowledge, KnowledgeSubcommand::Index {
+            path: Some("src".to_string())
+        });
+    }
+
     #[test]
     fn test_clap_markdown_parsing_issue() {
         let help_result = TestCli::try_parse_from(["test", "add", "--help"]);
@@ -14,6 +14,7 @@ use crossterm::style::{
 use zip::ZipWriter;
 use zip::write::SimpleFileOptions;
 
+use crate::cli::chat::resource_monitor::ResourceStats;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -40,7 +41,8 @@ impl LogdumpArgs {
         let logs_directory =
             logs_dir().map_err(|e| ChatError::Custom(format!("Failed to get logs directory: {}", e).into()))?;
 
-        match self.create_log_dump(&zip_path, logs_directory).await {
+        let resources = session.resource_monitor.stats();
+        match self.create_log_dump(&zip_path, logs_directory, Some(&resources)).await {
             Ok(log_count) => {
                 execute!(
                     session.stderr,
@@ -68,7 +70,12 @@ impl LogdumpArgs {
         })
     }
 
-    async fn create_log_dump(&self, zip_path: &Path, logs_dir: PathBuf) -> Result<usize, Box<dyn std::error::Error>> {
+    async fn create_log_dump(
+        &self,
+        zip_path: &Path,
+        logs_dir: PathBuf,
+        resources: Option<&ResourceStats>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
         let file = std::fs::File::create(zip_path)?;
         let mut zip = ZipWriter::new(file);
         let mut log_count = 0;
@@ -76,6 +83,17 @@ impl LogdumpArgs {
         // Only collect qchat.log (keeping current implementation logic)
         log_count += Self::collect_qchat_log(&mut zip, &logs_dir)?;
 
+        // The log of this session alone, in the format set with Q_LOG_FORMAT.
+        if let Some(session_log) = crate::logging::session_log_path().filter(|path| path.exists()) {
+            log_count += Self::add_log_file_to_zip(&session_log, &mut zip, "logs/sessions")?;
+        }
+
+        // The resources of the session, for complaints about it slowing down the machine.
+        if let Some(resources) = resources {
+            zip.start_file("resources.json", SimpleFileOptions::default())?;
+            zip.write_all(&serde_json::to_vec_pretty(resources)?)?;
+        }
+
         zip.finish()?;
         Ok(log_count)
     }
@@ -129,7 +147,7 @@ mod tests {
         let logdump = LogdumpArgs;
 
         // Create the zip file (even if no logs are found, it should create an empty zip)
-        let result = logdump.create_log_dump(&zip_path, logs_dir).await;
+        let result = logdump.create_log_dump(&zip_path, logs_dir, None).await;
 
         // The function should succeed and create a zip file with 0 log files
         assert!(result.is_ok());
@@ -156,7 +174,7 @@ mod tests {
 
         let logdump = LogdumpArgs;
 
-        let result = logdump.create_log_dump(&zip_path, logs_dir).await;
+        let result = logdump.create_log_dump(&zip_path, logs_dir, None).await;
 
         // The function should succeed and include 1 log file
         assert!(result.is_ok());
@@ -173,4 +191,27 @@ mod tests {
         std::io::Read::read_to_string(&mut log_file, &mut contents).unwrap();
         assert_eq!(contents, "test log content");
     }
+
+    #[tokio::test]
+    async fn test_logdump_includes_resources() {
+        let temp_dir = TempDir::new().unwrap();
+        let zip_path = temp_dir.path().join("test-logs.zip");
+        let logs_dir = temp_dir.path().join("logs");
+        fs::create_dir_all(&logs_dir).unwrap();
+
+        let resources = ResourceStats {
+            peak_memory: 1024,
+            samples: 3,
+            ..Default::default()
+        };
+        let result = LogdumpArgs.create_log_dump(&zip_path, logs_dir, Some(&resources)).await;
+        assert_eq!(result.unwrap(), 0);
+
+        let file = fs::File::open(&zip_path).unwrap();
+        let mut archive = zip::ZipArchive::new(file).unwrap();
+        let resources_file = archive.by_name("resources.json").unwrap();
+        let resources: serde_json::Value = serde_json::from_reader(resources_file).unwrap();
+        assert_eq!(resources["peakMemory"], 1024);
+        assert_eq!(resources["samples"], 3);
+    }
 }
@@ -13,17 +13,25 @@ use crate::cli::chat::{
     ChatSession,
     ChatState,
 };
+use crate::cli::mcp::McpSubcommand;
+use crate::os::Os;
 
 /// Arguments for the MCP (Model Context Protocol) command.
 ///
 /// This struct handles MCP-related functionality, allowing users to view
-/// the status of MCP servers and their loading progress.
+/// the status of MCP servers and their loading progress, and to manage
+/// their configuration as `q mcp` does.
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
-pub struct McpArgs;
+pub struct McpArgs {
+    /// The `q mcp` subcommand to run, such as `add` or `test`. Without one, the loading status of
+    /// the servers of the session is shown.
+    #[command(subcommand)]
+    pub subcommand: Option<McpSubcommand>,
+}
 
 impl McpArgs {
-    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         if !session.conversation.mcp_enabled {
             queue!(
                 session.stderr,
@@ -39,6 +47,28 @@ impl McpArgs {
             });
         }
 
+        if let Some(subcommand) = self.subcommand {
+            let changes_config = !matches!(
+                subcommand,
+                McpSubcommand::List(_) | McpSubcommand::Status(_) | McpSubcommand::Test(_)
+            );
+            if let Err(err) = subcommand.execute(os, &mut session.stderr).await {
+                return Err(ChatError::Custom(err.to_string().into()));
+            }
+            if changes_config {
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print("The change applies to new q chat sessions.\n\n"),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            }
+            session.stderr.flush()?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
         let terminal_width = session.terminal_width();
         let still_loading = session
             .conversation
@@ -71,6 +101,34 @@ impl McpArgs {
             )?;
         }
 
+        let conflicts = &session.conversation.tool_manager.tool_conflicts;
+        if !conflicts.is_empty() {
+            queue!(
+                session.stderr,
+                style::Print("Tool name conflicts:\n"),
+                style::Print(format!("{}\n", "▔".repeat(terminal_width))),
+            )?;
+            for conflict in conflicts {
+                queue!(
+                    session.stderr,
+                    style::Print(format!(
+                        " - {}: @{}/{} is left out, as the name is taken by @{}/{}\n",
+                        conflict.model_tool_name,
+                        conflict.rejected.server_name,
+                        conflict.rejected.host_tool_name,
+                        conflict.kept.server_name,
+                        conflict.kept.host_tool_name,
+                    )),
+                )?;
+            }
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("Give one of them another name in the toolAliases of the agent to use both.\n\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+
         if !still_loading.is_empty() {
             queue!(
                 session.stderr,
@@ -1,44 +1,66 @@
+pub mod bad;
+pub mod cd;
 pub mod changelog;
 pub mod checkpoint;
 pub mod clear;
+pub mod commit;
 pub mod compact;
 pub mod context;
+pub mod cost;
+pub mod debug;
 pub mod editor;
 pub mod experiment;
 pub mod hooks;
+#[cfg(feature = "knowledge")]
 pub mod knowledge;
 pub mod logdump;
 pub mod mcp;
 pub mod model;
+pub mod open;
 pub mod persist;
+pub mod plan;
 pub mod profile;
 pub mod prompts;
 pub mod reply;
+pub mod stats;
 pub mod subscribe;
 pub mod tangent;
 pub mod todos;
 pub mod tools;
+pub mod tour;
+pub mod undo;
 pub mod usage;
 
+use bad::BadArgs;
+use cd::CdArgs;
 use changelog::ChangelogArgs;
 use clap::Parser;
 use clear::ClearArgs;
+use commit::CommitArgs;
 use compact::CompactArgs;
 use context::ContextSubcommand;
+use cost::CostArgs;
+use debug::DebugSubcommand;
 use editor::EditorArgs;
 use experiment::ExperimentArgs;
 use hooks::HooksArgs;
+#[cfg(feature = "knowledge")]
 use knowledge::KnowledgeSubcommand;
 use logdump::LogdumpArgs;
 use mcp::McpArgs;
 use model::ModelArgs;
+use open::OpenArgs;
 use persist::PersistSubcommand;
+use plan::PlanArgs;
 use profile::AgentSubcommand;
 use prompts::PromptsArgs;
 use reply::ReplyArgs;
+use stats::StatsArgs;
 use tangent::TangentArgs;
 use todos::TodoSubcommand;
 use tools::ToolsArgs;
+use tour::TourArgs;
+use undo::UndoArgs;
 
 use crate::cli::chat::cli::checkpoint::CheckpointSubcommand;
 use crate::cli::chat::cli::subscribe::SubscribeArgs;
@@ -63,7 +85,7 @@ pub enum SlashCommand {
     /// Clear the conversation history
     Clear(ClearArgs),
     /// Manage agents
-    #[command(subcommand)]
+    #[command(subcommand, alias = "agents")]
     Agent(AgentSubcommand),
     #[command(hide = true)]
     Profile,
@@ -72,6 +94,7 @@ pub enum SlashCommand {
     Context(ContextSubcommand),
     /// (Beta) Manage knowledge base for persistent context storage. Requires "q settings
     /// chat.enableKnowledge true"
+    #[cfg(feature = "knowledge")]
     #[command(subcommand, hide = true)]
     Knowledge(KnowledgeSubcommand),
     /// Open $EDITOR (defaults to vi) to compose a prompt
@@ -79,14 +102,29 @@ pub enum SlashCommand {
     PromptEditor(EditorArgs),
     /// Open $EDITOR with the most recent assistant message quoted for reply
     Reply(ReplyArgs),
+    /// Open a file, or the files cited in the last response, in your editor at the cited line
+    Open(OpenArgs),
     /// Summarize the conversation to free up context space
     Compact(CompactArgs),
+    /// Revert the file changes and messages of the most recent turn
+    Undo(UndoArgs),
+    /// Record changes as a plan to review and apply at once, instead of making them right away
+    Plan(PlanArgs),
+    /// Show or change the working directory of the session
+    Cd(CdArgs),
+    /// Write a commit message for the staged changes, review it in $EDITOR, and commit
+    Commit(CommitArgs),
     /// View tools and permissions
     Tools(ToolsArgs),
     /// Create a new Github issue or make a feature request
     Issue(issue::IssueArgs),
+    /// Report that the last response was poor
+    Bad(BadArgs),
     /// Create a zip file with logs for support investigation
     Logdump(LogdumpArgs),
+    /// Show or change the log level of the session
+    #[command(subcommand)]
+    Debug(DebugSubcommand),
     /// View changelog for Amazon Q CLI
     #[command(name = "changelog")]
     Changelog(ChangelogArgs),
@@ -96,7 +134,11 @@ pub enum SlashCommand {
     Hooks(HooksArgs),
     /// Show current session's context window usage
     Usage(UsageArgs),
-    /// See mcp server loaded
+    /// Show tokens used and estimated cost for the current session
+    Cost(CostArgs),
+    /// Show the memory and CPU used by the session and its tools
+    Stats(StatsArgs),
+    /// See mcp server loaded, or add, remove, enable, disable and test servers
     Mcp(McpArgs),
     /// Select a model for the current conversation session
     Model(ModelArgs),
@@ -104,6 +146,8 @@ pub enum SlashCommand {
     Experiment(ExperimentArgs),
     /// Upgrade to a Q Developer Pro subscription for increased query limits
     Subscribe(SubscribeArgs),
+    /// Take a guided tour of context, tools, checkpoints and agents
+    Tour(TourArgs),
     /// (Beta) Toggle tangent mode for isolated conversations. Requires "q settings
     /// chat.enableTangentMode true"
     #[command(hide = true)]
@@ -154,10 +198,16 @@ impl SlashCommand {
                 })
             },
             Self::Context(args) => args.execute(os, session).await,
+            #[cfg(feature = "knowledge")]
             Self::Knowledge(subcommand) => subcommand.execute(os, session).await,
             Self::PromptEditor(args) => args.execute(session).await,
             Self::Reply(args) => args.execute(session).await,
+            Self::Open(args) => args.execute(os, session).await,
             Self::Compact(args) => args.execute(os, session).await,
+            Self::Undo(args) => args.execute(session).await,
+            Self::Plan(args) => args.execute(os, session).await,
+            Self::Cd(args) => args.execute(os, session).await,
+            Self::Commit(args) => args.execute(os, session).await,
             Self::Tools(args) => args.execute(session).await,
             Self::Issue(args) => {
                 if let Err(err) = args.execute(os).await {
@@ -168,15 +218,20 @@ impl SlashCommand {
                     skip_printing_tools: true,
                 })
             },
+            Self::Bad(args) => args.execute(os, session).await,
             Self::Logdump(args) => args.execute(session).await,
+            Self::Debug(subcommand) => subcommand.execute(session).await,
             Self::Changelog(args) => args.execute(session).await,
             Self::Prompts(args) => args.execute(os, session).await,
             Self::Hooks(args) => args.execute(session).await,
             Self::Usage(args) => args.execute(os, session).await,
-            Self::Mcp(args) => args.execute(session).await,
+            Self::Cost(args) => args.execute(os, session).await,
+            Self::Stats(args) => args.execute(session).await,
+            Self::Mcp(args) => args.execute(os, session).await,
             Self::Model(args) => args.execute(os, session).await,
             Self::Experiment(args) => args.execute(os, session).await,
             Self::Subscribe(args) => args.execute(os, session).await,
+            Self::Tour(args) => args.execute(os, session).await,
             Self::Tangent(args) => args.execute(os, session).await,
             Self::Persist(subcommand) => subcommand.execute(os, session).await,
             // Self::Root(subcommand) => {
@@ -200,21 +255,32 @@ impl SlashCommand {
             Self::Agent(_) => "agent",
             Self::Profile => "profile",
             Self::Context(_) => "context",
+            #[cfg(feature = "knowledge")]
             Self::Knowledge(_) => "knowledge",
             Self::PromptEditor(_) => "editor",
             Self::Reply(_) => "reply",
+            Self::Open(_) => "open",
             Self::Compact(_) => "compact",
+            Self::Undo(_) => "undo",
+            Self::Plan(_) => "plan",
+            Self::Cd(_) => "cd",
+            Self::Commit(_) => "commit",
             Self::Tools(_) => "tools",
             Self::Issue(_) => "issue",
+            Self::Bad(_) => "bad",
             Self::Logdump(_) => "logdump",
+            Self::Debug(_) => "debug",
             Self::Changelog(_) => "changelog",
             Self::Prompts(_) => "prompts",
             Self::Hooks(_) => "hooks",
             Self::Usage(_) => "usage",
+            Self::Cost(_) => "cost",
+            Self::Stats(_) => "stats",
             Self::Mcp(_) => "mcp",
             Self::Model(_) => "model",
             Self::Experiment(_) => "experiment",
             Self::Subscribe(_) => "subscribe",
+            Self::Tour(_) => "tour",
             Self::Tangent(_) => "tangent",
             Self::Persist(sub) => match sub {
                 PersistSubcommand::Save { .. } => "save",
@@ -229,9 +295,11 @@ impl SlashCommand {
         match self {
             SlashCommand::Agent(sub) => Some(sub.name()),
             SlashCommand::Context(sub) => Some(sub.name()),
+            #[cfg(feature = "knowledge")]
             SlashCommand::Knowledge(sub) => Some(sub.name()),
             SlashCommand::Tools(arg) => arg.subcommand_name(),
             SlashCommand::Prompts(arg) => arg.subcommand_name(),
+            SlashCommand::Debug(sub) => Some(sub.name()),
             _ => None,
         }
     }
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use amzn_codewhisperer_client::types::Model;
 use clap::Args;
 use crossterm::style::{
@@ -15,13 +17,20 @@ use serde::{
 };
 
 use crate::api_client::Endpoint;
+use crate::cli::chat::cli::compact::CompactStrategy;
+use crate::cli::chat::conversation::TokenWarningLevel;
+use crate::cli::chat::error::CategorizedError;
 use crate::cli::chat::{
     ChatError,
     ChatSession,
     ChatState,
 };
+use crate::database::settings::Setting;
 use crate::os::Os;
 
+/// How long the models fetched by one invocation are reused by the following ones.
+const MODEL_CATALOG_TTL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModelInfo {
     /// Display name
@@ -65,6 +74,12 @@ impl ModelInfo {
         self.model_name.as_deref().unwrap_or(&self.model_id)
     }
 
+    /// Display name without the model family prefix, e.g. `sonnet-4` for `claude-sonnet-4`.
+    pub fn short_name(&self) -> &str {
+        let name = self.display_name();
+        name.strip_prefix("claude-").unwrap_or(name)
+    }
+
     pub fn description(&self) -> Option<&str> {
         self.description
             .as_deref()
@@ -75,12 +90,41 @@ impl ModelInfo {
 /// Command-line arguments for model selection operations
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
-pub struct ModelArgs;
+pub struct ModelArgs {
+    /// The model to switch to, by name or id. Shows a picker if omitted
+    pub model: Option<String>,
+}
+
 impl ModelArgs {
     pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
-        Ok(select_model(os, session).await?.unwrap_or(ChatState::PromptUser {
-            skip_printing_tools: false,
-        }))
+        let Some(name) = self.model else {
+            return Ok(select_model(os, session).await?.unwrap_or(ChatState::PromptUser {
+                skip_printing_tools: false,
+            }));
+        };
+
+        let (models, _) = get_available_models(os).await?;
+        let Some(model) = find_model(&models, &name).cloned() else {
+            let available = models.iter().map(|m| m.display_name()).collect::<Vec<_>>().join(", ");
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!(
+                    "\nModel '{name}' is not available. Available models: {available}\n\n"
+                )),
+                style::ResetColor,
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: false,
+            });
+        };
+
+        queue!(session.stderr, style::Print("\n"))?;
+        Ok(switch_model(os, session, model)
+            .await?
+            .unwrap_or(ChatState::PromptUser {
+                skip_printing_tools: false,
+            }))
     }
 }
 
@@ -100,8 +144,119 @@ pub async fn select_model(os: &Os, session: &mut ChatSession) -> Result<Option<C
         return Ok(None);
     }
 
+    if !session.model_health.is_empty() {
+        queue!(session.stderr, style::Print("Model health this session:\n"))?;
+        for model in &models {
+            let Some(description) = session.model_health.describe(&model.model_id) else {
+                continue;
+            };
+            if session.model_health.is_unhealthy(&model.model_id) {
+                queue!(session.stderr, style::SetForegroundColor(Color::Yellow))?;
+            }
+            queue!(
+                session.stderr,
+                style::Print(format!("  {}: {description}\n", model.display_name())),
+                style::ResetColor
+            )?;
+        }
+        queue!(session.stderr, style::Print("\n"))?;
+    }
+
     let active_model_id = session.conversation.model_info.as_ref().map(|m| m.model_id.as_str());
+    let selection = pick_model("Select a model for this chat session", &models, active_model_id)?;
+
+    queue!(session.stderr, style::ResetColor)?;
+
+    if let Some(index) = selection {
+        queue!(session.stderr, style::Print("\n"))?;
+        if let Some(state) = switch_model(os, session, models[index].clone()).await? {
+            return Ok(Some(state));
+        }
+    }
+
+    execute!(session.stderr, style::ResetColor)?;
+
+    Ok(Some(ChatState::PromptUser {
+        skip_printing_tools: false,
+    }))
+}
+
+/// Switches the conversation to `model`, keeping its history. The history is sent to the new model
+/// as is, and the budget of the context files follows its context window. If the history doesn't
+/// fit in that window, the returned state compacts it before the next request.
+pub async fn switch_model(
+    os: &Os,
+    session: &mut ChatSession,
+    model: ModelInfo,
+) -> Result<Option<ChatState>, ChatError> {
+    let display_name = model.display_name().to_string();
+    session.conversation.set_model(model);
+
+    execute!(
+        session.stderr,
+        style::Print(format!(" Using {display_name}\n\n")),
+        style::ResetColor,
+        style::SetForegroundColor(Color::Reset),
+        style::SetBackgroundColor(Color::Reset),
+    )?;
+
+    compact_to_fit(os, session).await
+}
+
+/// The state that compacts the history before the next request if it doesn't fit in the context
+/// window of the model the conversation just switched to, unless auto compaction is disabled.
+pub async fn compact_to_fit(os: &Os, session: &mut ChatSession) -> Result<Option<ChatState>, ChatError> {
+    if session.conversation.history().is_empty()
+        || session.conversation.get_token_warning_level(os).await? == TokenWarningLevel::None
+    {
+        return Ok(None);
+    }
+
+    let context_window = context_window_tokens(session.conversation.model_info.as_ref());
+    let display_name = session
+        .conversation
+        .model_info
+        .as_ref()
+        .map_or("the model", |model| model.display_name())
+        .to_string();
+
+    let message =
+        format!("The conversation is larger than the {context_window} token context window of {display_name}");
+    if os
+        .database
+        .settings
+        .get_bool(Setting::ChatDisableAutoCompaction)
+        .unwrap_or(false)
+    {
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!("{message}, run /compact before continuing.\n\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        return Ok(None);
+    }
+
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::Yellow),
+        style::Print(format!("{message}, summarizing the history...\n\n")),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    Ok(Some(ChatState::CompactHistory {
+        prompt: None,
+        show_summary: false,
+        strategy: CompactStrategy::default(),
+    }))
+}
 
+/// Shows an interactive picker over `models`, returning the index of the chosen model or `None`
+/// if the picker was dismissed.
+pub fn pick_model(
+    prompt: &str,
+    models: &[ModelInfo],
+    active_model_id: Option<&str>,
+) -> Result<Option<usize>, ChatError> {
     let labels: Vec<String> = models
         .iter()
         .map(|model| {
@@ -121,8 +276,8 @@ pub async fn select_model(os: &Os, session: &mut ChatSession) -> Result<Option<C
         })
         .collect();
 
-    let selection: Option<_> = match Select::with_theme(&crate::util::dialoguer_theme())
-        .with_prompt("Select a model for this chat session")
+    match Select::with_theme(&crate::util::dialoguer_theme())
+        .with_prompt(prompt)
         .items(&labels)
         .default(0)
         .interact_on_opt(&dialoguer::console::Term::stdout())
@@ -132,57 +287,45 @@ pub async fn select_model(os: &Os, session: &mut ChatSession) -> Result<Option<C
                 std::io::stdout(),
                 crossterm::style::SetForegroundColor(crossterm::style::Color::Magenta)
             );
-            sel
+            Ok(sel)
         },
         // Ctrl‑C -> Err(Interrupted)
-        Err(dialoguer::Error::IO(ref e)) if e.kind() == std::io::ErrorKind::Interrupted => return Ok(None),
-        Err(e) => return Err(ChatError::Custom(format!("Failed to choose model: {e}").into())),
-    };
-
-    queue!(session.stderr, style::ResetColor)?;
-
-    if let Some(index) = selection {
-        let selected = models[index].clone();
-        session.conversation.model_info = Some(selected.clone());
-        let display_name = selected.display_name();
-
-        queue!(
-            session.stderr,
-            style::Print("\n"),
-            style::Print(format!(" Using {}\n\n", display_name)),
-            style::ResetColor,
-            style::SetForegroundColor(Color::Reset),
-            style::SetBackgroundColor(Color::Reset),
-        )?;
+        Err(dialoguer::Error::IO(ref e)) if e.kind() == std::io::ErrorKind::Interrupted => Ok(None),
+        Err(e) => Err(ChatError::Custom(format!("Failed to choose model: {e}").into())),
     }
-
-    execute!(session.stderr, style::ResetColor)?;
-
-    Ok(Some(ChatState::PromptUser {
-        skip_printing_tools: false,
-    }))
 }
 
 pub async fn get_model_info(model_id: &str, os: &Os) -> Result<ModelInfo, ChatError> {
     let (models, _) = get_available_models(os).await?;
 
-    models
-        .into_iter()
-        .find(|m| m.model_id == model_id)
-        .ok_or_else(|| ChatError::Custom(format!("Model '{}' not found", model_id).into()))
+    models.into_iter().find(|m| m.model_id == model_id).ok_or_else(|| {
+        CategorizedError::config_invalid(format!("Model '{}' not found", model_id))
+            .with_remediation("Run /model to list the models that are currently available.")
+            .into()
+    })
 }
 
-/// Get available models with caching support
+/// The models available to the profile in the configured region, and which of them is the
+/// default. They are cached between invocations for [MODEL_CATALOG_TTL].
 pub async fn get_available_models(os: &Os) -> Result<(Vec<ModelInfo>, ModelInfo), ChatError> {
     let endpoint = Endpoint::configured_value(&os.database);
     let region = endpoint.region().as_ref();
 
+    let cache_key = model_catalog_cache_key(os, region);
+    if let Some(cached) = os.database.get_cached(&cache_key, MODEL_CATALOG_TTL) {
+        tracing::debug!("Using the model catalog cached by an earlier invocation");
+        return Ok(cached);
+    }
+
     match os.client.get_available_models(region).await {
         Ok(api_res) => {
             let models: Vec<ModelInfo> = api_res.models.iter().map(ModelInfo::from_api_model).collect();
             let default_model = ModelInfo::from_api_model(&api_res.default_model);
 
             tracing::debug!("Successfully fetched {} models from API", models.len());
+            if let Err(err) = os.database.set_cached(&cache_key, (&models, &default_model)) {
+                tracing::warn!(?err, "Failed to cache the model catalog");
+            }
             Ok((models, default_model))
         },
         // In case of API throttling or other errors, fall back to hardcoded models
@@ -197,6 +340,24 @@ pub async fn get_available_models(os: &Os) -> Result<(Vec<ModelInfo>, ModelInfo)
     }
 }
 
+/// Forgets the models fetched so far, e.g. when one of them turned out to be unavailable.
+pub async fn invalidate_model_catalog(os: &Os) {
+    os.client.invalidate_model_cache().await;
+    let endpoint = Endpoint::configured_value(&os.database);
+    if let Err(err) = os
+        .database
+        .clear_cached(&model_catalog_cache_key(os, endpoint.region().as_ref()))
+    {
+        tracing::warn!(?err, "Failed to clear the cached model catalog");
+    }
+}
+
+/// The models depend on both the region and the profile.
+fn model_catalog_cache_key(os: &Os, region: &str) -> String {
+    let profile = os.database.get_auth_profile().ok().flatten().map(|profile| profile.arn);
+    format!("models.{region}.{}", profile.unwrap_or_default())
+}
+
 /// Returns the context window length in tokens for the given model_id.
 /// Uses cached model data when available
 pub fn context_window_tokens(model_info: Option<&ModelInfo>) -> usize {
@@ -0,0 +1,144 @@
+use std::path::Path;
+
+use clap::Args;
+use crossterm::style::{
+    self,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+
+use crate::cli::chat::file_reference::{
+    FileReference,
+    find_references,
+};
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "Opens files in your editor at the cited line. Files are opened with $EDITOR, or with the
+command in the chat.editorCommand setting, where {file} and {line} are replaced with the file and
+the line, e.g.
+
+  q settings chat.editorCommand \"code -g {file}:{line}\"
+
+In terminals that support hyperlinks, files cited in responses can also be clicked."
+)]
+pub struct OpenArgs {
+    /// A file to open, as `path`, `path:line` or `last` for the files cited in the last response
+    pub target: String,
+}
+
+impl OpenArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let cwd = os.env.current_dir()?;
+        let references = if self.target == "last" {
+            let last_response = session
+                .conversation
+                .transcript
+                .iter()
+                .rev()
+                .find(|msg| !msg.starts_with("> "))
+                .cloned()
+                .unwrap_or_default();
+            let references = find_references(&last_response, &cwd);
+            if references.is_empty() {
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print("\nThe last response doesn't cite any existing file.\n\n"),
+                    style::SetForegroundColor(Color::Reset)
+                )?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: true,
+                });
+            }
+            match choose_references(session, references, &cwd)? {
+                Some(references) => references,
+                None => {
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                },
+            }
+        } else {
+            match FileReference::parse(&self.target, &cwd) {
+                Some(reference) => vec![reference],
+                None => return Err(ChatError::Custom(format!("No such file: {}", self.target).into())),
+            }
+        };
+
+        let template = os.database.settings.get_string(Setting::ChatEditorCommand);
+        for reference in references {
+            let Some(args) = reference.editor_command(template.as_deref()) else {
+                return Err(ChatError::Custom(
+                    "Failed to parse the editor command, check the chat.editorCommand setting or EDITOR".into(),
+                ));
+            };
+            let status = std::process::Command::new(&args[0])
+                .args(&args[1..])
+                .current_dir(&cwd)
+                .status()
+                .map_err(|e| ChatError::Custom(format!("Failed to run {}: {e}", args[0]).into()))?;
+            if !status.success() {
+                return Err(ChatError::Custom(format!("{} exited with {status}", args[0]).into()));
+            }
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Asks which of the cited files to open when there are several. Returns `None` if the user
+/// cancels.
+fn choose_references(
+    session: &mut ChatSession,
+    mut references: Vec<FileReference>,
+    cwd: &Path,
+) -> Result<Option<Vec<FileReference>>, ChatError> {
+    if references.len() == 1 {
+        return Ok(Some(references));
+    }
+
+    queue!(session.stderr, style::Print("\nFiles cited in the last response:\n"))?;
+    for (i, reference) in references.iter().enumerate() {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("  {}. ", i + 1)),
+            style::SetForegroundColor(Color::Green),
+            style::Print(reference.display(cwd)),
+            style::SetForegroundColor(Color::Reset),
+            style::Print("\n"),
+        )?;
+    }
+    execute!(session.stderr, style::Print("\n"))?;
+
+    loop {
+        let Some(answer) = session.read_user_input(&format!("Open which? [1-{}/a]: ", references.len()), true) else {
+            return Ok(None);
+        };
+        let answer = answer.trim();
+        if answer.eq_ignore_ascii_case("a") {
+            return Ok(Some(references));
+        }
+        if let Some(index) = answer
+            .parse::<usize>()
+            .ok()
+            .filter(|index| (1..=references.len()).contains(index))
+        {
+            return Ok(Some(vec![references.swap_remove(index - 1)]));
+        }
+    }
+}
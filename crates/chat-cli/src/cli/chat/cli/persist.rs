@@ -108,6 +108,7 @@ impl PersistSubcommand {
                     &mut session.conversation.context_manager,
                 );
                 std::mem::swap(&mut new_state.agents, &mut session.conversation.agents);
+                std::mem::swap(&mut new_state.session_usage, &mut session.conversation.session_usage);
                 session.conversation = new_state;
 
                 execute!(
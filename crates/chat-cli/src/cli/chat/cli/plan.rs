@@ -0,0 +1,202 @@
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::style::{
+    self,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "/plan toggles plan mode. While it is on, tools that would change something (fs_write,
+execute_bash commands that aren't read-only and use_aws operations that aren't read-only) are
+recorded as steps of a plan instead of being run. Read-only tools still run, so the assistant can
+look around while planning.
+
+Review the plan with /plan show, then run all of its steps with /plan apply after a single
+confirmation."
+)]
+pub struct PlanArgs {
+    #[command(subcommand)]
+    pub subcommand: Option<PlanSubcommand>,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum PlanSubcommand {
+    /// Show the recorded steps
+    Show,
+    /// Run the recorded steps in order, then leave plan mode
+    Apply,
+    /// Discard the recorded steps
+    Clear,
+}
+
+impl PlanArgs {
+    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        match self.subcommand {
+            None => {
+                session.plan.active = !session.plan.active;
+                let steps = session.plan.steps().len();
+                if session.plan.active {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print("\nPlan mode is on. "),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("Changes will be recorded instead of made. Use /plan apply to run them.\n"),
+                    )?;
+                } else {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print("\nPlan mode is off.\n"),
+                    )?;
+                    if steps > 0 {
+                        queue!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(format!(
+                                "{steps} recorded step{} can still be applied with /plan apply or discarded with /plan clear.\n",
+                                if steps == 1 { "" } else { "s" }
+                            )),
+                        )?;
+                    }
+                }
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print("\n")
+                )?;
+            },
+            Some(PlanSubcommand::Show) => {
+                show_steps(os, session).await?;
+            },
+            Some(PlanSubcommand::Clear) => {
+                session.plan.clear();
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print("\nDiscarded the recorded steps.\n\n"),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            },
+            Some(PlanSubcommand::Apply) => {
+                if !show_steps(os, session).await? {
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+
+                let count = session.plan.steps().len();
+                let confirmation = session.read_user_input(&format!("Apply {count} step(s)? [y/n]: "), true);
+                if !confirmation.is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y")) {
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("\nThe plan was not applied.\n\n"),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                }
+
+                let mut steps = session.plan.take_steps().into_iter().enumerate();
+                while let Some((index, step)) = steps.next() {
+                    queue!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Magenta),
+                        style::Print(format!("\nStep {}: {}\n", index + 1, step.tool.display_name())),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    let result = step
+                        .tool
+                        .invoke(
+                            os,
+                            &mut session.stdout,
+                            &mut session.conversation.file_line_tracker,
+                            &session.conversation.agents,
+                        )
+                        .await;
+                    if let Err(err) = result {
+                        execute!(
+                            session.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!("\n● Step {} failed: {err}\n", index + 1)),
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print("It and the steps after it were kept in the plan.\n\n"),
+                            style::SetForegroundColor(Color::Reset),
+                        )?;
+                        let remaining = std::iter::once(step).chain(steps.map(|(_, step)| step)).collect();
+                        session.plan.restore_steps(remaining);
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    }
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::Green),
+                        style::Print("\n● Done\n"),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                }
+
+                session.plan.active = false;
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(format!("\n✓ Applied {count} step(s). Plan mode is off.\n\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+            },
+        }
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Prints the recorded steps, returning whether there are any.
+async fn show_steps(os: &Os, session: &mut ChatSession) -> Result<bool, ChatError> {
+    if session.plan.steps().is_empty() {
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print("\nNo steps have been recorded. Turn on plan mode with /plan.\n\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        return Ok(false);
+    }
+
+    for (index, step) in session.plan.steps().iter().enumerate() {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Magenta),
+            style::Print(format!("\nStep {}: {}\n", index + 1, step.tool.display_name())),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        step.tool
+            .queue_description(os, &mut session.stderr)
+            .await
+            .map_err(|e| ChatError::Custom(format!("failed to print step, `{}`: {}", step.name, e).into()))?;
+        queue!(session.stderr, style::Print("\n"))?;
+    }
+    execute!(session.stderr, style::Print("\n"))?;
+
+    Ok(true)
+}
@@ -28,13 +28,23 @@ use syntect::util::{
     as_24_bit_terminal_escaped,
 };
 
+use crate::cli::DEFAULT_AGENT_NAME;
 use crate::cli::agent::{
     Agent,
     Agents,
     McpServerConfig,
     create_agent,
 };
+use crate::cli::chat::cli::model::{
+    find_model,
+    get_available_models,
+    switch_model,
+};
 use crate::cli::chat::conversation::McpServerInfo;
+use crate::cli::chat::tools::delegate::{
+    resolve_working_directory,
+    run_agent,
+};
 use crate::cli::chat::{
     ChatError,
     ChatSession,
@@ -111,6 +121,18 @@ pub enum AgentSubcommand {
         /// Optional name of the agent to swap to. If not provided, a selection dialog will be shown
         name: Option<String>,
     },
+    /// Run a task with a child agent and add its final response to the conversation
+    Spawn {
+        /// Name of the agent to run the task with. Defaults to the default agent
+        #[arg(long, short)]
+        agent: Option<String>,
+        /// Directory to run the agent in. Defaults to the current working directory
+        #[arg(long)]
+        cwd: Option<String>,
+        /// The task for the agent
+        #[arg(required = true, num_args = 1..)]
+        task: Vec<String>,
+    },
 }
 
 fn prompt_mcp_server_selection(servers: &[McpServerInfo]) -> eyre::Result<Option<Vec<&McpServerInfo>>> {
@@ -452,7 +474,9 @@ impl AgentSubcommand {
             },
             Self::Swap { name } => {
                 if let Some(name) = name {
-                    session.conversation.swap_agent(os, &mut session.stderr, &name).await?;
+                    if let Some(state) = swap_agent(os, session, &name).await? {
+                        return Ok(state);
+                    }
                 } else {
                     let labels = session
                         .conversation
@@ -489,10 +513,41 @@ impl AgentSubcommand {
                     };
 
                     if let Some(name) = name {
-                        session.conversation.swap_agent(os, &mut session.stderr, &name).await?;
+                        if let Some(state) = swap_agent(os, session, &name).await? {
+                            return Ok(state);
+                        }
                     }
                 }
             },
+            Self::Spawn { agent, cwd, task } => {
+                let agent = agent.unwrap_or_else(|| DEFAULT_AGENT_NAME.to_string());
+                let task = task.join(" ");
+                let cwd = match cwd.map(|dir| resolve_working_directory(os, &dir)).transpose() {
+                    Ok(cwd) => cwd,
+                    Err(err) => {
+                        _print_err!(err);
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                };
+
+                execute!(
+                    session.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("\nRunning the task with agent {agent}...\n\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+                match run_agent(&agent, agents, &task, cwd.as_deref(), &mut session.stderr).await {
+                    // Hand the result to the model, the same way the delegate tool would.
+                    Ok(result) => {
+                        return Ok(ChatState::HandleInput {
+                            input: format!("I ran the task \"{task}\" with a child agent. {result}"),
+                        });
+                    },
+                    Err(err) => _print_err!(err),
+                }
+            },
         }
 
         Ok(ChatState::PromptUser {
@@ -511,10 +566,48 @@ impl AgentSubcommand {
             Self::Schema => "schema",
             Self::SetDefault { .. } => "set_default",
             Self::Swap { .. } => "swap",
+            Self::Spawn { .. } => "spawn",
         }
     }
 }
 
+/// Swaps to the agent `name`, and to the model it configures, if any, keeping the conversation.
+async fn swap_agent(os: &mut Os, session: &mut ChatSession, name: &str) -> Result<Option<ChatState>, ChatError> {
+    session.conversation.swap_agent(os, &mut session.stderr, name).await?;
+
+    let Some(agent_model) = session.conversation.agents.get_active().and_then(|a| a.model.clone()) else {
+        return Ok(None);
+    };
+    let (models, _) = get_available_models(os).await?;
+    match find_model(&models, &agent_model) {
+        Some(model)
+            if session
+                .conversation
+                .model_info
+                .as_ref()
+                .is_some_and(|current| current.model_id == model.model_id) =>
+        {
+            Ok(None)
+        },
+        Some(model) => {
+            let model = model.clone();
+            queue!(session.stderr, style::Print("\n"))?;
+            switch_model(os, session, model).await
+        },
+        None => {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!(
+                    "Agent {name} specifies model '{agent_model}', which is not available. Keeping the current model.\n"
+                )),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            Ok(None)
+        },
+    }
+}
+
 fn highlight_json(output: &mut impl Write, json_str: &str) -> eyre::Result<()> {
     let ps = SyntaxSet::load_defaults_newlines();
     let ts = ThemeSet::load_defaults();
@@ -24,6 +24,7 @@ use crossterm::{
 };
 use regex::Regex;
 use rmcp::model::{
+    PromptArgument,
     PromptMessage,
     PromptMessageContent,
     PromptMessageRole,
@@ -33,6 +34,7 @@ use thiserror::Error;
 use unicode_width::UnicodeWidthStr;
 
 use crate::cli::chat::cli::editor::open_editor_file;
+use crate::cli::chat::token_counter::TokenCounter;
 use crate::cli::chat::tool_manager::PromptBundle;
 use crate::cli::chat::{
     ChatError,
@@ -577,6 +579,104 @@ fn display_missing_args_error(
     Ok(())
 }
 
+/// The required arguments of `schema` that `arguments` leaves out, with their position.
+/// Arguments are given by position, in the order the prompt declares them.
+fn missing_required_arguments<'a>(
+    schema: &'a [PromptArgument],
+    arguments: &[String],
+) -> Vec<(usize, &'a PromptArgument)> {
+    schema
+        .iter()
+        .enumerate()
+        .skip(arguments.len())
+        .filter(|(_, arg)| arg.required == Some(true))
+        .collect()
+}
+
+/// Asks for each required argument of the MCP prompt `name` that wasn't given, one at a time.
+///
+/// Returns the arguments to get the prompt with, or `None` if the user cancelled. Optional
+/// arguments in between that weren't given are left empty.
+async fn fill_missing_arguments(
+    session: &mut ChatSession,
+    name: &str,
+    mut arguments: Vec<String>,
+) -> Result<Option<Vec<String>>, ChatError> {
+    let (server_name, prompt_name) = match name.split_once('/') {
+        Some((server_name, prompt_name)) => (Some(server_name), prompt_name),
+        None => (None, name),
+    };
+    // Ambiguous or unknown prompts are reported when getting them.
+    let prompts = session
+        .conversation
+        .tool_manager
+        .list_prompts()
+        .await
+        .unwrap_or_default();
+    let schema = prompts
+        .get(prompt_name)
+        .and_then(|bundles| match server_name {
+            Some(server_name) => bundles.iter().find(|b| b.server_name == server_name),
+            None if bundles.len() == 1 => bundles.first(),
+            None => None,
+        })
+        .and_then(|bundle| bundle.prompt_get.arguments.clone())
+        .unwrap_or_default();
+
+    let missing = missing_required_arguments(&schema, &arguments);
+    if missing.is_empty() {
+        return Ok(Some(arguments));
+    }
+
+    queue!(
+        session.stderr,
+        style::Print("\n"),
+        style::SetForegroundColor(Color::Cyan),
+        style::Print(format!("@{name}")),
+        style::SetForegroundColor(Color::Reset),
+        style::Print(format!(
+            " needs {} more argument{}. ",
+            missing.len(),
+            if missing.len() == 1 { "" } else { "s" }
+        )),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print("Press Ctrl+C to cancel.\n"),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+
+    for (position, arg) in missing {
+        queue!(
+            session.stderr,
+            style::Print("\n"),
+            style::SetForegroundColor(Color::Cyan),
+            style::Print(arg.title.as_deref().unwrap_or(&arg.name)),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        if let Some(description) = arg.description.as_deref().filter(|d| !d.trim().is_empty()) {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(" - {description}")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        execute!(session.stderr, style::Print("\n"))?;
+
+        // Required arguments can't be left empty, so this asks again until something is entered.
+        let Some(value) = session.read_user_input(&format!("{}> ", arg.name), true) else {
+            execute!(session.stderr, style::Print("\n"))?;
+            return Ok(None);
+        };
+        if arguments.len() <= position {
+            arguments.resize(position + 1, String::new());
+        }
+        arguments[position] = value.trim().to_string();
+    }
+    execute!(session.stderr, style::Print("\n"))?;
+
+    Ok(Some(arguments))
+}
+
 /// Command-line arguments for prompt operations
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
@@ -605,6 +705,7 @@ impl PromptsArgs {
             if matches!(
                 subcommand,
                 PromptsSubcommand::Get { .. }
+                    | PromptsSubcommand::Preview { .. }
                     | PromptsSubcommand::Details { .. }
                     | PromptsSubcommand::Create { .. }
                     | PromptsSubcommand::Edit { .. }
@@ -866,6 +967,13 @@ pub enum PromptsSubcommand {
         /// Optional arguments for the prompt
         arguments: Option<Vec<String>>,
     },
+    /// Show a prompt as it would be sent, arguments included, without sending it
+    Preview {
+        /// Name of the prompt to preview
+        name: String,
+        /// Optional arguments for the prompt
+        arguments: Option<Vec<String>>,
+    },
     /// Create a new local prompt
     Create {
         /// Name of the prompt to create
@@ -905,6 +1013,7 @@ impl PromptsSubcommand {
                 name,
                 arguments,
             } => Self::execute_get(os, session, orig_input, name, arguments).await,
+            PromptsSubcommand::Preview { name, arguments } => Self::execute_preview(os, session, name, arguments).await,
             PromptsSubcommand::Create { name, content, global } => {
                 Self::execute_create(os, session, name, content, global).await
             },
@@ -1351,11 +1460,25 @@ impl PromptsSubcommand {
             });
         }
 
-        // If not found locally, try MCP prompts
+        // If not found locally, try MCP prompts, asking for the required arguments that are missing
+        // rather than letting the server reject the request.
+        let arguments = if session.interactive {
+            match fill_missing_arguments(session, &name, arguments.unwrap_or_default()).await? {
+                Some(arguments) => Some(arguments),
+                None => {
+                    return Ok(ChatState::PromptUser {
+                        skip_printing_tools: true,
+                    });
+                },
+            }
+        } else {
+            arguments
+        };
+
         let prompts = match session
             .conversation
             .tool_manager
-            .get_prompt(name.clone(), arguments)
+            .get_prompt(os, name.clone(), arguments)
             .await
         {
             Ok(resp) => {
@@ -1364,76 +1487,7 @@ impl PromptsSubcommand {
                 resp
             },
             Err(e) => {
-                match e {
-                    GetPromptError::AmbiguousPrompt(prompt_name, alt_msg) => {
-                        queue!(
-                            session.stderr,
-                            style::Print("\n"),
-                            style::SetForegroundColor(Color::Yellow),
-                            style::Print("Prompt "),
-                            style::SetForegroundColor(Color::Cyan),
-                            style::Print(prompt_name),
-                            style::SetForegroundColor(Color::Yellow),
-                            style::Print(" is ambiguous. Use one of the following "),
-                            style::SetForegroundColor(Color::Cyan),
-                            style::Print(alt_msg),
-                            style::SetForegroundColor(Color::Reset),
-                        )?;
-                    },
-                    GetPromptError::PromptNotFound(prompt_name) => {
-                        queue!(
-                            session.stderr,
-                            style::Print("\n"),
-                            style::SetForegroundColor(Color::Yellow),
-                            style::Print("Prompt "),
-                            style::SetForegroundColor(Color::Cyan),
-                            style::Print(prompt_name),
-                            style::SetForegroundColor(Color::Yellow),
-                            style::Print(" not found. Use "),
-                            style::SetForegroundColor(Color::Cyan),
-                            style::Print("/prompts list"),
-                            style::SetForegroundColor(Color::Yellow),
-                            style::Print(" to see available prompts.\n"),
-                            style::SetForegroundColor(Color::Reset),
-                        )?;
-                    },
-                    GetPromptError::McpClient(_) | GetPromptError::Service(_) => {
-                        let error_str = e.to_string();
-
-                        // Check for specific MCP error codes in the error string
-                        if error_str.contains("-32602") {
-                            // Invalid params error
-                            let prompts_list = session
-                                .conversation
-                                .tool_manager
-                                .list_prompts()
-                                .await
-                                .unwrap_or_default();
-                            handle_mcp_invalid_params_error(&name, &error_str, &prompts_list, session)?;
-                        } else if error_str.contains("-32603") {
-                            // Internal server error
-                            handle_mcp_internal_error(&name, &error_str, session)?;
-                        } else {
-                            // Other MCP errors - show generic message
-                            queue!(
-                                session.stderr,
-                                style::Print("\n"),
-                                style::SetForegroundColor(Color::Yellow),
-                                style::Print("Error: Failed to execute prompt "),
-                                style::SetForegroundColor(Color::Cyan),
-                                style::Print(&name),
-                                style::SetForegroundColor(Color::Yellow),
-                                style::Print(". "),
-                                style::Print(&error_str),
-                                style::SetForegroundColor(Color::Reset),
-                                style::Print("\n"),
-                            )?;
-                            execute!(session.stderr)?;
-                        }
-                    },
-                    _ => return Err(ChatError::Custom(e.to_string().into())),
-                }
-                execute!(session.stderr, style::Print("\n"))?;
+                display_get_prompt_error(&name, e, session).await?;
                 return Ok(ChatState::PromptUser {
                     skip_printing_tools: true,
                 });
@@ -1448,6 +1502,60 @@ impl PromptsSubcommand {
         })
     }
 
+    async fn execute_preview(
+        os: &Os,
+        session: &mut ChatSession,
+        name: String,
+        arguments: Option<Vec<String>>,
+    ) -> Result<ChatState, ChatError> {
+        // Resolved the same way as /prompts get: file-based prompts first, then MCP prompts.
+        let prompts = Prompts::new(&name, os).map_err(|e| ChatError::Custom(e.to_string().into()))?;
+        let messages = match prompts
+            .load_existing()
+            .map_err(|e| ChatError::Custom(e.to_string().into()))?
+        {
+            Some((content, _)) => vec![PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::Text { text: content },
+            }],
+            None => {
+                let arguments = if session.interactive {
+                    match fill_missing_arguments(session, &name, arguments.unwrap_or_default()).await? {
+                        Some(arguments) => Some(arguments),
+                        None => {
+                            return Ok(ChatState::PromptUser {
+                                skip_printing_tools: true,
+                            });
+                        },
+                    }
+                } else {
+                    arguments
+                };
+
+                match session
+                    .conversation
+                    .tool_manager
+                    .get_prompt(os, name.clone(), arguments)
+                    .await
+                {
+                    Ok(resp) => resp.messages,
+                    Err(e) => {
+                        display_get_prompt_error(&name, e, session).await?;
+                        return Ok(ChatState::PromptUser {
+                            skip_printing_tools: true,
+                        });
+                    },
+                }
+            },
+        };
+
+        display_prompt_preview(&name, &messages, session)?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+
     async fn execute_create(
         os: &Os,
         session: &mut ChatSession,
@@ -1979,6 +2087,7 @@ impl PromptsSubcommand {
             PromptsSubcommand::List { .. } => "list",
             PromptsSubcommand::Details { .. } => "details",
             PromptsSubcommand::Get { .. } => "get",
+            PromptsSubcommand::Preview { .. } => "preview",
             PromptsSubcommand::Create { .. } => "create",
             PromptsSubcommand::Edit { .. } => "edit",
             PromptsSubcommand::Remove { .. } => "remove",
@@ -1986,34 +2095,153 @@ impl PromptsSubcommand {
     }
 }
 
+/// Reports a failure to get the prompt `name` in a user-friendly way.
+async fn display_get_prompt_error(name: &str, e: GetPromptError, session: &mut ChatSession) -> Result<(), ChatError> {
+    match e {
+        GetPromptError::AmbiguousPrompt(prompt_name, alt_msg) => {
+            queue!(
+                session.stderr,
+                style::Print("\n"),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("Prompt "),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(prompt_name),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(" is ambiguous. Use one of the following "),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(alt_msg),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        },
+        GetPromptError::PromptNotFound(prompt_name) => {
+            queue!(
+                session.stderr,
+                style::Print("\n"),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("Prompt "),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(prompt_name),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(" not found. Use "),
+                style::SetForegroundColor(Color::Cyan),
+                style::Print("/prompts list"),
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(" to see available prompts.\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        },
+        GetPromptError::McpClient(_) | GetPromptError::Service(_) => {
+            let error_str = e.to_string();
+
+            // Check for specific MCP error codes in the error string
+            if error_str.contains("-32602") {
+                // Invalid params error
+                let prompts_list = session
+                    .conversation
+                    .tool_manager
+                    .list_prompts()
+                    .await
+                    .unwrap_or_default();
+                handle_mcp_invalid_params_error(name, &error_str, &prompts_list, session)?;
+            } else if error_str.contains("-32603") {
+                // Internal server error
+                handle_mcp_internal_error(name, &error_str, session)?;
+            } else {
+                // Other MCP errors - show generic message
+                queue!(
+                    session.stderr,
+                    style::Print("\n"),
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print("Error: Failed to execute prompt "),
+                    style::SetForegroundColor(Color::Cyan),
+                    style::Print(name),
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(". "),
+                    style::Print(&error_str),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print("\n"),
+                )?;
+                execute!(session.stderr)?;
+            }
+        },
+        _ => return Err(ChatError::Custom(e.to_string().into())),
+    }
+    execute!(session.stderr, style::Print("\n"))?;
+    Ok(())
+}
+
+fn stringify_prompt_message_content(content: &PromptMessageContent) -> String {
+    match content {
+        PromptMessageContent::Text { text } => text.clone(),
+        PromptMessageContent::Image { image } => image.raw.data.clone(),
+        PromptMessageContent::Resource { resource } => match &resource.raw.resource {
+            rmcp::model::ResourceContents::TextResourceContents {
+                uri, mime_type, text, ..
+            } => {
+                let mime_type = mime_type.as_deref().unwrap_or("unknown");
+                format!("Text resource of uri: {uri}, mime_type: {mime_type}, text: {text}")
+            },
+            rmcp::model::ResourceContents::BlobResourceContents { uri, mime_type, .. } => {
+                let mime_type = mime_type.as_deref().unwrap_or("unknown");
+                format!("Blob resource of uri: {uri}, mime_type: {mime_type}")
+            },
+        },
+        PromptMessageContent::ResourceLink { link } => {
+            format!("Resource link with uri: {}, name: {}", link.raw.uri, link.raw.name)
+        },
+    }
+}
+
+/// Displays each message of a prompt with its role, followed by an estimate of its size.
+fn display_prompt_preview(name: &str, messages: &[PromptMessage], session: &mut ChatSession) -> Result<(), ChatError> {
+    let terminal_width = session.terminal_width();
+    queue!(
+        session.stderr,
+        style::Print("\n"),
+        style::SetAttribute(Attribute::Bold),
+        style::Print(format!("Preview of @{name}")),
+        style::SetAttribute(Attribute::Reset),
+        style::Print("\n"),
+        style::Print("▔".repeat(terminal_width)),
+        style::Print("\n"),
+    )?;
+
+    let mut tokens = 0;
+    for message in messages {
+        let content = stringify_prompt_message_content(&message.content);
+        tokens += TokenCounter::count_tokens(&content);
+        let role = match message.role {
+            PromptMessageRole::User => "user",
+            PromptMessageRole::Assistant => "assistant",
+        };
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Cyan),
+            style::Print(format!("[{role}]\n")),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(content.trim_end()),
+            style::Print("\n\n"),
+        )?;
+    }
+
+    execute!(
+        session.stderr,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!(
+            "~{tokens} tokens in {} message(s). Nothing was sent, use @{name} to send it.\n\n",
+            messages.len()
+        )),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    Ok(())
+}
+
 /// Display fetched prompt content to the user before AI processing
 fn display_prompt_content(
     _prompt_name: &str,
     messages: &[PromptMessage],
     session: &mut ChatSession,
 ) -> Result<(), ChatError> {
-    fn stringify_prompt_message_content(content: &PromptMessageContent) -> String {
-        match content {
-            PromptMessageContent::Text { text } => text.clone(),
-            PromptMessageContent::Image { image } => image.raw.data.clone(),
-            PromptMessageContent::Resource { resource } => match &resource.raw.resource {
-                rmcp::model::ResourceContents::TextResourceContents {
-                    uri, mime_type, text, ..
-                } => {
-                    let mime_type = mime_type.as_deref().unwrap_or("unknown");
-                    format!("Text resource of uri: {uri}, mime_type: {mime_type}, text: {text}")
-                },
-                rmcp::model::ResourceContents::BlobResourceContents { uri, mime_type, .. } => {
-                    let mime_type = mime_type.as_deref().unwrap_or("unknown");
-                    format!("Blob resource of uri: {uri}, mime_type: {mime_type}")
-                },
-            },
-            PromptMessageContent::ResourceLink { link } => {
-                format!("Resource link with uri: {}, name: {}", link.raw.uri, link.raw.name)
-            },
-        }
-    }
-
     queue!(session.stderr, style::Print("\n"),)?;
 
     for message in messages {
@@ -2502,6 +2730,28 @@ mod tests {
         assert_eq!(all.len(), 2);
     }
 
+    #[test]
+    fn test_missing_required_arguments() {
+        let arg = |name: &str, required: bool| PromptArgument {
+            name: name.to_string(),
+            title: None,
+            description: None,
+            required: Some(required),
+        };
+        let schema = vec![arg("repo", true), arg("branch", false), arg("pr", true)];
+
+        let missing = |arguments: &[&str]| {
+            let arguments = arguments.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+            missing_required_arguments(&schema, &arguments)
+                .into_iter()
+                .map(|(position, arg)| (position, arg.name.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(missing(&[]), vec![(0, "repo".to_string()), (2, "pr".to_string())]);
+        assert_eq!(missing(&["q-cli"]), vec![(2, "pr".to_string())]);
+        assert!(missing(&["q-cli", "main", "42"]).is_empty());
+    }
+
     #[test]
     fn test_ambiguous_prompt_message_generation() {
         // Test generating disambiguation message
@@ -0,0 +1,92 @@
+use clap::Args;
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+
+use crate::cli::chat::resource_monitor::format_bytes;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::util::time::format_duration;
+
+/// Arguments for the stats command that shows the memory and CPU used by the session and the
+/// processes started by its tools.
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct StatsArgs;
+
+impl StatsArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let stats = session.resource_monitor.stats();
+        if stats.samples == 0 {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("\nNo resource usage has been sampled yet, try again in a few seconds.\n\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
+        let current = stats.current;
+        let rows = [
+            ("Running for", format_duration(session.resource_monitor.uptime())),
+            (
+                "Session",
+                format!("{}, {:.0}% CPU", format_bytes(current.memory), current.cpu),
+            ),
+            (
+                "Tool processes",
+                format!(
+                    "{} using {}, {:.0}% CPU",
+                    current.children,
+                    format_bytes(current.children_memory),
+                    current.children_cpu
+                ),
+            ),
+            (
+                "Peak",
+                format!("{}, {:.0}% CPU", format_bytes(stats.peak_memory), stats.peak_cpu),
+            ),
+        ];
+        queue!(
+            session.stderr,
+            style::SetAttribute(Attribute::Bold),
+            style::Print("\nResource usage\n"),
+            style::SetAttribute(Attribute::Reset),
+        )?;
+        for (label, value) in rows {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("  {label:<16}")),
+                style::SetForegroundColor(Color::Reset),
+                style::Print(value),
+                style::Print("\n"),
+            )?;
+        }
+        for warning in &stats.warnings {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!("  ! {warning}\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        execute!(session.stderr, style::Print("\n"))?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
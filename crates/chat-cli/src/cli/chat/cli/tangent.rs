@@ -29,8 +29,19 @@ pub struct TangentArgs {
 pub enum TangentSubcommand {
     /// Exit tangent mode and keep the last conversation entry (user question + assistant response)
     Tail,
+    /// Start a named tangent off the main conversation. The current tangent is kept if it is named
+    New { name: String },
+    /// Switch to a named tangent, or back to the main conversation with "main"
+    Switch { name: String },
+    /// Add a summary of the current tangent to the main conversation, and discard the tangent
+    Merge,
+    /// List the named tangents
+    List,
 }
 
+/// The name `/tangent switch` takes for the main conversation.
+const MAIN: &str = "main";
+
 impl TangentArgs {
     async fn send_tangent_telemetry(os: &Os, session: &ChatSession, duration_seconds: i64) {
         if let Err(err) = os
@@ -47,7 +58,54 @@ impl TangentArgs {
         }
     }
 
-    pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+    fn warn_checkpoint_disabled(os: &Os, session: &mut ChatSession) -> Result<(), ChatError> {
+        if ExperimentManager::is_enabled(os, ExperimentName::Checkpoint) {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(
+                    "⚠️ Checkpoint is disabled while in tangent mode. Please exit tangent mode if you want to use checkpoint.\n"
+                ),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns to the main conversation, if in a tangent. A named tangent is kept, an unnamed one
+    /// is discarded.
+    async fn leave_tangent(os: &Os, session: &mut ChatSession) -> Result<(), ChatError> {
+        if !session.conversation.is_in_tangent_mode() {
+            return Ok(());
+        }
+        let duration_seconds = session.conversation.get_tangent_duration_seconds().unwrap_or(0);
+        let discarded = session.conversation.tangent_name().is_none() && session.conversation.tangent_len() > 0;
+        session.conversation.exit_tangent_mode();
+        Self::send_tangent_telemetry(os, session, duration_seconds).await;
+
+        if discarded {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("Discarded the unnamed tangent.\n"),
+                style::SetForegroundColor(Color::Reset)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn print_error(session: &mut ChatSession, message: &str) -> Result<(), ChatError> {
+        execute!(
+            session.stderr,
+            style::SetForegroundColor(Color::Red),
+            style::Print(message),
+            style::Print("\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        Ok(())
+    }
+
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
         // Check if tangent mode is enabled
         if !ExperimentManager::is_enabled(os, ExperimentName::TangentMode) {
             execute!(
@@ -63,17 +121,7 @@ impl TangentArgs {
 
         match self.subcommand {
             Some(TangentSubcommand::Tail) => {
-                // Check if checkpoint is enabled
-                if ExperimentManager::is_enabled(os, ExperimentName::Checkpoint) {
-                    execute!(
-                        session.stderr,
-                        style::SetForegroundColor(Color::Yellow),
-                        style::Print(
-                            "⚠️ Checkpoint is disabled while in tangent mode. Please exit tangent mode if you want to use checkpoint.\n"
-                        ),
-                        style::SetForegroundColor(Color::Reset),
-                    )?;
-                }
+                Self::warn_checkpoint_disabled(os, session)?;
                 if session.conversation.is_in_tangent_mode() {
                     let duration_seconds = session.conversation.get_tangent_duration_seconds().unwrap_or(0);
                     session.conversation.exit_tangent_mode_with_tail();
@@ -90,16 +138,136 @@ impl TangentArgs {
                         style::SetForegroundColor(Color::Reset)
                     )?;
                 } else {
+                    Self::print_error(session, "You need to be in tangent mode to use tail.")?;
+                }
+            },
+            Some(TangentSubcommand::New { name }) => {
+                if name == MAIN {
+                    Self::print_error(session, "'main' is the name of the main conversation.")?;
+                } else if session.conversation.has_tangent(&name) {
+                    Self::print_error(
+                        session,
+                        &format!("A tangent named '{name}' already exists. Use /tangent switch {name} to go to it."),
+                    )?;
+                } else {
+                    Self::leave_tangent(os, session).await?;
+                    Self::warn_checkpoint_disabled(os, session)?;
+                    session.conversation.new_tangent(name.clone());
+
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("Started the tangent "),
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!("↯ {name}")),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(" off the main conversation. Use "),
+                        style::SetForegroundColor(Color::Green),
+                        style::Print("/tangent switch main"),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(" to go back to it, or "),
+                        style::SetForegroundColor(Color::Green),
+                        style::Print("/tangent merge"),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(" to add a summary of this tangent to it.\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+            },
+            Some(TangentSubcommand::Switch { name }) => {
+                if name == MAIN {
+                    Self::leave_tangent(os, session).await?;
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("Returned to main conversation.\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                } else if !session.conversation.has_tangent(&name) {
+                    Self::print_error(
+                        session,
+                        &format!("There is no tangent named '{name}'. Use /tangent list to see the tangents."),
+                    )?;
+                } else {
+                    if session.conversation.tangent_name() != Some(name.as_str()) {
+                        Self::leave_tangent(os, session).await?;
+                        Self::warn_checkpoint_disabled(os, session)?;
+                        session.conversation.switch_tangent(&name);
+                    }
                     execute!(
                         session.stderr,
-                        style::SetForegroundColor(Color::Red),
-                        style::Print("You need to be in tangent mode to use tail.\n"),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("Switched to the tangent "),
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!("↯ {name}")),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(".\n"),
                         style::SetForegroundColor(Color::Reset)
                     )?;
                 }
             },
+            Some(TangentSubcommand::Merge) => {
+                if !session.conversation.is_in_tangent_mode() {
+                    Self::print_error(session, "You need to be in tangent mode to merge it.")?;
+                } else if session.conversation.tangent_len() == 0 {
+                    Self::print_error(session, "There is nothing to merge: the tangent has no messages yet.")?;
+                } else {
+                    let duration_seconds = session.conversation.get_tangent_duration_seconds().unwrap_or(0);
+                    let (summary, request_metadata) = session.summarize_tangent(os).await?;
+                    session.conversation.merge_tangent(summary, request_metadata);
+                    Self::send_tangent_telemetry(os, session, duration_seconds).await;
+
+                    execute!(
+                        session.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print("Added a summary of the tangent ("),
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("↯"),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(") to the main conversation. - Returned to main conversation.\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+            },
+            Some(TangentSubcommand::List) => {
+                let current = session.conversation.tangent_name().map(str::to_string);
+                let in_main = !session.conversation.is_in_tangent_mode();
+                let mut names = session
+                    .conversation
+                    .parked_tangents()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                names.extend(current.clone());
+                names.sort_unstable();
+
+                let marker = |is_current: bool| if is_current { "* " } else { "  " };
+                execute!(session.stderr, style::Print(format!("\n{}{}\n", marker(in_main), MAIN)))?;
+                for name in &names {
+                    execute!(
+                        session.stderr,
+                        style::Print(marker(current.as_ref() == Some(name))),
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print(format!("↯ {name}\n")),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+                if !in_main && current.is_none() {
+                    execute!(
+                        session.stderr,
+                        style::Print("* "),
+                        style::SetForegroundColor(Color::Yellow),
+                        style::Print("↯"),
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(" (unnamed, discarded when you leave it)\n"),
+                        style::SetForegroundColor(Color::Reset)
+                    )?;
+                }
+                execute!(session.stderr, style::Print("\n"))?;
+            },
             None => {
                 if session.conversation.is_in_tangent_mode() {
+                    let name = session.conversation.tangent_name().map(str::to_string);
                     let duration_seconds = session.conversation.get_tangent_duration_seconds().unwrap_or(0);
                     session.conversation.exit_tangent_mode();
                     Self::send_tangent_telemetry(os, session, duration_seconds).await;
@@ -114,18 +282,20 @@ impl TangentArgs {
                         style::Print("). - Returned to main conversation.\n"),
                         style::SetForegroundColor(Color::Reset)
                     )?;
-                } else {
-                    // Check if checkpoint is enabled
-                    if ExperimentManager::is_enabled(os, ExperimentName::Checkpoint) {
+                    if let Some(name) = name {
                         execute!(
                             session.stderr,
-                            style::SetForegroundColor(Color::Yellow),
-                            style::Print(
-                                "⚠️ Checkpoint is disabled while in tangent mode. Please exit tangent mode if you want to use checkpoint.\n"
-                            ),
-                            style::SetForegroundColor(Color::Reset),
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(format!("The tangent '{name}' is kept. Use ")),
+                            style::SetForegroundColor(Color::Green),
+                            style::Print(format!("/tangent switch {name}")),
+                            style::SetForegroundColor(Color::DarkGrey),
+                            style::Print(" to go back to it.\n"),
+                            style::SetForegroundColor(Color::Reset)
                         )?;
                     }
+                } else {
+                    Self::warn_checkpoint_disabled(os, session)?;
 
                     session.conversation.enter_tangent_mode();
 
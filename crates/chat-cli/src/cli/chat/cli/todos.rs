@@ -37,6 +37,9 @@ pub enum TodoSubcommand {
         #[arg(long, short)]
         all: bool,
     },
+
+    /// View the plan Q keeps for this conversation
+    Plan,
 }
 
 /// Used for displaying completed and in-progress todo lists
@@ -160,6 +163,21 @@ impl TodoSubcommand {
                 },
                 Err(e) => return Err(ChatError::Custom(format!("Could not show to-do lists: {e}").into())),
             },
+            Self::Plan => match TodoListState::load(os, session.conversation.conversation_id()).await {
+                Ok(plan) => {
+                    execute!(
+                        session.stderr,
+                        style::Print(format!("{} {}\n\n", "Plan:".magenta(), plan.description))
+                    )?;
+                    if plan.display_list(&mut session.stderr).is_err() {
+                        return Err(ChatError::Custom("Could not display the plan".into()));
+                    }
+                    execute!(session.stderr, style::Print("\n"))?;
+                },
+                Err(_) => {
+                    execute!(session.stderr, style::Print("No plan for this conversation yet!\n"))?;
+                },
+            },
             Self::Delete { all } => match Self::get_descriptions_and_statuses(os).await {
                 Ok(entries) => {
                     if entries.is_empty() {
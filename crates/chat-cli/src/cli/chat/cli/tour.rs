@@ -0,0 +1,462 @@
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use clap::{
+    Args,
+    ValueEnum,
+};
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+    Stylize,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use eyre::Result;
+
+use crate::api_client::model::Tool as FigTool;
+use crate::cli::agent::Agent;
+use crate::cli::chat::checkpoint::{
+    CheckpointManager,
+    is_git_installed,
+};
+use crate::cli::chat::context::ContextManager;
+use crate::cli::chat::token_counter::TokenCounter;
+use crate::cli::chat::tools::ToolOrigin;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// The sample project of the tour workspace, as paths relative to it and their content.
+const SAMPLE_FILES: &[(&str, &str)] = &[
+    (
+        "README.md",
+        "# Tour project\n\nA sample project for /tour. It is removed when the chat session ends.\n",
+    ),
+    (
+        "notes/ideas.md",
+        "# Ideas\n\n- Ask Q to summarize this project\n- Ask Q to add a section to the README\n",
+    ),
+    (
+        "src/main.rs",
+        "fn main() {\n    println!(\"Hello from the tour\");\n}\n",
+    ),
+];
+
+/// The file the checkpoints step changes and restores.
+const CHECKPOINT_SAMPLE: &str = "README.md";
+
+/// Arguments for the guided tour of the chat features.
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+pub struct TourArgs {
+    /// The step to start the tour at. Starts at the beginning if omitted.
+    #[arg(value_enum)]
+    pub step: Option<TourStep>,
+}
+
+/// A step of the tour.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum TourStep {
+    Context,
+    Tools,
+    Checkpoints,
+    Agents,
+}
+
+impl TourStep {
+    const ALL: [TourStep; 4] = [Self::Context, Self::Tools, Self::Checkpoints, Self::Agents];
+
+    fn title(&self) -> &'static str {
+        match self {
+            Self::Context => "Context",
+            Self::Tools => "Tools and trust",
+            Self::Checkpoints => "Checkpoints",
+            Self::Agents => "Agents",
+        }
+    }
+}
+
+impl TourArgs {
+    pub async fn execute(self, os: &mut Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let workspace = prepare_workspace(os)
+            .await
+            .map_err(|err| ChatError::Custom(format!("Failed to set up the tour workspace: {err}").into()))?;
+
+        execute!(
+            session.stderr,
+            style::Print("\n"),
+            style::SetAttribute(Attribute::Bold),
+            style::Print("Welcome to the tour of Amazon Q CLI\n"),
+            style::SetAttribute(Attribute::Reset),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!(
+                "The examples run in a sandbox workspace at {}.\nNothing in your project or configuration is changed.\n",
+                workspace.display()
+            )),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+
+        let start = self
+            .step
+            .and_then(|step| TourStep::ALL.iter().position(|s| *s == step))
+            .unwrap_or(0);
+        let steps = &TourStep::ALL[start..];
+        for (i, step) in steps.iter().enumerate() {
+            let number = start + i + 1;
+            queue!(
+                session.stderr,
+                style::Print("\n"),
+                style::SetAttribute(Attribute::Bold),
+                style::Print(format!("{number}/{} {}\n", TourStep::ALL.len(), step.title())),
+                style::SetAttribute(Attribute::Reset),
+                style::Print(format!("{}\n", "▔".repeat(session.terminal_width()))),
+            )?;
+
+            match step {
+                TourStep::Context => context_step(os, session, &workspace).await?,
+                TourStep::Tools => tools_step(session)?,
+                TourStep::Checkpoints => checkpoints_step(os, session, &workspace).await?,
+                TourStep::Agents => agents_step(os, session, &workspace).await?,
+            }
+            session.stderr.flush()?;
+
+            if i + 1 < steps.len() {
+                // Setting `exit_on_single_ctrl_c` so that ctrl+c leaves the tour rather than the CLI
+                let answer = session.read_user_input(&format!("{} ", "Continue? [y/n]:".dark_grey()), true);
+                if !answer.is_some_and(|a| ["y", "Y"].contains(&a.trim())) {
+                    break;
+                }
+            }
+        }
+
+        execute!(
+            session.stderr,
+            style::Print("\nThat's the tour. Run "),
+            style::SetForegroundColor(Color::Green),
+            style::Print("/help"),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(" to see every command, or "),
+            style::SetForegroundColor(Color::Green),
+            style::Print("/tour <step>"),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(" to revisit a step.\n\n"),
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
+
+/// Creates the sandbox workspace of the tour in the session temp dir, resetting the sample
+/// project if an earlier tour changed it.
+async fn prepare_workspace(os: &Os) -> Result<PathBuf> {
+    let workspace = os.session_temp_dir().await?.join("tour");
+    if os.fs.exists(&workspace) {
+        os.fs.remove_dir_all(&workspace).await?;
+    }
+
+    let project = workspace.join("project");
+    for (path, content) in SAMPLE_FILES {
+        let path = project.join(path);
+        if let Some(parent) = path.parent() {
+            os.fs.create_dir_all(parent).await?;
+        }
+        os.fs.write(&path, content).await?;
+    }
+
+    Ok(workspace)
+}
+
+async fn context_step(os: &Os, session: &mut ChatSession, workspace: &Path) -> Result<(), ChatError> {
+    let rules = session
+        .conversation
+        .context_manager
+        .as_ref()
+        .map_or(0, |cm| cm.paths.len());
+    queue!(
+        session.stderr,
+        style::Print("Context files are sent with every message, so Q knows your project without being told.\n"),
+        style::Print(format!(
+            "Your session has {rules} context rule(s). See them with {}.\n\n",
+            "/context show".green()
+        )),
+        style::Print(format!(
+            "Adding the markdown files of the sample project with {}:\n",
+            "/context add".green()
+        )),
+    )?;
+
+    // Work on a copy so that the context of the session is left as it is.
+    let mut context_manager = match &session.conversation.context_manager {
+        Some(cm) => cm.clone(),
+        None => ContextManager::from_agent(&Agent::default(), usize::MAX)
+            .map_err(|err| ChatError::Custom(err.to_string().into()))?,
+    };
+    let rule = workspace
+        .join("project")
+        .join("**")
+        .join("*.md")
+        .to_string_lossy()
+        .to_string();
+    queue!(session.stderr, style::Print(format!("  > /context add {rule}\n")))?;
+    let files = match context_manager.add_paths(os, vec![rule.clone()], false).await {
+        Ok(()) => context_manager
+            .get_context_files_by_path(os, &rule)
+            .await
+            .unwrap_or_default(),
+        Err(err) => {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("  {err}\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            Vec::new()
+        },
+    };
+
+    for (path, content) in &files {
+        queue!(
+            session.stderr,
+            style::Print(format!("  {path} ")),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("(~{} tkns)\n", TokenCounter::count_tokens(content))),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+    }
+    queue!(
+        session.stderr,
+        style::Print(
+            "\nRules added with /context add last for the session. Add them to the resources of an agent to keep them.\n\n"
+        ),
+    )?;
+
+    Ok(())
+}
+
+fn tools_step(session: &mut ChatSession) -> Result<(), ChatError> {
+    queue!(
+        session.stderr,
+        style::Print("Q uses tools to read files, run commands and call AWS. Tools that are not trusted ask\n"),
+        style::Print("for your approval before they run. These are the built-in tools of your session:\n\n"),
+    )?;
+
+    let mut native = session
+        .conversation
+        .tools
+        .get(&ToolOrigin::Native)
+        .map(|tools| {
+            tools
+                .iter()
+                .map(|FigTool::ToolSpecification(spec)| spec.name.clone())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    native.sort();
+    let longest = native.iter().map(String::len).max().unwrap_or(0);
+    for name in &native {
+        let label = session.conversation.agents.display_label(name, &ToolOrigin::Native);
+        queue!(
+            session.stderr,
+            style::Print(format!("  - {name:<longest$}    {label}\n")),
+        )?;
+    }
+
+    let servers = session
+        .conversation
+        .tools
+        .keys()
+        .filter(|origin| **origin != ToolOrigin::Native)
+        .count();
+    queue!(
+        session.stderr,
+        style::Print(format!(
+            "\nYour session also has tools from {servers} MCP server(s). See them all with {}.\n",
+            "/tools".green()
+        )),
+        style::Print(format!(
+            "Trust a tool for the session with {}, or every tool with {}.\n",
+            "/tools trust <tool>".green(),
+            "/tools trust-all".green()
+        )),
+        style::Print("When a tool asks for approval, answer t to trust it from then on.\n\n"),
+    )?;
+
+    Ok(())
+}
+
+async fn checkpoints_step(os: &Os, session: &mut ChatSession, workspace: &Path) -> Result<(), ChatError> {
+    queue!(
+        session.stderr,
+        style::Print("Checkpoints snapshot your workspace every turn, so that changes made by Q can be\n"),
+        style::Print(format!(
+            "reviewed with {} and undone with {} or {}.\n\n",
+            "/checkpoint diff".green(),
+            "/undo".green(),
+            "/checkpoint restore".green()
+        )),
+    )?;
+
+    match &session.conversation.checkpoint_manager {
+        Some(manager) => queue!(
+            session.stderr,
+            style::Print(format!(
+                "Checkpoints are on for this session, with {} so far.\n\n",
+                manager.checkpoints.len()
+            )),
+        )?,
+        None => queue!(
+            session.stderr,
+            style::Print(format!(
+                "Checkpoints are off for this session. Turn them on with {}.\n\n",
+                "/checkpoint init".green()
+            )),
+        )?,
+    }
+
+    if !is_git_installed() {
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("Checkpoints need git, which is not installed, so the example is skipped.\n\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        return Ok(());
+    }
+
+    let project = workspace.join("project");
+    let sample = project.join(CHECKPOINT_SAMPLE);
+    let demo = async {
+        let history = session.conversation.history().clone();
+        let mut manager =
+            CheckpointManager::init_with_work_tree(os, workspace.join("checkpoints"), project.clone(), &history)
+                .await?;
+        os.fs
+            .write(
+                &sample,
+                "# Tour project\n\nThis line was written by a turn of the tour.\n",
+            )
+            .await?;
+        manager.create_checkpoint("1", "Rewrite the README", &history, true, None)?;
+        let stats = manager.compute_file_stats("1")?;
+        let restored = manager.restore_paths("0", &[CHECKPOINT_SAMPLE.to_string()], true)?;
+        Ok::<_, eyre::Report>((stats, restored))
+    };
+
+    match demo.await {
+        Ok((stats, restored)) => queue!(
+            session.stderr,
+            style::Print(format!("  A turn rewrote {CHECKPOINT_SAMPLE} of the sample project:\n")),
+            style::Print(format!("  [1] Rewrite the README ({} modified)\n", stats.modified)),
+            style::Print(format!("  > /checkpoint restore 0 {CHECKPOINT_SAMPLE}\n")),
+            style::Print(format!("  Restored {}\n\n", restored.join(", "))),
+        )?,
+        Err(err) => queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Red),
+            style::Print(format!("  The example failed: {err}\n\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?,
+    }
+
+    Ok(())
+}
+
+async fn agents_step(os: &Os, session: &mut ChatSession, workspace: &Path) -> Result<(), ChatError> {
+    queue!(
+        session.stderr,
+        style::Print("Agents bundle a prompt, tools, trusted tools, context and MCP servers for a kind of task.\n"),
+        style::Print("The agents you have:\n\n"),
+    )?;
+
+    let agents = &session.conversation.agents;
+    let mut names = agents.agents.keys().collect::<Vec<_>>();
+    names.sort();
+    for name in names {
+        if *name == agents.active_idx {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::Green),
+                style::Print(format!("  * {name} (active)\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        } else {
+            queue!(session.stderr, style::Print(format!("    {name}\n")))?;
+        }
+    }
+
+    let project = workspace.join("project");
+    let agent = Agent {
+        name: "tour".to_string(),
+        description: Some("Answers questions about the tour project".to_string()),
+        prompt: Some("You help with the tour project. Keep answers short.".to_string()),
+        tools: vec!["fs_read".to_string()],
+        allowed_tools: HashSet::from(["fs_read".to_string()]),
+        resources: vec![format!("file://{}", project.join("README.md").display()).into()],
+        ..Default::default()
+    };
+    let path = workspace.join("tour-agent.json");
+    let written = match agent.to_str_pretty() {
+        Ok(content) => os.fs.write(&path, content).await.map_err(eyre::Report::from),
+        Err(err) => Err(err),
+    };
+    match written {
+        Ok(()) => queue!(
+            session.stderr,
+            style::Print(format!(
+                "\nAn agent is a JSON file. A read-only agent for the sample project was written to\n  {}\n",
+                path.display()
+            )),
+            style::Print(format!(
+                "Copy it to your agents directory to use it with {}, or make your own with {}.\n\n",
+                "/agent swap tour".green(),
+                "/agent create".green()
+            )),
+        )?,
+        Err(err) => queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Red),
+            style::Print(format!("\n  The example failed: {err}\n\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?,
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_prepare_workspace() {
+        let os = Os::new().await.unwrap();
+        let workspace = prepare_workspace(&os).await.unwrap();
+        for (path, content) in SAMPLE_FILES {
+            assert_eq!(
+                os.fs
+                    .read_to_string(workspace.join("project").join(path))
+                    .await
+                    .unwrap(),
+                *content
+            );
+        }
+
+        // A second tour starts from a clean workspace.
+        os.fs.write(workspace.join("leftover"), "").await.unwrap();
+        let workspace = prepare_workspace(&os).await.unwrap();
+        assert!(!os.fs.exists(workspace.join("leftover")));
+    }
+}
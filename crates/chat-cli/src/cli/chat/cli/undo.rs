@@ -0,0 +1,110 @@
+use clap::Args;
+use crossterm::style::{
+    self,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+
+use crate::cli::chat::checkpoint::truncate_message;
+use crate::cli::chat::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+
+/// Maximum length of the undone prompt shown to the user.
+const PROMPT_PREVIEW_MAX_LENGTH: usize = 60;
+
+#[deny(missing_docs)]
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    before_long_help = "/undo reverts the most recent turn: the files changed by the assistant are restored
+from the automatic checkpoint and the turn is removed from the conversation history.
+
+Files can only be restored when checkpoints are enabled. Without them, only the conversation
+history is reverted."
+)]
+pub struct UndoArgs {
+    /// Overwrite files modified since the last checkpoint
+    #[arg(long)]
+    pub force: bool,
+}
+
+impl UndoArgs {
+    pub async fn execute(self, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        let Some(turn_start) = session.conversation.last_turn_start() else {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nNothing to undo.\n\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        };
+
+        let history = session.conversation.history();
+        let turn_entry = &history[turn_start];
+        let prompt = truncate_message(turn_entry.prompt().unwrap_or_default(), PROMPT_PREVIEW_MAX_LENGTH);
+        let used_tools = history.range(turn_start..).any(|entry| entry.has_tool_uses());
+        // Turns that didn't change any files don't have a checkpoint, in which case the latest
+        // checkpoint belongs to an earlier turn.
+        let has_checkpoint = session
+            .conversation
+            .checkpoint_manager
+            .as_ref()
+            .and_then(|manager| manager.last_turn_checkpoint())
+            .is_some_and(|checkpoint| {
+                checkpoint
+                    .history_snapshot
+                    .get(turn_start)
+                    .is_some_and(|entry| entry.same_user_message(turn_entry))
+            });
+
+        let mut restored = Vec::new();
+        if has_checkpoint {
+            if let Some(manager) = session.conversation.checkpoint_manager.as_mut() {
+                restored = manager
+                    .undo_last_turn(self.force)
+                    .map_err(|e| ChatError::Custom(format!("Failed to undo: {e}").into()))?;
+            }
+        }
+
+        session.conversation.truncate_history(turn_start);
+        session.tool_uses.clear();
+        session.pending_tool_index = None;
+        session.tool_turn_start_time = None;
+
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("\n✓ Undid the last turn: {prompt}\n")),
+            style::SetForegroundColor(Color::DarkGrey),
+        )?;
+        for path in &restored {
+            queue!(session.stderr, style::Print(format!("  restored {path}\n")))?;
+        }
+        if used_tools && session.conversation.checkpoint_manager.is_none() {
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(
+                    "Files changed during this turn were not restored since checkpoints are not enabled. Use '/checkpoint init' to enable them.\n"
+                ),
+            )?;
+        }
+        execute!(
+            session.stderr,
+            style::Print("\n"),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+
+        Ok(ChatState::PromptUser {
+            skip_printing_tools: true,
+        })
+    }
+}
@@ -10,6 +10,7 @@ use crossterm::{
 };
 
 use super::model::context_window_tokens;
+use crate::cli::chat::monthly_usage::print_monthly_usage;
 use crate::cli::chat::token_counter::{
     CharCount,
     TokenCount,
@@ -25,14 +26,73 @@ use crate::os::Os;
 #[derive(Debug)]
 pub struct DetailedUsageData {
     pub total_tokens: TokenCount,
-    pub context_tokens: TokenCount,
     pub assistant_tokens: TokenCount,
     pub user_tokens: TokenCount,
     pub tools_tokens: TokenCount,
+    /// The agent prompt along with the framing around the context messages.
+    pub system_prompt_tokens: TokenCount,
+    pub context_files_tokens: TokenCount,
+    pub hooks_tokens: TokenCount,
+    pub summary_tokens: TokenCount,
+    /// User prompts and assistant responses.
+    pub history_tokens: TokenCount,
     pub context_window_size: usize,
     pub dropped_context_files: Vec<(String, String)>,
 }
 
+impl DetailedUsageData {
+    /// The categories the context window usage is broken down into, in display order.
+    fn categories(&self) -> [UsageCategory; 6] {
+        [
+            UsageCategory {
+                label: "System prompt",
+                color: Color::Yellow,
+                tokens: self.system_prompt_tokens,
+            },
+            UsageCategory {
+                label: "Tool specs",
+                color: Color::DarkRed,
+                tokens: self.tools_tokens,
+            },
+            UsageCategory {
+                label: "Context files",
+                color: Color::DarkCyan,
+                tokens: self.context_files_tokens,
+            },
+            UsageCategory {
+                label: "Hooks output",
+                color: Color::Green,
+                tokens: self.hooks_tokens,
+            },
+            UsageCategory {
+                label: "History",
+                color: Color::Blue,
+                tokens: self.history_tokens,
+            },
+            UsageCategory {
+                label: "Summary",
+                color: Color::Magenta,
+                tokens: self.summary_tokens,
+            },
+        ]
+    }
+}
+
+/// A single category of context window usage.
+struct UsageCategory {
+    label: &'static str,
+    color: Color,
+    tokens: TokenCount,
+}
+
+impl UsageCategory {
+    /// Width of the category's bar within a bar of `bar_width` representing the full context
+    /// window.
+    fn bar_width(&self, context_window_size: usize, bar_width: usize) -> usize {
+        ((self.tokens.value() as f64 / context_window_size as f64) * bar_width as f64) as usize
+    }
+}
+
 /// Calculate usage percentage from token counts
 pub fn calculate_usage_percentage(tokens: TokenCount, context_window_size: usize) -> f32 {
     (tokens.value() as f32 / context_window_size as f32) * 100.0
@@ -48,6 +108,7 @@ pub async fn get_detailed_usage_data(session: &mut ChatSession, os: &Os) -> Resu
         .await?;
 
     let data = state.calculate_conversation_size();
+    let breakdown = state.context_breakdown;
     let tool_specs_json: String = state
         .tools
         .values()
@@ -57,13 +118,22 @@ pub async fn get_detailed_usage_data(session: &mut ChatSession, os: &Os) -> Resu
     let tools_char_count: CharCount = tool_specs_json.len().into();
     let total_tokens: TokenCount =
         (data.context_messages + data.user_messages + data.assistant_messages + tools_char_count).into();
+    let system_prompt_chars: CharCount = data
+        .context_messages
+        .value()
+        .saturating_sub(breakdown.summary.value() + breakdown.context_files.value() + breakdown.hooks.value())
+        .into();
 
     Ok(DetailedUsageData {
         total_tokens,
-        context_tokens: data.context_messages.into(),
         assistant_tokens: data.assistant_messages.into(),
         user_tokens: data.user_messages.into(),
         tools_tokens: tools_char_count.into(),
+        system_prompt_tokens: system_prompt_chars.into(),
+        context_files_tokens: breakdown.context_files.into(),
+        hooks_tokens: breakdown.hooks.into(),
+        summary_tokens: breakdown.summary.into(),
+        history_tokens: (data.user_messages + data.assistant_messages).into(),
         context_window_size,
         dropped_context_files: state.dropped_context_files,
     })
@@ -78,14 +148,26 @@ pub async fn get_total_usage_percentage(session: &mut ChatSession, os: &Os) -> R
 /// Arguments for the usage command that displays token usage statistics and context window
 /// information.
 ///
-/// This command shows how many tokens are being used by different components (context files, tools,
-/// assistant responses, and user prompts) within the current chat session's context window.
+/// This command shows how many tokens are being used by each category (system prompt, tool specs,
+/// context files, hooks output, conversation history, and the compaction summary) within the
+/// current chat session's context window, with a bar per category.
 #[deny(missing_docs)]
 #[derive(Debug, PartialEq, Args)]
-pub struct UsageArgs;
+pub struct UsageArgs {
+    /// Show the requests used this month against your plan's limit
+    #[arg(long)]
+    pub monthly: bool,
+}
 
 impl UsageArgs {
     pub async fn execute(self, os: &Os, session: &mut ChatSession) -> Result<ChatState, ChatError> {
+        if self.monthly {
+            print_monthly_usage(os, &mut session.stderr).await?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: true,
+            });
+        }
+
         let usage_data = get_detailed_usage_data(session, os).await?;
 
         if !usage_data.dropped_context_files.is_empty() {
@@ -104,130 +186,100 @@ impl UsageArgs {
         let window_width = session.terminal_width();
         // set a max width for the progress bar for better aesthetic
         let progress_bar_width = std::cmp::min(window_width, 80);
+        let context_window_size = usage_data.context_window_size;
+        let categories = usage_data.categories();
 
-        let context_width = ((usage_data.context_tokens.value() as f64 / usage_data.context_window_size as f64)
-            * progress_bar_width as f64) as usize;
-        let assistant_width = ((usage_data.assistant_tokens.value() as f64 / usage_data.context_window_size as f64)
-            * progress_bar_width as f64) as usize;
-        let tools_width = ((usage_data.tools_tokens.value() as f64 / usage_data.context_window_size as f64)
-            * progress_bar_width as f64) as usize;
-        let user_width = ((usage_data.user_tokens.value() as f64 / usage_data.context_window_size as f64)
-            * progress_bar_width as f64) as usize;
-
-        let left_over_width = progress_bar_width
-            - std::cmp::min(
-                context_width + assistant_width + user_width + tools_width,
-                progress_bar_width,
-            );
+        let used_width = categories
+            .iter()
+            .map(|c| c.bar_width(context_window_size, progress_bar_width))
+            .sum::<usize>();
+        let left_over_width = progress_bar_width - std::cmp::min(used_width, progress_bar_width);
+        let is_overflow = used_width > progress_bar_width;
 
-        let is_overflow = (context_width + assistant_width + user_width + tools_width) > progress_bar_width;
+        let total_percentage = calculate_usage_percentage(usage_data.total_tokens, context_window_size);
 
-        let total_percentage = calculate_usage_percentage(usage_data.total_tokens, usage_data.context_window_size);
+        queue!(
+            session.stderr,
+            style::Print(format!(
+                "\nCurrent context window ({} of {}k tokens used)\n",
+                usage_data.total_tokens,
+                context_window_size / 1000
+            )),
+        )?;
 
         if is_overflow {
             queue!(
                 session.stderr,
-                style::Print(format!(
-                    "\nCurrent context window ({} of {}k tokens used)\n",
-                    usage_data.total_tokens,
-                    usage_data.context_window_size / 1000
-                )),
                 style::SetForegroundColor(Color::DarkRed),
                 style::Print("█".repeat(progress_bar_width)),
-                style::SetForegroundColor(Color::Reset),
-                style::Print(" "),
-                style::Print(format!("{:.2}%", total_percentage)),
             )?;
         } else {
-            queue!(
-                session.stderr,
-                style::Print(format!(
-                    "\nCurrent context window ({} of {}k tokens used)\n",
-                    usage_data.total_tokens,
-                    usage_data.context_window_size / 1000
-                )),
-                // Context files
-                style::SetForegroundColor(Color::DarkCyan),
-                // add a nice visual to mimic "tiny" progress, so the overrall progress bar doesn't look too
-                // empty
-                style::Print(
-                    "|".repeat(if context_width == 0 && usage_data.context_tokens.value() > 0 {
-                        1
-                    } else {
-                        0
-                    })
-                ),
-                style::Print("█".repeat(context_width)),
-                // Tools
-                style::SetForegroundColor(Color::DarkRed),
-                style::Print("|".repeat(if tools_width == 0 && usage_data.tools_tokens.value() > 0 {
-                    1
-                } else {
-                    0
-                })),
-                style::Print("█".repeat(tools_width)),
-                // Assistant responses
-                style::SetForegroundColor(Color::Blue),
-                style::Print(
-                    "|".repeat(if assistant_width == 0 && usage_data.assistant_tokens.value() > 0 {
+            for category in &categories {
+                let width = category.bar_width(context_window_size, progress_bar_width);
+                queue!(
+                    session.stderr,
+                    style::SetForegroundColor(category.color),
+                    // add a nice visual to mimic "tiny" progress, so the overrall progress bar doesn't look too
+                    // empty
+                    style::Print("|".repeat(if width == 0 && category.tokens.value() > 0 {
                         1
                     } else {
                         0
-                    })
-                ),
-                style::Print("█".repeat(assistant_width)),
-                // User prompts
-                style::SetForegroundColor(Color::Magenta),
-                style::Print("|".repeat(if user_width == 0 && usage_data.user_tokens.value() > 0 {
+                    })),
+                    style::Print("█".repeat(width)),
+                )?;
+            }
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("█".repeat(left_over_width)),
+            )?;
+        }
+
+        queue!(
+            session.stderr,
+            style::SetForegroundColor(Color::Reset),
+            style::Print(" "),
+            style::Print(format!("{:.2}%", total_percentage)),
+        )?;
+        execute!(session.stderr, style::Print("\n\n"))?;
+
+        // One bar per category, scaled to the full context window.
+        let label_width = categories.iter().map(|c| c.label.len()).max().unwrap_or_default();
+        let category_bar_width = progress_bar_width.saturating_sub(label_width + 30).max(10);
+        for category in &categories {
+            let width = category
+                .bar_width(context_window_size, category_bar_width)
+                .min(category_bar_width);
+            queue!(
+                session.stderr,
+                style::SetForegroundColor(category.color),
+                style::Print(format!("█ {:<label_width$} ", category.label)),
+                style::Print("█".repeat(width)),
+                style::Print("|".repeat(if width == 0 && category.tokens.value() > 0 {
                     1
                 } else {
                     0
                 })),
-                style::Print("█".repeat(user_width)),
                 style::SetForegroundColor(Color::DarkGrey),
-                style::Print("█".repeat(left_over_width)),
-                style::Print(" "),
+                style::Print("░".repeat(category_bar_width - width)),
                 style::SetForegroundColor(Color::Reset),
-                style::Print(format!("{:.2}%", total_percentage)),
+                style::Print(format!(
+                    " ~{} tokens ({:.2}%)\n",
+                    category.tokens,
+                    calculate_usage_percentage(category.tokens, context_window_size)
+                )),
             )?;
         }
 
-        execute!(session.stderr, style::Print("\n\n"))?;
-
         queue!(
             session.stderr,
-            style::SetForegroundColor(Color::DarkCyan),
-            style::Print("█ Context files: "),
-            style::SetForegroundColor(Color::Reset),
-            style::Print(format!(
-                "~{} tokens ({:.2}%)\n",
-                usage_data.context_tokens,
-                calculate_usage_percentage(usage_data.context_tokens, usage_data.context_window_size)
-            )),
-            style::SetForegroundColor(Color::DarkRed),
-            style::Print("█ Tools:    "),
-            style::SetForegroundColor(Color::Reset),
-            style::Print(format!(
-                " ~{} tokens ({:.2}%)\n",
-                usage_data.tools_tokens,
-                calculate_usage_percentage(usage_data.tools_tokens, usage_data.context_window_size)
-            )),
-            style::SetForegroundColor(Color::Blue),
-            style::Print("█ Q responses: "),
-            style::SetForegroundColor(Color::Reset),
+            style::SetForegroundColor(Color::DarkGrey),
             style::Print(format!(
-                "  ~{} tokens ({:.2}%)\n",
-                usage_data.assistant_tokens,
-                calculate_usage_percentage(usage_data.assistant_tokens, usage_data.context_window_size)
+                "  History: ~{} tokens from your prompts, ~{} tokens from Q responses\n\n",
+                usage_data.user_tokens, usage_data.assistant_tokens
             )),
-            style::SetForegroundColor(Color::Magenta),
-            style::Print("█ Your prompts: "),
             style::SetForegroundColor(Color::Reset),
-            style::Print(format!(
-                " ~{} tokens ({:.2}%)\n\n",
-                usage_data.user_tokens,
-                calculate_usage_percentage(usage_data.user_tokens, usage_data.context_window_size)
-            )),
         )?;
 
         queue!(
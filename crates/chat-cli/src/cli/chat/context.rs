@@ -1,6 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{
+    HashMap,
+    HashSet,
+};
 use std::io::Write;
 use std::path::Path;
+use std::time::{
+    Duration,
+    Instant,
+};
 
 use eyre::{
     Result,
@@ -13,9 +20,15 @@ use serde::{
     Serialize,
     Serializer,
 };
+use tracing::warn;
 
 use super::cli::hooks::HookOutput;
 use super::cli::model::context_window_tokens;
+use super::git_context::{
+    self,
+    GIT_CONTEXT,
+};
+use super::token_counter::TokenCounter;
 use super::util::drop_matched_context_files;
 use crate::cli::agent::Agent;
 use crate::cli::agent::hook::{
@@ -25,8 +38,14 @@ use crate::cli::agent::hook::{
 use crate::cli::chat::ChatError;
 use crate::cli::chat::cli::hooks::HookExecutor;
 use crate::cli::chat::cli::model::ModelInfo;
+use crate::database::settings::Setting;
 use crate::os::Os;
 
+/// How long hooks and context files have to be ready before a request, unless configured.
+const DEFAULT_CONTEXT_ASSEMBLY_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long the hooks of a tool use, stop and session end have to finish, unless configured.
+const DEFAULT_HOOKS_DEADLINE: Duration = Duration::from_secs(30);
+
 #[derive(Debug, Clone)]
 pub enum ContextFilePath {
     /// Signifies that the path is brought in from the agent config
@@ -100,16 +119,49 @@ pub struct ContextManager {
     pub paths: Vec<ContextFilePath>,
     /// Map of Hook Name to [`Hook`]. The hook name serves as the hook's ID.
     pub hooks: HashMap<HookTrigger, Vec<Hook>>,
+    /// Map of path or glob pattern to the maximum number of tokens its matched files may use.
+    #[serde(default)]
+    pub path_budgets: HashMap<String, usize>,
+    /// Map of watched path or glob pattern to the files it matched when last resolved.
+    #[serde(skip)]
+    watched_paths: HashMap<String, Vec<String>>,
     #[serde(skip)]
     pub hook_executor: HookExecutor,
+    /// The context files of the previous request.
+    #[serde(skip)]
+    assembled_files: Option<ContextFiles>,
+}
+
+/// The context files to use, and those dropped because of size limits.
+pub type ContextFiles = (Vec<(String, String)>, Vec<(String, String)>);
+
+/// What is gathered before each request, see [ContextManager::assemble].
+#[derive(Debug)]
+pub struct AssembledContext {
+    pub hook_results: Vec<((HookTrigger, Hook), HookOutput)>,
+    /// `None` if they couldn't be read.
+    pub files: Option<ContextFiles>,
+}
+
+/// Describes how the files matched by a watched context rule changed since it was last resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedPathChange {
+    pub path: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub total: usize,
 }
 
 impl ContextManager {
+    pub fn set_max_context_files_size(&mut self, max_context_files_size: usize) {
+        self.max_context_files_size = max_context_files_size;
+    }
+
     pub fn from_agent(agent: &Agent, max_context_files_size: usize) -> Result<Self> {
         let paths = agent
             .resources
             .iter()
-            .filter(|resource| resource.starts_with("file://"))
+            .filter(|resource| resource.starts_with("file://") || resource.as_str() == GIT_CONTEXT)
             .map(|s| ContextFilePath::Agent(s.trim_start_matches("file://").to_string()))
             .collect::<Vec<_>>();
 
@@ -118,7 +170,10 @@ impl ContextManager {
             current_profile: agent.name.clone(),
             paths,
             hooks: agent.hooks.clone(),
+            path_budgets: HashMap::new(),
+            watched_paths: HashMap::new(),
             hook_executor: HookExecutor::new(),
+            assembled_files: None,
         })
     }
 
@@ -176,12 +231,73 @@ impl ContextManager {
             return Err(eyre!("None of the specified paths were found in the context"));
         }
 
+        for path in &paths {
+            self.path_budgets.remove(path);
+            self.watched_paths.remove(path);
+        }
+
         Ok(())
     }
 
     /// Clear all paths from the context configuration.
     pub fn clear(&mut self) {
         self.paths.clear();
+        self.path_budgets.clear();
+        self.watched_paths.clear();
+    }
+
+    /// Limits the total number of tokens the files matched by `path` may contribute to the
+    /// context. Files beyond the budget are dropped, in filename order.
+    pub fn set_path_budget(&mut self, path: &str, max_tokens: usize) -> Result<()> {
+        if !self.paths.iter().any(|p| p == path) {
+            return Err(eyre!("Rule '{}' does not exist.", path));
+        }
+        self.path_budgets.insert(path.to_string(), max_tokens);
+        Ok(())
+    }
+
+    /// Starts watching `path` so that changes to the set of files it matches are reported by
+    /// [Self::refresh_watched_paths].
+    pub async fn watch_path(&mut self, os: &Os, path: &str) -> Result<()> {
+        if !self.paths.iter().any(|p| p == path) {
+            return Err(eyre!("Rule '{}' does not exist.", path));
+        }
+        let matches = resolve_matches(os, path).await?;
+        self.watched_paths.insert(path.to_string(), matches);
+        Ok(())
+    }
+
+    /// Returns whether `path` is being watched for changes.
+    pub fn is_watched(&self, path: &str) -> bool {
+        self.watched_paths.contains_key(path)
+    }
+
+    /// Re-resolves every watched path, returning the paths whose matched files have changed
+    /// since they were last resolved.
+    pub async fn refresh_watched_paths(&mut self, os: &Os) -> Vec<WatchedPathChange> {
+        let mut changes = Vec::new();
+        for (path, previous) in &mut self.watched_paths {
+            let current = match resolve_matches(os, path).await {
+                Ok(current) => current,
+                Err(err) => {
+                    warn!(?err, ?path, "Failed to resolve watched context path");
+                    continue;
+                },
+            };
+            if current == *previous {
+                continue;
+            }
+
+            changes.push(WatchedPathChange {
+                path: path.clone(),
+                added: current.iter().filter(|f| !previous.contains(f)).cloned().collect(),
+                removed: previous.iter().filter(|f| !current.contains(f)).cloned().collect(),
+                total: current.len(),
+            });
+            *previous = current;
+        }
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        changes
     }
 
     /// Get all context files (global + profile-specific).
@@ -212,20 +328,54 @@ impl ContextManager {
         Ok(context_files)
     }
 
-    /// Collects context files and optionally drops files if the total size exceeds the limit.
-    /// Returns (files_to_use, dropped_files)
-    pub async fn collect_context_files_with_limit(
-        &self,
+    /// Runs the hooks of `triggers` while the context files are read, both cut short at
+    /// `deadline`. The context files of the previous call are used if they can't be read in
+    /// time.
+    pub async fn assemble(
+        &mut self,
         os: &Os,
-    ) -> Result<(Vec<(String, String)>, Vec<(String, String)>)> {
-        let mut files = self.get_context_files(os).await?;
+        output: &mut impl Write,
+        triggers: &[HookTrigger],
+        prompt: Option<&str>,
+        deadline: Instant,
+    ) -> Result<AssembledContext, ChatError> {
+        let mut hooks = self.hooks.clone();
+        hooks.retain(|trigger, _| triggers.contains(trigger));
+        let cwd = os.env.current_dir()?.to_string_lossy().to_string();
 
-        let dropped_files = drop_matched_context_files(&mut files, self.max_context_files_size).unwrap_or_default();
+        let Self {
+            paths,
+            path_budgets,
+            max_context_files_size,
+            hook_executor,
+            ..
+        } = self;
+        let read_files = async {
+            let matched = read_context_files(os, paths).await?;
+            Ok::<_, eyre::Report>(limit_context_files(os, matched, path_budgets, *max_context_files_size))
+        };
+        let (hook_results, files) = tokio::join!(
+            hook_executor.run_hooks(hooks, output, &cwd, prompt, None, Some(deadline)),
+            tokio::time::timeout_at(deadline.into(), read_files),
+        );
 
-        // remove dropped files from files
-        files.retain(|file| !dropped_files.iter().any(|dropped| dropped.0 == file.0));
+        let hook_results = hook_results?;
+        let files = match files {
+            Ok(Ok(files)) => {
+                self.assembled_files = Some(files.clone());
+                Some(files)
+            },
+            Ok(Err(err)) => {
+                warn!("Failed to get context files: {}", err);
+                None
+            },
+            Err(_) => {
+                warn!("context files were not read before the context deadline, reusing the previous ones");
+                self.assembled_files.clone()
+            },
+        };
 
-        Ok((files, dropped_files))
+        Ok(AssembledContext { hook_results, files })
     }
 
     async fn collect_context_files(
@@ -255,18 +405,279 @@ impl ContextManager {
         let mut hooks = self.hooks.clone();
         hooks.retain(|t, _| *t == trigger);
         let cwd = os.env.current_dir()?.to_string_lossy().to_string();
+        let deadline = Instant::now() + hooks_deadline(os);
         self.hook_executor
-            .run_hooks(hooks, output, &cwd, prompt, tool_context)
+            .run_hooks(hooks, output, &cwd, prompt, tool_context, Some(deadline))
             .await
     }
 }
 
+/// Reads the files matched by each of `paths`.
+async fn read_context_files(os: &Os, paths: &[ContextFilePath]) -> Result<Vec<(String, Vec<(String, String)>)>> {
+    let mut matched = Vec::with_capacity(paths.len());
+    for path in paths {
+        let mut files = Vec::new();
+        process_path(os, path.get_path_as_str(), &mut files, false).await?;
+        matched.push((path.get_path_as_str().to_string(), files));
+    }
+    Ok(matched)
+}
+
+/// Applies the budgets of the rules, the size limit of single files, and the size limit of all
+/// of them to the files matched by each rule.
+fn limit_context_files(
+    os: &Os,
+    matched: Vec<(String, Vec<(String, String)>)>,
+    path_budgets: &HashMap<String, usize>,
+    max_context_files_size: usize,
+) -> ContextFiles {
+    let mut files = Vec::new();
+    let mut budget_dropped_files = Vec::new();
+    for (path, mut matched) in matched {
+        if let Some(budget) = path_budgets.get(&path) {
+            budget_dropped_files.extend(apply_path_budget(&mut matched, *budget));
+        }
+        files.extend(matched);
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    files.dedup_by(|a, b| a.0 == b.0);
+
+    // A file dropped by one rule's budget is still used if another rule includes it.
+    budget_dropped_files.retain(|dropped| !files.iter().any(|file| file.0 == dropped.0));
+    budget_dropped_files.sort_by(|a, b| a.0.cmp(&b.0));
+    budget_dropped_files.dedup_by(|a, b| a.0 == b.0);
+
+    if let Some(max_file_tokens) = max_file_tokens(os) {
+        for (_, content) in &mut files {
+            if TokenCounter::count_tokens(content) > max_file_tokens {
+                *content = truncate_head_tail(content, max_file_tokens);
+            }
+        }
+    }
+
+    let limit = apply_max_tokens_setting(os, max_context_files_size);
+    let mut dropped_files = drop_matched_context_files(&mut files, limit).unwrap_or_default();
+
+    // remove dropped files from files
+    files.retain(|file| !dropped_files.iter().any(|dropped| dropped.0 == file.0));
+    dropped_files.extend(budget_dropped_files);
+
+    (files, dropped_files)
+}
+
+/// The deadline for assembling the context of a request, configured with
+/// [Setting::ContextAssemblyTimeout].
+pub fn context_assembly_timeout(os: &Os) -> Duration {
+    os.database
+        .settings
+        .get_int(Setting::ContextAssemblyTimeout)
+        .and_then(|v| u64::try_from(v).ok())
+        .map_or(DEFAULT_CONTEXT_ASSEMBLY_TIMEOUT, Duration::from_millis)
+}
+
+/// How long the hooks of a trigger other than the ones run with the context have to finish
+/// together, configured with [Setting::HooksDeadline].
+fn hooks_deadline(os: &Os) -> Duration {
+    os.database
+        .settings
+        .get_int(Setting::HooksDeadline)
+        .and_then(|v| u64::try_from(v).ok())
+        .map_or(DEFAULT_HOOKS_DEADLINE, Duration::from_millis)
+}
+
+/// Keeps the files in `files`, in filename order, until their combined token count would exceed
+/// `budget`. Returns the files that were dropped.
+fn apply_path_budget(files: &mut Vec<(String, String)>, budget: usize) -> Vec<(String, String)> {
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut total_tokens = 0;
+    let mut dropped_files = Vec::new();
+    files.retain(|(filename, content)| {
+        let tokens = TokenCounter::count_tokens(content);
+        if total_tokens + tokens > budget {
+            dropped_files.push((filename.clone(), content.clone()));
+            false
+        } else {
+            total_tokens += tokens;
+            true
+        }
+    });
+    dropped_files
+}
+
+/// Returns the sorted list of file names currently matched by `path`.
+async fn resolve_matches(os: &Os, path: &str) -> Result<Vec<String>> {
+    let mut context_files = Vec::new();
+    process_path(os, path, &mut context_files, false).await?;
+    let mut matches = context_files
+        .into_iter()
+        .map(|(filename, _)| filename)
+        .collect::<Vec<_>>();
+    matches.sort();
+    matches.dedup();
+    Ok(matches)
+}
+
 /// Calculates the maximum context files size to use for the given model id.
 pub fn calc_max_context_files_size(model: Option<&ModelInfo>) -> usize {
     // Sets the max as 75% of the context window
     context_window_tokens(model).saturating_mul(3) / 4
 }
 
+/// Calculates the context files budget for the given model id, honoring
+/// [Setting::ContextMaxTokens] if configured.
+pub fn calc_context_files_budget(os: &Os, model: Option<&ModelInfo>) -> usize {
+    apply_max_tokens_setting(os, calc_max_context_files_size(model))
+}
+
+/// The maximum number of tokens a single context file may use before being truncated, if
+/// configured with [Setting::ContextMaxFileTokens].
+pub fn max_file_tokens(os: &Os) -> Option<usize> {
+    os.database
+        .settings
+        .get_int(Setting::ContextMaxFileTokens)
+        .and_then(|v| usize::try_from(v).ok())
+}
+
+/// The number of context files to keep for each prompt, if only the most relevant ones should be
+/// included, configured with [Setting::ContextRelevanceTopK].
+pub fn relevance_top_k(os: &Os) -> Option<usize> {
+    os.database
+        .settings
+        .get_int(Setting::ContextRelevanceTopK)
+        .and_then(|v| usize::try_from(v).ok())
+        .filter(|v| *v > 0)
+}
+
+/// Keeps the `top_k` files of `files` most relevant to `prompt`, in filename order, and returns
+/// the others.
+///
+/// Files are scored by the words they share with the prompt, weighted by how rare each word is
+/// among the files, with words of the file name counting double.
+pub fn retain_most_relevant(files: &mut Vec<(String, String)>, prompt: &str, top_k: usize) -> Vec<(String, String)> {
+    if files.len() <= top_k {
+        return Vec::new();
+    }
+
+    let terms = relevance_terms(prompt).into_iter().collect::<HashSet<_>>();
+    let file_terms = files
+        .iter()
+        .map(|(filename, content)| {
+            let mut counts = HashMap::<String, usize>::new();
+            for term in relevance_terms(content) {
+                *counts.entry(term).or_default() += 1;
+            }
+            let name_terms = relevance_terms(filename).into_iter().collect::<HashSet<_>>();
+            (counts, name_terms)
+        })
+        .collect::<Vec<_>>();
+
+    let scores = file_terms
+        .iter()
+        .map(|(counts, name_terms)| {
+            terms
+                .iter()
+                .map(|term| {
+                    let documents = file_terms
+                        .iter()
+                        .filter(|(counts, _)| counts.contains_key(term))
+                        .count();
+                    let idf = ((files.len() + 1) as f64 / (documents + 1) as f64).ln() + 1.0;
+                    let count = counts.get(term).copied().unwrap_or_default();
+                    let name_bonus = if name_terms.contains(term) { 2.0 } else { 0.0 };
+                    idf * ((count as f64).ln_1p() + name_bonus)
+                })
+                .sum::<f64>()
+        })
+        .collect::<Vec<_>>();
+
+    let mut ranked = (0..files.len()).collect::<Vec<_>>();
+    ranked.sort_by(|a, b| scores[*b].total_cmp(&scores[*a]));
+    let kept = ranked.into_iter().take(top_k).collect::<HashSet<_>>();
+
+    let mut index = 0;
+    let mut pruned = Vec::new();
+    files.retain(|file| {
+        let keep = kept.contains(&index);
+        if !keep {
+            pruned.push(file.clone());
+        }
+        index += 1;
+        keep
+    });
+    pruned
+}
+
+/// Lowercase words of at least three characters, split on anything but letters and digits.
+fn relevance_terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn apply_max_tokens_setting(os: &Os, max_size: usize) -> usize {
+    os.database
+        .settings
+        .get_int(Setting::ContextMaxTokens)
+        .and_then(|v| usize::try_from(v).ok())
+        .map_or(max_size, |v| v.min(max_size))
+}
+
+/// Truncates `content` to roughly `max_tokens`, preserving whole lines from the start and the end
+/// of the content and replacing the middle with a marker noting how much was removed.
+pub fn truncate_head_tail(content: &str, max_tokens: usize) -> String {
+    let max_chars = TokenCounter::token_to_chars(max_tokens);
+    if content.len() <= max_chars {
+        return content.to_string();
+    }
+    let half = max_chars / 2;
+
+    let lines = content.lines().collect::<Vec<_>>();
+    let mut head = 0;
+    let mut head_len = 0;
+    for line in &lines {
+        if head_len + line.len() + 1 > half {
+            break;
+        }
+        head_len += line.len() + 1;
+        head += 1;
+    }
+    let mut tail = 0;
+    let mut tail_len = 0;
+    for line in lines[head..].iter().rev() {
+        if tail_len + line.len() + 1 > half {
+            break;
+        }
+        tail_len += line.len() + 1;
+        tail += 1;
+    }
+
+    if head + tail == 0 {
+        // Content without line breaks, e.g. minified files. Fall back to truncating characters.
+        let mut head_end = half.min(content.len());
+        while !content.is_char_boundary(head_end) {
+            head_end -= 1;
+        }
+        let mut tail_start = content.len().saturating_sub(half).max(head_end);
+        while !content.is_char_boundary(tail_start) {
+            tail_start += 1;
+        }
+        return format!(
+            "{}\n[... {} characters truncated to fit the context budget ...]\n{}",
+            &content[..head_end],
+            tail_start - head_end,
+            &content[tail_start..]
+        );
+    }
+
+    format!(
+        "{}\n[... {} lines truncated to fit the context budget ...]\n{}",
+        lines[..head].join("\n"),
+        lines.len() - head - tail,
+        lines[lines.len() - tail..].join("\n")
+    )
+}
+
 /// Process a path, handling glob patterns and file types.
 ///
 /// This method:
@@ -276,6 +687,8 @@ pub fn calc_max_context_files_size(model: Option<&ModelInfo>) -> usize {
 /// 4. Handles directories by including all files in the directory (non-recursive)
 /// 5. With force=true, includes paths that don't exist yet
 ///
+/// The [GIT_CONTEXT] rule adds the state of the git repository instead, see [git_context].
+///
 /// # Arguments
 /// * `path` - The path to process
 /// * `context_files` - The collection to add files to
@@ -289,6 +702,15 @@ async fn process_path(
     context_files: &mut Vec<(String, String)>,
     is_validation: bool,
 ) -> Result<()> {
+    if path == GIT_CONTEXT {
+        match git_context::collect(os).await? {
+            Some(content) => context_files.push((GIT_CONTEXT.to_string(), content)),
+            None if is_validation => return Err(eyre!("The current directory is not in a git repository")),
+            None => (),
+        }
+        return Ok(());
+    }
+
     // Expand ~ to home directory
     let expanded_path = if path.starts_with('~') {
         if let Some(home_dir) = os.env.home() {
@@ -387,6 +809,18 @@ mod tests {
     use super::*;
     use crate::cli::chat::util::test::create_test_context_manager;
 
+    /// The context files of `manager` with every limit applied, as [ContextManager::assemble]
+    /// gathers them.
+    async fn collect_context_files_with_limit(manager: &ContextManager, os: &Os) -> Result<ContextFiles> {
+        let matched = read_context_files(os, &manager.paths).await?;
+        Ok(limit_context_files(
+            os,
+            matched,
+            &manager.path_budgets,
+            manager.max_context_files_size,
+        ))
+    }
+
     #[tokio::test]
     async fn test_collect_exceeds_limit() -> Result<()> {
         let os = Os::new().await.unwrap();
@@ -397,7 +831,7 @@ mod tests {
         os.fs.write("test/to-drop.md", "long content that exceed limit").await?;
         manager.add_paths(&os, vec!["test/*.md".to_string()], false).await?;
 
-        let (used, dropped) = manager.collect_context_files_with_limit(&os).await.unwrap();
+        let (used, dropped) = collect_context_files_with_limit(&manager, &os).await.unwrap();
 
         assert!(used.len() + dropped.len() == 2);
         assert!(used.len() == 1);
@@ -405,6 +839,35 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_assemble() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(Some(2)).expect("Failed to create test context manager");
+        os.fs.create_dir_all("test").await?;
+        os.fs.write("test/to-include.md", "ha").await?;
+        os.fs.write("test/to-drop.md", "long content that exceed limit").await?;
+        manager.add_paths(&os, vec!["test/*.md".to_string()], false).await?;
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let assembled = manager
+            .assemble(&os, &mut vec![], &[HookTrigger::AgentSpawn], None, deadline)
+            .await?;
+        assert!(assembled.hook_results.is_empty());
+        assert_eq!(
+            assembled.files,
+            Some(collect_context_files_with_limit(&manager, &os).await?)
+        );
+        assert_eq!(manager.assembled_files, assembled.files);
+
+        // Changed files are read again.
+        os.fs.write("test/to-include.md", "hi").await?;
+        let assembled = manager
+            .assemble(&os, &mut vec![], &[HookTrigger::AgentSpawn], None, deadline)
+            .await?;
+        assert_eq!(assembled.files.unwrap().0[0].1, "hi");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_path_ops() -> Result<()> {
         let os = Os::new().await.unwrap();
@@ -438,6 +901,128 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_path_budget() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).expect("Failed to create test context manager");
+
+        os.fs.create_dir_all("test").await?;
+        os.fs.write("test/a.md", "a".repeat(40)).await?;
+        os.fs.write("test/b.md", "b".repeat(40)).await?;
+        os.fs.write("test/c.md", "c".repeat(40)).await?;
+        manager.add_paths(&os, vec!["test/*.md".to_string()], false).await?;
+        assert!(manager.set_path_budget("test/*.txt", 10).is_err());
+        manager.set_path_budget("test/*.md", 25)?;
+
+        let (used, dropped) = collect_context_files_with_limit(&manager, &os).await?;
+        assert_eq!(used.len(), 2);
+        assert!(used[0].0.ends_with("a.md"));
+        assert!(used[1].0.ends_with("b.md"));
+        assert_eq!(dropped.len(), 1);
+        assert!(dropped[0].0.ends_with("c.md"));
+
+        // Files dropped by a budget are kept if matched by another rule.
+        manager.add_paths(&os, vec!["test/c.md".to_string()], false).await?;
+        let (used, dropped) = collect_context_files_with_limit(&manager, &os).await?;
+        assert_eq!(used.len(), 3);
+        assert!(dropped.is_empty());
+
+        manager.remove_paths(vec!["test/*.md".to_string()])?;
+        assert!(manager.path_budgets.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watched_paths() -> Result<()> {
+        let os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).expect("Failed to create test context manager");
+
+        os.fs.create_dir_all("test").await?;
+        os.fs.write("test/p1.md", "p1").await?;
+        manager.add_paths(&os, vec!["test/*.md".to_string()], false).await?;
+        manager.watch_path(&os, "test/*.md").await?;
+        assert!(manager.is_watched("test/*.md"));
+        assert!(manager.refresh_watched_paths(&os).await.is_empty());
+
+        os.fs.write("test/p2.md", "p2").await?;
+        os.fs.remove_file("test/p1.md").await?;
+        let changes = manager.refresh_watched_paths(&os).await;
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].path, "test/*.md");
+        assert_eq!(changes[0].total, 1);
+        assert!(changes[0].added[0].ends_with("p2.md"));
+        assert!(changes[0].removed[0].ends_with("p1.md"));
+        assert!(manager.refresh_watched_paths(&os).await.is_empty());
+
+        manager.clear();
+        assert!(!manager.is_watched("test/*.md"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_collect_truncates_large_files() -> Result<()> {
+        let mut os = Os::new().await.unwrap();
+        let mut manager = create_test_context_manager(None).expect("Failed to create test context manager");
+        os.database.settings.set(Setting::ContextMaxFileTokens, 20).await?;
+
+        let large = (0..100).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+        os.fs.create_dir_all("test").await?;
+        os.fs.write("test/small.md", "small").await?;
+        os.fs.write("test/large.md", &large).await?;
+        manager.add_paths(&os, vec!["test/*.md".to_string()], false).await?;
+
+        let (used, dropped) = collect_context_files_with_limit(&manager, &os).await?;
+        assert!(dropped.is_empty());
+        assert_eq!(used.len(), 2);
+        let (_, truncated) = used.iter().find(|(name, _)| name.ends_with("large.md")).unwrap();
+        assert!(truncated.starts_with("line 0\n"));
+        assert!(truncated.ends_with("line 99"));
+        assert!(truncated.contains("lines truncated to fit the context budget"));
+        assert!(TokenCounter::count_tokens(truncated) <= 30);
+        let (_, small) = used.iter().find(|(name, _)| name.ends_with("small.md")).unwrap();
+        assert_eq!(small, "small");
+        Ok(())
+    }
+
+    #[test]
+    fn test_retain_most_relevant() {
+        let file = |name: &str, content: &str| (name.to_string(), content.to_string());
+        let mut files = vec![
+            file(
+                "docs/deploy.md",
+                "Run cdk deploy to ship the stack to the staging account.",
+            ),
+            file("docs/style.md", "Use four spaces for indentation and keep lines short."),
+            file(
+                "docs/testing.md",
+                "Run cargo test before pushing. Integration tests need docker.",
+            ),
+        ];
+
+        assert!(retain_most_relevant(&mut files.clone(), "anything", 3).is_empty());
+
+        let pruned = retain_most_relevant(&mut files, "How do I deploy to staging?", 1);
+        assert_eq!(files, vec![file(
+            "docs/deploy.md",
+            "Run cdk deploy to ship the stack to the staging account."
+        )]);
+        assert_eq!(pruned.len(), 2);
+    }
+
+    #[test]
+    fn test_truncate_head_tail() {
+        assert_eq!(truncate_head_tail("short", 10), "short");
+
+        let content = "a".repeat(200);
+        let truncated = truncate_head_tail(&content, 10);
+        assert!(truncated.starts_with(&"a".repeat(20)));
+        assert!(truncated.contains("160 characters truncated"));
+
+        let content = "é".repeat(100);
+        let truncated = truncate_head_tail(&content, 10);
+        assert!(truncated.contains("characters truncated"));
+    }
+
     #[test]
     fn test_calc_max_context_files_size() {
         assert_eq!(
@@ -0,0 +1,168 @@
+//! Output contracts for `q chat --contract schema.json`: the final answer of a session without user
+//! input has to be a JSON object matching the schema, so scripts can consume it as is.
+
+use std::path::Path;
+
+use eyre::{
+    Result,
+    eyre,
+};
+use serde_json::Value;
+
+use crate::os::Os;
+
+/// How many times the model is asked to fix an answer that doesn't match the schema.
+const MAX_RETRIES: usize = 2;
+
+pub struct Contract {
+    schema: Value,
+    validator: jsonschema::Validator,
+    retries: usize,
+}
+
+impl std::fmt::Debug for Contract {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Contract")
+            .field("schema", &self.schema)
+            .field("retries", &self.retries)
+            .finish()
+    }
+}
+
+impl Contract {
+    pub async fn load(os: &Os, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = os
+            .fs
+            .read_to_string(path)
+            .await
+            .map_err(|e| eyre!("Failed to read the contract {}: {e}", path.display()))?;
+        let schema = serde_json::from_str::<Value>(&content)
+            .map_err(|e| eyre!("The contract {} isn't valid JSON: {e}", path.display()))?;
+        Self::new(schema)
+    }
+
+    pub fn new(schema: Value) -> Result<Self> {
+        let validator =
+            jsonschema::validator_for(&schema).map_err(|e| eyre!("The contract isn't a valid JSON schema: {e}"))?;
+        Ok(Self {
+            schema,
+            validator,
+            retries: 0,
+        })
+    }
+
+    /// `input` with the instructions for the answer appended.
+    pub fn instruct(&self, input: &str) -> String {
+        format!(
+            "{input}\n\nOnce you are done, answer with a single JSON object matching the following JSON schema, \
+             and nothing else:\n```json\n{}\n```",
+            serde_json::to_string_pretty(&self.schema).unwrap_or_default()
+        )
+    }
+
+    /// The object in `response`, or why it doesn't match the schema.
+    pub fn validate(&self, response: &str) -> Result<Value, Vec<String>> {
+        let Some(value) = extract_json(response) else {
+            return Err(vec!["the answer doesn't contain a JSON object".to_string()]);
+        };
+        let violations = self
+            .validator
+            .iter_errors(&value)
+            .map(|e| match e.instance_path.to_string() {
+                path if path.is_empty() => e.to_string(),
+                path => format!("{path}: {e}"),
+            })
+            .collect::<Vec<_>>();
+        if violations.is_empty() {
+            Ok(value)
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// The prompt asking the model to fix its answer, or `None` once the retries are used up.
+    pub fn retry(&mut self, violations: &[String]) -> Option<String> {
+        if self.retries >= MAX_RETRIES {
+            return None;
+        }
+        self.retries += 1;
+        Some(format!(
+            "Your answer doesn't match the JSON schema:\n{}\n\nAnswer again with only the corrected JSON object.",
+            violations
+                .iter()
+                .map(|violation| format!("- {violation}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+}
+
+/// The JSON in `text`: all of it, the first fenced code block, or the span from the first `{` to
+/// the last `}`.
+fn extract_json(text: &str) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(value) = serde_json::from_str(text) {
+        return Some(value);
+    }
+    if let Some((_, rest)) = text.split_once("```") {
+        let block = rest.split_once('\n').map_or(rest, |(_, block)| block);
+        if let Some((block, _)) = block.split_once("```") {
+            if let Ok(value) = serde_json::from_str(block) {
+                return Some(value);
+            }
+        }
+    }
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    serde_json::from_str(text.get(start..=end)?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contract() -> Contract {
+        Contract::new(serde_json::json!({
+            "type": "object",
+            "properties": {
+                "summary": { "type": "string" },
+                "files": { "type": "array", "items": { "type": "string" } }
+            },
+            "required": ["summary"]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extract_json() {
+        assert_eq!(extract_json(r#"{"a": 1}"#), Some(serde_json::json!({ "a": 1 })));
+        assert_eq!(
+            extract_json("Here it is:\n```json\n{\"a\": 1}\n```\n"),
+            Some(serde_json::json!({ "a": 1 }))
+        );
+        assert_eq!(
+            extract_json("The result is {\"a\": {\"b\": 2}}."),
+            Some(serde_json::json!({ "a": { "b": 2 } }))
+        );
+        assert_eq!(extract_json("no json here"), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut contract = contract();
+        assert_eq!(
+            contract.validate(r#"{"summary": "done", "files": ["a.rs"]}"#),
+            Ok(serde_json::json!({ "summary": "done", "files": ["a.rs"] }))
+        );
+
+        let violations = contract.validate(r#"{"files": [1]}"#).unwrap_err();
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().any(|v| v.starts_with("/files/0: ")));
+        assert!(contract.validate("I couldn't do it").is_err());
+
+        assert!(contract.retry(&violations).unwrap().contains("- /files/0: "));
+        assert!(contract.retry(&violations).is_some());
+        assert!(contract.retry(&violations).is_none());
+    }
+}
@@ -4,9 +4,15 @@ use std::collections::{
     VecDeque,
 };
 use std::io::Write;
+use std::path::PathBuf;
 use std::sync::atomic::Ordering;
+use std::time::Instant;
 
-use chrono::Local;
+use chrono::{
+    DateTime,
+    FixedOffset,
+    Local,
+};
 use crossterm::style::Color;
 use crossterm::{
     execute,
@@ -28,22 +34,34 @@ use tracing::{
     warn,
 };
 
-use super::cli::compact::CompactStrategy;
+use super::cli::compact::{
+    CompactStrategy,
+    CompactionPromptVariant,
+};
 use super::cli::hooks::HookOutput;
 use super::cli::model::context_window_tokens;
 use super::consts::{
+    AGENT_FORMAT_DOC_URL,
     DUMMY_TOOL_NAME,
     MAX_CONVERSATION_STATE_HISTORY_LEN,
 };
 use super::context::{
+    ContextFiles,
     ContextManager,
     calc_max_context_files_size,
+    context_assembly_timeout,
+    relevance_top_k,
+    retain_most_relevant,
 };
+use super::cost::SessionUsage;
+use super::error::CategorizedError;
 use super::line_tracker::FileLineTracker;
 use super::message::{
     AssistantMessage,
+    AssistantToolUse,
     ToolUseResult,
     UserMessage,
+    UserMessageContent,
 };
 use super::parser::RequestMetadata;
 use super::token_counter::{
@@ -59,6 +77,10 @@ use super::tools::{
     ToolSpec,
 };
 use super::util::serde_value_to_document;
+use super::{
+    history,
+    mention,
+};
 use crate::api_client::model::{
     ChatMessage,
     ConversationState as FigConversationState,
@@ -96,6 +118,45 @@ pub struct HistoryEntry {
     request_metadata: Option<RequestMetadata>,
 }
 
+impl HistoryEntry {
+    /// The prompt typed by the user, if this entry starts a turn rather than returning tool
+    /// results.
+    pub fn prompt(&self) -> Option<&str> {
+        self.user.prompt()
+    }
+
+    /// Whether both entries hold the same user message.
+    pub fn same_user_message(&self, other: &Self) -> bool {
+        self.user.timestamp == other.user.timestamp && self.user.prompt() == other.user.prompt()
+    }
+
+    /// The response of the assistant.
+    pub fn response(&self) -> &str {
+        self.assistant.content()
+    }
+
+    pub fn has_tool_uses(&self) -> bool {
+        self.assistant
+            .tool_uses()
+            .is_some_and(|tool_uses| !tool_uses.is_empty())
+    }
+
+    /// The tools the assistant asked to use.
+    pub fn tool_uses(&self) -> &[AssistantToolUse] {
+        self.assistant.tool_uses().unwrap_or_default()
+    }
+
+    /// The results of the tool uses of the previous entry, if this entry returns them.
+    pub fn tool_use_results(&self) -> &[ToolUseResult] {
+        self.user.tool_use_results().unwrap_or_default()
+    }
+
+    /// Whether the user rejected the tool uses of the previous entry.
+    pub fn cancelled_tool_uses(&self) -> bool {
+        matches!(self.user.content(), UserMessageContent::CancelledToolUses { .. })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct McpServerInfo {
     pub name: String,
@@ -149,6 +210,30 @@ pub struct ConversationState {
     /// Tangent mode checkpoint - stores main conversation when in tangent mode
     #[serde(default, skip_serializing_if = "Option::is_none")]
     tangent_state: Option<ConversationCheckpoint>,
+    /// Name of the current tangent, if it was started with `/tangent new`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tangent_name: Option<String>,
+    /// Named tangents that were left for the main conversation or another tangent, by name
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    tangents: HashMap<String, TangentBranch>,
+    /// Token usage reported by the backend over this session, used for cost accounting.
+    #[serde(skip)]
+    pub session_usage: SessionUsage,
+    /// Directory the session was started in. The conversation is persisted under it even after
+    /// `/cd` changed the working directory.
+    #[serde(skip, default = "current_dir")]
+    pub pinned_dir: Option<PathBuf>,
+    /// Title shown in the history index, taken from the first prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Languages, AWS services, and kinds of task the conversation is about, used to filter the
+    /// history index.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+fn current_dir() -> Option<PathBuf> {
+    std::env::current_dir().ok()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -164,6 +249,27 @@ struct ConversationCheckpoint {
     /// Timestamp when tangent mode was entered (milliseconds since epoch)
     #[serde(default = "time::OffsetDateTime::now_utc")]
     tangent_start_time: time::OffsetDateTime,
+    /// Length of the history of the tangent when it branched off the main conversation, if the
+    /// tangent did not branch off [Self::main_history]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    branch_len: Option<usize>,
+}
+
+impl ConversationCheckpoint {
+    /// Length of the history of the current tangent when it branched off the main conversation
+    fn branch_len(&self) -> usize {
+        self.branch_len.unwrap_or(self.main_history.len())
+    }
+}
+
+/// A named tangent that is not the current one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TangentBranch {
+    history: VecDeque<HistoryEntry>,
+    transcript: VecDeque<String>,
+    latest_summary: Option<(String, RequestMetadata)>,
+    /// Length of the history when the tangent branched off the main conversation
+    branch_len: usize,
 }
 
 impl ConversationState {
@@ -212,6 +318,12 @@ impl ConversationState {
             checkpoint_manager: None,
             mcp_enabled,
             tangent_state: None,
+            tangent_name: None,
+            tangents: HashMap::new(),
+            session_usage: SessionUsage::default(),
+            pinned_dir: current_dir(),
+            title: None,
+            tags: Vec::new(),
         }
     }
 
@@ -223,11 +335,41 @@ impl ConversationState {
         &self.history
     }
 
+    /// Index of the history entry that started the most recent turn.
+    pub fn last_turn_start(&self) -> Option<usize> {
+        self.history.iter().rposition(|entry| entry.prompt().is_some())
+    }
+
+    /// The final response to an earlier prompt of this conversation that is nearly identical to
+    /// `prompt`, if that turn completed.
+    pub fn find_previous_answer(&self, prompt: &str) -> Option<&str> {
+        let mut turn_end = None;
+        for entry in self.history.iter().rev() {
+            let end = *turn_end.get_or_insert(entry);
+            if let Some(earlier) = entry.prompt() {
+                if !end.has_tool_uses() && !end.response().trim().is_empty() && is_near_duplicate(earlier, prompt) {
+                    return Some(end.response());
+                }
+                turn_end = None;
+            }
+        }
+        None
+    }
+
+    /// Removes the history entries from `start` onwards, along with any pending message.
+    pub fn truncate_history(&mut self, start: usize) {
+        self.history.truncate(start);
+        self.next_message = None;
+        self.valid_history_range = (0, self.history.len());
+    }
+
     /// Clears the conversation history and summary.
     pub fn clear(&mut self) {
         self.next_message = None;
         self.history.clear();
         self.latest_summary = None;
+        self.title = None;
+        self.tags.clear();
     }
 
     /// Check if currently in tangent mode
@@ -243,6 +385,7 @@ impl ConversationState {
             main_transcript: self.transcript.clone(),
             main_latest_summary: self.latest_summary.clone(),
             tangent_start_time: time::OffsetDateTime::now_utc(),
+            branch_len: None,
         }
     }
 
@@ -274,16 +417,115 @@ impl ConversationState {
         })
     }
 
-    /// Exit tangent mode - restore from checkpoint
+    /// Exit tangent mode - restore from checkpoint. A named tangent is kept to switch back to or
+    /// merge later, an unnamed one is discarded.
     pub fn exit_tangent_mode(&mut self) {
         if let Some(checkpoint) = self.tangent_state.take() {
+            if let Some(name) = self.tangent_name.take() {
+                let branch = TangentBranch {
+                    history: std::mem::take(&mut self.history),
+                    transcript: std::mem::take(&mut self.transcript),
+                    latest_summary: self.latest_summary.take(),
+                    branch_len: checkpoint.branch_len(),
+                };
+                self.tangents.insert(name, branch);
+            }
             self.restore_from_checkpoint(checkpoint);
         }
     }
 
+    /// Name of the current tangent, if it is named
+    pub fn tangent_name(&self) -> Option<&str> {
+        self.tangent_name.as_deref()
+    }
+
+    /// Names of the named tangents other than the current one, sorted
+    pub fn parked_tangents(&self) -> Vec<&str> {
+        let mut names = self.tangents.keys().map(String::as_str).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    /// Whether a named tangent exists, current or not
+    pub fn has_tangent(&self, name: &str) -> bool {
+        self.tangent_name.as_deref() == Some(name) || self.tangents.contains_key(name)
+    }
+
+    /// Number of history entries added since the current tangent branched off the main
+    /// conversation
+    pub fn tangent_len(&self) -> usize {
+        self.tangent_state.as_ref().map_or(0, |checkpoint| {
+            self.history.len().saturating_sub(checkpoint.branch_len())
+        })
+    }
+
+    /// Starts a named tangent off the main conversation, leaving the current tangent first
+    pub fn new_tangent(&mut self, name: String) {
+        self.exit_tangent_mode();
+        self.tangent_state = Some(self.create_checkpoint());
+        self.tangent_name = Some(name);
+    }
+
+    /// Switches to a named tangent, leaving the current tangent first. Returns false if there is
+    /// no tangent of that name.
+    pub fn switch_tangent(&mut self, name: &str) -> bool {
+        if self.tangent_name.as_deref() == Some(name) {
+            return true;
+        }
+        let Some(branch) = self.tangents.remove(name) else {
+            return false;
+        };
+
+        self.exit_tangent_mode();
+        let mut checkpoint = self.create_checkpoint();
+        checkpoint.branch_len = Some(branch.branch_len);
+        self.tangent_state = Some(checkpoint);
+        self.tangent_name = Some(name.to_string());
+        self.history = branch.history;
+        self.next_message = None;
+        self.transcript = branch.transcript;
+        self.latest_summary = branch.latest_summary;
+        self.valid_history_range = (0, self.history.len());
+        true
+    }
+
+    /// Returns to the main conversation with the summary of the current tangent added to it, and
+    /// discards the tangent.
+    ///
+    /// `summary` should be created with [Self::create_tangent_summary_request].
+    pub fn merge_tangent(&mut self, summary: String, request_metadata: RequestMetadata) {
+        let Some(checkpoint) = self.tangent_state.take() else {
+            return;
+        };
+        let tangent = match self.tangent_name.take() {
+            Some(name) => format!("the tangent '{}'", name),
+            None => "a tangent".to_string(),
+        };
+        self.restore_from_checkpoint(checkpoint);
+
+        let prompt = format!(
+            "[SYSTEM NOTE: This summarizes a side discussion, not a new request from the user]\n\n\
+            The user explored {} apart from this conversation. This is a summary of it, to take into account from now on:\n\n{}",
+            tangent, summary
+        );
+        let response = format!("Noted. I will take the summary of {} into account.", tangent);
+        self.append_user_transcript(&prompt);
+        let assistant = AssistantMessage::new_response(None, response);
+        self.append_assistant_transcript(&assistant);
+        self.record_usage(&request_metadata);
+        self.history.push_back(HistoryEntry {
+            user: UserMessage::new_prompt(prompt, None),
+            assistant,
+            request_metadata: Some(request_metadata),
+        });
+    }
+
     /// Exit tangent mode and preserve the last conversation entry (user + assistant)
     pub fn exit_tangent_mode_with_tail(&mut self) {
         if let Some(checkpoint) = self.tangent_state.take() {
+            // The tangent is discarded even if it is named, its tail being kept instead
+            self.tangent_name = None;
+
             // Capture the last history entry from tangent conversation if it exists
             // and if it's different from what was in the main conversation
             let last_entry = if self.history.len() > checkpoint.main_history.len() {
@@ -405,15 +647,45 @@ impl ConversationState {
         debug_assert!(self.next_message.is_some(), "next_message should exist");
         let next_user_message = self.next_message.take().expect("next user message should exist");
 
+        if let Some(request_metadata) = &request_metadata {
+            self.record_usage(request_metadata);
+        }
         self.append_assistant_transcript(&message);
         self.history.push_back(HistoryEntry {
             user: next_user_message,
             assistant: message,
             request_metadata,
         });
+        self.update_title_and_tags();
+
+        if let Some(dir) = self.pinned_dir.as_ref() {
+            os.database.set_conversation_by_path(dir, self).ok();
+        }
+    }
+
+    /// Derives the title and tags shown in the history index from the prompts and edited files.
+    fn update_title_and_tags(&mut self) {
+        let mut prompts = self.history.iter().filter_map(HistoryEntry::prompt).peekable();
+        if self.title.is_none() {
+            self.title = prompts.peek().map(|prompt| history::title(prompt));
+        }
+        self.tags = history::tags(prompts, self.file_line_tracker.keys().map(String::as_str));
+    }
 
-        if let Ok(cwd) = std::env::current_dir() {
-            os.database.set_conversation_by_path(cwd, self).ok();
+    /// When the user last sent a message.
+    pub fn last_activity(&self) -> Option<DateTime<FixedOffset>> {
+        self.history.iter().rev().find_map(|entry| entry.user.timestamp)
+    }
+
+    /// When the conversation saved for the directory `path` was last used. Conversations from
+    /// versions that did not record when messages were sent fall back to when the directory was
+    /// modified, or the epoch once it no longer exists.
+    pub fn last_activity_or_modified(&self, path: &str) -> std::time::SystemTime {
+        match self.last_activity() {
+            Some(time) => time.into(),
+            None => std::fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .unwrap_or(std::time::UNIX_EPOCH),
         }
     }
 
@@ -511,6 +783,25 @@ impl ConversationState {
         self.history.drain(self.valid_history_range.1..);
         self.history.drain(..self.valid_history_range.0);
 
+        if let Some(context_manager) = self.context_manager.as_mut() {
+            for change in context_manager.refresh_watched_paths(os).await {
+                execute!(
+                    stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!(
+                        "Context rule {} now matches {} file{} (+{} -{})\n",
+                        change.path,
+                        change.total,
+                        if change.total == 1 { "" } else { "s" },
+                        change.added.len(),
+                        change.removed.len()
+                    )),
+                    style::SetForegroundColor(style::Color::Reset)
+                )
+                .ok();
+            }
+        }
+
         let context = self.backend_conversation_state(os, run_perprompt_hooks, stderr).await?;
         if !context.dropped_context_files.is_empty() {
             execute!(
@@ -571,38 +862,39 @@ impl ConversationState {
         self.update_state(false).await;
         self.enforce_conversation_invariants();
 
-        // Run hooks and add to conversation start and next user message.
+        // Run hooks and add to conversation start and next user message, while the context
+        // files are read.
         let mut agent_spawn_context = None;
+        let mut prompt_context = Vec::new();
+        let mut context_files = None;
         if let Some(cm) = self.context_manager.as_mut() {
             let user_prompt = self.next_message.as_ref().and_then(|m| m.prompt());
-            let agent_spawn = cm
-                .run_hooks(
-                    HookTrigger::AgentSpawn,
-                    output,
-                    os,
-                    user_prompt,
-                    None, // tool_context
-                )
-                .await?;
-            agent_spawn_context = format_hook_context(&agent_spawn, HookTrigger::AgentSpawn);
-
-            if let (true, Some(next_message)) = (run_perprompt_hooks, self.next_message.as_mut()) {
-                let per_prompt = cm
-                    .run_hooks(
-                        HookTrigger::UserPromptSubmit,
-                        output,
-                        os,
-                        next_message.prompt(),
-                        None, // tool_context
-                    )
-                    .await?;
-                if let Some(ctx) = format_hook_context(&per_prompt, HookTrigger::UserPromptSubmit) {
-                    next_message.additional_context = ctx;
-                }
+            let triggers = match run_perprompt_hooks && self.next_message.is_some() {
+                true => vec![HookTrigger::AgentSpawn, HookTrigger::UserPromptSubmit],
+                false => vec![HookTrigger::AgentSpawn],
+            };
+            let deadline = Instant::now() + context_assembly_timeout(os);
+            let assembled = cm.assemble(os, output, &triggers, user_prompt, deadline).await?;
+
+            agent_spawn_context = format_hook_context(&assembled.hook_results, HookTrigger::AgentSpawn);
+            prompt_context.extend(format_hook_context(
+                &assembled.hook_results,
+                HookTrigger::UserPromptSubmit,
+            ));
+            context_files = assembled.files;
+        }
+
+        if let (true, Some(next_message)) = (run_perprompt_hooks, self.next_message.as_mut()) {
+            if let Some(prompt) = next_message.prompt() {
+                prompt_context.extend(mention::mentioned_context(os, &mut self.tool_manager, prompt, output).await);
+            }
+            if !prompt_context.is_empty() {
+                next_message.additional_context = prompt_context.join("\n");
             }
         }
 
-        let (context_messages, dropped_context_files) = self.context_messages(os, agent_spawn_context).await;
+        let (context_messages, dropped_context_files, context_breakdown) =
+            self.context_messages(os, agent_spawn_context, context_files);
 
         Ok(BackendConversationState {
             conversation_id: self.conversation_id.as_str(),
@@ -612,6 +904,7 @@ impl ConversationState {
                 .range(self.valid_history_range.0..self.valid_history_range.1),
             context_messages,
             dropped_context_files,
+            context_breakdown,
             tools: &self.tools,
             model_id: self.model_info.as_ref().map(|m| m.model_id.as_str()),
         })
@@ -654,9 +947,8 @@ impl ConversationState {
                     custom_prompt.as_ref()
                 )
             },
-            None => {
-                // Default prompt
-                "[SYSTEM NOTE: This is an automated summarization request, not from the user]\n\n\
+            None => match CompactionPromptVariant::from_settings(os) {
+                CompactionPromptVariant::Default => "[SYSTEM NOTE: This is an automated summarization request, not from the user]\n\n\
                         FORMAT REQUIREMENTS: Create a structured, concise summary in bullet-point format. DO NOT respond conversationally. DO NOT address the user directly.\n\n\
                         Your task is to create a structured summary document containing:\n\
                         1) A bullet-point list of key topics/questions covered\n\
@@ -673,7 +965,29 @@ impl ConversationState {
                         ## TODO ID\n\
                         * <id>\n\n\
                         Remember this is a DOCUMENT not a chat response.\n\
-                        FILTER OUT CHAT CONVENTIONS (greetings, offers to help, etc).".to_string()
+                        FILTER OUT CHAT CONVENTIONS (greetings, offers to help, etc).".to_string(),
+                CompactionPromptVariant::TaskFocused => "[SYSTEM NOTE: This is an automated summarization request, not from the user]\n\n\
+                        FORMAT REQUIREMENTS: Create a structured, concise summary in bullet-point format. DO NOT respond conversationally. DO NOT address the user directly.\n\n\
+                        Your task is to create a summary document that lets the work continue exactly where it left off, containing:\n\
+                        1) The goal the user is currently working towards, in their own terms\n\
+                        2) The current state of the work: files created or modified, commands run and their outcomes\n\
+                        3) Decisions made and constraints given by the user, including approaches that were rejected\n\
+                        4) Open questions, errors still unresolved, and the next steps\n\
+                        5) REQUIRED: the ID of the currently loaded todo list, if any\n\n\
+                        Keep exact file paths, identifiers, and error messages. Drop topics that no longer affect the current task.\n\n\
+                        FORMAT THE SUMMARY IN THIRD PERSON, NOT AS A DIRECT RESPONSE. Example format:\n\n\
+                        ## CURRENT GOAL\n\
+                        * Goal\n\n\
+                        ## STATE\n\
+                        * File or command: Outcome\n\n\
+                        ## DECISIONS\n\
+                        * Decision\n\n\
+                        ## NEXT STEPS\n\
+                        * Step\n\n\
+                        ## TODO ID\n\
+                        * <id>\n\n\
+                        Remember this is a DOCUMENT not a chat response.\n\
+                        FILTER OUT CHAT CONVENTIONS (greetings, offers to help, etc).".to_string(),
             },
         };
         if let Some((summary, _)) = &self.latest_summary {
@@ -698,19 +1012,47 @@ impl ConversationState {
             }
         }
 
-        // Only send the dummy tool spec in order to prevent the model from ever attempting a tool
-        // use.
-        let mut tools = self.tools.clone();
-        tools.retain(|k, v| match k {
-            ToolOrigin::Native => {
-                v.retain(|tool| match tool {
-                    Tool::ToolSpecification(tool_spec) => tool_spec.name == DUMMY_TOOL_NAME,
-                });
-                true
-            },
-            ToolOrigin::McpServer(_) => false,
-        });
+        let tools = self.summary_tools();
+        enforce_conversation_invariants(&mut history, &mut summary_message, &tools);
+
+        Ok(FigConversationState {
+            conversation_id: Some(self.conversation_id.clone()),
+            user_input_message: summary_message
+                .unwrap_or(UserMessage::new_prompt(summary_content, None)) // should not happen
+                .into_user_input_message(self.model_info.as_ref().map(|m| m.model_id.clone()), &tools),
+            history: Some(flatten_history(history.iter())),
+        })
+    }
+
+    /// Returns a [FigConversationState] asking the model to summarize the current tangent.
+    ///
+    /// The resulting summary should update the state by immediately following with
+    /// [ConversationState::merge_tangent].
+    pub async fn create_tangent_summary_request(&mut self, os: &Os) -> Result<FigConversationState, ChatError> {
+        let summary_content = format!(
+            "[SYSTEM NOTE: This is an automated summarization request, not from the user]\n\n\
+            FORMAT REQUIREMENTS: Create a structured, concise summary in bullet-point format. DO NOT respond conversationally. DO NOT address the user directly.\n\n\
+            The last {} messages of this conversation are a tangent: a side discussion that the user now wants to bring back into the main conversation. \
+            Your task is to summarize ONLY the tangent, not the conversation before it, containing:\n\
+            1) The questions explored\n\
+            2) The conclusions reached and decisions made\n\
+            3) Code, commands, file paths and other technical details that the main conversation will need\n\
+            4) Open questions\n\n\
+            FORMAT THE SUMMARY IN THIRD PERSON, NOT AS A DIRECT RESPONSE. Example format:\n\n\
+            ## TANGENT SUMMARY\n\
+            * Question: Conclusion\n\n\
+            ## DETAILS\n\
+            * Detail\n\n\
+            Remember this is a DOCUMENT not a chat response.\n\
+            FILTER OUT CHAT CONVENTIONS (greetings, offers to help, etc).",
+            self.tangent_len()
+        );
+
+        let conv_state = self.backend_conversation_state(os, false, &mut vec![]).await?;
+        let mut summary_message = Some(UserMessage::new_prompt(summary_content.clone(), None));
+        let mut history = conv_state.history.cloned().collect::<VecDeque<_>>();
 
+        let tools = self.summary_tools();
         enforce_conversation_invariants(&mut history, &mut summary_message, &tools);
 
         Ok(FigConversationState {
@@ -722,6 +1064,22 @@ impl ConversationState {
         })
     }
 
+    /// Only the dummy tool spec, sent with summary requests in order to prevent the model from
+    /// ever attempting a tool use.
+    fn summary_tools(&self) -> HashMap<ToolOrigin, Vec<Tool>> {
+        let mut tools = self.tools.clone();
+        tools.retain(|k, v| match k {
+            ToolOrigin::Native => {
+                v.retain(|tool| match tool {
+                    Tool::ToolSpecification(tool_spec) => tool_spec.name == DUMMY_TOOL_NAME,
+                });
+                true
+            },
+            ToolOrigin::McpServer(_) => false,
+        });
+        tools
+    }
+
     /// `strategy` - The [CompactStrategy] used for the corresponding
     /// [ConversationState::create_summary_request].
     pub fn replace_history_with_summary(
@@ -732,9 +1090,35 @@ impl ConversationState {
     ) {
         self.history
             .drain(..(self.history.len().saturating_sub(strategy.messages_to_exclude)));
+        self.record_usage(&request_metadata);
         self.latest_summary = Some((summary, request_metadata));
     }
 
+    /// Token usage recorded in the history. Unlike [Self::session_usage], this survives the
+    /// conversation being saved and loaded, but only covers the requests still in the history.
+    pub fn history_usage(&self) -> SessionUsage {
+        let mut usage = SessionUsage::default();
+        let metadata = self
+            .history
+            .iter()
+            .filter_map(|entry| entry.request_metadata.as_ref())
+            .chain(self.latest_summary.as_ref().map(|(_, metadata)| metadata));
+        for metadata in metadata {
+            if let Some(token_usage) = &metadata.token_usage {
+                usage.record(metadata.model_id.as_deref(), token_usage);
+            }
+        }
+        usage
+    }
+
+    /// Adds the token usage reported for a request, if any, to [Self::session_usage].
+    pub fn record_usage(&mut self, request_metadata: &RequestMetadata) {
+        if let Some(token_usage) = &request_metadata.token_usage {
+            self.session_usage
+                .record(request_metadata.model_id.as_deref(), token_usage);
+        }
+    }
+
     pub async fn create_agent_generation_request(
         &mut self,
         agent_name: &str,
@@ -796,13 +1180,15 @@ Return only the JSON configuration, no additional text.",
     ///   a single user message, or handle this case more gracefully. For now, always return 2
     ///   messages.
     /// - Cache this return for some period of time.
-    async fn context_messages(
+    fn context_messages(
         &mut self,
         os: &Os,
         additional_context: Option<String>,
-    ) -> (Option<Vec<HistoryEntry>>, Vec<(String, String)>) {
+        context_files: Option<ContextFiles>,
+    ) -> (Option<Vec<HistoryEntry>>, Vec<(String, String)>, ContextBreakdown) {
         let mut context_content = String::new();
         let mut dropped_context_files = Vec::new();
+        let mut breakdown = ContextBreakdown::default();
         if let Some((summary, _)) = &self.latest_summary {
             context_content.push_str(CONTEXT_ENTRY_START_HEADER);
             context_content.push_str("This summary contains ALL relevant information from our previous conversation including tool uses, results, code analysis, and file operations. YOU MUST reference this information when answering questions and explicitly acknowledge specific details from the summary when they're relevant to the current question.\n\n");
@@ -810,36 +1196,51 @@ Return only the JSON configuration, no additional text.",
             context_content.push_str(summary);
             context_content.push('\n');
             context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+            breakdown.summary = context_content.len().into();
         }
 
         // Add context files if available
-        if let Some(context_manager) = self.context_manager.as_mut() {
-            match context_manager.collect_context_files_with_limit(os).await {
-                Ok((files_to_use, files_dropped)) => {
-                    if !files_dropped.is_empty() {
-                        dropped_context_files.extend(files_dropped);
-                    }
+        if let Some((mut files_to_use, files_dropped)) = context_files {
+            if !files_dropped.is_empty() {
+                dropped_context_files.extend(files_dropped);
+            }
 
-                    if !files_to_use.is_empty() {
-                        context_content.push_str(CONTEXT_ENTRY_START_HEADER);
-                        for (filename, content) in files_to_use {
-                            context_content.push_str(&format!("[{}]\n{}\n", filename, content));
-                        }
-                        context_content.push_str(CONTEXT_ENTRY_END_HEADER);
-                    }
-                },
-                Err(e) => {
-                    warn!("Failed to get context files: {}", e);
-                },
+            // Turns continuing with tool results are scored against the prompt that
+            // started them.
+            let prompt = self
+                .next_message
+                .as_ref()
+                .and_then(|message| message.prompt())
+                .or_else(|| self.history.iter().rev().find_map(HistoryEntry::prompt));
+            if let (Some(top_k), Some(prompt)) = (relevance_top_k(os), prompt) {
+                let pruned = retain_most_relevant(&mut files_to_use, prompt, top_k);
+                if !pruned.is_empty() {
+                    debug!(
+                        pruned = ?pruned.iter().map(|(filename, _)| filename).collect::<Vec<_>>(),
+                        "left out context files irrelevant to the prompt"
+                    );
+                }
+            }
+
+            if !files_to_use.is_empty() {
+                let start = context_content.len();
+                context_content.push_str(CONTEXT_ENTRY_START_HEADER);
+                for (filename, content) in files_to_use {
+                    context_content.push_str(&format!("[{}]\n{}\n", filename, content));
+                }
+                context_content.push_str(CONTEXT_ENTRY_END_HEADER);
+                breakdown.context_files = (context_content.len() - start).into();
             }
         }
 
         if let Some(context) = additional_context {
+            breakdown.hooks = context.len().into();
             context_content.push_str(&context);
         }
 
         if let Some(agent_prompt) = self.agents.get_active().and_then(|a| a.prompt.as_ref()) {
-            context_content.push_str(&format!("Follow this instruction: {}", agent_prompt));
+            let agent_prompt = format!("Follow this instruction: {}", agent_prompt);
+            context_content.push_str(&agent_prompt);
         }
 
         if !context_content.is_empty() {
@@ -853,9 +1254,10 @@ Return only the JSON configuration, no additional text.",
                     request_metadata: None,
                 }]),
                 dropped_context_files,
+                breakdown,
             )
         } else {
-            (None, dropped_context_files)
+            (None, dropped_context_files, breakdown)
         }
     }
 
@@ -922,7 +1324,7 @@ Return only the JSON configuration, no additional text.",
             .tool_manager
             .load_tools(os, stderr)
             .await
-            .map_err(|e| ChatError::Custom(format!("Failed to reload built-in tools: {e}").into()))?;
+            .map_err(|e| CategorizedError::tool_failure(format!("Failed to reload built-in tools: {e}")))?;
 
         // Remove existing built-in tools and add updated ones, preserving MCP tools
         self.tools.retain(|origin, _| *origin != ToolOrigin::Native);
@@ -931,6 +1333,15 @@ Return only the JSON configuration, no additional text.",
         Ok(())
     }
 
+    /// Switches the model of the conversation, keeping its history. The budget of the context
+    /// files follows the context window of the new model.
+    pub fn set_model(&mut self, model: ModelInfo) {
+        if let Some(context_manager) = &mut self.context_manager {
+            context_manager.set_max_context_files_size(calc_max_context_files_size(Some(&model)));
+        }
+        self.model_info = Some(model);
+    }
+
     /// Swapping agent involves the following:
     /// - Reinstantiate the context manager
     /// - Swap agent on tool manager
@@ -942,8 +1353,10 @@ Return only the JSON configuration, no additional text.",
     ) -> Result<(), ChatError> {
         let agent = self.agents.switch(agent_name).map_err(ChatError::AgentSwapError)?;
         self.context_manager.replace({
-            ContextManager::from_agent(agent, calc_max_context_files_size(self.model_info.as_ref()))
-                .map_err(|e| ChatError::Custom(format!("Context manager has failed to instantiate: {e}").into()))?
+            ContextManager::from_agent(agent, calc_max_context_files_size(self.model_info.as_ref())).map_err(|e| {
+                CategorizedError::config_invalid(format!("Context manager has failed to instantiate: {e}"))
+                    .with_doc_url(AGENT_FORMAT_DOC_URL)
+            })?
         });
 
         self.tool_manager
@@ -990,6 +1403,8 @@ pub struct BackendConversationStateImpl<'a, T, U> {
     pub history: T,
     pub context_messages: U,
     pub dropped_context_files: Vec<(String, String)>,
+    /// Character counts of the components making up [Self::context_messages].
+    pub context_breakdown: ContextBreakdown,
     pub tools: &'a HashMap<ToolOrigin, Vec<Tool>>,
     pub model_id: Option<&'a str>,
 }
@@ -1050,6 +1465,19 @@ pub struct ConversationSize {
     pub assistant_messages: CharCount,
 }
 
+/// Reflects the components that make up the context messages of a conversation. Any characters
+/// not accounted for here are the agent prompt and the overhead added around these components,
+/// e.g. headers, which `/usage` reports together as the system prompt.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContextBreakdown {
+    /// The latest compaction summary.
+    pub summary: CharCount,
+    /// Files matched by the context rules.
+    pub context_files: CharCount,
+    /// Output of agent spawn hooks.
+    pub hooks: CharCount,
+}
+
 /// Converts a list of user/assistant message pairs into a flattened list of ChatMessage.
 fn flatten_history<'a, T>(history: T) -> Vec<ChatMessage>
 where
@@ -1083,14 +1511,16 @@ impl From<InputSchema> for ToolInputSchema {
 /// prompts).
 ///
 /// # Returns
-/// [Option::Some] if `hook_results` is not empty and at least one hook has content. Otherwise,
+/// [Option::Some] if at least one hook of `trigger` succeeded with content. Otherwise,
 /// [Option::None]
 fn format_hook_context(hook_results: &[((HookTrigger, Hook), HookOutput)], trigger: HookTrigger) -> Option<String> {
     // Note: only format context when hook command exit code is 0
-    if hook_results
+    let outputs = hook_results
         .iter()
-        .all(|(_, (exit_code, content))| *exit_code != 0 || content.is_empty())
-    {
+        .filter(|((h_trigger, _), (exit_code, _))| *h_trigger == trigger && *exit_code == 0)
+        .map(|(_, (_, output))| output)
+        .collect::<Vec<_>>();
+    if outputs.iter().all(|output| output.is_empty()) {
         return None;
     }
 
@@ -1103,10 +1533,7 @@ fn format_hook_context(hook_results: &[((HookTrigger, Hook), HookOutput)], trigg
     }
     context_content.push_str("\n\n");
 
-    for (_, (_, output)) in hook_results
-        .iter()
-        .filter(|((h_trigger, _), (exit_code, _))| *h_trigger == trigger && *exit_code == 0)
-    {
+    for output in outputs {
         context_content.push_str(&format!("{output}\n\n"));
     }
     context_content.push_str(CONTEXT_ENTRY_END_HEADER);
@@ -1263,6 +1690,38 @@ fn enforce_tool_use_history_invariants(history: &mut VecDeque<HistoryEntry>, too
 fn default_true() -> bool {
     true
 }
+/// Words of a prompt, ignoring case and punctuation.
+fn prompt_words(prompt: &str) -> Vec<String> {
+    prompt
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Whether two prompts ask the same thing, give or take case, punctuation, and a word here and
+/// there.
+fn is_near_duplicate(a: &str, b: &str) -> bool {
+    /// Share of distinct words both prompts must have in common.
+    const MIN_SIMILARITY: f64 = 0.85;
+    /// Short prompts such as "continue" or "try again" are repeated on purpose, so they never
+    /// count.
+    const MIN_WORDS: usize = 5;
+
+    let (a, b) = (prompt_words(a), prompt_words(b));
+    if a.len().min(b.len()) < MIN_WORDS {
+        return false;
+    }
+    if a == b {
+        return true;
+    }
+
+    let a = a.iter().collect::<HashSet<_>>();
+    let b = b.iter().collect::<HashSet<_>>();
+    let shared = a.intersection(&b).count();
+    shared as f64 / a.union(&b).count() as f64 >= MIN_SIMILARITY
+}
+
 #[cfg(test)]
 mod tests {
     use super::super::message::AssistantToolUse;
@@ -1543,6 +2002,68 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_set_model_keeps_history() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let mut conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+        conversation.set_next_user_message("start".to_string()).await;
+        conversation.push_assistant_message(&mut os, AssistantMessage::new_response(None, "a".repeat(4000)), None);
+
+        let model = |context_window_tokens| ModelInfo {
+            model_name: None,
+            description: None,
+            model_id: format!("model-{context_window_tokens}"),
+            context_window_tokens,
+        };
+        conversation.set_model(model(200_000));
+        assert_eq!(conversation.history().len(), 1);
+        assert_eq!(
+            conversation.get_token_warning_level(&os).await.unwrap(),
+            TokenWarningLevel::None
+        );
+
+        conversation.set_model(model(100));
+        assert_eq!(conversation.history().len(), 1);
+        assert_eq!(conversation.model_info.as_ref().unwrap().model_id, "model-100");
+        assert_eq!(
+            conversation.get_token_warning_level(&os).await.unwrap(),
+            TokenWarningLevel::Critical
+        );
+    }
+
+    #[tokio::test]
+    async fn test_last_activity_or_modified_falls_back_to_mtime() {
+        let mut os = Os::new().await.unwrap();
+        let mut tool_manager = ToolManager::default();
+        let conversation = ConversationState::new(
+            "fake_conv_id",
+            Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+        assert!(conversation.last_activity().is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("conversation.json");
+        std::fs::write(&path, "{}").unwrap();
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        assert_eq!(conversation.last_activity_or_modified(path.to_str().unwrap()), modified);
+    }
+
     #[tokio::test]
     async fn test_tangent_mode() {
         let mut os = Os::new().await.unwrap();
@@ -1746,4 +2267,91 @@ mod tests {
         conversation.exit_tangent_mode_with_tail();
         assert_eq!(conversation.history.len(), main_history_len);
     }
+
+    #[tokio::test]
+    async fn test_named_tangents() {
+        let mut os = Os::new().await.unwrap();
+        let agents = Agents::default();
+        let mut tool_manager = ToolManager::default();
+        let mut conversation = ConversationState::new(
+            "test_conv_id",
+            agents,
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+
+        conversation.set_next_user_message("main question".to_string()).await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_response(None, "main response".to_string()),
+            None,
+        );
+
+        // Start a named tangent and add to it
+        conversation.new_tangent("csv".to_string());
+        assert_eq!(conversation.tangent_name(), Some("csv"));
+        conversation.set_next_user_message("csv question".to_string()).await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_response(None, "csv response".to_string()),
+            None,
+        );
+        assert_eq!(conversation.tangent_len(), 1);
+
+        // Starting another tangent keeps the first one, and branches off the main conversation
+        conversation.new_tangent("errors".to_string());
+        assert_eq!(conversation.history.len(), 1);
+        assert_eq!(conversation.tangent_len(), 0);
+        assert_eq!(conversation.parked_tangents(), vec!["csv"]);
+        assert!(conversation.has_tangent("csv") && conversation.has_tangent("errors"));
+
+        // Returning to main keeps the current tangent too
+        conversation.exit_tangent_mode();
+        assert!(!conversation.is_in_tangent_mode());
+        assert_eq!(conversation.parked_tangents(), vec!["csv", "errors"]);
+
+        // Main moves on, and switching back restores the tangent as it was left
+        conversation.set_next_user_message("main follow up".to_string()).await;
+        conversation.push_assistant_message(
+            &mut os,
+            AssistantMessage::new_response(None, "main follow up response".to_string()),
+            None,
+        );
+        assert!(!conversation.switch_tangent("missing"));
+        assert!(conversation.switch_tangent("csv"));
+        assert_eq!(conversation.tangent_name(), Some("csv"));
+        assert_eq!(conversation.tangent_len(), 1);
+        assert_eq!(conversation.history.back().unwrap().assistant.content(), "csv response");
+
+        // Merging adds the summary to the main conversation and discards the tangent
+        conversation.merge_tangent("* CSV: use the csv module".to_string(), RequestMetadata::default());
+        assert!(!conversation.is_in_tangent_mode());
+        assert!(!conversation.has_tangent("csv"));
+        assert_eq!(conversation.parked_tangents(), vec!["errors"]);
+        assert_eq!(conversation.history.len(), 3);
+        let merged = conversation.history.back().unwrap();
+        assert!(merged.prompt().unwrap().contains("the tangent 'csv'"));
+        assert!(merged.prompt().unwrap().contains("use the csv module"));
+    }
+
+    #[test]
+    fn test_is_near_duplicate() {
+        assert!(is_near_duplicate(
+            "How do I configure the retry policy for the S3 client?",
+            "how do i configure the retry policy for the s3 client"
+        ));
+        assert!(is_near_duplicate(
+            "Explain how the token counter estimates the size of tool results",
+            "Explain how the token counter estimates the size of the tool results"
+        ));
+        assert!(!is_near_duplicate(
+            "How do I configure the retry policy for the S3 client?",
+            "How do I configure the timeout policy for the DynamoDB client?"
+        ));
+        assert!(!is_near_duplicate("try again", "try again"));
+    }
 }
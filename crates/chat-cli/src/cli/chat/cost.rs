@@ -0,0 +1,314 @@
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::io::Write;
+
+use crossterm::style::{
+    Attribute,
+    Color,
+};
+use crossterm::{
+    queue,
+    style,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::warn;
+
+use crate::api_client::model::TokenUsage;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Key used for requests that did not explicitly select a model.
+const DEFAULT_MODEL_KEY: &str = "default";
+
+/// Built-in prices in USD per million tokens, overridable with [Setting::ChatPriceTable].
+const DEFAULT_PRICES: &[(&str, ModelPrice)] = &[
+    ("claude-3.7-sonnet", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-sonnet-4", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-sonnet-4.5", ModelPrice::new(3.0, 15.0, 0.3, 3.75)),
+    ("claude-haiku-4.5", ModelPrice::new(1.0, 5.0, 0.1, 1.25)),
+];
+
+/// Cumulative token usage for a single model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ModelUsage {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub cache_write_input_tokens: u64,
+}
+
+impl ModelUsage {
+    fn add(&mut self, other: &ModelUsage) {
+        self.requests += other.requests;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+        self.cache_write_input_tokens += other.cache_write_input_tokens;
+    }
+
+    /// Estimated cost in USD according to `price`.
+    pub fn estimated_cost(&self, price: &ModelPrice) -> f64 {
+        let per_token = |tokens: u64, price_per_million: f64| tokens as f64 * price_per_million / 1_000_000.0;
+        per_token(self.input_tokens, price.input)
+            + per_token(self.output_tokens, price.output)
+            + per_token(self.cache_read_input_tokens, price.cache_read.unwrap_or(price.input))
+            + per_token(self.cache_write_input_tokens, price.cache_write.unwrap_or(price.input))
+    }
+}
+
+impl From<&TokenUsage> for ModelUsage {
+    fn from(value: &TokenUsage) -> Self {
+        Self {
+            requests: 1,
+            input_tokens: value.uncached_input_tokens,
+            output_tokens: value.output_tokens,
+            cache_read_input_tokens: value.cache_read_input_tokens,
+            cache_write_input_tokens: value.cache_write_input_tokens,
+        }
+    }
+}
+
+/// Token usage accumulated over a chat session, keyed by model id.
+#[derive(Debug, Clone, Default)]
+pub struct SessionUsage {
+    by_model: BTreeMap<String, ModelUsage>,
+}
+
+impl SessionUsage {
+    /// Records the token usage reported for a single request.
+    pub fn record(&mut self, model_id: Option<&str>, usage: &TokenUsage) {
+        self.by_model
+            .entry(model_id.unwrap_or(DEFAULT_MODEL_KEY).to_string())
+            .or_default()
+            .add(&usage.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_model.is_empty()
+    }
+
+    pub fn by_model(&self) -> impl Iterator<Item = (&str, &ModelUsage)> {
+        self.by_model.iter().map(|(model_id, usage)| (model_id.as_str(), usage))
+    }
+
+    pub fn total(&self) -> ModelUsage {
+        let mut total = ModelUsage::default();
+        for usage in self.by_model.values() {
+            total.add(usage);
+        }
+        total
+    }
+}
+
+/// Price in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    /// Price of input tokens read from the prompt cache. Defaults to the input price.
+    #[serde(default)]
+    pub cache_read: Option<f64>,
+    /// Price of input tokens written to the prompt cache. Defaults to the input price.
+    #[serde(default)]
+    pub cache_write: Option<f64>,
+}
+
+impl ModelPrice {
+    const fn new(input: f64, output: f64, cache_read: f64, cache_write: f64) -> Self {
+        Self {
+            input,
+            output,
+            cache_read: Some(cache_read),
+            cache_write: Some(cache_write),
+        }
+    }
+}
+
+/// Returns the built-in price table merged with the user's [Setting::ChatPriceTable].
+pub fn price_table(os: &Os) -> HashMap<String, ModelPrice> {
+    let mut prices = DEFAULT_PRICES
+        .iter()
+        .map(|(model_id, price)| (model_id.to_string(), *price))
+        .collect::<HashMap<_, _>>();
+
+    if let Some(value) = os.database.settings.get(Setting::ChatPriceTable) {
+        match serde_json::from_value::<HashMap<String, ModelPrice>>(value.clone()) {
+            Ok(overrides) => prices.extend(overrides),
+            Err(err) => warn!(?err, "ignoring invalid {}", Setting::ChatPriceTable.as_ref()),
+        }
+    }
+
+    prices
+}
+
+/// Prints a per-model table of token usage with estimated costs.
+pub fn print_usage_summary(
+    output: &mut impl Write,
+    usage: &SessionUsage,
+    prices: &HashMap<String, ModelPrice>,
+) -> Result<(), std::io::Error> {
+    if usage.is_empty() {
+        queue!(
+            output,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("\nNo token usage has been reported in this session yet.\n\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        return Ok(());
+    }
+
+    let model_width = usage
+        .by_model()
+        .map(|(model_id, _)| model_id.len())
+        .chain(["Model".len(), "Total".len()])
+        .max()
+        .unwrap_or_default();
+
+    queue!(
+        output,
+        style::SetAttribute(Attribute::Bold),
+        style::Print(format!(
+            "\n{:<model_width$}  {:>8}  {:>10}  {:>10}  {:>10}  {:>11}  {:>10}\n",
+            "Model", "Requests", "Input", "Output", "Cache read", "Cache write", "Est. cost"
+        )),
+        style::SetAttribute(Attribute::Reset),
+    )?;
+
+    let mut total_cost = 0.0;
+    let mut unpriced = Vec::new();
+    for (model_id, model_usage) in usage.by_model() {
+        let cost = prices.get(model_id).map(|price| model_usage.estimated_cost(price));
+        match cost {
+            Some(cost) => total_cost += cost,
+            None => unpriced.push(model_id),
+        }
+        print_usage_row(output, model_id, model_width, model_usage, cost)?;
+    }
+    queue!(output, style::SetAttribute(Attribute::Bold))?;
+    print_usage_row(output, "Total", model_width, &usage.total(), Some(total_cost))?;
+    queue!(output, style::SetAttribute(Attribute::Reset))?;
+
+    queue!(output, style::SetForegroundColor(Color::DarkGrey))?;
+    if !unpriced.is_empty() {
+        queue!(
+            output,
+            style::Print(format!(
+                "\nNo price configured for: {}. These are excluded from the total cost.",
+                unpriced.join(", ")
+            )),
+        )?;
+    }
+    queue!(
+        output,
+        style::Print(format!(
+            "\nCosts are estimates. Configure prices with: q settings {} '<json>'\n\n",
+            Setting::ChatPriceTable.as_ref()
+        )),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+
+    Ok(())
+}
+
+fn print_usage_row(
+    output: &mut impl Write,
+    label: &str,
+    label_width: usize,
+    usage: &ModelUsage,
+    cost: Option<f64>,
+) -> Result<(), std::io::Error> {
+    let cost = cost.map_or_else(|| "-".to_string(), |cost| format!("${cost:.4}"));
+    queue!(
+        output,
+        style::Print(format!(
+            "{:<label_width$}  {:>8}  {:>10}  {:>10}  {:>10}  {:>11}  {:>10}\n",
+            label,
+            usage.requests,
+            usage.input_tokens,
+            usage.output_tokens,
+            usage.cache_read_input_tokens,
+            usage.cache_write_input_tokens,
+            cost
+        )),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_usage(input: u64, output: u64) -> TokenUsage {
+        TokenUsage {
+            uncached_input_tokens: input,
+            output_tokens: output,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_session_usage_by_model() {
+        let mut usage = SessionUsage::default();
+        usage.record(Some("claude-sonnet-4"), &token_usage(1000, 100));
+        usage.record(Some("claude-sonnet-4"), &token_usage(2000, 200));
+        usage.record(None, &token_usage(500, 50));
+
+        let by_model = usage.by_model().collect::<Vec<_>>();
+        assert_eq!(by_model.len(), 2);
+        assert_eq!(by_model[0].0, "claude-sonnet-4");
+        assert_eq!(by_model[0].1.requests, 2);
+        assert_eq!(by_model[0].1.input_tokens, 3000);
+        assert_eq!(by_model[1].0, DEFAULT_MODEL_KEY);
+
+        let total = usage.total();
+        assert_eq!(total.requests, 3);
+        assert_eq!(total.input_tokens, 3500);
+        assert_eq!(total.output_tokens, 350);
+    }
+
+    #[test]
+    fn test_estimated_cost() {
+        let usage = ModelUsage {
+            requests: 1,
+            input_tokens: 1_000_000,
+            output_tokens: 100_000,
+            cache_read_input_tokens: 1_000_000,
+            cache_write_input_tokens: 0,
+        };
+        let price = ModelPrice::new(3.0, 15.0, 0.3, 3.75);
+        assert!((usage.estimated_cost(&price) - 4.8).abs() < f64::EPSILON * 10.0);
+
+        let price = ModelPrice {
+            input: 1.0,
+            output: 2.0,
+            cache_read: None,
+            cache_write: None,
+        };
+        assert!((usage.estimated_cost(&price) - 2.2).abs() < f64::EPSILON * 10.0);
+    }
+
+    #[tokio::test]
+    async fn test_price_table_overrides() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(
+                Setting::ChatPriceTable,
+                serde_json::json!({ "claude-sonnet-4": { "input": 1.0, "output": 2.0 }, "custom": { "input": 0.5, "output": 1.5 } }),
+            )
+            .await
+            .unwrap();
+
+        let prices = price_table(&os);
+        assert_eq!(prices["claude-sonnet-4"].input, 1.0);
+        assert_eq!(prices["claude-sonnet-4"].cache_read, None);
+        assert_eq!(prices["custom"].output, 1.5);
+        assert_eq!(prices["claude-3.7-sonnet"].output, 15.0);
+    }
+}
@@ -0,0 +1,353 @@
+use std::borrow::Cow;
+use std::io::Write;
+
+use crossterm::style::{
+    Color,
+    Stylize,
+};
+use crossterm::{
+    queue,
+    style,
+};
+use serde::Serialize;
+use thiserror::Error;
+
+use super::ChatError;
+use super::consts::{
+    AGENT_FORMAT_DOC_URL,
+    AGENT_FORMAT_TOOLS_DOC_URL,
+};
+use super::parser::RecvErrorKind;
+use crate::api_client::ApiClientError;
+use crate::telemetry::ReasonCode;
+
+const PRICING_DOC_URL: &str = "https://aws.amazon.com/q/developer/pricing/";
+const AUTH_DOC_URL: &str = "https://docs.aws.amazon.com/amazonq/latest/qdeveloper-ug/command-line-installing.html";
+
+/// Broad classification of errors surfaced to the user.
+///
+/// The category decides which remediation hint is shown, and is exported as-is in structured
+/// (JSON) output so that scripts can branch on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCategory {
+    /// Missing, expired, or otherwise invalid credentials.
+    Auth,
+    /// Connectivity problems or the service being unavailable.
+    Network,
+    /// Request quotas, subscription limits, and context window limits.
+    Quota,
+    /// A tool, MCP server, or prompt failed.
+    ToolFailure,
+    /// Invalid agent, settings, or command line configuration.
+    ConfigInvalid,
+    /// The user interrupted the operation.
+    Interrupted,
+    /// Anything else.
+    Internal,
+}
+
+impl ErrorCategory {
+    /// Short human readable title for the category.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Auth => "Authentication error",
+            Self::Network => "Network error",
+            Self::Quota => "Limit reached",
+            Self::ToolFailure => "Tool failure",
+            Self::ConfigInvalid => "Invalid configuration",
+            Self::Interrupted => "Interrupted",
+            Self::Internal => "Unexpected error",
+        }
+    }
+
+    /// The default remediation hint and documentation link for the category.
+    fn default_hint(&self) -> (Option<&'static str>, Option<&'static str>) {
+        match self {
+            Self::Auth => (
+                Some(
+                    "Run `q login` to sign in again. If you use IAM Identity Center, check that your session has not expired.",
+                ),
+                Some(AUTH_DOC_URL),
+            ),
+            Self::Network => (
+                Some(
+                    "Check your network connection and proxy settings, then try again. Run `q diagnostic` to inspect your setup.",
+                ),
+                None,
+            ),
+            Self::Quota => (
+                Some(
+                    "Wait a moment and try again. Use /usage to inspect your context window and /compact to free up space.",
+                ),
+                Some(PRICING_DOC_URL),
+            ),
+            Self::ToolFailure => (
+                Some("Run /mcp to check the status of your MCP servers and /tools to review tool permissions."),
+                Some(AGENT_FORMAT_TOOLS_DOC_URL),
+            ),
+            Self::ConfigInvalid => (
+                Some("Review your agent configuration with /agent and your settings with `q settings list`."),
+                Some(AGENT_FORMAT_DOC_URL),
+            ),
+            Self::Interrupted | Self::Internal => (None, None),
+        }
+    }
+}
+
+/// An error with an explicit category, remediation hint, and documentation link.
+///
+/// Prefer this over [ChatError::Custom] for failures the user can act on.
+#[derive(Debug, Clone, Error)]
+#[error("{message}")]
+pub struct CategorizedError {
+    pub category: ErrorCategory,
+    pub message: Cow<'static, str>,
+    pub remediation: Option<Cow<'static, str>>,
+    pub doc_url: Option<&'static str>,
+}
+
+impl CategorizedError {
+    pub fn new(category: ErrorCategory, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            category,
+            message: message.into(),
+            remediation: None,
+            doc_url: None,
+        }
+    }
+
+    pub fn auth(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCategory::Auth, message)
+    }
+
+    pub fn network(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCategory::Network, message)
+    }
+
+    pub fn quota(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCategory::Quota, message)
+    }
+
+    pub fn tool_failure(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCategory::ToolFailure, message)
+    }
+
+    pub fn config_invalid(message: impl Into<Cow<'static, str>>) -> Self {
+        Self::new(ErrorCategory::ConfigInvalid, message)
+    }
+
+    /// Overrides the category's default remediation hint.
+    pub fn with_remediation(mut self, remediation: impl Into<Cow<'static, str>>) -> Self {
+        self.remediation = Some(remediation.into());
+        self
+    }
+
+    /// Overrides the category's default documentation link.
+    pub fn with_doc_url(mut self, doc_url: &'static str) -> Self {
+        self.doc_url = Some(doc_url);
+        self
+    }
+}
+
+/// Remediation information for an error, rendered beneath the error message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorHint {
+    pub category: ErrorCategory,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<Cow<'static, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_url: Option<&'static str>,
+}
+
+impl ErrorHint {
+    fn from_category(category: ErrorCategory) -> Self {
+        let (remediation, doc_url) = category.default_hint();
+        Self {
+            category,
+            remediation: remediation.map(Cow::Borrowed),
+            doc_url,
+        }
+    }
+
+    fn with_remediation(mut self, remediation: &'static str) -> Self {
+        self.remediation = Some(Cow::Borrowed(remediation));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remediation.is_none() && self.doc_url.is_none()
+    }
+
+    /// Queues the hint as a bulleted list. Does not flush `output`.
+    pub fn queue(&self, output: &mut impl Write) -> Result<(), std::io::Error> {
+        if let Some(remediation) = &self.remediation {
+            queue!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("• {}\n", remediation)),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        if let Some(doc_url) = self.doc_url {
+            queue!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("• Learn more: "),
+                style::Print(doc_url.blue()),
+                style::Print("\n"),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializable representation of a [ChatError] used by the structured output modes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorReport {
+    pub message: String,
+    pub reason_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    #[serde(flatten)]
+    pub hint: ErrorHint,
+}
+
+impl ChatError {
+    /// Returns the [ErrorCategory] this error belongs to.
+    pub fn category(&self) -> ErrorCategory {
+        self.hint().category
+    }
+
+    /// Returns the remediation hint for this error.
+    pub fn hint(&self) -> ErrorHint {
+        match self {
+            ChatError::Client(e) => api_client_error_hint(e),
+            ChatError::Auth(_) => ErrorHint::from_category(ErrorCategory::Auth),
+            ChatError::SendMessage(e) => api_client_error_hint(&e.source),
+            ChatError::ResponseStream(e) => match &e.source {
+                RecvErrorKind::Client(e) => api_client_error_hint(e),
+                RecvErrorKind::StreamTimeout { .. } => ErrorHint::from_category(ErrorCategory::Network),
+                RecvErrorKind::Cancelled => ErrorHint::from_category(ErrorCategory::Interrupted),
+                RecvErrorKind::Json(_)
+                | RecvErrorKind::UnexpectedToolUseEos { .. }
+                | RecvErrorKind::ToolValidationError { .. } => ErrorHint::from_category(ErrorCategory::Internal),
+            },
+            ChatError::Std(_) | ChatError::Readline(_) | ChatError::Custom(_) => {
+                ErrorHint::from_category(ErrorCategory::Internal)
+            },
+            ChatError::Interrupted { .. } => ErrorHint::from_category(ErrorCategory::Interrupted),
+            ChatError::GetPromptError(_) => ErrorHint::from_category(ErrorCategory::ToolFailure)
+                .with_remediation("Run /prompts list to see the prompts that are currently available."),
+            ChatError::NonInteractiveToolApproval => ErrorHint::from_category(ErrorCategory::ConfigInvalid)
+                .with_remediation(
+                    "Relaunch with --trust-all-tools, or list the tools to allow with --trust-tools=<tool names>.",
+                ),
+            ChatError::CompactHistoryFailure => ErrorHint::from_category(ErrorCategory::Quota)
+                .with_remediation("Run /clear to reset the conversation, or /context to remove large context files."),
+            ChatError::AgentSwapError(_) => ErrorHint::from_category(ErrorCategory::ConfigInvalid),
+            ChatError::Categorized(e) => {
+                let default = ErrorHint::from_category(e.category);
+                ErrorHint {
+                    category: e.category,
+                    remediation: e.remediation.clone().or(default.remediation),
+                    doc_url: e.doc_url.or(default.doc_url),
+                }
+            },
+        }
+    }
+
+    /// Returns a serializable report of this error.
+    pub fn report(&self) -> ErrorReport {
+        ErrorReport {
+            message: self.to_string(),
+            reason_code: self.reason_code(),
+            status_code: self.status_code(),
+            hint: self.hint(),
+        }
+    }
+}
+
+fn api_client_error_hint(err: &ApiClientError) -> ErrorHint {
+    match err {
+        ApiClientError::AuthError(_) | ApiClientError::Credentials(_) => ErrorHint::from_category(ErrorCategory::Auth),
+        ApiClientError::QuotaBreach { .. } => ErrorHint::from_category(ErrorCategory::Quota),
+        ApiClientError::MonthlyLimitReached { .. } => ErrorHint::from_category(ErrorCategory::Quota)
+            .with_remediation("Use /subscribe to upgrade your subscription, or wait until your limits reset."),
+        ApiClientError::ContextWindowOverflow { .. } => ErrorHint::from_category(ErrorCategory::Quota)
+            .with_remediation("Run /compact to summarize the conversation, or /clear to start over."),
+        ApiClientError::ModelOverloadedError { .. } => ErrorHint::from_category(ErrorCategory::Network)
+            .with_remediation("Use /model to select a different model and try again."),
+        err if err.is_connectivity_error() => ErrorHint::from_category(ErrorCategory::Network),
+        err => match err.status_code() {
+            Some(401 | 403) => ErrorHint::from_category(ErrorCategory::Auth),
+            Some(429) => ErrorHint::from_category(ErrorCategory::Quota),
+            Some(500..=599) => ErrorHint::from_category(ErrorCategory::Network),
+            _ => ErrorHint::from_category(ErrorCategory::Internal),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_error_categories() {
+        let cases = [
+            (ChatError::NonInteractiveToolApproval, ErrorCategory::ConfigInvalid),
+            (ChatError::CompactHistoryFailure, ErrorCategory::Quota),
+            (ChatError::Custom("oops".into()), ErrorCategory::Internal),
+            (ChatError::Interrupted { tool_uses: None }, ErrorCategory::Interrupted),
+            (
+                ChatError::Client(Box::new(ApiClientError::QuotaBreach {
+                    message: "quota",
+                    status_code: Some(429),
+                })),
+                ErrorCategory::Quota,
+            ),
+            (
+                ChatError::Client(Box::new(ApiClientError::ModelOverloadedError {
+                    request_id: None,
+                    status_code: Some(500),
+                })),
+                ErrorCategory::Network,
+            ),
+            (
+                CategorizedError::tool_failure("server crashed").into(),
+                ErrorCategory::ToolFailure,
+            ),
+        ];
+
+        for (err, expected) in cases {
+            assert_eq!(err.category(), expected, "unexpected category for {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_categorized_error_overrides_hint() {
+        let err: ChatError = CategorizedError::config_invalid("bad agent")
+            .with_remediation("fix it")
+            .into();
+        let hint = err.hint();
+        assert_eq!(hint.remediation.as_deref(), Some("fix it"));
+        assert_eq!(hint.doc_url, Some(AGENT_FORMAT_DOC_URL));
+
+        let err: ChatError = CategorizedError::config_invalid("bad agent").into();
+        assert!(err.hint().remediation.is_some());
+    }
+
+    #[test]
+    fn test_error_report_serialization() {
+        let err: ChatError = CategorizedError::auth("token expired").into();
+        let json = serde_json::to_value(err.report()).unwrap();
+        assert_eq!(json["message"], "token expired");
+        assert_eq!(json["category"], "auth");
+        assert_eq!(json["reasonCode"], "AuthError");
+        assert_eq!(json["docUrl"], AUTH_DOC_URL);
+        assert!(json.get("statusCode").is_none());
+    }
+}
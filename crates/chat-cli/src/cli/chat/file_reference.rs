@@ -0,0 +1,154 @@
+//! Files cited in responses, like `src/main.rs:42`, and opening them in the user's editor.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+/// Placeholder for the path in `chat.editorCommand`.
+const FILE_PLACEHOLDER: &str = "{file}";
+/// Placeholder for the line in `chat.editorCommand`.
+const LINE_PLACEHOLDER: &str = "{line}";
+
+/// A file cited in a response, with the line it was cited at, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReference {
+    pub path: PathBuf,
+    pub line: Option<usize>,
+}
+
+impl FileReference {
+    /// Parses `path`, `path:line`, `path:line:column` or `path#Lline`, relative to `cwd`. Only
+    /// references to existing files are returned, so that words that merely look like paths
+    /// aren't mistaken for them.
+    pub fn parse(text: &str, cwd: &Path) -> Option<Self> {
+        let text = text.trim();
+        if text.contains("://") {
+            return None;
+        }
+        let (path, line) = match text.split_once("#L") {
+            Some((path, line)) => (path, line.parse().ok()),
+            None => {
+                let mut parts = text.splitn(3, ':');
+                let path = parts.next()?;
+                (path, parts.next().and_then(|line| line.parse().ok()))
+            },
+        };
+        if path.is_empty() {
+            return None;
+        }
+
+        let path = cwd.join(path);
+        path.is_file().then_some(Self {
+            path,
+            line: line.filter(|line| *line > 0),
+        })
+    }
+
+    /// `path:line` as it would be cited.
+    pub fn display(&self, cwd: &Path) -> String {
+        let path = self.path.strip_prefix(cwd).unwrap_or(&self.path).display();
+        match self.line {
+            Some(line) => format!("{path}:{line}"),
+            None => path.to_string(),
+        }
+    }
+
+    /// The command opening the file at its line, from the `chat.editorCommand` template, or
+    /// `$EDITOR +{line} {file}` without one.
+    pub fn editor_command(&self, template: Option<&str>) -> Option<Vec<String>> {
+        let line = self.line.unwrap_or(1).to_string();
+        let file = self.path.to_string_lossy();
+
+        let mut args = match template {
+            Some(template) => shlex::split(template)?,
+            None => {
+                let editor = std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string());
+                let mut args = shlex::split(&editor)?;
+                args.push(format!("+{LINE_PLACEHOLDER}"));
+                args
+            },
+        };
+        if args.is_empty() {
+            return None;
+        }
+        if !args.iter().any(|arg| arg.contains(FILE_PLACEHOLDER)) {
+            args.push(FILE_PLACEHOLDER.to_string());
+        }
+        Some(
+            args.into_iter()
+                .map(|arg| arg.replace(FILE_PLACEHOLDER, &file).replace(LINE_PLACEHOLDER, &line))
+                .collect(),
+        )
+    }
+
+    /// `text` as an OSC 8 hyperlink to the file, for terminals that support clicking them.
+    pub fn hyperlink(&self, text: &str) -> String {
+        let path = self.path.canonicalize().unwrap_or_else(|_| self.path.clone());
+        format!("\x1b]8;;file://{}\x1b\\{text}\x1b]8;;\x1b\\", path.display())
+    }
+}
+
+/// The existing files cited in `text`, in order of first mention.
+pub fn find_references(text: &str, cwd: &Path) -> Vec<FileReference> {
+    let mut references: Vec<FileReference> = Vec::new();
+    let words = text
+        .split(|c: char| c.is_whitespace() || c == '`')
+        .map(|word| word.trim_matches(|c: char| matches!(c, '(' | ')' | '[' | ']' | '"' | '\'' | ',' | '.' | ';')))
+        .filter(|word| word.contains('.') || word.contains('/'));
+    for word in words {
+        if let Some(reference) = FileReference::parse(word, cwd) {
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+    }
+    references
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_references() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+
+        let text = "The entry point is `src/main.rs:1`, see also (Cargo.toml). Ignore src/missing.rs:3, \
+                    https://example.com and src/main.rs#L1.";
+        let references = find_references(text, dir.path());
+        assert_eq!(references, vec![
+            FileReference {
+                path: dir.path().join("src/main.rs"),
+                line: Some(1),
+            },
+            FileReference {
+                path: dir.path().join("Cargo.toml"),
+                line: None,
+            },
+        ]);
+        assert_eq!(references[0].display(dir.path()), "src/main.rs:1");
+        assert!(FileReference::parse("src", dir.path()).is_none());
+    }
+
+    #[test]
+    fn test_editor_command() {
+        let reference = FileReference {
+            path: PathBuf::from("/repo/src/main.rs"),
+            line: Some(42),
+        };
+        assert_eq!(reference.editor_command(Some("code -g {file}:{line}")).unwrap(), vec![
+            "code",
+            "-g",
+            "/repo/src/main.rs:42"
+        ]);
+        assert_eq!(reference.editor_command(Some("subl")).unwrap(), vec![
+            "subl",
+            "/repo/src/main.rs"
+        ]);
+        assert!(reference.editor_command(Some("")).is_none());
+    }
+}
@@ -0,0 +1,129 @@
+//! The `@git` context provider: the state of the git repository of the current directory, given to
+//! the model as context.
+//!
+//! Mentioning `@git` in a prompt attaches it for that turn, and `/context add @git` (or `@git` in
+//! the resources of an agent) for every turn. It holds `git status`, the staged and unstaged diffs
+//! cut to [Setting::ContextGitDiffTokens], and the subjects of the last
+//! [Setting::ContextGitCommits] commits.
+
+use std::path::Path;
+use std::process::Stdio;
+
+use eyre::{
+    Result,
+    bail,
+};
+
+use super::context::truncate_head_tail;
+use super::token_counter::TokenCounter;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// The context rule, and the name of the context entry, of the git context.
+pub const GIT_CONTEXT: &str = "@git";
+
+const DEFAULT_DIFF_TOKENS: usize = 8_000;
+const DEFAULT_COMMITS: usize = 10;
+
+/// The git context of the current directory, or none if it is not in a git repository.
+pub async fn collect(os: &Os) -> Result<Option<String>> {
+    let dir = os.env.current_dir()?;
+    let Ok(status) = git(&dir, ["status", "--short", "--branch"]).await else {
+        return Ok(None);
+    };
+    let staged = git(&dir, ["diff", "--cached", "--no-color"]).await?;
+    let unstaged = git(&dir, ["diff", "--no-color"]).await?;
+    let commits = os
+        .database
+        .settings
+        .get_int_or(Setting::ContextGitCommits, DEFAULT_COMMITS);
+    // Fails in a repository without commits yet.
+    let log = match commits {
+        0 => String::new(),
+        n => git(&dir, ["log", &format!("-{n}"), "--format=%h %s"])
+            .await
+            .unwrap_or_default(),
+    };
+    let diff_tokens = os
+        .database
+        .settings
+        .get_int_or(Setting::ContextGitDiffTokens, DEFAULT_DIFF_TOKENS);
+
+    Ok(Some(format_context(&status, &staged, &unstaged, &log, diff_tokens)))
+}
+
+/// Lays out the git context. The diffs share `diff_tokens`, each getting at least half of it when
+/// both are larger.
+fn format_context(status: &str, staged: &str, unstaged: &str, log: &str, diff_tokens: usize) -> String {
+    let half = diff_tokens / 2;
+    let staged_budget = diff_tokens.saturating_sub(TokenCounter::count_tokens(unstaged).min(half));
+    let unstaged_budget = diff_tokens.saturating_sub(TokenCounter::count_tokens(staged).min(half));
+
+    let mut context = format!("## git status\n{}\n", status.trim_end());
+    for (title, diff, budget) in [
+        ("staged changes", staged, staged_budget),
+        ("unstaged changes", unstaged, unstaged_budget),
+    ] {
+        if diff.trim().is_empty() {
+            context.push_str(&format!("\n## {title}\nNone\n"));
+        } else {
+            context.push_str(&format!(
+                "\n## {title}\n{}\n",
+                truncate_head_tail(diff.trim_end(), budget)
+            ));
+        }
+    }
+    if !log.trim().is_empty() {
+        context.push_str(&format!("\n## recent commits\n{}\n", log.trim_end()));
+    }
+    context
+}
+
+/// Runs git in `dir`, returning its output.
+pub async fn git<const N: usize>(dir: &Path, args: [&str; N]) -> Result<String> {
+    let output = tokio::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    if !output.status.success() {
+        bail!("git failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_context() {
+        let context = format_context(
+            "## main...origin/main\n M src/lib.rs\n",
+            "",
+            "diff --git a/src/lib.rs b/src/lib.rs\n+fn added() {}\n",
+            "abc1234 Add the parser\ndef5678 Initial commit\n",
+            100,
+        );
+        assert_eq!(
+            context,
+            "## git status\n## main...origin/main\n M src/lib.rs\n\n\
+            ## staged changes\nNone\n\n\
+            ## unstaged changes\ndiff --git a/src/lib.rs b/src/lib.rs\n+fn added() {}\n\n\
+            ## recent commits\nabc1234 Add the parser\ndef5678 Initial commit\n"
+        );
+    }
+
+    #[test]
+    fn test_format_context_caps_diffs() {
+        let diff = (0..1000).map(|i| format!("+line {i}\n")).collect::<String>();
+        let context = format_context("", &diff, &diff, "", 1000);
+        assert!(TokenCounter::count_tokens(&context) <= 1100);
+        assert_eq!(context.matches("truncated").count(), 2);
+
+        // A small staged diff leaves the rest of the budget to the unstaged one.
+        let context = format_context("", "+one line\n", &diff, "", 1000);
+        assert!(TokenCounter::count_tokens(&context) > 800);
+    }
+}
@@ -0,0 +1,90 @@
+//! Sessions run with `q chat --no-interactive` from scripts: only the final answer goes to
+//! stdout, and the exit code tells why the run stopped.
+
+use std::process::ExitCode;
+
+use super::ChatError;
+use super::error::ErrorCategory;
+
+/// Why a session without user input stopped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HeadlessOutcome {
+    /// The model finished its answer.
+    #[default]
+    Success,
+    /// A tool needed approval, and wasn't trusted.
+    ToolDenied,
+    /// The model was still working after `--max-turns` requests.
+    MaxTurnsExceeded,
+    /// A request to the model failed.
+    ModelError,
+    /// The final answer didn't match the `--contract` schema.
+    ContractViolation,
+    /// Any other error.
+    Failure,
+}
+
+impl HeadlessOutcome {
+    /// 2 is left out, since clap exits with it on usage errors.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::Failure => 1,
+            Self::ToolDenied => 3,
+            Self::MaxTurnsExceeded => 4,
+            Self::ModelError => 5,
+            Self::ContractViolation => 6,
+        }
+    }
+}
+
+impl From<HeadlessOutcome> for ExitCode {
+    fn from(outcome: HeadlessOutcome) -> Self {
+        ExitCode::from(outcome.exit_code())
+    }
+}
+
+impl From<&ChatError> for HeadlessOutcome {
+    fn from(err: &ChatError) -> Self {
+        match err {
+            ChatError::Client(_) | ChatError::SendMessage(_) | ChatError::ResponseStream(_) => Self::ModelError,
+            ChatError::NonInteractiveToolApproval => Self::ToolDenied,
+            ChatError::Categorized(err) => match err.category {
+                ErrorCategory::Network | ErrorCategory::Quota => Self::ModelError,
+                _ => Self::Failure,
+            },
+            _ => Self::Failure,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        let outcomes = [
+            HeadlessOutcome::Success,
+            HeadlessOutcome::Failure,
+            HeadlessOutcome::ToolDenied,
+            HeadlessOutcome::MaxTurnsExceeded,
+            HeadlessOutcome::ModelError,
+            HeadlessOutcome::ContractViolation,
+        ];
+        let mut codes = outcomes.map(HeadlessOutcome::exit_code).to_vec();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), outcomes.len());
+        assert_eq!(HeadlessOutcome::Success.exit_code(), 0);
+
+        assert_eq!(
+            HeadlessOutcome::from(&ChatError::NonInteractiveToolApproval),
+            HeadlessOutcome::ToolDenied
+        );
+        assert_eq!(
+            HeadlessOutcome::from(&ChatError::Custom("oops".into())),
+            HeadlessOutcome::Failure
+        );
+    }
+}
@@ -0,0 +1,628 @@
+//! The history index: the latest conversation of every directory, along with a title and tags
+//! derived from it when it is saved. Saved or exported conversations can also be compared side
+//! by side.
+
+use std::collections::{
+    BTreeMap,
+    BTreeSet,
+    HashMap,
+};
+use std::io::Write;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use crossterm::style::{
+    self,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use unicode_width::UnicodeWidthChar;
+
+use super::conversation::ConversationState;
+use super::tools::fs_write::FsWrite;
+use crate::api_client::model::ToolResultStatus;
+use crate::os::Os;
+use crate::util::time::{
+    humanize,
+    parse_duration,
+};
+
+/// Maximum length of a title, in characters.
+const MAX_TITLE_LENGTH: usize = 72;
+
+/// Separator between the two sessions of a diff.
+const COLUMN_SEPARATOR: &str = " │ ";
+
+/// Tags for the languages of edited files, by extension.
+const EXTENSION_TAGS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("py", "python"),
+    ("ts", "typescript"),
+    ("tsx", "typescript"),
+    ("js", "javascript"),
+    ("jsx", "javascript"),
+    ("go", "go"),
+    ("java", "java"),
+    ("kt", "kotlin"),
+    ("rb", "ruby"),
+    ("cs", "csharp"),
+    ("c", "c"),
+    ("h", "c"),
+    ("cpp", "cpp"),
+    ("cc", "cpp"),
+    ("hpp", "cpp"),
+    ("swift", "swift"),
+    ("php", "php"),
+    ("sh", "shell"),
+    ("tf", "terraform"),
+];
+
+/// Tags for words found in prompts.
+const WORD_TAGS: &[(&str, &str)] = &[
+    // Languages
+    ("rust", "rust"),
+    ("cargo", "rust"),
+    ("python", "python"),
+    ("typescript", "typescript"),
+    ("javascript", "javascript"),
+    ("node", "javascript"),
+    ("golang", "go"),
+    ("java", "java"),
+    ("kotlin", "kotlin"),
+    ("ruby", "ruby"),
+    ("terraform", "terraform"),
+    ("bash", "shell"),
+    // AWS services
+    ("aws", "aws"),
+    ("s3", "s3"),
+    ("lambda", "lambda"),
+    ("dynamodb", "dynamodb"),
+    ("ec2", "ec2"),
+    ("ecs", "ecs"),
+    ("eks", "eks"),
+    ("iam", "iam"),
+    ("sqs", "sqs"),
+    ("sns", "sns"),
+    ("rds", "rds"),
+    ("cloudformation", "cloudformation"),
+    ("cdk", "cdk"),
+    ("cloudwatch", "cloudwatch"),
+    ("bedrock", "bedrock"),
+    // Kinds of task
+    ("fix", "bugfix"),
+    ("bug", "bugfix"),
+    ("crash", "bugfix"),
+    ("error", "bugfix"),
+    ("test", "testing"),
+    ("tests", "testing"),
+    ("refactor", "refactor"),
+    ("rename", "refactor"),
+    ("cleanup", "refactor"),
+    ("docs", "docs"),
+    ("document", "docs"),
+    ("readme", "docs"),
+    ("implement", "feature"),
+    ("add", "feature"),
+    ("explain", "question"),
+    ("why", "question"),
+    ("review", "review"),
+    ("deploy", "deploy"),
+];
+
+/// AWS services, which also get the `aws` tag.
+const AWS_SERVICE_TAGS: &[&str] = &[
+    "s3",
+    "lambda",
+    "dynamodb",
+    "ec2",
+    "ecs",
+    "eks",
+    "iam",
+    "sqs",
+    "sns",
+    "rds",
+    "cloudformation",
+    "cdk",
+    "cloudwatch",
+    "bedrock",
+];
+
+/// The title of a conversation: the first line of its first prompt, shortened if needed.
+pub fn title(first_prompt: &str) -> String {
+    let line = first_prompt
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    if line.chars().count() <= MAX_TITLE_LENGTH {
+        return line.to_string();
+    }
+    let mut title = line.chars().take(MAX_TITLE_LENGTH - 1).collect::<String>();
+    title.push('…');
+    title
+}
+
+/// The languages, AWS services, and kinds of task a conversation is about, sorted.
+pub fn tags<'a>(
+    prompts: impl IntoIterator<Item = &'a str>,
+    edited_files: impl IntoIterator<Item = &'a str>,
+) -> Vec<String> {
+    let mut tags = BTreeSet::new();
+
+    for prompt in prompts {
+        for word in prompt.split(|c: char| !c.is_alphanumeric()).map(str::to_lowercase) {
+            if let Some((_, tag)) = WORD_TAGS.iter().find(|(w, _)| *w == word) {
+                tags.insert(*tag);
+            }
+        }
+    }
+
+    for file in edited_files {
+        let extension = std::path::Path::new(file)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        if let Some((_, tag)) = EXTENSION_TAGS.iter().find(|(e, _)| Some(*e) == extension.as_deref()) {
+            tags.insert(*tag);
+        }
+    }
+
+    if tags.iter().any(|tag| AWS_SERVICE_TAGS.contains(tag)) {
+        tags.insert("aws");
+    }
+
+    tags.into_iter().map(str::to_string).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    pub cmd: HistorySubcommand,
+}
+
+#[deny(missing_docs)]
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum HistorySubcommand {
+    /// List the saved conversations, most recent first. Only the latest conversation of each
+    /// directory is saved
+    List {
+        /// Only list conversations with this tag. Can be repeated, in which case conversations
+        /// must have every tag
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Maximum number of conversations to list
+        #[arg(long, short, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Compare two sessions side by side: their prompts, the tools used and how they ended, and
+    /// the changes to the files written. Sessions are files exported with /save, saved directories
+    /// as listed by `q chat history list`, or conversation ids
+    Diff {
+        /// The first session
+        first: String,
+        /// The second session
+        second: String,
+    },
+    /// Delete saved conversations last active longer ago than --older-than, or all of them with
+    /// --all
+    Purge {
+        /// Only delete conversations last active longer ago than this, such as 30d or 12h
+        #[arg(long, value_parser = parse_duration)]
+        older_than: Option<Duration>,
+        /// Delete every saved conversation
+        #[arg(long, required_unless_present = "older_than", conflicts_with = "older_than")]
+        all: bool,
+    },
+}
+
+impl HistoryArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        match self.cmd {
+            HistorySubcommand::List { tag, limit } => list(os, tag, limit),
+            HistorySubcommand::Diff { first, second } => diff(os, &first, &second).await,
+            HistorySubcommand::Purge { older_than, .. } => purge(os, older_than),
+        }
+    }
+}
+
+fn list(os: &Os, tag: Vec<String>, limit: usize) -> Result<ExitCode> {
+    let mut stderr = std::io::stderr();
+
+    let mut conversations = os
+        .database
+        .get_all_conversations()?
+        .into_iter()
+        .filter(|(_, conversation)| !conversation.history().is_empty())
+        .filter(|(_, conversation)| {
+            tag.iter()
+                .all(|tag| conversation.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        })
+        .collect::<Vec<_>>();
+    conversations.sort_by_key(|(_, conversation)| std::cmp::Reverse(conversation.last_activity()));
+
+    if conversations.is_empty() {
+        execute!(stderr, style::Print("No saved conversations\n"))?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    for (path, conversation) in conversations.iter().take(limit) {
+        let when = conversation
+            .last_activity()
+            .map(|time| humanize(&time))
+            .unwrap_or_default();
+        queue!(
+            stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("{when:<16}  ")),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(format!("{path}\n  ")),
+            style::SetAttribute(style::Attribute::Bold),
+            style::Print(conversation.title.as_deref().unwrap_or("(untitled)")),
+            style::SetAttribute(style::Attribute::Reset),
+        )?;
+        if !conversation.tags.is_empty() {
+            queue!(
+                stderr,
+                style::SetForegroundColor(Color::Cyan),
+                style::Print(format!("  [{}]", conversation.tags.join(", "))),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        queue!(stderr, style::Print("\n"))?;
+    }
+    stderr.flush()?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn purge(os: &mut Os, older_than: Option<Duration>) -> Result<ExitCode> {
+    let deleted = match older_than {
+        Some(older_than) => {
+            let cutoff = chrono::TimeDelta::from_std(older_than)
+                .ok()
+                .and_then(|older_than| chrono::Utc::now().checked_sub_signed(older_than))
+                .ok_or_else(|| eyre::eyre!("--older-than is too far in the past"))?;
+            let cutoff = std::time::SystemTime::from(cutoff);
+            let paths = os
+                .database
+                .get_all_conversations()?
+                .into_iter()
+                .filter(|(path, conversation)| conversation.last_activity_or_modified(path) < cutoff)
+                .map(|(path, _)| path)
+                .collect::<Vec<_>>();
+            for path in &paths {
+                os.database.delete_conversation_by_path(path)?;
+            }
+            paths.len()
+        },
+        None => os.database.delete_all_conversations()?,
+    };
+
+    let mut stderr = std::io::stderr();
+    match deleted {
+        1 => execute!(stderr, style::Print("Deleted 1 saved conversation\n"))?,
+        n => execute!(stderr, style::Print(format!("Deleted {n} saved conversations\n")))?,
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// What a session did, as compared by `q chat history diff`.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct SessionSummary {
+    prompts: Vec<String>,
+    /// The tool uses, each with how it ended.
+    tool_uses: Vec<String>,
+    /// The files written successfully, with the lines each write removed and added.
+    files: BTreeMap<String, Vec<Vec<String>>>,
+}
+
+impl SessionSummary {
+    fn new(conversation: &ConversationState) -> Self {
+        let history = conversation.history();
+        let mut outcomes = HashMap::new();
+        for entry in history {
+            for result in entry.tool_use_results() {
+                let outcome = match (entry.cancelled_tool_uses(), &result.status) {
+                    (true, _) => "rejected",
+                    (false, ToolResultStatus::Success) => "ok",
+                    (false, ToolResultStatus::Error) => "failed",
+                };
+                outcomes.insert(result.tool_use_id.as_str(), outcome);
+            }
+        }
+
+        let mut summary = Self::default();
+        for entry in history {
+            if let Some(prompt) = entry.prompt() {
+                summary.prompts.push(title(prompt));
+            }
+            for tool_use in entry.tool_uses() {
+                let outcome = outcomes.get(tool_use.id.as_str()).copied().unwrap_or("not run");
+                let target = ["path", "command", "query"]
+                    .iter()
+                    .find_map(|key| tool_use.args.get(key).and_then(|value| value.as_str()));
+                summary.tool_uses.push(match target {
+                    Some(target) => format!("{} {} ({outcome})", tool_use.name, title(target)),
+                    None => format!("{} ({outcome})", tool_use.name),
+                });
+                if let (Some(path), "fs_write", "ok") = (target, tool_use.name.as_str(), outcome) {
+                    let lines = serde_json::from_value::<FsWrite>(tool_use.args.clone())
+                        .map(|write| changed_lines(&write))
+                        .unwrap_or_default();
+                    summary.files.entry(path.to_string()).or_default().push(lines);
+                }
+            }
+        }
+        summary
+    }
+
+    /// The files of both sessions, each with how many times either wrote it followed by the lines
+    /// the writes removed and added, padded so that the files of both sessions line up.
+    fn file_rows(&self, other: &Self) -> (Vec<String>, Vec<String>) {
+        let paths = self.files.keys().chain(other.files.keys()).collect::<BTreeSet<_>>();
+        let cells = |files: &BTreeMap<String, Vec<Vec<String>>>, path: &str| match files.get(path) {
+            Some(writes) => {
+                let header = match writes.len() {
+                    1 => path.to_string(),
+                    n => format!("{path} ({n} writes)"),
+                };
+                std::iter::once(header)
+                    .chain(writes.iter().flatten().cloned())
+                    .collect()
+            },
+            None => vec!["-".to_string()],
+        };
+
+        let (mut left, mut right) = (Vec::new(), Vec::new());
+        for path in paths {
+            let (mut left_cells, mut right_cells) = (cells(&self.files, path), cells(&other.files, path));
+            let rows = left_cells.len().max(right_cells.len());
+            left_cells.resize(rows, String::new());
+            right_cells.resize(rows, String::new());
+            left.append(&mut left_cells);
+            right.append(&mut right_cells);
+        }
+        (left, right)
+    }
+}
+
+/// The lines `write` removed and added, as `-` and `+` lines.
+fn changed_lines(write: &FsWrite) -> Vec<String> {
+    let added = |text: &str| text.lines().map(|line| format!("+{line}")).collect::<Vec<_>>();
+    match write {
+        FsWrite::Create { file_text, new_str, .. } => {
+            added(file_text.as_deref().or(new_str.as_deref()).unwrap_or_default())
+        },
+        FsWrite::StrReplace { old_str, new_str, .. } => similar::TextDiff::from_lines(old_str, new_str)
+            .iter_all_changes()
+            .filter_map(|change| {
+                let sign = match change.tag() {
+                    similar::ChangeTag::Delete => '-',
+                    similar::ChangeTag::Insert => '+',
+                    similar::ChangeTag::Equal => return None,
+                };
+                Some(format!("{sign}{}", change.value().trim_end_matches('\n')))
+            })
+            .collect(),
+        FsWrite::Insert { new_str, .. } | FsWrite::Append { new_str, .. } => added(new_str),
+    }
+}
+
+/// Finds a session: an exported file, a saved directory, or a conversation id or its prefix.
+/// Fails if the prefix matches more than one conversation.
+async fn load_session(os: &Os, id: &str) -> Result<ConversationState> {
+    let conversations = os.database.get_all_conversations()?;
+    let directory = os.env.current_dir()?.join(id).canonicalize().ok();
+    let directory = directory.as_deref().and_then(|directory| directory.to_str());
+    if let Some((_, conversation)) = conversations
+        .iter()
+        .find(|(path, _)| path == id || directory == Some(path.as_str()))
+    {
+        return Ok(conversation.clone());
+    }
+
+    let matches = conversations
+        .iter()
+        .filter(|(_, conversation)| conversation.conversation_id().starts_with(id))
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [] => (),
+        [(_, conversation)] => return Ok(conversation.clone()),
+        _ => bail!(
+            "{id} matches the conversations of {}, give more of the conversation id",
+            matches
+                .iter()
+                .map(|(path, _)| path.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+
+    if os.fs.exists(id) {
+        let contents = os.fs.read_to_string(id).await?;
+        return serde_json::from_str(&contents)
+            .map_err(|err| eyre::eyre!("{id} is not a conversation exported with /save: {err}"));
+    }
+    bail!("No saved conversation or exported file matches {id}")
+}
+
+async fn diff(os: &Os, first: &str, second: &str) -> Result<ExitCode> {
+    let left = SessionSummary::new(&load_session(os, first).await?);
+    let right = SessionSummary::new(&load_session(os, second).await?);
+
+    let width = crossterm::terminal::size()
+        .map(|(width, _)| width as usize)
+        .unwrap_or(120);
+    let column = width.saturating_sub(COLUMN_SEPARATOR.len()) / 2;
+    let (left_files, right_files) = left.file_rows(&right);
+    let sections = [
+        (
+            "Prompts",
+            (left.prompts.len(), right.prompts.len()),
+            &left.prompts,
+            &right.prompts,
+        ),
+        (
+            "Tool uses",
+            (left.tool_uses.len(), right.tool_uses.len()),
+            &left.tool_uses,
+            &right.tool_uses,
+        ),
+        (
+            "Files written",
+            (left.files.len(), right.files.len()),
+            &left_files,
+            &right_files,
+        ),
+    ];
+
+    let mut stdout = std::io::stdout();
+    queue!(
+        stdout,
+        style::SetAttribute(style::Attribute::Bold),
+        style::Print(side_by_side(first, second, column)),
+        style::SetAttribute(style::Attribute::Reset),
+        style::Print("\n"),
+    )?;
+    for (name, (left_count, right_count), left, right) in sections {
+        queue!(
+            stdout,
+            style::SetForegroundColor(Color::Cyan),
+            style::Print(format!("\n{name} ({left_count} / {right_count})\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        for i in 0..left.len().max(right.len()) {
+            let (left, right) = (
+                left.get(i).map(String::as_str).unwrap_or_default(),
+                right.get(i).map(String::as_str).unwrap_or_default(),
+            );
+            // Rows that are the same in both sessions are dimmed, so that differences stand out.
+            queue!(
+                stdout,
+                style::SetForegroundColor(if left == right { Color::DarkGrey } else { Color::Reset }),
+                style::Print(side_by_side(left, right, column)),
+                style::SetForegroundColor(Color::Reset),
+                style::Print("\n"),
+            )?;
+        }
+    }
+    stdout.flush()?;
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// `left` and `right` in columns of `width`, shortened if needed.
+fn side_by_side(left: &str, right: &str, width: usize) -> String {
+    let fit = |text: &str| {
+        let mut cell = String::new();
+        let mut used = 0;
+        for c in text.chars() {
+            let w = c.width().unwrap_or(0);
+            if used + w > width {
+                cell.pop();
+                cell.push('…');
+                break;
+            }
+            cell.push(c);
+            used += w;
+        }
+        (cell, used)
+    };
+    let (left, used) = fit(left);
+    let (right, _) = fit(right);
+    format!(
+        "{left}{}{COLUMN_SEPARATOR}{right}",
+        " ".repeat(width.saturating_sub(used))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title() {
+        assert_eq!(title("\n  Fix the login bug  \nmore details"), "Fix the login bug");
+        let long = "a".repeat(100);
+        assert_eq!(title(&long).chars().count(), MAX_TITLE_LENGTH);
+        assert!(title(&long).ends_with('…'));
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        assert_eq!(side_by_side("abc", "de", 5), "abc   │ de");
+        assert_eq!(side_by_side("abcdefgh", "", 5), "abcd… │ ");
+    }
+
+    #[test]
+    fn test_file_rows() {
+        let lines = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect::<Vec<_>>();
+        let left = SessionSummary {
+            files: [
+                ("a.rs".to_string(), vec![lines(&["+a"]), lines(&["-a", "+b"])]),
+                ("b.rs".to_string(), vec![lines(&["+x"])]),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let right = SessionSummary {
+            files: [
+                ("b.rs".to_string(), vec![lines(&["+x", "+y"])]),
+                ("c.rs".to_string(), vec![vec![]]),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            left.file_rows(&right),
+            (
+                lines(&["a.rs (2 writes)", "+a", "-a", "+b", "b.rs", "+x", "", "-"]),
+                lines(&["-", "", "", "", "b.rs", "+x", "+y", "c.rs"])
+            )
+        );
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let write = |args: serde_json::Value| changed_lines(&serde_json::from_value(args).unwrap());
+        assert_eq!(
+            write(serde_json::json!({ "command": "create", "path": "a", "file_text": "one\ntwo\n" })),
+            vec!["+one", "+two"]
+        );
+        assert_eq!(
+            write(serde_json::json!({
+                "command": "str_replace",
+                "path": "a",
+                "old_str": "one\ntwo\n",
+                "new_str": "one\nthree\n"
+            })),
+            vec!["-two", "+three"]
+        );
+        assert_eq!(
+            write(serde_json::json!({ "command": "append", "path": "a", "new_str": "four" })),
+            vec!["+four"]
+        );
+    }
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(
+            tags(["Why does the Lambda handler crash when reading from S3?"], [
+                "/repo/src/handler.rs",
+                "/repo/README.md"
+            ]),
+            vec!["aws", "bugfix", "lambda", "question", "rust", "s3"]
+        );
+        assert!(tags(["hello there"], []).is_empty());
+    }
+}
@@ -0,0 +1,359 @@
+//! Reviewing an `fs_write` hunk by hunk, like `git add -p`: only the approved hunks are written,
+//! and the model is told which ones were rejected and why.
+
+use std::io::Write;
+use std::ops::Range;
+
+use crossterm::style::{
+    self,
+    Color,
+};
+use crossterm::{
+    execute,
+    queue,
+};
+use similar::{
+    ChangeTag,
+    TextDiff,
+};
+
+use super::cli::editor::open_editor;
+use super::tools::Tool;
+use super::{
+    ChatError,
+    ChatSession,
+    ChatState,
+};
+use crate::os::Os;
+
+/// Lines of unchanged context around each hunk.
+const CONTEXT_LINES: usize = 3;
+
+/// A group of nearby changes, with the context around them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    /// The lines of the current file the hunk replaces.
+    pub old_range: Range<usize>,
+    /// The lines of the proposed file the hunk replaces them with.
+    pub new_range: Range<usize>,
+    /// The lines as displayed, each with whether it is kept, removed or added.
+    pub lines: Vec<(ChangeTag, String)>,
+}
+
+impl Hunk {
+    /// The proposed text of the hunk, context included.
+    fn new_text(&self) -> String {
+        self.lines
+            .iter()
+            .filter(|(tag, _)| *tag != ChangeTag::Delete)
+            .map(|(_, line)| line.as_str())
+            .collect()
+    }
+
+    fn header(&self) -> String {
+        format!(
+            "@@ -{},{} +{},{} @@",
+            self.old_range.start + 1,
+            self.old_range.len(),
+            self.new_range.start + 1,
+            self.new_range.len()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkDecision {
+    Accept,
+    Reject {
+        reason: Option<String>,
+    },
+    /// Accepted with the proposed text replaced by this.
+    Edit(String),
+}
+
+/// The hunks that turn `old` into `new`.
+pub fn hunks(old: &str, new: &str) -> Vec<Hunk> {
+    let diff = TextDiff::from_lines(old, new);
+    diff.grouped_ops(CONTEXT_LINES)
+        .into_iter()
+        .filter_map(|group| {
+            let (first, last) = (group.first()?, group.last()?);
+            Some(Hunk {
+                old_range: first.old_range().start..last.old_range().end,
+                new_range: first.new_range().start..last.new_range().end,
+                lines: group
+                    .iter()
+                    .flat_map(|op| diff.iter_changes(op))
+                    .map(|change| (change.tag(), change.value().to_string()))
+                    .collect(),
+            })
+        })
+        .filter(|hunk| hunk.lines.iter().any(|(tag, _)| *tag != ChangeTag::Equal))
+        .collect()
+}
+
+/// `old` with the changes of the hunks applied according to `decisions`.
+pub fn merge(old: &str, new: &str, hunks: &[Hunk], decisions: &[HunkDecision]) -> String {
+    let old_lines = old.split_inclusive('\n').collect::<Vec<_>>();
+    let new_lines = new.split_inclusive('\n').collect::<Vec<_>>();
+
+    let mut merged = String::with_capacity(new.len());
+    let mut cursor = 0;
+    for (hunk, decision) in hunks.iter().zip(decisions) {
+        merged.extend(&old_lines[cursor..hunk.old_range.start]);
+        match decision {
+            HunkDecision::Accept => merged.extend(&new_lines[hunk.new_range.clone()]),
+            HunkDecision::Reject { .. } => merged.extend(&old_lines[hunk.old_range.clone()]),
+            HunkDecision::Edit(text) => merged.push_str(text),
+        }
+        cursor = hunk.old_range.end;
+    }
+    merged.extend(&old_lines[cursor..]);
+    merged
+}
+
+/// What the user answers to deny a new file whose every hunk was rejected, since nothing is left
+/// to create. `None` unless there are hunks and all of them were rejected.
+fn denial(decisions: &[HunkDecision]) -> Option<String> {
+    if decisions.is_empty()
+        || !decisions
+            .iter()
+            .all(|decision| matches!(decision, HunkDecision::Reject { .. }))
+    {
+        return None;
+    }
+    let reasons = decisions
+        .iter()
+        .filter_map(|decision| match decision {
+            HunkDecision::Reject { reason } => reason.as_deref(),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    Some(match reasons.is_empty() {
+        true => "n".to_string(),
+        false => format!("I deny creating this file: {}", reasons.join("; ")),
+    })
+}
+
+/// What the model is told about the review, unless every hunk was accepted as is.
+pub fn review_note(hunks: &[Hunk], decisions: &[HunkDecision]) -> Option<String> {
+    if decisions.iter().all(|decision| *decision == HunkDecision::Accept) {
+        return None;
+    }
+
+    let mut note = "The user reviewed this change hunk by hunk, and only the approved hunks were written:".to_string();
+    for (index, (hunk, decision)) in hunks.iter().zip(decisions).enumerate() {
+        let lines = format!("lines {}-{}", hunk.old_range.start + 1, hunk.old_range.end.max(1));
+        let outcome = match decision {
+            HunkDecision::Accept => "applied".to_string(),
+            HunkDecision::Reject { reason: Some(reason) } => format!("rejected: {reason}"),
+            HunkDecision::Reject { reason: None } => "rejected, no reason given".to_string(),
+            HunkDecision::Edit(_) => "edited by the user, then applied".to_string(),
+        };
+        note.push_str(&format!("\n- Hunk {} ({lines}): {outcome}", index + 1));
+    }
+    note.push_str("\nRead the file again before making further changes to it.");
+    Some(note)
+}
+
+fn print_hunk(output: &mut impl Write, hunk: &Hunk, index: usize, total: usize) -> Result<(), ChatError> {
+    queue!(
+        output,
+        style::SetForegroundColor(Color::Cyan),
+        style::Print(format!("\n{} ({}/{total})\n", hunk.header(), index + 1)),
+    )?;
+    for (tag, line) in &hunk.lines {
+        let (sign, color) = match tag {
+            ChangeTag::Equal => (" ", Color::Reset),
+            ChangeTag::Delete => ("-", Color::Red),
+            ChangeTag::Insert => ("+", Color::Green),
+        };
+        queue!(
+            output,
+            style::SetForegroundColor(color),
+            style::Print(format!("{sign}{}", line.trim_end_matches(['\r', '\n']))),
+            style::Print("\n"),
+        )?;
+    }
+    execute!(output, style::SetForegroundColor(Color::Reset))?;
+    Ok(())
+}
+
+/// Reviews the `fs_write` awaiting approval at `index` hunk by hunk. The tool is replaced by one
+/// writing only the approved hunks, and the review is kept to tell the model about it.
+pub async fn review_pending_write(os: &Os, session: &mut ChatSession, index: usize) -> Result<ChatState, ChatError> {
+    let Tool::FsWrite(fs_write) = &session.tool_uses[index].tool else {
+        return Ok(ChatState::PromptUser {
+            skip_printing_tools: false,
+        });
+    };
+    let fs_write = fs_write.clone();
+
+    let (old, new) = match fs_write.proposed_content(os).await {
+        Ok(contents) => contents,
+        Err(err) => {
+            execute!(
+                session.stderr,
+                style::SetForegroundColor(Color::Red),
+                style::Print(format!("Unable to review this change: {err}\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+            return Ok(ChatState::PromptUser {
+                skip_printing_tools: false,
+            });
+        },
+    };
+    let hunks = hunks(&old, &new);
+
+    queue!(
+        session.stderr,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!(
+            "Reviewing {} hunk(s): y - apply, n - reject, e - edit, a - apply this and the rest, d - reject this and the rest\n",
+            hunks.len()
+        )),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+
+    let mut decisions = Vec::with_capacity(hunks.len());
+    let mut remaining = None;
+    for (i, hunk) in hunks.iter().enumerate() {
+        if let Some(decision) = &remaining {
+            decisions.push(decision_for_rest(decision));
+            continue;
+        }
+        print_hunk(&mut session.stderr, hunk, i, hunks.len())?;
+
+        let decision = loop {
+            let Some(answer) = session.read_user_input("Apply this hunk? [y/n/e/a/d]: ", true) else {
+                execute!(session.stderr, style::Print("\nStopped reviewing.\n"))?;
+                return Ok(ChatState::PromptUser {
+                    skip_printing_tools: false,
+                });
+            };
+            match answer.trim() {
+                "y" | "Y" => break HunkDecision::Accept,
+                "a" | "A" => {
+                    remaining = Some(HunkDecision::Accept);
+                    break HunkDecision::Accept;
+                },
+                "n" | "N" | "d" | "D" => {
+                    let reason = session
+                        .input_source
+                        .read_line(Some("Why? (optional, tell the model what to do instead): "))
+                        .ok()
+                        .flatten()
+                        .map(|reason| reason.trim().to_string())
+                        .filter(|reason| !reason.is_empty());
+                    let decision = HunkDecision::Reject { reason };
+                    if answer.trim().eq_ignore_ascii_case("d") {
+                        remaining = Some(HunkDecision::Reject { reason: None });
+                    }
+                    break decision;
+                },
+                "e" | "E" => {
+                    let proposed = hunk.new_text();
+                    let mut edited = open_editor(Some(proposed.clone()))?;
+                    if proposed.ends_with('\n') && !edited.is_empty() {
+                        edited.push('\n');
+                    }
+                    break HunkDecision::Edit(edited);
+                },
+                _ => continue,
+            }
+        };
+        decisions.push(decision);
+    }
+
+    if !os.fs.exists(fs_write.path(os)) {
+        if let Some(input) = denial(&decisions) {
+            execute!(
+                session.stderr,
+                style::Print("Every hunk was rejected, the file is not created.\n")
+            )?;
+            return Ok(ChatState::HandleInput { input });
+        }
+    }
+
+    let tool_use = &mut session.tool_uses[index];
+    if let Some(note) = review_note(&hunks, &decisions) {
+        tool_use.tool = Tool::FsWrite(fs_write.with_content(merge(&old, &new, &hunks, &decisions)));
+        session.hunk_reviews.insert(tool_use.id.clone(), note);
+    }
+    tool_use.accepted = true;
+
+    Ok(ChatState::ExecuteTools)
+}
+
+fn decision_for_rest(decision: &HunkDecision) -> HunkDecision {
+    match decision {
+        HunkDecision::Accept => HunkDecision::Accept,
+        _ => HunkDecision::Reject { reason: None },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n";
+    const NEW: &str = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nK\nl\n";
+
+    #[test]
+    fn test_hunks() {
+        let hunks = hunks(OLD, NEW);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].old_range, 0..5);
+        assert_eq!(hunks[0].header(), "@@ -1,5 +1,5 @@");
+        assert_eq!(hunks[1].new_text(), "h\ni\nj\nK\nl\n");
+        assert!(super::hunks(OLD, OLD).is_empty());
+    }
+
+    #[test]
+    fn test_merge_and_review_note() {
+        let hunks = hunks(OLD, NEW);
+
+        let accepted = [HunkDecision::Accept, HunkDecision::Accept];
+        assert_eq!(merge(OLD, NEW, &hunks, &accepted), NEW);
+        assert!(review_note(&hunks, &accepted).is_none());
+
+        let decisions = [HunkDecision::Accept, HunkDecision::Reject {
+            reason: Some("keep k lowercase".to_string()),
+        }];
+        assert_eq!(
+            merge(OLD, NEW, &hunks, &decisions),
+            "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl\n"
+        );
+        let note = review_note(&hunks, &decisions).unwrap();
+        assert!(note.contains("- Hunk 1 (lines 1-5): applied"));
+        assert!(note.contains("- Hunk 2 (lines 8-12): rejected: keep k lowercase"));
+
+        let edited = [
+            HunkDecision::Reject { reason: None },
+            HunkDecision::Edit("h\ni\nj\nKK\nl\n".to_string()),
+        ];
+        assert_eq!(
+            merge(OLD, NEW, &hunks, &edited),
+            "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nKK\nl\n"
+        );
+    }
+
+    #[test]
+    fn test_denial() {
+        assert_eq!(denial(&[]), None);
+        assert_eq!(
+            denial(&[HunkDecision::Accept, HunkDecision::Reject { reason: None }]),
+            None
+        );
+        assert_eq!(denial(&[HunkDecision::Reject { reason: None }]), Some("n".to_string()));
+        assert_eq!(
+            denial(&[
+                HunkDecision::Reject {
+                    reason: Some("not needed".to_string())
+                },
+                HunkDecision::Reject { reason: None },
+            ]),
+            Some("I deny creating this file: not needed".to_string())
+        );
+    }
+}
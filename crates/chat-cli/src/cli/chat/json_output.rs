@@ -0,0 +1,165 @@
+//! Machine readable output for `q chat --output-format json`: newline-delimited JSON events on
+//! stdout, while everything meant for humans goes to stderr.
+
+use std::io::{
+    self,
+    Stderr,
+    Stdout,
+    Write,
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use super::message::{
+    ToolUseResult,
+    ToolUseResultBlock,
+};
+use crate::api_client::model::{
+    TokenUsage,
+    ToolResultStatus,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ChatOutputFormat {
+    /// Rendered markdown for humans
+    #[default]
+    Text,
+    /// Newline-delimited JSON events, one per line. Implies --no-interactive
+    Json,
+}
+
+/// An event of a session, printed as a single line of JSON.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JsonEvent<'a> {
+    /// Text of the response, as it streams in.
+    TextDelta { text: &'a str },
+    /// Reasoning of the model, as it streams in before the response.
+    ReasoningDelta { text: &'a str },
+    /// A tool the model asked to use.
+    ToolRequest {
+        id: &'a str,
+        name: &'a str,
+        input: &'a serde_json::Value,
+    },
+    /// The result of a tool use, sent back to the model.
+    ToolResult {
+        id: &'a str,
+        status: &'static str,
+        output: String,
+    },
+    /// A complete response. `stop_reason` is `tool_use` when the model is waiting on tool results,
+    /// `end_turn` when the turn is over.
+    Message {
+        message_id: Option<&'a str>,
+        text: &'a str,
+        stop_reason: &'static str,
+    },
+    /// Tokens used by the request that produced the last message.
+    Usage {
+        model_id: Option<&'a str>,
+        input_tokens: u64,
+        output_tokens: u64,
+        cache_read_input_tokens: u64,
+        cache_write_input_tokens: u64,
+    },
+    /// The final answer, once it matches the `--contract` schema.
+    Output { value: &'a serde_json::Value },
+    /// The turn failed.
+    Error { error: serde_json::Value },
+}
+
+impl<'a> JsonEvent<'a> {
+    pub fn tool_result(result: &'a ToolUseResult) -> Self {
+        let output = result
+            .content
+            .iter()
+            .map(|block| match block {
+                ToolUseResultBlock::Text(text) => text.clone(),
+                ToolUseResultBlock::Json(json) => json.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self::ToolResult {
+            id: &result.tool_use_id,
+            status: match result.status {
+                ToolResultStatus::Success => "success",
+                ToolResultStatus::Error => "error",
+            },
+            output,
+        }
+    }
+
+    pub fn usage(model_id: Option<&'a str>, usage: &TokenUsage) -> Self {
+        Self::Usage {
+            model_id,
+            input_tokens: usage.uncached_input_tokens + usage.cache_read_input_tokens + usage.cache_write_input_tokens,
+            output_tokens: usage.output_tokens,
+            cache_read_input_tokens: usage.cache_read_input_tokens,
+            cache_write_input_tokens: usage.cache_write_input_tokens,
+        }
+    }
+}
+
+/// Writes [JsonEvent]s to stdout.
+pub struct JsonEvents {
+    stdout: Stdout,
+}
+
+impl JsonEvents {
+    pub fn new(stdout: Stdout) -> Self {
+        Self { stdout }
+    }
+
+    pub fn emit(&mut self, event: &JsonEvent<'_>) -> io::Result<()> {
+        serde_json::to_writer(&mut self.stdout, event)?;
+        self.stdout.write_all(b"\n")?;
+        self.stdout.flush()
+    }
+}
+
+/// Where the output of a session that isn't [JsonEvent]s goes: stdout, unless stdout is reserved
+/// for events.
+pub enum SessionOutput {
+    Stdout(Stdout),
+    Stderr(Stderr),
+}
+
+impl Write for SessionOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::Stderr(stderr) => stderr.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::Stderr(stderr) => stderr.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_event_serialization() {
+        let result = ToolUseResult {
+            tool_use_id: "tooluse_1".to_string(),
+            content: vec![ToolUseResultBlock::Text("ok".to_string())],
+            status: ToolResultStatus::Success,
+        };
+        assert_eq!(
+            serde_json::to_string(&JsonEvent::tool_result(&result)).unwrap(),
+            r#"{"type":"tool_result","id":"tooluse_1","status":"success","output":"ok"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&JsonEvent::TextDelta { text: "Hi" }).unwrap(),
+            r#"{"type":"text_delta","text":"Hi"}"#
+        );
+    }
+}
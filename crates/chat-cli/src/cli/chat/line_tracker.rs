@@ -19,6 +19,10 @@ pub struct FileLineTracker {
     pub lines_removed_by_agent: usize,
     /// Whether or not this is the first `fs_write` invocation
     pub is_first_write: bool,
+    /// Lines written by agent in the current operation, as 1-based inclusive ranges of the file
+    /// after it
+    #[serde(default)]
+    pub lines_written_by_agent: Vec<(usize, usize)>,
 }
 
 impl Default for FileLineTracker {
@@ -30,6 +34,7 @@ impl Default for FileLineTracker {
             lines_added_by_agent: 0,
             lines_removed_by_agent: 0,
             is_first_write: true,
+            lines_written_by_agent: Vec::new(),
         }
     }
 }
@@ -43,3 +48,19 @@ impl FileLineTracker {
         (self.lines_added_by_agent + self.lines_removed_by_agent) as isize
     }
 }
+
+/// The lines of `after` that differ from `before`, as 1-based inclusive ranges.
+pub fn changed_line_ranges(before: &str, after: &str) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for op in similar::TextDiff::from_lines(before, after).ops() {
+        let lines = op.new_range();
+        if op.tag() == similar::DiffTag::Equal || lines.is_empty() {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(last) if last.1 == lines.start => last.1 = lines.end,
+            _ => ranges.push((lines.start + 1, lines.end)),
+        }
+    }
+    ranges
+}
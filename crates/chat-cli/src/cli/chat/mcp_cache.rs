@@ -0,0 +1,168 @@
+//! A cache of the tools and prompts that MCP servers listed in earlier sessions.
+//!
+//! With it, the tools of a server are offered to the model before the server has finished
+//! starting, and, with `mcp.lazyStartup`, the server is only started once one of its tools or
+//! prompts is used. Entries are keyed by a hash of the server's configuration, so that changing
+//! how a server is launched invalidates what was cached for it.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use rmcp::model::{
+    Prompt,
+    Tool,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+use tracing::debug;
+
+use crate::cli::chat::tools::custom_tool::CustomToolConfig;
+use crate::os::{
+    Fs,
+    Os,
+};
+use crate::util::directories;
+
+/// What a server listed the last time it was started.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedLists {
+    pub config_hash: String,
+    pub tools: Vec<Tool>,
+    pub prompts: Vec<Prompt>,
+}
+
+#[derive(Debug, Clone)]
+pub struct McpCache {
+    fs: Fs,
+    dir: PathBuf,
+}
+
+impl McpCache {
+    pub fn new(os: &Os) -> Option<Self> {
+        match directories::mcp_cache_dir() {
+            Ok(dir) => Some(Self { fs: os.fs.clone(), dir }),
+            Err(err) => {
+                debug!(?err, "No directory for the MCP cache");
+                None
+            },
+        }
+    }
+
+    /// The lists cached for `server_name`, if they were cached with the same configuration.
+    pub async fn load(&self, server_name: &str, config: &CustomToolConfig) -> Option<CachedLists> {
+        let content = self.fs.read_to_string(self.path(server_name)).await.ok()?;
+        let cached = serde_json::from_str::<CachedLists>(&content).ok()?;
+        (cached.config_hash == config_hash(config)).then_some(cached)
+    }
+
+    pub async fn store_tools(&self, server_name: &str, config: &CustomToolConfig, tools: Vec<Tool>) {
+        let mut cached = self.load(server_name, config).await.unwrap_or_default();
+        cached.tools = tools;
+        self.store(server_name, config, cached).await;
+    }
+
+    pub async fn store_prompts(&self, server_name: &str, config: &CustomToolConfig, prompts: Vec<Prompt>) {
+        let mut cached = self.load(server_name, config).await.unwrap_or_default();
+        cached.prompts = prompts;
+        self.store(server_name, config, cached).await;
+    }
+
+    async fn store(&self, server_name: &str, config: &CustomToolConfig, mut cached: CachedLists) {
+        cached.config_hash = config_hash(config);
+        let result = async {
+            self.fs.create_dir_all(&self.dir).await?;
+            self.fs
+                .write(self.path(server_name), serde_json::to_vec(&cached)?)
+                .await?;
+            Ok::<_, eyre::Report>(())
+        }
+        .await;
+        if let Err(err) = result {
+            debug!(?err, %server_name, "Failed to cache the lists of the MCP server");
+        }
+    }
+
+    fn path(&self, server_name: &str) -> PathBuf {
+        self.dir.join(format!("{server_name}.json"))
+    }
+}
+
+/// A hash of what determines the tools a server offers: how it is launched or reached. Maps are
+/// hashed in key order, so that the hash doesn't change between sessions.
+pub fn config_hash(config: &CustomToolConfig) -> String {
+    let identity = serde_json::json!({
+        "type": config.r#type,
+        "url": config.url,
+        "headers": config.headers.iter().collect::<BTreeMap<_, _>>(),
+        "command": config.command,
+        "args": config.args,
+        "cwd": config.cwd,
+        "env": config.env.as_ref().map(|env| env.iter().collect::<BTreeMap<_, _>>()),
+    });
+    hex::encode(Sha256::digest(identity.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn config(env: &[(&str, &str)]) -> CustomToolConfig {
+        serde_json::from_value(serde_json::json!({
+            "command": "uvx",
+            "args": ["mcp-server-git"],
+            "env": env.iter().cloned().collect::<HashMap<_, _>>(),
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_config_hash() {
+        let hash = config_hash(&config(&[("A", "1"), ("B", "2"), ("C", "3")]));
+        assert_eq!(hash, config_hash(&config(&[("C", "3"), ("A", "1"), ("B", "2")])));
+        assert_ne!(hash, config_hash(&config(&[("A", "1")])));
+
+        // Settings that don't change what the server offers don't invalidate the cache.
+        let mut other = config(&[("A", "1"), ("B", "2"), ("C", "3")]);
+        other.timeout = 1;
+        other.disabled = true;
+        assert_eq!(hash, config_hash(&other));
+    }
+
+    #[tokio::test]
+    async fn test_store_and_load() {
+        let os = Os::new().await.unwrap();
+        let cache = McpCache {
+            fs: os.fs.clone(),
+            dir: PathBuf::from("/mcp-cache"),
+        };
+        let config = config(&[]);
+        assert_eq!(cache.load("git", &config).await, None);
+
+        let tool = serde_json::from_value::<Tool>(serde_json::json!({
+            "name": "git_status",
+            "description": "Shows the working tree status",
+            "inputSchema": { "type": "object" }
+        }))
+        .unwrap();
+        cache.store_tools("git", &config, vec![tool.clone()]).await;
+        let prompt = Prompt::new("commit", Some("Write a commit message"), None);
+        cache.store_prompts("git", &config, vec![prompt.clone()]).await;
+
+        let cached = cache.load("git", &config).await.unwrap();
+        assert_eq!(cached.tools, vec![tool]);
+        assert_eq!(cached.prompts, vec![prompt]);
+
+        let mut changed = config.clone();
+        changed.args.push("--repository".to_string());
+        assert_eq!(cache.load("git", &changed).await, None);
+    }
+}
@@ -0,0 +1,342 @@
+//! Mentions with `@`, resolved the same way wherever they appear in a prompt:
+//!
+//! - `@path/to/file` attaches a file as context for the turn it was sent in.
+//! - `@scheme://uri` attaches a resource of an MCP server, like a file.
+//! - `@git` attaches the status, diffs and recent commits of the git repository, see
+//!   [super::git_context].
+//! - `@prompt`, `@server:prompt` or `@server/prompt` at the start of the input runs a prompt.
+
+use std::borrow::Cow;
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+    Stylize,
+};
+use rmcp::model::ResourceContents;
+
+use super::context::{
+    max_file_tokens,
+    truncate_head_tail,
+};
+use super::conversation::{
+    CONTEXT_ENTRY_END_HEADER,
+    CONTEXT_ENTRY_START_HEADER,
+};
+use super::git_context::{
+    self,
+    GIT_CONTEXT,
+};
+use super::tool_manager::ToolManager;
+use super::tools::sanitize_path_tool_arg;
+use crate::os::Os;
+
+/// Maximum number of tokens of a mentioned file attached, unless a lower limit is configured
+/// for context files.
+const MAX_MENTIONED_FILE_TOKENS: usize = 25_000;
+
+/// Characters not considered part of a mention when they end it, so that `@main.rs,` and
+/// `(see @main.rs)` mention `main.rs`.
+const TRAILING_PUNCTUATION: &[char] = &['.', ',', ';', ':', '!', '?', ')', ']', '}', '"', '\''];
+
+/// The mentions of `input` as the byte offset of their `@` and the path mentioned.
+pub fn mentions(input: &str) -> Vec<(usize, &str)> {
+    let mut mentions = Vec::new();
+    let mut previous = None;
+    for (offset, c) in input.char_indices() {
+        if c == '@' && previous.is_none_or(char::is_whitespace) {
+            let rest = &input[offset + 1..];
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let path = rest[..end].trim_end_matches(TRAILING_PUNCTUATION);
+            if !path.is_empty() {
+                mentions.push((offset, path));
+            }
+        }
+        previous = Some(c);
+    }
+    mentions
+}
+
+/// What a mention refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mention<'a> {
+    /// A file or directory, relative to the current directory.
+    Path(&'a str),
+    /// A resource of an MCP server, by URI.
+    Resource(&'a str),
+    /// The state of the git repository of the current directory.
+    Git,
+    /// A prompt, optionally qualified by the server offering it.
+    Prompt { server: Option<&'a str>, name: &'a str },
+}
+
+impl<'a> Mention<'a> {
+    /// Existing paths take precedence over prompts of the same name.
+    pub fn classify(os: &Os, text: &'a str) -> Self {
+        if text.contains("://") {
+            return Self::Resource(text);
+        }
+        if os.fs.exists(resolve(os, text)) {
+            return Self::Path(text);
+        }
+        if GIT_CONTEXT.strip_prefix('@') == Some(text) {
+            return Self::Git;
+        }
+        match text.split_once([':', '/']) {
+            Some((server, name)) => Self::Prompt {
+                server: Some(server),
+                name,
+            },
+            None => Self::Prompt {
+                server: None,
+                name: text,
+            },
+        }
+    }
+
+    /// Whether this looks like it was meant as a path, for reporting paths that don't exist.
+    fn is_path_like(text: &str) -> bool {
+        text.starts_with(['.', '~', '/']) || Path::new(text).extension().is_some()
+    }
+}
+
+fn resolve(os: &Os, path: &str) -> PathBuf {
+    sanitize_path_tool_arg(os, path)
+}
+
+/// The prompt `input` runs, as `server/prompt` or `prompt`, when it starts with a mention of a
+/// prompt.
+pub fn prompt_name(os: &Os, input: &str) -> Option<String> {
+    match mentions(input).first() {
+        Some((0, text)) => match Mention::classify(os, text) {
+            Mention::Prompt {
+                server: Some(server),
+                name,
+            } => Some(format!("{server}/{name}")),
+            Mention::Prompt { server: None, name } => Some(name.to_string()),
+            Mention::Path(_) | Mention::Resource(_) | Mention::Git => None,
+        },
+        _ => None,
+    }
+}
+
+/// The context entry holding the files and resources mentioned in `prompt`, if any. Mentions
+/// that can't be resolved are reported to `output` and left out.
+pub async fn mentioned_context(
+    os: &Os,
+    tool_manager: &mut ToolManager,
+    prompt: &str,
+    output: &mut impl Write,
+) -> Option<String> {
+    let max_tokens = max_file_tokens(os).map_or(MAX_MENTIONED_FILE_TOKENS, |max| max.min(MAX_MENTIONED_FILE_TOKENS));
+
+    let mut attached = Vec::<(&str, String)>::new();
+    for (_, text) in mentions(prompt) {
+        if attached.iter().any(|(t, _)| *t == text) {
+            continue;
+        }
+        let content = match Mention::classify(os, text) {
+            Mention::Path(path) => {
+                let resolved = resolve(os, path);
+                if resolved.is_dir() {
+                    continue;
+                }
+                os.fs.read_to_string(&resolved).await.map_err(|err| err.to_string())
+            },
+            Mention::Resource(uri) => tool_manager
+                .read_resource(uri)
+                .await
+                .map(|result| {
+                    result
+                        .contents
+                        .into_iter()
+                        .map(|contents| match contents {
+                            ResourceContents::TextResourceContents { text, .. } => text,
+                            ResourceContents::BlobResourceContents { mime_type, blob, .. } => {
+                                let mime_type = mime_type.as_deref().unwrap_or("unknown");
+                                format!("Blob resource, mime_type: {mime_type}, blob: {blob}")
+                            },
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .map_err(|err| err.to_string()),
+            Mention::Git => match git_context::collect(os).await {
+                // Capped by its own setting, so not truncated again.
+                Ok(Some(context)) => {
+                    attached.push((text, context));
+                    continue;
+                },
+                Ok(None) => Err("The current directory is not in a git repository".to_string()),
+                Err(err) => Err(err.to_string()),
+            },
+            Mention::Prompt { .. } if Mention::is_path_like(text) => Err("No such file or directory".to_string()),
+            // Prompts only run from the start of the input, and anything else is likely not a
+            // mention at all, e.g. a username.
+            Mention::Prompt { .. } => continue,
+        };
+
+        match content {
+            Ok(content) => attached.push((text, truncate_head_tail(&content, max_tokens))),
+            Err(err) => {
+                let _ = queue!(
+                    output,
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(format!("Could not attach @{text}: ")),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print(format!("{err}\n")),
+                );
+            },
+        }
+    }
+    let _ = output.flush();
+
+    if attached.is_empty() {
+        return None;
+    }
+
+    let mut context = String::new();
+    context.push_str(CONTEXT_ENTRY_START_HEADER);
+    context.push_str("These files and resources were mentioned in my message with @, use them to answer it:\n\n");
+    for (text, content) in attached {
+        context.push_str(&format!("[{text}]\n{content}\n"));
+    }
+    context.push_str(CONTEXT_ENTRY_END_HEADER);
+    Some(context)
+}
+
+/// Completions for `word`, a mention being typed, from the entries of the directory it is in.
+/// Directories complete with a trailing `/` so the next level can be completed right away.
+pub fn complete(word: &str) -> Vec<String> {
+    let Some(partial) = word.strip_prefix('@') else {
+        return Vec::new();
+    };
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(index) => (&partial[..=index], &partial[index + 1..]),
+        None => ("", partial),
+    };
+
+    let search_dir = match dir.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().map(|home| home.join(rest)),
+        None => Some(PathBuf::from(if dir.is_empty() { "." } else { dir })),
+    };
+    let Some(Ok(entries)) = search_dir.map(std::fs::read_dir) else {
+        return Vec::new();
+    };
+
+    let mut completions = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let suffix = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("@{dir}{name}{suffix}"))
+        })
+        .collect::<Vec<_>>();
+    completions.sort();
+    completions
+}
+
+/// Highlights the mentions of `line` that point at existing paths or resources.
+pub fn highlight(line: &str) -> Cow<'_, str> {
+    let mentions = mentions(line)
+        .into_iter()
+        .filter(|(_, text)| {
+            text.contains("://") || GIT_CONTEXT.strip_prefix('@') == Some(*text) || expand_home(text).exists()
+        })
+        .collect::<Vec<_>>();
+    if mentions.is_empty() {
+        return Cow::Borrowed(line);
+    }
+
+    let mut highlighted = String::with_capacity(line.len());
+    let mut last = 0;
+    for (offset, path) in mentions {
+        let end = offset + 1 + path.len();
+        highlighted.push_str(&line[last..offset]);
+        highlighted.push_str(&line[offset..end].cyan().to_string());
+        last = end;
+    }
+    highlighted.push_str(&line[last..]);
+    Cow::Owned(highlighted)
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/").zip(dirs::home_dir()) {
+        Some((rest, home)) => home.join(rest),
+        None => Path::new(path).to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentions() {
+        assert_eq!(mentions("explain @src/main.rs, and (see @README.md)"), vec![
+            (8, "src/main.rs"),
+            (31, "README.md")
+        ]);
+        assert_eq!(mentions("@prompt arg"), vec![(0, "prompt")]);
+        assert!(mentions("mail me at someone@example.com or @").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_classify() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("notes.md", "remember the milk").await.unwrap();
+
+        assert_eq!(Mention::classify(&os, "notes.md"), Mention::Path("notes.md"));
+        assert_eq!(
+            Mention::classify(&os, "file:///logs/app.log"),
+            Mention::Resource("file:///logs/app.log")
+        );
+        assert_eq!(Mention::classify(&os, "github:review"), Mention::Prompt {
+            server: Some("github"),
+            name: "review"
+        });
+
+        assert_eq!(prompt_name(&os, "@github:review 42"), Some("github/review".to_string()));
+        assert_eq!(prompt_name(&os, "@review"), Some("review".to_string()));
+        assert_eq!(prompt_name(&os, "@notes.md summarize this"), None);
+        assert_eq!(prompt_name(&os, "summarize @review"), None);
+
+        assert_eq!(Mention::classify(&os, "git"), Mention::Git);
+        assert_eq!(prompt_name(&os, "@git what changed?"), None);
+    }
+
+    #[tokio::test]
+    async fn test_mentioned_context() {
+        let os = Os::new().await.unwrap();
+        os.fs.write("notes.md", "remember the milk").await.unwrap();
+        os.fs.create_dir_all("src").await.unwrap();
+        let mut tool_manager = ToolManager::default();
+
+        let mut output = Vec::new();
+        let context = mentioned_context(
+            &os,
+            &mut tool_manager,
+            "summarize @notes.md, not @src, @someone or @missing.md",
+            &mut output,
+        )
+        .await
+        .unwrap();
+        assert!(context.contains("[notes.md]\nremember the milk\n"));
+        assert!(!context.contains("[src]"));
+        assert!(!context.contains("[someone]"));
+        let output = String::from_utf8(strip_ansi_escapes::strip(output)).unwrap();
+        assert_eq!(output, "Could not attach @missing.md: No such file or directory\n");
+
+        let context = mentioned_context(&os, &mut tool_manager, "no mentions here", &mut Vec::new()).await;
+        assert!(context.is_none());
+    }
+}
@@ -1,17 +1,41 @@
+pub mod audit;
 pub mod cli;
 mod consts;
 pub mod context;
+mod contract;
 mod conversation;
+mod cost;
+pub mod error;
+mod file_reference;
+pub mod git_context;
+mod headless;
+pub mod history;
+mod hunk_review;
 mod input_source;
+pub mod json_output;
 mod message;
-mod parse;
-use std::path::MAIN_SEPARATOR;
+mod model_health;
+pub mod monthly_usage;
+mod notification;
+pub mod parse;
+use std::path::{
+    MAIN_SEPARATOR,
+    PathBuf,
+};
 pub mod checkpoint;
 mod line_tracker;
+pub mod mcp_cache;
+mod mention;
 mod parser;
+mod plan;
 mod prompt;
 mod prompt_parser;
+mod rate_limit;
+mod reasoning;
+pub mod resource_monitor;
 pub mod server_messenger;
+mod stream_retry;
+mod supervisor;
 use crate::cli::chat::checkpoint::CHECKPOINT_MESSAGE_MAX_LENGTH;
 use crate::constants::ui_text::{
     LIMIT_REACHED_TEXT,
@@ -45,19 +69,33 @@ use std::time::{
 };
 
 use amzn_codewhisperer_client::types::SubscriptionStatus;
+use audit::{
+    AuditEntry,
+    Decision,
+};
 use clap::{
     Args,
     CommandFactory,
     Parser,
     ValueEnum,
 };
-use cli::compact::CompactStrategy;
+use cli::compact::{
+    COMPACTION_FEEDBACK_TURNS,
+    CompactStrategy,
+    CompactionPromptVariant,
+    RecentCompaction,
+};
 use cli::hooks::ToolContext;
 use cli::model::{
+    ModelInfo,
+    compact_to_fit,
     find_model,
     get_available_models,
+    invalidate_model_catalog,
+    pick_model,
     select_model,
 };
+use contract::Contract;
 pub use conversation::ConversationState;
 use conversation::TokenWarningLevel;
 use crossterm::style::{
@@ -72,37 +110,82 @@ use crossterm::{
     style,
     terminal,
 };
+use error::{
+    CategorizedError,
+    ErrorCategory,
+};
 use eyre::{
     Report,
     Result,
     bail,
     eyre,
 };
+use headless::HeadlessOutcome;
+use history::HistoryArgs;
 use input_source::InputSource;
+use json_output::{
+    ChatOutputFormat,
+    JsonEvent,
+    JsonEvents,
+    SessionOutput,
+};
 use message::{
     AssistantMessage,
     AssistantToolUse,
     ToolUseResult,
     ToolUseResultBlock,
 };
+use model_health::{
+    ModelHealth,
+    capacity_error,
+};
+use notification::Notifier;
 use parse::{
+    CodeHighlighting,
     ParseState,
     interpret_markdown,
 };
 use parser::{
     RecvErrorKind,
     RequestMetadata,
+    SendMessageError,
     SendMessageStream,
 };
+use plan::Plan;
+use prompt_parser::{
+    PromptSegment,
+    count_uncommitted_edits,
+};
+use rate_limit::{
+    RateLimits,
+    ToolRateLimiter,
+};
+use reasoning::{
+    ReasoningDisplay,
+    ReasoningTrace,
+};
 use regex::Regex;
+use resource_monitor::{
+    ResourceMonitor,
+    ResourceThresholds,
+};
 use rmcp::model::PromptMessage;
 use spinners::{
     Spinner,
     Spinners,
 };
+use stream_retry::{
+    MAX_STREAM_RETRIES,
+    Stitched,
+    Stitcher,
+};
+use supervisor::TaskSupervisor;
 use thiserror::Error;
 use time::OffsetDateTime;
-use token_counter::TokenCounter;
+use token_counter::{
+    TokenCount,
+    TokenCounter,
+};
 use tokio::signal::ctrl_c;
 use tokio::sync::{
     Mutex,
@@ -116,12 +199,16 @@ use tool_manager::{
 };
 use tools::delegate::status_all_agents;
 use tools::gh_issue::GhIssueContext;
+use tools::infra::InfraChange;
+use tools::issue_tracker::IssueTracker;
+use tools::todo::TodoList;
 use tools::{
     NATIVE_TOOLS,
     OutputKind,
     QueuedTool,
     Tool,
     ToolSpec,
+    update_plan,
 };
 use tracing::{
     debug,
@@ -130,31 +217,42 @@ use tracing::{
     trace,
     warn,
 };
+use util::animate_output;
 use util::images::RichImageBlock;
 use util::ui::draw_box;
-use util::{
-    animate_output,
-    play_notification_bell,
-};
 use winnow::Partial;
 use winnow::stream::Offset;
 
 use super::agent::{
     Agent,
     DEFAULT_AGENT_NAME,
+    ManagedPolicy,
     PermissionEvalResult,
+    PolicyEvalResult,
 };
 use crate::api_client::model::ToolResultStatus;
 use crate::api_client::{
     self,
     ApiClientError,
+    Endpoint,
 };
 use crate::auth::AuthError;
 use crate::auth::builder_id::is_idc_user;
 use crate::cli::TodoListState;
-use crate::cli::agent::Agents;
+use crate::cli::agent::bridge::attach_selections;
+use crate::cli::agent::ipc::IncomingPrompt;
+use crate::cli::agent::protocol::SessionEvent;
+use crate::cli::agent::registry::{
+    self,
+    AgentRegistration,
+};
+use crate::cli::agent::{
+    Agents,
+    WorkspaceConfig,
+};
 use crate::cli::chat::checkpoint::{
     CheckpointManager,
+    gc_shadow_repos,
     truncate_message,
 };
 use crate::cli::chat::cli::SlashCommand;
@@ -180,19 +278,23 @@ use crate::telemetry::core::{
     AgentConfigInitArgs,
     ChatAddedMessageParams,
     ChatConversationType,
+    CompactHistoryArgs,
     MessageMetaTag,
     RecordUserTurnCompletionArgs,
     ToolUseEventBuilder,
 };
+use crate::telemetry::otel::OtelSpan;
 use crate::telemetry::{
     ReasonCode,
     TelemetryResult,
     get_error_reason,
 };
 use crate::util::directories::get_shadow_repo_dir;
+use crate::util::time::format_duration;
 use crate::util::{
     MCP_SERVER_TOOL_DELIMITER,
     directories,
+    shutdown,
     ui,
 };
 
@@ -207,6 +309,7 @@ pub enum WrapMode {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Args)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct ChatArgs {
     /// Resumes the previous conversation from this directory.
     #[arg(short, long)]
@@ -232,12 +335,40 @@ pub struct ChatArgs {
     /// Control line wrapping behavior (default: auto-detect)
     #[arg(short = 'w', long, value_enum)]
     pub wrap: Option<WrapMode>,
+    /// Format of the output. With json, stdout has one JSON event per line for the response text,
+    /// tool requests and results, and token usage, while everything else goes to stderr
+    #[arg(long, value_enum, default_value_t)]
+    pub output_format: ChatOutputFormat,
+    /// With --no-interactive, the maximum number of requests to the model before giving up. Exits
+    /// with 3 when a tool needs approval, 4 when this is exceeded and 5 when the model fails
+    #[arg(long, value_name = "N")]
+    pub max_turns: Option<usize>,
+    /// A JSON schema the final answer has to match. The model is asked to fix answers that don't,
+    /// and the session exits with 6 if it can't. Implies --no-interactive
+    #[arg(long, value_name = "SCHEMA")]
+    pub contract: Option<PathBuf>,
+    #[command(subcommand)]
+    pub subcommand: Option<ChatSubcommand>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum ChatSubcommand {
+    /// Browse the saved conversations
+    History(HistoryArgs),
 }
 
 impl ChatArgs {
     pub async fn execute(mut self, os: &mut Os) -> Result<ExitCode> {
+        if let Some(ChatSubcommand::History(args)) = self.subcommand {
+            return args.execute(os).await;
+        }
+
         let mut input = self.input;
 
+        if self.output_format == ChatOutputFormat::Json || self.contract.is_some() {
+            self.no_interactive = true;
+        }
+
         if self.no_interactive && input.is_none() {
             if !std::io::stdin().is_terminal() {
                 let mut buffer = String::new();
@@ -258,6 +389,14 @@ impl ChatArgs {
             }
         }
 
+        let contract = match &self.contract {
+            Some(path) => Some(Contract::load(os, path).await?),
+            None => None,
+        };
+        if let Some(contract) = &contract {
+            input = input.map(|input| contract.instruct(&input));
+        }
+
         let stdout = std::io::stdout();
         let mut stderr = std::io::stderr();
 
@@ -282,6 +421,11 @@ impl ChatArgs {
         let conversation_id = uuid::Uuid::new_v4().to_string();
         info!(?conversation_id, "Generated new conversation id");
 
+        // A policy that can't be read is not silently ignored.
+        let managed_policy = ManagedPolicy::load(os)
+            .await
+            .map_err(|err| eyre!("Failed to load the managed policy: {err}"))?;
+
         // Check MCP status once at the beginning of the session
         let mcp_enabled = match os.client.is_mcp_enabled().await {
             Ok(enabled) => enabled,
@@ -289,13 +433,22 @@ impl ChatArgs {
                 tracing::warn!(?err, "Failed to check MCP configuration, defaulting to enabled");
                 true
             },
-        };
+        } && ManagedPolicy::mcp_enabled(managed_policy.as_ref());
+
+        // The workspace config comes with the repository, so it is only used once the user trusts it.
+        // Errors loading it are reported by Agents::load.
+        if let Ok(Some(config)) = WorkspaceConfig::load(os).await {
+            let interactive = !self.no_interactive && std::io::stdin().is_terminal();
+            config.confirm_trust(os, &mut stderr, interactive).await?;
+        }
 
         let agents = {
             let skip_migration = self.no_interactive;
             let (mut agents, md) =
                 Agents::load(os, self.agent.as_deref(), skip_migration, &mut stderr, mcp_enabled).await;
             agents.trust_all_tools = self.trust_all_tools;
+            agents.managed_policy = managed_policy;
+            agents.apply_workspace_config(mcp_enabled);
 
             os.telemetry
                 .send_agent_config_init(&os.database, conversation_id.clone(), AgentConfigInitArgs {
@@ -376,12 +529,26 @@ impl ChatArgs {
             if let Some(m) = find_model(&models, requested) {
                 Some(m.model_id.clone())
             } else {
-                let available = models
-                    .iter()
-                    .map(|m| m.model_name.as_deref().unwrap_or(&m.model_id))
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                bail!("Model '{}' does not exist. Available models: {}", requested, available);
+                let region = Endpoint::configured_value(&os.database).region().to_string();
+                if self.no_interactive || !std::io::stdin().is_terminal() {
+                    let available = models
+                        .iter()
+                        .map(|m| m.model_name.as_deref().unwrap_or(&m.model_id))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    bail!(
+                        "Model '{}' is not available in region {}. Available models: {}",
+                        requested,
+                        region,
+                        available
+                    );
+                }
+
+                let prompt = format!("Model '{requested}' is not available in region {region}. Select a model");
+                match pick_model(&prompt, &models, None)? {
+                    Some(index) => Some(models[index].model_id.clone()),
+                    None => bail!("No model selected."),
+                }
             }
         } else if let Some(agent_model) = agents.get_active().and_then(|a| a.model.as_ref()) {
             // Agent model takes second priority
@@ -419,7 +586,7 @@ impl ChatArgs {
             .await?;
         let tool_config = tool_manager.load_tools(os, &mut stderr).await?;
 
-        ChatSession::new(
+        let result = ChatSession::new(
             os,
             stdout,
             stderr,
@@ -435,11 +602,16 @@ impl ChatArgs {
             !self.no_interactive,
             mcp_enabled,
             self.wrap,
+            self.output_format,
+            self.max_turns.filter(|_| self.no_interactive),
+            contract,
         )
         .await?
         .spawn(os)
         .await
-        .map(|_| ExitCode::SUCCESS)
+        .map(ExitCode::from);
+        crate::logging::end_session_log();
+        result
     }
 }
 
@@ -447,10 +619,17 @@ impl ChatArgs {
 const CHANGELOG_MAX_SHOW_COUNT: i64 = 2;
 
 // Only show the model-related tip for now to make users aware of this feature.
-const ROTATING_TIPS: [&str; 20] = tips::ROTATING_TIPS;
+const ROTATING_TIPS: [&str; 22] = tips::ROTATING_TIPS;
 
 const GREETING_BREAK_POINT: usize = 80;
 
+/// How long rendered response text is collected before it is written to the terminal, once the
+/// response is received faster than it is paced.
+const RENDER_FRAME: Duration = Duration::from_millis(16);
+/// How much received but not yet rendered response text is paced. Beyond it, text is rendered a
+/// frame at a time without pauses, so that the terminal doesn't fall behind long responses.
+const RENDER_PACED_BACKLOG: usize = 1024;
+
 const RESPONSE_TIMEOUT_CONTENT: &str = "Response timed out - message took too long to generate";
 fn trust_all_text() -> String {
     ui_text::trust_all_warning()
@@ -502,6 +681,8 @@ pub enum ChatError {
     CompactHistoryFailure,
     #[error("Failed to swap to agent: {0}")]
     AgentSwapError(eyre::Report),
+    #[error(transparent)]
+    Categorized(#[from] CategorizedError),
 }
 
 impl ChatError {
@@ -519,6 +700,7 @@ impl ChatError {
             ChatError::NonInteractiveToolApproval => None,
             ChatError::CompactHistoryFailure => None,
             ChatError::AgentSwapError(_) => None,
+            ChatError::Categorized(_) => None,
         }
     }
 }
@@ -538,6 +720,15 @@ impl ReasonCode for ChatError {
             ChatError::NonInteractiveToolApproval => "NonInteractiveToolApproval".to_string(),
             ChatError::CompactHistoryFailure => "CompactHistoryFailure".to_string(),
             ChatError::AgentSwapError(_) => "AgentSwapError".to_string(),
+            ChatError::Categorized(e) => match e.category {
+                ErrorCategory::Auth => "AuthError".to_string(),
+                ErrorCategory::Network => "NetworkError".to_string(),
+                ErrorCategory::Quota => "QuotaError".to_string(),
+                ErrorCategory::ToolFailure => "ToolFailure".to_string(),
+                ErrorCategory::ConfigInvalid => "ConfigInvalid".to_string(),
+                ErrorCategory::Interrupted => "Interrupted".to_string(),
+                ErrorCategory::Internal => "GenericError".to_string(),
+            },
         }
     }
 }
@@ -562,7 +753,7 @@ impl From<parser::RecvError> for ChatError {
 
 pub struct ChatSession {
     /// For output read by humans and machine
-    pub stdout: std::io::Stdout,
+    pub stdout: SessionOutput,
     /// For display output, only read by humans
     pub stderr: std::io::Stderr,
     initial_input: Option<String>,
@@ -591,12 +782,47 @@ pub struct ChatSession {
     tool_use_status: ToolUseStatus,
     /// Any failed requests that could be useful for error report/debugging
     failed_request_ids: Vec<String>,
+    /// How the models used in this session fared, shown by /model.
+    model_health: ModelHealth,
     /// Pending prompts to be sent
     pending_prompts: VecDeque<PromptMessage>,
     interactive: bool,
     inner: Option<ChatState>,
     ctrlc_rx: broadcast::Receiver<()>,
     wrap: Option<WrapMode>,
+    /// The most recent compaction, while `/bad` is still reported as feedback on it.
+    recent_compaction: Option<RecentCompaction>,
+    /// Entry of this session in the registry of running agents.
+    agent_registration: Option<AgentRegistration>,
+    /// Where to send the final response of a turn started with `q agent send --wait`, along with
+    /// the length of the history when the turn started.
+    control_reply: Option<(tokio::sync::oneshot::Sender<Result<String, String>>, usize)>,
+    /// Where to write events with `--output-format json`.
+    json_events: Option<JsonEvents>,
+    /// Where to print the final answer when running without user input, with the rest of the
+    /// output going to stderr.
+    answer_output: Option<std::io::Stdout>,
+    /// Maximum number of requests to the model when running without user input.
+    max_turns: Option<usize>,
+    /// Number of requests sent to the model so far.
+    turns: usize,
+    /// The span of the ongoing chat turn, from the user's prompt to the final response.
+    turn_span: Option<OtelSpan>,
+    /// Why the session stopped, for the exit code when running without user input.
+    outcome: HeadlessOutcome,
+    /// The schema the final answer has to match, with `--contract`.
+    contract: Option<Contract>,
+    /// The final answer, once it matches the contract.
+    contract_output: Option<serde_json::Value>,
+    /// Tool uses recorded with `/plan` instead of being run.
+    plan: Plan,
+    /// What the model is told about writes reviewed hunk by hunk, by tool use id.
+    hunk_reviews: HashMap<String, String>,
+    tool_rate_limiter: ToolRateLimiter,
+    notifier: Notifier,
+    resource_monitor: ResourceMonitor,
+    /// The background tasks of the session.
+    tasks: TaskSupervisor,
 }
 
 impl ChatSession {
@@ -617,6 +843,9 @@ impl ChatSession {
         interactive: bool,
         mcp_enabled: bool,
         wrap: Option<WrapMode>,
+        output_format: ChatOutputFormat,
+        max_turns: Option<usize>,
+        contract: Option<Contract>,
     ) -> Result<Self> {
         // Reload prior conversation
         let mut existing_conversation = false;
@@ -671,9 +900,14 @@ impl ChatSession {
             },
         };
 
+        if let Err(err) = crate::logging::start_session_log(conversation.conversation_id()) {
+            warn!(?err, "failed to open the log file of the session");
+        }
+
         // Spawn a task for listening and broadcasting sigints.
+        let mut tasks = TaskSupervisor::new();
         let (ctrlc_tx, ctrlc_rx) = tokio::sync::broadcast::channel(4);
-        tokio::spawn(async move {
+        tasks.spawn("ctrl_c listener", async move {
             loop {
                 match ctrl_c().await {
                     Ok(_) => {
@@ -688,6 +922,16 @@ impl ChatSession {
             }
         });
 
+        let (stdout, json_events, answer_output) = match output_format {
+            ChatOutputFormat::Text if interactive => (SessionOutput::Stdout(stdout), None, None),
+            ChatOutputFormat::Text => (SessionOutput::Stderr(std::io::stderr()), None, Some(stdout)),
+            ChatOutputFormat::Json => (
+                SessionOutput::Stderr(std::io::stderr()),
+                Some(JsonEvents::new(stdout)),
+                None,
+            ),
+        };
+
         Ok(Self {
             stdout,
             stderr,
@@ -704,11 +948,29 @@ impl ChatSession {
             tool_use_telemetry_events: HashMap::new(),
             tool_use_status: ToolUseStatus::Idle,
             failed_request_ids: Vec::new(),
+            model_health: ModelHealth::default(),
             pending_prompts: VecDeque::new(),
             interactive,
             inner: Some(ChatState::default()),
             ctrlc_rx,
             wrap,
+            recent_compaction: None,
+            agent_registration: None,
+            control_reply: None,
+            json_events,
+            answer_output,
+            max_turns,
+            turns: 0,
+            turn_span: None,
+            outcome: HeadlessOutcome::default(),
+            contract,
+            contract_output: None,
+            plan: Plan::default(),
+            hunk_reviews: HashMap::new(),
+            tool_rate_limiter: ToolRateLimiter::default(),
+            notifier: Notifier::default(),
+            resource_monitor: ResourceMonitor::spawn(ResourceThresholds::from_settings(os)),
+            tasks,
         })
     }
 
@@ -716,16 +978,35 @@ impl ChatSession {
         // Update conversation state with new tool information
         self.conversation.update_state(false).await;
 
+        if let Some(registration) = &self.agent_registration {
+            registration.set_agent(&self.conversation.agents.active_idx);
+        }
+
         let mut ctrl_c_stream = self.ctrlc_rx.resubscribe();
         let result = match self.inner.take().expect("state must always be Some") {
             ChatState::PromptUser { skip_printing_tools } => {
                 match (self.interactive, self.tool_uses.is_empty()) {
                     (false, true) => {
-                        self.inner = Some(ChatState::Exit);
+                        self.inner = Some(self.check_contract()?);
                         return Ok(());
                     },
                     (false, false) => {
-                        return Err(ChatError::NonInteractiveToolApproval);
+                        let err = ChatError::NonInteractiveToolApproval;
+                        let error_report = err.report();
+                        execute!(
+                            self.stderr,
+                            style::SetForegroundColor(Color::Red),
+                            style::Print(format!("{err}\n")),
+                            style::SetForegroundColor(Color::Reset),
+                        )?;
+                        error_report.hint.queue(&mut self.stderr)?;
+                        self.stderr.flush()?;
+                        if let Ok(error) = serde_json::to_value(&error_report) {
+                            self.emit_json(JsonEvent::Error { error })?;
+                        }
+                        self.outcome = HeadlessOutcome::from(&err);
+                        self.inner = Some(ChatState::Exit);
+                        return Ok(());
                     },
                     _ => (),
                 };
@@ -760,6 +1041,32 @@ impl ChatSession {
                 }
             },
             ChatState::HandleResponseStream(conversation_state) => {
+                if let Some(max_turns) = self.max_turns.filter(|max| self.turns >= *max) {
+                    execute!(
+                        self.stderr,
+                        style::SetForegroundColor(Color::Red),
+                        style::Print(format!("Stopped after reaching the maximum of {max_turns} turns\n")),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                    self.outcome = HeadlessOutcome::MaxTurnsExceeded;
+                    self.inner = Some(ChatState::Exit);
+                    return Ok(());
+                }
+                self.turns += 1;
+                if self.turn_span.is_none() {
+                    self.turn_span = Some(OtelSpan::start("chat_turn", &[
+                        ("conversation.id", self.conversation.conversation_id().to_string()),
+                        (
+                            "model.id",
+                            self.conversation
+                                .model_info
+                                .as_ref()
+                                .map(|model| model.model_id.clone())
+                                .unwrap_or_default(),
+                        ),
+                    ]));
+                }
+
                 let request_metadata: Arc<Mutex<Option<RequestMetadata>>> = Arc::new(Mutex::new(None));
                 let request_metadata_clone = Arc::clone(&request_metadata);
 
@@ -788,6 +1095,11 @@ impl ChatSession {
 
         let err = match result {
             Ok(state) => {
+                if matches!(state, ChatState::PromptUser { .. } | ChatState::Exit) {
+                    if let Some(span) = self.turn_span.take() {
+                        span.end(None);
+                    }
+                }
                 self.inner = Some(state);
                 return Ok(());
             },
@@ -796,9 +1108,17 @@ impl ChatSession {
 
         // We encountered an error. Handle it.
         error!(?err, "An error occurred processing the current state");
+        if let Some(span) = self.turn_span.take() {
+            span.end(Some(&err.to_string()));
+        }
         let (reason, reason_desc) = get_error_reason(&err);
         self.send_error_telemetry(os, reason, Some(reason_desc), err.status_code())
             .await;
+        // An interactive session carries on after an error, so it doesn't decide how it exits.
+        if !self.interactive {
+            self.outcome = HeadlessOutcome::from(&err);
+        }
+        let error_report = err.report();
 
         if self.spinner.is_some() {
             drop(self.spinner.take());
@@ -811,6 +1131,7 @@ impl ChatSession {
 
         let (context, report, display_err_message) = match err {
             ChatError::Interrupted { tool_uses: ref inter } => {
+                self.tasks.cancel_turn();
                 execute!(self.stderr, style::Print("\n\n"))?;
 
                 // If there was an interrupt during tool execution, then we add fake
@@ -900,6 +1221,8 @@ impl ChatSession {
                             style::Print("\n\n"),
                         )?;
 
+                        // The conversation carries on once compacted.
+                        self.outcome = HeadlessOutcome::Success;
                         return Ok(());
                     }
                 },
@@ -967,6 +1290,7 @@ impl ChatSession {
                     (error_messages::TROUBLE_RESPONDING, eyre!(err), false)
                 },
                 ApiClientError::MonthlyLimitReached { .. } => {
+                    monthly_usage::record_limit_reached(&os.database);
                     let subscription_status = get_subscription_status(os).await;
                     if subscription_status.is_err() {
                         execute!(
@@ -1041,11 +1365,23 @@ impl ChatSession {
             queue!(self.stderr, style::Print(&text),)?;
             self.conversation.append_transcript(text);
 
-            execute!(
+            queue!(
                 self.stderr,
                 style::SetAttribute(Attribute::Reset),
                 style::SetForegroundColor(Color::Reset),
             )?;
+            error_report.hint.queue(&mut self.stderr)?;
+
+            // When running headless, also emit the error in a machine readable form.
+            if !self.interactive && !self.stderr.is_terminal() {
+                if let Ok(json) = serde_json::to_string(&serde_json::json!({ "error": &error_report })) {
+                    queue!(self.stderr, style::Print(json), style::Print("\n"))?;
+                }
+            }
+            self.stderr.flush()?;
+        }
+        if let Ok(error) = serde_json::to_value(&error_report) {
+            self.emit_json(JsonEvent::Error { error })?;
         }
 
         self.conversation.enforce_conversation_invariants();
@@ -1094,7 +1430,7 @@ impl ChatSession {
         self.conversation
             .reload_builtin_tools(os, &mut self.stderr)
             .await
-            .map_err(|e| ChatError::Custom(format!("Failed to update tool spec: {e}").into()))
+            .map_err(|e| CategorizedError::tool_failure(format!("Failed to update tool spec: {e}")).into())
     }
 }
 
@@ -1159,36 +1495,224 @@ impl Default for ChatState {
     }
 }
 
+/// What [ChatSession::send_conversation] comes back with.
+#[allow(clippy::large_enum_variant)]
+enum SendOutcome {
+    /// The response to the request.
+    Stream(SendMessageStream),
+    /// The request failed over to a model the conversation doesn't fit, so this state compacts it
+    /// before sending the request again.
+    Compact(ChatState),
+}
+
 impl ChatSession {
     /// Sends a request to the SendMessage API. Emits error telemetry on failure.
+    ///
+    /// If the model is throttled or out of capacity, the request is sent again with the next model
+    /// of [Setting::ChatFallbackModels], which the conversation then carries on with. Requests of
+    /// the conversation itself are sent with [Self::send_conversation] instead.
     async fn send_message(
         &mut self,
         os: &mut Os,
-        conversation_state: api_client::model::ConversationState,
+        mut conversation_state: api_client::model::ConversationState,
         request_metadata_lock: Arc<Mutex<Option<RequestMetadata>>>,
         message_meta_tags: Option<Vec<MessageMetaTag>>,
     ) -> Result<SendMessageStream, ChatError> {
-        match SendMessageStream::send_message(&os.client, conversation_state, request_metadata_lock, message_meta_tags)
-            .await
+        let mut tried = Vec::new();
+        loop {
+            let err = match self
+                .try_send_message(os, &conversation_state, &request_metadata_lock, &message_meta_tags)
+                .await?
+            {
+                Ok(res) => return Ok(res),
+                Err(err) => err,
+            };
+            let fallback = self.fallback_after(os, err, &mut tried).await?;
+            conversation_state.user_input_message.model_id = Some(fallback.model_id.clone());
+            self.conversation.set_model(fallback);
+            self.show_thinking();
+        }
+    }
+
+    /// Sends a request of the conversation like [Self::send_message]. If it fails over to a model
+    /// whose context window the conversation doesn't fit, the conversation is compacted first, with
+    /// the returned state, which then sends the request again.
+    async fn send_conversation(
+        &mut self,
+        os: &mut Os,
+        mut conversation_state: api_client::model::ConversationState,
+        request_metadata_lock: Arc<Mutex<Option<RequestMetadata>>>,
+    ) -> Result<SendOutcome, ChatError> {
+        let mut tried = Vec::new();
+        loop {
+            let err = match self
+                .try_send_message(os, &conversation_state, &request_metadata_lock, &None)
+                .await?
+            {
+                Ok(res) => return Ok(SendOutcome::Stream(res)),
+                Err(err) => err,
+            };
+            let fallback = self.fallback_after(os, err, &mut tried).await?;
+            conversation_state.user_input_message.model_id = Some(fallback.model_id.clone());
+            self.conversation.set_model(fallback);
+            if let Some(state) = compact_to_fit(os, self).await? {
+                return Ok(SendOutcome::Compact(state));
+            }
+            self.show_thinking();
+        }
+    }
+
+    /// Sends the request once, noting how the model fared.
+    async fn try_send_message(
+        &mut self,
+        os: &mut Os,
+        conversation_state: &api_client::model::ConversationState,
+        request_metadata_lock: &Arc<Mutex<Option<RequestMetadata>>>,
+        message_meta_tags: &Option<Vec<MessageMetaTag>>,
+    ) -> Result<Result<SendMessageStream, SendMessageError>, ChatError> {
+        let model_id = conversation_state.user_input_message.model_id.clone();
+        match SendMessageStream::send_message(
+            &os.client,
+            conversation_state.clone(),
+            Arc::clone(request_metadata_lock),
+            message_meta_tags.clone(),
+            self.tasks.turn_token(),
+        )
+        .await
         {
-            Ok(res) => Ok(res),
+            Ok(res) => {
+                if let Some(model_id) = &model_id {
+                    self.model_health.record_success(model_id);
+                }
+                monthly_usage::record_request(os, &mut self.stderr).await?;
+                Ok(Ok(res))
+            },
             Err(err) => {
-                let (reason, reason_desc) = get_error_reason(&err);
+                if let Some(model_id) = &model_id {
+                    self.model_health.record_error(model_id, &err.source);
+                }
+                Ok(Err(err))
+            },
+        }
+    }
+
+    /// The model to send the request that failed with `err` again with, if the model was
+    /// throttled or out of capacity and a fallback model that wasn't `tried` yet is available.
+    /// Otherwise the error to fail with, once its telemetry is sent.
+    async fn fallback_after(
+        &mut self,
+        os: &Os,
+        err: SendMessageError,
+        tried: &mut Vec<String>,
+    ) -> Result<ModelInfo, ChatError> {
+        let model_id = err.request_metadata.model_id.clone();
+        if let (Some(model_id), Some(reason)) = (model_id, capacity_error(&err.source)) {
+            let unavailable = match &self.conversation.model_info {
+                Some(model) if model.model_id == model_id => model.display_name().to_string(),
+                _ => model_id.clone(),
+            };
+            tried.push(model_id);
+            if let Some(fallback) = self.fallback_model(os, tried).await {
+                let (reason_code, reason_desc) = get_error_reason(&err);
                 self.send_chat_telemetry(
                     os,
                     TelemetryResult::Failed,
-                    Some(reason),
+                    Some(reason_code),
                     Some(reason_desc),
                     err.status_code(),
-                    true, // We never retry failed requests, so this always ends the current turn.
+                    false, // We retry the request with the fallback model.
                 )
                 .await;
-                Err(err.into())
-            },
+                self.spinner.take();
+                execute!(
+                    self.stderr,
+                    terminal::Clear(terminal::ClearType::CurrentLine),
+                    cursor::MoveToColumn(0),
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(format!(
+                        "{unavailable} is {reason}, switching to {}\n",
+                        fallback.display_name()
+                    )),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+                return Ok(fallback);
+            }
+        }
+
+        let (reason, reason_desc) = get_error_reason(&err);
+        self.send_chat_telemetry(
+            os,
+            TelemetryResult::Failed,
+            Some(reason),
+            Some(reason_desc),
+            err.status_code(),
+            true, // We never retry failed requests, so this always ends the current turn.
+        )
+        .await;
+        Err(err.into())
+    }
+
+    fn show_thinking(&mut self) {
+        if self.interactive {
+            self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_owned()));
         }
     }
 
-    async fn spawn(&mut self, os: &mut Os) -> Result<()> {
+    /// The first model of [Setting::ChatFallbackModels] that is available, wasn't `tried` and
+    /// didn't fail recently.
+    async fn fallback_model(&self, os: &Os, tried: &[String]) -> Option<ModelInfo> {
+        let names = os
+            .database
+            .settings
+            .get(Setting::ChatFallbackModels)
+            .and_then(|value| serde_json::from_value::<Vec<String>>(value.clone()).ok())?;
+        let (models, _) = get_available_models(os).await.ok()?;
+        names
+            .iter()
+            .filter_map(|name| find_model(&models, name))
+            .find(|model| !tried.contains(&model.model_id) && !self.model_health.is_unhealthy(&model.model_id))
+            .cloned()
+    }
+
+    async fn spawn(&mut self, os: &mut Os) -> Result<HeadlessOutcome> {
+        // Tests shouldn't register in the user's registry.
+        if !cfg!(test) {
+            match registry::collect_garbage() {
+                Ok(reclaimed) if !reclaimed.is_empty() => {
+                    let mut reclaimed_items = reclaimed
+                        .processes
+                        .iter()
+                        .map(|process| format!("{} (pid {})", process.name, process.pid))
+                        .collect::<Vec<_>>();
+                    if reclaimed.sockets > 0 {
+                        reclaimed_items.push(format!("{} stale control socket(s)", reclaimed.sockets));
+                    }
+                    if reclaimed.temp_dirs > 0 {
+                        reclaimed_items.push(format!("{} temporary directory(ies)", reclaimed.temp_dirs));
+                    }
+                    execute!(
+                        self.stderr,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(format!(
+                            "Cleaned up after sessions that didn't exit cleanly: {}\n\n",
+                            reclaimed_items.join(", ")
+                        )),
+                        style::SetForegroundColor(Color::Reset),
+                    )?;
+                },
+                Ok(_) => {},
+                Err(err) => warn!(?err, "failed to clean up the agent registry"),
+            }
+
+            match AgentRegistration::register(
+                &self.conversation.agents.active_idx,
+                self.conversation.conversation_id(),
+            ) {
+                Ok(registration) => self.agent_registration = Some(registration),
+                Err(err) => warn!(?err, "failed to register in the agent registry"),
+            }
+        }
+
         let is_small_screen = self.terminal_width() < GREETING_BREAK_POINT;
         if os
             .database
@@ -1245,6 +1769,10 @@ impl ChatSession {
         // Check if we should show the whats-new announcement
         self.show_changelog_announcement(os).await?;
 
+        if self.existing_conversation {
+            self.display_plan(os, true).await?;
+        }
+
         if self.all_tools_trusted() {
             queue!(
                 self.stderr,
@@ -1279,6 +1807,7 @@ impl ChatSession {
         // Initialize capturing if possible
         if ExperimentManager::is_enabled(os, ExperimentName::Checkpoint) {
             let path = get_shadow_repo_dir(os, self.conversation.conversation_id().to_string())?;
+            gc_shadow_repos(os, &path);
             let start = std::time::Instant::now();
             let checkpoint_manager = match CheckpointManager::auto_init(os, &path, self.conversation.history()).await {
                 Ok(manager) => {
@@ -1307,11 +1836,69 @@ impl ChatSession {
             self.inner = Some(ChatState::HandleInput { input: user_input });
         }
 
+        // An error ends the session, but only once it is wound down like any other.
+        let mut res = Ok(());
         while !matches!(self.inner, Some(ChatState::Exit)) {
-            self.next(os).await?;
+            tokio::select! {
+                next = self.next(os) => if let Err(err) = next {
+                    res = Err(err);
+                    break;
+                },
+                _ = shutdown::requested() => {
+                    info!("shutdown requested, exiting the chat session");
+                    self.inner = Some(ChatState::Exit);
+                },
+            }
         }
 
-        Ok(())
+        self.tasks.shutdown(supervisor::SHUTDOWN_DEADLINE).await;
+
+        // Run SessionEnd hooks once, however the session ended
+        if let Some(cm) = self.conversation.context_manager.as_mut() {
+            let _ = cm
+                .run_hooks(
+                    crate::cli::agent::hook::HookTrigger::SessionEnd,
+                    &mut std::io::stderr(),
+                    os,
+                    None,
+                    None,
+                )
+                .await;
+        }
+
+        // Persist the conversation so that a session ended by a signal can still be resumed.
+        if !self.conversation.history().is_empty() {
+            if let Some(dir) = self.conversation.pinned_dir.as_ref() {
+                os.database.set_conversation_by_path(dir, &self.conversation).ok();
+            }
+        }
+        res?;
+
+        if self.interactive && !self.conversation.session_usage.is_empty() {
+            cost::print_usage_summary(
+                &mut self.stderr,
+                &self.conversation.session_usage,
+                &cost::price_table(os),
+            )?;
+            execute!(self.stderr)?;
+        }
+
+        if let Some(value) = self.contract_output.take() {
+            self.emit_json(JsonEvent::Output { value: &value })?;
+            if let Some(output) = self.answer_output.as_mut() {
+                writeln!(output, "{value}")?;
+                output.flush()?;
+            }
+        } else if let Some(output) = self.answer_output.as_mut() {
+            if self.outcome == HeadlessOutcome::Success {
+                if let Some(entry) = self.conversation.history().last() {
+                    writeln!(output, "{}", entry.response().trim_end())?;
+                    output.flush()?;
+                }
+            }
+        }
+
+        Ok(self.outcome)
     }
 
     /// Compacts the conversation history using the strategy specified by [CompactStrategy],
@@ -1394,10 +1981,16 @@ impl ChatSession {
             )?;
         }
 
+        let tokens_before = TokenCount::from(self.conversation.calculate_char_count(os).await?);
+        let prompt_variant = match custom_prompt {
+            Some(_) => "custom".to_string(),
+            None => CompactionPromptVariant::from_settings(os).to_string(),
+        };
         let summary_state = self
             .conversation
             .create_summary_request(os, custom_prompt.as_ref(), strategy)
             .await?;
+        let summary_start = Instant::now();
 
         if self.interactive {
             execute!(self.stderr, cursor::Hide, style::Print("\n"))?;
@@ -1528,9 +2121,35 @@ impl ChatSession {
             )?;
         }
 
+        let latency_ms = summary_start.elapsed().as_millis() as i64;
+        let request_id = request_metadata.request_id.clone();
+        let model = request_metadata.model_id.clone();
         self.conversation
             .replace_history_with_summary(summary.clone(), strategy, request_metadata);
 
+        let tokens_after = TokenCount::from(self.conversation.calculate_char_count(os).await?);
+        os.telemetry
+            .send_compact_history(
+                &os.database,
+                self.conversation.conversation_id().to_string(),
+                TelemetryResult::Succeeded,
+                CompactHistoryArgs {
+                    request_id: request_id.clone(),
+                    model,
+                    tokens_before: tokens_before.value() as i64,
+                    tokens_after: tokens_after.value() as i64,
+                    latency_ms,
+                    prompt_variant: prompt_variant.clone(),
+                },
+            )
+            .await
+            .ok();
+        self.recent_compaction = Some(RecentCompaction {
+            request_id,
+            prompt_variant,
+            turns: 0,
+        });
+
         // If a next message is set, then retry the request.
         let should_retry = self.conversation.next_user_message().is_some();
 
@@ -1608,6 +2227,62 @@ impl ChatSession {
         }
     }
 
+    /// Asks the model for a summary of the current tangent, for `/tangent merge`.
+    async fn summarize_tangent(&mut self, os: &mut Os) -> Result<(String, RequestMetadata), ChatError> {
+        let summary_state = self.conversation.create_tangent_summary_request(os).await?;
+
+        if self.interactive {
+            execute!(self.stderr, cursor::Hide, style::Print("\n"))?;
+            self.spinner = Some(Spinner::new(Spinners::Dots, "Summarizing the tangent...".to_string()));
+        }
+
+        let response = self
+            .send_message(
+                os,
+                summary_state,
+                Arc::new(Mutex::new(None)),
+                Some(vec![MessageMetaTag::TangentMode]),
+            )
+            .await;
+        let result: Result<(String, RequestMetadata), ChatError> = match response {
+            Ok(mut response) => loop {
+                match response.recv().await {
+                    Some(Ok(parser::ResponseEvent::EndStream {
+                        message,
+                        request_metadata,
+                    })) => {
+                        self.user_turn_request_metadata.push(request_metadata.clone());
+                        break Ok((message.content().to_string(), request_metadata));
+                    },
+                    Some(Ok(_)) => (),
+                    Some(Err(err)) => {
+                        if let Some(request_id) = &err.request_metadata.request_id {
+                            self.failed_request_ids.push(request_id.clone());
+                        }
+                        self.user_turn_request_metadata.push(err.request_metadata.clone());
+                        break Err(err.into());
+                    },
+                    None => {
+                        error!("response stream receiver closed before receiving a stop event");
+                        break Err(ChatError::Custom("Stream failed while summarizing the tangent".into()));
+                    },
+                }
+            },
+            Err(err) => Err(err),
+        };
+
+        if self.spinner.is_some() {
+            drop(self.spinner.take());
+            queue!(
+                self.stderr,
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                cursor::MoveToColumn(0),
+                cursor::Show
+            )?;
+        }
+        result
+    }
+
     /// Generates a custom agent configuration (system prompt and tool config) based on user input.
     /// Uses an LLM to create the agent specifications from the provided name and description.
     async fn generate_agent_config(
@@ -1841,16 +2516,31 @@ impl ChatSession {
     async fn prompt_user(&mut self, os: &Os, skip_printing_tools: bool) -> Result<ChatState, ChatError> {
         execute!(self.stderr, cursor::Show)?;
 
+        for name in self.tasks.reap() {
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!(
+                    "\nWarning: the {name} task stopped unexpectedly, see the logs for details\n"
+                )),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+
         // Check token usage and display warnings if needed
         if self.pending_tool_index.is_none() {
             // Only display warnings when not waiting for tool approval
             if let Err(err) = self.display_char_warnings(os).await {
                 warn!("Failed to display character limit warnings: {}", err);
             }
+            self.display_plan(os, false).await?;
         }
 
         let show_tool_use_confirmation_dialog = !skip_printing_tools && self.pending_tool_index.is_some();
         if show_tool_use_confirmation_dialog {
+            let reviewable = self
+                .pending_tool_index
+                .is_some_and(|index| matches!(self.tool_uses[index].tool, Tool::FsWrite(_)));
             execute!(
                 self.stderr,
                 style::SetForegroundColor(Color::DarkGrey),
@@ -1870,7 +2560,16 @@ impl ChatSession {
                 style::SetForegroundColor(Color::Green),
                 style::Print("t"),
                 style::SetForegroundColor(Color::DarkGrey),
-                style::Print("]:\n\n"),
+                style::Print(if reviewable { "/" } else { "" }),
+                style::SetForegroundColor(Color::Green),
+                style::Print(if reviewable { "p" } else { "" }),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("]:\n"),
+                style::Print(if reviewable {
+                    "Use 'p' to review the changes hunk by hunk.\n\n"
+                } else {
+                    "\n"
+                }),
                 style::SetForegroundColor(Color::Reset),
             )?;
         }
@@ -1901,6 +2600,44 @@ impl ChatSession {
             style::SetForegroundColor(Color::Reset),
             style::SetAttribute(Attribute::Reset)
         )?;
+        if let Some(registration) = &self.agent_registration {
+            registration
+                .bridge()
+                .set_transcript(self.conversation.transcript.iter().cloned().collect());
+        }
+
+        // Prompts sent with `q agent send` take the place of user input, but never answer a tool
+        // approval.
+        if self.pending_tool_index.is_none() {
+            // The turn started by the previous prompt is over once the session waits for input again.
+            if let Some((reply, turn_start)) = self.control_reply.take() {
+                let response = self
+                    .conversation
+                    .history()
+                    .iter()
+                    .skip(turn_start)
+                    .last()
+                    .map(|entry| entry.response().to_string())
+                    .ok_or_else(|| "the turn ended without a response".to_string());
+                reply.send(response).ok();
+            }
+
+            if let Some(IncomingPrompt { text: prompt, reply }) =
+                self.agent_registration.as_mut().and_then(|r| r.next_prompt())
+            {
+                self.control_reply = reply.map(|reply| (reply, self.conversation.history().len()));
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print("Received from q agent send:\n"),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print(format!("> {prompt}\n")),
+                )?;
+                self.conversation.append_user_transcript(&prompt);
+                return Ok(ChatState::HandleInput { input: prompt });
+            }
+        }
+
         let prompt = self.generate_tool_trust_prompt(os).await;
         let user_input = match self.read_user_input(&prompt, false) {
             Some(input) => input,
@@ -1963,6 +2700,7 @@ impl ChatSession {
                                 style::Print(format!("\nFailed to execute command: {}\n", err)),
                                 style::SetForegroundColor(Color::Reset)
                             )?;
+                            err.hint().queue(&mut self.stderr)?;
                             let _ = self
                                 .send_slash_command_telemetry(
                                     os,
@@ -2012,16 +2750,13 @@ impl ChatSession {
             Ok(ChatState::PromptUser {
                 skip_printing_tools: false,
             })
-        } else if let Some(command) = input.strip_prefix("@") {
+        } else if let Some(prompt_name) = mention::prompt_name(os, input) {
+            let command = input.strip_prefix("@").unwrap_or(input);
             let input_parts =
                 shlex::split(command).ok_or(ChatError::Custom("Error splitting prompt command".into()))?;
 
-            let mut iter = input_parts.into_iter();
-            let prompt_name = iter
-                .next()
-                .ok_or(ChatError::Custom("Prompt name needs to be specified".into()))?;
-
-            let args: Vec<String> = iter.collect();
+            // The first part is the prompt name, which may have been written as server:prompt.
+            let args: Vec<String> = input_parts.into_iter().skip(1).collect();
             let arguments = if args.is_empty() { None } else { Some(args) };
 
             let subcommand = PromptsSubcommand::Get {
@@ -2078,6 +2813,9 @@ impl ChatSession {
 
             // Check for a pending tool approval
             if let Some(index) = self.pending_tool_index {
+                if ["p", "P"].contains(&input) && matches!(self.tool_uses[index].tool, Tool::FsWrite(_)) {
+                    return hunk_review::review_pending_write(os, self, index).await;
+                }
                 let is_trust = ["t", "T"].contains(&input);
                 let tool_use = &mut self.tool_uses[index];
                 if ["y", "Y"].contains(&input) || is_trust {
@@ -2129,12 +2867,29 @@ impl ChatSession {
                 } else {
                     user_input
                 };
+                if let Some(tool_use) = self.pending_tool_index.and_then(|index| self.tool_uses.get(index)) {
+                    let entry = AuditEntry::new(self.conversation.conversation_id(), tool_use, Decision::Denied);
+                    audit::log(&entry).await;
+                }
                 self.conversation.abandon_tool_use(&self.tool_uses, user_input);
             } else {
+                if let Some(chat_state) = self.offer_previous_answer(os, &user_input)? {
+                    return Ok(chat_state);
+                }
+                let user_input = self.attach_ide_selections(user_input)?;
                 self.conversation.set_next_user_message(user_input).await;
             }
 
             self.reset_user_turn();
+            self.tool_rate_limiter.start_turn();
+            self.notifier.start_turn();
+
+            if let Some(compaction) = &mut self.recent_compaction {
+                compaction.turns += 1;
+                if compaction.turns > COMPACTION_FEEDBACK_TURNS {
+                    self.recent_compaction = None;
+                }
+            }
 
             let conv_state = self
                 .conversation
@@ -2180,6 +2935,35 @@ impl ChatSession {
                 continue;
             }
 
+            // The managed policy is evaluated first, nothing the user trusts overrides it.
+            let policy_result = self
+                .conversation
+                .agents
+                .managed_policy
+                .as_ref()
+                .map_or(PolicyEvalResult::Unrestricted, |policy| policy.eval(&tool.tool));
+            if let PolicyEvalResult::Deny(_) = &policy_result {
+                let entry = AuditEntry::new(self.conversation.conversation_id(), tool, Decision::Blocked);
+                audit::log(&entry).await;
+                execute!(
+                    self.stderr,
+                    style::SetForegroundColor(Color::Red),
+                    style::Print("Tool "),
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(&tool.name),
+                    style::SetForegroundColor(Color::Red),
+                    style::Print(format!(" is rejected because {policy_result}\n")),
+                    style::SetForegroundColor(Color::Reset),
+                )?;
+
+                return Ok(ChatState::HandleInput {
+                    input: format!(
+                        "Tool use with {} was rejected by the policy of the administrator, don't try to work around it",
+                        tool.name
+                    ),
+                });
+            }
+
             let mut denied_match_set = None::<Vec<String>>;
             let allowed =
                 self.conversation
@@ -2194,8 +2978,14 @@ impl ChatSession {
                         },
                     })
                     || self.conversation.agents.trust_all_tools;
+            let allowed = allowed && !matches!(policy_result, PolicyEvalResult::RequireApproval(_));
+            // Infrastructure changes are confirmed every time, whatever is trusted.
+            let infra_change = tool.tool.infra_change();
+            let allowed = allowed && infra_change.is_none();
 
             if let Some(match_set) = denied_match_set {
+                let entry = AuditEntry::new(self.conversation.conversation_id(), tool, Decision::Blocked);
+                audit::log(&entry).await;
                 let formatted_set = match_set.into_iter().fold(String::new(), |mut acc, rule| {
                     acc.push_str(&format!("\n  - {rule}"));
                     acc
@@ -2222,13 +3012,8 @@ impl ChatSession {
                 });
             }
 
-            if os
-                .database
-                .settings
-                .get_bool(Setting::ChatEnableNotifications)
-                .unwrap_or(false)
-            {
-                play_notification_bell(!allowed);
+            if !allowed && self.interactive {
+                self.notifier.approval_needed(os, &tool.name);
             }
 
             // TODO: Control flow is hacky here because of borrow rules
@@ -2236,6 +3021,16 @@ impl ChatSession {
             self.print_tool_description(os, i, allowed).await?;
             let tool = &mut self.tool_uses[i];
 
+            // Nothing to approve until the plan is applied.
+            if self.plan.intercepts(&tool.tool) {
+                continue;
+            }
+
+            if let Some(change) = &infra_change {
+                self.print_infra_preview(os, change).await?;
+            }
+            let tool = &mut self.tool_uses[i];
+
             if allowed {
                 tool.accepted = true;
                 self.tool_use_telemetry_events
@@ -2251,13 +3046,72 @@ impl ChatSession {
             });
         }
 
+        if self.offer_resource_pause()? {
+            return Err(ChatError::Interrupted {
+                tool_uses: Some(self.tool_uses.clone()),
+            });
+        }
+
         // All tools are allowed now
         // Execute the requested tools.
         let mut tool_results = vec![];
         let mut image_blocks: Vec<RichImageBlock> = Vec::new();
 
         for tool in &self.tool_uses {
+            if self.plan.intercepts(&tool.tool) {
+                let step = self.plan.record(tool);
+                execute!(
+                    self.stdout,
+                    style::Print(CONTINUATION_LINE),
+                    style::Print("\n"),
+                    style::SetForegroundColor(Color::Yellow),
+                    style::SetAttribute(Attribute::Bold),
+                    style::Print(format!(" ● Added to the plan as step {step}")),
+                    style::SetAttribute(Attribute::Reset),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print("\n\n"),
+                )?;
+                tool_results.push(ToolUseResult {
+                    tool_use_id: tool.id.clone(),
+                    content: vec![ToolUseResultBlock::Text(plan::recorded_result(step))],
+                    status: ToolResultStatus::Success,
+                });
+                continue;
+            }
+
             let tool_start = std::time::Instant::now();
+            if let Err(limited) = self
+                .tool_rate_limiter
+                .acquire(RateLimits::from_settings(os), &tool.tool, tool_start)
+            {
+                execute!(
+                    self.stderr,
+                    style::Print(CONTINUATION_LINE),
+                    style::Print("\n"),
+                    style::SetForegroundColor(Color::Yellow),
+                    style::SetAttribute(Attribute::Bold),
+                    style::Print(" ● Rate limited: "),
+                    style::SetAttribute(Attribute::Reset),
+                    style::SetForegroundColor(Color::Yellow),
+                    style::Print(&limited.message),
+                    style::SetForegroundColor(Color::Reset),
+                    style::Print("\n\n"),
+                )?;
+                tool_results.push(ToolUseResult {
+                    tool_use_id: tool.id.clone(),
+                    content: vec![ToolUseResultBlock::Json(limited.to_json())],
+                    status: ToolResultStatus::Error,
+                });
+                continue;
+            }
+            let decision = match self
+                .tool_use_telemetry_events
+                .get(&tool.id)
+                .is_some_and(|ev| ev.is_trusted)
+            {
+                true => Decision::Trusted,
+                false => Decision::Accepted,
+            };
             let mut tool_telemetry = self.tool_use_telemetry_events.entry(tool.id.clone());
             tool_telemetry = tool_telemetry.and_modify(|ev| {
                 ev.is_accepted = true;
@@ -2275,6 +3129,11 @@ impl ChatSession {
                 }
             }
 
+            let tool_attributes = [("tool.name", tool.name.clone()), ("tool.use_id", tool.id.clone())];
+            let tool_span = match &self.turn_span {
+                Some(turn_span) => turn_span.start_child("tool_use", &tool_attributes),
+                None => OtelSpan::start("tool_use", &tool_attributes),
+            };
             let invoke_result = tool
                 .tool
                 .invoke(
@@ -2284,6 +3143,10 @@ impl ChatSession {
                     &self.conversation.agents,
                 )
                 .await;
+            tool_span.end(invoke_result.as_ref().err().map(ToString::to_string).as_deref());
+            let entry = AuditEntry::new(self.conversation.conversation_id(), tool, decision)
+                .with_result(&invoke_result, tool_start.elapsed());
+            audit::log(&entry).await;
 
             if self.spinner.is_some() {
                 queue!(
@@ -2378,6 +3241,7 @@ impl ChatSession {
 
             let tool_end_time = Instant::now();
             let tool_time = tool_end_time.duration_since(tool_start);
+            self.tool_rate_limiter.record(&tool.tool, tool_time);
             tool_telemetry = tool_telemetry.and_modify(|ev| {
                 ev.execution_duration = Some(tool_time);
                 ev.turn_duration = self.tool_turn_start_time.map(|t| tool_end_time.duration_since(t));
@@ -2390,7 +3254,7 @@ impl ChatSession {
                     ev.input_token_size = Some(ct.get_input_token_size());
                 });
             }
-            let tool_time = format!("{}.{}", tool_time.as_secs(), tool_time.subsec_millis());
+            let tool_time = format_duration(tool_time);
             match invoke_result {
                 Ok(result) => {
                     match result.output {
@@ -2416,7 +3280,7 @@ impl ChatSession {
                         style::Print("\n"),
                         style::SetForegroundColor(Color::Green),
                         style::SetAttribute(Attribute::Bold),
-                        style::Print(format!(" ● Completed in {}s", tool_time)),
+                        style::Print(format!(" ● Completed in {}", tool_time)),
                         style::SetForegroundColor(Color::Reset),
                     )?;
                     if let Some(tag) = checkpoint_tag {
@@ -2439,6 +3303,12 @@ impl ChatSession {
 
                     // Send telemetry for agent contribution
                     if let Tool::FsWrite(w) = &tool.tool {
+                        if let Some(registration) = &self.agent_registration {
+                            registration.bridge().notify(SessionEvent::EditApplied {
+                                path: w.path(os),
+                                tool_use_id: tool.id.clone(),
+                            });
+                        }
                         let sanitized_path_str = w.path(os).to_string_lossy().to_string();
                         let conversation_id = self.conversation.conversation_id().to_string();
                         let message_id = self.conversation.message_id().map(|s| s.to_string());
@@ -2460,12 +3330,19 @@ impl ChatSession {
                                 .ok();
 
                             tracker.prev_fswrite_lines = tracker.after_fswrite_lines;
+
+                            let lines = std::mem::take(&mut tracker.lines_written_by_agent);
+                            crate::cli::provenance::record(os, &self.conversation, &w.path(os), lines, &tool.id).await;
                         }
                     }
 
+                    let mut content = vec![result.into()];
+                    if let Some(review) = self.hunk_reviews.remove(&tool.id) {
+                        content.push(ToolUseResultBlock::Text(review));
+                    }
                     tool_results.push(ToolUseResult {
                         tool_use_id: tool.id.clone(),
-                        content: vec![result.into()],
+                        content,
                         status: ToolResultStatus::Success,
                     });
                 },
@@ -2477,7 +3354,7 @@ impl ChatSession {
                         style::Print("\n"),
                         style::SetAttribute(Attribute::Bold),
                         style::SetForegroundColor(Color::Red),
-                        style::Print(format!(" ● Execution failed after {}s:\n", tool_time)),
+                        style::Print(format!(" ● Execution failed after {}:\n", tool_time)),
                         style::SetAttribute(Attribute::Reset),
                         style::SetForegroundColor(Color::Red),
                         style::Print(&err),
@@ -2510,7 +3387,7 @@ impl ChatSession {
 
         // Run PostToolUse hooks for all executed tools after we have the tool_results
         if let Some(cm) = self.conversation.context_manager.as_mut() {
-            for result in &tool_results {
+            for result in &mut tool_results {
                 if let Some(tool) = self.tool_uses.iter().find(|t| t.id == result.tool_use_id) {
                     let content: Vec<serde_json::Value> = result
                         .content
@@ -2536,10 +3413,11 @@ impl ChatSession {
                     };
 
                     // Here is how we handle postToolUse output:
-                    // Exit code is 0: nothing. stdout is not shown to user. We don't support processing the PostToolUse
-                    // hook output yet. Exit code is non-zero: display an error to user (already
-                    // taken care of by the ContextManager.run_hooks)
-                    let _ = cm
+                    // Exit code is 0: stdout is not shown to user, and is added to the tool result for the model.
+                    // Exit code is 2: stderr is added to the tool result for the model. The tool already ran.
+                    // Exit code is non-zero: display an error to user (already taken care of by the
+                    // ContextManager.run_hooks)
+                    if let Ok(hook_results) = cm
                         .run_hooks(
                             crate::cli::agent::hook::HookTrigger::PostToolUse,
                             &mut std::io::stderr(),
@@ -2547,11 +3425,20 @@ impl ChatSession {
                             None,
                             Some(tool_context),
                         )
-                        .await;
+                        .await
+                    {
+                        if let Some(context) = cli::hooks::post_tool_use_context(&hook_results) {
+                            result.content.push(ToolUseResultBlock::Text(context));
+                        }
+                    }
                 }
             }
         }
 
+        for result in &tool_results {
+            self.emit_json(JsonEvent::tool_result(result))?;
+        }
+
         if !image_blocks.is_empty() {
             let images = image_blocks.into_iter().map(|(block, _)| block).collect();
             self.conversation.add_tool_results_with_images(tool_results, images);
@@ -2594,34 +3481,39 @@ impl ChatSession {
         state: crate::api_client::model::ConversationState,
         request_metadata_lock: Arc<Mutex<Option<RequestMetadata>>>,
     ) -> Result<ChatState, ChatError> {
-        let mut rx = self.send_message(os, state, request_metadata_lock, None).await?;
+        // Kept to send the request again if the response breaks off.
+        let conversation_state = state.clone();
+        let mut rx = match self
+            .send_conversation(os, state, Arc::clone(&request_metadata_lock))
+            .await?
+        {
+            SendOutcome::Stream(rx) => rx,
+            SendOutcome::Compact(state) => return Ok(state),
+        };
 
         let request_id = rx.request_id().map(String::from);
 
         let mut buf = String::new();
         let mut offset = 0;
         let mut ended = false;
-        let terminal_width = match self.wrap {
-            Some(WrapMode::Never) => None,
-            Some(WrapMode::Always) => Some(self.terminal_width()),
-            Some(WrapMode::Auto) | None => {
-                if std::io::stdout().is_terminal() {
-                    Some(self.terminal_width())
-                } else {
-                    None
-                }
+        let mut state = self.parse_state(os);
+        let mut response_prefix_printed = false;
+        let mut reasoning = ReasoningTrace::new(
+            match self.interactive {
+                true => ReasoningDisplay::from_settings(os),
+                false => ReasoningDisplay::Hidden,
             },
-        };
-
-        let mut state = ParseState::new(
-            terminal_width,
-            os.database.settings.get_bool(Setting::ChatDisableMarkdownRendering),
+            self.terminal_width(),
         );
-        let mut response_prefix_printed = false;
 
         let mut tool_uses = Vec::new();
         let mut tool_name_being_recvd: Option<String> = None;
 
+        // The assistant text shown so far, and how the text of a retried response is stitched to it.
+        let mut shown_text = String::new();
+        let mut retries = 0;
+        let mut stitcher: Option<Stitcher> = None;
+
         if self.spinner.is_some() {
             drop(self.spinner.take());
             queue!(
@@ -2639,12 +3531,54 @@ impl ChatSession {
                     trace!("Consumed: {:?}", msg_event);
                     match msg_event {
                         parser::ResponseEvent::ToolUseStart { name } => {
+                            reasoning.finish(&mut self.stderr)?;
                             // We need to flush the buffer here, otherwise text will not be
                             // printed while we are receiving tool use events.
                             buf.push('\n');
                             tool_name_being_recvd = Some(name);
                         },
+                        parser::ResponseEvent::Reasoning(text) => {
+                            self.emit_json(JsonEvent::ReasoningDelta { text: &text })?;
+                            if reasoning.is_visible() {
+                                if self.spinner.is_some() {
+                                    drop(self.spinner.take());
+                                    queue!(
+                                        self.stderr,
+                                        terminal::Clear(terminal::ClearType::CurrentLine),
+                                        cursor::MoveToColumn(0),
+                                        cursor::Show
+                                    )?;
+                                }
+                            } else if self.spinner.is_none() && self.interactive {
+                                queue!(self.stderr, cursor::Hide)?;
+                                self.spinner = Some(Spinner::new(Spinners::Dots, "Thinking...".to_string()));
+                            }
+                            reasoning.push(&mut self.stderr, &text)?;
+                        },
                         parser::ResponseEvent::AssistantText(text) => {
+                            let text = match stitcher.as_mut().map(|stitcher| stitcher.push(&text)) {
+                                None => text,
+                                Some(Stitched::Append(text)) => text,
+                                Some(Stitched::Restart(text)) => {
+                                    // The retried response doesn't continue the one shown, so show it again in full.
+                                    queue!(
+                                        self.stdout,
+                                        style::SetForegroundColor(Color::DarkGrey),
+                                        style::Print("\n\n(The response was interrupted, here it is again.)\n\n"),
+                                        style::SetForegroundColor(Color::Reset),
+                                    )?;
+                                    buf.clear();
+                                    offset = 0;
+                                    state = self.parse_state(os);
+                                    response_prefix_printed = false;
+                                    shown_text.clear();
+                                    text
+                                },
+                            };
+                            shown_text.push_str(&text);
+                            if !text.trim().is_empty() {
+                                reasoning.finish(&mut self.stderr)?;
+                            }
                             // Add Q response prefix before the first assistant text.
                             if !response_prefix_printed && !text.trim().is_empty() {
                                 queue!(
@@ -2655,6 +3589,7 @@ impl ChatSession {
                                 )?;
                                 response_prefix_printed = true;
                             }
+                            self.emit_json(JsonEvent::TextDelta { text: &text })?;
                             buf.push_str(&text);
                         },
                         parser::ResponseEvent::ToolUse(tool_use) => {
@@ -2667,6 +3602,11 @@ impl ChatSession {
                                     cursor::Show
                                 )?;
                             }
+                            self.emit_json(JsonEvent::ToolRequest {
+                                id: &tool_use.id,
+                                name: &tool_use.name,
+                                input: &tool_use.args,
+                            })?;
                             tool_uses.push(tool_use);
                             tool_name_being_recvd = None;
                         },
@@ -2674,11 +3614,23 @@ impl ChatSession {
                             message,
                             request_metadata: rm,
                         } => {
+                            reasoning.finish(&mut self.stderr)?;
                             // This log is attempting to help debug instances where users encounter
                             // the response timeout message.
                             if message.content() == RESPONSE_TIMEOUT_CONTENT {
                                 error!(?request_id, ?message, "Encountered an unexpected model response");
                             }
+                            self.emit_json(JsonEvent::Message {
+                                message_id: message.message_id(),
+                                text: message.content(),
+                                stop_reason: match message.tool_uses() {
+                                    Some(tool_uses) if !tool_uses.is_empty() => "tool_use",
+                                    _ => "end_turn",
+                                },
+                            })?;
+                            if let Some(token_usage) = &rm.token_usage {
+                                self.emit_json(JsonEvent::usage(rm.model_id.as_deref(), token_usage))?;
+                            }
                             self.conversation.push_assistant_message(os, message, Some(rm.clone()));
                             self.user_turn_request_metadata.push(rm);
                             ended = true;
@@ -2696,6 +3648,50 @@ impl ChatSession {
                     let status_code = recv_error.status_code();
 
                     match recv_error.source {
+                        // Only retried before any tool use was received, those would otherwise be
+                        // received again.
+                        RecvErrorKind::Client(ref err)
+                            if err.is_interrupted_stream()
+                                && retries < MAX_STREAM_RETRIES
+                                && tool_uses.is_empty()
+                                && tool_name_being_recvd.is_none() =>
+                        {
+                            self.send_chat_telemetry(
+                                os,
+                                TelemetryResult::Failed,
+                                Some(reason),
+                                Some(reason_desc),
+                                status_code,
+                                false, // We retry the request, so don't end the current turn yet.
+                            )
+                            .await;
+
+                            retries += 1;
+                            let delay = stream_retry::backoff(retries);
+                            warn!(
+                                recv_error.request_metadata.request_id,
+                                ?err,
+                                retries,
+                                "The response stream broke off, retrying in {}ms",
+                                delay.as_millis()
+                            );
+                            reasoning.finish(&mut self.stderr)?;
+                            if shown_text.is_empty() && self.interactive {
+                                execute!(self.stderr, cursor::Hide)?;
+                                self.spinner = Some(Spinner::new(Spinners::Dots, "Reconnecting...".to_string()));
+                            }
+                            tokio::time::sleep(delay).await;
+
+                            rx = match self
+                                .send_conversation(os, conversation_state.clone(), Arc::clone(&request_metadata_lock))
+                                .await?
+                            {
+                                SendOutcome::Stream(rx) => rx,
+                                SendOutcome::Compact(state) => return Ok(state),
+                            };
+                            stitcher = Some(Stitcher::new(shown_text.clone()));
+                            continue;
+                        },
                         RecvErrorKind::StreamTimeout { source, duration } => {
                             self.send_chat_telemetry(
                                 os,
@@ -2850,6 +3846,7 @@ impl ChatSession {
             // still left in the buffer. I'm not sure how this is intended to be handled.
             if ended {
                 buf.push('\n');
+                state.ended = true;
             }
 
             if tool_name_being_recvd.is_none() && !buf.is_empty() && self.spinner.is_some() {
@@ -2863,12 +3860,13 @@ impl ChatSession {
             }
 
             // Print the response for normal cases
+            let mut frame = Vec::new();
+            let mut frame_start = Instant::now();
             loop {
                 let input = Partial::new(&buf[offset..]);
-                match interpret_markdown(input, &mut self.stdout, &mut state) {
+                match interpret_markdown(input, &mut frame, &mut state) {
                     Ok(parsed) => {
                         offset += parsed.offset_from(&input);
-                        self.stdout.flush()?;
                         state.newline = state.set_newline;
                         state.set_newline = false;
                     },
@@ -2878,10 +3876,28 @@ impl ChatSession {
                     },
                 }
 
-                // TODO: We should buffer output based on how much we have to parse, not as a constant
+                if buf.len() - offset > RENDER_PACED_BACKLOG {
+                    if frame_start.elapsed() >= RENDER_FRAME {
+                        self.stdout.write_all(&frame)?;
+                        self.stdout.flush()?;
+                        frame.clear();
+                        frame_start = Instant::now();
+                        tokio::task::yield_now().await;
+                    }
+                    continue;
+                }
+
+                self.stdout.write_all(&frame)?;
+                self.stdout.flush()?;
+                frame.clear();
+                frame_start = Instant::now();
+
+                // Short backlogs are paced so that the response reads as it's typed.
                 // Do not remove unless you are nabochay :)
                 tokio::time::sleep(Duration::from_millis(8)).await;
             }
+            self.stdout.write_all(&frame)?;
+            self.stdout.flush()?;
 
             // Set spinner after showing all of the assistant text content so far.
             if tool_name_being_recvd.is_some() {
@@ -2892,14 +3908,8 @@ impl ChatSession {
             }
 
             if ended {
-                if os
-                    .database
-                    .settings
-                    .get_bool(Setting::ChatEnableNotifications)
-                    .unwrap_or(false)
-                {
-                    // For final responses (no tools suggested), always play the bell
-                    play_notification_bell(tool_uses.is_empty());
+                if tool_uses.is_empty() && self.interactive {
+                    self.notifier.turn_finished(os);
                 }
 
                 queue!(self.stderr, style::ResetColor, style::SetAttribute(Attribute::Reset))?;
@@ -3023,7 +4033,12 @@ impl ChatSession {
             .set_tool_use_id(tool_use_id.clone())
             .set_tool_name(tool_use.name.clone())
             .utterance_id(self.conversation.message_id().map(|s| s.to_string()));
-            match self.conversation.tool_manager.get_tool_from_tool_use(tool_use).await {
+            match self
+                .conversation
+                .tool_manager
+                .get_tool_from_tool_use(os, tool_use)
+                .await
+            {
                 Ok(mut tool) => {
                     // Apply non-Q-generated context to tools
                     self.contextualize_tool(&mut tool);
@@ -3089,6 +4104,9 @@ impl ChatSession {
                 }
             }
 
+            for result in &tool_results {
+                self.emit_json(JsonEvent::tool_result(result))?;
+            }
             self.conversation.add_tool_results(tool_results);
             self.send_chat_telemetry(os, TelemetryResult::Succeeded, None, None, None, false)
                 .await;
@@ -3169,6 +4187,7 @@ impl ChatSession {
                         )?;
                     }
                 }
+                self.emit_json(JsonEvent::tool_result(tool_result))?;
             }
 
             self.conversation.add_tool_results(tool_results);
@@ -3187,7 +4206,7 @@ impl ChatSession {
     }
 
     async fn retry_model_overload(&mut self, os: &mut Os) -> Result<ChatState, ChatError> {
-        os.client.invalidate_model_cache().await;
+        invalidate_model_catalog(os).await;
         match select_model(os, self).await {
             Ok(Some(_)) => (),
             Ok(None) => {
@@ -3219,7 +4238,7 @@ impl ChatSession {
     // output from Amazon Q.
     // TODO: Is there a better way?
     fn contextualize_tool(&self, tool: &mut Tool) {
-        if let Tool::GhIssue(gh_issue) = tool {
+        if let Tool::IssueTracker(IssueTracker::Report(gh_issue)) = tool {
             let allowed_tools = self
                 .conversation
                 .agents
@@ -3236,6 +4255,9 @@ impl ChatSession {
                 tool_permissions: allowed_tools,
             });
         }
+        if let Tool::UpdatePlan(update_plan) = tool {
+            update_plan.set_plan_id(self.conversation.conversation_id().to_string());
+        }
     }
 
     async fn print_tool_description(&mut self, os: &Os, tool_index: usize, trusted: bool) -> Result<(), ChatError> {
@@ -3279,6 +4301,63 @@ impl ChatSession {
         Ok(())
     }
 
+    /// Shows what an infrastructure change would do, before the user is asked to confirm it.
+    async fn print_infra_preview(&mut self, os: &Os, change: &InfraChange) -> Result<(), ChatError> {
+        queue!(
+            self.stdout,
+            style::Print("\n\n"),
+            style::SetForegroundColor(Color::Yellow),
+            style::SetAttribute(Attribute::Bold),
+            style::Print(format!(" ● Infrastructure change: {}", change.action)),
+            style::SetAttribute(Attribute::Reset),
+        )?;
+        if let Some(stack) = &change.stack {
+            queue!(self.stdout, style::Print(format!(" ({stack})")))?;
+        }
+        execute!(self.stdout, style::SetForegroundColor(Color::Reset), style::Print("\n"))?;
+
+        let spinner = self
+            .interactive
+            .then(|| Spinner::new(Spinners::Dots, "Previewing the change...".to_string()));
+        let preview = change.preview(os).await;
+        if let Some(spinner) = spinner {
+            drop(spinner);
+            queue!(
+                self.stdout,
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                cursor::MoveToColumn(0),
+                cursor::Show
+            )?;
+        }
+
+        match preview {
+            Ok(summary) => queue!(
+                self.stdout,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!(
+                    "{}\n",
+                    summary
+                        .lines()
+                        .map(|line| format!("   {line}"))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )),
+            )?,
+            Err(err) => queue!(
+                self.stdout,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!("   Could not preview the change: {err}\n")),
+            )?,
+        }
+        execute!(
+            self.stdout,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("   Infrastructure changes are confirmed every time, even for trusted tools.\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        Ok(())
+    }
+
     /// Helper function to read user input with a prompt and Ctrl+C handling
     fn read_user_input(&mut self, prompt: &str, exit_on_single_ctrl_c: bool) -> Option<String> {
         let mut ctrl_c = false;
@@ -3310,22 +4389,261 @@ impl ChatSession {
         }
     }
 
+    /// Shows the latest resource warning, if any, and asks whether to pause before running more
+    /// tools. Returns whether the user wants to pause.
+    fn offer_resource_pause(&mut self) -> Result<bool, ChatError> {
+        let Some(warning) = self.resource_monitor.take_warning() else {
+            return Ok(false);
+        };
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!("\n{warning}. See /stats for details.\n")),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        if !self.interactive {
+            return Ok(false);
+        }
+        let answer = self.read_user_input("Pause before running more tools? [y/N]: ", true);
+        Ok(answer.is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y")))
+    }
+
+    /// Checks the final answer against the `--contract`, if any, once the model is done. Answers
+    /// that don't match are sent back to the model until the retries run out.
+    fn check_contract(&mut self) -> Result<ChatState, ChatError> {
+        let Some(contract) = self.contract.as_mut() else {
+            return Ok(ChatState::Exit);
+        };
+        // The turn failed or was cut short, the outcome already says why.
+        if self.outcome != HeadlessOutcome::Success {
+            return Ok(ChatState::Exit);
+        }
+        let response = self
+            .conversation
+            .history()
+            .last()
+            .map(|entry| entry.response().to_string())
+            .unwrap_or_default();
+        let violations = match contract.validate(&response) {
+            Ok(value) => {
+                self.contract_output = Some(value);
+                return Ok(ChatState::Exit);
+            },
+            Err(violations) => violations,
+        };
+
+        let retry = contract.retry(&violations);
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::Yellow),
+            style::Print(format!(
+                "The answer doesn't match the contract{}:\n",
+                if retry.is_some() {
+                    ", asking the model to fix it"
+                } else {
+                    ""
+                }
+            )),
+            style::SetForegroundColor(Color::Reset),
+            style::Print(
+                violations
+                    .iter()
+                    .map(|violation| format!("  - {violation}\n"))
+                    .collect::<String>()
+            ),
+        )?;
+        match retry {
+            Some(input) => Ok(ChatState::HandleInput { input }),
+            None => {
+                self.outcome = HeadlessOutcome::ContractViolation;
+                Ok(ChatState::Exit)
+            },
+        }
+    }
+
+    /// Writes `event` to stdout when running with `--output-format json`.
+    fn emit_json(&mut self, event: JsonEvent<'_>) -> Result<(), ChatError> {
+        if let Some(events) = self.json_events.as_mut() {
+            events.emit(&event)?;
+        }
+        Ok(())
+    }
+
+    /// Markdown rendering state for printing a response.
+    fn parse_state(&self, os: &Os) -> ParseState {
+        let terminal_width = match self.wrap {
+            Some(WrapMode::Never) => None,
+            Some(WrapMode::Always) => Some(self.terminal_width()),
+            Some(WrapMode::Auto) | None => {
+                if std::io::stdout().is_terminal() {
+                    Some(self.terminal_width())
+                } else {
+                    None
+                }
+            },
+        };
+
+        let mut state = ParseState::new(
+            terminal_width,
+            os.database.settings.get_bool(Setting::ChatDisableMarkdownRendering),
+        );
+        if std::io::stdout().is_terminal() {
+            state.link_files_in = os.env.current_dir().ok();
+            state.code_highlighting = Some(match tools::supports_truecolor(os) {
+                true => CodeHighlighting::TrueColor,
+                false => CodeHighlighting::Ansi256,
+            });
+        }
+        state
+    }
+
+    /// Prepends the selections sent from an IDE since the last prompt to `user_input`.
+    fn attach_ide_selections(&mut self, user_input: String) -> Result<String, ChatError> {
+        let Some(registration) = &self.agent_registration else {
+            return Ok(user_input);
+        };
+        let selections = registration.bridge().take_selections();
+        if selections.is_empty() {
+            return Ok(user_input);
+        }
+
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(format!("Attached {} selection(s) from your editor\n", selections.len())),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+        Ok(attach_selections(&selections, &user_input))
+    }
+
+    /// When `prompt` nearly repeats an earlier prompt of the conversation, offers to print the
+    /// answer it got rather than asking again. Returns the next state if the user took the offer.
+    fn offer_previous_answer(&mut self, os: &Os, prompt: &str) -> Result<Option<ChatState>, ChatError> {
+        // Nobody is there to answer for prompts received over the control channel.
+        if !self.interactive
+            || self.control_reply.is_some()
+            || !os
+                .database
+                .settings
+                .get_bool(Setting::ChatDetectDuplicatePrompts)
+                .unwrap_or(true)
+        {
+            return Ok(None);
+        }
+        // The markdown parser only renders complete lines.
+        let Some(answer) = self
+            .conversation
+            .find_previous_answer(prompt)
+            .map(|answer| format!("{answer}\n"))
+        else {
+            return Ok(None);
+        };
+
+        execute!(
+            self.stderr,
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print(
+                "You asked nearly the same question earlier in this conversation. Show the previous answer instead of asking again? "
+            ),
+            style::Print("["),
+            style::SetForegroundColor(Color::Green),
+            style::Print("y"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("/"),
+            style::SetForegroundColor(Color::Green),
+            style::Print("n"),
+            style::SetForegroundColor(Color::DarkGrey),
+            style::Print("]:\n\n"),
+            style::SetForegroundColor(Color::Reset),
+        )?;
+
+        // Setting `exit_on_single_ctrl_c` for better ux: exit the confirmation dialog rather than the CLI
+        let choice = self
+            .read_user_input("> ".yellow().to_string().as_str(), true)
+            .unwrap_or_default();
+        if !["y", "Y"].contains(&choice.trim()) {
+            return Ok(None);
+        }
+
+        queue!(
+            self.stdout,
+            style::Print("\n"),
+            style::SetForegroundColor(Color::Green),
+            style::Print("> "),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        let mut state = self.parse_state(os);
+        state.ended = true;
+        let mut offset = 0;
+        loop {
+            let input = Partial::new(&answer[offset..]);
+            match interpret_markdown(input, &mut self.stdout, &mut state) {
+                Ok(parsed) => {
+                    offset += parsed.offset_from(&input);
+                    state.newline = state.set_newline;
+                    state.set_newline = false;
+                },
+                Err(err) => match err.into_inner() {
+                    Some(err) => return Err(ChatError::Custom(err.to_string().into())),
+                    None => break, // Data was incomplete
+                },
+            }
+        }
+        execute!(self.stdout, style::Print("\n\n"))?;
+
+        Ok(Some(ChatState::PromptUser {
+            skip_printing_tools: true,
+        }))
+    }
+
     /// Helper function to generate a prompt based on the current context
     async fn generate_tool_trust_prompt(&mut self, os: &Os) -> String {
         let profile = self.conversation.current_profile().map(|s| s.to_string());
         let all_trusted = self.all_tools_trusted();
         let tangent_mode = self.conversation.is_in_tangent_mode();
 
-        // Check if context usage indicator is enabled
-        let usage_percentage = if ExperimentManager::is_enabled(os, ExperimentName::ContextUsageIndicator) {
+        let segments = PromptSegment::from_settings(os);
+        let enabled = |segment: PromptSegment| match &segments {
+            Some(segments) => segments.contains(&segment),
+            None => match segment {
+                PromptSegment::Agent => true,
+                // Check if context usage indicator is enabled
+                PromptSegment::Tokens => ExperimentManager::is_enabled(os, ExperimentName::ContextUsageIndicator),
+                PromptSegment::Model | PromptSegment::Edits => false,
+            },
+        };
+
+        let profile = profile.filter(|_| enabled(PromptSegment::Agent));
+        let model = match &self.conversation.model_info {
+            Some(model_info) if enabled(PromptSegment::Model) => Some(model_info.short_name().to_string()),
+            _ => None,
+        };
+        let usage_percentage = if enabled(PromptSegment::Tokens) {
             use crate::cli::chat::cli::usage::get_total_usage_percentage;
             get_total_usage_percentage(self, os).await.ok()
         } else {
             None
         };
+        let edits = if enabled(PromptSegment::Edits) {
+            let paths = self
+                .conversation
+                .file_line_tracker
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>();
+            count_uncommitted_edits(os, &paths).await
+        } else {
+            None
+        };
 
-        let mut generated_prompt =
-            prompt::generate_prompt(profile.as_deref(), all_trusted, tangent_mode, usage_percentage);
+        let mut generated_prompt = prompt::generate_prompt(
+            profile.as_deref(),
+            all_trusted,
+            tangent_mode,
+            usage_percentage,
+            model.as_deref(),
+            edits,
+        );
 
         if ExperimentManager::is_enabled(os, ExperimentName::Delegate) && status_all_agents(os).await.is_ok() {
             generated_prompt = format!("{DELEGATE_NOTIFIER}\n{generated_prompt}");
@@ -3381,6 +4699,35 @@ impl ChatSession {
         Ok(())
     }
 
+    /// Shows the plan the model keeps for this conversation with the `update_plan` tool, if it
+    /// has steps left: the whole checklist, or one line on its progress.
+    async fn display_plan(&mut self, os: &Os, whole: bool) -> Result<(), ChatError> {
+        if !TodoList::is_enabled(os) {
+            return Ok(());
+        }
+        let Ok(plan) = TodoListState::load(os, self.conversation.conversation_id()).await else {
+            return Ok(());
+        };
+        let Some(status) = update_plan::status_line(&plan) else {
+            return Ok(());
+        };
+
+        if whole {
+            queue!(self.stderr, style::Print("\n"))?;
+            plan.display_list(&mut self.stderr)
+                .map_err(|e| ChatError::Custom(format!("Could not display the plan: {e}").into()))?;
+            execute!(self.stderr, style::Print("\n"))?;
+        } else {
+            execute!(
+                self.stderr,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("\n◔ {status}\n")),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        Ok(())
+    }
+
     /// Resets state associated with the active user turn.
     ///
     /// This should *always* be called whenever a new user prompt is sent to the backend. Note
@@ -3743,6 +5090,9 @@ mod tests {
             true,
             false,
             None,
+            ChatOutputFormat::Text,
+            None,
+            None,
         )
         .await
         .unwrap()
@@ -3753,6 +5103,53 @@ mod tests {
         assert_eq!(os.fs.read_to_string("/file.txt").await.unwrap(), "Hello, world!\n");
     }
 
+    #[tokio::test]
+    async fn test_flow_fallback_model() {
+        let mut os = Os::new().await.unwrap();
+        os.database
+            .settings
+            .set(Setting::ChatFallbackModels, serde_json::json!(["model-1"]))
+            .await
+            .unwrap();
+        os.client
+            .set_mock_output(serde_json::json!(["ModelOverloadedError", ["Hello"]]));
+
+        let agents = get_test_agents(&os).await;
+        let tool_manager = ToolManager::default();
+        let tool_config = serde_json::from_str::<HashMap<String, ToolSpec>>(include_str!("tools/tool_index.json"))
+            .expect("Tools failed to load");
+        let mut session = ChatSession::new(
+            &mut os,
+            std::io::stdout(),
+            std::io::stderr(),
+            "fake_conv_id",
+            agents,
+            None,
+            InputSource::new_mock(vec!["hi".to_string(), "exit".to_string()]),
+            false,
+            || Some(80),
+            tool_manager,
+            Some("model-0".to_string()),
+            tool_config,
+            true,
+            false,
+            None,
+            ChatOutputFormat::Text,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        session.spawn(&mut os).await.unwrap();
+
+        let model_id = session
+            .conversation
+            .model_info
+            .as_ref()
+            .map(|model| model.model_id.as_str());
+        assert_eq!(model_id, Some("model-1"));
+    }
+
     #[tokio::test]
     async fn test_flow_tool_permissions() {
         let mut os = Os::new().await.unwrap();
@@ -3886,6 +5283,9 @@ mod tests {
             true,
             false,
             None,
+            ChatOutputFormat::Text,
+            None,
+            None,
         )
         .await
         .unwrap()
@@ -3984,6 +5384,9 @@ mod tests {
             true,
             false,
             None,
+            ChatOutputFormat::Text,
+            None,
+            None,
         )
         .await
         .unwrap()
@@ -4060,6 +5463,9 @@ mod tests {
             true,
             false,
             None,
+            ChatOutputFormat::Text,
+            None,
+            None,
         )
         .await
         .unwrap()
@@ -4112,6 +5518,9 @@ mod tests {
             true,
             false,
             None,
+            ChatOutputFormat::Text,
+            None,
+            None,
         )
         .await
         .unwrap()
@@ -4221,6 +5630,9 @@ mod tests {
             true,
             false,
             None,
+            ChatOutputFormat::Text,
+            None,
+            None,
         )
         .await
         .unwrap()
@@ -4353,6 +5765,9 @@ mod tests {
             true,
             false,
             None,
+            ChatOutputFormat::Text,
+            None,
+            None,
         )
         .await
         .unwrap()
@@ -4388,10 +5803,10 @@ mod tests {
 async fn save_agent_config(os: &mut Os, config: &Agent, agent_name: &str, is_global: bool) -> Result<(), ChatError> {
     let config_dir = if is_global {
         directories::chat_global_agent_path(os)
-            .map_err(|e| ChatError::Custom(format!("Could not find global agent directory: {}", e).into()))?
+            .map_err(|e| CategorizedError::config_invalid(format!("Could not find global agent directory: {}", e)))?
     } else {
         directories::chat_local_agent_dir(os)
-            .map_err(|e| ChatError::Custom(format!("Could not find local agent directory: {}", e).into()))?
+            .map_err(|e| CategorizedError::config_invalid(format!("Could not find local agent directory: {}", e)))?
     };
 
     tokio::fs::create_dir_all(&config_dir)
@@ -4400,7 +5815,7 @@ async fn save_agent_config(os: &mut Os, config: &Agent, agent_name: &str, is_glo
 
     let config_file = config_dir.join(format!("{}.json", agent_name));
     let config_json = serde_json::to_string_pretty(config)
-        .map_err(|e| ChatError::Custom(format!("Failed to serialize agent config: {}", e).into()))?;
+        .map_err(|e| CategorizedError::config_invalid(format!("Failed to serialize agent config: {}", e)))?;
 
     tokio::fs::write(&config_file, config_json)
         .await
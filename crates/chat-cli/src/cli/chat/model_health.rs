@@ -0,0 +1,131 @@
+//! How the models used in a session fared, so that `/model` can show which ones are throttled or
+//! out of capacity, and failover can skip them.
+
+use std::collections::HashMap;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use crate::api_client::ApiClientError;
+use crate::util::time::format_duration;
+
+/// How long after a capacity error a model is still considered unhealthy.
+const UNHEALTHY_FOR: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Debug, Default)]
+struct ModelStats {
+    requests: u32,
+    errors: u32,
+    /// When the model last failed with a throttling or capacity error, and which.
+    last_capacity_error: Option<(Instant, &'static str)>,
+}
+
+#[derive(Debug, Default)]
+pub struct ModelHealth {
+    models: HashMap<String, ModelStats>,
+}
+
+/// Why the model can't take the request right now, if that is what `err` means, in which case
+/// another model may.
+pub fn capacity_error(err: &ApiClientError) -> Option<&'static str> {
+    match err {
+        ApiClientError::ModelOverloadedError { .. } => Some("out of capacity"),
+        ApiClientError::QuotaBreach { .. } => Some("throttled"),
+        _ => None,
+    }
+}
+
+impl ModelHealth {
+    pub fn record_success(&mut self, model_id: &str) {
+        self.models.entry(model_id.to_string()).or_default().requests += 1;
+    }
+
+    pub fn record_error(&mut self, model_id: &str, err: &ApiClientError) {
+        let stats = self.models.entry(model_id.to_string()).or_default();
+        stats.requests += 1;
+        stats.errors += 1;
+        if let Some(reason) = capacity_error(err) {
+            stats.last_capacity_error = Some((Instant::now(), reason));
+        }
+    }
+
+    /// Whether `model_id` had a capacity error recently.
+    pub fn is_unhealthy(&self, model_id: &str) -> bool {
+        self.models
+            .get(model_id)
+            .and_then(|stats| stats.last_capacity_error)
+            .is_some_and(|(at, _)| at.elapsed() < UNHEALTHY_FOR)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.models.is_empty()
+    }
+
+    /// How `model_id` fared, such as `2 of 9 requests failed, throttled 3m ago`, or `None` if it
+    /// wasn't used.
+    pub fn describe(&self, model_id: &str) -> Option<String> {
+        let stats = self.models.get(model_id)?;
+        let mut description = match stats.errors {
+            0 => format!("{} requests, no errors", stats.requests),
+            errors => format!("{errors} of {} requests failed", stats.requests),
+        };
+        if let Some((at, reason)) = stats.last_capacity_error {
+            description.push_str(&format!(", {reason} {} ago", format_duration(at.elapsed())));
+        }
+        Some(description)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn throttled() -> ApiClientError {
+        ApiClientError::QuotaBreach {
+            message: "quota has reached its limit",
+            status_code: Some(429),
+        }
+    }
+
+    #[test]
+    fn test_model_health() {
+        let mut health = ModelHealth::default();
+        assert!(health.is_empty());
+        assert_eq!(health.describe("claude-sonnet-4"), None);
+
+        health.record_success("claude-sonnet-4");
+        health.record_success("claude-sonnet-4");
+        assert_eq!(
+            health.describe("claude-sonnet-4").as_deref(),
+            Some("2 requests, no errors")
+        );
+        assert!(!health.is_unhealthy("claude-sonnet-4"));
+
+        health.record_error("claude-sonnet-4", &throttled());
+        assert!(health.is_unhealthy("claude-sonnet-4"));
+        assert!(
+            health
+                .describe("claude-sonnet-4")
+                .unwrap()
+                .starts_with("1 of 3 requests failed, throttled ")
+        );
+        assert!(!health.is_unhealthy("claude-3.7-sonnet"));
+    }
+
+    #[test]
+    fn test_capacity_error() {
+        assert_eq!(capacity_error(&throttled()), Some("throttled"));
+        assert_eq!(
+            capacity_error(&ApiClientError::ModelOverloadedError {
+                request_id: None,
+                status_code: Some(429),
+            }),
+            Some("out of capacity")
+        );
+        assert_eq!(
+            capacity_error(&ApiClientError::ContextWindowOverflow { status_code: Some(400) }),
+            None
+        );
+    }
+}
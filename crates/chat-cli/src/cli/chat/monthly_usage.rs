@@ -0,0 +1,331 @@
+use std::io::Write;
+
+use chrono::{
+    Datelike,
+    Utc,
+};
+use crossterm::style::{
+    Attribute,
+    Color,
+};
+use crossterm::{
+    queue,
+    style,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::warn;
+
+use crate::api_client::usage_limits::ReportedUsage;
+use crate::auth::builder_id::is_idc_user;
+use crate::database::Database;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// Requests per month included in the free tier. Assumed for Builder ID users when the limit is
+/// neither reported by the service nor configured with [Setting::ChatMonthlyRequestLimit].
+pub const FREE_TIER_MONTHLY_REQUEST_LIMIT: u64 = 50;
+
+/// Percentages of the monthly limit at which the user is warned, overridable with
+/// [Setting::ChatUsageAlertThresholds].
+const DEFAULT_ALERT_THRESHOLDS: &[u64] = &[80, 90, 100];
+
+/// Requests sent during the current month, persisted across sessions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyUsage {
+    /// The month the counts apply to, as `YYYY-MM`.
+    pub month: String,
+    /// Requests sent from this machine.
+    pub requests: u64,
+    /// Usage most recently reported by the service, which includes requests from other clients.
+    #[serde(default)]
+    pub reported: Option<ReportedUsage>,
+    /// Highest alert threshold already shown this month.
+    #[serde(default)]
+    pub alerted_percent: u64,
+    /// Requests used when the monthly limit was last reached. Kept across months and used as the
+    /// limit when it isn't otherwise known.
+    #[serde(default)]
+    pub observed_limit: Option<u64>,
+}
+
+impl MonthlyUsage {
+    /// Loads the usage of the current month.
+    pub fn load(database: &Database) -> Self {
+        let usage = match database.get_monthly_usage() {
+            Ok(usage) => usage.unwrap_or_default(),
+            Err(err) => {
+                warn!(?err, "failed to load the monthly usage");
+                Self::default()
+            },
+        };
+        usage.for_month(&current_month())
+    }
+
+    pub fn save(&self, database: &Database) {
+        if let Err(err) = database.set_monthly_usage(self) {
+            warn!(?err, "failed to save the monthly usage");
+        }
+    }
+
+    /// Returns `self` if it is for `month`, otherwise starts the counts over.
+    fn for_month(self, month: &str) -> Self {
+        if self.month == month {
+            return self;
+        }
+
+        Self {
+            month: month.to_string(),
+            observed_limit: self.observed_limit,
+            ..Default::default()
+        }
+    }
+
+    pub fn record_request(&mut self, reported: Option<ReportedUsage>) {
+        self.requests += 1;
+        if reported.is_some() {
+            self.reported = reported;
+        }
+    }
+
+    /// Requests used this month, including those reported by the service.
+    pub fn used(&self) -> u64 {
+        self.reported
+            .map_or(self.requests, |reported| reported.used.max(self.requests))
+    }
+
+    /// Returns the monthly request limit from, in order, [Setting::ChatMonthlyRequestLimit], the
+    /// service, or the last time the limit was reached. A limit of 0 means alerts are disabled.
+    pub fn known_limit(&self, database: &Database) -> Option<u64> {
+        database
+            .settings
+            .get_int(Setting::ChatMonthlyRequestLimit)
+            .map(|limit| limit.max(0) as u64)
+            .or(self.reported.map(|reported| reported.limit))
+            .or(self.observed_limit)
+    }
+
+    /// Returns the highest of `thresholds` crossed since the last alert and marks it as shown.
+    pub fn next_alert(&mut self, limit: u64, thresholds: &[u64]) -> Option<u64> {
+        if limit == 0 {
+            return None;
+        }
+
+        let percent = self.used() * 100 / limit;
+        let threshold = thresholds
+            .iter()
+            .copied()
+            .filter(|threshold| *threshold <= percent && *threshold > self.alerted_percent)
+            .max()?;
+        self.alerted_percent = threshold;
+        Some(threshold)
+    }
+}
+
+/// Counts a request towards the monthly usage, warning on `output` when the usage crosses one of
+/// the alert thresholds.
+pub async fn record_request(os: &Os, output: &mut impl Write) -> Result<(), std::io::Error> {
+    let mut usage = MonthlyUsage::load(&os.database);
+    usage.record_request(os.client.reported_usage());
+
+    let limit = resolve_limit(os, &usage).await;
+
+    if let Some(limit) = limit {
+        if usage.next_alert(limit, &alert_thresholds(&os.database)).is_some() {
+            print_alert(output, &usage, limit)?;
+        }
+    }
+
+    usage.save(&os.database);
+    Ok(())
+}
+
+/// Returns the monthly request limit, assuming the free tier for Builder ID users when it isn't
+/// otherwise known.
+async fn resolve_limit(os: &Os, usage: &MonthlyUsage) -> Option<u64> {
+    match usage.known_limit(&os.database) {
+        Some(limit) => Some(limit),
+        None => match is_idc_user(&os.database).await {
+            Ok(false) => Some(FREE_TIER_MONTHLY_REQUEST_LIMIT),
+            _ => None,
+        },
+    }
+}
+
+/// Remembers the current usage as the monthly limit after the service rejected a request for
+/// reaching it.
+pub fn record_limit_reached(database: &Database) {
+    let mut usage = MonthlyUsage::load(database);
+    if usage.used() > 0 {
+        usage.observed_limit = Some(usage.used());
+    }
+    usage.alerted_percent = usage.alerted_percent.max(100);
+    usage.save(database);
+}
+
+fn alert_thresholds(database: &Database) -> Vec<u64> {
+    match database.settings.get(Setting::ChatUsageAlertThresholds) {
+        Some(value) => serde_json::from_value(value.clone()).unwrap_or_else(|err| {
+            warn!(?err, "ignoring invalid {}", Setting::ChatUsageAlertThresholds.as_ref());
+            DEFAULT_ALERT_THRESHOLDS.to_vec()
+        }),
+        None => DEFAULT_ALERT_THRESHOLDS.to_vec(),
+    }
+}
+
+fn print_alert(output: &mut impl Write, usage: &MonthlyUsage, limit: u64) -> Result<(), std::io::Error> {
+    let used = usage.used();
+    let message = if used >= limit {
+        format!("You've used all {limit} requests included in your plan this month.")
+    } else {
+        format!(
+            "You've used {used} of {limit} monthly requests ({}%).",
+            used * 100 / limit
+        )
+    };
+
+    queue!(
+        output,
+        style::SetForegroundColor(Color::Yellow),
+        style::Print(format!("\n⚠️ {message}")),
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!(
+            " The limits reset on {}. Run /usage --monthly for details.\n",
+            reset_date()
+        )),
+        style::SetForegroundColor(Color::Reset),
+    )?;
+    output.flush()
+}
+
+/// Prints the requests used this month against the plan's limit.
+pub async fn print_monthly_usage(os: &Os, output: &mut impl Write) -> Result<(), std::io::Error> {
+    let usage = MonthlyUsage::load(&os.database);
+    let limit = resolve_limit(os, &usage).await;
+    let used = usage.used();
+
+    queue!(
+        output,
+        style::SetAttribute(Attribute::Bold),
+        style::Print(format!("\nMonthly requests ({})\n", usage.month)),
+        style::SetAttribute(Attribute::Reset),
+    )?;
+
+    match limit.filter(|limit| *limit > 0) {
+        Some(limit) => {
+            const BAR_WIDTH: u64 = 40;
+            let filled = (used.min(limit) * BAR_WIDTH / limit) as usize;
+            let color = match used * 100 / limit {
+                0..80 => Color::Green,
+                80..100 => Color::Yellow,
+                _ => Color::Red,
+            };
+            queue!(
+                output,
+                style::SetForegroundColor(color),
+                style::Print("█".repeat(filled)),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print("█".repeat(BAR_WIDTH as usize - filled)),
+                style::SetForegroundColor(Color::Reset),
+                style::Print(format!(" {used} of {limit} ({}%)\n", used * 100 / limit)),
+            )?;
+        },
+        None => {
+            queue!(output, style::Print(format!("{used} requests\n")))?;
+        },
+    }
+
+    queue!(
+        output,
+        style::SetForegroundColor(Color::DarkGrey),
+        style::Print(format!("Sent from this machine: {}\n", usage.requests)),
+        style::Print(format!("The limits reset on {}.\n", reset_date())),
+    )?;
+    if limit.is_none() {
+        queue!(
+            output,
+            style::Print(format!(
+                "Set your plan's limit to get usage alerts: q settings {} <number>\n",
+                Setting::ChatMonthlyRequestLimit.as_ref()
+            )),
+        )?;
+    }
+    queue!(output, style::SetForegroundColor(Color::Reset), style::Print("\n"))?;
+
+    Ok(())
+}
+
+fn current_month() -> String {
+    Utc::now().format("%Y-%m").to_string()
+}
+
+/// The date the monthly limits reset, as `MM/01`.
+fn reset_date() -> String {
+    format!("{:02}/01", Utc::now().month() % 12 + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_month_resets_counts() {
+        let usage = MonthlyUsage {
+            month: "2025-01".to_string(),
+            requests: 10,
+            reported: Some(ReportedUsage { used: 12, limit: 50 }),
+            alerted_percent: 80,
+            observed_limit: Some(50),
+        };
+
+        assert_eq!(usage.clone().for_month("2025-01"), usage);
+        assert_eq!(usage.for_month("2025-02"), MonthlyUsage {
+            month: "2025-02".to_string(),
+            observed_limit: Some(50),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_next_alert() {
+        let mut usage = MonthlyUsage::default();
+        let thresholds = [80, 90, 100];
+        for _ in 0..39 {
+            usage.record_request(None);
+        }
+        assert_eq!(usage.next_alert(50, &thresholds), None);
+
+        usage.record_request(None);
+        assert_eq!(usage.next_alert(50, &thresholds), Some(80));
+        assert_eq!(usage.next_alert(50, &thresholds), None);
+
+        // The service reports usage from other clients too, skipping straight past 90%.
+        usage.record_request(Some(ReportedUsage { used: 50, limit: 50 }));
+        assert_eq!(usage.used(), 50);
+        assert_eq!(usage.next_alert(50, &thresholds), Some(100));
+        assert_eq!(usage.next_alert(0, &thresholds), None);
+    }
+
+    #[tokio::test]
+    async fn test_known_limit() {
+        let mut database = Database::new().await.unwrap();
+        let mut usage = MonthlyUsage::default();
+        assert_eq!(usage.known_limit(&database), None);
+
+        usage.observed_limit = Some(50);
+        assert_eq!(usage.known_limit(&database), Some(50));
+
+        usage.reported = Some(ReportedUsage { used: 1, limit: 1000 });
+        assert_eq!(usage.known_limit(&database), Some(1000));
+
+        database
+            .settings
+            .set(Setting::ChatMonthlyRequestLimit, 0)
+            .await
+            .unwrap();
+        assert_eq!(usage.known_limit(&database), Some(0));
+    }
+}
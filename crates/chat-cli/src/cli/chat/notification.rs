@@ -0,0 +1,191 @@
+//! Notifying the user when the session needs them: a tool waiting for approval, or the end of a
+//! turn that took long enough for them to look away.
+
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use tracing::debug;
+
+use super::util::play_notification_bell;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::time::format_duration;
+
+const TITLE: &str = "Amazon Q";
+/// Turns shorter than this are over before anyone looks away, and aren't notified.
+const LONG_TURN: Duration = Duration::from_secs(10);
+
+/// The application user model id of Windows PowerShell. Windows drops toasts from an id that no
+/// installed shortcut registers, and the CLI doesn't install one, so toasts are shown as coming
+/// from PowerShell, which sends them.
+const POWERSHELL_AUMID: &str = r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+/// How the user is notified, from the `chat.notifications` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NotificationMode {
+    #[default]
+    Off,
+    /// The terminal bell.
+    Bell,
+    /// The terminal bell and a desktop notification.
+    Desktop,
+}
+
+impl NotificationMode {
+    pub fn from_settings(os: &Os) -> Self {
+        match os.database.settings.get_string(Setting::ChatNotifications).as_deref() {
+            Some("bell") => Self::Bell,
+            Some("desktop") => Self::Desktop,
+            Some(_) => Self::Off,
+            // Before chat.notifications, notifications could only be turned on.
+            None => match os.database.settings.get_bool(Setting::ChatEnableNotifications) {
+                Some(true) => Self::Bell,
+                _ => Self::Off,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Notifier {
+    turn_started_at: Option<Instant>,
+}
+
+impl Notifier {
+    pub fn start_turn(&mut self) {
+        self.turn_started_at = Some(Instant::now());
+    }
+
+    pub fn approval_needed(&self, os: &Os, tool_name: &str) {
+        notify(
+            NotificationMode::from_settings(os),
+            &format!("{tool_name} is waiting for your approval"),
+        );
+    }
+
+    /// Notifies the end of the turn, if it was a long one.
+    pub fn turn_finished(&mut self, os: &Os) {
+        let Some(elapsed) = self.turn_started_at.take().map(|start| start.elapsed()) else {
+            return;
+        };
+        if elapsed >= LONG_TURN {
+            notify(
+                NotificationMode::from_settings(os),
+                &format!("Finished responding after {}", format_duration(elapsed)),
+            );
+        }
+    }
+}
+
+fn notify(mode: NotificationMode, message: &str) {
+    if mode == NotificationMode::Off {
+        return;
+    }
+    play_notification_bell(true);
+    if mode != NotificationMode::Desktop {
+        return;
+    }
+
+    let Some((program, args)) = desktop_command(TITLE, message) else {
+        return;
+    };
+    // Notifications are best effort, and shouldn't hold up the session.
+    std::thread::spawn(move || {
+        if let Err(err) = std::process::Command::new(&program)
+            .args(&args)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+        {
+            debug!(?err, %program, "failed to send a desktop notification");
+        }
+    });
+}
+
+/// The command showing a desktop notification on this platform.
+fn desktop_command(title: &str, message: &str) -> Option<(String, Vec<String>)> {
+    if cfg!(target_os = "macos") {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+        Some(("osascript".to_string(), vec![
+            "-e".to_string(),
+            format!("display notification {} with title {}", quote(message), quote(title)),
+        ]))
+    } else if cfg!(windows) {
+        let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+        let script = format!(
+            "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+             $t = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+             $x = $t.GetElementsByTagName('text'); \
+             $x.Item(0).AppendChild($t.CreateTextNode({})) > $null; \
+             $x.Item(1).AppendChild($t.CreateTextNode({})) > $null; \
+             [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier({}).Show([Windows.UI.Notifications.ToastNotification]::new($t))",
+            quote(title),
+            quote(message),
+            quote(POWERSHELL_AUMID)
+        );
+        Some(("powershell".to_string(), vec![
+            "-NoProfile".to_string(),
+            "-Command".to_string(),
+            script,
+        ]))
+    } else if cfg!(target_os = "linux") {
+        Some(("notify-send".to_string(), vec![
+            "--app-name".to_string(),
+            title.to_string(),
+            title.to_string(),
+            message.to_string(),
+        ]))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_notification_mode() {
+        let mut os = Os::new().await.unwrap();
+        assert_eq!(NotificationMode::from_settings(&os), NotificationMode::Off);
+
+        os.database
+            .settings
+            .set(Setting::ChatEnableNotifications, true)
+            .await
+            .unwrap();
+        assert_eq!(NotificationMode::from_settings(&os), NotificationMode::Bell);
+
+        os.database
+            .settings
+            .set(Setting::ChatNotifications, "desktop")
+            .await
+            .unwrap();
+        assert_eq!(NotificationMode::from_settings(&os), NotificationMode::Desktop);
+
+        os.database
+            .settings
+            .set(Setting::ChatNotifications, "off")
+            .await
+            .unwrap();
+        assert_eq!(NotificationMode::from_settings(&os), NotificationMode::Off);
+    }
+
+    #[test]
+    fn test_desktop_command() {
+        let (program, args) = desktop_command("Amazon Q", "fs_write is \"waiting\"").unwrap();
+        if cfg!(target_os = "macos") {
+            assert_eq!(program, "osascript");
+            assert_eq!(
+                args[1],
+                "display notification \"fs_write is \\\"waiting\\\"\" with title \"Amazon Q\""
+            );
+        } else if cfg!(target_os = "linux") {
+            assert_eq!(program, "notify-send");
+            assert_eq!(args.last().unwrap(), "fs_write is \"waiting\"");
+        }
+    }
+}
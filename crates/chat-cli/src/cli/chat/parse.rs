@@ -1,4 +1,5 @@
 use std::io::Write;
+use std::path::PathBuf;
 
 use crossterm::style::{
     Attribute,
@@ -9,6 +10,7 @@ use crossterm::{
     Command,
     style,
 };
+use syntect::easy::HighlightLines;
 use unicode_width::{
     UnicodeWidthChar,
     UnicodeWidthStr,
@@ -45,6 +47,12 @@ use winnow::token::{
     take_while,
 };
 
+use super::file_reference::FileReference;
+use super::tools::fs_write::{
+    SYNTAX_SET,
+    THEME_SET,
+};
+
 const CODE_COLOR: Color = Color::Green;
 const HEADING_COLOR: Color = Color::Magenta;
 const BLOCKQUOTE_COLOR: Color = Color::DarkGrey;
@@ -53,6 +61,18 @@ const URL_LINK_COLOR: Color = Color::DarkGrey;
 
 const DEFAULT_RULE_WIDTH: usize = 40;
 
+/// Bullets of nested list items, by depth.
+const BULLETS: [&str; 3] = ["•", "◦", "▪"];
+/// Columns a tab indents a list item by.
+const TAB_WIDTH: usize = 4;
+/// Narrowest a table column is shrunk to when the table doesn't fit the terminal.
+const MIN_COLUMN_WIDTH: usize = 3;
+/// Most rows of a table held back to size its columns. The rest are printed as they come, sized
+/// like the first ones, so that a long table isn't parsed again for every chunk of the response.
+const MAX_BUFFERED_TABLE_ROWS: usize = 50;
+
+const CODE_THEME: &str = "base16-ocean.dark";
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error<'a> {
     #[error(transparent)]
@@ -92,6 +112,20 @@ pub struct ParseState {
     pub set_newline: bool,
     pub newline: bool,
     pub citations: Vec<(String, String)>,
+    /// When set, inline code citing an existing file under this directory is printed as a link
+    /// to it.
+    pub link_files_in: Option<PathBuf>,
+    /// When set, code blocks tagged with a known language are syntax highlighted.
+    pub code_highlighting: Option<CodeHighlighting>,
+    /// Whether the whole response has been received, so that a table at its end is printed
+    /// without waiting for the line after it.
+    pub ended: bool,
+    /// Columns that lines wrapped inside a list item are indented by.
+    indent: usize,
+    /// Highlighter for the code block being printed, if it is highlighted.
+    highlighter: Option<CodeHighlighter>,
+    /// Layout of the table being printed, once it had too many rows to wait for the rest.
+    table: Option<TableLayout>,
 }
 
 impl ParseState {
@@ -108,10 +142,68 @@ impl ParseState {
             set_newline: false,
             newline: true,
             citations: vec![],
+            link_files_in: None,
+            code_highlighting: None,
+            ended: false,
+            indent: 0,
+            highlighter: None,
+            table: None,
+        }
+    }
+}
+
+/// The colors code blocks are highlighted with, depending on what the terminal supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeHighlighting {
+    TrueColor,
+    Ansi256,
+}
+
+/// Highlights a code block line by line, keeping the state of the syntax across lines.
+struct CodeHighlighter {
+    lines: HighlightLines<'static>,
+    colors: CodeHighlighting,
+}
+
+impl std::fmt::Debug for CodeHighlighter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CodeHighlighter").field("colors", &self.colors).finish()
+    }
+}
+
+impl CodeHighlighter {
+    /// A highlighter for the language of a code fence, e.g. `rust` or `py`.
+    fn for_language(language: &str, colors: CodeHighlighting) -> Option<Self> {
+        let token = language.split(|c: char| c.is_whitespace() || c == ',').next()?;
+        let syntax = SYNTAX_SET.find_syntax_by_token(token)?;
+        Some(Self {
+            lines: HighlightLines::new(syntax, THEME_SET.themes.get(CODE_THEME)?),
+            colors,
+        })
+    }
+
+    fn color(&self, color: syntect::highlighting::Color) -> Color {
+        match self.colors {
+            CodeHighlighting::TrueColor => Color::Rgb {
+                r: color.r,
+                g: color.g,
+                b: color.b,
+            },
+            CodeHighlighting::Ansi256 => Color::AnsiValue(ansi_256(color)),
         }
     }
 }
 
+/// The closest color of the 6x6x6 cube of 256 color terminals.
+fn ansi_256(color: syntect::highlighting::Color) -> u8 {
+    let level = |v: u8| match v {
+        0..48 => 0,
+        48..115 => 1,
+        _ => (v - 35) / 40,
+    };
+    16 + 36 * level(color.r) + 6 * level(color.g) + level(color.b)
+}
+
 pub fn interpret_markdown<'a, 'b>(
     mut i: Partial<&'a str>,
     mut o: impl Write + 'b,
@@ -146,18 +238,21 @@ pub fn interpret_markdown<'a, 'b>(
         },
         (false, false) => {
             stateful_alt!(
+                // Before text, which would otherwise take the number of the item
+                numbered_item,
                 // This pattern acts as a short circuit for alphanumeric plaintext
                 // More importantly, it's needed to support manual wordwrapping
                 text,
                 // multiline patterns
                 blockquote,
+                table_continued,
+                table,
                 // linted_codeblock,
                 codeblock_begin,
                 // single line patterns
                 horizontal_rule,
                 heading,
                 bulleted_item,
-                numbered_item,
                 // inline patterns
                 code,
                 citation,
@@ -177,6 +272,7 @@ pub fn interpret_markdown<'a, 'b>(
         },
         (true, false) => {
             stateful_alt!(
+                codeblock_highlighted_line,
                 codeblock_less_than,
                 codeblock_greater_than,
                 codeblock_ampersand,
@@ -244,9 +340,12 @@ fn bulleted_item<'a, 'b>(
         }
 
         let ws = (space0, alt(("-", "*")), space1).parse_next(i)?.0;
-        let print = format!("{ws}• ");
+        let ws = ws.replace('\t', &" ".repeat(TAB_WIDTH));
+        let bullet = BULLETS[(ws.len() / 2) % BULLETS.len()];
+        let print = format!("{ws}{bullet} ");
 
         queue_newline_or_advance(&mut o, state, print.width())?;
+        state.indent = print.width();
         queue(&mut o, style::Print(print))
     }
 }
@@ -261,9 +360,11 @@ fn numbered_item<'a, 'b>(
         }
 
         let (ws, digits, _, _) = (space0, digit1, ".", space1).parse_next(i)?;
+        let ws = ws.replace('\t', &" ".repeat(TAB_WIDTH));
         let print = format!("{ws}{digits}. ");
 
         queue_newline_or_advance(&mut o, state, print.width())?;
+        state.indent = print.width();
         queue(&mut o, style::Print(print))
     }
 }
@@ -291,6 +392,245 @@ fn horizontal_rule<'a, 'b>(
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug)]
+struct TableLayout {
+    widths: Vec<usize>,
+    alignments: Vec<Alignment>,
+}
+
+/// A GFM table. Tables are printed once complete, since the widths of their columns depend on
+/// every row, or once they have [MAX_BUFFERED_TABLE_ROWS] rows.
+fn table<'a, 'b>(
+    mut o: impl Write + 'b,
+    state: &'b mut ParseState,
+) -> impl FnMut(&mut Partial<&'a str>) -> PResult<(), Error<'a>> + 'b {
+    move |i| {
+        if !state.newline {
+            return Err(ErrMode::from_error_kind(i, ErrorKind::Fail));
+        }
+
+        let header = table_row(i)?;
+        let alignments = table_row(i)?
+            .iter()
+            .map(|cell| column_alignment(cell))
+            .collect::<Option<Vec<_>>>();
+        let alignments = match alignments {
+            Some(alignments) if alignments.len() == header.len() => alignments,
+            _ => return Err(ErrMode::from_error_kind(i, ErrorKind::Fail)),
+        };
+
+        let mut rows = Vec::new();
+        let mut complete = true;
+        loop {
+            if state.ended && i.eof_offset() == 0 {
+                break;
+            }
+            if rows.len() == MAX_BUFFERED_TABLE_ROWS {
+                complete = false;
+                break;
+            }
+            let checkpoint = i.checkpoint();
+            match table_row(i) {
+                Ok(row) => rows.push(row),
+                Err(ErrMode::Backtrack(_)) => {
+                    i.reset(&checkpoint);
+                    break;
+                },
+                Err(err) => return Err(err),
+            }
+        }
+
+        let widths = column_widths(&header, &rows, state.terminal_width);
+        queue_table_row(&mut o, &header, &widths, &alignments, true)?;
+        let separator = widths.iter().map(|width| "─".repeat(width + 2)).collect::<Vec<_>>();
+        queue(&mut o, style::SetForegroundColor(BLOCKQUOTE_COLOR))?;
+        queue(&mut o, style::Print(format!("├{}┤\n", separator.join("┼"))))?;
+        queue(&mut o, style::ResetColor)?;
+        for row in &rows {
+            queue_table_row(&mut o, row, &widths, &alignments, false)?;
+        }
+        if !complete {
+            state.table = Some(TableLayout { widths, alignments });
+        }
+
+        state.column = 0;
+        state.set_newline = true;
+        Ok(())
+    }
+}
+
+/// A row of a table printed before it was complete, laid out like the rows before it.
+fn table_continued<'a, 'b>(
+    mut o: impl Write + 'b,
+    state: &'b mut ParseState,
+) -> impl FnMut(&mut Partial<&'a str>) -> PResult<(), Error<'a>> + 'b {
+    move |i| {
+        if !state.newline || state.table.is_none() {
+            return Err(ErrMode::from_error_kind(i, ErrorKind::Fail));
+        }
+
+        let row = match table_row(i) {
+            Ok(row) => row,
+            Err(ErrMode::Backtrack(err)) => {
+                // The table ended.
+                state.table = None;
+                return Err(ErrMode::Backtrack(err));
+            },
+            Err(err) => return Err(err),
+        };
+        if let Some(layout) = &state.table {
+            queue_table_row(&mut o, &row, &layout.widths, &layout.alignments, false)?;
+        }
+
+        state.column = 0;
+        state.set_newline = true;
+        Ok(())
+    }
+}
+
+/// The cells of a line starting with `|`, with inline markup removed.
+fn table_row<'a>(i: &mut Partial<&'a str>) -> PResult<Vec<String>, Error<'a>> {
+    let (_, _, line, _) = (space0, "|", till_line_ending, ascii::line_ending).parse_next(i)?;
+    let line = line.trim_end().strip_suffix('|').unwrap_or(line.trim_end());
+
+    let mut cells = vec![String::new()];
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'|') => cells.last_mut().unwrap().push(chars.next().unwrap()),
+            '|' => cells.push(String::new()),
+            _ => cells.last_mut().unwrap().push(c),
+        }
+    }
+    Ok(cells
+        .into_iter()
+        .map(|cell| {
+            cell.trim()
+                .replace("**", "")
+                .replace('`', "")
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&amp;", "&")
+        })
+        .collect())
+}
+
+/// The alignment of a column from its cell in the delimiter row, like `:--:`.
+fn column_alignment(cell: &str) -> Option<Alignment> {
+    let dashes = cell.trim_start_matches(':').trim_end_matches(':');
+    if dashes.is_empty() || !dashes.chars().all(|c| c == '-') {
+        return None;
+    }
+    Some(match (cell.starts_with(':'), cell.ends_with(':')) {
+        (true, true) => Alignment::Center,
+        (false, true) => Alignment::Right,
+        _ => Alignment::Left,
+    })
+}
+
+/// The widths of the columns, with the widest shrunk until the table fits `terminal_width`.
+fn column_widths(header: &[String], rows: &[Vec<String>], terminal_width: Option<usize>) -> Vec<usize> {
+    let mut widths = header.iter().map(|cell| cell.width()).collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    if let Some(terminal_width) = terminal_width {
+        // Each column is padded with a space on both sides, and separated with a border.
+        let available = terminal_width.saturating_sub(3 * widths.len() + 1);
+        while widths.iter().sum::<usize>() > available {
+            let Some(widest) = widths.iter_mut().filter(|width| **width > MIN_COLUMN_WIDTH).max() else {
+                break;
+            };
+            *widest -= 1;
+        }
+    }
+    widths
+}
+
+fn queue_table_row<'a>(
+    mut o: impl Write,
+    row: &[String],
+    widths: &[usize],
+    alignments: &[Alignment],
+    header: bool,
+) -> Result<(), ErrMode<Error<'a>>> {
+    let cells = widths
+        .iter()
+        .enumerate()
+        .map(|(column, width)| wrap(row.get(column).map(String::as_str).unwrap_or_default(), *width))
+        .collect::<Vec<_>>();
+    let height = cells.iter().map(Vec::len).max().unwrap_or(1);
+
+    for line in 0..height {
+        for (column, width) in widths.iter().enumerate() {
+            let text = cells[column].get(line).map(String::as_str).unwrap_or_default();
+            let padding = width.saturating_sub(text.width());
+            let (before, after) = match alignments[column] {
+                Alignment::Left => (0, padding),
+                Alignment::Center => (padding / 2, padding - padding / 2),
+                Alignment::Right => (padding, 0),
+            };
+            queue(&mut o, style::SetForegroundColor(BLOCKQUOTE_COLOR))?;
+            queue(&mut o, style::Print(if column == 0 { "│ " } else { " │ " }))?;
+            queue(&mut o, style::ResetColor)?;
+            if header {
+                queue(&mut o, style::SetAttribute(Attribute::Bold))?;
+            }
+            queue(
+                &mut o,
+                style::Print(format!("{}{text}{}", " ".repeat(before), " ".repeat(after))),
+            )?;
+            queue(&mut o, style::SetAttribute(Attribute::Reset))?;
+        }
+        queue(&mut o, style::SetForegroundColor(BLOCKQUOTE_COLOR))?;
+        queue(&mut o, style::Print(" │\n"))?;
+        queue(&mut o, style::ResetColor)?;
+    }
+    Ok(())
+}
+
+/// `text` broken into lines of at most `width` columns, between words where possible.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    for word in text.split_whitespace() {
+        let line = lines.last_mut().unwrap();
+        let needed = if line.is_empty() {
+            word.width()
+        } else {
+            line.width() + 1 + word.width()
+        };
+        if needed <= width {
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(String::new());
+        }
+        for c in word.chars() {
+            let line = lines.last_mut().unwrap();
+            if line.width() + c.width().unwrap_or(0) > width && !line.is_empty() {
+                lines.push(String::new());
+            }
+            lines.last_mut().unwrap().push(c);
+        }
+    }
+    lines
+}
+
 fn code<'a, 'b>(
     mut o: impl Write + 'b,
     state: &'b mut ParseState,
@@ -302,7 +642,14 @@ fn code<'a, 'b>(
 
         queue_newline_or_advance(&mut o, state, out.width())?;
         queue(&mut o, style::SetForegroundColor(Color::Green))?;
-        queue(&mut o, style::Print(out))?;
+        let reference = state
+            .link_files_in
+            .as_deref()
+            .and_then(|cwd| FileReference::parse(&out, cwd));
+        match reference {
+            Some(reference) => queue(&mut o, style::Print(reference.hyperlink(&out)))?,
+            None => queue(&mut o, style::Print(out))?,
+        }
         queue(&mut o, style::ResetColor)
     }
 }
@@ -507,6 +854,7 @@ fn line_ending<'a, 'b>(
         ascii::line_ending.parse_next(i)?;
 
         state.column = 0;
+        state.indent = 0;
         state.set_newline = true;
 
         queue(&mut o, style::ResetColor)?;
@@ -523,7 +871,7 @@ fn fallback<'a, 'b>(
         let fallback = any.parse_next(i)?;
         if let Some(width) = fallback.width() {
             queue_newline_or_advance(&mut o, state, width)?;
-            if fallback != ' ' || state.column != 1 {
+            if fallback != ' ' || state.column != state.indent + 1 {
                 queue(&mut o, style::Print(fallback))?;
             }
         }
@@ -538,9 +886,11 @@ fn queue_newline_or_advance<'a, 'b>(
     width: usize,
 ) -> Result<(), ErrMode<Error<'a>>> {
     if let Some(terminal_width) = state.terminal_width {
-        if state.column > 0 && state.column + width > terminal_width {
-            state.column = width;
+        if state.column > state.indent && state.column + width > terminal_width {
+            // Lines wrapped inside a list item are aligned with its text.
+            state.column = state.indent + width;
             queue(&mut o, style::Print('\n'))?;
+            queue(&mut o, style::Print(" ".repeat(state.indent)))?;
             return Ok(());
         }
     }
@@ -572,6 +922,9 @@ fn codeblock_begin<'a, 'b>(
         ascii::line_ending.parse_next(i)?;
 
         state.in_codeblock = true;
+        state.highlighter = state
+            .code_highlighting
+            .and_then(|colors| CodeHighlighter::for_language(language.trim(), colors));
 
         if !language.is_empty() {
             queue(&mut o, style::Print(format!("{}\n", language).bold()))?;
@@ -590,10 +943,58 @@ fn codeblock_end<'a, 'b>(
     move |i| {
         "```".parse_next(i)?;
         state.in_codeblock = false;
+        state.highlighter = None;
         queue(&mut o, style::ResetColor)
     }
 }
 
+/// A whole line of a highlighted code block. Lines are highlighted once complete, since the
+/// syntax of a line depends on all of it.
+fn codeblock_highlighted_line<'a, 'b>(
+    mut o: impl Write + 'b,
+    state: &'b mut ParseState,
+) -> impl FnMut(&mut Partial<&'a str>) -> PResult<(), Error<'a>> + 'b {
+    move |i| {
+        let Some(highlighter) = state.highlighter.as_mut() else {
+            return Err(ErrMode::from_error_kind(i, ErrorKind::Fail));
+        };
+        let line = terminated(till_line_ending, ascii::line_ending).parse_next(i)?;
+        // The closing fence is left to codeblock_end.
+        if line.contains("```") {
+            return Err(ErrMode::from_error_kind(i, ErrorKind::Fail));
+        }
+
+        let line = format!(
+            "{}\n",
+            line.replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&amp;", "&")
+        );
+        let Ok(regions) = highlighter.lines.highlight_line(&line, &SYNTAX_SET) else {
+            return Err(ErrMode::from_error_kind(i, ErrorKind::Fail));
+        };
+        // Neighbouring regions often share a color, so the color is only set when it changes.
+        let mut current = CODE_COLOR;
+        for (style, text) in regions {
+            let text = text.trim_end_matches('\n');
+            if text.is_empty() {
+                continue;
+            }
+            let color = highlighter.color(style.foreground);
+            if color != current && !text.trim().is_empty() {
+                queue(&mut o, style::SetForegroundColor(color))?;
+                current = color;
+            }
+            queue(&mut o, style::Print(text))?;
+        }
+        if current != CODE_COLOR {
+            queue(&mut o, style::SetForegroundColor(CODE_COLOR))?;
+        }
+        queue(&mut o, style::Print("\n"))
+    }
+}
+
 fn codeblock_less_than<'a, 'b>(
     mut o: impl Write + 'b,
     _state: &'b mut ParseState,
@@ -829,4 +1230,119 @@ mod tests {
         [style::Print("+ % @ . ?")],
         true
     );
+
+    #[test]
+    fn test_highlighted_codeblock() {
+        let input = "```rust\nlet a = 1 &lt; 2;\n```\n";
+        let mut state = ParseState::new(Some(80), Some(false));
+        state.code_highlighting = Some(CodeHighlighting::TrueColor);
+        let mut output = vec![];
+        let mut offset = 0;
+        loop {
+            let partial = Partial::new(&input[offset..]);
+            let Ok(parsed) = interpret_markdown(partial, &mut output, &mut state) else {
+                break;
+            };
+            offset += parsed.offset_from(&partial);
+            state.newline = state.set_newline;
+            state.set_newline = false;
+        }
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("\x1b[38;2;"), "{output:?}");
+        assert!(output.contains("<"));
+        assert!(!state.in_codeblock);
+        // Whitespace doesn't switch colors back and forth.
+        let whitespace_only = regex::Regex::new("\x1b\\[38;2;[0-9;]*m\\s+\x1b").unwrap();
+        assert!(!whitespace_only.is_match(&output), "{output:?}");
+
+        // Lines are only highlighted once complete.
+        let mut state = ParseState::new(Some(80), Some(false));
+        state.code_highlighting = Some(CodeHighlighting::Ansi256);
+        let mut output = vec![];
+        let partial = Partial::new("```py\nprint(");
+        let parsed = interpret_markdown(partial, &mut output, &mut state).unwrap();
+        assert_eq!(parsed.offset_from(&partial), "```py\n".len());
+        assert!(interpret_markdown(parsed, &mut output, &mut state).is_err());
+    }
+
+    /// Renders `input` as a complete response, without styling.
+    fn render(input: &str, width: usize) -> String {
+        render_partial(input, width, true)
+    }
+
+    /// Renders as much of `input` as can be, without styling.
+    fn render_partial(input: &str, width: usize, ended: bool) -> String {
+        let mut state = ParseState::new(Some(width), Some(false));
+        state.ended = ended;
+        let mut output = vec![];
+        let mut offset = 0;
+        loop {
+            let partial = Partial::new(&input[offset..]);
+            let Ok(parsed) = interpret_markdown(partial, &mut output, &mut state) else {
+                break;
+            };
+            offset += parsed.offset_from(&partial);
+            state.newline = state.set_newline;
+            state.set_newline = false;
+        }
+        let output = String::from_utf8(output).unwrap();
+        let escape = regex::Regex::new("\x1b\\[[0-9;]*m").unwrap();
+        escape.replace_all(&output, "").to_string()
+    }
+
+    #[test]
+    fn test_table() {
+        let table = "| Name | Size |\n|:-----|-----:|\n| a.rs | 10 |\n| `b.rs` | 200 |\n";
+        assert_eq!(
+            render(table, 80),
+            "│ Name │ Size │\n├──────┼──────┤\n│ a.rs │   10 │\n│ b.rs │  200 │\n"
+        );
+
+        // Cells wrap when the table is wider than the terminal.
+        let table = "| Key | Description |\n|---|---|\n| x | the quick brown fox |\n";
+        assert_eq!(
+            render(table, 22),
+            "│ Key │ Description  │\n├─────┼──────────────┤\n│ x   │ the quick    │\n│     │ brown fox    │\n"
+        );
+
+        // Without a delimiter row, pipes are printed as they are.
+        assert_eq!(render("| not | a table |\nnext\n", 80), "| not | a table |\nnext\n");
+    }
+
+    #[test]
+    fn test_long_table() {
+        let mut table = "| n |\n|---|\n".to_string();
+        let mut expected = "│ n  │\n├────┤\n".to_string();
+        for n in 10..10 + MAX_BUFFERED_TABLE_ROWS {
+            table.push_str(&format!("| {n} |\n"));
+            expected.push_str(&format!("│ {n} │\n"));
+        }
+
+        // Once there are enough rows to size the columns, the table is printed without waiting
+        // for the rest of it.
+        assert_eq!(render_partial(&format!("{table}| 1"), 80, false), expected);
+
+        // Later rows are laid out like the first ones, until the table ends.
+        table.push_str("| 7 |\n| 1234 |\nnext\n");
+        expected.push_str("│ 7  │\n│ 12 │\n│ 34 │\nnext\n");
+        assert_eq!(render(&table, 80), expected);
+    }
+
+    #[test]
+    fn test_nested_lists() {
+        assert_eq!(
+            render("- one\n  - two\n    - three\n1. first\n   2. second\n", 80),
+            "• one\n  ◦ two\n    ▪ three\n1. first\n   2. second\n"
+        );
+        // Wrapped lines are aligned with the text of the item.
+        assert_eq!(render("  - aaaa bbbb cccc\n", 12), "  ◦ aaaa \n    bbbb \n    cccc\n");
+    }
+
+    #[test]
+    fn test_ansi_256() {
+        let color = |r, g, b| syntect::highlighting::Color { r, g, b, a: 0xff };
+        assert_eq!(ansi_256(color(0, 0, 0)), 16);
+        assert_eq!(ansi_256(color(255, 255, 255)), 231);
+        assert_eq!(ansi_256(color(255, 0, 0)), 196);
+    }
 }
@@ -32,6 +32,7 @@ use super::message::{
 use crate::api_client::model::{
     ChatResponseStream,
     ConversationState,
+    TokenUsage,
 };
 use crate::api_client::send_message_output::SendMessageOutput;
 use crate::api_client::{
@@ -194,6 +195,7 @@ impl SendMessageStream {
     /// * `request_metadata_lock` - a mutex that will be updated with metadata about the consumed
     ///   response stream on stream completion (ie, [ResponseEvent::EndStream] is returned) or on
     ///   drop.
+    /// * `cancel_token` - cancels consuming the response stream, along with dropping [Self]
     ///
     /// # Details
     ///
@@ -211,6 +213,7 @@ impl SendMessageStream {
         conversation_state: ConversationState,
         request_metadata_lock: Arc<Mutex<Option<RequestMetadata>>>,
         message_meta_tags: Option<Vec<MessageMetaTag>>,
+        cancel_token: CancellationToken,
     ) -> Result<Self, SendMessageError> {
         let message_id = uuid::Uuid::new_v4().to_string();
         info!(?message_id, "Generated new message id");
@@ -218,7 +221,6 @@ impl SendMessageStream {
         let model_id = conversation_state.user_input_message.model_id.clone();
         let message_meta_tags = message_meta_tags.unwrap_or_default();
 
-        let cancel_token = CancellationToken::new();
         let cancel_token_clone = cancel_token.clone();
 
         let start_time = Instant::now();
@@ -320,6 +322,8 @@ struct ResponseParser {
     request_start_time_sys: SystemTime,
     /// Total size (in bytes) of the response received so far.
     received_response_size: usize,
+    /// Token usage reported by the backend, if any.
+    token_usage: Option<TokenUsage>,
     time_to_first_chunk: Option<Duration>,
     time_between_chunks: Vec<Duration>,
 }
@@ -353,6 +357,7 @@ impl ResponseParser {
             request_start_time,
             request_start_time_sys,
             received_response_size: 0,
+            token_usage: None,
             time_to_first_chunk: None,
             time_between_chunks: Vec::new(),
             request_metadata,
@@ -417,6 +422,9 @@ impl ResponseParser {
                     ChatResponseStream::InvalidStateEvent { reason, message } => {
                         error!(%reason, %message, "invalid state event");
                     },
+                    ChatResponseStream::ReasoningContentEvent { text } if !text.is_empty() => {
+                        return Ok(ResponseEvent::Reasoning(text));
+                    },
                     ChatResponseStream::ToolUseEvent {
                         tool_use_id,
                         name,
@@ -614,6 +622,12 @@ impl ResponseParser {
                         ChatResponseStream::ToolUseEvent { input, .. } => {
                             self.received_response_size += input.as_ref().map(String::len).unwrap_or_default();
                         },
+                        ChatResponseStream::MetadataEvent { token_usage } => {
+                            if token_usage.is_some() {
+                                self.token_usage = *token_usage;
+                            }
+                        },
+                        ChatResponseStream::ReasoningContentEvent { .. } => {},
                         _ => {
                             warn!(?r, "received unexpected event from the response stream");
                         },
@@ -661,6 +675,7 @@ impl ResponseParser {
                 .map(|t| (t.id.clone(), t.name.clone()))
                 .collect::<_>(),
             model_id: self.model_id.clone(),
+            token_usage: self.token_usage,
         }
     }
 }
@@ -669,6 +684,8 @@ impl ResponseParser {
 pub enum ResponseEvent {
     /// Text returned by the assistant. This should be displayed to the user as it is received.
     AssistantText(String),
+    /// Reasoning of the model, streamed before its answer. It isn't part of the message.
+    Reasoning(String),
     /// Notification that a tool use is being received.
     ToolUseStart { name: String },
     /// A tool use requested by the assistant. This should be displayed to the user as it is
@@ -712,6 +729,9 @@ pub struct RequestMetadata {
     pub model_id: Option<String>,
     /// Meta tags for the request.
     pub message_meta_tags: Vec<MessageMetaTag>,
+    /// Token usage reported by the backend for the request.
+    #[serde(default)]
+    pub token_usage: Option<TokenUsage>,
 }
 
 fn system_time_to_unix_ms(time: SystemTime) -> u64 {
@@ -870,4 +890,37 @@ mod tests {
             "Expected to find tool validation error for non-object JSON"
         );
     }
+
+    #[tokio::test]
+    async fn test_response_parser_reasoning() {
+        let mut events = vec![
+            ChatResponseStream::ReasoningContentEvent {
+                text: "The user wants".to_string(),
+            },
+            ChatResponseStream::ReasoningContentEvent { text: String::new() },
+            ChatResponseStream::AssistantResponseEvent {
+                content: "hello".to_string(),
+            },
+        ];
+        events.reverse();
+        let mut parser = ResponseParser::new(
+            SendMessageOutput::Mock(events),
+            "".to_string(),
+            None,
+            1,
+            vec![],
+            mpsc::channel(32).0,
+            Instant::now(),
+            SystemTime::now(),
+            CancellationToken::new(),
+            Arc::new(Mutex::new(None)),
+        );
+
+        assert!(matches!(parser.recv().await.unwrap(), ResponseEvent::Reasoning(text) if text == "The user wants"));
+        assert!(matches!(parser.recv().await.unwrap(), ResponseEvent::AssistantText(text) if text == "hello"));
+        match parser.recv().await.unwrap() {
+            ResponseEvent::EndStream { message, .. } => assert_eq!(message.content(), "hello"),
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
 }
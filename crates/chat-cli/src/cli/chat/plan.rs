@@ -0,0 +1,112 @@
+//! Plan mode: tools that would change something are recorded instead of run, so that the whole
+//! plan can be reviewed and then applied at once with `/plan apply`.
+
+use super::tools::{
+    QueuedTool,
+    Tool,
+};
+
+/// A tool use recorded while planning.
+#[derive(Debug, Clone)]
+pub struct PlannedStep {
+    pub name: String,
+    pub tool: Tool,
+    pub tool_input: serde_json::Value,
+}
+
+#[derive(Debug, Default)]
+pub struct Plan {
+    /// Whether tools that would change something are recorded rather than run.
+    pub active: bool,
+    steps: Vec<PlannedStep>,
+}
+
+impl Plan {
+    /// Whether `tool` is recorded in the plan instead of being run.
+    pub fn intercepts(&self, tool: &Tool) -> bool {
+        self.active && tool.is_mutating()
+    }
+
+    /// Records `tool` as the next step, returning its 1-based number.
+    pub fn record(&mut self, tool: &QueuedTool) -> usize {
+        self.steps.push(PlannedStep {
+            name: tool.name.clone(),
+            tool: tool.tool.clone(),
+            tool_input: tool.tool_input.clone(),
+        });
+        self.steps.len()
+    }
+
+    pub fn steps(&self) -> &[PlannedStep] {
+        &self.steps
+    }
+
+    /// Removes the steps to apply them.
+    pub fn take_steps(&mut self) -> Vec<PlannedStep> {
+        std::mem::take(&mut self.steps)
+    }
+
+    /// Puts back steps that weren't applied, in front of any recorded since.
+    pub fn restore_steps(&mut self, mut steps: Vec<PlannedStep>) {
+        steps.append(&mut self.steps);
+        self.steps = steps;
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+    }
+}
+
+/// The tool result the model gets for a step recorded in the plan.
+pub fn recorded_result(step: usize) -> String {
+    format!(
+        "Plan mode is on, so this was not run. It was recorded as step {step} of a plan that the user will review \
+        and apply with /plan apply. Continue as if it succeeded, without trying to verify its effects."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::tools::execute::ExecuteCommand;
+
+    fn queued(command: &str) -> QueuedTool {
+        QueuedTool {
+            id: "tooluse_1".to_string(),
+            name: "execute_bash".to_string(),
+            accepted: false,
+            tool: Tool::ExecuteCommand(ExecuteCommand {
+                command: command.to_string(),
+                summary: None,
+                notice: None,
+            }),
+            tool_input: serde_json::json!({ "command": command }),
+        }
+    }
+
+    #[test]
+    fn test_plan_records_mutating_tools() {
+        let mut plan = Plan::default();
+        let write = queued("rm -rf target");
+        let read = queued("ls");
+        assert!(!plan.intercepts(&write.tool));
+
+        plan.active = true;
+        assert!(plan.intercepts(&write.tool));
+        assert!(!plan.intercepts(&read.tool));
+        assert_eq!(plan.record(&write), 1);
+        assert_eq!(plan.record(&queued("cargo build")), 2);
+
+        let mut steps = plan.take_steps();
+        assert!(plan.steps().is_empty());
+        plan.record(&queued("touch new"));
+        steps.remove(0);
+        plan.restore_steps(steps);
+        let commands = plan
+            .steps()
+            .iter()
+            .map(|step| step.tool_input["command"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(commands, vec!["cargo build", "touch new"]);
+    }
+}
@@ -40,6 +40,7 @@ use rustyline::{
 };
 use winnow::stream::AsChar;
 
+use super::mention;
 pub use super::prompt_parser::generate_prompt;
 use super::prompt_parser::parse_prompt_components;
 use super::tool_manager::{
@@ -59,7 +60,10 @@ pub const COMMANDS: &[&str] = &[
     "/help",
     "/editor",
     "/reply",
+    "/open",
+    "/open last",
     "/issue",
+    "/bad",
     "/quit",
     "/tools",
     "/tools trust",
@@ -67,6 +71,12 @@ pub const COMMANDS: &[&str] = &[
     "/tools trust-all",
     "/tools reset",
     "/mcp",
+    "/mcp list",
+    "/mcp add",
+    "/mcp remove",
+    "/mcp enable",
+    "/mcp disable",
+    "/mcp test",
     "/model",
     "/experiment",
     "/agent",
@@ -78,6 +88,7 @@ pub const COMMANDS: &[&str] = &[
     "/agent set",
     "/agent schema",
     "/agent generate",
+    "/agent spawn",
     "/prompts",
     "/context",
     "/context help",
@@ -88,6 +99,7 @@ pub const COMMANDS: &[&str] = &[
     "/context clear",
     "/hooks",
     "/hooks help",
+    "/hooks status",
     "/hooks add",
     "/hooks rm",
     "/hooks enable",
@@ -96,11 +108,25 @@ pub const COMMANDS: &[&str] = &[
     "/hooks disable-all",
     "/compact",
     "/compact help",
+    "/undo",
+    "/undo --force",
+    "/plan",
+    "/plan show",
+    "/plan apply",
+    "/plan clear",
+    "/cd",
+    "/commit",
+    "/commit --dry-run",
     "/usage",
+    "/usage --monthly",
+    "/cost",
+    "/stats",
+    "/debug level",
     "/changelog",
     "/save",
     "/load",
     "/subscribe",
+    "/tour",
 ];
 
 /// Generate dynamic command list including experiment-based commands when enabled
@@ -176,9 +202,25 @@ impl PromptCompleter {
     }
 
     fn complete_prompt(&self, word: &str) -> Result<Vec<String>, ReadlineError> {
+        self.search(PromptQuery::Search(if !word.is_empty() {
+            Some(word.to_string())
+        } else {
+            None
+        }))
+    }
+
+    /// Completes the URI of a resource offered by a server.
+    fn complete_resource(&self, word: &str) -> Result<Vec<String>, ReadlineError> {
+        self.search(PromptQuery::SearchResources(if !word.is_empty() {
+            Some(word.to_string())
+        } else {
+            None
+        }))
+    }
+
+    fn search(&self, query: PromptQuery) -> Result<Vec<String>, ReadlineError> {
         let sender = &self.sender;
         let receiver = self.receiver.borrow_mut();
-        let query = PromptQuery::Search(if !word.is_empty() { Some(word.to_string()) } else { None });
 
         sender
             .send(query)
@@ -212,7 +254,7 @@ impl PromptCompleter {
         };
         let matches = match query_res {
             PromptQueryResult::Search(list) => list.into_iter().map(|n| format!("@{n}")).collect::<Vec<_>>(),
-            PromptQueryResult::List(_) => {
+            PromptQueryResult::List(_) | PromptQueryResult::Resources(_) => {
                 return Err(ReadlineError::Io(std::io::Error::other(eyre::eyre!(
                     "Wrong query response type received",
                 ))));
@@ -268,6 +310,16 @@ impl Completer for ChatCompleter {
             }
         }
 
+        if word.starts_with('@') {
+            let mut completions = mention::complete(word);
+            if let Ok(resources) = self.prompt_completer.complete_resource(&word[1..]) {
+                completions.extend(resources);
+            }
+            if !completions.is_empty() {
+                return Ok((start, completions));
+            }
+        }
+
         // Handle file path completion as fallback
         if let Ok((pos, completions)) = self.path_completer.complete_path(line, pos, _ctx) {
             if !completions.is_empty() {
@@ -356,26 +408,43 @@ pub struct MultiLineValidator;
 
 impl Validator for MultiLineValidator {
     fn validate(&self, os: &mut ValidationContext<'_>) -> rustyline::Result<ValidationResult> {
-        let input = os.input();
-
-        // Check for code block markers
-        if input.contains("```") {
-            // Count the number of ``` occurrences
-            let triple_backtick_count = input.matches("```").count();
+        Ok(match is_incomplete(os.input()) {
+            true => ValidationResult::Incomplete,
+            false => ValidationResult::Valid(None),
+        })
+    }
+}
 
-            // If we have an odd number of ```, we're in an incomplete code block
-            if triple_backtick_count % 2 == 1 {
-                return Ok(ValidationResult::Incomplete);
-            }
+/// Whether Enter should continue `input` on a new line rather than submit it: inside a fenced
+/// code block that isn't closed yet, or after a trailing backslash.
+fn is_incomplete(input: &str) -> bool {
+    // The opening fence of the code block we're in, as its character and length.
+    let mut fence: Option<(char, usize)> = None;
+    for line in input.lines() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let line = line.trim_start_matches(' ');
+        let Some(marker) = line.chars().next().filter(|c| *c == '`' || *c == '~') else {
+            continue;
+        };
+        let len = line.chars().take_while(|c| *c == marker).count();
+        if indent > 3 || len < 3 {
+            continue;
         }
 
-        // Check for backslash continuation
-        if input.ends_with('\\') {
-            return Ok(ValidationResult::Incomplete);
+        match fence {
+            // Only a bare fence of the same kind, at least as long, closes the block.
+            Some((open, open_len)) => {
+                if marker == open && len >= open_len && line[len..].trim().is_empty() {
+                    fence = None;
+                }
+            },
+            // Backtick fences can't have backticks in their info string.
+            None if marker == '`' && line[len..].contains('`') => {},
+            None => fence = Some((marker, len)),
         }
-
-        Ok(ValidationResult::Valid(None))
     }
+
+    fence.is_some() || input.ends_with('\\')
 }
 
 #[derive(Helper, Completer, Hinter)]
@@ -405,11 +474,11 @@ impl Highlighter for ChatHelper {
     }
 
     fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
-        Cow::Borrowed(line)
+        mention::highlight(line)
     }
 
-    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
-        false
+    fn highlight_char(&self, line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        line.contains('@')
     }
 
     fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
@@ -430,6 +499,11 @@ impl Highlighter for ChatHelper {
                 result.push_str(&format!("[{}] ", profile).cyan().to_string());
             }
 
+            // Add model part if present (dark grey)
+            if let Some(model) = components.model {
+                result.push_str(&format!("({}) ", model).dark_grey().to_string());
+            }
+
             // Add percentage part if present (colored by usage level)
             if let Some(percentage) = components.usage_percentage {
                 let colored_percentage = if percentage < 50.0 {
@@ -442,6 +516,16 @@ impl Highlighter for ChatHelper {
                 result.push_str(&colored_percentage.to_string());
             }
 
+            // Add uncommitted edits part if present (yellow once there is anything to commit)
+            if let Some(edits) = components.edits {
+                let edits_part = format!("✎{} ", edits);
+                if edits == 0 {
+                    result.push_str(&edits_part.dark_grey().to_string());
+                } else {
+                    result.push_str(&edits_part.yellow().to_string());
+                }
+            }
+
             // Add tangent indicator if present (yellow)
             if components.tangent_mode {
                 result.push_str(&"↯ ".yellow().to_string());
@@ -468,14 +552,17 @@ pub fn rl(
     sender: PromptQuerySender,
     receiver: PromptQueryResponseReceiver,
 ) -> Result<Editor<ChatHelper, FileHistory>> {
-    let edit_mode = match os.database.settings.get_string(Setting::ChatEditMode).as_deref() {
-        Some("vi" | "vim") => EditMode::Vi,
+    let edit_mode = match os.database.settings.get_string(Setting::ChatEditMode) {
+        Some(mode) if mode.eq_ignore_ascii_case("vi") || mode.eq_ignore_ascii_case("vim") => EditMode::Vi,
         _ => EditMode::Emacs,
     };
     let config = Config::builder()
         .history_ignore_space(true)
         .completion_type(CompletionType::List)
         .edit_mode(edit_mode)
+        // Pasted text is inserted as is rather than submitted at its first newline, in terminals
+        // that support bracketed paste.
+        .bracketed_paste(true)
         .build();
 
     let history_hints_enabled = os
@@ -855,6 +942,21 @@ mod tests {
         assert_eq!(hint, None);
     }
 
+    #[test]
+    fn test_multi_line_input_is_incomplete() {
+        assert!(!is_incomplete("hello"));
+        assert!(is_incomplete("hello \\"));
+        assert!(is_incomplete("```rust\nfn main() {}"));
+        assert!(!is_incomplete("```rust\nfn main() {}\n```"));
+        // Inline backticks don't open a block.
+        assert!(!is_incomplete("what does ``` mean in markdown?"));
+        assert!(!is_incomplete("```ls``` fails, why?"));
+        // A shorter fence or one of the other kind doesn't close the block.
+        assert!(is_incomplete("````\n```\n"));
+        assert!(is_incomplete("~~~\n```\n"));
+        assert!(!is_incomplete("~~~\n```\n~~~"));
+    }
+
     #[tokio::test]
     // If you get a unit test failure for key override, please consider using a new key binding instead.
     // The list of reserved keybindings here are the standard in UNIX world so please don't take them
@@ -1,10 +1,88 @@
+use std::process::Stdio;
+
+use tracing::warn;
+
 use crate::cli::agent::DEFAULT_AGENT_NAME;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// A piece of information shown before the prompt, configured with the `chat.prompt.segments`
+/// setting. Segments are always shown in the order of this enum, whatever the order of the
+/// setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptSegment {
+    /// The active agent, unless it is the default one.
+    Agent,
+    /// The short name of the model.
+    Model,
+    /// The percentage of the context window in use.
+    Tokens,
+    /// The number of files edited by the agent that have uncommitted changes.
+    Edits,
+}
+
+impl PromptSegment {
+    /// The configured segments, or `None` if the setting is not set, in which case the agent is
+    /// shown along with the context usage when the context usage indicator experiment is enabled.
+    pub fn from_settings(os: &Os) -> Option<Vec<Self>> {
+        let value = os.database.settings.get(Setting::ChatPromptSegments)?;
+        match serde_json::from_value(value.clone()) {
+            Ok(segments) => Some(segments),
+            Err(err) => {
+                warn!(?err, "ignoring invalid {}", Setting::ChatPromptSegments.as_ref());
+                None
+            },
+        }
+    }
+}
+
+/// Counts the files edited by the agent that have uncommitted changes. `None` if git can't tell,
+/// e.g. outside of a git repository.
+pub async fn count_uncommitted_edits(os: &Os, paths: &[&str]) -> Option<usize> {
+    if paths.is_empty() {
+        return Some(0);
+    }
+
+    let output = tokio::process::Command::new("git")
+        .arg("status")
+        .arg("--porcelain")
+        .arg("-z")
+        .arg("--")
+        .args(paths)
+        .current_dir(os.env.current_dir().unwrap_or_default())
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await;
+
+    match output {
+        Ok(output) if output.status.success() => Some(count_status_entries(&output.stdout)),
+        _ => None,
+    }
+}
+
+/// Counts the files in the output of `git status --porcelain -z`, where a rename or copy is
+/// followed by the path it was made from.
+fn count_status_entries(status: &[u8]) -> usize {
+    let mut entries = status.split(|byte| *byte == 0).filter(|entry| !entry.is_empty());
+    let mut count = 0;
+    while let Some(entry) = entries.next() {
+        if entry.first().is_some_and(|x| matches!(x, b'R' | b'C')) {
+            entries.next();
+        }
+        count += 1;
+    }
+    count
+}
 
 /// Components extracted from a prompt string
 #[derive(Debug, PartialEq)]
 pub struct PromptComponents {
     pub delegate_notifier: Option<String>,
     pub profile: Option<String>,
+    pub model: Option<String>,
+    pub edits: Option<usize>,
     pub warning: bool,
     pub tangent_mode: bool,
     pub usage_percentage: Option<f32>,
@@ -12,9 +90,11 @@ pub struct PromptComponents {
 
 /// Parse prompt components from a plain text prompt
 pub fn parse_prompt_components(prompt: &str) -> Option<PromptComponents> {
-    // Expected format: "[agent] 6% !> " or "> " or "!> " or "[agent] ↯ > " or "6% ↯ > " etc.
+    // Expected format: "[agent] (model) 6% ✎2 !> " or "> " or "!> " or "[agent] ↯ > " or "6% ↯ > " etc.
     let mut delegate_notifier = None::<String>;
     let mut profile = None;
+    let mut model = None;
+    let mut edits = None;
     let mut warning = false;
     let mut tangent_mode = false;
     let mut usage_percentage = None;
@@ -45,6 +125,14 @@ pub fn parse_prompt_components(prompt: &str) -> Option<PromptComponents> {
         }
     }
 
+    // Check for model pattern (model)
+    if let Some(after_paren) = remaining.strip_prefix('(') {
+        if let Some(end) = after_paren.find(')') {
+            model = Some(after_paren[..end].to_string());
+            remaining = after_paren[end + 1..].trim_start();
+        }
+    }
+
     // Check for percentage pattern (e.g., "6% ")
     if let Some(percent_pos) = remaining.find('%') {
         let before_percent = &remaining[..percent_pos];
@@ -56,6 +144,15 @@ pub fn parse_prompt_components(prompt: &str) -> Option<PromptComponents> {
         }
     }
 
+    // Check for edits pattern (e.g., "✎2 ")
+    if let Some(after_pencil) = remaining.strip_prefix('✎') {
+        let end = after_pencil.find(' ').unwrap_or(after_pencil.len());
+        if let Ok(count) = after_pencil[..end].parse::<usize>() {
+            edits = Some(count);
+            remaining = after_pencil[end..].trim_start();
+        }
+    }
+
     // Check for tangent mode ↯ first
     if let Some(after_tangent) = remaining.strip_prefix('↯') {
         tangent_mode = true;
@@ -73,6 +170,8 @@ pub fn parse_prompt_components(prompt: &str) -> Option<PromptComponents> {
         Some(PromptComponents {
             delegate_notifier,
             profile,
+            model,
+            edits,
             warning,
             tangent_mode,
             usage_percentage,
@@ -87,6 +186,8 @@ pub fn generate_prompt(
     warning: bool,
     tangent_mode: bool,
     usage_percentage: Option<f32>,
+    model: Option<&str>,
+    edits: Option<usize>,
 ) -> String {
     // Generate plain text prompt that will be colored by highlight_prompt
     let warning_symbol = if warning { "!" } else { "" };
@@ -95,12 +196,14 @@ pub fn generate_prompt(
         .map(|p| format!("[{p}] "))
         .unwrap_or_default();
 
+    let model_part = model.map(|m| format!("({m}) ")).unwrap_or_default();
     let percentage_part = usage_percentage.map(|p| format!("{:.0}% ", p)).unwrap_or_default();
+    let edits_part = edits.map(|e| format!("✎{e} ")).unwrap_or_default();
 
     if tangent_mode {
-        format!("{profile_part}{percentage_part}↯ {warning_symbol}> ")
+        format!("{profile_part}{model_part}{percentage_part}{edits_part}↯ {warning_symbol}> ")
     } else {
-        format!("{profile_part}{percentage_part}{warning_symbol}> ")
+        format!("{profile_part}{model_part}{percentage_part}{edits_part}{warning_symbol}> ")
     }
 }
 
@@ -108,46 +211,65 @@ pub fn generate_prompt(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_count_status_entries() {
+        assert_eq!(count_status_entries(b""), 0);
+        assert_eq!(count_status_entries(b" M src/main.rs\0?? notes.md\0"), 2);
+        assert_eq!(count_status_entries(b"R  new.rs\0old.rs\0 M lib.rs\0"), 2);
+    }
+
     #[test]
     fn test_generate_prompt() {
         // Test default prompt (no profile)
-        assert_eq!(generate_prompt(None, false, false, None), "> ");
+        assert_eq!(generate_prompt(None, false, false, None, None, None), "> ");
         // Test default prompt with warning
-        assert_eq!(generate_prompt(None, true, false, None), "!> ");
+        assert_eq!(generate_prompt(None, true, false, None, None, None), "!> ");
         // Test tangent mode
-        assert_eq!(generate_prompt(None, false, true, None), "↯ > ");
+        assert_eq!(generate_prompt(None, false, true, None, None, None), "↯ > ");
         // Test tangent mode with warning
-        assert_eq!(generate_prompt(None, true, true, None), "↯ !> ");
+        assert_eq!(generate_prompt(None, true, true, None, None, None), "↯ !> ");
         // Test default profile (should be same as no profile)
-        assert_eq!(generate_prompt(Some(DEFAULT_AGENT_NAME), false, false, None), "> ");
+        assert_eq!(
+            generate_prompt(Some(DEFAULT_AGENT_NAME), false, false, None, None, None),
+            "> "
+        );
         // Test custom profile
         assert_eq!(
-            generate_prompt(Some("test-profile"), false, false, None),
+            generate_prompt(Some("test-profile"), false, false, None, None, None),
             "[test-profile] > "
         );
         // Test custom profile with tangent mode
         assert_eq!(
-            generate_prompt(Some("test-profile"), false, true, None),
+            generate_prompt(Some("test-profile"), false, true, None, None, None),
             "[test-profile] ↯ > "
         );
         // Test another custom profile with warning
-        assert_eq!(generate_prompt(Some("dev"), true, false, None), "[dev] !> ");
+        assert_eq!(generate_prompt(Some("dev"), true, false, None, None, None), "[dev] !> ");
         // Test custom profile with warning and tangent mode
-        assert_eq!(generate_prompt(Some("dev"), true, true, None), "[dev] ↯ !> ");
+        assert_eq!(
+            generate_prompt(Some("dev"), true, true, None, None, None),
+            "[dev] ↯ !> "
+        );
         // Test custom profile with usage percentage
         assert_eq!(
-            generate_prompt(Some("rust-agent"), false, false, Some(6.2)),
+            generate_prompt(Some("rust-agent"), false, false, Some(6.2), None, None),
             "[rust-agent] 6% > "
         );
         // Test custom profile with usage percentage and warning
         assert_eq!(
-            generate_prompt(Some("rust-agent"), true, false, Some(15.7)),
+            generate_prompt(Some("rust-agent"), true, false, Some(15.7), None, None),
             "[rust-agent] 16% !> "
         );
         // Test usage percentage without profile
-        assert_eq!(generate_prompt(None, false, false, Some(25.3)), "25% > ");
+        assert_eq!(generate_prompt(None, false, false, Some(25.3), None, None), "25% > ");
         // Test usage percentage with tangent mode
-        assert_eq!(generate_prompt(None, false, true, Some(8.9)), "9% ↯ > ");
+        assert_eq!(generate_prompt(None, false, true, Some(8.9), None, None), "9% ↯ > ");
+        // Test model and edits
+        assert_eq!(
+            generate_prompt(Some("dev"), true, false, Some(6.2), Some("sonnet-4"), Some(3)),
+            "[dev] (sonnet-4) 6% ✎3 !> "
+        );
+        assert_eq!(generate_prompt(None, false, true, None, None, Some(0)), "✎0 ↯ > ");
     }
 
     #[test]
@@ -227,6 +349,22 @@ mod tests {
         assert!(components.tangent_mode);
         assert_eq!(components.usage_percentage, Some(8.0));
 
+        // Test prompts with model and edits
+        let components = parse_prompt_components("[dev] (sonnet-4) 6% ✎3 !> ").unwrap();
+        assert_eq!(components.profile.as_deref(), Some("dev"));
+        assert_eq!(components.model.as_deref(), Some("sonnet-4"));
+        assert_eq!(components.usage_percentage, Some(6.0));
+        assert_eq!(components.edits, Some(3));
+        assert!(components.warning);
+        assert!(!components.tangent_mode);
+
+        let components = parse_prompt_components("(sonnet-4) ✎0 ↯ > ").unwrap();
+        assert!(components.profile.is_none());
+        assert_eq!(components.model.as_deref(), Some("sonnet-4"));
+        assert!(components.usage_percentage.is_none());
+        assert_eq!(components.edits, Some(0));
+        assert!(components.tangent_mode);
+
         // Test invalid prompt
         assert!(parse_prompt_components("invalid").is_none());
     }
@@ -0,0 +1,185 @@
+//! Caps on how much the model can run on its own, so a runaway loop of tool uses can't keep a
+//! machine busy: tool uses per minute, and the time spent in shell commands per user turn.
+
+use std::collections::VecDeque;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use serde_json::json;
+
+use super::tools::Tool;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// The caps from the `chat.toolRateLimit.*` settings. Unset or zero means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub tool_uses_per_minute: Option<usize>,
+    pub command_time_per_turn: Option<Duration>,
+}
+
+impl RateLimits {
+    pub fn from_settings(os: &Os) -> Self {
+        let positive = |setting| os.database.settings.get_int(setting).filter(|v| *v > 0);
+        Self {
+            tool_uses_per_minute: positive(Setting::ChatToolRateLimitPerMinute).map(|v| v as usize),
+            command_time_per_turn: positive(Setting::ChatToolRateLimitCommandSecondsPerTurn)
+                .map(|v| Duration::from_secs(v as u64)),
+        }
+    }
+}
+
+/// A tool use refused because a cap was reached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimited {
+    pub limit: &'static str,
+    pub message: String,
+    /// When the tool use could succeed again, `None` if not before the next user turn.
+    pub retry_after: Option<Duration>,
+}
+
+impl RateLimited {
+    /// The tool result telling the model why the tool didn't run.
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "error": "rate_limited",
+            "limit": self.limit,
+            "message": self.message,
+            "retryAfterSeconds": self.retry_after.map(|d| d.as_secs().max(1)),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ToolRateLimiter {
+    /// When the tool uses of the last minute started, oldest first.
+    recent: VecDeque<Instant>,
+    /// Time spent running shell commands since the user last sent a prompt.
+    command_time: Duration,
+}
+
+impl ToolRateLimiter {
+    /// Resets the per turn budget, once the user sends a prompt.
+    pub fn start_turn(&mut self) {
+        self.command_time = Duration::ZERO;
+    }
+
+    /// Counts a use of `tool` starting `now`, unless it goes over `limits`.
+    pub fn acquire(&mut self, limits: RateLimits, tool: &Tool, now: Instant) -> Result<(), RateLimited> {
+        while self
+            .recent
+            .front()
+            .is_some_and(|start| now.duration_since(*start) >= WINDOW)
+        {
+            self.recent.pop_front();
+        }
+
+        if let Some(max) = limits.tool_uses_per_minute {
+            if self.recent.len() >= max {
+                let retry_after = self
+                    .recent
+                    .front()
+                    .map(|start| WINDOW.saturating_sub(now.duration_since(*start)));
+                return Err(RateLimited {
+                    limit: "toolUsesPerMinute",
+                    message: format!("At most {max} tools can be used per minute. Wait before using more tools."),
+                    retry_after,
+                });
+            }
+        }
+        if let (Tool::ExecuteCommand(_), Some(max)) = (tool, limits.command_time_per_turn) {
+            if self.command_time >= max {
+                return Err(RateLimited {
+                    limit: "commandSecondsPerTurn",
+                    message: format!(
+                        "Commands already ran for {}s in this turn, the limit is {}s. Finish without running more \
+                         commands, or ask the user to continue.",
+                        self.command_time.as_secs(),
+                        max.as_secs()
+                    ),
+                    retry_after: None,
+                });
+            }
+        }
+
+        self.recent.push_back(now);
+        Ok(())
+    }
+
+    /// Adds the time a shell command ran to the turn's budget.
+    pub fn record(&mut self, tool: &Tool, elapsed: Duration) {
+        if let Tool::ExecuteCommand(_) = tool {
+            self.command_time += elapsed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::chat::tools::execute::ExecuteCommand;
+    use crate::cli::chat::tools::thinking::Thinking;
+
+    fn command() -> Tool {
+        Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(json!({ "command": "make" })).unwrap())
+    }
+
+    fn thinking() -> Tool {
+        Tool::Thinking(serde_json::from_value::<Thinking>(json!({ "thought": "hmm" })).unwrap())
+    }
+
+    #[test]
+    fn test_tool_uses_per_minute() {
+        let limits = RateLimits {
+            tool_uses_per_minute: Some(2),
+            command_time_per_turn: None,
+        };
+        let mut limiter = ToolRateLimiter::default();
+        let start = Instant::now();
+        assert!(limiter.acquire(limits, &thinking(), start).is_ok());
+        assert!(
+            limiter
+                .acquire(limits, &thinking(), start + Duration::from_secs(10))
+                .is_ok()
+        );
+
+        let limited = limiter
+            .acquire(limits, &thinking(), start + Duration::from_secs(20))
+            .unwrap_err();
+        assert_eq!(limited.limit, "toolUsesPerMinute");
+        assert_eq!(limited.retry_after, Some(Duration::from_secs(40)));
+        assert_eq!(limited.to_json()["retryAfterSeconds"], 40);
+
+        assert!(limiter.acquire(limits, &thinking(), start + WINDOW).is_ok());
+        assert!(
+            limiter
+                .acquire(RateLimits::default(), &thinking(), start + WINDOW)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_command_time_per_turn() {
+        let limits = RateLimits {
+            tool_uses_per_minute: None,
+            command_time_per_turn: Some(Duration::from_secs(30)),
+        };
+        let mut limiter = ToolRateLimiter::default();
+        let now = Instant::now();
+        assert!(limiter.acquire(limits, &command(), now).is_ok());
+        limiter.record(&command(), Duration::from_secs(31));
+
+        // Other tools keep working.
+        assert!(limiter.acquire(limits, &thinking(), now).is_ok());
+        let limited = limiter.acquire(limits, &command(), now).unwrap_err();
+        assert_eq!(limited.limit, "commandSecondsPerTurn");
+        assert_eq!(limited.retry_after, None);
+
+        limiter.start_turn();
+        assert!(limiter.acquire(limits, &command(), now).is_ok());
+    }
+}
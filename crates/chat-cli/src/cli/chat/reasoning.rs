@@ -0,0 +1,221 @@
+//! Rendering the reasoning the model streams before its answer, for models that share it.
+
+use std::io::Write;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use crossterm::style::{
+    self,
+    Attribute,
+    Color,
+};
+use crossterm::{
+    cursor,
+    queue,
+    terminal,
+};
+use unicode_width::UnicodeWidthChar;
+
+use super::ChatError;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::time::format_duration;
+
+const PREVIEW_PREFIX: &str = "Thinking: ";
+
+/// How reasoning is shown, from the `chat.reasoningDisplay` setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReasoningDisplay {
+    /// The latest line of reasoning replaces itself as it streams, and collapses into a summary
+    /// once the answer starts.
+    #[default]
+    Collapsed,
+    /// The whole reasoning is printed as it streams.
+    Expanded,
+    /// Only the spinner is shown.
+    Hidden,
+}
+
+impl ReasoningDisplay {
+    pub fn from_settings(os: &Os) -> Self {
+        match os
+            .database
+            .settings
+            .get_string(Setting::ChatReasoningDisplay)
+            .as_deref()
+        {
+            Some("expanded") => Self::Expanded,
+            Some("hidden") => Self::Hidden,
+            _ => Self::Collapsed,
+        }
+    }
+}
+
+/// The reasoning of the response being received.
+#[derive(Debug)]
+pub struct ReasoningTrace {
+    display: ReasoningDisplay,
+    width: usize,
+    started_at: Instant,
+    text: String,
+    /// Whether the trace has been collapsed into its summary, or closed off when expanded.
+    finished: bool,
+}
+
+impl ReasoningTrace {
+    pub fn new(display: ReasoningDisplay, width: usize) -> Self {
+        Self {
+            display,
+            width,
+            started_at: Instant::now(),
+            text: String::new(),
+            finished: false,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.text.is_empty() && !self.finished
+    }
+
+    /// Whether the trace is printed, rather than left to the spinner.
+    pub fn is_visible(&self) -> bool {
+        self.display != ReasoningDisplay::Hidden
+    }
+
+    /// Adds `delta` to the trace and renders it. A visible trace is printed over the current line,
+    /// so the spinner must be cleared first.
+    pub fn push(&mut self, output: &mut impl Write, delta: &str) -> Result<(), ChatError> {
+        if self.text.is_empty() {
+            self.started_at = Instant::now();
+        }
+        self.text.push_str(delta);
+        match self.display {
+            ReasoningDisplay::Hidden => return Ok(()),
+            ReasoningDisplay::Collapsed => queue!(
+                output,
+                cursor::MoveToColumn(0),
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(preview(&self.text, self.width)),
+            )?,
+            ReasoningDisplay::Expanded => {
+                if self.text.len() == delta.len() {
+                    queue!(
+                        output,
+                        style::SetForegroundColor(Color::DarkGrey),
+                        style::Print(PREVIEW_PREFIX),
+                        style::Print("\n"),
+                    )?;
+                }
+                queue!(
+                    output,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::SetAttribute(Attribute::Italic),
+                    style::Print(delta),
+                )?;
+            },
+        }
+        queue!(
+            output,
+            style::SetAttribute(Attribute::Reset),
+            style::SetForegroundColor(Color::Reset)
+        )?;
+        output.flush()?;
+        Ok(())
+    }
+
+    /// Ends the trace once the answer or a tool use starts: a collapsed trace is replaced with a
+    /// summary line.
+    pub fn finish(&mut self, output: &mut impl Write) -> Result<(), ChatError> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        self.finished = true;
+        match self.display {
+            ReasoningDisplay::Hidden => (),
+            ReasoningDisplay::Collapsed => queue!(
+                output,
+                cursor::MoveToColumn(0),
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(summary(self.started_at.elapsed())),
+                style::SetForegroundColor(Color::Reset),
+                style::Print("\n\n"),
+            )?,
+            ReasoningDisplay::Expanded => queue!(output, style::Print("\n\n"))?,
+        }
+        output.flush()?;
+        Ok(())
+    }
+}
+
+/// The latest line of `text`, fitted on one line of `width` columns.
+fn preview(text: &str, width: usize) -> String {
+    let line = text
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or_default();
+    let mut preview = PREVIEW_PREFIX.to_string();
+    let mut used = PREVIEW_PREFIX.len();
+    // Leave a column so the cursor doesn't wrap to the next line.
+    let available = width.saturating_sub(1);
+    let chars = line.trim().chars().collect::<Vec<_>>();
+    let total = chars.iter().map(|c| c.width().unwrap_or(0)).sum::<usize>();
+    if used + total <= available {
+        preview.extend(chars);
+        return preview;
+    }
+    for c in chars {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > available {
+            break;
+        }
+        preview.push(c);
+        used += w;
+    }
+    preview.push('…');
+    preview
+}
+
+fn summary(elapsed: Duration) -> String {
+    format!(
+        "Thought for {} (set chat.reasoningDisplay to expanded to see the reasoning)",
+        format_duration(elapsed.max(Duration::from_secs(1)))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(
+            preview("Let me look at\nthe parser first\n", 80),
+            "Thinking: the parser first"
+        );
+        assert_eq!(preview("abcdefghijklmnopqrstuvwxyz", 20), "Thinking: abcdefgh…");
+        assert_eq!(preview("", 80), "Thinking: ");
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut output = Vec::new();
+        let mut trace = ReasoningTrace::new(ReasoningDisplay::Hidden, 80);
+        trace.push(&mut output, "hmm").unwrap();
+        trace.finish(&mut output).unwrap();
+        assert!(output.is_empty());
+
+        let mut trace = ReasoningTrace::new(ReasoningDisplay::Collapsed, 80);
+        assert!(!trace.is_active());
+        trace.push(&mut output, "Checking the tests").unwrap();
+        assert!(String::from_utf8_lossy(&output).contains("Thinking: Checking the tests"));
+        assert!(trace.is_active());
+        trace.finish(&mut output).unwrap();
+        assert!(!trace.is_active());
+        assert!(String::from_utf8_lossy(&output).contains("Thought for 1s"));
+    }
+}
@@ -0,0 +1,316 @@
+//! Sampling the memory and CPU used by the session and the processes its tools started, to warn
+//! before a runaway command bogs down the machine, and to help diagnose complaints about it.
+
+use std::sync::Arc;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use parking_lot::Mutex;
+use serde::Serialize;
+use sysinfo::{
+    Pid,
+    ProcessesToUpdate,
+    System,
+};
+use tokio::task::JoinHandle;
+
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::time::format_duration;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_MAX_MEMORY_MB: i64 = 4096;
+const DEFAULT_MAX_CPU_PERCENT: i64 = 300;
+/// CPU usage has to stay over the threshold for this many samples in a row, so that a build
+/// that is busy for a few seconds doesn't warn.
+const SUSTAINED_CPU_SAMPLES: usize = 6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUsage {
+    /// Memory of the chat process, in bytes.
+    pub memory: u64,
+    /// CPU usage of the chat process, where 100 is a whole core.
+    pub cpu: f32,
+    /// Number of processes started by the session, such as commands run by tools and MCP servers.
+    pub children: usize,
+    pub children_memory: u64,
+    pub children_cpu: f32,
+}
+
+impl ResourceUsage {
+    pub fn total_memory(&self) -> u64 {
+        self.memory + self.children_memory
+    }
+
+    pub fn total_cpu(&self) -> f32 {
+        self.cpu + self.children_cpu
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceStats {
+    pub current: ResourceUsage,
+    pub peak_memory: u64,
+    pub peak_cpu: f32,
+    pub samples: usize,
+    /// The warnings given so far.
+    pub warnings: Vec<String>,
+}
+
+/// Totals over which the user is warned, from the `chat.resourceMonitor.*` settings. Zero turns a
+/// threshold off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResourceThresholds {
+    pub max_memory_mb: Option<u64>,
+    pub max_cpu_percent: Option<u64>,
+}
+
+impl ResourceThresholds {
+    pub fn from_settings(os: &Os) -> Self {
+        let positive = |setting, default| {
+            Some(os.database.settings.get_int(setting).unwrap_or(default))
+                .filter(|v| *v > 0)
+                .map(|v| v as u64)
+        };
+        Self {
+            max_memory_mb: positive(Setting::ChatResourceMonitorMaxMemoryMb, DEFAULT_MAX_MEMORY_MB),
+            max_cpu_percent: positive(Setting::ChatResourceMonitorMaxCpuPercent, DEFAULT_MAX_CPU_PERCENT),
+        }
+    }
+}
+
+/// Decides when samples are worth a warning: once when a threshold is crossed, and again only
+/// after usage went back under it.
+#[derive(Debug)]
+struct Watch {
+    thresholds: ResourceThresholds,
+    over_memory: bool,
+    cpu_samples_over: usize,
+}
+
+impl Watch {
+    fn new(thresholds: ResourceThresholds) -> Self {
+        Self {
+            thresholds,
+            over_memory: false,
+            cpu_samples_over: 0,
+        }
+    }
+
+    fn observe(&mut self, usage: &ResourceUsage) -> Option<String> {
+        let mut warning = None;
+        if let Some(max) = self.thresholds.max_cpu_percent {
+            if usage.total_cpu() > max as f32 {
+                self.cpu_samples_over += 1;
+                if self.cpu_samples_over == SUSTAINED_CPU_SAMPLES {
+                    warning = Some(format!(
+                        "The session and its {} processes have used {:.0}% CPU for the last {}",
+                        usage.children,
+                        usage.total_cpu(),
+                        format_duration(SAMPLE_INTERVAL * SUSTAINED_CPU_SAMPLES as u32)
+                    ));
+                }
+            } else {
+                self.cpu_samples_over = 0;
+            }
+        }
+        if let Some(max) = self.thresholds.max_memory_mb {
+            let over = usage.total_memory() > max.saturating_mul(1024 * 1024);
+            if over && !self.over_memory {
+                warning = Some(format!(
+                    "The session and its {} processes use {}, over the limit of {max} MB",
+                    usage.children,
+                    format_bytes(usage.total_memory())
+                ));
+            }
+            self.over_memory = over;
+        }
+        warning
+    }
+}
+
+/// Samples the resources of the session in the background, for as long as it lives.
+#[derive(Debug)]
+pub struct ResourceMonitor {
+    stats: Arc<Mutex<ResourceStats>>,
+    /// A warning not shown to the user yet.
+    pending_warning: Arc<Mutex<Option<String>>>,
+    started_at: Instant,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl ResourceMonitor {
+    pub fn spawn(thresholds: ResourceThresholds) -> Self {
+        let stats = Arc::new(Mutex::new(ResourceStats::default()));
+        let pending_warning = Arc::new(Mutex::new(None));
+        let Ok(pid) = sysinfo::get_current_pid() else {
+            return Self {
+                stats,
+                pending_warning,
+                started_at: Instant::now(),
+                handle: None,
+            };
+        };
+
+        let handle = tokio::spawn({
+            let stats = Arc::clone(&stats);
+            let pending_warning = Arc::clone(&pending_warning);
+            async move {
+                let mut system = System::new();
+                let mut watch = Watch::new(thresholds);
+                let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    let Some(usage) = sample(&mut system, pid) else {
+                        continue;
+                    };
+                    let warning = watch.observe(&usage);
+                    let mut stats = stats.lock();
+                    stats.current = usage;
+                    stats.peak_memory = stats.peak_memory.max(usage.total_memory());
+                    stats.peak_cpu = stats.peak_cpu.max(usage.total_cpu());
+                    stats.samples += 1;
+                    if let Some(warning) = warning {
+                        stats.warnings.push(warning.clone());
+                        *pending_warning.lock() = Some(warning);
+                    }
+                }
+            }
+        });
+
+        Self {
+            stats,
+            pending_warning,
+            started_at: Instant::now(),
+            handle: Some(handle),
+        }
+    }
+
+    /// How long the session has been running.
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    pub fn stats(&self) -> ResourceStats {
+        self.stats.lock().clone()
+    }
+
+    /// The latest warning, if it hasn't been shown yet.
+    pub fn take_warning(&self) -> Option<String> {
+        self.pending_warning.lock().take()
+    }
+}
+
+impl Drop for ResourceMonitor {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
+    }
+}
+
+/// The usage of `pid` and of all the processes under it.
+fn sample(system: &mut System, pid: Pid) -> Option<ResourceUsage> {
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    let process = system.process(pid)?;
+    let mut usage = ResourceUsage {
+        memory: process.memory(),
+        cpu: process.cpu_usage(),
+        ..Default::default()
+    };
+
+    let mut parents = vec![pid];
+    while let Some(parent) = parents.pop() {
+        for (child_pid, child) in system.processes() {
+            // Threads are listed as processes on Linux, and are part of their process already.
+            if child.parent() != Some(parent) || child.thread_kind().is_some() {
+                continue;
+            }
+            usage.children += 1;
+            usage.children_memory += child.memory();
+            usage.children_cpu += child.cpu_usage();
+            parents.push(*child_pid);
+        }
+    }
+    Some(usage)
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const MB: u64 = 1024 * 1024;
+    if bytes >= 1024 * MB {
+        format!("{:.1} GB", bytes as f64 / (1024 * MB) as f64)
+    } else {
+        format!("{} MB", bytes / MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn usage(memory_mb: u64, cpu: f32) -> ResourceUsage {
+        ResourceUsage {
+            memory: 100 * MB,
+            cpu: 1.0,
+            children: 2,
+            children_memory: memory_mb * MB - 100 * MB,
+            children_cpu: cpu - 1.0,
+        }
+    }
+
+    #[test]
+    fn test_watch_memory() {
+        let mut watch = Watch::new(ResourceThresholds {
+            max_memory_mb: Some(1024),
+            max_cpu_percent: None,
+        });
+        assert_eq!(watch.observe(&usage(500, 10.0)), None);
+        assert_eq!(
+            watch.observe(&usage(2048, 10.0)).unwrap(),
+            "The session and its 2 processes use 2.0 GB, over the limit of 1024 MB"
+        );
+        // Only warned again after going back under the limit.
+        assert_eq!(watch.observe(&usage(2048, 10.0)), None);
+        assert_eq!(watch.observe(&usage(500, 10.0)), None);
+        assert!(watch.observe(&usage(2048, 10.0)).is_some());
+
+        // A limit too large to be reached in bytes is never exceeded.
+        let mut watch = Watch::new(ResourceThresholds {
+            max_memory_mb: Some(u64::MAX),
+            max_cpu_percent: None,
+        });
+        assert_eq!(watch.observe(&usage(2048, 10.0)), None);
+    }
+
+    #[test]
+    fn test_watch_cpu() {
+        let mut watch = Watch::new(ResourceThresholds {
+            max_memory_mb: None,
+            max_cpu_percent: Some(200),
+        });
+        for _ in 1..SUSTAINED_CPU_SAMPLES {
+            assert_eq!(watch.observe(&usage(500, 350.0)), None);
+        }
+        assert_eq!(
+            watch.observe(&usage(500, 350.0)).unwrap(),
+            "The session and its 2 processes have used 350% CPU for the last 30s"
+        );
+        assert_eq!(watch.observe(&usage(500, 350.0)), None);
+
+        // A spike that doesn't last isn't worth a warning.
+        assert_eq!(watch.observe(&usage(500, 10.0)), None);
+        assert_eq!(watch.observe(&usage(500, 350.0)), None);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(300 * MB), "300 MB");
+        assert_eq!(format_bytes(1536 * MB), "1.5 GB");
+    }
+}
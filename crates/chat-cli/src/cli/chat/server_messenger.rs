@@ -14,6 +14,7 @@ use tokio::sync::mpsc::{
     channel,
 };
 
+use crate::cli::chat::mcp_cache::CachedLists;
 use crate::mcp_client::messenger::{
     Messenger,
     MessengerError,
@@ -48,6 +49,12 @@ pub enum UpdateEventMessage {
         server_name: String,
         link: String,
     },
+    /// The lists the server sent in an earlier session, processed as if the server had sent them,
+    /// which it may not have been started to do.
+    CachedLists {
+        server_name: String,
+        lists: CachedLists,
+    },
     InitStart {
         server_name: String,
     },
@@ -0,0 +1,102 @@
+//! Retrying a response stream that broke off mid-message, e.g. because of a network blip or
+//! throttling.
+//!
+//! The API can't resume a response, so the conversation is sent again and the text of the new
+//! response is stitched to the text already shown: the part the user has seen is skipped, and the
+//! rest is appended. When the new response turns out to differ, it is shown again from the start.
+
+use std::time::Duration;
+
+/// How many times a broken off response is retried before giving up on the turn.
+pub const MAX_STREAM_RETRIES: u32 = 3;
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// How long to wait before the `attempt`th retry, starting at 1.
+pub fn backoff(attempt: u32) -> Duration {
+    INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_BACKOFF)
+}
+
+/// What to show of the text received from a retried response.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Stitched {
+    /// Text to append to what is shown, empty while the response repeats what was shown already.
+    Append(String),
+    /// The response differs from what was shown, this is all of it so far.
+    Restart(String),
+}
+
+#[derive(Debug)]
+pub struct Stitcher {
+    /// The text of the interrupted response shown to the user.
+    shown: String,
+    /// The text of the retried response so far.
+    received: String,
+    /// Whether the retried response got past the text shown.
+    caught_up: bool,
+}
+
+impl Stitcher {
+    pub fn new(shown: String) -> Self {
+        Self {
+            caught_up: shown.is_empty(),
+            shown,
+            received: String::new(),
+        }
+    }
+
+    pub fn push(&mut self, text: &str) -> Stitched {
+        if self.caught_up {
+            return Stitched::Append(text.to_string());
+        }
+
+        self.received.push_str(text);
+        if self.shown.starts_with(&self.received) {
+            Stitched::Append(String::new())
+        } else if let Some(rest) = self.received.strip_prefix(&self.shown) {
+            self.caught_up = true;
+            Stitched::Append(rest.to_string())
+        } else {
+            self.caught_up = true;
+            Stitched::Restart(std::mem::take(&mut self.received))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(2));
+        assert_eq!(backoff(10), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn test_stitch_same_response() {
+        let mut stitcher = Stitcher::new("Hello, wor".to_string());
+        assert_eq!(stitcher.push("Hello"), Stitched::Append(String::new()));
+        assert_eq!(stitcher.push(", world!"), Stitched::Append("ld!".to_string()));
+        assert_eq!(stitcher.push(" Bye."), Stitched::Append(" Bye.".to_string()));
+    }
+
+    #[test]
+    fn test_stitch_different_response() {
+        let mut stitcher = Stitcher::new("Hello, world".to_string());
+        assert_eq!(stitcher.push("Hello"), Stitched::Append(String::new()));
+        assert_eq!(stitcher.push(" there"), Stitched::Restart("Hello there".to_string()));
+        assert_eq!(stitcher.push("!"), Stitched::Append("!".to_string()));
+    }
+
+    #[test]
+    fn test_stitch_nothing_shown() {
+        let mut stitcher = Stitcher::new(String::new());
+        assert_eq!(stitcher.push("Hi"), Stitched::Append("Hi".to_string()));
+    }
+}
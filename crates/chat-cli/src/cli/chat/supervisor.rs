@@ -0,0 +1,162 @@
+//! Tracking the background tasks of a chat session, so that none of them outlives it.
+//!
+//! Tasks run for the whole session, while work of the current turn, like consuming a response
+//! stream, is handed a token from [TaskSupervisor::turn_token] that aborting the turn cancels.
+//! Ending the session cancels all of them. A task that panics is reported when the session next
+//! reaps its tasks, rather than dying unnoticed.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::task::{
+    Id,
+    JoinSet,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{
+    debug,
+    error,
+    warn,
+};
+
+/// How long tasks are given to wind down once the session ends, before they are aborted.
+pub const SHUTDOWN_DEADLINE: Duration = Duration::from_millis(500);
+
+/// Owns the tasks a session spawns. Dropping it aborts whatever is still running.
+#[derive(Debug)]
+pub struct TaskSupervisor {
+    tasks: JoinSet<()>,
+    names: HashMap<Id, &'static str>,
+    session: CancellationToken,
+    turn: CancellationToken,
+}
+
+impl TaskSupervisor {
+    pub fn new() -> Self {
+        let session = CancellationToken::new();
+        let turn = session.child_token();
+        Self {
+            tasks: JoinSet::new(),
+            names: HashMap::new(),
+            session,
+            turn,
+        }
+    }
+
+    /// Spawns a task that runs until it completes or the session ends.
+    pub fn spawn<F>(&mut self, name: &'static str, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let token = self.session.clone();
+        self.spawn_until(name, token, task);
+    }
+
+    /// A token that is cancelled when the current turn is aborted or the session ends, for work
+    /// that manages its own task.
+    pub fn turn_token(&self) -> CancellationToken {
+        self.turn.child_token()
+    }
+
+    /// Cancels the tokens of the current turn. Tokens taken afterwards belong to the next turn.
+    pub fn cancel_turn(&mut self) {
+        self.turn.cancel();
+        self.turn = self.session.child_token();
+    }
+
+    /// Forgets the tasks that have finished, returning the names of those that panicked.
+    pub fn reap(&mut self) -> Vec<&'static str> {
+        let mut panicked = Vec::new();
+        while let Some(result) = self.tasks.try_join_next_with_id() {
+            let id = match &result {
+                Ok((id, ())) => *id,
+                Err(err) => err.id(),
+            };
+            let name = self.names.remove(&id).unwrap_or("unknown");
+            match result {
+                Err(err) if err.is_panic() => {
+                    error!(name, ?err, "A background task panicked");
+                    panicked.push(name);
+                },
+                _ => debug!(name, "A background task finished"),
+            }
+        }
+        panicked
+    }
+
+    /// Cancels every task, waiting up to `deadline` for them to finish before aborting the rest.
+    pub async fn shutdown(&mut self, deadline: Duration) {
+        self.session.cancel();
+        let joined = tokio::time::timeout(deadline, async { while self.tasks.join_next().await.is_some() {} }).await;
+        if joined.is_err() {
+            warn!(
+                remaining = self.tasks.len(),
+                "Aborting background tasks that did not finish"
+            );
+            self.tasks.abort_all();
+        }
+        self.names.clear();
+    }
+
+    fn spawn_until<F>(&mut self, name: &'static str, token: CancellationToken, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = self.tasks.spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => debug!(name, "A background task was cancelled"),
+                _ = task => {},
+            }
+        });
+        self.names.insert(handle.id(), name);
+    }
+}
+
+impl Default for TaskSupervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_turn() {
+        let mut supervisor = TaskSupervisor::new();
+        let (session_tx, mut session_rx) = tokio::sync::oneshot::channel::<()>();
+        supervisor.spawn("session", async move {
+            std::future::pending::<()>().await;
+            drop(session_tx);
+        });
+        let turn_token = supervisor.turn_token();
+
+        supervisor.cancel_turn();
+        assert!(turn_token.is_cancelled());
+        assert_eq!(
+            session_rx.try_recv(),
+            Err(tokio::sync::oneshot::error::TryRecvError::Empty)
+        );
+        assert!(!supervisor.turn_token().is_cancelled());
+
+        supervisor.shutdown(SHUTDOWN_DEADLINE).await;
+        // The sender of a cancelled task is dropped without sending.
+        assert!(session_rx.await.is_err());
+        assert!(supervisor.tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reap_reports_panics() {
+        let mut supervisor = TaskSupervisor::new();
+        supervisor.spawn("fine", async {});
+        supervisor.spawn("broken", async { panic!("oops") });
+        let mut panicked = Vec::new();
+        while !supervisor.names.is_empty() {
+            panicked.extend(supervisor.reap());
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(panicked, vec!["broken"]);
+    }
+}
@@ -12,7 +12,7 @@ use crate::cli::chat::conversation::{
     ConversationSize,
 };
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default)]
 pub struct CharCount(usize);
 
 impl CharCount {
@@ -14,11 +14,14 @@ use std::io::{
 };
 use std::path::PathBuf;
 use std::pin::Pin;
-use std::sync::Arc;
 use std::sync::atomic::{
     AtomicBool,
     Ordering,
 };
+use std::sync::{
+    Arc,
+    LazyLock,
+};
 use std::time::{
     Duration,
     Instant,
@@ -39,6 +42,8 @@ use rmcp::model::{
     GetPromptRequestParam,
     GetPromptResult,
     Prompt,
+    ReadResourceRequestParam,
+    ReadResourceResult,
 };
 use tokio::signal::ctrl_c;
 use tokio::sync::{
@@ -65,21 +70,35 @@ use crate::cli::agent::{
 };
 use crate::cli::chat::cli::prompts::GetPromptError;
 use crate::cli::chat::consts::DUMMY_TOOL_NAME;
+use crate::cli::chat::mcp_cache::{
+    CachedLists,
+    McpCache,
+};
 use crate::cli::chat::message::AssistantToolUse;
 use crate::cli::chat::server_messenger::{
     ServerMessengerBuilder,
     UpdateEventMessage,
 };
+use crate::cli::chat::tools::cloudwatch_logs::CloudwatchLogs;
 use crate::cli::chat::tools::custom_tool::CustomTool;
 use crate::cli::chat::tools::delegate::Delegate;
+use crate::cli::chat::tools::dependency_report::DependencyReport;
 use crate::cli::chat::tools::execute::ExecuteCommand;
 use crate::cli::chat::tools::fs_read::FsRead;
 use crate::cli::chat::tools::fs_write::FsWrite;
 use crate::cli::chat::tools::gh_issue::GhIssue;
+use crate::cli::chat::tools::git::Git;
 use crate::cli::chat::tools::introspect::Introspect;
+use crate::cli::chat::tools::issue_tracker::IssueTracker;
+#[cfg(feature = "knowledge")]
 use crate::cli::chat::tools::knowledge::Knowledge;
+use crate::cli::chat::tools::retrieve_output::RetrieveOutput;
+use crate::cli::chat::tools::security_scan::SecurityScan;
+#[cfg(feature = "knowledge")]
+use crate::cli::chat::tools::semantic_search::SemanticSearch;
 use crate::cli::chat::tools::thinking::Thinking;
 use crate::cli::chat::tools::todo::TodoList;
+use crate::cli::chat::tools::update_plan::UpdatePlan;
 use crate::cli::chat::tools::use_aws::UseAws;
 use crate::cli::chat::tools::{
     Tool,
@@ -92,6 +111,7 @@ use crate::mcp_client::messenger::Messenger;
 use crate::mcp_client::{
     InitializedMcpClient,
     InnerService,
+    McpClientError,
     McpClientService,
 };
 use crate::os::Os;
@@ -100,6 +120,7 @@ use crate::util::MCP_SERVER_TOOL_DELIMITER;
 use crate::util::directories::home_dir;
 
 const NAMESPACE_DELIMITER: &str = "___";
+const MAX_TOOL_NAME_LEN: usize = 64;
 // This applies for both mcp server and tool name
 const VALID_TOOL_NAME: &str = "^[a-zA-Z][a-zA-Z0-9_]*$";
 const SPINNER_CHARS: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
@@ -319,6 +340,26 @@ impl ToolManagerBuilder {
             })
             .collect::<Vec<_>>();
 
+        // The tools listed in an earlier session are offered before the servers have started, and
+        // with lazy startup the servers are only started once one of them is used.
+        let cache = McpCache::new(os);
+        let mut cached_lists = HashMap::<String, CachedLists>::new();
+        if let Some(cache) = &cache {
+            for (server_name, server_config) in &pre_initialized {
+                if let Some(lists) = cache.load(server_name, server_config).await {
+                    cached_lists.insert(server_name.clone(), lists);
+                }
+            }
+        }
+        let deferred = match os.database.settings.get_bool(Setting::McpLazyStartup).unwrap_or(false) {
+            true => cached_lists.keys().cloned().collect::<HashSet<_>>(),
+            false => HashSet::new(),
+        };
+        let pre_initialized = pre_initialized
+            .into_iter()
+            .filter(|(server_name, _)| !deferred.contains(server_name))
+            .collect::<Vec<_>>();
+
         let mut clients = HashMap::<String, InitializedMcpClient>::new();
         let new_tool_specs = self.new_tool_specs;
         let has_new_stuff = self.has_new_stuff;
@@ -363,6 +404,7 @@ impl ToolManagerBuilder {
             let conv_id = conversation_id.clone();
             let pending = pending.clone();
             let regex = Regex::new(VALID_TOOL_NAME)?;
+            let cache = cache.clone();
 
             spawn_orchestrator_task(
                 has_new_stuff,
@@ -381,11 +423,32 @@ impl ToolManagerBuilder {
                 new_tool_specs,
                 total,
                 conv_id,
+                cache,
             );
         }
 
         debug_assert!(messenger_builder.is_some());
         let messenger_builder = messenger_builder.unwrap();
+        for (server_name, lists) in cached_lists {
+            if deferred.contains(&server_name) {
+                load_record
+                    .lock()
+                    .await
+                    .entry(server_name.clone())
+                    .or_default()
+                    .push(LoadingRecord::success(format!(
+                        "{server_name} will start when one of its tools or prompts is first used\n"
+                    )));
+            }
+            let msg = UpdateEventMessage::CachedLists { server_name, lists };
+            if let Err(e) = messenger_builder.update_event_sender.send(msg).await {
+                warn!("Error sending cached tools to the orchestrator task: {:?}", e);
+            }
+        }
+
+        let (deferred_servers, enabled_servers): (Vec<_>, Vec<_>) = enabled_servers
+            .into_iter()
+            .partition(|(server_name, _)| deferred.contains(server_name));
         let pre_initialized = enabled_servers
             .into_iter()
             .map(|(server_name, server_config)| {
@@ -456,6 +519,7 @@ impl ToolManagerBuilder {
                 }
             },
             messenger_builder: Some(messenger_builder),
+            deferred_clients: deferred_servers.into_iter().collect(),
             is_first_launch: self.is_first_launch,
             ..Default::default()
         })
@@ -477,12 +541,35 @@ pub struct PromptBundle {
 pub enum PromptQuery {
     List,
     Search(Option<String>),
+    /// The URIs of the resources offered by each server.
+    ListResources,
+    /// The URIs of the resources containing the search word, if any.
+    SearchResources(Option<String>),
 }
 
 #[derive(Clone, Debug)]
 pub enum PromptQueryResult {
     List(HashMap<String, Vec<PromptBundle>>),
     Search(Vec<String>),
+    Resources(HashMap<String, Vec<String>>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReadResourceError {
+    #[error("No server offers the resource {0}")]
+    ResourceNotFound(String),
+    #[error("Missing client")]
+    MissingClient,
+    #[error("Incorrect response type received")]
+    IncorrectResponseType,
+    #[error("Missing channel")]
+    MissingChannel,
+    #[error(transparent)]
+    General(#[from] eyre::Report),
+    #[error(transparent)]
+    McpClient(#[from] McpClientError),
+    #[error(transparent)]
+    Service(#[from] rmcp::ServiceError),
 }
 
 /// Categorizes different types of tool name validation failures:
@@ -503,6 +590,17 @@ pub struct ToolInfo {
     pub host_tool_name: HostToolName,
 }
 
+/// A tool left out because the name the model would know it by is taken by another tool, and so
+/// is the name after its server it would otherwise get.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ToolConflict {
+    pub model_tool_name: ModelToolName,
+    /// The tool that was left out.
+    pub rejected: ToolInfo,
+    /// The tool that has the name.
+    pub kept: ToolInfo,
+}
+
 impl Borrow<HostToolName> for ToolInfo {
     fn borrow(&self) -> &HostToolName {
         &self.host_tool_name
@@ -605,6 +703,9 @@ pub struct ToolManager {
     /// List of disabled MCP server names for display purposes
     disabled_servers: Vec<String>,
 
+    /// The tools left out because of a name taken by another tool, for `/mcp` to report.
+    pub tool_conflicts: Vec<ToolConflict>,
+
     /// A builder for mcp clients to communicate with the orchestrator task
     /// We need to store this for when we switch agent - we need to be spawning messengers that are
     /// already listened to by the orchestrator task
@@ -615,6 +716,10 @@ pub struct ToolManager {
     /// We need to put this behind a lock because the orchestrator task depends on agent
     pub agent: Arc<Mutex<Agent>>,
 
+    /// The configurations of the servers whose start is deferred until one of their tools or
+    /// prompts is first used, which is done with `mcp.lazyStartup` for servers with cached tools.
+    deferred_clients: HashMap<String, CustomToolConfig>,
+
     is_first_launch: bool,
 }
 
@@ -629,6 +734,7 @@ impl Clone for ToolManager {
             is_interactive: self.is_interactive,
             mcp_load_record: self.mcp_load_record.clone(),
             disabled_servers: self.disabled_servers.clone(),
+            tool_conflicts: self.tool_conflicts.clone(),
             ..Default::default()
         }
     }
@@ -722,11 +828,21 @@ impl ToolManager {
             if !crate::cli::chat::tools::thinking::Thinking::is_enabled(os) {
                 tool_specs.remove("thinking");
             }
+            #[cfg(feature = "knowledge")]
             if !crate::cli::chat::tools::knowledge::Knowledge::is_enabled(os) {
                 tool_specs.remove("knowledge");
             }
+            #[cfg(feature = "knowledge")]
+            if !crate::cli::chat::tools::semantic_search::SemanticSearch::is_enabled(os) {
+                tool_specs.remove("semantic_search");
+            }
+            #[cfg(not(feature = "knowledge"))]
+            tool_specs.remove("knowledge");
+            #[cfg(not(feature = "knowledge"))]
+            tool_specs.remove("semantic_search");
             if !crate::cli::chat::tools::todo::TodoList::is_enabled(os) {
                 tool_specs.remove("todo_list");
+                tool_specs.remove("update_plan");
             }
             if !crate::cli::chat::tools::delegate::Delegate::is_enabled(os) {
                 tool_specs.remove("delegate");
@@ -740,15 +856,15 @@ impl ToolManager {
 
                 tool_specs.remove("execute_bash");
 
-                tool_specs.insert("execute_cmd".to_string(), ToolSpec {
-                    name: "execute_cmd".to_string(),
-                    description: "Execute the specified Windows command.".to_string(),
+                tool_specs.insert("execute_powershell".to_string(), ToolSpec {
+                    name: "execute_powershell".to_string(),
+                    description: "Execute the specified PowerShell command. Use PowerShell syntax and cmdlets, e.g. Get-ChildItem instead of ls -la.".to_string(),
                     input_schema: InputSchema(json!({
                     "type": "object",
                     "properties": {
                     "command": {
                         "type": "string",
-                        "description": "Windows command to execute"
+                        "description": "PowerShell command to execute"
                     },
                     "summary": {
                         "type": "string",
@@ -850,7 +966,7 @@ impl ToolManager {
         Ok(self.schema.clone())
     }
 
-    pub async fn get_tool_from_tool_use(&mut self, value: AssistantToolUse) -> Result<Tool, ToolResult> {
+    pub async fn get_tool_from_tool_use(&mut self, os: &Os, value: AssistantToolUse) -> Result<Tool, ToolResult> {
         let map_err = |parse_error| ToolResult {
             tool_use_id: value.id.clone(),
             content: vec![ToolResultContentBlock::Text(format!(
@@ -862,22 +978,52 @@ impl ToolManager {
         Ok(match value.name.as_str() {
             "fs_read" => Tool::FsRead(serde_json::from_value::<FsRead>(value.args).map_err(map_err)?),
             "fs_write" => Tool::FsWrite(serde_json::from_value::<FsWrite>(value.args).map_err(map_err)?),
+            // execute_cmd is what the tool was called before it ran PowerShell, and conversations
+            // from back then can still use it.
             #[cfg(windows)]
-            "execute_cmd" => {
+            "execute_powershell" | "execute_cmd" => {
                 Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(value.args).map_err(map_err)?)
             },
+            // Models trained on bash keep asking for it.
+            #[cfg(windows)]
+            "execute_bash" => {
+                let mut execute = serde_json::from_value::<ExecuteCommand>(value.args).map_err(map_err)?;
+                execute.notice = Some(crate::cli::chat::tools::execute::powershell::BASH_NOTICE.to_string());
+                Tool::ExecuteCommand(execute)
+            },
             #[cfg(not(windows))]
             "execute_bash" => {
                 Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(value.args).map_err(map_err)?)
             },
             "use_aws" => Tool::UseAws(serde_json::from_value::<UseAws>(value.args).map_err(map_err)?),
-            "report_issue" => Tool::GhIssue(serde_json::from_value::<GhIssue>(value.args).map_err(map_err)?),
+            "cloudwatch_logs" => {
+                Tool::CloudwatchLogs(serde_json::from_value::<CloudwatchLogs>(value.args).map_err(map_err)?)
+            },
+            "issue_tracker" => Tool::IssueTracker(serde_json::from_value::<IssueTracker>(value.args).map_err(map_err)?),
+            // The name of the tool before it could fetch issues.
+            "report_issue" => Tool::IssueTracker(IssueTracker::Report(
+                serde_json::from_value::<GhIssue>(value.args).map_err(map_err)?,
+            )),
+            "git" => Tool::Git(serde_json::from_value::<Git>(value.args).map_err(map_err)?),
             "introspect" => Tool::Introspect(serde_json::from_value::<Introspect>(value.args).map_err(map_err)?),
             "thinking" => Tool::Thinking(serde_json::from_value::<Thinking>(value.args).map_err(map_err)?),
+            #[cfg(feature = "knowledge")]
             "knowledge" => Tool::Knowledge(serde_json::from_value::<Knowledge>(value.args).map_err(map_err)?),
+            #[cfg(feature = "knowledge")]
+            "semantic_search" => {
+                Tool::SemanticSearch(serde_json::from_value::<SemanticSearch>(value.args).map_err(map_err)?)
+            },
             "todo_list" => Tool::Todo(serde_json::from_value::<TodoList>(value.args).map_err(map_err)?),
+            "update_plan" => Tool::UpdatePlan(serde_json::from_value::<UpdatePlan>(value.args).map_err(map_err)?),
             // Note that this name is NO LONGER namespaced with server_name{DELIMITER}tool_name
             "delegate" => Tool::Delegate(serde_json::from_value::<Delegate>(value.args).map_err(map_err)?),
+            "dependency_report" => {
+                Tool::DependencyReport(serde_json::from_value::<DependencyReport>(value.args).map_err(map_err)?)
+            },
+            "security_scan" => Tool::SecurityScan(serde_json::from_value::<SecurityScan>(value.args).map_err(map_err)?),
+            "retrieve_output" => {
+                Tool::RetrieveOutput(serde_json::from_value::<RetrieveOutput>(value.args).map_err(map_err)?)
+            },
             name => {
                 // Note: tn_map also has tools that underwent no transformation. In otherwords, if
                 // it is a valid tool name, we should get a hit.
@@ -897,7 +1043,18 @@ impl ToolManager {
                         })
                     },
                 }?;
-                let Some(client) = self.clients.get_mut(server_name) else {
+                let server_name = server_name.clone();
+                let tool_name = tool_name.clone();
+                if let Err(e) = self.start_deferred_client(os, &server_name).await {
+                    return Err(ToolResult {
+                        tool_use_id: value.id,
+                        content: vec![ToolResultContentBlock::Text(format!(
+                            "Failed to start the mcp server {server_name}: {e}"
+                        ))],
+                        status: ToolResultStatus::Error,
+                    });
+                }
+                let Some(client) = self.clients.get_mut(&server_name) else {
                     return Err(ToolResult {
                         tool_use_id: value.id,
                         content: vec![ToolResultContentBlock::Text(format!(
@@ -914,8 +1071,8 @@ impl ToolManager {
                 })?;
 
                 Tool::Custom(CustomTool {
-                    name: tool_name.to_owned(),
-                    server_name: server_name.to_owned(),
+                    name: tool_name,
+                    server_name,
                     client: running_service.clone(),
                     params: value.args.as_object().cloned(),
                 })
@@ -939,26 +1096,73 @@ impl ToolManager {
         };
 
         let mut updated_servers = HashSet::<ToolOrigin>::new();
-        let mut conflicts = HashMap::<ServerName, String>::new();
-        for (server_name, (tool_name_map, specs)) in new_tools {
+        let mut conflicts = HashMap::<ServerName, Vec<LoadingRecord>>::new();
+        for (server_name, (tool_name_map, mut specs)) in new_tools {
             // First we evict the tools that were already in the tn_map
             self.tn_map.retain(|_, tool_info| tool_info.server_name != server_name);
+            self.tool_conflicts
+                .retain(|conflict| conflict.rejected.server_name != server_name);
 
             // And update them with the new tools queried
             // valid: tools that do not have conflicts in naming
-            let (valid, invalid) = tool_name_map
+            let (mut valid, conflicting) = tool_name_map
                 .into_iter()
                 .partition::<HashMap<ModelToolName, ToolInfo>, _>(|(model_tool_name, _)| {
                     !self.tn_map.contains_key(model_tool_name)
                 });
+
+            // A tool whose name is taken by another server's tool is named after its own server
+            // instead, as in `server___tool`, so that neither shadows the other. Other tools keep
+            // their own name, which agent configs and saved conversations may refer to.
+            let namespace = server_namespace(&server_name);
+            let mut renamed = HashMap::<ModelToolName, ModelToolName>::new();
+            let mut invalid = Vec::new();
+            for (model_tool_name, tool_info) in conflicting {
+                let namespaced = namespaced_name(&namespace, &model_tool_name);
+                if model_tool_name.contains(NAMESPACE_DELIMITER)
+                    || namespaced.len() > MAX_TOOL_NAME_LEN
+                    || self.tn_map.contains_key(&namespaced)
+                    || valid.contains_key(&namespaced)
+                {
+                    invalid.push((model_tool_name, tool_info));
+                } else {
+                    valid.insert(namespaced.clone(), tool_info);
+                    renamed.insert(model_tool_name, namespaced);
+                }
+            }
+            for spec in &mut specs {
+                if let Some(name) = renamed.get(&spec.name) {
+                    spec.name = name.clone();
+                }
+            }
+            if !renamed.is_empty() {
+                let msg = renamed.iter().fold(
+                    "The following tools are named after their server because another server has a tool of the same name: \n"
+                        .to_string(),
+                    |mut acc, (model_tool_name, namespaced)| {
+                        acc.push_str(&format!(" - {model_tool_name} is {namespaced}\n"));
+                        acc
+                    },
+                );
+                conflicts
+                    .entry(server_name.clone())
+                    .or_default()
+                    .push(LoadingRecord::warn(msg));
+            }
             // We reject tools that are conflicting with the existing tools by not including them
             // in the tn_map. We would also want to report this error.
             if !invalid.is_empty() {
+                self.tool_conflicts
+                    .extend(invalid.iter().map(|(model_tool_name, tool_info)| ToolConflict {
+                        model_tool_name: model_tool_name.clone(),
+                        rejected: tool_info.clone(),
+                        kept: self.tn_map.get(model_tool_name).cloned().unwrap_or_default(),
+                    }));
                 let msg = invalid.into_iter().fold("The following tools are rejected because they conflict with existing tools in names. Avoid this via setting aliases for them: \n".to_string(), |mut acc, (model_tool_name, tool_info)| {
                     acc.push_str(&format!(" - {} from {}\n", model_tool_name, tool_info.server_name));
                     acc
                 });
-                conflicts.insert(server_name, msg);
+                conflicts.entry(server_name).or_default().push(LoadingRecord::err(msg));
             }
             if let Some(spec) = specs.first() {
                 updated_servers.insert(spec.tool_origin.clone());
@@ -983,12 +1187,8 @@ impl ToolManager {
         // if block here to avoid repeatedly asking for loc
         if !conflicts.is_empty() {
             let mut record_lock = self.mcp_load_record.lock().await;
-            for (server_name, msg) in conflicts {
-                let record = LoadingRecord::err(msg);
-                record_lock
-                    .entry(server_name)
-                    .and_modify(|v| v.push(record.clone()))
-                    .or_insert(vec![record]);
+            for (server_name, records) in conflicts {
+                record_lock.entry(server_name).or_default().extend(records);
             }
         }
     }
@@ -1006,7 +1206,9 @@ impl ToolManager {
 
             Ok(match query_result {
                 PromptQueryResult::List(list) => list,
-                PromptQueryResult::Search(_) => return Err(GetPromptError::IncorrectResponseType),
+                PromptQueryResult::Search(_) | PromptQueryResult::Resources(_) => {
+                    return Err(GetPromptError::IncorrectResponseType);
+                },
             })
         } else {
             Err(GetPromptError::MissingChannel)
@@ -1025,13 +1227,15 @@ impl ToolManager {
             (Some(_schema), None) => Some(serde_json::Map::new()),
             // Schema exists with user values - process normally
             (Some(schema), Some(value)) => {
-                let params = schema.iter().zip(value.iter()).fold(
-                    HashMap::<String, String>::new(),
-                    |mut acc, (prompt_get_arg, value)| {
+                // Optional arguments skipped while filling in the required ones are left out.
+                let params = schema
+                    .iter()
+                    .zip(value.iter())
+                    .filter(|(_, value)| !value.is_empty())
+                    .fold(HashMap::<String, String>::new(), |mut acc, (prompt_get_arg, value)| {
                         acc.insert(prompt_get_arg.name.clone(), value.clone());
                         acc
-                    },
-                );
+                    });
                 Some(
                     params
                         .into_iter()
@@ -1044,6 +1248,7 @@ impl ToolManager {
 
     pub async fn get_prompt(
         &mut self,
+        os: &Os,
         name: String,
         arguments: Option<Vec<String>>,
     ) -> Result<GetPromptResult, GetPromptError> {
@@ -1112,6 +1317,9 @@ impl ToolManager {
                     };
 
                     let server_name = &bundle.server_name;
+                    self.start_deferred_client(os, server_name)
+                        .await
+                        .map_err(GetPromptError::General)?;
                     let client = self.clients.get_mut(server_name).ok_or(GetPromptError::MissingClient)?;
                     let PromptBundle { prompt_get, .. } = bundle;
 
@@ -1134,9 +1342,70 @@ impl ToolManager {
         }
     }
 
+    /// Reads the resource at `uri` from the server that offers it.
+    pub async fn read_resource(&mut self, uri: &str) -> Result<ReadResourceResult, ReadResourceError> {
+        let Some((query_sender, query_result_receiver)) = &self.prompts_sender_receiver_pair else {
+            return Err(ReadResourceError::MissingChannel);
+        };
+        let mut new_receiver = query_result_receiver.resubscribe();
+        query_sender
+            .send(PromptQuery::ListResources)
+            .map_err(|e| ReadResourceError::General(eyre::eyre!(e)))?;
+        let PromptQueryResult::Resources(resources) = new_receiver
+            .recv()
+            .await
+            .map_err(|e| ReadResourceError::General(eyre::eyre!(e)))?
+        else {
+            return Err(ReadResourceError::IncorrectResponseType);
+        };
+
+        let server_name = resources
+            .into_iter()
+            .find_map(|(server_name, uris)| uris.iter().any(|u| u == uri).then_some(server_name))
+            .ok_or_else(|| ReadResourceError::ResourceNotFound(uri.to_string()))?;
+        let client = self
+            .clients
+            .get_mut(&server_name)
+            .ok_or(ReadResourceError::MissingClient)?;
+        let running_service = client.get_running_service().await?;
+        Ok(running_service
+            .read_resource(ReadResourceRequestParam { uri: uri.to_string() })
+            .await?)
+    }
+
     pub async fn pending_clients(&self) -> Vec<String> {
         self.pending_clients.read().await.iter().cloned().collect::<Vec<_>>()
     }
+
+    /// Starts `server_name` if its start was deferred until first use, and waits for it to
+    /// complete initialization, within the server's timeout.
+    async fn start_deferred_client(&mut self, os: &Os, server_name: &str) -> eyre::Result<()> {
+        let Some(config) = self.deferred_clients.remove(server_name) else {
+            return Ok(());
+        };
+        let Some(messenger_builder) = &self.messenger_builder else {
+            eyre::bail!("the tool manager has no messenger for the server");
+        };
+        info!("Starting {server_name} on first use");
+
+        let messenger = messenger_builder.build_with_name(server_name.to_string());
+        // The orchestrator tracks the load as it does for the servers started with chat.
+        let _ = messenger.send_init_msg().await;
+        let timeout = Duration::from_millis(config.startup_timeout.unwrap_or(config.timeout));
+        let client = McpClientService::new(server_name.to_string(), config, messenger)
+            .init(os)
+            .await?;
+        let client = match client {
+            InitializedMcpClient::Pending(handle) => match tokio::time::timeout(timeout, handle).await {
+                Ok(running_service) => InitializedMcpClient::Ready(running_service??),
+                Err(_) => eyre::bail!("the server did not start within {} ms", timeout.as_millis()),
+            },
+            ready @ InitializedMcpClient::Ready(_) => ready,
+        };
+        self.clients.insert(server_name.to_string(), client);
+
+        Ok(())
+    }
 }
 
 type DisplayTaskJoinHandle = JoinHandle<Result<(), eyre::Report>>;
@@ -1298,6 +1567,7 @@ fn spawn_orchestrator_task(
     new_tool_specs: NewToolSpecs,
     total: usize,
     conv_id: String,
+    cache: Option<McpCache>,
 ) {
     tokio::spawn(async move {
         use tokio::sync::broadcast::Sender as BroadcastSender;
@@ -1306,6 +1576,7 @@ fn spawn_orchestrator_task(
         let mut record_temp_buf = Vec::<u8>::new();
         let mut initialized = HashSet::<String>::new();
         let mut prompts = HashMap::<String, Vec<PromptBundle>>::new();
+        let mut resources = HashMap::<String, Vec<String>>::new();
 
         enum ToolFilter {
             All,
@@ -1327,6 +1598,7 @@ fn spawn_orchestrator_task(
         async fn handle_prompt_queries(
             query: PromptQuery,
             prompts: &HashMap<String, Vec<PromptBundle>>,
+            resources: &HashMap<String, Vec<String>>,
             prompt_query_response_sender: &mut BroadcastSender<PromptQueryResult>,
         ) {
             match query {
@@ -1363,6 +1635,114 @@ fn spawn_orchestrator_task(
                         error!("Error sending prompts to chat helper: {:?}", e);
                     }
                 },
+                PromptQuery::ListResources => {
+                    let query_res = PromptQueryResult::Resources(resources.clone());
+                    if let Err(e) = prompt_query_response_sender.send(query_res) {
+                        error!("Error sending resources to chat helper: {:?}", e);
+                    }
+                },
+                PromptQuery::SearchResources(search_word) => {
+                    let mut filtered_resources = resources
+                        .values()
+                        .flatten()
+                        .filter(|uri| search_word.as_ref().is_none_or(|word| uri.contains(word.as_str())))
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    filtered_resources.sort();
+                    filtered_resources.dedup();
+
+                    let query_res = PromptQueryResult::Search(filtered_resources);
+                    if let Err(e) = prompt_query_response_sender.send(query_res) {
+                        error!("Error sending resources to chat helper: {:?}", e);
+                    }
+                },
+            }
+        }
+
+        /// The specs of the tools of `server_name` that the agent includes, the aliases the agent
+        /// gives them, and the alias of the server itself, if it has one.
+        async fn included_tool_specs(
+            agent: &Arc<Mutex<Agent>>,
+            server_name: &str,
+            tools: Vec<rmcp::model::Tool>,
+        ) -> (Vec<ToolSpec>, HashMap<HostToolName, ModelToolName>, Option<String>) {
+            let agent_lock = agent.lock().await;
+
+            // We will assume all tools are allowed if the tool list consists of 1
+            // element and it's a *
+            let tool_filter = if agent_lock.tools.len() == 1
+                && agent_lock.tools.first().map(String::as_str).is_some_and(|c| c == "*")
+            {
+                ToolFilter::All
+            } else {
+                let set = agent_lock
+                    .tools
+                    .iter()
+                    .filter(|tool_name| tool_name.starts_with(&format!("@{server_name}")))
+                    .map(|full_name| {
+                        match full_name.split_once(MCP_SERVER_TOOL_DELIMITER) {
+                            Some((_, tool_name)) if !tool_name.is_empty() => tool_name,
+                            _ => "*",
+                        }
+                        .to_string()
+                    })
+                    .collect::<HashSet<_>>();
+
+                if set.contains("*") {
+                    ToolFilter::All
+                } else {
+                    ToolFilter::List(set)
+                }
+            };
+
+            // "@server/tool" aliases a tool, and "@server" the namespace of the server's tools.
+            let mut server_alias = None;
+            let mut alias_list = HashMap::<HostToolName, ModelToolName>::new();
+            for (full_path, model_tool_name) in &agent_lock.tool_aliases {
+                let Some(path) = full_path.strip_prefix('@') else {
+                    continue;
+                };
+                match path.split_once(MCP_SERVER_TOOL_DELIMITER) {
+                    Some((server, host_tool_name)) if server == server_name => {
+                        alias_list.insert(host_tool_name.to_string(), model_tool_name.clone());
+                    },
+                    None if path == server_name => server_alias = Some(model_tool_name.clone()),
+                    _ => {},
+                }
+            }
+
+            drop(agent_lock);
+
+            let specs = tools
+                .into_iter()
+                .map(|v| ToolSpec {
+                    name: v.name.to_string(),
+                    description: v.description.as_ref().map(|d| d.to_string()).unwrap_or_default(),
+                    input_schema: crate::cli::chat::tools::InputSchema(v.schema_as_json_value()),
+                    tool_origin: ToolOrigin::Native,
+                })
+                .filter(|spec| tool_filter.should_include(&spec.name))
+                .collect::<Vec<_>>();
+
+            (specs, alias_list, server_alias)
+        }
+
+        /// Replaces the prompts of `server_name` with `server_prompts`, as a list declares
+        /// everything a server offers (and not the diff).
+        fn replace_prompts(
+            prompts: &mut HashMap<String, Vec<PromptBundle>>,
+            server_name: &str,
+            server_prompts: Vec<Prompt>,
+        ) {
+            prompts
+                .values_mut()
+                .for_each(|bundles| bundles.retain(|bundle| bundle.server_name != server_name));
+
+            for prompt in server_prompts {
+                prompts.entry(prompt.name.clone()).or_default().push(PromptBundle {
+                    server_name: server_name.to_string(),
+                    prompt_get: prompt,
+                });
             }
         }
 
@@ -1387,7 +1767,9 @@ fn spawn_orchestrator_task(
             notify_weak: &std::sync::Weak<Notify>,
             initialized: &mut HashSet<String>,
             prompts: &mut HashMap<String, Vec<PromptBundle>>,
+            resources: &mut HashMap<String, Vec<String>>,
             total: usize,
+            cache: Option<&McpCache>,
         ) {
             record_temp_buf.clear();
             // For now we will treat every list result as if they contain the
@@ -1417,52 +1799,6 @@ fn spawn_orchestrator_task(
                         Err(_) => vec![],
                     };
 
-                    let (tool_filter, alias_list) = {
-                        let agent_lock = agent.lock().await;
-
-                        // We will assume all tools are allowed if the tool list consists of 1
-                        // element and it's a *
-                        let tool_filter = if agent_lock.tools.len() == 1
-                            && agent_lock.tools.first().map(String::as_str).is_some_and(|c| c == "*")
-                        {
-                            ToolFilter::All
-                        } else {
-                            let set = agent_lock
-                                .tools
-                                .iter()
-                                .filter(|tool_name| tool_name.starts_with(&format!("@{server_name}")))
-                                .map(|full_name| {
-                                    match full_name.split_once(MCP_SERVER_TOOL_DELIMITER) {
-                                        Some((_, tool_name)) if !tool_name.is_empty() => tool_name,
-                                        _ => "*",
-                                    }
-                                    .to_string()
-                                })
-                                .collect::<HashSet<_>>();
-
-                            if set.contains("*") {
-                                ToolFilter::All
-                            } else {
-                                ToolFilter::List(set)
-                            }
-                        };
-
-                        let server_prefix = format!("@{server_name}");
-                        let alias_list = agent_lock.tool_aliases.iter().fold(
-                            HashMap::<HostToolName, ModelToolName>::new(),
-                            |mut acc, (full_path, model_tool_name)| {
-                                if full_path.starts_with(&server_prefix) {
-                                    if let Some((_, host_tool_name)) = full_path.split_once(MCP_SERVER_TOOL_DELIMITER) {
-                                        acc.insert(host_tool_name.to_string(), model_tool_name.clone());
-                                    }
-                                }
-                                acc
-                            },
-                        );
-
-                        (tool_filter, alias_list)
-                    };
-
                     match result {
                         Ok(result) => {
                             if let Some(peer) = peer {
@@ -1477,27 +1813,26 @@ fn spawn_orchestrator_task(
                                 return;
                             }
 
-                            let mut specs = result
-                                .tools
-                                .into_iter()
-                                .map(|v| ToolSpec {
-                                    name: v.name.to_string(),
-                                    description: v.description.as_ref().map(|d| d.to_string()).unwrap_or_default(),
-                                    input_schema: crate::cli::chat::tools::InputSchema(v.schema_as_json_value()),
-                                    tool_origin: ToolOrigin::Native,
-                                })
-                                .filter(|spec| tool_filter.should_include(&spec.name))
-                                .collect::<Vec<_>>();
+                            if let Some(cache) = cache {
+                                let config = agent.lock().await.mcp_servers.mcp_servers.get(&server_name).cloned();
+                                if let Some(config) = config {
+                                    cache.store_tools(&server_name, &config, result.tools.clone()).await;
+                                }
+                            }
+
+                            let (mut specs, alias_list, server_alias) =
+                                included_tool_specs(agent, &server_name, result.tools).await;
                             let mut sanitized_mapping = HashMap::<ModelToolName, ToolInfo>::new();
                             let process_result = process_tool_specs(
                                 database,
                                 conv_id,
                                 &server_name,
+                                server_alias.as_deref(),
                                 &mut specs,
                                 &mut sanitized_mapping,
                                 &alias_list,
                                 regex,
-                                telemetry_clone,
+                                Some(telemetry_clone),
                                 &result_tools,
                             )
                             .await;
@@ -1621,30 +1956,16 @@ fn spawn_orchestrator_task(
                             error!("Received prompt list result from {server_name} without a peer. Ignoring.");
                             return;
                         }
-                        // We first need to clear all the PromptGets that are associated with
-                        // this server because PromptsListResult is declaring what is available
-                        // (and not the diff)
-                        prompts
-                            .values_mut()
-                            .for_each(|bundles| bundles.retain(|bundle| bundle.server_name != server_name));
-
-                        // And then we update them with the new comers
-                        for prompt in prompt_list_result.prompts {
-                            prompts
-                                .entry(prompt.name.clone())
-                                .and_modify(|bundles| {
-                                    bundles.push(PromptBundle {
-                                        server_name: server_name.clone(),
-                                        prompt_get: prompt.clone(),
-                                    });
-                                })
-                                .or_insert_with(|| {
-                                    vec![PromptBundle {
-                                        server_name: server_name.clone(),
-                                        prompt_get: prompt,
-                                    }]
-                                });
+                        if let Some(cache) = cache {
+                            let config = agent.lock().await.mcp_servers.mcp_servers.get(&server_name).cloned();
+                            if let Some(config) = config {
+                                cache
+                                    .store_prompts(&server_name, &config, prompt_list_result.prompts.clone())
+                                    .await;
+                            }
                         }
+
+                        replace_prompts(prompts, &server_name, prompt_list_result.prompts);
                     },
                     Err(e) => {
                         error!("Error fetching prompts from server {server_name}: {:?}", e);
@@ -1665,7 +1986,22 @@ fn spawn_orchestrator_task(
                             .or_insert(vec![record]);
                     },
                 },
-                UpdateEventMessage::ListResourcesResult { .. } => {},
+                UpdateEventMessage::ListResourcesResult {
+                    server_name, result, ..
+                } => match result {
+                    // Like prompts, the list declares everything the server offers.
+                    Ok(resource_list_result) => {
+                        let uris = resource_list_result
+                            .resources
+                            .into_iter()
+                            .map(|resource| resource.raw.uri)
+                            .collect();
+                        resources.insert(server_name, uris);
+                    },
+                    Err(e) => {
+                        error!("Error fetching resources from server {server_name}: {:?}", e);
+                    },
+                },
                 UpdateEventMessage::ResourceTemplatesListResult { .. } => {},
                 UpdateEventMessage::OauthLink { server_name, link } => {
                     let mut buf_writer = BufWriter::new(&mut *record_temp_buf);
@@ -1696,18 +2032,47 @@ fn spawn_orchestrator_task(
                         }
                     }
                 },
+                UpdateEventMessage::CachedLists { server_name, lists } => {
+                    // The cached tools stand in for the server's until it lists them itself, so
+                    // the server isn't considered loaded, and problems with the specs are only
+                    // reported then.
+                    let (mut specs, alias_list, server_alias) =
+                        included_tool_specs(agent, &server_name, lists.tools).await;
+                    let mut sanitized_mapping = HashMap::<ModelToolName, ToolInfo>::new();
+                    let _ = process_tool_specs(
+                        database,
+                        conv_id,
+                        &server_name,
+                        server_alias.as_deref(),
+                        &mut specs,
+                        &mut sanitized_mapping,
+                        &alias_list,
+                        regex,
+                        None,
+                        &[],
+                    )
+                    .await;
+                    new_tool_specs
+                        .lock()
+                        .await
+                        .insert(server_name.clone(), (sanitized_mapping, specs));
+                    has_new_stuff.store(true, Ordering::Release);
+
+                    replace_prompts(prompts, &server_name, lists.prompts);
+                },
                 UpdateEventMessage::InitStart { server_name, .. } => {
                     pending.write().await.insert(server_name.clone());
                     loading_servers.insert(server_name, std::time::Instant::now());
                 },
                 UpdateEventMessage::Deinit { server_name, .. } => {
-                    // Only prompts are stored here so we'll just be clearing that
+                    // Only prompts and resources are stored here so we'll just be clearing those
                     // In the future if we are also storing tools, we need to make sure that
                     // the tools are also pruned.
                     for (_prompt_name, bundles) in prompts.iter_mut() {
                         bundles.retain(|bundle| bundle.server_name != server_name);
                     }
                     prompts.retain(|_, bundles| !bundles.is_empty());
+                    resources.remove(&server_name);
                     has_new_stuff.store(true, Ordering::Release);
                 },
             }
@@ -1716,7 +2081,7 @@ fn spawn_orchestrator_task(
         loop {
             tokio::select! {
                 Ok(query) = prompt_list_receiver.recv() => {
-                    handle_prompt_queries(query, &prompts, &mut prompt_list_sender).await;
+                    handle_prompt_queries(query, &prompts, &resources, &mut prompt_list_sender).await;
                 },
                 Some(msg) = msg_rx.recv() => {
                     handle_messenger_msg(
@@ -1736,7 +2101,9 @@ fn spawn_orchestrator_task(
                             &notify_weak,
                             &mut initialized,
                             &mut prompts,
-                            total
+                            &mut resources,
+                            total,
+                            cache.as_ref(),
                         ).await;
                 },
                 // Nothing else to poll
@@ -1754,11 +2121,12 @@ async fn process_tool_specs(
     database: &Database,
     conversation_id: &str,
     server_name: &str,
+    server_alias: Option<&str>,
     specs: &mut Vec<ToolSpec>,
     tn_map: &mut HashMap<ModelToolName, ToolInfo>,
     alias_list: &HashMap<HostToolName, ModelToolName>,
     regex: &Regex,
-    telemetry: &TelemetryThread,
+    telemetry: Option<&TelemetryThread>,
     result_tools: &[String],
 ) -> eyre::Result<()> {
     // Tools are subjected to the following validations:
@@ -1766,7 +2134,8 @@ async fn process_tool_specs(
     // 2. less than 64 characters in length
     // 3. a non-empty description
     //
-    // For non-compliance due to point 1, we shall change it on behalf of the users.
+    // For non-compliance due to point 1, we shall change it on behalf of the users. For point 2,
+    // the server name is shortened if that is enough for the tool name to fit.
     // For the rest, we simply throw a warning and reject the tool.
     let mut out_of_spec_tool_names = Vec::<ToolValidationViolation>::new();
     let mut hasher = DefaultHasher::new();
@@ -1780,19 +2149,24 @@ async fn process_tool_specs(
         None
     };
 
+    // Tools keep their own name, unless the agent aliases them or their server. Tools whose name
+    // another server's tool has are named after their server by [ToolManager::update].
+    let namespace = server_alias.map(|alias| sanitize_name(alias.to_string(), regex, &mut hasher));
     for spec in specs.iter_mut() {
-        let model_tool_name = alias_list.get(&spec.name).cloned().unwrap_or({
-            if !regex.is_match(&spec.name) {
-                let mut sn = sanitize_name(spec.name.clone(), regex, &mut hasher);
-                while tn_map.contains_key(&sn) {
-                    sn.push('1');
+        let model_tool_name = match (alias_list.get(&spec.name), &namespace) {
+            (Some(alias), _) => alias.clone(),
+            (None, Some(namespace)) => {
+                namespaced_name(namespace, &sanitize_name(spec.name.clone(), regex, &mut hasher))
+            },
+            (None, None) => {
+                let mut name = sanitize_name(spec.name.clone(), regex, &mut hasher);
+                while tn_map.contains_key(&name) {
+                    name.push('1');
                 }
-                sn
-            } else {
-                spec.name.clone()
-            }
-        });
-        if model_tool_name.len() > 64 {
+                name
+            },
+        };
+        if model_tool_name.len() > MAX_TOOL_NAME_LEN {
             out_of_spec_tool_names.push(ToolValidationViolation::TooLong(spec.name.clone()));
             continue;
         } else if spec.description.is_empty() {
@@ -1822,19 +2196,21 @@ async fn process_tool_specs(
         Some(specs.iter().map(|spec| spec.name.clone()).collect::<Vec<_>>().join(","))
     };
     // Send server load success metric datum
-    let conversation_id = conversation_id.to_string();
-    let _ = telemetry
-        .send_mcp_server_init(
-            database,
-            conversation_id,
-            server_name.to_string(),
-            None,
-            number_of_tools,
-            all_tool_names,
-            loaded_tool_names,
-            number_of_tools_in_mcp_server,
-        )
-        .await;
+    if let Some(telemetry) = telemetry {
+        let conversation_id = conversation_id.to_string();
+        let _ = telemetry
+            .send_mcp_server_init(
+                database,
+                conversation_id,
+                server_name.to_string(),
+                None,
+                number_of_tools,
+                all_tool_names,
+                loaded_tool_names,
+                number_of_tools_in_mcp_server,
+            )
+            .await;
+    }
     // Tool name translation. This is beyond of the scope of what is
     // considered a "server load". Reasoning being:
     // - Failures here are not related to server load
@@ -1897,6 +2273,36 @@ fn sanitize_name(orig: String, regex: &regex::Regex, hasher: &mut impl Hasher) -
     }
 }
 
+/// The namespace of the tools of `server_name`, as in `server___tool`.
+fn server_namespace(server_name: &str) -> String {
+    static REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(VALID_TOOL_NAME).unwrap());
+    sanitize_name(server_name.to_string(), &REGEX, &mut DefaultHasher::new())
+}
+
+/// Joins `namespace` and `tool_name` into the name presented to the model.
+///
+/// If the result would exceed [MAX_TOOL_NAME_LEN], the namespace is cut down to a prefix followed
+/// by a hash of the full namespace, so that servers sharing a long prefix stay distinct. The
+/// result is still too long if `tool_name` alone leaves no room for the shortened namespace.
+fn namespaced_name(namespace: &str, tool_name: &str) -> String {
+    const HASH_LEN: usize = 6;
+
+    let name = format!("{namespace}{NAMESPACE_DELIMITER}{tool_name}");
+    let room = MAX_TOOL_NAME_LEN.saturating_sub(NAMESPACE_DELIMITER.len() + tool_name.len());
+    // Keep at least one character of the namespace so that the name still starts with a letter.
+    if name.len() <= MAX_TOOL_NAME_LEN || room <= HASH_LEN {
+        return name;
+    }
+
+    // A fresh hasher, so that the shortened namespace is the same across sessions and tools.
+    let mut hasher = DefaultHasher::new();
+    hasher.write(namespace.as_bytes());
+    let hash = format!("{:016x}", hasher.finish());
+    // Sanitized names are ascii, so slicing on byte offsets is fine.
+    let prefix = namespace[..room - HASH_LEN].trim_end_matches('_');
+    format!("{prefix}{}{NAMESPACE_DELIMITER}{tool_name}", &hash[..HASH_LEN])
+}
+
 fn queue_success_message(name: &str, time_taken: &str, output: &mut impl Write) -> eyre::Result<()> {
     Ok(queue!(
         output,
@@ -2105,6 +2511,145 @@ mod tests {
         assert_eq!(sanitized, "abc");
     }
 
+    #[tokio::test]
+    async fn test_process_tool_specs_names() {
+        let os = Os::new().await.unwrap();
+        let regex = Regex::new(VALID_TOOL_NAME).unwrap();
+        let spec = |name: &str| ToolSpec {
+            name: name.to_string(),
+            description: "A tool".to_string(),
+            input_schema: crate::cli::chat::tools::InputSchema(serde_json::json!({})),
+            tool_origin: ToolOrigin::Native,
+        };
+        let aliases = HashMap::from([("get-log".to_string(), "log".to_string())]);
+
+        let mut specs = vec![spec("status"), spec("get-log")];
+        let mut tn_map = HashMap::new();
+        process_tool_specs(
+            &os.database,
+            "conversation",
+            "my-git",
+            None,
+            &mut specs,
+            &mut tn_map,
+            &aliases,
+            &regex,
+            None,
+            &[],
+        )
+        .await
+        .unwrap();
+        let names = specs.iter().map(|spec| spec.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["status", "log"]);
+        assert_eq!(tn_map["status"], ToolInfo {
+            server_name: "my-git".to_string(),
+            host_tool_name: "status".to_string(),
+        });
+
+        // An alias of the server names its tools after it.
+        let mut specs = vec![spec("status")];
+        let mut tn_map = HashMap::new();
+        process_tool_specs(
+            &os.database,
+            "conversation",
+            "my-git",
+            Some("git"),
+            &mut specs,
+            &mut tn_map,
+            &HashMap::new(),
+            &regex,
+            None,
+            &[],
+        )
+        .await
+        .unwrap();
+        assert_eq!(specs[0].name, "git___status");
+        assert_eq!(tn_map["git___status"].server_name, "my-git");
+
+        // A long server alias is shortened rather than the tool being dropped.
+        let server_alias = "internal-developer-tools-platform-configuration-management-server";
+        let mut specs = vec![spec("status"), spec(&"x".repeat(60))];
+        let mut tn_map = HashMap::new();
+        let result = process_tool_specs(
+            &os.database,
+            "conversation",
+            "my-git",
+            Some(server_alias),
+            &mut specs,
+            &mut tn_map,
+            &HashMap::new(),
+            &regex,
+            None,
+            &[],
+        )
+        .await;
+        // A tool name that is too long on its own is still rejected.
+        assert!(result.is_err());
+        assert_eq!(specs.len(), 1);
+        let name = &specs[0].name;
+        assert!(name.len() <= MAX_TOOL_NAME_LEN, "{name}");
+        assert!(name.starts_with("internaldevelopertools"), "{name}");
+        assert!(name.ends_with("___status"), "{name}");
+        assert!(regex.is_match(name));
+        assert_eq!(tn_map[name], ToolInfo {
+            server_name: "my-git".to_string(),
+            host_tool_name: "status".to_string(),
+        });
+    }
+
+    #[tokio::test]
+    async fn test_update_names_conflicting_tools_after_their_server() {
+        let mut manager = ToolManager::default();
+        let load = |server_name: &str, tool_name: &str| {
+            let tool_info = ToolInfo {
+                server_name: server_name.to_string(),
+                host_tool_name: tool_name.to_string(),
+            };
+            let spec = ToolSpec {
+                name: tool_name.to_string(),
+                description: "A tool".to_string(),
+                input_schema: crate::cli::chat::tools::InputSchema(serde_json::json!({})),
+                tool_origin: ToolOrigin::McpServer(server_name.to_string()),
+            };
+            (
+                server_name.to_string(),
+                (HashMap::from([(tool_name.to_string(), tool_info)]), vec![spec]),
+            )
+        };
+
+        manager.new_tool_specs.lock().await.extend([load("git", "status")]);
+        manager.update().await;
+        manager.new_tool_specs.lock().await.extend([load("my-hg", "status")]);
+        manager.update().await;
+
+        assert_eq!(manager.tn_map["status"].server_name, "git");
+        assert_eq!(manager.tn_map["myhg___status"].server_name, "my-hg");
+        assert!(manager.schema.contains_key("status"));
+        assert!(manager.schema.contains_key("myhg___status"));
+        assert!(manager.tool_conflicts.is_empty());
+
+        // Only when the name after the server is taken as well is the tool left out.
+        manager.new_tool_specs.lock().await.extend([load("myhg", "status")]);
+        manager.update().await;
+        assert_eq!(manager.tool_conflicts.len(), 1);
+        assert_eq!(manager.tool_conflicts[0].rejected.server_name, "myhg");
+    }
+
+    #[test]
+    fn test_namespaced_name() {
+        assert_eq!(namespaced_name("git", "status"), "git___status");
+
+        let long = "a".repeat(60);
+        let shortened = namespaced_name(&long, "status");
+        assert_eq!(shortened.len(), MAX_TOOL_NAME_LEN);
+        assert_eq!(shortened, namespaced_name(&long, "status"));
+        // Servers sharing a long prefix stay distinct.
+        assert_ne!(shortened, namespaced_name(&format!("{long}b"), "status"));
+
+        let tool = "t".repeat(60);
+        assert!(namespaced_name("git", &tool).len() > MAX_TOOL_NAME_LEN);
+    }
+
     #[test]
     fn test_server_prompt_name_parsing() {
         // Test parsing server/prompt format
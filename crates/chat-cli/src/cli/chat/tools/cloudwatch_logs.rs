@@ -0,0 +1,547 @@
+//! The `cloudwatch_logs` tool, with which the model runs Logs Insights queries and tails log groups
+//! through the AWS CLI, and gets back the log events as compact JSON rather than the raw output of
+//! `aws logs`.
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::process::Stdio;
+use std::time::Duration;
+
+use chrono::{
+    DateTime,
+    TimeDelta,
+    TimeZone,
+    Utc,
+};
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+    eyre,
+};
+use globset::Glob;
+use serde::Deserialize;
+use serde_json::{
+    Map,
+    Value,
+    json,
+};
+use tracing::error;
+
+use super::{
+    InvokeOutput,
+    MAX_TOOL_RESPONSE_SIZE,
+    OutputKind,
+    env_vars_with_user_agent,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::cli::chat::util::truncate_safe;
+use crate::os::Os;
+use crate::util::time::parse_duration;
+use crate::util::tool_permission_checker::is_tool_in_allowlist;
+
+/// The rows of a query, or events of a tail, returned when the model does not say.
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// How long a query may run before it is stopped and its results so far returned.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(60);
+const QUERY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+const DEFAULT_TAIL_SECONDS: u64 = 30;
+const MAX_TAIL_SECONDS: u64 = 120;
+const TAIL_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The longest a single field, such as a log message, may be.
+const MAX_FIELD_SIZE: usize = 2_000;
+
+/// The most bytes of rows returned, leaving the rest of the tool response for the conversation.
+const MAX_ROWS_SIZE: usize = MAX_TOOL_RESPONSE_SIZE / 4;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum CloudwatchLogs {
+    /// Runs a Logs Insights query.
+    Query(LogsQuery),
+    /// Collects the new events of a log group for some seconds.
+    Tail(LogsTail),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogsQuery {
+    pub log_groups: Vec<String>,
+    pub query: String,
+    /// How far back to query, like `1h`, or an RFC 3339 timestamp.
+    #[serde(default = "default_since")]
+    pub since: String,
+    /// The end of the query, like `10m` ago or an RFC 3339 timestamp, now by default.
+    pub until: Option<String>,
+    pub limit: Option<usize>,
+    pub region: String,
+    pub profile_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LogsTail {
+    pub log_group: String,
+    /// A CloudWatch Logs filter pattern, such as `ERROR` or `{ $.level = "error" }`.
+    pub filter_pattern: Option<String>,
+    pub seconds: Option<u64>,
+    pub limit: Option<usize>,
+    pub region: String,
+    pub profile_name: Option<String>,
+}
+
+fn default_since() -> String {
+    "1h".to_string()
+}
+
+impl CloudwatchLogs {
+    fn log_groups(&self) -> Vec<&str> {
+        match self {
+            CloudwatchLogs::Query(query) => query.log_groups.iter().map(String::as_str).collect(),
+            CloudwatchLogs::Tail(tail) => vec![tail.log_group.as_str()],
+        }
+    }
+
+    pub async fn validate(&mut self, _os: &Os) -> Result<()> {
+        match self {
+            CloudwatchLogs::Query(query) => {
+                if query.log_groups.is_empty() {
+                    bail!("At least one log group is required");
+                }
+                if query.query.trim().is_empty() {
+                    bail!("The query cannot be empty");
+                }
+                let now = Utc::now();
+                let start = parse_time(&query.since, now)?;
+                let end = query.until.as_deref().map(|until| parse_time(until, now)).transpose()?;
+                if end.is_some_and(|end| end <= start) {
+                    bail!("The end of the query must be after its start");
+                }
+            },
+            CloudwatchLogs::Tail(tail) => {
+                if tail.log_group.trim().is_empty() {
+                    bail!("The log group cannot be empty");
+                }
+                if tail
+                    .seconds
+                    .is_some_and(|seconds| seconds == 0 || seconds > MAX_TAIL_SECONDS)
+                {
+                    bail!("A log group can be tailed for 1 to {MAX_TAIL_SECONDS} seconds");
+                }
+            },
+        }
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, _output: &mut impl Write) -> Result<InvokeOutput> {
+        let result = match self {
+            CloudwatchLogs::Query(query) => query.invoke(os).await?,
+            CloudwatchLogs::Tail(tail) => tail.invoke(os).await?,
+        };
+        Ok(InvokeOutput {
+            output: OutputKind::Json(result),
+        })
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        match self {
+            CloudwatchLogs::Query(query) => {
+                queue!(
+                    output,
+                    style::Print("Querying "),
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(query.log_groups.join(", ")),
+                    style::ResetColor,
+                    style::Print(format!(" from {}", query.since)),
+                )?;
+                if let Some(until) = &query.until {
+                    queue!(output, style::Print(format!(" until {until}")))?;
+                }
+                queue!(
+                    output,
+                    style::Print(format!(" in {}:\n\n", query.region)),
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!("{}\n", query.query.trim())),
+                    style::ResetColor,
+                )?;
+            },
+            CloudwatchLogs::Tail(tail) => {
+                queue!(
+                    output,
+                    style::Print("Tailing "),
+                    style::SetForegroundColor(Color::Green),
+                    style::Print(&tail.log_group),
+                    style::ResetColor,
+                    style::Print(format!(
+                        " in {} for {}s",
+                        tail.region,
+                        tail.seconds.unwrap_or(DEFAULT_TAIL_SECONDS)
+                    )),
+                )?;
+                if let Some(pattern) = &tail.filter_pattern {
+                    queue!(output, style::Print(format!(", matching {pattern}")))?;
+                }
+                queue!(output, style::Print("\n"))?;
+            },
+        }
+        Ok(())
+    }
+
+    pub fn eval_perm(&self, _os: &Os, agent: &Agent) -> PermissionEvalResult {
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Settings {
+            #[serde(default)]
+            allowed_log_groups: Vec<String>,
+            #[serde(default)]
+            denied_log_groups: Vec<String>,
+        }
+
+        let is_in_allowlist = is_tool_in_allowlist(&agent.allowed_tools, "cloudwatch_logs", None);
+        match agent.tools_settings.get("cloudwatch_logs") {
+            Some(settings) => {
+                let settings = match serde_json::from_value::<Settings>(settings.clone()) {
+                    Ok(settings) => settings,
+                    Err(e) => {
+                        error!("Failed to deserialize tool settings for cloudwatch_logs: {:?}", e);
+                        return PermissionEvalResult::Ask;
+                    },
+                };
+                let log_groups = self.log_groups();
+                let denied = log_groups
+                    .iter()
+                    .filter(|group| matches_any(&settings.denied_log_groups, group))
+                    .map(|group| group.to_string())
+                    .collect::<Vec<_>>();
+                if !denied.is_empty() {
+                    return PermissionEvalResult::Deny(denied);
+                }
+                if is_in_allowlist
+                    || log_groups
+                        .iter()
+                        .all(|group| matches_any(&settings.allowed_log_groups, group))
+                {
+                    return PermissionEvalResult::Allow;
+                }
+                PermissionEvalResult::Ask
+            },
+            None if is_in_allowlist => PermissionEvalResult::Allow,
+            _ => PermissionEvalResult::Ask,
+        }
+    }
+}
+
+impl LogsQuery {
+    async fn invoke(&self, os: &Os) -> Result<Value> {
+        let now = Utc::now();
+        let start = parse_time(&self.since, now)?;
+        let end = self.until.as_deref().map_or(Ok(now), |until| parse_time(until, now))?;
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let mut args = vec!["start-query".to_string(), "--log-group-names".to_string()];
+        args.extend(self.log_groups.iter().cloned());
+        args.extend([
+            "--start-time".to_string(),
+            start.timestamp().to_string(),
+            "--end-time".to_string(),
+            end.timestamp().to_string(),
+            "--query-string".to_string(),
+            self.query.clone(),
+            "--limit".to_string(),
+            limit.to_string(),
+        ]);
+        let started = aws_logs(os, &self.region, self.profile_name.as_deref(), args).await?;
+        let query_id = started["queryId"]
+            .as_str()
+            .ok_or_else(|| eyre!("The query was not started"))?
+            .to_string();
+
+        let deadline = tokio::time::Instant::now() + QUERY_TIMEOUT;
+        let results = loop {
+            let results = aws_logs(os, &self.region, self.profile_name.as_deref(), vec![
+                "get-query-results".to_string(),
+                "--query-id".to_string(),
+                query_id.clone(),
+            ])
+            .await?;
+            match results["status"].as_str().unwrap_or_default() {
+                "Scheduled" | "Running" if tokio::time::Instant::now() < deadline => {
+                    tokio::time::sleep(QUERY_POLL_INTERVAL).await;
+                },
+                "Scheduled" | "Running" => {
+                    // Returns what was found so far rather than nothing.
+                    let _ = aws_logs(os, &self.region, self.profile_name.as_deref(), vec![
+                        "stop-query".to_string(),
+                        "--query-id".to_string(),
+                        query_id.clone(),
+                    ])
+                    .await;
+                    break results;
+                },
+                "Complete" => break results,
+                status => bail!("The query ended with the status {status}"),
+            }
+        };
+        Ok(query_output(&results))
+    }
+}
+
+impl LogsTail {
+    async fn invoke(&self, os: &Os) -> Result<Value> {
+        let seconds = self.seconds.unwrap_or(DEFAULT_TAIL_SECONDS).min(MAX_TAIL_SECONDS);
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        let start = Utc::now();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(seconds);
+
+        let mut seen = HashSet::new();
+        let mut events = Vec::new();
+        let mut matched = 0;
+        loop {
+            let mut args = vec![
+                "filter-log-events".to_string(),
+                "--log-group-name".to_string(),
+                self.log_group.clone(),
+                "--start-time".to_string(),
+                start.timestamp_millis().to_string(),
+                "--max-items".to_string(),
+                MAX_LIMIT.to_string(),
+            ];
+            if let Some(pattern) = &self.filter_pattern {
+                args.extend(["--filter-pattern".to_string(), pattern.clone()]);
+            }
+            let found = aws_logs(os, &self.region, self.profile_name.as_deref(), args).await?;
+            for event in found["events"].as_array().into_iter().flatten() {
+                // Every poll finds the events of the earlier ones again.
+                if !seen.insert(event["eventId"].as_str().unwrap_or_default().to_string()) {
+                    continue;
+                }
+                matched += 1;
+                if events.len() < limit {
+                    events.push(tail_event(event));
+                }
+            }
+            if matched >= limit || tokio::time::Instant::now() + TAIL_POLL_INTERVAL > deadline {
+                break;
+            }
+            tokio::time::sleep(TAIL_POLL_INTERVAL).await;
+        }
+
+        events.sort_by(|a, b| a["timestamp"].as_str().cmp(&b["timestamp"].as_str()));
+        let (events, _) = fit_rows(events);
+        Ok(json!({
+            "logGroup": self.log_group,
+            "seconds": seconds,
+            "events": events,
+            "omittedEvents": matched - events.len(),
+        }))
+    }
+}
+
+/// The results of `get-query-results` as one object per row, without the internal `@ptr` field.
+fn query_output(results: &Value) -> Value {
+    let rows = results["results"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|fields| {
+            let mut row = Map::new();
+            for field in fields.as_array().into_iter().flatten() {
+                let Some(name) = field["field"].as_str() else {
+                    continue;
+                };
+                if name == "@ptr" {
+                    continue;
+                }
+                row.insert(
+                    name.to_string(),
+                    Value::String(truncate_field(&string(&field["value"]))),
+                );
+            }
+            Value::Object(row)
+        })
+        .collect::<Vec<_>>();
+    let (rows, omitted) = fit_rows(rows);
+    let statistics = &results["statistics"];
+    json!({
+        "status": results["status"],
+        "rows": rows,
+        "omittedRows": omitted,
+        "recordsMatched": statistics["recordsMatched"],
+        "recordsScanned": statistics["recordsScanned"],
+    })
+}
+
+fn tail_event(event: &Value) -> Value {
+    let timestamp = event["timestamp"]
+        .as_i64()
+        .and_then(|millis| Utc.timestamp_millis_opt(millis).single())
+        .map(|time| time.to_rfc3339())
+        .unwrap_or_default();
+    json!({
+        "timestamp": timestamp,
+        "logStream": event["logStreamName"],
+        "message": truncate_field(string(&event["message"]).trim_end()),
+    })
+}
+
+/// The rows that fit in [MAX_ROWS_SIZE], and how many were left out.
+fn fit_rows(rows: Vec<Value>) -> (Vec<Value>, usize) {
+    let total = rows.len();
+    let mut size = 0;
+    let rows = rows
+        .into_iter()
+        .take_while(|row| {
+            size += row.to_string().len();
+            size <= MAX_ROWS_SIZE
+        })
+        .collect::<Vec<_>>();
+    let omitted = total - rows.len();
+    (rows, omitted)
+}
+
+fn truncate_field(value: &str) -> String {
+    match truncate_safe(value, MAX_FIELD_SIZE) {
+        truncated if truncated.len() < value.len() => format!("{truncated}... (truncated)"),
+        _ => value.to_string(),
+    }
+}
+
+fn string(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+/// A time given as a duration before `now`, like `15m`, or as an RFC 3339 timestamp.
+fn parse_time(text: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text.trim()) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let ago = parse_duration(text)
+        .map_err(|err| eyre!("`{text}` is neither an RFC 3339 timestamp nor a duration like 1h: {err}"))?;
+    TimeDelta::from_std(ago)
+        .ok()
+        .and_then(|ago| now.checked_sub_signed(ago))
+        .ok_or_else(|| eyre!("`{text}` is too far in the past"))
+}
+
+/// Whether `log_group` is one of `patterns`, which may be globs like `/aws/lambda/*`.
+fn matches_any(patterns: &[String], log_group: &str) -> bool {
+    patterns.iter().any(|pattern| match Glob::new(pattern) {
+        Ok(glob) => glob.compile_matcher().is_match(log_group),
+        Err(_) => pattern == log_group,
+    })
+}
+
+/// Runs `aws logs` with `args`, returning the JSON it printed.
+async fn aws_logs(os: &Os, region: &str, profile_name: Option<&str>, args: Vec<String>) -> Result<Value> {
+    let mut command = tokio::process::Command::new("aws");
+    command
+        .envs(env_vars_with_user_agent(os))
+        .args(["--region", region, "--output", "json"]);
+    if let Some(profile_name) = profile_name {
+        command.args(["--profile", profile_name]);
+    }
+    let output = command
+        .arg("logs")
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .wrap_err("Unable to run the aws cli, is it installed?")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    if output.stdout.iter().all(u8::is_ascii_whitespace) {
+        return Ok(Value::Null);
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::agent::ToolSettingTarget;
+
+    #[test]
+    fn test_parse_time() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            parse_time("90m", now).unwrap(),
+            Utc.with_ymd_and_hms(2025, 6, 1, 10, 30, 0).unwrap()
+        );
+        assert_eq!(
+            parse_time("2025-06-01T09:00:00+02:00", now).unwrap(),
+            Utc.with_ymd_and_hms(2025, 6, 1, 7, 0, 0).unwrap()
+        );
+        assert!(parse_time("yesterday", now).is_err());
+        assert!(parse_time("99999999999d", now).is_err());
+    }
+
+    #[test]
+    fn test_query_output() {
+        let output = query_output(&json!({
+            "status": "Complete",
+            "results": [
+                [
+                    { "field": "@timestamp", "value": "2025-06-01 12:00:00.000" },
+                    { "field": "@message", "value": "x".repeat(MAX_FIELD_SIZE + 10) },
+                    { "field": "@ptr", "value": "CmAKJwoj..." }
+                ]
+            ],
+            "statistics": { "recordsMatched": 1.0, "recordsScanned": 250.0, "bytesScanned": 9000.0 }
+        }));
+        let row = &output["rows"][0];
+        assert_eq!(row["@timestamp"], "2025-06-01 12:00:00.000");
+        assert!(row["@message"].as_str().unwrap().ends_with("... (truncated)"));
+        assert!(row.get("@ptr").is_none());
+        assert_eq!(output["omittedRows"], 0);
+        assert_eq!(output["recordsScanned"], 250.0);
+    }
+
+    #[tokio::test]
+    async fn test_eval_perm() {
+        let os = Os::new().await.unwrap();
+        let mut agent = Agent::default();
+        agent.tools_settings.insert(
+            ToolSettingTarget("cloudwatch_logs".to_string()),
+            json!({
+                "allowedLogGroups": ["/aws/lambda/*"],
+                "deniedLogGroups": ["/aws/lambda/payments-*"]
+            }),
+        );
+        let tail = |log_group: &str| {
+            CloudwatchLogs::Tail(LogsTail {
+                log_group: log_group.to_string(),
+                filter_pattern: None,
+                seconds: None,
+                limit: None,
+                region: "us-east-1".to_string(),
+                profile_name: None,
+            })
+        };
+
+        assert!(matches!(
+            tail("/aws/lambda/orders").eval_perm(&os, &agent),
+            PermissionEvalResult::Allow
+        ));
+        assert!(matches!(
+            tail("/aws/lambda/payments-api").eval_perm(&os, &agent),
+            PermissionEvalResult::Deny(_)
+        ));
+        assert!(matches!(
+            tail("/ecs/web").eval_perm(&os, &agent),
+            PermissionEvalResult::Ask
+        ));
+    }
+}
@@ -3,8 +3,10 @@ use std::collections::HashMap;
 use std::io::Write;
 
 use crossterm::{
+    cursor,
     queue,
     style,
+    terminal,
 };
 use eyre::Result;
 use rmcp::model::CallToolRequestParam;
@@ -16,14 +18,17 @@ use serde::{
 use tracing::warn;
 
 use super::InvokeOutput;
+use super::retrieve_output::truncate_with_handle;
 use crate::cli::agent::{
     Agent,
     PermissionEvalResult,
 };
 use crate::cli::chat::CONTINUATION_LINE;
+use crate::cli::chat::consts::MAX_TOOL_RESPONSE_SIZE;
 use crate::cli::chat::token_counter::TokenCounter;
 use crate::mcp_client::{
     RunningService,
+    ToolProgress,
     oauth_util,
 };
 use crate::os::Os;
@@ -65,12 +70,19 @@ pub struct CustomToolConfig {
     /// A list of arguments to be used to run the command with
     #[serde(default)]
     pub args: Vec<String>,
-    /// A list of environment variables to run the command with
+    /// A list of environment variables to run the command with. Values can refer to the
+    /// environment of chat with ${env:VAR}
     #[serde(skip_serializing_if = "Option::is_none")]
     pub env: Option<HashMap<String, String>>,
+    /// The directory to run the command in. Defaults to the directory chat is started in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
     /// Timeout for each mcp request in ms
-    #[serde(default = "default_timeout")]
+    #[serde(default = "default_timeout", alias = "requestTimeout")]
     pub timeout: u64,
+    /// Timeout for the server to start and complete initialization in ms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub startup_timeout: Option<u64>,
     /// A boolean flag to denote whether or not to load this mcp server
     #[serde(default)]
     pub disabled: bool,
@@ -113,22 +125,75 @@ impl CustomTool {
         format!("@{}{}{}", self.server_name, MCP_SERVER_TOOL_DELIMITER, self.name)
     }
 
-    pub async fn invoke(&self, _os: &Os, _updates: &mut impl Write) -> Result<InvokeOutput> {
+    pub async fn invoke(&self, os: &Os, updates: &mut impl Write) -> Result<InvokeOutput> {
         let params = CallToolRequestParam {
             name: Cow::from(self.name.clone()),
             arguments: self.params.clone(),
         };
 
-        let resp = self.client.call_tool(params.clone()).await?;
+        let (progress_tx, mut progress_rx) = tokio::sync::watch::channel(None::<ToolProgress>);
+        let call = self.client.call_tool_with_progress(params, progress_tx);
+        tokio::pin!(call);
+        let mut shown_progress = false;
+        let resp = loop {
+            tokio::select! {
+                resp = &mut call => break resp,
+                Ok(()) = progress_rx.changed() => {
+                    if let Some(progress) = progress_rx.borrow_and_update().clone() {
+                        queue!(
+                            updates,
+                            terminal::Clear(terminal::ClearType::CurrentLine),
+                            cursor::MoveToColumn(0),
+                            style::SetForegroundColor(style::Color::DarkGrey),
+                            style::Print(format!("{CONTINUATION_LINE} {}", progress.render())),
+                            style::ResetColor,
+                        )?;
+                        updates.flush()?;
+                        shown_progress = true;
+                    }
+                },
+            }
+        };
+        if shown_progress {
+            queue!(
+                updates,
+                terminal::Clear(terminal::ClearType::CurrentLine),
+                cursor::MoveToColumn(0)
+            )?;
+        }
+
+        let resp = match resp {
+            Err(rmcp::ServiceError::Timeout { timeout }) => eyre::bail!(
+                "The mcp server {} did not respond to {} within {} ms",
+                self.server_name,
+                self.name,
+                timeout.as_millis()
+            ),
+            Err(err @ (rmcp::ServiceError::TransportSend(_) | rmcp::ServiceError::TransportClosed)) => {
+                match self.client.stderr_excerpt() {
+                    Some(stderr) => eyre::bail!(
+                        "{err}\nThe last lines the mcp server {} wrote to its stderr:\n{stderr}",
+                        self.server_name
+                    ),
+                    None => return Err(err.into()),
+                }
+            },
+            resp => resp?,
+        };
 
-        if resp.is_error.is_none_or(|v| !v) {
+        if resp.is_error.is_some_and(|v| v) {
+            warn!("Tool call for {} failed", self.name);
+        }
+
+        let output = serde_json::json!(resp);
+        let serialized = output.to_string();
+        if serialized.len() > MAX_TOOL_RESPONSE_SIZE {
             Ok(InvokeOutput {
-                output: super::OutputKind::Json(serde_json::json!(resp)),
+                output: super::OutputKind::Text(truncate_with_handle(os, &serialized, MAX_TOOL_RESPONSE_SIZE).await),
             })
         } else {
-            warn!("Tool call for {} failed", self.name);
             Ok(InvokeOutput {
-                output: super::OutputKind::Json(serde_json::json!(resp)),
+                output: super::OutputKind::Json(output),
             })
         }
     }
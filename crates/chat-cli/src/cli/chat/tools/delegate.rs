@@ -3,7 +3,10 @@ use std::io::{
     stdin,
     stdout,
 };
-use std::path::PathBuf;
+use std::path::{
+    Path,
+    PathBuf,
+};
 
 use chrono::Utc;
 use crossterm::style::{
@@ -25,10 +28,15 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use strip_ansi_escapes::strip_str;
 use strum::{
     Display,
     EnumString,
 };
+use tokio::io::{
+    AsyncBufReadExt,
+    BufReader,
+};
 
 use crate::cli::agent::Agents;
 use crate::cli::chat::tools::{
@@ -44,6 +52,17 @@ use crate::cli::{
     DEFAULT_AGENT_NAME,
 };
 use crate::os::Os;
+use crate::util::directories::canonicalizes_path;
+use crate::util::shutdown;
+
+/// Appended to the task of an agent run with `wait`, so that the end of its output summarizes the
+/// result for the parent conversation.
+const SUMMARY_INSTRUCTION: &str =
+    "\n\nWhen you are done, end your response with a short summary of what you did and what the outcome was.";
+
+/// Maximum length of the output of a child agent returned to the parent conversation. The
+/// summary is at the end, so earlier output is dropped first.
+const MAX_RESULT_LENGTH: usize = 10_000;
 
 /// Launch and manage async agent processes. Delegate tasks to agents that run independently in
 /// background.
@@ -55,6 +74,9 @@ use crate::os::Os;
 ///
 /// Only one task per agent. Files stored in ~/.aws/amazonq/.subagents/
 ///
+/// With `wait`, a launched agent runs in the foreground instead: its progress is streamed to the
+/// user and its final response is returned as the result of the tool use.
+///
 /// Examples:
 /// - Launch: {"operation": "launch", "agent": "rust-agent", "task": "Create snake game"}
 /// - Status: {"operation": "status", "agent": "rust-agent"}
@@ -69,6 +91,12 @@ pub struct Delegate {
     /// Task description (required for launch operation)
     #[serde(default)]
     pub task: Option<String>,
+    /// Directory to run the agent in (optional - defaults to the current working directory)
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    /// Wait for the agent to finish and return its final response (optional - defaults to false)
+    #[serde(default)]
+    pub wait: bool,
 }
 
 #[derive(Serialize, Clone, Deserialize, Debug, Display, JsonSchema)]
@@ -87,7 +115,7 @@ impl Delegate {
         ExperimentManager::is_enabled(os, ExperimentName::Delegate)
     }
 
-    pub async fn invoke(&self, os: &Os, _output: &mut impl Write, agents: &Agents) -> Result<InvokeOutput> {
+    pub async fn invoke(&self, os: &Os, output: &mut impl Write, agents: &Agents) -> Result<InvokeOutput> {
         if !Self::is_enabled(os) {
             return Ok(InvokeOutput {
                 output: OutputKind::Text(
@@ -104,8 +132,17 @@ impl Delegate {
                     .ok_or(eyre::eyre!("Task description is required for launch operation"))?;
 
                 let agent_name = self.agent.as_deref().unwrap_or(DEFAULT_AGENT_NAME);
-
-                launch_agent(os, agent_name, agents, task).await?
+                let cwd = self
+                    .working_directory
+                    .as_deref()
+                    .map(|dir| resolve_working_directory(os, dir))
+                    .transpose()?;
+
+                if self.wait {
+                    run_agent(agent_name, agents, task, cwd.as_deref(), output).await?
+                } else {
+                    launch_agent(os, agent_name, agents, task, cwd.as_deref()).await?
+                }
             },
             Operation::Status => match &self.agent {
                 Some(agent_name) => status_agent(os, agent_name).await?,
@@ -130,7 +167,18 @@ impl Delegate {
 
     pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
         match self.operation {
-            Operation::Launch => queue!(output, style::Print("Delegating task to agent\n"))?,
+            Operation::Launch => {
+                queue!(
+                    output,
+                    style::Print(format!(
+                        "Delegating task to agent {}\n",
+                        self.agent.as_deref().unwrap_or(DEFAULT_AGENT_NAME)
+                    ))
+                )?;
+                if let Some(dir) = &self.working_directory {
+                    queue!(output, style::Print(format!("Working directory: {dir}\n")))?;
+                }
+            },
             Operation::Status => queue!(output, style::Print("Checking agent status\n"))?,
             Operation::List => queue!(output, style::Print("Listing available agents\n"))?,
         }
@@ -139,7 +187,7 @@ impl Delegate {
     }
 }
 
-pub async fn launch_agent(os: &Os, agent: &str, agents: &Agents, task: &str) -> Result<String> {
+pub async fn launch_agent(os: &Os, agent: &str, agents: &Agents, task: &str, cwd: Option<&Path>) -> Result<String> {
     validate_agent_availability(os, agent).await?;
 
     // Check if agent is already running
@@ -152,17 +200,135 @@ pub async fn launch_agent(os: &Os, agent: &str, agents: &Agents, task: &str) ->
         }
     }
 
+    confirm_launch(agent, agents, task).await?;
+    spawn_agent_process(os, agent, task, cwd).await?;
+
+    Ok(format_launch_success(agent, task))
+}
+
+/// Runs `task` with `agent` in a child process until it finishes, streaming its progress to
+/// `output`, and returns its final response.
+pub async fn run_agent(
+    agent: &str,
+    agents: &Agents,
+    task: &str,
+    cwd: Option<&Path>,
+    output: &mut impl Write,
+) -> Result<String> {
+    confirm_launch(agent, agents, task).await?;
+
+    let mut cmd = agent_command(agent, &format!("{task}{SUMMARY_INSTRUCTION}"), cwd);
+    cmd.kill_on_drop(true);
+    let mut child = cmd.spawn()?;
+    let _child_guard = child
+        .id()
+        .map(|pid| shutdown::track_child(pid, format!("delegate agent {agent}")));
+
+    let mut stdout = BufReader::new(child.stdout.take().ok_or(eyre::eyre!("Missing agent stdout"))?).lines();
+    let mut stderr = BufReader::new(child.stderr.take().ok_or(eyre::eyre!("Missing agent stderr"))?).lines();
+    let mut response = String::new();
+    let mut errors = String::new();
+    let (mut stdout_done, mut stderr_done) = (false, false);
+    while !stdout_done || !stderr_done {
+        let line = tokio::select! {
+            line = stdout.next_line(), if !stdout_done => match line? {
+                Some(line) => {
+                    response.push_str(&line);
+                    response.push('\n');
+                    Some(line)
+                },
+                None => {
+                    stdout_done = true;
+                    None
+                },
+            },
+            line = stderr.next_line(), if !stderr_done => match line? {
+                Some(line) => {
+                    errors.push_str(&line);
+                    errors.push('\n');
+                    Some(line)
+                },
+                None => {
+                    stderr_done = true;
+                    None
+                },
+            },
+        };
+
+        // Spinners redraw the line with carriage returns, only the final state is interesting.
+        let Some(line) = line.map(|line| strip_str(line.rsplit('\r').next().unwrap_or_default())) else {
+            continue;
+        };
+        if !line.trim().is_empty() {
+            queue!(
+                output,
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!("  │ {line}\n")),
+                SetForegroundColor(Color::Reset),
+            )?;
+            output.flush()?;
+        }
+    }
+
+    let status = child.wait().await?;
+    if !status.success() {
+        bail!(
+            "Agent '{agent}' failed ({status}):\n{}",
+            tail(strip_str(&errors).trim(), MAX_RESULT_LENGTH)
+        );
+    }
+
+    Ok(format!(
+        "Agent '{agent}' finished the task.\n\nFinal response:\n{}",
+        tail(strip_str(&response).trim(), MAX_RESULT_LENGTH)
+    ))
+}
+
+/// Resolves the working directory of a child agent relative to the current one.
+pub fn resolve_working_directory(os: &Os, dir: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(canonicalizes_path(os, dir)?);
+    if !path.is_dir() {
+        bail!("{} is not a directory", path.display());
+    }
+    Ok(path)
+}
+
+async fn confirm_launch(agent: &str, agents: &Agents, task: &str) -> Result<()> {
     if agent == DEFAULT_AGENT_NAME {
         // Show warning for default agent but no approval needed
-        display_default_agent_warning()?;
+        display_default_agent_warning()
     } else {
         // Show agent info and require approval for specific agents
-        request_user_approval(agent, agents, task).await?;
+        request_user_approval(agent, agents, task).await
     }
+}
 
-    spawn_agent_process(os, agent, task).await?;
+/// The `q chat` invocation running `task` with `agent`, without any user input.
+fn agent_command(agent: &str, task: &str, cwd: Option<&Path>) -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new("q");
+    cmd.args(["chat", "--no-interactive", "--agent", agent, task]);
+    if let Some(cwd) = cwd {
+        cmd.current_dir(cwd);
+    }
 
-    Ok(format_launch_success(agent, task))
+    // Redirect to capture output (runs silently)
+    cmd.stdout(std::process::Stdio::piped());
+    cmd.stderr(std::process::Stdio::piped());
+    cmd.stdin(std::process::Stdio::null()); // No user input
+
+    #[cfg(not(windows))]
+    cmd.process_group(0);
+
+    cmd
+}
+
+/// The last `max_len` bytes of `s`, adjusted to a char boundary.
+fn tail(s: &str, max_len: usize) -> &str {
+    let mut start = s.len().saturating_sub(max_len);
+    while !s.is_char_boundary(start) {
+        start += 1;
+    }
+    &s[start..]
 }
 
 fn format_launch_success(agent: &str, task: &str) -> String {
@@ -321,22 +487,11 @@ impl From<&Agent> for AgentConfig {
     }
 }
 
-pub async fn spawn_agent_process(os: &Os, agent: &str, task: &str) -> Result<AgentExecution> {
+pub async fn spawn_agent_process(os: &Os, agent: &str, task: &str, cwd: Option<&Path>) -> Result<AgentExecution> {
     let now = Utc::now();
 
     // Run Q chat with specific agent in background, non-interactive
-    let mut cmd = tokio::process::Command::new("q");
-    cmd.args(["chat", "--agent", agent, task]);
-
-    // Redirect to capture output (runs silently)
-    cmd.stdout(std::process::Stdio::piped());
-    cmd.stderr(std::process::Stdio::piped());
-    cmd.stdin(std::process::Stdio::null()); // No user input
-
-    #[cfg(not(windows))]
-    cmd.process_group(0);
-
-    let child = cmd.spawn()?;
+    let child = agent_command(agent, task, cwd).spawn()?;
     let pid = child.id().ok_or(eyre::eyre!("Process spawned had already exited"))?;
 
     let execution = AgentExecution {
@@ -486,7 +641,9 @@ pub async fn request_user_approval(agent: &str, agents: &Agents, task: &str) ->
         .ok_or(eyre::eyre!("No agent by the name {agent} found"))?
         .into();
     display_agent_info(agent, task, &config)?;
-    get_user_confirmation()?;
+    if !get_user_confirmation()? {
+        bail!("The user declined to run the task with agent '{agent}'");
+    }
 
     Ok(())
 }
@@ -527,6 +684,13 @@ pub async fn subagents_dir(os: &Os) -> Result<PathBuf> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tail() {
+        assert_eq!(tail("hello", 10), "hello");
+        assert_eq!(tail("hello", 3), "llo");
+        assert_eq!(tail("héllo", 4), "llo");
+    }
+
     #[test]
     fn get_schema() {
         let schema = schemars::schema_for!(Delegate);
@@ -0,0 +1,786 @@
+//! The `dependency_report` tool, which reads the lockfiles of a project and reports the licenses
+//! of its dependencies and the versions with known vulnerabilities.
+//!
+//! Vulnerabilities are looked up in a local directory of advisories in the [OSV] format, such as
+//! the exports of osv.dev or the RustSec advisory database, so that nothing about the project is
+//! sent anywhere.
+//!
+//! [OSV]: https://ossf.github.io/osv-schema/
+
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+    sanitize_path_tool_arg,
+};
+use crate::os::Os;
+use crate::util::directories;
+
+/// Licenses that place obligations on the projects depending on them.
+const COPYLEFT_LICENSES: &[&str] = &["AGPL", "GPL", "LGPL", "MPL", "EPL", "CDDL", "SSPL", "EUPL", "OSL"];
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DependencyReport {
+    /// The directory of the project, the current directory by default.
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Only report these packages, as `name` or `name@version`. Packages that aren't dependencies
+    /// yet are checked too, to tell whether they are safe to add.
+    #[serde(default)]
+    pub packages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Ecosystem {
+    #[serde(rename = "crates.io")]
+    Cargo,
+    #[serde(rename = "npm")]
+    Npm,
+    #[serde(rename = "PyPI")]
+    PyPi,
+}
+
+impl Ecosystem {
+    const ALL: [Self; 3] = [Self::Cargo, Self::Npm, Self::PyPi];
+
+    /// The name of the ecosystem in OSV advisories.
+    fn osv_name(self) -> &'static str {
+        match self {
+            Self::Cargo => "crates.io",
+            Self::Npm => "npm",
+            Self::PyPi => "PyPI",
+        }
+    }
+
+    /// Package names as they are compared, PyPI names being case and separator insensitive.
+    fn normalize(self, name: &str) -> String {
+        match self {
+            Self::PyPi => name.to_lowercase().replace(['_', '.'], "-"),
+            Self::Cargo | Self::Npm => name.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Dependency {
+    pub ecosystem: Ecosystem,
+    pub name: String,
+    /// `None` when the lockfile doesn't pin it, such as `requests>=2` in a requirements file.
+    pub version: Option<String>,
+    pub license: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Finding {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    /// The first version fixing it, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fixed: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Vulnerable<'a> {
+    #[serde(flatten)]
+    dependency: &'a Dependency,
+    advisories: Vec<Finding>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report<'a> {
+    lockfiles: Vec<String>,
+    dependency_count: usize,
+    advisory_db: String,
+    /// `None` when there is no advisory database, so vulnerabilities weren't checked.
+    advisory_count: Option<usize>,
+    /// How many dependencies use each license.
+    licenses: BTreeMap<String, usize>,
+    copyleft: Vec<&'a Dependency>,
+    vulnerable: Vec<Vulnerable<'a>>,
+    /// Dependencies whose version isn't pinned, which can't be checked for vulnerabilities.
+    unpinned: Vec<&'a str>,
+    /// The reported dependencies, when only some packages were asked for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    dependencies: Option<Vec<&'a Dependency>>,
+}
+
+impl DependencyReport {
+    pub async fn invoke(&self, os: &Os, _output: &mut impl Write) -> Result<InvokeOutput> {
+        let dir = self.dir(os)?;
+        let (lockfiles, mut dependencies) = read_lockfiles(os, &dir).await?;
+        if lockfiles.is_empty() && self.packages.is_empty() {
+            bail!(
+                "No Cargo.lock, package-lock.json or requirements*.txt found in {}",
+                dir.display()
+            );
+        }
+        fill_cargo_licenses(os, &mut dependencies).await;
+
+        let asked_for = !self.packages.is_empty();
+        if asked_for {
+            dependencies = self.select(dependencies, &lockfiles);
+        }
+
+        let advisory_dir = directories::advisory_db_dir(os)?;
+        let advisories = match os.fs.exists(&advisory_dir) {
+            true => Some(load_advisories(advisory_dir.clone(), &dependencies).await?),
+            false => None,
+        };
+
+        let mut licenses = BTreeMap::new();
+        for dependency in &dependencies {
+            let license = dependency.license.clone().unwrap_or_else(|| "unknown".to_string());
+            *licenses.entry(license).or_default() += 1;
+        }
+
+        let report = Report {
+            lockfiles: lockfiles.iter().map(|path| path.display().to_string()).collect(),
+            dependency_count: dependencies.len(),
+            advisory_db: advisory_dir.display().to_string(),
+            advisory_count: advisories.as_ref().map(|advisories| advisories.count),
+            licenses,
+            copyleft: dependencies
+                .iter()
+                .filter(|d| d.license.as_deref().is_some_and(is_copyleft))
+                .collect(),
+            vulnerable: match &advisories {
+                Some(advisories) => dependencies
+                    .iter()
+                    .filter_map(|dependency| {
+                        let findings = advisories.findings(dependency);
+                        (!findings.is_empty()).then_some(Vulnerable {
+                            dependency,
+                            advisories: findings,
+                        })
+                    })
+                    .collect(),
+                None => Vec::new(),
+            },
+            unpinned: dependencies
+                .iter()
+                .filter(|d| d.version.is_none())
+                .map(|d| d.name.as_str())
+                .collect(),
+            dependencies: asked_for.then(|| dependencies.iter().collect()),
+        };
+
+        Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::to_value(&report)?),
+        })
+    }
+
+    /// The dependencies asked for, with the packages that aren't dependencies yet added in every
+    /// ecosystem of the project.
+    fn select(&self, dependencies: Vec<Dependency>, lockfiles: &[PathBuf]) -> Vec<Dependency> {
+        let mut ecosystems = lockfiles
+            .iter()
+            .filter_map(|path| lockfile_ecosystem(path))
+            .collect::<Vec<_>>();
+        ecosystems.sort();
+        ecosystems.dedup();
+        if ecosystems.is_empty() {
+            ecosystems = Ecosystem::ALL.to_vec();
+        }
+
+        let mut selected = Vec::new();
+        for package in &self.packages {
+            let (name, version) = match package.rsplit_once('@') {
+                // Scoped npm packages start with an @.
+                Some((name, version)) if !name.is_empty() => (name, Some(version)),
+                _ => (package.as_str(), None),
+            };
+            let matches = dependencies
+                .iter()
+                .filter(|d| {
+                    d.ecosystem.normalize(&d.name) == d.ecosystem.normalize(name)
+                        && version.is_none_or(|version| d.version.as_deref() == Some(version))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            if matches.is_empty() {
+                selected.extend(ecosystems.iter().map(|ecosystem| Dependency {
+                    ecosystem: *ecosystem,
+                    name: name.to_string(),
+                    version: version.map(str::to_string),
+                    license: None,
+                }));
+            } else {
+                selected.extend(matches);
+            }
+        }
+        selected
+    }
+
+    pub fn queue_description(&self, os: &Os, output: &mut impl Write) -> Result<()> {
+        let dir = self.dir(os)?;
+        queue!(output, style::Print("Checking the dependencies of "))?;
+        queue!(
+            output,
+            style::SetForegroundColor(Color::Green),
+            style::Print(dir.display()),
+            style::ResetColor,
+        )?;
+        if !self.packages.is_empty() {
+            queue!(output, style::Print(format!(" ({})", self.packages.join(", "))))?;
+        }
+        Ok(())
+    }
+
+    pub async fn validate(&self, os: &Os) -> Result<()> {
+        let dir = self.dir(os)?;
+        if !os.fs.exists(&dir) {
+            bail!("'{}' does not exist", dir.display());
+        }
+        Ok(())
+    }
+
+    fn dir(&self, os: &Os) -> Result<PathBuf> {
+        Ok(match &self.path {
+            Some(path) => sanitize_path_tool_arg(os, path),
+            None => os.env.current_dir()?,
+        })
+    }
+}
+
+fn is_copyleft(license: &str) -> bool {
+    // `MIT OR Apache-2.0 OR LGPL-2.1` can be used under a permissive license.
+    let upper = license.to_uppercase();
+    upper.split(" OR ").all(|choice| {
+        COPYLEFT_LICENSES.iter().any(|copyleft| {
+            choice
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .any(|part| part == *copyleft)
+        })
+    })
+}
+
+fn lockfile_ecosystem(path: &Path) -> Option<Ecosystem> {
+    let name = path.file_name()?.to_str()?;
+    match name {
+        "Cargo.lock" => Some(Ecosystem::Cargo),
+        "package-lock.json" => Some(Ecosystem::Npm),
+        _ if name.starts_with("requirements") && name.ends_with(".txt") => Some(Ecosystem::PyPi),
+        _ => None,
+    }
+}
+
+/// The lockfiles in `dir`, and the dependencies they lock.
+async fn read_lockfiles(os: &Os, dir: &Path) -> Result<(Vec<PathBuf>, Vec<Dependency>)> {
+    let mut paths = Vec::new();
+    let mut entries = os.fs.read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if lockfile_ecosystem(&entry.path()).is_some() {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+
+    let mut dependencies = Vec::new();
+    for path in &paths {
+        let content = os.fs.read_to_string(path).await?;
+        let parsed = match lockfile_ecosystem(path) {
+            Some(Ecosystem::Cargo) => parse_cargo_lock(&content),
+            Some(Ecosystem::Npm) => parse_package_lock(&content),
+            Some(Ecosystem::PyPi) => Ok(parse_requirements(&content)),
+            None => continue,
+        };
+        match parsed {
+            Ok(parsed) => dependencies.extend(parsed),
+            Err(err) => bail!("Failed to parse {}: {err}", path.display()),
+        }
+    }
+    Ok((paths, dependencies))
+}
+
+fn parse_cargo_lock(content: &str) -> Result<Vec<Dependency>> {
+    #[derive(Deserialize)]
+    struct CargoLock {
+        #[serde(default)]
+        package: Vec<Package>,
+    }
+    #[derive(Deserialize)]
+    struct Package {
+        name: String,
+        version: String,
+        /// Workspace members have no source.
+        source: Option<String>,
+    }
+
+    Ok(toml::from_str::<CargoLock>(content)?
+        .package
+        .into_iter()
+        .filter(|package| package.source.is_some())
+        .map(|package| Dependency {
+            ecosystem: Ecosystem::Cargo,
+            name: package.name,
+            version: Some(package.version),
+            license: None,
+        })
+        .collect())
+}
+
+fn parse_package_lock(content: &str) -> Result<Vec<Dependency>> {
+    let lock = serde_json::from_str::<Value>(content)?;
+    let dependency = |name: &str, entry: &Value| Dependency {
+        ecosystem: Ecosystem::Npm,
+        name: name.to_string(),
+        version: entry.get("version").and_then(Value::as_str).map(str::to_string),
+        license: entry.get("license").and_then(Value::as_str).map(str::to_string),
+    };
+
+    let mut dependencies = Vec::new();
+    // Lockfile versions 2 and 3, keyed by the install path.
+    if let Some(packages) = lock.get("packages").and_then(Value::as_object) {
+        for (path, entry) in packages {
+            let Some((_, name)) = path.rsplit_once("node_modules/") else {
+                // The project itself.
+                continue;
+            };
+            if entry.get("link").and_then(Value::as_bool) == Some(true) {
+                continue;
+            }
+            dependencies.push(dependency(name, entry));
+        }
+        return Ok(dependencies);
+    }
+
+    // Lockfile version 1, nested by dependency.
+    fn walk(deps: &serde_json::Map<String, Value>, out: &mut Vec<(String, Value)>) {
+        for (name, entry) in deps {
+            out.push((name.clone(), entry.clone()));
+            if let Some(nested) = entry.get("dependencies").and_then(Value::as_object) {
+                walk(nested, out);
+            }
+        }
+    }
+    let mut entries = Vec::new();
+    if let Some(deps) = lock.get("dependencies").and_then(Value::as_object) {
+        walk(deps, &mut entries);
+    }
+    Ok(entries.iter().map(|(name, entry)| dependency(name, entry)).collect())
+}
+
+fn parse_requirements(content: &str) -> Vec<Dependency> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.split('#').next()?.split(';').next()?.trim();
+            // Options such as `-r dev.txt`, and URLs.
+            if line.is_empty() || line.starts_with('-') || line.contains("://") {
+                return None;
+            }
+            let end = line
+                .find(|c: char| !(c.is_ascii_alphanumeric() || "-_.".contains(c)))
+                .unwrap_or(line.len());
+            if end == 0 {
+                return None;
+            }
+            let (name, spec) = line.split_at(end);
+            // Extras, `requests[security]==2.31.0`.
+            let spec = match spec.strip_prefix('[') {
+                Some(rest) => rest.split_once(']').map_or("", |(_, spec)| spec),
+                None => spec,
+            };
+            Some(Dependency {
+                ecosystem: Ecosystem::PyPi,
+                name: name.to_string(),
+                version: spec
+                    .trim()
+                    .strip_prefix("==")
+                    .map(|version| version.trim().to_string())
+                    .filter(|version| !version.contains('*')),
+                license: None,
+            })
+        })
+        .collect()
+}
+
+/// Reads the licenses of crates from the sources cargo downloaded, when it did.
+async fn fill_cargo_licenses(os: &Os, dependencies: &mut [Dependency]) {
+    let cargo_home = match os.env.get("CARGO_HOME") {
+        Ok(cargo_home) => PathBuf::from(cargo_home),
+        Err(_) => match directories::home_dir(os) {
+            Ok(home) => home.join(".cargo"),
+            Err(_) => return,
+        },
+    };
+    let mut registries = Vec::new();
+    if let Ok(mut entries) = os.fs.read_dir(cargo_home.join("registry").join("src")).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            registries.push(entry.path());
+        }
+    }
+
+    for dependency in dependencies.iter_mut().filter(|d| d.ecosystem == Ecosystem::Cargo) {
+        let Some(version) = &dependency.version else {
+            continue;
+        };
+        for registry in &registries {
+            let manifest = registry
+                .join(format!("{}-{version}", dependency.name))
+                .join("Cargo.toml");
+            let Ok(content) = os.fs.read_to_string(&manifest).await else {
+                continue;
+            };
+            dependency.license = toml::from_str::<toml::Value>(&content)
+                .ok()
+                .and_then(|manifest| Some(manifest.get("package")?.get("license")?.as_str()?.to_string()));
+            break;
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Advisory {
+    id: String,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    withdrawn: Option<String>,
+    #[serde(default)]
+    affected: Vec<Affected>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Affected {
+    package: AffectedPackage,
+    #[serde(default)]
+    ranges: Vec<AffectedRange>,
+    #[serde(default)]
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedPackage {
+    ecosystem: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AffectedRange {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    events: Vec<RangeEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RangeEvent {
+    introduced: Option<String>,
+    fixed: Option<String>,
+    last_affected: Option<String>,
+}
+
+/// The advisories of the ecosystems being reported.
+#[derive(Debug, Default)]
+struct Advisories {
+    /// How many advisories the database has.
+    count: usize,
+    advisories: Vec<Advisory>,
+    /// The indexes in `advisories` of each package's.
+    by_package: HashMap<(Ecosystem, String), Vec<usize>>,
+}
+
+impl Advisories {
+    fn add(&mut self, advisory: Advisory, wanted: &HashMap<&'static str, Ecosystem>) {
+        self.count += 1;
+        if advisory.withdrawn.is_some() {
+            return;
+        }
+        let mut keys = advisory
+            .affected
+            .iter()
+            .filter_map(|affected| {
+                let ecosystem = *wanted.get(affected.package.ecosystem.as_str())?;
+                Some((ecosystem, ecosystem.normalize(&affected.package.name)))
+            })
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return;
+        }
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            self.by_package.entry(key).or_default().push(self.advisories.len());
+        }
+        self.advisories.push(advisory);
+    }
+
+    fn findings(&self, dependency: &Dependency) -> Vec<Finding> {
+        let Some(version) = &dependency.version else {
+            return Vec::new();
+        };
+        let key = (dependency.ecosystem, dependency.ecosystem.normalize(&dependency.name));
+        let Some(indexes) = self.by_package.get(&key) else {
+            return Vec::new();
+        };
+
+        indexes
+            .iter()
+            .map(|index| &self.advisories[*index])
+            .filter_map(|advisory| {
+                let affected = advisory.affected.iter().filter(|affected| {
+                    affected.package.ecosystem == dependency.ecosystem.osv_name()
+                        && dependency.ecosystem.normalize(&affected.package.name) == key.1
+                });
+                let mut vulnerable = false;
+                let mut fixed = None;
+                for affected in affected {
+                    if let Some(range_fixed) = affected_by(affected, version) {
+                        vulnerable = true;
+                        fixed = fixed.or(range_fixed);
+                    }
+                }
+                vulnerable.then(|| Finding {
+                    id: advisory.id.clone(),
+                    summary: advisory.summary.clone(),
+                    fixed,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Whether `version` is affected, with the version fixing it if there is one.
+fn affected_by(affected: &Affected, version: &str) -> Option<Option<String>> {
+    if affected.versions.iter().any(|v| v == version) {
+        return Some(None);
+    }
+
+    let parsed = parse_version(version)?;
+    for range in affected.ranges.iter().filter(|range| range.kind != "GIT") {
+        let mut introduced = None;
+        for event in &range.events {
+            if let Some(v) = &event.introduced {
+                introduced = parse_version(v);
+            }
+            let Some(start) = &introduced else {
+                continue;
+            };
+            if let Some(fixed) = &event.fixed {
+                if parsed >= *start && parse_version(fixed).is_some_and(|fixed| parsed < fixed) {
+                    return Some(Some(fixed.clone()));
+                }
+                introduced = None;
+            } else if let Some(last) = &event.last_affected {
+                if parsed >= *start && parse_version(last).is_some_and(|last| parsed <= last) {
+                    return Some(None);
+                }
+                introduced = None;
+            }
+        }
+        // Introduced and never fixed.
+        if introduced.is_some_and(|start| parsed >= start) {
+            return Some(None);
+        }
+    }
+    None
+}
+
+/// Parses `version` as semver, padding versions such as `2.1` that PyPI allows.
+fn parse_version(version: &str) -> Option<semver::Version> {
+    let version = version.trim().trim_start_matches('v');
+    if let Ok(version) = semver::Version::parse(version) {
+        return Some(version);
+    }
+    let mut parts = version
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    parts.resize(3, 0);
+    Some(semver::Version::new(parts[0], parts[1], parts[2]))
+}
+
+/// Reads the advisories in `dir` that affect the ecosystems of `dependencies`.
+async fn load_advisories(dir: PathBuf, dependencies: &[Dependency]) -> Result<Advisories> {
+    let wanted = dependencies
+        .iter()
+        .map(|d| (d.ecosystem.osv_name(), d.ecosystem))
+        .collect::<HashMap<_, _>>();
+
+    Ok(tokio::task::spawn_blocking(move || {
+        let mut advisories = Advisories::default();
+        for entry in walkdir::WalkDir::new(dir).into_iter().flatten() {
+            if entry.path().extension().is_none_or(|extension| extension != "json") {
+                continue;
+            }
+            let Ok(content) = std::fs::read(entry.path()) else {
+                continue;
+            };
+            // A file has an advisory, or a list of them.
+            if let Ok(advisory) = serde_json::from_slice::<Advisory>(&content) {
+                advisories.add(advisory, &wanted);
+            } else if let Ok(list) = serde_json::from_slice::<Vec<Advisory>>(&content) {
+                for advisory in list {
+                    advisories.add(advisory, &wanted);
+                }
+            }
+        }
+        advisories
+    })
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cargo_lock() {
+        let lock = r#"
+version = 4
+
+[[package]]
+name = "chat_cli"
+version = "1.0.0"
+
+[[package]]
+name = "serde"
+version = "1.0.219"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        assert_eq!(parse_cargo_lock(lock).unwrap(), vec![Dependency {
+            ecosystem: Ecosystem::Cargo,
+            name: "serde".to_string(),
+            version: Some("1.0.219".to_string()),
+            license: None,
+        }]);
+    }
+
+    #[test]
+    fn test_parse_package_lock() {
+        let v3 = r#"{
+            "lockfileVersion": 3,
+            "packages": {
+                "": { "name": "app" },
+                "node_modules/left-pad": { "version": "1.3.0", "license": "WTFPL" },
+                "node_modules/a/node_modules/@scope/b": { "version": "2.0.0", "license": "MIT" },
+                "node_modules/local": { "link": true }
+            }
+        }"#;
+        let deps = parse_package_lock(v3).unwrap();
+        assert_eq!(deps.len(), 2);
+        assert!(
+            deps.iter()
+                .any(|d| d.name == "@scope/b" && d.license.as_deref() == Some("MIT"))
+        );
+
+        let v1 = r#"{
+            "lockfileVersion": 1,
+            "dependencies": {
+                "a": { "version": "1.0.0", "dependencies": { "b": { "version": "0.1.0" } } }
+            }
+        }"#;
+        let deps = parse_package_lock(v1).unwrap();
+        assert_eq!(deps.iter().map(|d| d.name.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_parse_requirements() {
+        let requirements = "# pinned\nrequests[security]==2.31.0\nDjango==4.2 ; python_version >= \"3.8\"\nflask>=2\n-r dev.txt\nnumpy\n";
+        let deps = parse_requirements(requirements)
+            .into_iter()
+            .map(|d| (d.name, d.version))
+            .collect::<Vec<_>>();
+        assert_eq!(deps, vec![
+            ("requests".to_string(), Some("2.31.0".to_string())),
+            ("Django".to_string(), Some("4.2".to_string())),
+            ("flask".to_string(), None),
+            ("numpy".to_string(), None),
+        ]);
+    }
+
+    #[test]
+    fn test_findings() {
+        let advisory = serde_json::from_value::<Advisory>(serde_json::json!({
+            "id": "GHSA-xxxx",
+            "summary": "Something bad",
+            "affected": [{
+                "package": { "ecosystem": "PyPI", "name": "Django" },
+                "ranges": [{
+                    "type": "ECOSYSTEM",
+                    "events": [{ "introduced": "4.0" }, { "fixed": "4.2.1" }, { "introduced": "5.0" }]
+                }]
+            }]
+        }))
+        .unwrap();
+        let mut advisories = Advisories::default();
+        advisories.add(advisory, &HashMap::from([("PyPI", Ecosystem::PyPi)]));
+
+        let django = |version: &str| Dependency {
+            ecosystem: Ecosystem::PyPi,
+            name: "django".to_string(),
+            version: Some(version.to_string()),
+            license: None,
+        };
+        assert_eq!(advisories.findings(&django("4.2")), vec![Finding {
+            id: "GHSA-xxxx".to_string(),
+            summary: Some("Something bad".to_string()),
+            fixed: Some("4.2.1".to_string()),
+        }]);
+        assert!(advisories.findings(&django("4.2.1")).is_empty());
+        assert!(advisories.findings(&django("3.2")).is_empty());
+        assert_eq!(advisories.findings(&django("5.1")).len(), 1);
+    }
+
+    #[test]
+    fn test_is_copyleft() {
+        assert!(is_copyleft("GPL-3.0-only"));
+        assert!(is_copyleft("LGPL-2.1 OR MPL-2.0"));
+        assert!(!is_copyleft("MIT OR Apache-2.0"));
+        assert!(!is_copyleft("MIT OR LGPL-2.1"));
+        assert!(!is_copyleft("MIT"));
+    }
+
+    #[test]
+    fn test_select_packages_not_in_lockfiles() {
+        let report = DependencyReport {
+            path: None,
+            packages: vec!["serde@1.0.0".to_string(), "@scope/pkg".to_string()],
+        };
+        let selected = report.select(vec![], &[PathBuf::from("package-lock.json")]);
+        assert_eq!(
+            selected
+                .iter()
+                .map(|d| (d.ecosystem, d.name.as_str(), d.version.as_deref()))
+                .collect::<Vec<_>>(),
+            vec![
+                (Ecosystem::Npm, "serde", Some("1.0.0")),
+                (Ecosystem::Npm, "@scope/pkg", None)
+            ]
+        );
+    }
+}
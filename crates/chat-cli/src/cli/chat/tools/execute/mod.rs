@@ -1,3 +1,4 @@
+use std::collections::VecDeque;
 use std::io::Write;
 
 use crossterm::queue;
@@ -16,6 +17,7 @@ use crate::cli::agent::{
     PermissionEvalResult,
 };
 use crate::cli::chat::sanitize_unicode_tags;
+use crate::cli::chat::tools::retrieve_output::truncate_with_handle;
 use crate::cli::chat::tools::{
     InvokeOutput,
     MAX_TOOL_RESPONSE_SIZE,
@@ -36,6 +38,18 @@ mod unix;
 #[cfg(not(windows))]
 pub use unix::*;
 
+#[cfg(any(windows, test))]
+pub mod powershell;
+
+/// The name of the tool running shell commands on this platform.
+pub const EXECUTE_TOOL_NAME: &str = if cfg!(windows) {
+    "execute_powershell"
+} else {
+    "execute_bash"
+};
+/// The name the tool had on Windows before it ran PowerShell, still honored in agent configs.
+const LEGACY_WINDOWS_TOOL_NAME: &str = "execute_cmd";
+
 // Common readonly commands that are safe to execute without user confirmation
 pub const READONLY_COMMANDS: &[&str] = &[
     "ls", "cat", "echo", "pwd", "which", "head", "tail", "find", "grep", "dir", "type",
@@ -45,6 +59,10 @@ pub const READONLY_COMMANDS: &[&str] = &[
 pub struct ExecuteCommand {
     pub command: String,
     pub summary: Option<String>,
+    /// How the command is run when it differs from what the model asked for, shown to the user
+    /// and returned to the model.
+    #[serde(skip)]
+    pub notice: Option<String>,
 }
 
 impl ExecuteCommand {
@@ -67,6 +85,13 @@ impl ExecuteCommand {
             return false;
         }
 
+        #[cfg(windows)]
+        {
+            if allow_read_only && powershell::is_read_only(&self.command) {
+                return false;
+            }
+        }
+
         let Some(args) = shlex::split(&self.command) else {
             return true;
         };
@@ -141,15 +166,22 @@ impl ExecuteCommand {
     }
 
     pub async fn invoke(&self, os: &Os, output: &mut impl Write) -> Result<InvokeOutput> {
-        let output = run_command(os, &self.command, MAX_TOOL_RESPONSE_SIZE / 3, Some(output)).await?;
-        let clean_stdout = sanitize_unicode_tags(&output.stdout);
-        let clean_stderr = sanitize_unicode_tags(&output.stderr);
-
-        let result = serde_json::json!({
+        // The output is truncated here rather than by run_command, so that the whole of it can be
+        // kept for retrieve_output.
+        let output = run_command(os, &self.command, usize::MAX, Some(output)).await?;
+        let clean_stdout =
+            truncate_with_handle(os, &sanitize_unicode_tags(&output.stdout), MAX_TOOL_RESPONSE_SIZE / 3).await;
+        let clean_stderr =
+            truncate_with_handle(os, &sanitize_unicode_tags(&output.stderr), MAX_TOOL_RESPONSE_SIZE / 3).await;
+
+        let mut result = serde_json::json!({
             "exit_status": output.exit_status.unwrap_or(0).to_string(),
             "stdout": clean_stdout,
             "stderr": clean_stderr,
         });
+        if let Some(notice) = &self.notice {
+            result["notice"] = serde_json::Value::String(notice.clone());
+        }
 
         Ok(InvokeOutput {
             output: OutputKind::Json(result),
@@ -157,6 +189,14 @@ impl ExecuteCommand {
     }
 
     pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        if let Some(ref notice) = self.notice {
+            queue!(
+                output,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print(format!("{notice}\n")),
+                style::ResetColor
+            )?;
+        }
         queue!(output, style::Print("I will run the following shell command: "),)?;
 
         // TODO: Could use graphemes for a better heuristic
@@ -204,9 +244,15 @@ impl ExecuteCommand {
         }
 
         let Self { command, .. } = self;
-        let tool_name = if cfg!(windows) { "execute_cmd" } else { "execute_bash" };
-        let is_in_allowlist = is_tool_in_allowlist(&agent.allowed_tools, tool_name, None);
-        match agent.tools_settings.get(tool_name) {
+        let tool_names: &[&str] = if cfg!(windows) {
+            &[EXECUTE_TOOL_NAME, LEGACY_WINDOWS_TOOL_NAME]
+        } else {
+            &[EXECUTE_TOOL_NAME]
+        };
+        let is_in_allowlist = tool_names
+            .iter()
+            .any(|name| is_tool_in_allowlist(&agent.allowed_tools, name, None));
+        match tool_names.iter().find_map(|name| agent.tools_settings.get(*name)) {
             Some(settings) => {
                 let Settings {
                     allowed_commands,
@@ -215,7 +261,7 @@ impl ExecuteCommand {
                 } = match serde_json::from_value::<Settings>(settings.clone()) {
                     Ok(settings) => settings,
                     Err(e) => {
-                        error!("Failed to deserialize tool settings for execute_bash: {:?}", e);
+                        error!("Failed to deserialize tool settings for {EXECUTE_TOOL_NAME}: {:?}", e);
                         return PermissionEvalResult::Ask;
                     },
                 };
@@ -268,6 +314,35 @@ pub fn format_output(output: &str, max_size: usize) -> String {
     )
 }
 
+/// The last [LastLines::MAX_LINES] lines of an output streamed while a command runs, and how many
+/// lines before them were not kept.
+#[derive(Debug, Default)]
+struct LastLines {
+    lines: VecDeque<String>,
+    dropped: usize,
+}
+
+impl LastLines {
+    const MAX_LINES: usize = 1024;
+
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= Self::MAX_LINES {
+            self.lines.pop_front();
+            self.dropped += 1;
+        }
+        self.lines.push_back(line);
+    }
+
+    /// The kept lines, after a note saying how many were not kept if any.
+    fn into_output(self) -> String {
+        let lines = self.lines.into_iter().collect::<Vec<_>>().join("\n");
+        match self.dropped {
+            0 => lines,
+            dropped => format!("[the first {dropped} lines of the output were not kept]\n{lines}"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -275,6 +350,22 @@ mod tests {
     use super::*;
     use crate::cli::agent::ToolSettingTarget;
 
+    #[test]
+    fn test_last_lines() {
+        let mut lines = LastLines::default();
+        lines.push("a".to_string());
+        lines.push("b".to_string());
+        assert_eq!(lines.into_output(), "a\nb");
+
+        let mut lines = LastLines::default();
+        for i in 0..LastLines::MAX_LINES + 2 {
+            lines.push(i.to_string());
+        }
+        let output = lines.into_output();
+        assert!(output.starts_with("[the first 2 lines of the output were not kept]\n2\n"));
+        assert!(output.ends_with(&format!("\n{}", LastLines::MAX_LINES + 1)));
+    }
+
     #[test]
     fn test_requires_acceptance_for_readonly_commands() {
         let cmds = &[
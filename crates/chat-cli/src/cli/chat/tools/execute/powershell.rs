@@ -0,0 +1,119 @@
+//! Trust heuristics for the commands of `execute_powershell`, the Windows counterpart of
+//! `execute_bash`.
+
+/// Told to the user and the model when the model asks for `execute_bash` on Windows.
+pub const BASH_NOTICE: &str = "execute_bash isn't available on Windows, the command ran in PowerShell instead. Use \
+                               execute_powershell with PowerShell syntax for the next commands.";
+
+/// Cmdlets and aliases that only read, and are safe to run without the user's confirmation.
+pub const READONLY_CMDLETS: &[&str] = &[
+    "get-childitem",
+    "get-content",
+    "get-item",
+    "get-itemproperty",
+    "get-location",
+    "get-command",
+    "get-help",
+    "get-member",
+    "get-process",
+    "get-service",
+    "get-date",
+    "test-path",
+    "resolve-path",
+    "split-path",
+    "join-path",
+    "select-string",
+    "select-object",
+    "where-object",
+    "sort-object",
+    "measure-object",
+    "format-table",
+    "format-list",
+    "out-string",
+    "convertto-json",
+    "write-output",
+    "write-host",
+    // Aliases
+    "ls",
+    "dir",
+    "gci",
+    "cat",
+    "type",
+    "gc",
+    "pwd",
+    "gl",
+    "gi",
+    "gcm",
+    "gps",
+    "sls",
+    "select",
+    "where",
+    "sort",
+    "measure",
+    "ft",
+    "fl",
+    "echo",
+    "write",
+    "whoami",
+];
+
+/// Syntax that can run other commands, write files, or hide what runs: statement separators, the
+/// call operator, subexpressions, script blocks, redirection and escapes.
+const DANGEROUS_PATTERNS: &[&str] = &[";", "&", "`", "(", "{", ">", "\n", "\r", "$env:", "--%"];
+
+/// Whether every command of the pipeline in `command` is a read-only cmdlet.
+pub fn is_read_only(command: &str) -> bool {
+    if DANGEROUS_PATTERNS.iter().any(|p| command.contains(p)) {
+        return false;
+    }
+
+    // A `|` in a quoted argument splits the pipeline at the wrong place, which errs on the side of
+    // asking.
+    command.split('|').all(|segment| {
+        segment
+            .split_whitespace()
+            .next()
+            .is_some_and(|cmdlet| READONLY_CMDLETS.contains(&cmdlet.to_lowercase().as_str()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_read_only() {
+        let cmds = &[
+            ("Get-ChildItem -Recurse -Filter *.rs", true),
+            ("get-content README.md | Select-String TODO", true),
+            ("ls | Sort-Object Length | Select-Object -First 5", true),
+            ("dir", true),
+            ("Test-Path C:\\Windows", true),
+            // Writes
+            ("Remove-Item file.txt", false),
+            ("Get-ChildItem | Remove-Item", false),
+            ("Get-Content a.txt > b.txt", false),
+            ("Set-Content a.txt hello", false),
+            // Runs other commands
+            ("Get-Date; Remove-Item file.txt", false),
+            ("Get-Date && Remove-Item file.txt", false),
+            ("Get-Date || Remove-Item file.txt", false),
+            ("& Remove-Item file.txt", false),
+            ("Write-Output $(Remove-Item file.txt)", false),
+            ("Get-ChildItem | Where-Object { Remove-Item $_ }", false),
+            ("Get-ChildItem | ForEach-Object Delete", false),
+            ("Invoke-Expression 'rm file.txt'", false),
+            ("Get-Date `\nRemove-Item file.txt", false),
+            ("", false),
+        ];
+        for (cmd, expected) in cmds {
+            assert_eq!(
+                is_read_only(cmd),
+                *expected,
+                "expected command: `{}` to have is_read_only: `{}`",
+                cmd,
+                expected
+            );
+        }
+    }
+}
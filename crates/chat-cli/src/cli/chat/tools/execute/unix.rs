@@ -1,4 +1,3 @@
-use std::collections::VecDeque;
 use std::io::Write;
 use std::process::Stdio;
 
@@ -12,10 +11,12 @@ use tracing::error;
 
 use super::{
     CommandResult,
+    LastLines,
     env_vars_with_user_agent,
     format_output,
 };
 use crate::os::Os;
+use crate::util::shutdown;
 
 /// Run a bash command on Unix systems.
 /// # Arguments
@@ -45,6 +46,7 @@ pub async fn run_command<W: Write>(
         .stderr(Stdio::piped())
         .spawn()
         .wrap_err_with(|| format!("Unable to spawn command '{}'", command))?;
+    let _child_guard = child.id().map(|pid| shutdown::track_child(pid, "execute_bash"));
 
     let stdout_final: String;
     let stderr_final: String;
@@ -60,9 +62,8 @@ pub async fn run_command<W: Write>(
         let stderr = tokio::io::BufReader::new(stderr);
         let mut stderr = stderr.lines();
 
-        const LINE_COUNT: usize = 1024;
-        let mut stdout_buf = VecDeque::with_capacity(LINE_COUNT);
-        let mut stderr_buf = VecDeque::with_capacity(LINE_COUNT);
+        let mut stdout_buf = LastLines::default();
+        let mut stderr_buf = LastLines::default();
 
         let mut stdout_done = false;
         let mut stderr_done = false;
@@ -72,10 +73,7 @@ pub async fn run_command<W: Write>(
                 line = stdout.next_line(), if !stdout_done => match line {
                     Ok(Some(line)) => {
                         writeln!(u, "{line}")?;
-                        if stdout_buf.len() >= LINE_COUNT {
-                            stdout_buf.pop_front();
-                        }
-                        stdout_buf.push_back(line);
+                        stdout_buf.push(line);
                     },
                     Ok(None) => stdout_done = true,
                     Err(err) => error!(%err, "Failed to read stdout of child process"),
@@ -83,10 +81,7 @@ pub async fn run_command<W: Write>(
                 line = stderr.next_line(), if !stderr_done => match line {
                     Ok(Some(line)) => {
                         writeln!(u, "{line}")?;
-                        if stderr_buf.len() >= LINE_COUNT {
-                            stderr_buf.pop_front();
-                        }
-                        stderr_buf.push_back(line);
+                        stderr_buf.push(line);
                     },
                     Ok(None) => stderr_done = true,
                     Err(err) => error!(%err, "Failed to read stderr of child process"),
@@ -100,8 +95,8 @@ pub async fn run_command<W: Write>(
 
         u.flush()?;
 
-        stdout_final = stdout_buf.into_iter().collect::<Vec<_>>().join("\n");
-        stderr_final = stderr_buf.into_iter().collect::<Vec<_>>().join("\n");
+        stdout_final = stdout_buf.into_output();
+        stderr_final = stderr_buf.into_output();
     } else {
         // Take output all at once since we are not reporting anything in real time
         //
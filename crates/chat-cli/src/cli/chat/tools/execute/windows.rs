@@ -1,4 +1,3 @@
-use std::collections::VecDeque;
 use std::io::Write;
 use std::process::Stdio;
 
@@ -12,12 +11,14 @@ use tracing::error;
 
 use super::{
     CommandResult,
+    LastLines,
     env_vars_with_user_agent,
     format_output,
 };
 use crate::os::Os;
+use crate::util::shutdown;
 
-/// Run a command on Windows using cmd.exe.
+/// Run a command on Windows using PowerShell.
 /// # Arguments
 /// * `command` - The command to run
 /// * `max_result_size` - max size of output streams, truncating if required
@@ -34,8 +35,8 @@ pub async fn run_command<W: Write>(
     let env_vars = env_vars_with_user_agent(os);
 
     // We need to maintain a handle on stderr and stdout, but pipe it to the terminal as well
-    let mut child = tokio::process::Command::new("cmd")
-        .arg("/C")
+    let mut child = tokio::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command"])
         .arg(command)
         .envs(env_vars)
         .stdin(Stdio::inherit())
@@ -43,6 +44,7 @@ pub async fn run_command<W: Write>(
         .stderr(Stdio::piped())
         .spawn()
         .wrap_err_with(|| format!("Unable to spawn command '{}'", command))?;
+    let _child_guard = child.id().map(|pid| shutdown::track_child(pid, "execute_powershell"));
 
     let stdout_final: String;
     let stderr_final: String;
@@ -58,9 +60,8 @@ pub async fn run_command<W: Write>(
         let stderr = tokio::io::BufReader::new(stderr);
         let mut stderr = stderr.lines();
 
-        const LINE_COUNT: usize = 1024;
-        let mut stdout_buf = VecDeque::with_capacity(LINE_COUNT);
-        let mut stderr_buf = VecDeque::with_capacity(LINE_COUNT);
+        let mut stdout_buf = LastLines::default();
+        let mut stderr_buf = LastLines::default();
 
         let mut stdout_done = false;
         let mut stderr_done = false;
@@ -70,10 +71,7 @@ pub async fn run_command<W: Write>(
                 line = stdout.next_line(), if !stdout_done => match line {
                     Ok(Some(line)) => {
                         writeln!(u, "{line}")?;
-                        if stdout_buf.len() >= LINE_COUNT {
-                            stdout_buf.pop_front();
-                        }
-                        stdout_buf.push_back(line);
+                        stdout_buf.push(line);
                     },
                     Ok(None) => stdout_done = true,
                     Err(err) => error!(%err, "Failed to read stdout of child process"),
@@ -81,10 +79,7 @@ pub async fn run_command<W: Write>(
                 line = stderr.next_line(), if !stderr_done => match line {
                     Ok(Some(line)) => {
                         writeln!(u, "{line}")?;
-                        if stderr_buf.len() >= LINE_COUNT {
-                            stderr_buf.pop_front();
-                        }
-                        stderr_buf.push_back(line);
+                        stderr_buf.push(line);
                     },
                     Ok(None) => stderr_done = true,
                     Err(err) => error!(%err, "Failed to read stderr of child process"),
@@ -98,8 +93,8 @@ pub async fn run_command<W: Write>(
 
         u.flush()?;
 
-        stdout_final = stdout_buf.into_iter().collect::<Vec<_>>().join("\n");
-        stderr_final = stderr_buf.into_iter().collect::<Vec<_>>().join("\n");
+        stdout_final = stdout_buf.into_output();
+        stderr_final = stderr_buf.into_output();
     } else {
         // Take output all at once since we are not reporting anything in real time
         let output = child
@@ -126,13 +121,13 @@ mod tests {
     use crate::os::Os;
 
     #[tokio::test]
-    async fn test_execute_cmd_tool() {
+    async fn test_execute_powershell_tool() {
         let os = Os::new().await.unwrap();
         let mut stdout = std::io::stdout();
 
         // Verifying stdout
         let v = serde_json::json!({
-            "command": "echo Hello, world!",
+            "command": "Write-Output 'Hello, world!'",
         });
         let out = serde_json::from_value::<ExecuteCommand>(v)
             .unwrap()
@@ -148,9 +143,9 @@ mod tests {
             panic!("Expected JSON output");
         }
 
-        // Verifying stderr (using 2>&1 redirection for Windows)
+        // Verifying stderr
         let v = serde_json::json!({
-            "command": "echo Hello, world! 1>&2",
+            "command": "[Console]::Error.WriteLine('Hello, world!')",
         });
         let out = serde_json::from_value::<ExecuteCommand>(v)
             .unwrap()
@@ -168,7 +163,7 @@ mod tests {
 
         // Verifying exit code
         let v = serde_json::json!({
-            "command": "exit /b 1",
+            "command": "exit 1",
         });
         let out = serde_json::from_value::<ExecuteCommand>(v)
             .unwrap()
@@ -42,13 +42,16 @@ use crate::cli::agent::{
     Agent,
     PermissionEvalResult,
 };
-use crate::cli::chat::line_tracker::FileLineTracker;
+use crate::cli::chat::line_tracker::{
+    FileLineTracker,
+    changed_line_ranges,
+};
 use crate::os::Os;
 use crate::util::directories;
 use crate::util::tool_permission_checker::is_tool_in_allowlist;
 
-static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
-static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+pub static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+pub static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
 
 #[derive(Debug, Clone, Deserialize)]
 #[serde(tag = "command")]
@@ -103,7 +106,7 @@ impl FsWrite {
         let cwd = os.env.current_dir()?;
         let path = self.path(os);
 
-        self.update_line_tracker_before_invoke(os, line_tracker).await?;
+        let before = self.update_line_tracker_before_invoke(os, line_tracker).await?;
 
         match self {
             FsWrite::Create { .. } => {
@@ -161,15 +164,7 @@ impl FsWrite {
                     style::Print("\n"),
                 )?;
 
-                // Get the index of the start of the line to insert at.
-                let num_lines = file.lines().enumerate().map(|(i, _)| i + 1).last().unwrap_or(1);
-                let insert_line = insert_line.clamp(&0, &num_lines);
-                let mut i = 0;
-                for _ in 0..*insert_line {
-                    let line_len = &file[i..].find("\n").map_or(file[i..].len(), |i| i + 1);
-                    i += line_len;
-                }
-                file.insert_str(i, new_str);
+                insert_at_line(&mut file, *insert_line, new_str);
                 write_to_file(os, &path, file).await?;
             },
             FsWrite::Append { new_str, .. } => {
@@ -191,24 +186,77 @@ impl FsWrite {
             },
         };
 
-        self.update_line_tracker_after_invoke(os, line_tracker).await?;
+        self.update_line_tracker_after_invoke(os, line_tracker, &before).await?;
 
         Ok(Default::default())
     }
 
+    /// The current content of the file and the content it would have after this write.
+    pub async fn proposed_content(&self, os: &Os) -> Result<(String, String)> {
+        let path = self.path(os);
+        let current = if os.fs.exists(&path) {
+            os.fs.read_to_string(&path).await?
+        } else {
+            String::new()
+        };
+
+        let proposed = match self {
+            FsWrite::Create { .. } => self.canonical_create_command_text(),
+            FsWrite::StrReplace { old_str, new_str, .. } => match current.matches(old_str.as_str()).count() {
+                0 => bail!("no occurrences of \"{old_str}\" were found"),
+                1 => current.replacen(old_str, new_str, 1),
+                x => bail!("{x} occurrences of old_str were found when only 1 is expected"),
+            },
+            FsWrite::Insert {
+                insert_line, new_str, ..
+            } => {
+                let mut file = current.clone();
+                insert_at_line(&mut file, *insert_line, new_str);
+                file
+            },
+            FsWrite::Append { new_str, .. } => {
+                let mut file = current.clone();
+                if !file.ends_with_newline() {
+                    file.push('\n');
+                }
+                file.push_str(new_str);
+                file
+            },
+        };
+
+        Ok((current, proposed))
+    }
+
+    /// A write of `content` to the same file, with the same summary.
+    pub fn with_content(&self, content: String) -> Self {
+        let (path, summary) = match self {
+            FsWrite::Create { path, summary, .. }
+            | FsWrite::StrReplace { path, summary, .. }
+            | FsWrite::Insert { path, summary, .. }
+            | FsWrite::Append { path, summary, .. } => (path.clone(), summary.clone()),
+        };
+        FsWrite::Create {
+            path,
+            file_text: Some(content),
+            new_str: None,
+            summary,
+        }
+    }
+
+    /// Returns the content of the file before the write.
     async fn update_line_tracker_before_invoke(
         &self,
         os: &Os,
         line_tracker: &mut HashMap<String, FileLineTracker>,
-    ) -> Result<()> {
+    ) -> Result<String> {
         let path = self.path(os);
 
-        let curr_lines = if os.fs.exists(&path) {
-            let content = os.fs.read_to_string(&path).await?;
-            content.lines().count()
+        let content = if os.fs.exists(&path) {
+            os.fs.read_to_string(&path).await?
         } else {
-            0
+            String::new()
         };
+        let curr_lines = content.lines().count();
 
         let tracker = line_tracker.entry(path.to_string_lossy().to_string()).or_default();
         match self {
@@ -228,25 +276,26 @@ impl FsWrite {
         }
         tracker.before_fswrite_lines = curr_lines;
 
-        Ok(())
+        Ok(content)
     }
 
     async fn update_line_tracker_after_invoke(
         &self,
         os: &Os,
         line_tracker: &mut HashMap<String, FileLineTracker>,
+        before: &str,
     ) -> Result<()> {
         let path = self.path(os);
 
-        let after_lines = if os.fs.exists(&path) {
-            let content = os.fs.read_to_string(&path).await?;
-            content.lines().count()
+        let content = if os.fs.exists(&path) {
+            os.fs.read_to_string(&path).await?
         } else {
-            0
+            String::new()
         };
 
         let tracker = line_tracker.entry(path.to_string_lossy().to_string()).or_default();
-        tracker.after_fswrite_lines = after_lines;
+        tracker.after_fswrite_lines = content.lines().count();
+        tracker.lines_written_by_agent = changed_line_ranges(before, &content);
 
         // Calculate actual lines added and removed by analyzing the diff
         let (lines_added, lines_removed) = self.calculate_diff_lines(os).await?;
@@ -573,6 +622,19 @@ async fn write_to_file(os: &Os, path: impl AsRef<Path>, mut content: String) ->
     Ok(())
 }
 
+/// Inserts `new_str` after line `insert_line` of `file`, or at the start for line 0.
+fn insert_at_line(file: &mut String, insert_line: usize, new_str: &str) {
+    // Get the index of the start of the line to insert at.
+    let num_lines = file.lines().enumerate().map(|(i, _)| i + 1).last().unwrap_or(1);
+    let insert_line = insert_line.clamp(0, num_lines);
+    let mut i = 0;
+    for _ in 0..insert_line {
+        let line_len = &file[i..].find("\n").map_or(file[i..].len(), |i| i + 1);
+        i += line_len;
+    }
+    file.insert_str(i, new_str);
+}
+
 /// Returns a prefix/suffix pair before and after the content dictated by `[start_line, end_line]`
 /// within `content`. The updated start and end lines containing the original context along with
 /// the suffix and prefix are returned.
@@ -1128,6 +1190,11 @@ mod tests {
             TEST_FILE_CONTENTS.lines().skip(1).collect::<Vec<_>>(),
             "the rest of the file should not have been updated"
         );
+        assert_eq!(
+            line_tracker.values().next().unwrap().lines_written_by_agent,
+            vec![(2, 2)],
+            "only the inserted line should be tracked as written"
+        );
     }
 
     #[tokio::test]
@@ -0,0 +1,437 @@
+//! The `git` tool, with which the model inspects the repository and records its work: status,
+//! diffs, staging, commits, branches and stashes.
+//!
+//! It is separate from `execute_bash` so that it can be trusted on its own: read-only commands run
+//! without asking by default, and the `git` tool settings of an agent allow or deny each command.
+//! Commit messages are written by the model, put into [Setting::ChatGitCommitTemplate] when set,
+//! and given the `Co-authored-by` trailer of [Setting::ChatGitCoAuthoredBy] when set.
+
+use std::io::Write;
+use std::process::Stdio;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde::Deserialize;
+use tracing::error;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+    sanitize_path_tool_arg,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::tool_permission_checker::is_tool_in_allowlist;
+
+/// The placeholder of the commit message in [Setting::ChatGitCommitTemplate].
+const MESSAGE_PLACEHOLDER: &str = "{message}";
+
+const CO_AUTHORED_BY: &str = "Co-authored-by:";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Git {
+    Status,
+    Diff {
+        /// Show the staged changes instead of the unstaged ones.
+        #[serde(default)]
+        staged: bool,
+        #[serde(default)]
+        paths: Vec<String>,
+    },
+    Add {
+        paths: Vec<String>,
+    },
+    Commit {
+        message: String,
+    },
+    Branch {
+        name: String,
+        /// The commit or branch to start from, the current commit by default.
+        #[serde(default)]
+        start_point: Option<String>,
+        /// Switch to the new branch.
+        #[serde(default = "default_checkout")]
+        checkout: bool,
+    },
+    Stash {
+        action: StashAction,
+        #[serde(default)]
+        message: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StashAction {
+    Push,
+    Pop,
+    List,
+}
+
+fn default_checkout() -> bool {
+    true
+}
+
+impl Git {
+    /// The name of the command, as used in the tool settings.
+    pub fn command(&self) -> &'static str {
+        match self {
+            Git::Status => "status",
+            Git::Diff { .. } => "diff",
+            Git::Add { .. } => "add",
+            Git::Commit { .. } => "commit",
+            Git::Branch { .. } => "branch",
+            Git::Stash { .. } => "stash",
+        }
+    }
+
+    /// Whether the command leaves the repository as it is.
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Git::Status | Git::Diff { .. } => true,
+            Git::Stash { action, .. } => *action == StashAction::List,
+            Git::Add { .. } | Git::Commit { .. } | Git::Branch { .. } => false,
+        }
+    }
+
+    pub async fn validate(&self, os: &Os) -> Result<()> {
+        match self {
+            Git::Add { paths } if paths.is_empty() => bail!("At least one path must be given to add"),
+            Git::Commit { message } if message.trim().is_empty() => bail!("The commit message cannot be empty"),
+            Git::Branch { name, .. } if name.trim().is_empty() || name.starts_with('-') => {
+                bail!("Invalid branch name: '{name}'")
+            },
+            Git::Branch {
+                start_point: Some(start_point),
+                ..
+            } if start_point.starts_with('-') => bail!("Invalid start point: '{start_point}'"),
+            _ => {},
+        }
+        let cwd = os.env.current_dir()?;
+        if run(&cwd, &["rev-parse", "--git-dir"]).await.is_err() {
+            bail!("The current directory is not in a git repository");
+        }
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, _output: &mut impl Write) -> Result<InvokeOutput> {
+        let args = self.args(os);
+        let cwd = os.env.current_dir()?;
+        let output = run(&cwd, &args.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+        Ok(InvokeOutput {
+            output: OutputKind::Text(match output.trim() {
+                "" => format!("git {} succeeded", self.command()),
+                output => output.to_string(),
+            }),
+        })
+    }
+
+    pub fn queue_description(&self, os: &Os, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Running "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(format!("git {}", self.command())),
+            style::ResetColor,
+        )?;
+        match self {
+            Git::Commit { .. } => {
+                queue!(output, style::Print(" with the message:\n\n"))?;
+                for line in self.commit_message(os).lines() {
+                    queue!(output, style::Print(format!("  {line}\n")))?;
+                }
+            },
+            _ => {
+                let args = self.args(os);
+                queue!(
+                    output,
+                    style::SetForegroundColor(Color::DarkGrey),
+                    style::Print(format!(" ({})\n", args.join(" "))),
+                    style::ResetColor,
+                )?;
+            },
+        }
+        Ok(())
+    }
+
+    pub fn eval_perm(&self, _os: &Os, agent: &Agent) -> PermissionEvalResult {
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Settings {
+            #[serde(default)]
+            allowed_commands: Vec<String>,
+            #[serde(default)]
+            denied_commands: Vec<String>,
+            #[serde(default = "default_auto_allow_readonly")]
+            auto_allow_readonly: bool,
+        }
+
+        fn default_auto_allow_readonly() -> bool {
+            true
+        }
+
+        let command = self.command().to_string();
+        let is_in_allowlist = is_tool_in_allowlist(&agent.allowed_tools, "git", None);
+        let settings = agent
+            .tools_settings
+            .get("git")
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({}));
+        let settings = match serde_json::from_value::<Settings>(settings) {
+            Ok(settings) => settings,
+            Err(e) => {
+                error!("Failed to deserialize tool settings for git: {:?}", e);
+                return PermissionEvalResult::Ask;
+            },
+        };
+        if settings.denied_commands.contains(&command) {
+            return PermissionEvalResult::Deny(vec![command]);
+        }
+        if is_in_allowlist
+            || settings.allowed_commands.contains(&command)
+            || (settings.auto_allow_readonly && self.is_read_only())
+        {
+            return PermissionEvalResult::Allow;
+        }
+        PermissionEvalResult::Ask
+    }
+
+    /// The arguments git is run with.
+    fn args(&self, os: &Os) -> Vec<String> {
+        let paths = |paths: &[String]| {
+            paths
+                .iter()
+                .map(|path| sanitize_path_tool_arg(os, path).to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        };
+        let mut args = Vec::new();
+        match self {
+            Git::Status => args.extend(["status", "--short", "--branch"].map(String::from)),
+            Git::Diff { staged, paths: diffed } => {
+                args.extend(["diff", "--no-color"].map(String::from));
+                if *staged {
+                    args.push("--cached".to_string());
+                }
+                if !diffed.is_empty() {
+                    args.push("--".to_string());
+                    args.extend(paths(diffed));
+                }
+            },
+            Git::Add { paths: added } => {
+                args.extend(["add", "--"].map(String::from));
+                args.extend(paths(added));
+            },
+            Git::Commit { .. } => {
+                args.extend(["commit", "-m"].map(String::from));
+                args.push(self.commit_message(os));
+            },
+            Git::Branch {
+                name,
+                start_point,
+                checkout,
+            } => {
+                match checkout {
+                    true => args.extend(["switch", "-c"].map(String::from)),
+                    false => args.push("branch".to_string()),
+                }
+                args.push(name.clone());
+                args.extend(start_point.clone());
+            },
+            Git::Stash { action, message } => {
+                args.push("stash".to_string());
+                match action {
+                    StashAction::Push => {
+                        args.push("push".to_string());
+                        if let Some(message) = message {
+                            args.extend(["-m".to_string(), message.clone()]);
+                        }
+                    },
+                    StashAction::Pop => args.push("pop".to_string()),
+                    StashAction::List => args.push("list".to_string()),
+                }
+            },
+        }
+        args
+    }
+
+    /// The message a commit is made with, from the one the model wrote.
+    fn commit_message(&self, os: &Os) -> String {
+        match self {
+            Git::Commit { message } => commit_message(os, message),
+            _ => String::new(),
+        }
+    }
+}
+
+/// The message a commit written by the model is made with: `message` in the commit template, with
+/// the `Co-authored-by` trailer, as the user set them.
+pub fn commit_message(os: &Os, message: &str) -> String {
+    let settings = &os.database.settings;
+    format_commit_message(
+        message,
+        settings.get_string(Setting::ChatGitCommitTemplate).as_deref(),
+        settings.get_string(Setting::ChatGitCoAuthoredBy).as_deref(),
+    )
+}
+
+/// Puts `message` into `template`, at its `{message}` placeholder or else before it, and adds the
+/// `Co-authored-by` trailer of `co_author` unless the message has it already.
+fn format_commit_message(message: &str, template: Option<&str>, co_author: Option<&str>) -> String {
+    let message = message.trim();
+    let mut formatted = match template.map(str::trim).filter(|template| !template.is_empty()) {
+        Some(template) if template.contains(MESSAGE_PLACEHOLDER) => template.replace(MESSAGE_PLACEHOLDER, message),
+        Some(template) => format!("{message}\n\n{template}"),
+        None => message.to_string(),
+    };
+
+    let Some(co_author) = co_author.map(str::trim).filter(|co_author| !co_author.is_empty()) else {
+        return formatted;
+    };
+    let trailer = format!("{CO_AUTHORED_BY} {co_author}");
+    if formatted.lines().any(|line| line.trim().eq_ignore_ascii_case(&trailer)) {
+        return formatted;
+    }
+    // Trailers are the last paragraph of the message, so a new one joins those already there.
+    let last_paragraph = formatted.rsplit("\n\n").next().unwrap_or_default();
+    let has_trailers = formatted.contains("\n\n") && last_paragraph.lines().all(is_trailer);
+    formatted.push_str(if has_trailers { "\n" } else { "\n\n" });
+    formatted.push_str(&trailer);
+    formatted
+}
+
+/// Whether a line of a commit message is a trailer, like `Signed-off-by: Name <email>`.
+fn is_trailer(line: &str) -> bool {
+    line.split_once(": ")
+        .is_some_and(|(key, _)| !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+}
+
+/// Runs git in `dir`, returning what it printed, or an error with it if it failed.
+async fn run(dir: &std::path::Path, args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}{}",
+            args.first().unwrap_or(&""),
+            stdout,
+            stderr.trim_end()
+        );
+    }
+    Ok(format!("{stdout}{stderr}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::cli::agent::ToolSettingTarget;
+
+    macro_rules! git {
+        ($value:tt) => {
+            serde_json::from_value::<Git>(serde_json::json!($value)).unwrap()
+        };
+    }
+
+    #[test]
+    fn test_format_commit_message() {
+        assert_eq!(format_commit_message(" Add the parser\n", None, None), "Add the parser");
+        assert_eq!(
+            format_commit_message("Add the parser", Some("[PROJ-1] {message}"), None),
+            "[PROJ-1] Add the parser"
+        );
+        assert_eq!(
+            format_commit_message("Add the parser", Some("Reviewed: no"), None),
+            "Add the parser\n\nReviewed: no"
+        );
+        assert_eq!(
+            format_commit_message("Add the parser", None, Some("Amazon Q <q@example.com>")),
+            "Add the parser\n\nCo-authored-by: Amazon Q <q@example.com>"
+        );
+        // Joins the trailers there are, and isn't added twice.
+        assert_eq!(
+            format_commit_message(
+                "Add the parser\n\nSigned-off-by: Dev <dev@example.com>",
+                None,
+                Some("Amazon Q <q@example.com>")
+            ),
+            "Add the parser\n\nSigned-off-by: Dev <dev@example.com>\nCo-authored-by: Amazon Q <q@example.com>"
+        );
+        assert_eq!(
+            format_commit_message(
+                "Add the parser\n\nCo-authored-by: Amazon Q <q@example.com>",
+                None,
+                Some("Amazon Q <q@example.com>")
+            ),
+            "Add the parser\n\nCo-authored-by: Amazon Q <q@example.com>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_args() {
+        let os = Os::new().await.unwrap();
+        assert_eq!(git!({ "command": "status" }).args(&os), [
+            "status", "--short", "--branch"
+        ]);
+        assert_eq!(git!({ "command": "diff", "staged": true }).args(&os), [
+            "diff",
+            "--no-color",
+            "--cached"
+        ]);
+        assert_eq!(git!({ "command": "branch", "name": "fix-parser" }).args(&os), [
+            "switch",
+            "-c",
+            "fix-parser"
+        ]);
+        assert_eq!(
+            git!({ "command": "branch", "name": "fix-parser", "start_point": "main", "checkout": false }).args(&os),
+            ["branch", "fix-parser", "main"]
+        );
+        assert_eq!(
+            git!({ "command": "stash", "action": "push", "message": "wip" }).args(&os),
+            ["stash", "push", "-m", "wip"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_eval_perm() {
+        let os = Os::new().await.unwrap();
+        let status = git!({ "command": "status" });
+        let commit = git!({ "command": "commit", "message": "Add the parser" });
+        let push = git!({ "command": "stash", "action": "push" });
+
+        let mut agent = Agent::default();
+        assert!(matches!(status.eval_perm(&os, &agent), PermissionEvalResult::Allow));
+        assert!(matches!(commit.eval_perm(&os, &agent), PermissionEvalResult::Ask));
+
+        agent.tools_settings = HashMap::from([(
+            ToolSettingTarget("git".to_string()),
+            serde_json::json!({
+                "allowedCommands": ["commit"],
+                "deniedCommands": ["stash"],
+                "autoAllowReadonly": false,
+            }),
+        )]);
+        assert!(matches!(status.eval_perm(&os, &agent), PermissionEvalResult::Ask));
+        assert!(matches!(commit.eval_perm(&os, &agent), PermissionEvalResult::Allow));
+        assert!(matches!(push.eval_perm(&os, &agent), PermissionEvalResult::Deny(_)));
+    }
+}
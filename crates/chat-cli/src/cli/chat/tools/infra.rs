@@ -0,0 +1,447 @@
+//! Infrastructure changes: the CloudFormation, CDK and SAM deployments a `use_aws` or
+//! `execute_bash` tool use would make. They are confirmed each time, however much the tool is
+//! trusted, after a summary of what they would change is shown.
+//!
+//! The summary is made before the user has agreed to anything, so it only ever runs `cdk diff`
+//! and read-only `aws cloudformation` operations, never a program or operation named by the model.
+
+use std::process::Stdio;
+
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+    eyre,
+};
+use serde_json::Value;
+
+use super::env_vars_with_user_agent;
+use super::use_aws::UseAws;
+use crate::os::Os;
+
+/// The CloudFormation operations that create, change or delete stacks.
+const CLOUDFORMATION_OPS: [&str; 5] = [
+    "deploy",
+    "create-stack",
+    "update-stack",
+    "delete-stack",
+    "execute-change-set",
+];
+
+/// The flags of `cdk deploy` that take a value, so that the value is not taken for a stack.
+const CDK_VALUE_FLAGS: [&str; 18] = [
+    "--app",
+    "-a",
+    "--profile",
+    "--context",
+    "-c",
+    "--require-approval",
+    "--outputs-file",
+    "-O",
+    "--concurrency",
+    "--method",
+    "-m",
+    "--parameters",
+    "--toolkit-stack-name",
+    "--role-arn",
+    "-r",
+    "--tags",
+    "-t",
+    "--change-set-name",
+];
+
+/// The flags of `cdk deploy` that `cdk diff` also takes, and so are kept for the preview. `--app`
+/// is left out as it is a command to run, the preview uses the app of `cdk.json`.
+const CDK_DIFF_FLAGS: [&str; 5] = ["--profile", "--context", "-c", "--exclusively", "-e"];
+
+/// The `aws cloudformation` operations a preview runs, none of which change anything.
+const PREVIEW_OPS: [&str; 3] = ["describe-change-set", "get-template-summary", "list-stack-resources"];
+
+/// The most resource changes listed in a summary.
+const MAX_CHANGES: usize = 50;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfraChange {
+    /// What is run, such as `cloudformation deploy` or `cdk deploy`.
+    pub action: String,
+    pub stack: Option<String>,
+    preview: Preview,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Preview {
+    /// The resource types of the template of `aws cloudformation deploy`, `create-stack` or
+    /// `update-stack`.
+    Template(AwsCall),
+    /// The resources `aws cloudformation delete-stack` removes.
+    DeleteStack(AwsCall),
+    /// The change set `aws cloudformation execute-change-set` executes.
+    ExecuteChangeSet(AwsCall),
+    /// `cdk diff` with these arguments, for `cdk deploy`.
+    CdkDiff(Vec<String>),
+    /// Nothing that can be run ahead, such as for `cdk destroy` or a shell `aws` command.
+    Unavailable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AwsCall {
+    region: String,
+    profile_name: Option<String>,
+    /// The `--name value` arguments of the operation.
+    args: Vec<(String, String)>,
+}
+
+impl AwsCall {
+    fn arg(&self, name: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find(|(arg, _)| arg == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The arguments named in `names`, as they are passed to the CLI.
+    fn pick(&self, names: &[&str]) -> Vec<String> {
+        self.args
+            .iter()
+            .filter(|(name, _)| names.contains(&name.as_str()))
+            .flat_map(|(name, value)| [name.clone(), value.clone()])
+            .filter(|arg| !arg.is_empty())
+            .collect()
+    }
+}
+
+impl InfraChange {
+    /// The infrastructure change the `use_aws` call would make, if any.
+    pub fn from_use_aws(use_aws: &UseAws) -> Option<Self> {
+        if use_aws.service_name != "cloudformation" || !CLOUDFORMATION_OPS.contains(&use_aws.operation_name.as_str()) {
+            return None;
+        }
+        let call = AwsCall {
+            region: use_aws.region.clone(),
+            profile_name: use_aws.profile_name.clone(),
+            args: use_aws.cli_parameters().unwrap_or_default(),
+        };
+        let stack = call.arg("--stack-name").map(str::to_string);
+        let preview = match use_aws.operation_name.as_str() {
+            "deploy" | "create-stack" | "update-stack" => Preview::Template(call),
+            "delete-stack" => Preview::DeleteStack(call),
+            _ => Preview::ExecuteChangeSet(call),
+        };
+        Some(Self {
+            action: format!("cloudformation {}", use_aws.operation_name),
+            stack,
+            preview,
+        })
+    }
+
+    /// The first infrastructure change the shell `command` would make, if any.
+    pub fn from_command(command: &str) -> Option<Self> {
+        command.split(["\n", ";", "|", "&"]).find_map(|segment| {
+            let args = shlex::split(segment)
+                .unwrap_or_else(|| segment.split_whitespace().map(str::to_string).collect::<Vec<_>>());
+            Self::from_args(&args)
+        })
+    }
+
+    fn from_args(args: &[String]) -> Option<Self> {
+        let position = |name: &str| args.iter().position(|arg| arg == name);
+        let after = |index: usize| args.get(index + 1).map(String::as_str);
+
+        if let Some(cdk) = position("cdk") {
+            let action = after(cdk).filter(|action| ["deploy", "destroy"].contains(action))?;
+            let mut stacks = Vec::new();
+            let mut diff = Vec::new();
+            let mut rest = args[cdk + 2..].iter();
+            while let Some(arg) = rest.next() {
+                if !arg.starts_with('-') {
+                    stacks.push(arg.clone());
+                    continue;
+                }
+                let name = arg.split('=').next().unwrap_or_default();
+                let value = match !arg.contains('=') && CDK_VALUE_FLAGS.contains(&name) {
+                    true => rest.next().cloned(),
+                    false => None,
+                };
+                if CDK_DIFF_FLAGS.contains(&name) {
+                    diff.push(arg.clone());
+                    diff.extend(value);
+                }
+            }
+            diff.extend(stacks.iter().cloned());
+            let preview = match action {
+                "deploy" => Preview::CdkDiff(diff),
+                _ => Preview::Unavailable,
+            };
+            return Some(Self {
+                action: format!("cdk {action}"),
+                stack: (!stacks.is_empty()).then(|| stacks.join(", ")),
+                preview,
+            });
+        }
+        if let Some(sam) = position("sam") {
+            let action = after(sam).filter(|action| ["deploy", "delete"].contains(action))?;
+            return Some(Self {
+                action: format!("sam {action}"),
+                stack: position("--stack-name").and_then(after).map(str::to_string),
+                preview: Preview::Unavailable,
+            });
+        }
+        if position("aws").is_some() {
+            let service = position("cloudformation")?;
+            let operation = after(service).filter(|op| CLOUDFORMATION_OPS.contains(op))?;
+            return Some(Self {
+                action: format!("cloudformation {operation}"),
+                stack: position("--stack-name").and_then(after).map(str::to_string),
+                preview: Preview::Unavailable,
+            });
+        }
+        None
+    }
+
+    /// A summary of what the change would do, from `cdk diff` or read-only CloudFormation calls.
+    pub async fn preview(&self, os: &Os) -> Result<String> {
+        match &self.preview {
+            Preview::Template(call) => {
+                let mut args = vec!["get-template-summary".to_string()];
+                args.extend(template_args(call));
+                let summary = serde_json::from_str::<Value>(&aws(os, call, args).await?)?;
+                let types = strings(&summary["ResourceTypes"]);
+                Ok(format!("The template has {} resources:\n{}", types.len(), list(&types)))
+            },
+            Preview::ExecuteChangeSet(call) => {
+                let change_set = call
+                    .arg("--change-set-name")
+                    .ok_or_else(|| eyre!("The change set is not named"))?;
+                change_set_summary(os, call, change_set).await
+            },
+            Preview::DeleteStack(call) => {
+                let mut args = vec!["list-stack-resources".to_string()];
+                args.extend(call.pick(&["--stack-name"]));
+                let resources = serde_json::from_str::<Value>(&aws(os, call, args).await?)?;
+                let removed = resources["StackResourceSummaries"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|resource| {
+                        format!(
+                            "- Remove {} {}",
+                            resource["ResourceType"].as_str().unwrap_or_default(),
+                            resource["LogicalResourceId"].as_str().unwrap_or_default()
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                Ok(format!(
+                    "Deletes the stack and its {} resources:\n{}",
+                    removed.len(),
+                    list(&removed)
+                ))
+            },
+            Preview::CdkDiff(args) => {
+                let output = tokio::process::Command::new("cdk")
+                    .arg("diff")
+                    .args(args)
+                    .current_dir(os.env.current_dir()?)
+                    .stdin(Stdio::null())
+                    .output()
+                    .await
+                    .wrap_err("Unable to run cdk, is it installed?")?;
+                // cdk prints the diff to stderr, and exits with 1 when there are differences
+                // only if asked to with --fail.
+                let diff = String::from_utf8_lossy(&output.stderr);
+                if !output.status.success() {
+                    bail!("cdk diff failed: {}", diff.trim());
+                }
+                Ok(diff.trim().to_string())
+            },
+            Preview::Unavailable => bail!("This change cannot be previewed"),
+        }
+    }
+}
+
+/// The template arguments of `get-template-summary` for the template `call` deploys. That is the
+/// stack's current template for an `update-stack` that keeps it.
+fn template_args(call: &AwsCall) -> Vec<String> {
+    if let Some(file) = call.arg("--template-file") {
+        return vec!["--template-body".to_string(), format!("file://{file}")];
+    }
+    match call.pick(&["--template-body", "--template-url"]) {
+        args if args.is_empty() => call.pick(&["--stack-name"]),
+        args => args,
+    }
+}
+
+/// The resource changes of `change_set`.
+async fn change_set_summary(os: &Os, call: &AwsCall, change_set: &str) -> Result<String> {
+    let mut args = vec!["describe-change-set".to_string()];
+    args.extend(["--change-set-name".to_string(), change_set.to_string()]);
+    // A change set given by name rather than ARN needs its stack.
+    args.extend(call.pick(&["--stack-name"]));
+    let described = serde_json::from_str::<Value>(&aws(os, call, args).await?)?;
+    Ok(summarize_change_set(&described))
+}
+
+/// One line per resource change of the output of `describe-change-set`.
+fn summarize_change_set(change_set: &Value) -> String {
+    let changes = change_set["Changes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|change| {
+            let change = &change["ResourceChange"];
+            let (sign, action) = match change["Action"].as_str().unwrap_or_default() {
+                "Add" => ('+', "Add"),
+                "Remove" => ('-', "Remove"),
+                "Import" => ('+', "Import"),
+                action => ('~', action),
+            };
+            let replacement = match change["Replacement"].as_str() {
+                Some("True") => " (replaced)",
+                Some("Conditional") => " (may be replaced)",
+                _ => "",
+            };
+            format!(
+                "{sign} {action} {} {}{replacement}",
+                change["ResourceType"].as_str().unwrap_or_default(),
+                change["LogicalResourceId"].as_str().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>();
+    match changes.is_empty() {
+        true => change_set["StatusReason"]
+            .as_str()
+            .unwrap_or("No resource changes.")
+            .to_string(),
+        false => format!("{} resource changes:\n{}", changes.len(), list(&changes)),
+    }
+}
+
+fn list(lines: &[String]) -> String {
+    let mut listed = lines.iter().take(MAX_CHANGES).cloned().collect::<Vec<_>>();
+    if lines.len() > MAX_CHANGES {
+        listed.push(format!("... and {} more", lines.len() - MAX_CHANGES));
+    }
+    listed.join("\n")
+}
+
+fn strings(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Runs `aws cloudformation` with `args` in the region and profile of `call`, returning its stdout.
+/// Only the read-only operations of [PREVIEW_OPS] are run.
+async fn aws(os: &Os, call: &AwsCall, args: Vec<String>) -> Result<String> {
+    match args.first() {
+        Some(operation) if PREVIEW_OPS.contains(&operation.as_str()) => (),
+        operation => bail!("Refusing to run cloudformation {operation:?} for a preview"),
+    }
+    let mut command = tokio::process::Command::new("aws");
+    command
+        .envs(env_vars_with_user_agent(os))
+        .args(["--region", &call.region, "--output", "json"]);
+    if let Some(profile_name) = &call.profile_name {
+        command.args(["--profile", profile_name]);
+    }
+    let output = command
+        .arg("cloudformation")
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .wrap_err("Unable to run the aws cli, is it installed?")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_use_aws() {
+        let use_aws = serde_json::from_value::<UseAws>(serde_json::json!({
+            "service_name": "cloudformation",
+            "operation_name": "deploy",
+            "parameters": { "stack-name": "api", "template-file": "template.yaml" },
+            "region": "us-east-1",
+            "label": ""
+        }))
+        .unwrap();
+        let change = InfraChange::from_use_aws(&use_aws).unwrap();
+        assert_eq!(change.action, "cloudformation deploy");
+        assert_eq!(change.stack.as_deref(), Some("api"));
+
+        let use_aws = serde_json::from_value::<UseAws>(serde_json::json!({
+            "service_name": "cloudformation",
+            "operation_name": "describe-stacks",
+            "region": "us-east-1",
+            "label": ""
+        }))
+        .unwrap();
+        assert_eq!(InfraChange::from_use_aws(&use_aws), None);
+    }
+
+    #[test]
+    fn test_from_command() {
+        let change =
+            InfraChange::from_command("npm run build && npx cdk deploy Api --require-approval never --profile dev")
+                .unwrap();
+        assert_eq!(change.action, "cdk deploy");
+        assert_eq!(change.stack.as_deref(), Some("Api"));
+        assert_eq!(
+            change.preview,
+            Preview::CdkDiff(["--profile", "dev", "Api"].map(String::from).to_vec())
+        );
+
+        // Neither the command around cdk nor its app are run for the preview.
+        let change = InfraChange::from_command("python x.py cdk deploy --app 'sh evil.sh' Api").unwrap();
+        assert_eq!(change.preview, Preview::CdkDiff(vec!["Api".to_string()]));
+
+        let change = InfraChange::from_command("aws cloudformation delete-stack --stack-name api").unwrap();
+        assert_eq!(change.action, "cloudformation delete-stack");
+        assert_eq!(change.stack.as_deref(), Some("api"));
+
+        assert!(InfraChange::from_command("sam deploy --stack-name api").is_some());
+        assert_eq!(InfraChange::from_command("cdk diff"), None);
+        assert_eq!(InfraChange::from_command("aws cloudformation describe-stacks"), None);
+    }
+
+    #[tokio::test]
+    async fn test_preview_is_read_only() {
+        let os = Os::new().await.unwrap();
+        let call = AwsCall {
+            region: "us-east-1".to_string(),
+            profile_name: None,
+            args: vec![
+                ("--stack-name".to_string(), "api".to_string()),
+                ("--template-file".to_string(), "template.yaml".to_string()),
+            ],
+        };
+        assert_eq!(template_args(&call), vec!["--template-body", "file://template.yaml"]);
+
+        for operation in ["deploy", "create-change-set", "delete-stack"] {
+            let err = aws(&os, &call, vec![operation.to_string()]).await.unwrap_err();
+            assert!(err.to_string().starts_with("Refusing"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_summarize_change_set() {
+        let summary = summarize_change_set(&serde_json::json!({
+            "Changes": [
+                { "ResourceChange": { "Action": "Add", "ResourceType": "AWS::S3::Bucket", "LogicalResourceId": "Assets" } },
+                { "ResourceChange": { "Action": "Modify", "ResourceType": "AWS::Lambda::Function", "LogicalResourceId": "Handler", "Replacement": "True" } }
+            ]
+        }));
+        assert_eq!(
+            summary,
+            "2 resource changes:\n+ Add AWS::S3::Bucket Assets\n~ Modify AWS::Lambda::Function Handler (replaced)"
+        );
+    }
+}
@@ -0,0 +1,482 @@
+//! The `issue_tracker` tool, with which the model reads issues and pull requests by URL or number,
+//! so that "fix issue #123" works without pasting the issue, and reports issues about Q CLI itself.
+//!
+//! Issues are fetched with the CLI of their tracker, with the login of the user: `gh` for GitHub,
+//! `glab` for GitLab. Numbers are looked up in the repository of the `origin` remote of the current
+//! directory.
+
+use std::io::Write;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    WrapErr,
+    bail,
+    eyre,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+
+use super::gh_issue::GhIssue;
+use super::{
+    InvokeOutput,
+    OutputKind,
+};
+use crate::os::Os;
+
+/// The most comments returned, the latest ones.
+const MAX_COMMENTS: usize = 30;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum IssueTracker {
+    /// Fetches an issue or pull request.
+    Fetch(FetchIssue),
+    /// Opens the browser to a GitHub issue about Q CLI, prefilled with the conversation.
+    Report(GhIssue),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FetchIssue {
+    /// The URL of the issue or pull request, or its number, like `123`, `#123`, or `!123` for a
+    /// GitLab merge request.
+    pub issue: String,
+    #[serde(default = "default_comments")]
+    pub comments: bool,
+}
+
+fn default_comments() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tracker {
+    GitHub,
+    GitLab,
+    CodeCatalyst,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    Issue,
+    PullRequest,
+}
+
+/// An issue or pull request, as given to the model.
+#[derive(Debug, PartialEq, Eq)]
+struct IssueRef {
+    tracker: Tracker,
+    host: String,
+    /// `owner/repo` on GitHub, the path of the project on GitLab.
+    project: String,
+    number: u64,
+    /// Unknown for GitHub numbers, which issues and pull requests share.
+    kind: Option<IssueKind>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Issue {
+    tracker: Tracker,
+    kind: IssueKind,
+    url: String,
+    number: u64,
+    title: String,
+    state: String,
+    author: String,
+    labels: Vec<String>,
+    body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_branch: Option<String>,
+    comments: Vec<Comment>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Comment {
+    author: String,
+    created_at: String,
+    body: String,
+}
+
+impl IssueTracker {
+    pub async fn validate(&mut self, os: &Os) -> Result<()> {
+        match self {
+            IssueTracker::Fetch(fetch) if fetch.issue.trim().is_empty() => bail!("The issue cannot be empty"),
+            IssueTracker::Fetch(_) => Ok(()),
+            IssueTracker::Report(report) => report.validate(os).await,
+        }
+    }
+
+    pub async fn invoke(&self, os: &Os, output: &mut impl Write) -> Result<InvokeOutput> {
+        match self {
+            IssueTracker::Fetch(fetch) => fetch.invoke(os).await,
+            IssueTracker::Report(report) => report.invoke(os, output).await,
+        }
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        match self {
+            IssueTracker::Fetch(fetch) => Ok(queue!(
+                output,
+                style::Print("Fetching "),
+                style::SetForegroundColor(Color::Green),
+                style::Print(&fetch.issue),
+                style::ResetColor,
+                style::Print("\n"),
+            )?),
+            IssueTracker::Report(report) => report.queue_description(output),
+        }
+    }
+}
+
+impl FetchIssue {
+    async fn invoke(&self, os: &Os) -> Result<InvokeOutput> {
+        let remote = match self.issue.contains("://") {
+            true => None,
+            false => {
+                let cwd = os.env.current_dir()?;
+                crate::cli::chat::git_context::git(&cwd, ["remote", "get-url", "origin"])
+                    .await
+                    .ok()
+            },
+        };
+        let issue_ref = parse_reference(self.issue.trim(), remote.as_deref().map(str::trim))?;
+        let issue = match issue_ref.tracker {
+            Tracker::GitHub => fetch_github(&issue_ref, self.comments).await?,
+            Tracker::GitLab => fetch_gitlab(&issue_ref, self.comments).await?,
+            Tracker::CodeCatalyst => bail!(
+                "CodeCatalyst issues cannot be fetched, as the CodeCatalyst API has no operations for them. Ask the user to paste the issue instead"
+            ),
+        };
+        Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::to_value(issue)?),
+        })
+    }
+}
+
+/// The issue `reference` points to: a URL, or a number in the repository of the `remote` URL.
+fn parse_reference(reference: &str, remote: Option<&str>) -> Result<IssueRef> {
+    if let Some(rest) = reference.split_once("://").map(|(_, rest)| rest) {
+        let (host, path) = rest
+            .split_once('/')
+            .ok_or_else(|| eyre!("Not an issue URL: {reference}"))?;
+        let path = path.split(['?', '#']).next().unwrap_or_default().trim_end_matches('/');
+        let segments = path.split('/').collect::<Vec<_>>();
+        let number = segments
+            .last()
+            .and_then(|number| number.parse::<u64>().ok())
+            .ok_or_else(|| eyre!("The URL does not end with the number of an issue: {reference}"))?;
+
+        if host.ends_with("codecatalyst.aws") {
+            return Ok(IssueRef {
+                tracker: Tracker::CodeCatalyst,
+                host: host.to_string(),
+                project: segments[..segments.len() - 1].join("/"),
+                number,
+                kind: Some(IssueKind::Issue),
+            });
+        }
+        // GitLab URLs are like group/project/-/issues/1.
+        if let Some(dash) = segments.iter().position(|segment| *segment == "-") {
+            let kind = match segments.get(dash + 1) {
+                Some(&"issues") | Some(&"work_items") => IssueKind::Issue,
+                Some(&"merge_requests") => IssueKind::PullRequest,
+                _ => bail!("Not a GitLab issue or merge request URL: {reference}"),
+            };
+            return Ok(IssueRef {
+                tracker: Tracker::GitLab,
+                host: host.to_string(),
+                project: segments[..dash].join("/"),
+                number,
+                kind: Some(kind),
+            });
+        }
+        // GitHub URLs are like owner/repo/issues/1.
+        let kind = match segments.as_slice() {
+            [_, _, "issues", _] => IssueKind::Issue,
+            [_, _, "pull", _] => IssueKind::PullRequest,
+            _ => bail!("Not a GitHub, GitLab or CodeCatalyst issue URL: {reference}"),
+        };
+        return Ok(IssueRef {
+            tracker: Tracker::GitHub,
+            host: host.to_string(),
+            project: segments[..2].join("/"),
+            number,
+            kind: Some(kind),
+        });
+    }
+
+    let (kind, number) = match reference.chars().next() {
+        Some('#') => (None, &reference[1..]),
+        Some('!') => (Some(IssueKind::PullRequest), &reference[1..]),
+        _ => (None, reference),
+    };
+    let number = number
+        .parse::<u64>()
+        .wrap_err_with(|| format!("Not an issue URL or number: {reference}"))?;
+    let Some((host, project)) = remote.and_then(parse_remote) else {
+        bail!("The number of an issue needs a git repository with an origin remote, give the URL of the issue instead");
+    };
+    let tracker = match host.as_str() {
+        host if host.contains("gitlab") => Tracker::GitLab,
+        host if host.ends_with("codecatalyst.aws") => Tracker::CodeCatalyst,
+        _ => Tracker::GitHub,
+    };
+    Ok(IssueRef {
+        tracker,
+        host,
+        project,
+        number,
+        kind: match tracker {
+            Tracker::GitHub => kind,
+            _ => Some(kind.unwrap_or(IssueKind::Issue)),
+        },
+    })
+}
+
+/// The host and project path of a git remote URL, in the https, ssh, or scp-like form.
+fn parse_remote(remote: &str) -> Option<(String, String)> {
+    let (host, path) = match remote.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?,
+        None => remote.split_once(':')?,
+    };
+    // Drops the user and port, as in git@host:22.
+    let host = host.rsplit('@').next()?.split(':').next()?;
+    let path = path.trim_end_matches('/').trim_end_matches(".git");
+    (!host.is_empty() && !path.is_empty()).then(|| (host.to_string(), path.to_string()))
+}
+
+async fn fetch_github(issue_ref: &IssueRef, with_comments: bool) -> Result<Issue> {
+    let api = |path: String| {
+        let mut args = vec!["api".to_string(), path];
+        if issue_ref.host != "github.com" {
+            args.extend(["--hostname".to_string(), issue_ref.host.clone()]);
+        }
+        run("gh", args)
+    };
+    let path = format!("repos/{}/issues/{}", issue_ref.project, issue_ref.number);
+    let mut issue = github_issue(&api(path.clone()).await?)?;
+    if issue.kind == IssueKind::PullRequest {
+        let pull = api(format!("repos/{}/pulls/{}", issue_ref.project, issue_ref.number)).await?;
+        issue.source_branch = pull["head"]["ref"].as_str().map(str::to_string);
+        issue.target_branch = pull["base"]["ref"].as_str().map(str::to_string);
+    }
+    if with_comments {
+        let comments = api(format!("{path}/comments?per_page=100")).await?;
+        issue.comments = latest(
+            comments
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|comment| Comment {
+                    author: string(&comment["user"]["login"]),
+                    created_at: string(&comment["created_at"]),
+                    body: string(&comment["body"]),
+                })
+                .collect(),
+        );
+    }
+    Ok(issue)
+}
+
+fn github_issue(value: &Value) -> Result<Issue> {
+    Ok(Issue {
+        tracker: Tracker::GitHub,
+        kind: match value.get("pull_request") {
+            Some(_) => IssueKind::PullRequest,
+            None => IssueKind::Issue,
+        },
+        url: string(&value["html_url"]),
+        number: value["number"]
+            .as_u64()
+            .ok_or_else(|| eyre!("The issue has no number"))?,
+        title: string(&value["title"]),
+        state: string(&value["state"]),
+        author: string(&value["user"]["login"]),
+        labels: value["labels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|label| label["name"].as_str().map(str::to_string))
+            .collect(),
+        body: string(&value["body"]),
+        source_branch: None,
+        target_branch: None,
+        comments: Vec::new(),
+    })
+}
+
+async fn fetch_gitlab(issue_ref: &IssueRef, with_comments: bool) -> Result<Issue> {
+    let api = |path: String| {
+        run("glab", vec![
+            "api".to_string(),
+            path,
+            "--hostname".to_string(),
+            issue_ref.host.clone(),
+        ])
+    };
+    let kind = issue_ref.kind.unwrap_or(IssueKind::Issue);
+    let path = format!(
+        "projects/{}/{}/{}",
+        issue_ref.project.replace('/', "%2F"),
+        match kind {
+            IssueKind::Issue => "issues",
+            IssueKind::PullRequest => "merge_requests",
+        },
+        issue_ref.number
+    );
+    let mut issue = gitlab_issue(&api(path.clone()).await?, kind)?;
+    if with_comments {
+        let notes = api(format!("{path}/notes?sort=asc&per_page=100")).await?;
+        issue.comments = latest(
+            notes
+                .as_array()
+                .into_iter()
+                .flatten()
+                // Leaves out the notes GitLab makes of changes, like labels being added.
+                .filter(|note| !note["system"].as_bool().unwrap_or_default())
+                .map(|note| Comment {
+                    author: string(&note["author"]["username"]),
+                    created_at: string(&note["created_at"]),
+                    body: string(&note["body"]),
+                })
+                .collect(),
+        );
+    }
+    Ok(issue)
+}
+
+fn gitlab_issue(value: &Value, kind: IssueKind) -> Result<Issue> {
+    Ok(Issue {
+        tracker: Tracker::GitLab,
+        kind,
+        url: string(&value["web_url"]),
+        number: value["iid"].as_u64().ok_or_else(|| eyre!("The issue has no number"))?,
+        title: string(&value["title"]),
+        state: string(&value["state"]),
+        author: string(&value["author"]["username"]),
+        labels: value["labels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|label| label.as_str().map(str::to_string))
+            .collect(),
+        body: string(&value["description"]),
+        source_branch: value["source_branch"].as_str().map(str::to_string),
+        target_branch: value["target_branch"].as_str().map(str::to_string),
+        comments: Vec::new(),
+    })
+}
+
+fn latest(mut comments: Vec<Comment>) -> Vec<Comment> {
+    comments.drain(..comments.len().saturating_sub(MAX_COMMENTS));
+    comments
+}
+
+fn string(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+/// Runs the CLI of a tracker, returning the JSON it printed.
+async fn run(program: &str, args: Vec<String>) -> Result<Value> {
+    let output = tokio::process::Command::new(program)
+        .args(&args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .map_err(|err| eyre!("Failed to run {program}, is it installed? {err}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reference() {
+        assert_eq!(
+            parse_reference("https://github.com/aws/amazon-q-developer-cli/issues/123", None).unwrap(),
+            IssueRef {
+                tracker: Tracker::GitHub,
+                host: "github.com".to_string(),
+                project: "aws/amazon-q-developer-cli".to_string(),
+                number: 123,
+                kind: Some(IssueKind::Issue),
+            }
+        );
+        assert_eq!(
+            parse_reference("https://gitlab.com/group/sub/project/-/merge_requests/7#note_1", None).unwrap(),
+            IssueRef {
+                tracker: Tracker::GitLab,
+                host: "gitlab.com".to_string(),
+                project: "group/sub/project".to_string(),
+                number: 7,
+                kind: Some(IssueKind::PullRequest),
+            }
+        );
+        assert_eq!(
+            parse_reference("#42", Some("git@github.com:owner/repo.git")).unwrap(),
+            IssueRef {
+                tracker: Tracker::GitHub,
+                host: "github.com".to_string(),
+                project: "owner/repo".to_string(),
+                number: 42,
+                kind: None,
+            }
+        );
+        assert_eq!(
+            parse_reference("42", Some("https://gitlab.example.com/team/app"))
+                .unwrap()
+                .kind,
+            Some(IssueKind::Issue)
+        );
+        assert!(parse_reference("42", None).is_err());
+        assert!(parse_reference("https://github.com/owner/repo/blob/main/README.md", None).is_err());
+    }
+
+    #[test]
+    fn test_parse_remote() {
+        let expected = Some(("github.com".to_string(), "owner/repo".to_string()));
+        assert_eq!(parse_remote("git@github.com:owner/repo.git"), expected);
+        assert_eq!(parse_remote("https://github.com/owner/repo"), expected);
+        assert_eq!(parse_remote("ssh://git@github.com:22/owner/repo.git"), expected);
+        assert_eq!(parse_remote("/local/path"), None);
+    }
+
+    #[test]
+    fn test_github_issue() {
+        let issue = github_issue(&serde_json::json!({
+            "html_url": "https://github.com/owner/repo/pull/5",
+            "number": 5,
+            "title": "Fix the parser",
+            "state": "open",
+            "user": { "login": "dev" },
+            "labels": [{ "name": "bug" }],
+            "body": "It panics on empty input.",
+            "pull_request": {},
+        }))
+        .unwrap();
+        assert_eq!(issue.kind, IssueKind::PullRequest);
+        assert_eq!(issue.labels, vec!["bug"]);
+        assert_eq!(issue.author, "dev");
+    }
+}
@@ -22,7 +22,9 @@ use crate::cli::experiment::experiment_manager::{
     ExperimentName,
 };
 use crate::os::Os;
+use crate::util::knowledge_documents;
 use crate::util::knowledge_store::KnowledgeStore;
+use crate::util::time::format_duration;
 use crate::util::tool_permission_checker::is_tool_in_allowlist;
 
 /// The Knowledge tool allows storing and retrieving information across chat sessions.
@@ -96,7 +98,7 @@ impl Knowledge {
         match self {
             Knowledge::Add(add) => {
                 // Check if value is intended to be a path (doesn't contain newlines)
-                if !add.value.contains('\n') {
+                if !add.value.contains('\n') && !knowledge_documents::is_url(&add.value) {
                     let path = crate::cli::chat::tools::sanitize_path_tool_arg(os, &add.value);
                     if !path.exists() {
                         eyre::bail!("Path '{}' does not exist", add.value);
@@ -129,7 +131,7 @@ impl Knowledge {
                 }
 
                 // Validate the path exists
-                if !update.path.is_empty() {
+                if !update.path.is_empty() && !knowledge_documents::is_url(&update.path) {
                     let path = crate::cli::chat::tools::sanitize_path_tool_arg(os, &update.path);
                     if !path.exists() {
                         eyre::bail!("Path '{}' does not exist", update.path);
@@ -161,9 +163,18 @@ impl Knowledge {
                     style::ResetColor,
                 )?;
 
-                // Check if value is a path or text content
+                // Check if value is a URL, a path or text content
                 let path = crate::cli::chat::tools::sanitize_path_tool_arg(os, &add.value);
-                if path.exists() {
+                if knowledge_documents::is_url(&add.value) {
+                    queue!(
+                        updates,
+                        style::Print(" (url: "),
+                        style::SetForegroundColor(Color::Green),
+                        style::Print(&add.value),
+                        style::ResetColor,
+                        style::Print(")\n")
+                    )?;
+                } else if path.exists() {
                     let path_type = if path.is_dir() { "directory" } else { "file" };
                     queue!(
                         updates,
@@ -361,8 +372,14 @@ impl Knowledge {
                     }
                 } else if !remove.path.is_empty() {
                     // Remove by path
-                    let sanitized_path = crate::cli::chat::tools::sanitize_path_tool_arg(os, &remove.path);
-                    match store.remove_by_path(sanitized_path.to_string_lossy().as_ref()).await {
+                    let sanitized_path = if knowledge_documents::is_url(&remove.path) {
+                        remove.path.clone()
+                    } else {
+                        crate::cli::chat::tools::sanitize_path_tool_arg(os, &remove.path)
+                            .to_string_lossy()
+                            .to_string()
+                    };
+                    match store.remove_by_path(&sanitized_path).await {
                         Ok(_) => format!("Removed context with path '{}' from knowledge base", remove.path),
                         Err(e) => format!("Failed to remove context by path: {}", e),
                     }
@@ -380,15 +397,18 @@ impl Knowledge {
                     });
                 }
 
-                // Sanitize the path
-                let path = crate::cli::chat::tools::sanitize_path_tool_arg(os, &update.path);
-                if !path.exists() {
-                    return Ok(InvokeOutput {
-                        output: OutputKind::Text(format!("Error: Path '{}' does not exist", update.path)),
-                    });
-                }
-
-                let sanitized_path = path.to_string_lossy().to_string();
+                // Sanitize the path, documents are updated by their URL
+                let sanitized_path = if knowledge_documents::is_url(&update.path) {
+                    update.path.clone()
+                } else {
+                    let path = crate::cli::chat::tools::sanitize_path_tool_arg(os, &update.path);
+                    if !path.exists() {
+                        return Ok(InvokeOutput {
+                            output: OutputKind::Text(format!("Error: Path '{}' does not exist", update.path)),
+                        });
+                    }
+                    path.to_string_lossy().to_string()
+                };
 
                 // Choose the appropriate update method based on provided identifiers
                 if !update.context_id.is_empty() {
@@ -434,7 +454,17 @@ impl Knowledge {
                             let mut output = format!("Search results for \"{}\":\n\n", search.query);
                             for result in results {
                                 if let Some(text) = result.text() {
-                                    output.push_str(&format!("{}\n\n", text));
+                                    output.push_str(&format!("{}\n", text));
+                                    // Cite the document a chunk came from, by its URL if it was fetched
+                                    let payload = &result.point.payload;
+                                    if let Some(source) = payload
+                                        .get("source")
+                                        .or_else(|| payload.get("path"))
+                                        .and_then(|source| source.as_str())
+                                    {
+                                        output.push_str(&format!("Source: {}\n", source));
+                                    }
+                                    output.push('\n');
                                 }
                             }
                             output
@@ -460,9 +490,10 @@ impl Knowledge {
                         } else {
                             output.push_str("Knowledge base entries:\n");
                             for context in contexts {
-                                output.push_str(&format!("- ID: {}\n  Name: {}\n  Description: {}\n  Persistent: {}\n  Created: {}\n  Last Updated: {}\n  Items: {}\n\n",
+                                output.push_str(&format!("- ID: {}\n  Name: {}\n  Source: {}\n  Description: {}\n  Persistent: {}\n  Created: {}\n  Last Updated: {}\n  Items: {}\n\n",
                                     context.id,
                                     context.name,
+                                    context.source.as_deref().or(context.source_path.as_deref()).unwrap_or("text"),
                                     context.description,
                                     context.persistent,
                                     context.created_at.format("%Y-%m-%d %H:%M:%S"),
@@ -486,9 +517,10 @@ impl Knowledge {
                         } else {
                             output.push_str("Knowledge base entries:\n");
                             for context in contexts {
-                                output.push_str(&format!("- ID: {}\n  Name: {}\n  Description: {}\n  Persistent: {}\n  Created: {}\n  Last Updated: {}\n  Items: {}\n\n",
+                                output.push_str(&format!("- ID: {}\n  Name: {}\n  Source: {}\n  Description: {}\n  Persistent: {}\n  Created: {}\n  Last Updated: {}\n  Items: {}\n\n",
                                     context.id,
                                     context.name,
+                                    context.source.as_deref().or(context.source_path.as_deref()).unwrap_or("text"),
                                     context.description,
                                     context.persistent,
                                     context.created_at.format("%Y-%m-%d %H:%M:%S"),
@@ -567,7 +599,7 @@ impl Knowledge {
             } else if op.total > 0 {
                 let percentage = (op.current as f64 / op.total as f64 * 100.0) as u8;
                 if let Some(eta) = op.eta {
-                    output.push_str(&format!("   {}% • ETA: {}s\n", percentage, eta.as_secs()));
+                    output.push_str(&format!("   {}% • ETA: {}\n", percentage, format_duration(eta)));
                 } else {
                     output.push_str(&format!("   {}%\n", percentage));
                 }
@@ -1,13 +1,24 @@
+pub mod cloudwatch_logs;
 pub mod custom_tool;
 pub mod delegate;
+pub mod dependency_report;
 pub mod execute;
 pub mod fs_read;
 pub mod fs_write;
 pub mod gh_issue;
+pub mod git;
+pub mod infra;
 pub mod introspect;
+pub mod issue_tracker;
+#[cfg(feature = "knowledge")]
 pub mod knowledge;
+pub mod retrieve_output;
+pub mod security_scan;
+#[cfg(feature = "knowledge")]
+pub mod semantic_search;
 pub mod thinking;
 pub mod todo;
+pub mod update_plan;
 pub mod use_aws;
 
 use std::borrow::{
@@ -21,6 +32,7 @@ use std::path::{
     PathBuf,
 };
 
+use cloudwatch_logs::CloudwatchLogs;
 use crossterm::queue;
 use crossterm::style::{
     self,
@@ -28,13 +40,21 @@ use crossterm::style::{
 };
 use custom_tool::CustomTool;
 use delegate::Delegate;
+use dependency_report::DependencyReport;
 use execute::ExecuteCommand;
 use eyre::Result;
 use fs_read::FsRead;
 use fs_write::FsWrite;
-use gh_issue::GhIssue;
+use git::Git;
+use infra::InfraChange;
 use introspect::Introspect;
+use issue_tracker::IssueTracker;
+#[cfg(feature = "knowledge")]
 use knowledge::Knowledge;
+use retrieve_output::RetrieveOutput;
+use security_scan::SecurityScan;
+#[cfg(feature = "knowledge")]
+use semantic_search::SemanticSearch;
 use serde::{
     Deserialize,
     Serialize,
@@ -42,6 +62,7 @@ use serde::{
 use thinking::Thinking;
 use todo::TodoList;
 use tracing::error;
+use update_plan::UpdatePlan;
 use use_aws::UseAws;
 
 use super::consts::{
@@ -60,19 +81,26 @@ use crate::cli::chat::line_tracker::FileLineTracker;
 use crate::os::Os;
 
 pub const DEFAULT_APPROVE: [&str; 0] = [];
-pub const NATIVE_TOOLS: [&str; 9] = [
+pub const NATIVE_TOOLS: [&str; 16] = [
     "fs_read",
     "fs_write",
     #[cfg(windows)]
-    "execute_cmd",
+    "execute_powershell",
     #[cfg(not(windows))]
     "execute_bash",
     "use_aws",
-    "gh_issue",
+    "cloudwatch_logs",
+    "issue_tracker",
+    "git",
     "knowledge",
+    "semantic_search",
     "thinking",
     "todo_list",
+    "update_plan",
     "delegate",
+    "dependency_report",
+    "security_scan",
+    "retrieve_output",
 ];
 
 /// Represents an executable tool use.
@@ -83,13 +111,22 @@ pub enum Tool {
     FsWrite(FsWrite),
     ExecuteCommand(ExecuteCommand),
     UseAws(UseAws),
+    CloudwatchLogs(CloudwatchLogs),
     Custom(CustomTool),
-    GhIssue(GhIssue),
+    IssueTracker(IssueTracker),
+    Git(Git),
     Introspect(Introspect),
+    #[cfg(feature = "knowledge")]
     Knowledge(Knowledge),
+    #[cfg(feature = "knowledge")]
+    SemanticSearch(SemanticSearch),
     Thinking(Thinking),
     Todo(TodoList),
+    UpdatePlan(UpdatePlan),
     Delegate(Delegate),
+    DependencyReport(DependencyReport),
+    SecurityScan(SecurityScan),
+    RetrieveOutput(RetrieveOutput),
 }
 
 impl Tool {
@@ -99,17 +136,26 @@ impl Tool {
             Tool::FsRead(_) => "fs_read",
             Tool::FsWrite(_) => "fs_write",
             #[cfg(windows)]
-            Tool::ExecuteCommand(_) => "execute_cmd",
+            Tool::ExecuteCommand(_) => "execute_powershell",
             #[cfg(not(windows))]
             Tool::ExecuteCommand(_) => "execute_bash",
             Tool::UseAws(_) => "use_aws",
+            Tool::CloudwatchLogs(_) => "cloudwatch_logs",
             Tool::Custom(custom_tool) => &custom_tool.name,
-            Tool::GhIssue(_) => "gh_issue",
+            Tool::IssueTracker(_) => "issue_tracker",
+            Tool::Git(_) => "git",
             Tool::Introspect(_) => "introspect",
+            #[cfg(feature = "knowledge")]
             Tool::Knowledge(_) => "knowledge",
+            #[cfg(feature = "knowledge")]
+            Tool::SemanticSearch(_) => "semantic_search",
             Tool::Thinking(_) => "thinking (prerelease)",
             Tool::Todo(_) => "todo_list",
+            Tool::UpdatePlan(_) => "update_plan",
             Tool::Delegate(_) => "delegate",
+            Tool::DependencyReport(_) => "dependency_report",
+            Tool::SecurityScan(_) => "security_scan",
+            Tool::RetrieveOutput(_) => "retrieve_output",
         }
         .to_owned()
     }
@@ -121,13 +167,44 @@ impl Tool {
             Tool::FsWrite(fs_write) => fs_write.eval_perm(os, agent),
             Tool::ExecuteCommand(execute_command) => execute_command.eval_perm(os, agent),
             Tool::UseAws(use_aws) => use_aws.eval_perm(os, agent),
+            Tool::CloudwatchLogs(cloudwatch_logs) => cloudwatch_logs.eval_perm(os, agent),
             Tool::Custom(custom_tool) => custom_tool.eval_perm(os, agent),
-            Tool::GhIssue(_) => PermissionEvalResult::Allow,
+            Tool::IssueTracker(_) => PermissionEvalResult::Allow,
+            Tool::Git(git) => git.eval_perm(os, agent),
             Tool::Introspect(_) => PermissionEvalResult::Allow,
             Tool::Thinking(_) => PermissionEvalResult::Allow,
             Tool::Todo(_) => PermissionEvalResult::Allow,
+            Tool::UpdatePlan(_) => PermissionEvalResult::Allow,
+            #[cfg(feature = "knowledge")]
             Tool::Knowledge(knowledge) => knowledge.eval_perm(os, agent),
+            #[cfg(feature = "knowledge")]
+            Tool::SemanticSearch(_) => PermissionEvalResult::Allow,
             Tool::Delegate(_) => PermissionEvalResult::Allow, // Allow delegate tool
+            Tool::DependencyReport(_) => PermissionEvalResult::Allow,
+            Tool::SecurityScan(scan) => scan.eval_perm(os, agent),
+            Tool::RetrieveOutput(_) => PermissionEvalResult::Allow,
+        }
+    }
+
+    /// Whether the tool changes files, or runs a command or an AWS operation that isn't read-only.
+    /// These are the tools recorded instead of run in plan mode.
+    pub fn is_mutating(&self) -> bool {
+        match self {
+            Tool::FsWrite(_) => true,
+            Tool::ExecuteCommand(execute_command) => execute_command.requires_acceptance(None, true),
+            Tool::UseAws(use_aws) => use_aws.requires_acceptance(),
+            Tool::Git(git) => !git.is_read_only(),
+            _ => false,
+        }
+    }
+
+    /// The infrastructure change the tool would make, which is confirmed every time however much
+    /// the tool is trusted.
+    pub fn infra_change(&self) -> Option<InfraChange> {
+        match self {
+            Tool::UseAws(use_aws) => InfraChange::from_use_aws(use_aws),
+            Tool::ExecuteCommand(execute_command) => InfraChange::from_command(&execute_command.command),
+            _ => None,
         }
     }
 
@@ -145,13 +222,22 @@ impl Tool {
             Tool::FsWrite(fs_write) => fs_write.invoke(os, stdout, line_tracker).await,
             Tool::ExecuteCommand(execute_command) => execute_command.invoke(os, stdout).await,
             Tool::UseAws(use_aws) => use_aws.invoke(os, stdout).await,
+            Tool::CloudwatchLogs(cloudwatch_logs) => cloudwatch_logs.invoke(os, stdout).await,
             Tool::Custom(custom_tool) => custom_tool.invoke(os, stdout).await,
-            Tool::GhIssue(gh_issue) => gh_issue.invoke(os, stdout).await,
+            Tool::IssueTracker(issue_tracker) => issue_tracker.invoke(os, stdout).await,
+            Tool::Git(git) => git.invoke(os, stdout).await,
             Tool::Introspect(introspect) => introspect.invoke(os, stdout).await,
+            #[cfg(feature = "knowledge")]
             Tool::Knowledge(knowledge) => knowledge.invoke(os, stdout, active_agent).await,
+            #[cfg(feature = "knowledge")]
+            Tool::SemanticSearch(search) => search.invoke(os, stdout, active_agent).await,
             Tool::Thinking(think) => think.invoke(stdout).await,
             Tool::Todo(todo) => todo.invoke(os, stdout).await,
+            Tool::UpdatePlan(update) => update.invoke(os, stdout).await,
             Tool::Delegate(delegate) => delegate.invoke(os, stdout, agents).await,
+            Tool::DependencyReport(report) => report.invoke(os, stdout).await,
+            Tool::SecurityScan(scan) => scan.invoke(os, stdout).await,
+            Tool::RetrieveOutput(retrieve) => retrieve.invoke(os, stdout).await,
         }
     }
 
@@ -162,13 +248,22 @@ impl Tool {
             Tool::FsWrite(fs_write) => fs_write.queue_description(os, output),
             Tool::ExecuteCommand(execute_command) => execute_command.queue_description(output),
             Tool::UseAws(use_aws) => use_aws.queue_description(output),
+            Tool::CloudwatchLogs(cloudwatch_logs) => cloudwatch_logs.queue_description(output),
             Tool::Custom(custom_tool) => custom_tool.queue_description(output),
-            Tool::GhIssue(gh_issue) => gh_issue.queue_description(output),
+            Tool::IssueTracker(issue_tracker) => issue_tracker.queue_description(output),
+            Tool::Git(git) => git.queue_description(os, output),
             Tool::Introspect(_) => Introspect::queue_description(output),
+            #[cfg(feature = "knowledge")]
             Tool::Knowledge(knowledge) => knowledge.queue_description(os, output).await,
+            #[cfg(feature = "knowledge")]
+            Tool::SemanticSearch(search) => search.queue_description(output),
             Tool::Thinking(thinking) => thinking.queue_description(output),
             Tool::Todo(_) => Ok(()),
+            Tool::UpdatePlan(update) => update.queue_description(output),
             Tool::Delegate(delegate) => delegate.queue_description(output),
+            Tool::DependencyReport(report) => report.queue_description(os, output),
+            Tool::SecurityScan(scan) => scan.queue_description(os, output),
+            Tool::RetrieveOutput(retrieve) => retrieve.queue_description(output),
         }
     }
 
@@ -179,13 +274,22 @@ impl Tool {
             Tool::FsWrite(fs_write) => fs_write.validate(os).await,
             Tool::ExecuteCommand(execute_command) => execute_command.validate(os).await,
             Tool::UseAws(use_aws) => use_aws.validate(os).await,
+            Tool::CloudwatchLogs(cloudwatch_logs) => cloudwatch_logs.validate(os).await,
             Tool::Custom(custom_tool) => custom_tool.validate(os).await,
-            Tool::GhIssue(gh_issue) => gh_issue.validate(os).await,
+            Tool::IssueTracker(issue_tracker) => issue_tracker.validate(os).await,
+            Tool::Git(git) => git.validate(os).await,
             Tool::Introspect(introspect) => introspect.validate(os).await,
+            #[cfg(feature = "knowledge")]
             Tool::Knowledge(knowledge) => knowledge.validate(os).await,
+            #[cfg(feature = "knowledge")]
+            Tool::SemanticSearch(search) => search.validate(os).await,
             Tool::Thinking(think) => think.validate(os).await,
             Tool::Todo(todo) => todo.validate(os).await,
+            Tool::UpdatePlan(update) => update.validate(os).await,
             Tool::Delegate(_) => Ok(()), // No validation needed for delegate tool
+            Tool::DependencyReport(report) => report.validate(os).await,
+            Tool::SecurityScan(scan) => scan.validate(os).await,
+            Tool::RetrieveOutput(retrieve) => retrieve.validate(os).await,
         }
     }
 
@@ -402,7 +506,7 @@ fn format_path(cwd: impl AsRef<Path>, path: impl AsRef<Path>) -> String {
         .unwrap_or(path.as_ref().to_string_lossy().to_string())
 }
 
-fn supports_truecolor(os: &Os) -> bool {
+pub fn supports_truecolor(os: &Os) -> bool {
     // Simple override to disable truecolor since shell_color doesn't use Context.
     !os.env.get("Q_DISABLE_TRUECOLOR").is_ok_and(|s| !s.is_empty())
         && shell_color::get_color_support().contains(shell_color::ColorSupport::TERM24BIT)
@@ -0,0 +1,202 @@
+//! The `retrieve_output` tool, with which the model reads the parts of a large tool output that
+//! were left out of the tool result.
+//!
+//! Outputs longer than a tool result may be are kept whole in the session temp dir, under a handle
+//! that the truncated tool result names. They are removed with the temp dir when the session ends.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde::Deserialize;
+use tracing::warn;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+};
+use crate::cli::chat::consts::MAX_TOOL_RESPONSE_SIZE;
+use crate::cli::chat::util::truncate_safe;
+use crate::os::Os;
+
+/// How much of a stored output is returned when the model does not say.
+const DEFAULT_PAGE_SIZE: usize = 50_000;
+
+/// Room kept for the note appended to a truncated output.
+const NOTE_SIZE: usize = 300;
+
+const HANDLE_PREFIX: &str = "output-";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetrieveOutput {
+    /// The handle of the output, as named in the truncated tool result.
+    pub handle: String,
+    /// The byte to start reading at.
+    #[serde(default)]
+    pub offset: usize,
+    /// How many bytes to read, [DEFAULT_PAGE_SIZE] by default.
+    #[serde(default)]
+    pub length: Option<usize>,
+}
+
+impl RetrieveOutput {
+    pub async fn invoke(&self, os: &Os, _output: &mut impl Write) -> Result<InvokeOutput> {
+        let path = output_path(os, &self.handle).await?;
+        let content = match os.fs.read_to_string(&path).await {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => bail!(
+                "No output is stored as '{}'. Outputs are only kept for the session they were made in.",
+                self.handle
+            ),
+            Err(err) => return Err(err.into()),
+        };
+        let length = self
+            .length
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .min(MAX_TOOL_RESPONSE_SIZE - NOTE_SIZE);
+
+        Ok(InvokeOutput {
+            output: OutputKind::Text(page(&content, self.offset, length)),
+        })
+    }
+
+    pub fn queue_description(&self, output: &mut impl Write) -> Result<()> {
+        queue!(
+            output,
+            style::Print("Reading "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(&self.handle),
+            style::ResetColor,
+            style::Print(format!(" from byte {}", self.offset)),
+        )?;
+        Ok(())
+    }
+
+    pub async fn validate(&self, os: &Os) -> Result<()> {
+        output_path(os, &self.handle).await?;
+        Ok(())
+    }
+}
+
+/// Returns `output` if it is at most `max_size` bytes long. Otherwise the whole of it is stored
+/// for [RetrieveOutput], and its start is returned with a note on how to read the rest.
+pub async fn truncate_with_handle(os: &Os, output: &str, max_size: usize) -> String {
+    if output.len() <= max_size {
+        return output.to_string();
+    }
+
+    let head = truncate_safe(output, max_size.saturating_sub(NOTE_SIZE));
+    match store(os, output).await {
+        Ok(handle) => format!(
+            "{head}\n... truncated, showing {} of {} bytes. The whole output is stored as `{handle}`: use the retrieve_output tool with offset {} to read on.",
+            head.len(),
+            output.len(),
+            head.len(),
+        ),
+        Err(err) => {
+            warn!(?err, "Failed to store a large tool output");
+            format!("{head} ... truncated")
+        },
+    }
+}
+
+async fn store(os: &Os, output: &str) -> Result<String> {
+    // Handles are unique rather than counted, since a resumed conversation names the handles of
+    // earlier sessions, which must not refer to this session's outputs.
+    let handle = format!("{HANDLE_PREFIX}{}", uuid::Uuid::new_v4().simple());
+    let path = output_path(os, &handle).await?;
+    if let Some(parent) = path.parent() {
+        os.fs.create_dir_all(parent).await?;
+    }
+    os.fs.write(&path, output).await?;
+    Ok(handle)
+}
+
+async fn output_path(os: &Os, handle: &str) -> Result<PathBuf> {
+    let is_handle = handle
+        .strip_prefix(HANDLE_PREFIX)
+        .is_some_and(|id| !id.is_empty() && id.bytes().all(|b| b.is_ascii_alphanumeric()));
+    if !is_handle {
+        bail!("'{handle}' is not the handle of a stored output");
+    }
+    Ok(os
+        .session_temp_dir()
+        .await?
+        .join("outputs")
+        .join(format!("{handle}.txt")))
+}
+
+/// Up to `length` bytes of `content` from `offset`, moved back to the start of a character if
+/// needed, with where they are in the output.
+fn page(content: &str, offset: usize, length: usize) -> String {
+    let mut start = offset.min(content.len());
+    while !content.is_char_boundary(start) {
+        start -= 1;
+    }
+    let text = truncate_safe(&content[start..], length);
+    let end = start + text.len();
+    let position = if end < content.len() {
+        format!("bytes {start}..{end} of {}. Continue from offset {end}", content.len())
+    } else {
+        format!("bytes {start}..{end} of {}, the end of the output", content.len())
+    };
+    format!("{text}\n... {position}.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_truncate_and_retrieve() {
+        let os = Os::new().await.unwrap();
+        assert_eq!(truncate_with_handle(&os, "short", 1000).await, "short");
+
+        let output = "é".repeat(1000);
+        let truncated = truncate_with_handle(&os, &output, 1000).await;
+        assert!(truncated.len() <= 1000);
+        let handle = truncated
+            .split('`')
+            .nth(1)
+            .expect("the handle is named in the note")
+            .to_string();
+
+        let retrieve = RetrieveOutput {
+            handle,
+            offset: 1999,
+            length: None,
+        };
+        retrieve.validate(&os).await.unwrap();
+        let OutputKind::Text(text) = retrieve.invoke(&os, &mut std::io::sink()).await.unwrap().output else {
+            panic!("the output is text");
+        };
+        assert_eq!(text, "é\n... bytes 1998..2000 of 2000, the end of the output.");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_other_handles() {
+        let os = Os::new().await.unwrap();
+        for handle in ["../secrets", "output-", "output-1/../../x", "output-.."] {
+            let retrieve = RetrieveOutput {
+                handle: handle.to_string(),
+                offset: 0,
+                length: None,
+            };
+            assert!(retrieve.validate(&os).await.is_err());
+        }
+    }
+
+    #[test]
+    fn test_page() {
+        assert_eq!(page("abcdef", 2, 2), "cd\n... bytes 2..4 of 6. Continue from offset 4.");
+        assert_eq!(page("abc", 10, 2), "\n... bytes 3..3 of 3, the end of the output.");
+    }
+}
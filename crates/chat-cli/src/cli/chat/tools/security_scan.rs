@@ -0,0 +1,744 @@
+//! The `security_scan` tool, which runs security scanners on paths of the project and returns
+//! their findings in one shape, most severe first, so that the model can explain and fix them and
+//! scan again to confirm.
+//!
+//! semgrep, bandit and cargo-audit are run locally when they are installed. `codescan` uploads the
+//! source files to the code scan API of the service instead.
+
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::Stdio;
+use std::time::Duration;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+    sanitize_path_tool_arg,
+};
+use crate::cli::agent::{
+    Agent,
+    PermissionEvalResult,
+};
+use crate::cli::chat::util::truncate_safe;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::tool_permission_checker::is_tool_in_allowlist;
+
+/// Findings beyond this many are only counted, to keep the result compact.
+const MAX_FINDINGS: usize = 50;
+const MAX_MESSAGE_BYTES: usize = 300;
+const SCANNER_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Directories of dependencies and build outputs, which are not uploaded for a code scan.
+const SKIPPED_DIRS: &[&str] = &[
+    ".git",
+    "node_modules",
+    "target",
+    "build",
+    "dist",
+    ".venv",
+    "venv",
+    "__pycache__",
+];
+/// Larger files are most likely generated, and are not uploaded for a code scan.
+const MAX_UPLOAD_FILE_SIZE: u64 = 1024 * 1024;
+const MAX_UPLOAD_SIZE: u64 = 200 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scanner {
+    Semgrep,
+    Bandit,
+    CargoAudit,
+    /// The code scan API of the service.
+    Codescan,
+}
+
+impl Scanner {
+    const DEFAULT: [Self; 3] = [Self::Semgrep, Self::Bandit, Self::CargoAudit];
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SecurityScan {
+    /// Files or directories to scan, the current directory by default.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// The scanners to run, those of [Setting::ChatSecurityScanScanners] by default.
+    #[serde(default)]
+    pub scanners: Vec<Scanner>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Critical,
+    High,
+    Medium,
+    Low,
+    Info,
+}
+
+impl Severity {
+    /// The severity of the levels of every scanner, such as semgrep's `ERROR` or bandit's `HIGH`.
+    fn parse(level: &str) -> Self {
+        match level.to_ascii_lowercase().as_str() {
+            "critical" => Self::Critical,
+            "high" | "error" => Self::High,
+            "medium" | "moderate" | "warning" => Self::Medium,
+            "low" => Self::Low,
+            _ => Self::Info,
+        }
+    }
+}
+
+/// A finding of any scanner.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Finding {
+    scanner: Scanner,
+    severity: Severity,
+    rule: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwe: Option<String>,
+    /// How to fix it, when the scanner says.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<String>,
+}
+
+/// What a scanner found, or why it was not run.
+enum Outcome {
+    Findings(Vec<Finding>),
+    Skipped(String),
+}
+
+#[derive(Debug, Serialize)]
+struct ScannerRun {
+    scanner: Scanner,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    findings: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    paths: Vec<String>,
+    scanners: Vec<ScannerRun>,
+    counts: BTreeMap<Severity, usize>,
+    findings: Vec<Finding>,
+    /// The number of findings left out of `findings`, all less or as severe as the last one.
+    omitted: usize,
+}
+
+impl SecurityScan {
+    pub async fn invoke(&self, os: &Os, _output: &mut impl Write) -> Result<InvokeOutput> {
+        let cwd = os.env.current_dir()?;
+        let paths = self.paths(os, &cwd);
+
+        let mut runs = Vec::new();
+        let mut findings = Vec::new();
+        for scanner in self.scanners(os) {
+            let outcome = match scanner {
+                Scanner::Semgrep => semgrep(os, &cwd, &paths).await,
+                Scanner::Bandit => bandit(&cwd, &paths).await,
+                Scanner::CargoAudit => cargo_audit(&cwd, &paths).await,
+                Scanner::Codescan => code_scan(os, &cwd, &paths).await,
+            };
+            let mut run = ScannerRun {
+                scanner,
+                findings: None,
+                skipped: None,
+                error: None,
+            };
+            match outcome {
+                Ok(Outcome::Findings(found)) => {
+                    run.findings = Some(found.len());
+                    findings.extend(found);
+                },
+                Ok(Outcome::Skipped(reason)) => run.skipped = Some(reason),
+                Err(err) => run.error = Some(format!("{err:#}")),
+            }
+            runs.push(run);
+        }
+
+        let mut counts = BTreeMap::new();
+        for finding in &findings {
+            *counts.entry(finding.severity).or_default() += 1;
+        }
+        findings.sort_by(|a, b| (a.severity, &a.path, a.line, &a.rule).cmp(&(b.severity, &b.path, b.line, &b.rule)));
+        findings.dedup();
+        let omitted = findings.len().saturating_sub(MAX_FINDINGS);
+        findings.truncate(MAX_FINDINGS);
+
+        let report = Report {
+            paths: paths.iter().map(|path| relative(&cwd, path)).collect(),
+            scanners: runs,
+            counts,
+            findings,
+            omitted,
+        };
+        Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::to_value(&report)?),
+        })
+    }
+
+    pub fn queue_description(&self, os: &Os, output: &mut impl Write) -> Result<()> {
+        let cwd = os.env.current_dir()?;
+        let paths = self
+            .paths(os, &cwd)
+            .iter()
+            .map(|path| relative(&cwd, path))
+            .collect::<Vec<_>>();
+        let scanners = self
+            .scanners(os)
+            .iter()
+            .map(|scanner| {
+                serde_json::to_value(scanner)
+                    .ok()
+                    .and_then(|v| v.as_str().map(str::to_string))
+            })
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+
+        queue!(
+            output,
+            style::Print("Scanning "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(paths.join(", ")),
+            style::ResetColor,
+            style::Print(format!(" with {}", scanners.join(", "))),
+        )?;
+        if self.scanners(os).contains(&Scanner::Codescan) {
+            queue!(
+                output,
+                style::SetForegroundColor(Color::Yellow),
+                style::Print("\nThe source files will be uploaded to the code scan service"),
+                style::ResetColor,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub async fn validate(&self, os: &Os) -> Result<()> {
+        let cwd = os.env.current_dir()?;
+        for path in self.paths(os, &cwd) {
+            if !os.fs.exists(&path) {
+                bail!("'{}' does not exist", path.display());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn eval_perm(&self, _os: &Os, agent: &Agent) -> PermissionEvalResult {
+        // Scanners are programs of their own, and the code scan uploads the code.
+        match is_tool_in_allowlist(&agent.allowed_tools, "security_scan", None) {
+            true => PermissionEvalResult::Allow,
+            false => PermissionEvalResult::Ask,
+        }
+    }
+
+    fn paths(&self, os: &Os, cwd: &Path) -> Vec<PathBuf> {
+        match self.paths.is_empty() {
+            true => vec![cwd.to_path_buf()],
+            false => self.paths.iter().map(|path| sanitize_path_tool_arg(os, path)).collect(),
+        }
+    }
+
+    fn scanners(&self, os: &Os) -> Vec<Scanner> {
+        if !self.scanners.is_empty() {
+            return self.scanners.clone();
+        }
+        os.database
+            .settings
+            .get(Setting::ChatSecurityScanScanners)
+            .and_then(|value| serde_json::from_value(value.clone()).ok())
+            .unwrap_or_else(|| Scanner::DEFAULT.to_vec())
+    }
+}
+
+/// `path` relative to `cwd`, when it is inside it.
+fn relative(cwd: &Path, path: &Path) -> String {
+    match path.strip_prefix(cwd) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
+
+fn truncate(message: &str) -> String {
+    let message = message.trim();
+    match message.len() > MAX_MESSAGE_BYTES {
+        true => format!("{}...", truncate_safe(message, MAX_MESSAGE_BYTES)),
+        false => message.to_string(),
+    }
+}
+
+struct CommandOutput {
+    stdout: String,
+    stderr: String,
+}
+
+/// Runs `program`, `None` if it is not installed.
+async fn run(program: &str, args: Vec<OsString>, cwd: &Path) -> Result<Option<CommandOutput>> {
+    let child = tokio::process::Command::new(program)
+        .args(args)
+        .current_dir(cwd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+
+    let Ok(output) = tokio::time::timeout(SCANNER_TIMEOUT, child.wait_with_output()).await else {
+        bail!(
+            "{program} did not finish within {} minutes",
+            SCANNER_TIMEOUT.as_secs() / 60
+        );
+    };
+    let output = output?;
+    Ok(Some(CommandOutput {
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }))
+}
+
+/// Parses the JSON report of a scanner, with its stderr in the error if there is none, as the
+/// scanners report failures there.
+fn parse_report<T>(output: &CommandOutput, parse: impl FnOnce(Value) -> T) -> Result<T> {
+    match serde_json::from_str(&output.stdout) {
+        Ok(value) => Ok(parse(value)),
+        Err(_) => bail!("{}", truncate(output.stderr.trim())),
+    }
+}
+
+async fn semgrep(os: &Os, cwd: &Path, paths: &[PathBuf]) -> Result<Outcome> {
+    let mut args = vec!["scan".into(), "--json".into(), "--quiet".into()];
+    // semgrep reads its rules from SEMGREP_RULES when it is set.
+    if os.env.get("SEMGREP_RULES").is_err() {
+        args.extend(["--config".into(), "auto".into()]);
+    }
+    args.extend(paths.iter().map(|path| path.clone().into_os_string()));
+
+    Ok(match run("semgrep", args, cwd).await? {
+        Some(output) => Outcome::Findings(parse_report(&output, |report| parse_semgrep(cwd, &report))?),
+        None => Outcome::Skipped("semgrep is not installed".to_string()),
+    })
+}
+
+async fn bandit(cwd: &Path, paths: &[PathBuf]) -> Result<Outcome> {
+    let mut args = vec!["-r".into(), "-f".into(), "json".into(), "-q".into()];
+    args.extend(paths.iter().map(|path| path.clone().into_os_string()));
+
+    Ok(match run("bandit", args, cwd).await? {
+        Some(output) => Outcome::Findings(parse_report(&output, |report| parse_bandit(cwd, &report))?),
+        None => Outcome::Skipped("bandit is not installed".to_string()),
+    })
+}
+
+async fn cargo_audit(cwd: &Path, paths: &[PathBuf]) -> Result<Outcome> {
+    let lockfiles = paths
+        .iter()
+        .map(|path| match path.is_dir() {
+            true => path.join("Cargo.lock"),
+            false => path.clone(),
+        })
+        .filter(|path| path.ends_with("Cargo.lock") && path.is_file())
+        .collect::<Vec<_>>();
+    if lockfiles.is_empty() {
+        return Ok(Outcome::Skipped("no Cargo.lock in the scanned paths".to_string()));
+    }
+
+    let mut findings = Vec::new();
+    for lockfile in lockfiles {
+        let args = vec![
+            "audit".into(),
+            "--json".into(),
+            "--file".into(),
+            lockfile.clone().into_os_string(),
+        ];
+        match run("cargo", args, cwd).await? {
+            Some(output) if output.stderr.contains("no such command") => {
+                return Ok(Outcome::Skipped("cargo-audit is not installed".to_string()));
+            },
+            Some(output) => findings.extend(parse_report(&output, |report| {
+                parse_cargo_audit(&relative(cwd, &lockfile), &report)
+            })?),
+            None => return Ok(Outcome::Skipped("cargo is not installed".to_string())),
+        }
+    }
+    Ok(Outcome::Findings(findings))
+}
+
+async fn code_scan(os: &Os, cwd: &Path, paths: &[PathBuf]) -> Result<Outcome> {
+    let (cwd_owned, paths_owned) = (cwd.to_path_buf(), paths.to_vec());
+    let (zip, language) = tokio::task::spawn_blocking(move || zip_sources(&cwd_owned, &paths_owned)).await??;
+    let Some(language) = language else {
+        return Ok(Outcome::Skipped(
+            "no source files in a language the code scan supports".to_string(),
+        ));
+    };
+
+    let pages = os.client.scan_code(zip, language, SCANNER_TIMEOUT).await?;
+    let mut findings = Vec::new();
+    for page in pages {
+        findings.extend(parse_code_scan(&serde_json::from_str(&page)?));
+    }
+    Ok(Outcome::Findings(findings))
+}
+
+/// The code scan language of a file extension.
+fn code_scan_language(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "py" => "python",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "ts" | "tsx" => "typescript",
+        "java" => "java",
+        "cs" => "csharp",
+        "go" => "go",
+        "rb" => "ruby",
+        "php" => "php",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" => "cpp",
+        "tf" => "tf",
+        _ => return None,
+    })
+}
+
+/// A zip of the source files under `paths` in a language the code scan supports, named relative
+/// to `cwd`, and the language most of them are in. Files ignored by git are left out.
+fn zip_sources(cwd: &Path, paths: &[PathBuf]) -> Result<(Vec<u8>, Option<&'static str>)> {
+    let mut zip = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let mut languages = HashMap::<&'static str, usize>::new();
+    let mut size = 0;
+    for path in paths {
+        let files = ignore::WalkBuilder::new(path)
+            .filter_entry(|entry| {
+                !(entry.file_type().is_some_and(|file_type| file_type.is_dir())
+                    && SKIPPED_DIRS.iter().any(|dir| entry.file_name() == *dir))
+            })
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|file_type| file_type.is_file()));
+        for file in files {
+            let Some(language) = file
+                .path()
+                .extension()
+                .and_then(|extension| code_scan_language(&extension.to_string_lossy()))
+            else {
+                continue;
+            };
+            let len = file.metadata().map_or(u64::MAX, |metadata| metadata.len());
+            if len > MAX_UPLOAD_FILE_SIZE {
+                continue;
+            }
+            size += len;
+            if size > MAX_UPLOAD_SIZE {
+                bail!(
+                    "The files to scan are over {} MB, scan fewer paths",
+                    MAX_UPLOAD_SIZE / 1024 / 1024
+                );
+            }
+
+            *languages.entry(language).or_default() += 1;
+            zip.start_file(relative(cwd, file.path()), SimpleFileOptions::default())?;
+            zip.write_all(&std::fs::read(file.path())?)?;
+        }
+    }
+
+    let language = languages
+        .into_iter()
+        .max_by_key(|(language, count)| (*count, *language))
+        .map(|(language, _)| language);
+    Ok((zip.finish()?.into_inner(), language))
+}
+
+/// The first CWE of a scanner's metadata, which is a string or a list of them.
+fn first_cwe(value: &Value) -> Option<String> {
+    let cwe = match value {
+        Value::Array(values) => values.first()?.as_str()?,
+        Value::String(cwe) => cwe,
+        _ => return None,
+    };
+    // semgrep's CWEs are like `CWE-89: Improper Neutralization...`.
+    Some(cwe.split(':').next().unwrap_or(cwe).trim().to_string())
+}
+
+fn parse_semgrep(cwd: &Path, report: &Value) -> Vec<Finding> {
+    let results = report["results"].as_array().map(Vec::as_slice).unwrap_or_default();
+    results
+        .iter()
+        .map(|result| Finding {
+            scanner: Scanner::Semgrep,
+            severity: Severity::parse(result["extra"]["severity"].as_str().unwrap_or_default()),
+            rule: result["check_id"].as_str().unwrap_or_default().to_string(),
+            path: result["path"].as_str().map(|path| relative(cwd, Path::new(path))),
+            line: result["start"]["line"].as_u64().map(|line| line as usize),
+            message: truncate(result["extra"]["message"].as_str().unwrap_or_default()),
+            cwe: first_cwe(&result["extra"]["metadata"]["cwe"]),
+            fix: result["extra"]["fix"].as_str().map(truncate),
+        })
+        .collect()
+}
+
+fn parse_bandit(cwd: &Path, report: &Value) -> Vec<Finding> {
+    let results = report["results"].as_array().map(Vec::as_slice).unwrap_or_default();
+    results
+        .iter()
+        .map(|result| Finding {
+            scanner: Scanner::Bandit,
+            severity: Severity::parse(result["issue_severity"].as_str().unwrap_or_default()),
+            rule: match (result["test_id"].as_str(), result["test_name"].as_str()) {
+                (Some(id), Some(name)) => format!("{id} {name}"),
+                (id, name) => id.or(name).unwrap_or_default().to_string(),
+            },
+            path: result["filename"].as_str().map(|path| relative(cwd, Path::new(path))),
+            line: result["line_number"].as_u64().map(|line| line as usize),
+            message: truncate(result["issue_text"].as_str().unwrap_or_default()),
+            cwe: result["issue_cwe"]["id"].as_u64().map(|id| format!("CWE-{id}")),
+            fix: None,
+        })
+        .collect()
+}
+
+fn parse_cargo_audit(lockfile: &str, report: &Value) -> Vec<Finding> {
+    let list = report["vulnerabilities"]["list"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    list.iter()
+        .map(|vulnerability| {
+            let advisory = &vulnerability["advisory"];
+            let patched = vulnerability["versions"]["patched"]
+                .as_array()
+                .map(|versions| versions.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+                .unwrap_or_default();
+            Finding {
+                scanner: Scanner::CargoAudit,
+                // RustSec advisories only have a severity through an optional CVSS vector, and
+                // are all vulnerabilities of a dependency that is in use.
+                severity: Severity::High,
+                rule: advisory["id"].as_str().unwrap_or_default().to_string(),
+                path: Some(lockfile.to_string()),
+                line: None,
+                message: truncate(&format!(
+                    "{} {}: {}",
+                    vulnerability["package"]["name"].as_str().unwrap_or_default(),
+                    vulnerability["package"]["version"].as_str().unwrap_or_default(),
+                    advisory["title"].as_str().unwrap_or_default()
+                )),
+                cwe: None,
+                fix: match patched.is_empty() {
+                    true => None,
+                    false => Some(format!("Upgrade to {}", patched.join(" or "))),
+                },
+            }
+        })
+        .collect()
+}
+
+fn parse_code_scan(findings: &Value) -> Vec<Finding> {
+    let findings = findings.as_array().map(Vec::as_slice).unwrap_or_default();
+    findings
+        .iter()
+        .map(|finding| {
+            let title = finding["title"].as_str().unwrap_or_default();
+            let message = match finding["description"]["text"].as_str() {
+                Some(description) if !description.is_empty() => format!("{title}: {description}"),
+                _ => title.to_string(),
+            };
+            Finding {
+                scanner: Scanner::Codescan,
+                severity: Severity::parse(finding["severity"].as_str().unwrap_or_default()),
+                rule: finding["ruleId"]
+                    .as_str()
+                    .or(finding["detectorId"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                path: finding["filePath"].as_str().map(str::to_string),
+                line: finding["startLine"].as_u64().map(|line| line as usize),
+                message: truncate(&message),
+                cwe: first_cwe(&finding["relatedVulnerabilities"]),
+                fix: finding["remediation"]["recommendation"]["text"].as_str().map(truncate),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let scan = serde_json::from_value::<SecurityScan>(json!({
+            "paths": ["src"],
+            "scanners": ["semgrep", "cargo-audit", "codescan"]
+        }))
+        .unwrap();
+        assert_eq!(scan.scanners, vec![
+            Scanner::Semgrep,
+            Scanner::CargoAudit,
+            Scanner::Codescan
+        ]);
+        assert!(serde_json::from_value::<SecurityScan>(json!({ "scanners": ["snyk"] })).is_err());
+    }
+
+    #[test]
+    fn test_parse_semgrep() {
+        let report = json!({
+            "results": [{
+                "check_id": "python.lang.security.audit.formatted-sql-query",
+                "path": "/repo/app/db.py",
+                "start": { "line": 12, "col": 5 },
+                "extra": {
+                    "message": "Detected possible formatted SQL query.",
+                    "severity": "ERROR",
+                    "metadata": { "cwe": ["CWE-89: Improper Neutralization of Special Elements used in an SQL Command"] }
+                }
+            }],
+            "errors": []
+        });
+        assert_eq!(parse_semgrep(Path::new("/repo"), &report), vec![Finding {
+            scanner: Scanner::Semgrep,
+            severity: Severity::High,
+            rule: "python.lang.security.audit.formatted-sql-query".to_string(),
+            path: Some("app/db.py".to_string()),
+            line: Some(12),
+            message: "Detected possible formatted SQL query.".to_string(),
+            cwe: Some("CWE-89".to_string()),
+            fix: None,
+        }]);
+    }
+
+    #[test]
+    fn test_parse_bandit() {
+        let report = json!({
+            "results": [{
+                "filename": "app/run.py",
+                "line_number": 3,
+                "issue_severity": "LOW",
+                "issue_text": "Consider possible security implications associated with the subprocess module.",
+                "test_id": "B404",
+                "test_name": "blacklist",
+                "issue_cwe": { "id": 78 }
+            }]
+        });
+        let findings = parse_bandit(Path::new("/repo"), &report);
+        assert_eq!(findings[0].severity, Severity::Low);
+        assert_eq!(findings[0].rule, "B404 blacklist");
+        assert_eq!(findings[0].cwe.as_deref(), Some("CWE-78"));
+    }
+
+    #[test]
+    fn test_parse_cargo_audit() {
+        let report = json!({
+            "vulnerabilities": {
+                "found": true,
+                "list": [{
+                    "advisory": { "id": "RUSTSEC-2020-0071", "title": "Potential segfault in the time crate" },
+                    "versions": { "patched": [">=0.2.23"] },
+                    "package": { "name": "time", "version": "0.1.45" }
+                }]
+            }
+        });
+        assert_eq!(parse_cargo_audit("Cargo.lock", &report), vec![Finding {
+            scanner: Scanner::CargoAudit,
+            severity: Severity::High,
+            rule: "RUSTSEC-2020-0071".to_string(),
+            path: Some("Cargo.lock".to_string()),
+            line: None,
+            message: "time 0.1.45: Potential segfault in the time crate".to_string(),
+            cwe: None,
+            fix: Some("Upgrade to >=0.2.23".to_string()),
+        }]);
+        assert!(parse_cargo_audit("Cargo.lock", &json!({ "vulnerabilities": { "list": [] } })).is_empty());
+    }
+
+    #[test]
+    fn test_parse_code_scan() {
+        let findings = json!([{
+            "filePath": "src/handler.js",
+            "startLine": 40,
+            "title": "Cross-site scripting",
+            "description": { "text": "User input is rendered without escaping." },
+            "detectorId": "javascript/xss@v1.0",
+            "ruleId": "javascript-xss",
+            "relatedVulnerabilities": ["CWE-79"],
+            "severity": "Critical",
+            "remediation": { "recommendation": { "text": "Escape the input before rendering it." } }
+        }]);
+        let findings = parse_code_scan(&findings);
+        assert_eq!(findings[0].severity, Severity::Critical);
+        assert_eq!(findings[0].rule, "javascript-xss");
+        assert_eq!(
+            findings[0].message,
+            "Cross-site scripting: User input is rendered without escaping."
+        );
+        assert_eq!(
+            findings[0].fix.as_deref(),
+            Some("Escape the input before rendering it.")
+        );
+    }
+
+    #[test]
+    fn test_zip_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.py"), "import os").unwrap();
+        std::fs::write(dir.path().join("util.py"), "import sys").unwrap();
+        std::fs::write(dir.path().join("index.js"), "eval(x)").unwrap();
+        std::fs::create_dir_all(dir.path().join("node_modules/left-pad")).unwrap();
+        std::fs::write(dir.path().join("node_modules/left-pad/index.js"), "").unwrap();
+        std::fs::write(dir.path().join("README.md"), "# app").unwrap();
+        std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "generated/\n").unwrap();
+        std::fs::create_dir_all(dir.path().join("generated")).unwrap();
+        std::fs::write(dir.path().join("generated/schema.py"), "").unwrap();
+
+        let (zip, language) = zip_sources(dir.path(), &[dir.path().to_path_buf()]).unwrap();
+        assert_eq!(language, Some("python"));
+        let archive = zip::ZipArchive::new(std::io::Cursor::new(zip)).unwrap();
+        let mut names = archive.file_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["app.py", "index.js", "util.py"]);
+    }
+}
@@ -0,0 +1,116 @@
+use std::io::Write;
+use std::path::Path;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::Result;
+use serde::Deserialize;
+
+use super::{
+    InvokeOutput,
+    OutputKind,
+};
+use crate::cli::experiment::experiment_manager::{
+    ExperimentManager,
+    ExperimentName,
+};
+use crate::os::Os;
+use crate::util::knowledge_store::KnowledgeStore;
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 50;
+
+/// Searches the knowledge base index of the workspace, so that the model can find the code and
+/// docs relevant to a task without reading whole files into context.
+///
+/// The workspace is indexed with `/knowledge index`. Each search also starts re-indexing the
+/// files that changed since, which later searches see once it completes.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SemanticSearch {
+    pub query: String,
+    pub limit: Option<usize>,
+}
+
+impl SemanticSearch {
+    /// Checks if the knowledge feature is enabled in settings
+    pub fn is_enabled(os: &Os) -> bool {
+        ExperimentManager::is_enabled(os, ExperimentName::Knowledge)
+    }
+
+    pub async fn validate(&self, _os: &Os) -> Result<()> {
+        if self.query.trim().is_empty() {
+            eyre::bail!("The search query must not be empty");
+        }
+        Ok(())
+    }
+
+    pub fn queue_description(&self, updates: &mut impl Write) -> Result<()> {
+        queue!(
+            updates,
+            style::Print("Searching the workspace index for: "),
+            style::SetForegroundColor(Color::Green),
+            style::Print(&self.query),
+            style::ResetColor,
+        )?;
+        Ok(())
+    }
+
+    pub async fn invoke(
+        &self,
+        os: &Os,
+        _updates: &mut impl Write,
+        agent: Option<&crate::cli::Agent>,
+    ) -> Result<InvokeOutput> {
+        let workspace = os.env.current_dir()?;
+        let async_knowledge_store = KnowledgeStore::get_async_instance(os, agent)
+            .await
+            .map_err(|e| eyre::eyre!("Failed to access knowledge base: {}", e))?;
+        let mut store = async_knowledge_store.lock().await;
+
+        let Some(context) = store.workspace_context(&workspace).await else {
+            eyre::bail!(
+                "The workspace {} is not indexed. The user can index it with /knowledge index",
+                workspace.display()
+            );
+        };
+        store.refresh(&context).await;
+
+        let results = store
+            .search(&self.query, Some(&context.id))
+            .await
+            .map_err(|e| eyre::eyre!("{}", e))?;
+        let source_path = context.source_path.as_deref().map(Path::new);
+        let results = results
+            .iter()
+            .take(self.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT))
+            .map(|result| {
+                let payload = &result.point.payload;
+                let path = payload.get("path").and_then(|path| path.as_str()).map(|path| {
+                    source_path
+                        .and_then(|source_path| Path::new(path).strip_prefix(source_path).ok())
+                        .map_or(path.to_string(), |relative| relative.to_string_lossy().to_string())
+                });
+                serde_json::json!({
+                    "path": path,
+                    "source": payload.get("source"),
+                    "chunk": payload.get("chunk_index"),
+                    "totalChunks": payload.get("total_chunks"),
+                    "distance": result.distance,
+                    "text": result.text(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        Ok(InvokeOutput {
+            output: OutputKind::Json(serde_json::json!({
+                "index": context.name,
+                "root": context.source_path,
+                "indexedAt": context.updated_at.to_rfc3339(),
+                "results": results,
+            })),
+        })
+    }
+}
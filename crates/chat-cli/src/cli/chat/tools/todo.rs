@@ -34,6 +34,9 @@ use crate::os::Os;
 pub struct Task {
     pub task_description: String,
     pub completed: bool,
+    /// Whether the task is the one being worked on, as set with the `update_plan` tool.
+    #[serde(default)]
+    pub in_progress: bool,
 }
 
 /// Contains all state to be serialized and deserialized into a todo list
@@ -81,7 +84,7 @@ impl TodoListState {
     pub fn display_list(&self, output: &mut impl Write) -> Result<()> {
         queue!(output, style::Print("TODO:\n".yellow()))?;
         for (index, task) in self.tasks.iter().enumerate() {
-            queue_next_without_newline(output, task.task_description.clone(), task.completed)?;
+            queue_next_without_newline(output, task)?;
             if index < self.tasks.len() - 1 {
                 queue!(output, style::Print("\n"))?;
             }
@@ -90,24 +93,35 @@ impl TodoListState {
     }
 }
 
-/// Displays a single empty or marked off to-do list task depending on
+/// Displays a single empty, in progress, or marked off to-do list task depending on
 /// the completion status
-fn queue_next_without_newline(output: &mut impl Write, task: String, completed: bool) -> Result<()> {
-    if completed {
+fn queue_next_without_newline(output: &mut impl Write, task: &Task) -> Result<()> {
+    let description = task.task_description.as_str();
+    if task.completed {
         queue!(
             output,
             style::SetForegroundColor(style::Color::Green),
             style::Print("[x] "),
             style::SetAttribute(style::Attribute::Italic),
             style::SetForegroundColor(style::Color::DarkGrey),
-            style::Print(task),
+            style::Print(description),
             style::SetAttribute(style::Attribute::NoItalic),
         )?;
+    } else if task.in_progress {
+        queue!(
+            output,
+            style::SetForegroundColor(style::Color::Yellow),
+            style::Print("[~] "),
+            style::SetAttribute(style::Attribute::Bold),
+            style::Print(description),
+            style::SetAttribute(style::Attribute::NormalIntensity),
+            style::SetForegroundColor(style::Color::Reset),
+        )?;
     } else {
         queue!(
             output,
             style::SetForegroundColor(style::Color::Reset),
-            style::Print(format!("[ ] {task}")),
+            style::Print(format!("[ ] {description}")),
         )?;
     }
     Ok(())
@@ -254,6 +268,7 @@ impl TodoList {
                     todo_tasks.push(Task {
                         task_description: task_description.clone(),
                         completed: false,
+                        in_progress: false,
                     });
                 }
 
@@ -279,6 +294,7 @@ impl TodoList {
 
                 for i in completed_indices.iter() {
                     state.tasks[*i].completed = true;
+                    state.tasks[*i].in_progress = false;
                 }
 
                 state.context.push(context_update.clone());
@@ -323,6 +339,7 @@ impl TodoList {
                     let new_task = Task {
                         task_description: task_description.clone(),
                         completed: false,
+                        in_progress: false,
                     };
                     state.tasks.insert(*i, new_task);
                 }
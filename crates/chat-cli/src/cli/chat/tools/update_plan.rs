@@ -0,0 +1,257 @@
+//! The `update_plan` tool, with which the model keeps the plan of a task as a checklist while it
+//! works on it.
+//!
+//! The plan is a todo list like those of [super::todo], saved under the id of the conversation, so
+//! `/todos` shows and manages it, and a resumed conversation continues the same plan. Every update
+//! holds the whole plan, which lets the model add, check off, and reorder steps in one call.
+
+use std::io::Write;
+
+use crossterm::queue;
+use crossterm::style::{
+    self,
+    Color,
+};
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::Deserialize;
+
+use super::todo::{
+    Task,
+    TodoList,
+    TodoListState,
+    id_to_path,
+};
+use super::{
+    InvokeOutput,
+    OutputKind,
+};
+use crate::os::Os;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdatePlan {
+    /// What the plan is for. The previous description is kept if this is not given.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Why the plan changed, shown to the user.
+    #[serde(default)]
+    pub explanation: Option<String>,
+    /// All the steps of the plan, in order.
+    pub plan: Vec<PlanStep>,
+    /// The id the plan is saved under, that of the conversation. Set with [Self::set_plan_id].
+    #[serde(skip)]
+    plan_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlanStep {
+    pub step: String,
+    pub status: StepStatus,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Pending,
+    InProgress,
+    Completed,
+}
+
+impl UpdatePlan {
+    pub fn set_plan_id(&mut self, plan_id: String) {
+        self.plan_id = Some(plan_id);
+    }
+
+    pub async fn validate(&self, _os: &Os) -> Result<()> {
+        if self.plan.is_empty() {
+            bail!("The plan must have at least one step");
+        } else if self.plan.iter().any(|step| step.step.trim().is_empty()) {
+            bail!("Steps cannot be empty");
+        } else if self.description.as_ref().is_some_and(|d| d.trim().is_empty()) {
+            bail!("The description cannot be empty");
+        } else if self
+            .plan
+            .iter()
+            .filter(|step| step.status == StepStatus::InProgress)
+            .count()
+            > 1
+        {
+            bail!("At most one step can be in progress");
+        }
+        Ok(())
+    }
+
+    pub async fn invoke(&self, os: &Os, output: &mut impl Write) -> Result<InvokeOutput> {
+        if !TodoList::is_enabled(os) {
+            return Ok(InvokeOutput {
+                output: OutputKind::Text("Todo lists are disabled.".to_string()),
+            });
+        }
+        let id = self
+            .plan_id
+            .clone()
+            .ok_or_else(|| eyre!("The plan is not tied to a conversation"))?;
+
+        let previous = match os.fs.exists(id_to_path(os, &id)?) {
+            true => Some(TodoListState::load(os, &id).await?),
+            false => None,
+        };
+        let state = self.apply(id.clone(), previous);
+        state.save(os, &id).await?;
+
+        if let Some(explanation) = &self.explanation {
+            queue!(
+                output,
+                style::SetForegroundColor(Color::DarkGrey),
+                style::Print(format!("{}\n", explanation.trim())),
+                style::SetForegroundColor(Color::Reset),
+            )?;
+        }
+        state.display_list(output)?;
+
+        let completed = state.tasks.iter().filter(|task| task.completed).count();
+        Ok(InvokeOutput {
+            output: OutputKind::Text(format!(
+                "Plan updated: {completed} of {} steps completed. The user sees the plan, do not repeat it.",
+                state.tasks.len()
+            )),
+        })
+    }
+
+    /// The todo list the plan is saved as, keeping what the todo list tool recorded in `previous`.
+    fn apply(&self, id: String, previous: Option<TodoListState>) -> TodoListState {
+        let previous = previous.unwrap_or_default();
+        TodoListState {
+            tasks: self
+                .plan
+                .iter()
+                .map(|step| Task {
+                    task_description: step.step.clone(),
+                    completed: step.status == StepStatus::Completed,
+                    in_progress: step.status == StepStatus::InProgress,
+                })
+                .collect(),
+            description: match &self.description {
+                Some(description) => description.clone(),
+                None if !previous.description.is_empty() => previous.description,
+                None => "Plan".to_string(),
+            },
+            context: previous.context,
+            modified_files: previous.modified_files,
+            id,
+        }
+    }
+
+    pub fn queue_description(&self, _output: &mut impl Write) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// One line on the progress of a plan, shown above the prompt while it has steps left.
+pub fn status_line(state: &TodoListState) -> Option<String> {
+    let completed = state.tasks.iter().filter(|task| task.completed).count();
+    if state.tasks.is_empty() || completed == state.tasks.len() {
+        return None;
+    }
+    let current = state
+        .tasks
+        .iter()
+        .find(|task| task.in_progress)
+        .or_else(|| state.tasks.iter().find(|task| !task.completed))
+        .map(|task| task.task_description.as_str())
+        .unwrap_or_default();
+    Some(format!("Plan {completed}/{}: {current}", state.tasks.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(plan: serde_json::Value) -> UpdatePlan {
+        serde_json::from_value(plan).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let os = Os::new().await.unwrap();
+        let valid = update(serde_json::json!({
+            "plan": [
+                { "step": "Read the config", "status": "completed" },
+                { "step": "Add the flag", "status": "in_progress" },
+                { "step": "Update the docs", "status": "pending" },
+            ]
+        }));
+        assert!(valid.validate(&os).await.is_ok());
+
+        let empty = update(serde_json::json!({ "plan": [] }));
+        assert!(empty.validate(&os).await.is_err());
+
+        let two_in_progress = update(serde_json::json!({
+            "plan": [
+                { "step": "Add the flag", "status": "in_progress" },
+                { "step": "Update the docs", "status": "in_progress" },
+            ]
+        }));
+        assert!(two_in_progress.validate(&os).await.is_err());
+    }
+
+    #[test]
+    fn test_apply_keeps_previous_state() {
+        let previous = TodoListState {
+            tasks: vec![Task {
+                task_description: "Old step".to_string(),
+                ..Default::default()
+            }],
+            description: "Add a --verbose flag".to_string(),
+            context: vec!["The flag is parsed in args.rs".to_string()],
+            modified_files: vec!["src/args.rs".to_string()],
+            id: "conversation".to_string(),
+        };
+        let state = update(serde_json::json!({
+            "plan": [
+                { "step": "Update the docs", "status": "pending" },
+                { "step": "Add the flag", "status": "completed" },
+            ]
+        }))
+        .apply("conversation".to_string(), Some(previous));
+
+        assert_eq!(state.description, "Add a --verbose flag");
+        assert_eq!(state.context, vec!["The flag is parsed in args.rs"]);
+        assert_eq!(state.tasks.len(), 2);
+        assert_eq!(state.tasks[0].task_description, "Update the docs");
+        assert!(state.tasks[1].completed);
+        assert_eq!(status_line(&state).unwrap(), "Plan 1/2: Update the docs");
+    }
+
+    #[test]
+    fn test_status_line() {
+        let mut state = TodoListState {
+            tasks: vec![
+                Task {
+                    task_description: "Read the config".to_string(),
+                    completed: true,
+                    ..Default::default()
+                },
+                Task {
+                    task_description: "Add the flag".to_string(),
+                    ..Default::default()
+                },
+                Task {
+                    task_description: "Update the docs".to_string(),
+                    in_progress: true,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(status_line(&state).unwrap(), "Plan 1/3: Update the docs");
+
+        for task in &mut state.tasks {
+            task.completed = true;
+        }
+        assert_eq!(status_line(&state), None);
+    }
+}
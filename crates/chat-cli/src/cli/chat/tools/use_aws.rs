@@ -28,6 +28,7 @@ use crate::cli::agent::{
     Agent,
     PermissionEvalResult,
 };
+use crate::cli::chat::tools::retrieve_output::truncate_with_handle;
 use crate::os::Os;
 use crate::util::tool_permission_checker::is_tool_in_allowlist;
 
@@ -81,25 +82,8 @@ impl UseAws {
         let stdout = output.stdout.to_str_lossy();
         let stderr = output.stderr.to_str_lossy();
 
-        let stdout = format!(
-            "{}{}",
-            &stdout[0..stdout.len().min(MAX_TOOL_RESPONSE_SIZE / 3)],
-            if stdout.len() > MAX_TOOL_RESPONSE_SIZE / 3 {
-                " ... truncated"
-            } else {
-                ""
-            }
-        );
-
-        let stderr = format!(
-            "{}{}",
-            &stderr[0..stderr.len().min(MAX_TOOL_RESPONSE_SIZE / 3)],
-            if stderr.len() > MAX_TOOL_RESPONSE_SIZE / 3 {
-                " ... truncated"
-            } else {
-                ""
-            }
-        );
+        let stdout = truncate_with_handle(os, &stdout, MAX_TOOL_RESPONSE_SIZE / 3).await;
+        let stderr = truncate_with_handle(os, &stderr, MAX_TOOL_RESPONSE_SIZE / 3).await;
 
         if status.eq("0") {
             Ok(InvokeOutput {
@@ -160,7 +144,7 @@ impl UseAws {
 
     /// Returns the CLI arguments properly formatted as kebab case if parameters is
     /// [Option::Some], otherwise None
-    fn cli_parameters(&self) -> Option<Vec<(String, String)>> {
+    pub fn cli_parameters(&self) -> Option<Vec<(String, String)>> {
         if let Some(parameters) = &self.parameters {
             let mut params = vec![];
             for (param_name, val) in parameters {
@@ -0,0 +1,528 @@
+//! Export and import of the user's configuration as a single archive, with `q settings export` and
+//! `q settings import`, to move it to another machine or onboard a teammate.
+//!
+//! The archive is a zip of the settings, the global agents, which carry their MCP servers and tool
+//! permissions, the global prompts and the legacy global MCP config. Workspace configuration is
+//! left out, as it is already shared through the workspace itself. With a passphrase, every file of
+//! the archive but its manifest is encrypted.
+
+use std::io::{
+    Cursor,
+    IsTerminal,
+    Read,
+    Write,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{
+    DateTime,
+    Utc,
+};
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    Map,
+    Value,
+};
+use tokio::io::AsyncWriteExt;
+use tracing::warn;
+use zip::write::SimpleFileOptions;
+use zip::{
+    ZipArchive,
+    ZipWriter,
+};
+
+use crate::database::encryption::{
+    Cipher,
+    new_salt,
+};
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::util::directories;
+
+/// Read for the passphrase of an archive, before asking for it.
+pub const PASSPHRASE_ENV: &str = "Q_CONFIG_PASSPHRASE";
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+const SETTINGS: &str = "settings.json";
+const MCP_CONFIG: &str = "mcp.json";
+const AGENTS_DIR: &str = "agents";
+const PROMPTS_DIR: &str = "prompts";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    version: u32,
+    created_at: DateTime<Utc>,
+    /// The base64 salt of the passphrase the files are encrypted with, if they are.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    salt: Option<String>,
+}
+
+/// A file of the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// The path in the archive, such as `agents/backend.json`.
+    name: String,
+    content: String,
+}
+
+/// Where an entry of the archive is imported to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Settings,
+    File(PathBuf),
+}
+
+/// A setting or file an import changes.
+#[derive(Debug, Clone)]
+enum Change {
+    Setting(Setting, Value),
+    File(PathBuf, String),
+}
+
+impl Change {
+    fn name(&self) -> String {
+        match self {
+            Self::Setting(setting, _) => setting.as_ref().to_string(),
+            Self::File(path, _) => path.display().to_string(),
+        }
+    }
+
+    /// The name, along with the new value of a setting.
+    fn describe(&self) -> String {
+        match self {
+            Self::Setting(setting, value) => format!("{} = {value}", setting.as_ref()),
+            Self::File(path, _) => path.display().to_string(),
+        }
+    }
+
+    /// Whether the change affects what q runs, trusts, or sends requests to. Every file is: agents
+    /// and the legacy MCP config carry hooks, MCP servers and tool permissions, and prompts are
+    /// instructions to the model.
+    fn is_sensitive(&self) -> bool {
+        match self {
+            // Settings that run commands, or change where requests go and which certificates
+            // are trusted.
+            Self::Setting(setting, _) => matches!(
+                setting,
+                Setting::ApiCodeWhispererService
+                    | Setting::ApiQService
+                    | Setting::ApiGatewayHeaders
+                    | Setting::ApiGatewayHeadersCommand
+                    | Setting::ApiGatewaySigningKeyEnv
+                    | Setting::ApiStreamingEndpoint
+                    | Setting::ApiAuthEndpoint
+                    | Setting::ApiTelemetryEndpoint
+                    | Setting::ApiTlsServerName
+                    | Setting::ApiTlsCaBundle
+                    | Setting::ChatEditorCommand
+                    | Setting::ChatCdAllowedRoots
+            ),
+            Self::File(..) => true,
+        }
+    }
+}
+
+/// What happened to the files and settings of an archive on import.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub imported: Vec<String>,
+    /// Those that already exist with another value, which are only replaced with `--force`.
+    pub kept: Vec<String>,
+    /// Those that can't be imported, with the reason.
+    pub skipped: Vec<(String, String)>,
+}
+
+/// Writes the configuration to `path`, encrypted with a passphrase if `encrypt`. Returns the
+/// number of files in the archive.
+pub async fn export(os: &Os, path: &Path, encrypt: bool) -> Result<usize> {
+    let entries = collect(os).await?;
+    let encryption = match encrypt {
+        true => {
+            let salt = new_salt()?;
+            Some((Cipher::from_passphrase(&passphrase(true)?, &salt), salt))
+        },
+        false => None,
+    };
+
+    let archive = write_archive(
+        &entries,
+        encryption.as_ref().map(|(cipher, salt)| (cipher, salt.as_slice())),
+    )?;
+
+    // The settings may hold secrets, such as API gateway headers, so the archive is only readable
+    // by the user from before anything is written to it.
+    let mut options = tokio::fs::File::options();
+    options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(os.fs.chroot_path(path)).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // The mode only applies to new files.
+        file.set_permissions(std::fs::Permissions::from_mode(0o600)).await?;
+    }
+    file.write_all(&archive).await?;
+    file.flush().await?;
+    Ok(entries.len())
+}
+
+/// Imports the archive at `path`. Settings and files that already exist are only replaced if
+/// `overwrite`.
+///
+/// Changes to what q runs, trusts, or sends requests to are only made if `overwrite`, or if
+/// `confirm` accepts them when given their descriptions. Otherwise they are skipped.
+pub async fn import(
+    os: &mut Os,
+    path: &Path,
+    overwrite: bool,
+    confirm: impl FnOnce(&[String]) -> Result<bool>,
+) -> Result<ImportSummary> {
+    let archive = os.fs.read(path).await?;
+    let entries = read_archive(archive, || passphrase(false))?;
+    apply(os, entries, overwrite, confirm).await
+}
+
+/// The files of the configuration, settings first.
+async fn collect(os: &Os) -> Result<Vec<Entry>> {
+    let mut entries = vec![Entry {
+        name: SETTINGS.to_string(),
+        content: serde_json::to_string_pretty(os.database.settings.map())?,
+    }];
+
+    let mcp_config = directories::chat_legacy_global_mcp_config(os)?;
+    if os.fs.exists(&mcp_config) {
+        entries.push(Entry {
+            name: MCP_CONFIG.to_string(),
+            content: os.fs.read_to_string(&mcp_config).await?,
+        });
+    }
+
+    for (prefix, dir) in [
+        (AGENTS_DIR, directories::chat_global_agent_path(os)?),
+        (PROMPTS_DIR, directories::chat_global_prompts_dir(os)?),
+    ] {
+        let Ok(mut files) = os.fs.read_dir(&dir).await else {
+            continue;
+        };
+        let mut dir_entries = Vec::new();
+        while let Some(file) = files.next_entry().await? {
+            if !file.file_type().await?.is_file() {
+                continue;
+            }
+            let path = file.path();
+            match os.fs.read_to_string(&path).await {
+                Ok(content) => dir_entries.push(Entry {
+                    name: format!("{prefix}/{}", file.file_name().to_string_lossy()),
+                    content,
+                }),
+                Err(err) => warn!(?err, ?path, "skipping a file that can't be exported"),
+            }
+        }
+        dir_entries.sort_by(|a, b| a.name.cmp(&b.name));
+        entries.extend(dir_entries);
+    }
+
+    Ok(entries)
+}
+
+fn write_archive(entries: &[Entry], encryption: Option<(&Cipher, &[u8])>) -> Result<Vec<u8>> {
+    let manifest = Manifest {
+        version: FORMAT_VERSION,
+        created_at: Utc::now(),
+        salt: encryption.map(|(_, salt)| BASE64.encode(salt)),
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file(MANIFEST, SimpleFileOptions::default())?;
+    zip.write_all(serde_json::to_string_pretty(&manifest)?.as_bytes())?;
+    for entry in entries {
+        let content = match encryption {
+            Some((cipher, _)) => cipher.encrypt(&entry.content)?,
+            None => entry.content.clone(),
+        };
+        zip.start_file(entry.name.as_str(), SimpleFileOptions::default())?;
+        zip.write_all(content.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+fn read_archive(archive: Vec<u8>, passphrase: impl FnOnce() -> Result<String>) -> Result<Vec<Entry>> {
+    fn not_an_archive(err: impl std::fmt::Display) -> eyre::Report {
+        eyre!("Not an archive written by `q settings export`: {err}")
+    }
+    let mut archive = ZipArchive::new(Cursor::new(archive)).map_err(not_an_archive)?;
+
+    let mut manifest = String::new();
+    archive
+        .by_name(MANIFEST)
+        .map_err(not_an_archive)?
+        .read_to_string(&mut manifest)?;
+    let manifest: Manifest = serde_json::from_str(&manifest).map_err(not_an_archive)?;
+    if manifest.version > FORMAT_VERSION {
+        bail!("The archive was written by a newer version of q, update q to import it");
+    }
+
+    let cipher = match manifest.salt {
+        Some(salt) => Some(Cipher::from_passphrase(&passphrase()?, &BASE64.decode(salt)?)),
+        None => None,
+    };
+
+    let mut entries = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.is_dir() || file.name() == MANIFEST {
+            continue;
+        }
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        if let Some(cipher) = &cipher {
+            content = cipher.decrypt(&content)?;
+        }
+        entries.push(Entry {
+            name: file.name().to_string(),
+            content,
+        });
+    }
+    Ok(entries)
+}
+
+/// Where `name` is imported to, `None` for names this version doesn't know, or that would escape
+/// their directory.
+fn target(os: &Os, name: &str) -> Result<Option<Target>> {
+    let in_dir = |prefix: &str, dir: PathBuf| {
+        name.strip_prefix(prefix)
+            .and_then(|name| name.strip_prefix('/'))
+            .filter(|file| !file.is_empty() && !file.contains(['/', '\\']) && *file != "." && *file != "..")
+            .map(|file| Target::File(dir.join(file)))
+    };
+
+    Ok(match name {
+        SETTINGS => Some(Target::Settings),
+        MCP_CONFIG => Some(Target::File(directories::chat_legacy_global_mcp_config(os)?)),
+        _ => in_dir(AGENTS_DIR, directories::chat_global_agent_path(os)?)
+            .or(in_dir(PROMPTS_DIR, directories::chat_global_prompts_dir(os)?)),
+    })
+}
+
+async fn apply(
+    os: &mut Os,
+    entries: Vec<Entry>,
+    overwrite: bool,
+    confirm: impl FnOnce(&[String]) -> Result<bool>,
+) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    let changes = plan(os, entries, overwrite, &mut summary).await?;
+
+    let sensitive = changes
+        .iter()
+        .filter(|change| change.is_sensitive())
+        .map(Change::describe)
+        .collect::<Vec<_>>();
+    let allow_sensitive = overwrite || sensitive.is_empty() || confirm(&sensitive)?;
+
+    for change in changes {
+        let name = change.name();
+        if change.is_sensitive() && !allow_sensitive {
+            summary.skipped.push((
+                name,
+                "changes what q runs or connects to, confirm it or import with --force".to_string(),
+            ));
+            continue;
+        }
+        match change {
+            Change::Setting(setting, value) => match os.database.settings.set(setting, value).await {
+                Ok(()) => summary.imported.push(name),
+                Err(err) => summary.skipped.push((name, err.to_string())),
+            },
+            Change::File(path, content) => {
+                if let Some(parent) = path.parent() {
+                    os.fs.create_dir_all(parent).await?;
+                }
+                os.fs.write(&path, content).await?;
+                summary.imported.push(name);
+            },
+        }
+    }
+    Ok(summary)
+}
+
+/// The changes importing `entries` makes, recording what is kept or skipped in `summary`.
+async fn plan(os: &Os, entries: Vec<Entry>, overwrite: bool, summary: &mut ImportSummary) -> Result<Vec<Change>> {
+    let mut changes = Vec::new();
+    for entry in entries {
+        match target(os, &entry.name)? {
+            Some(Target::Settings) => {
+                let settings: Map<String, Value> = serde_json::from_str(&entry.content)?;
+                for (key, value) in settings {
+                    let Ok(setting) = Setting::try_from(key.as_str()) else {
+                        summary.skipped.push((key, "not a known setting".to_string()));
+                        continue;
+                    };
+                    match os.database.settings.get(setting) {
+                        Some(existing) if *existing == value => continue,
+                        Some(_) if !overwrite => {
+                            summary.kept.push(key);
+                            continue;
+                        },
+                        _ => changes.push(Change::Setting(setting, value)),
+                    }
+                }
+            },
+            Some(Target::File(path)) => {
+                if let Ok(existing) = os.fs.read_to_string(&path).await {
+                    if existing == entry.content {
+                        continue;
+                    }
+                    if !overwrite {
+                        summary.kept.push(path.display().to_string());
+                        continue;
+                    }
+                }
+                changes.push(Change::File(path, entry.content));
+            },
+            None => summary
+                .skipped
+                .push((entry.name, "not a file q knows how to import".to_string())),
+        }
+    }
+    Ok(changes)
+}
+
+fn passphrase(confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        bail!("Set {PASSPHRASE_ENV} to the passphrase of the archive");
+    }
+    let theme = crate::util::dialoguer_theme();
+    let mut prompt = dialoguer::Password::with_theme(&theme).with_prompt("Passphrase of the archive");
+    if confirm {
+        prompt = prompt.with_confirmation("Repeat the passphrase", "The passphrases don't match");
+    }
+    Ok(prompt.interact()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries() -> Vec<Entry> {
+        vec![
+            Entry {
+                name: SETTINGS.to_string(),
+                content: r#"{"chat.editMode": "vi", "chat.notAThing": 1, "api.timeout": "soon"}"#.to_string(),
+            },
+            Entry {
+                name: "agents/backend.json".to_string(),
+                content: r#"{"name": "backend"}"#.to_string(),
+            },
+            Entry {
+                name: "prompts/review.md".to_string(),
+                content: "Review the staged changes".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let archive = write_archive(&entries(), None).unwrap();
+        assert_eq!(read_archive(archive, || unreachable!()).unwrap(), entries());
+
+        let salt = new_salt().unwrap();
+        let cipher = Cipher::from_passphrase("hunter2", &salt);
+        let archive = write_archive(&entries(), Some((&cipher, &salt))).unwrap();
+        assert!(!String::from_utf8_lossy(&archive).contains("Review the staged changes"));
+        assert_eq!(
+            read_archive(archive.clone(), || Ok("hunter2".to_string())).unwrap(),
+            entries()
+        );
+        assert!(read_archive(archive, || Ok("hunter3".to_string())).is_err());
+
+        assert!(read_archive(b"not a zip".to_vec(), || unreachable!()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_target() {
+        let os = Os::new().await.unwrap();
+        let agents = directories::chat_global_agent_path(&os).unwrap();
+
+        assert_eq!(target(&os, SETTINGS).unwrap(), Some(Target::Settings));
+        assert_eq!(
+            target(&os, "agents/backend.json").unwrap(),
+            Some(Target::File(agents.join("backend.json")))
+        );
+        assert_eq!(target(&os, "agents/../../.bashrc").unwrap(), None);
+        assert_eq!(target(&os, "agents/..").unwrap(), None);
+        assert_eq!(target(&os, "agentsx/backend.json").unwrap(), None);
+        assert_eq!(target(&os, "logs/qchat.log").unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_apply() {
+        let mut os = Os::new().await.unwrap();
+        let prompt = directories::chat_global_prompts_dir(&os).unwrap().join("review.md");
+        os.fs.create_dir_all(prompt.parent().unwrap()).await.unwrap();
+        os.fs.write(&prompt, "Review my changes").await.unwrap();
+
+        let agent = directories::chat_global_agent_path(&os)
+            .unwrap()
+            .join("backend.json")
+            .display()
+            .to_string();
+
+        // Agents are only imported once confirmed.
+        let summary = apply(&mut os, entries(), false, |_| Ok(false)).await.unwrap();
+        assert_eq!(summary.imported, vec!["chat.editMode".to_string()]);
+        assert!(summary.skipped.iter().any(|(name, _)| *name == agent));
+        assert!(!os.fs.exists(&agent));
+
+        let summary = apply(&mut os, entries(), false, |names| {
+            assert_eq!(names, [agent.clone()]);
+            Ok(true)
+        })
+        .await
+        .unwrap();
+        assert_eq!(summary.imported, vec![agent.clone()]);
+        assert_eq!(summary.kept, vec![prompt.display().to_string()]);
+        assert_eq!(
+            summary
+                .skipped
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["chat.notAThing", "api.timeout"]
+        );
+        assert_eq!(os.fs.read_to_string(&prompt).await.unwrap(), "Review my changes");
+
+        // Importing again changes nothing, unless forced, which needs no confirmation.
+        assert!(
+            apply(&mut os, entries(), false, |_| unreachable!())
+                .await
+                .unwrap()
+                .imported
+                .is_empty()
+        );
+        let summary = apply(&mut os, entries(), true, |_| unreachable!()).await.unwrap();
+        assert_eq!(summary.imported, vec![prompt.display().to_string()]);
+        assert_eq!(
+            os.fs.read_to_string(&prompt).await.unwrap(),
+            "Review the staged changes"
+        );
+    }
+}
@@ -2,9 +2,15 @@ use std::io::{
     IsTerminal,
     stdout,
 };
-use std::process::ExitCode;
+use std::process::{
+    Command,
+    ExitCode,
+};
 
-use anstream::println;
+use anstream::{
+    eprintln,
+    println,
+};
 use clap::Args;
 use color_eyre::Result;
 use crossterm::terminal::{
@@ -23,6 +29,7 @@ use spinners::{
 use super::OutputFormat;
 use crate::os::Os;
 use crate::os::diagnostics::Diagnostics;
+use crate::util::dialoguer_theme;
 
 #[derive(Clone, Debug, Args, PartialEq, Eq)]
 pub struct DiagnosticArgs {
@@ -32,6 +39,9 @@ pub struct DiagnosticArgs {
     /// Force limited diagnostic output
     #[arg(long)]
     force: bool,
+    /// Offer to run the commands repairing the problems found with the install
+    #[arg(long)]
+    fix: bool,
 }
 
 impl DiagnosticArgs {
@@ -64,6 +74,48 @@ impl DiagnosticArgs {
             || &diagnostics,
         );
 
+        if self.fix {
+            return repair(&diagnostics);
+        }
+
         Ok(ExitCode::SUCCESS)
     }
 }
+
+/// Asks before running each of the repair commands of the problems found.
+fn repair(diagnostics: &Diagnostics) -> Result<ExitCode> {
+    let issues = &diagnostics.package_health.issues;
+    if issues.is_empty() {
+        println!("No problems were found with the install");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let mut exit_code = ExitCode::SUCCESS;
+    for issue in issues {
+        println!("{}", issue.problem);
+        let Some(command) = &issue.repair else {
+            println!("  Reinstall q to repair this\n");
+            continue;
+        };
+
+        let run = dialoguer::Confirm::with_theme(&dialoguer_theme())
+            .with_prompt(format!("Run `{command}`?"))
+            .default(true)
+            .interact()?;
+        if !run {
+            continue;
+        }
+
+        let mut shell = match cfg!(windows) {
+            true => Command::new("cmd"),
+            false => Command::new("sh"),
+        };
+        shell.arg(if cfg!(windows) { "/C" } else { "-c" }).arg(command);
+        if !shell.status()?.success() {
+            eprintln!("`{command}` failed");
+            exit_code = ExitCode::FAILURE;
+        }
+        println!();
+    }
+    Ok(exit_code)
+}
@@ -44,12 +44,13 @@ static AVAILABLE_EXPERIMENTS: &[Experiment] = &[
         experiment_name: ExperimentName::Knowledge,
         description: "Enables persistent context storage and retrieval across chat sessions (/knowledge)",
         setting_key: Setting::EnabledKnowledge,
-        enabled: true,
+        enabled: cfg!(feature = "knowledge"),
         commands: &[
             "/knowledge",
             "/knowledge help",
             "/knowledge show",
             "/knowledge add",
+            "/knowledge index",
             "/knowledge remove",
             "/knowledge clear",
             "/knowledge search",
@@ -70,7 +71,14 @@ static AVAILABLE_EXPERIMENTS: &[Experiment] = &[
         description: "Enables entering into a temporary mode for sending isolated conversations (/tangent)",
         setting_key: Setting::EnabledTangentMode,
         enabled: true,
-        commands: &["/tangent", "/tangent tail"],
+        commands: &[
+            "/tangent",
+            "/tangent tail",
+            "/tangent new",
+            "/tangent switch",
+            "/tangent merge",
+            "/tangent list",
+        ],
     },
     Experiment {
         experiment_name: ExperimentName::TodoList,
@@ -83,6 +91,7 @@ static AVAILABLE_EXPERIMENTS: &[Experiment] = &[
             "/todos clear-finished",
             "/todos resume",
             "/todos view",
+            "/todos plan",
             "/todos delete",
             "/todos delete --all",
         ],
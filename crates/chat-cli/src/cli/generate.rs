@@ -0,0 +1,233 @@
+//! `q generate`, which writes text for other tools with the model, such as the commit messages of
+//! git hooks and scripts.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+
+use anstream::println;
+use clap::{
+    Args,
+    Subcommand,
+};
+use eyre::{
+    Result,
+    bail,
+};
+
+use crate::api_client::model::{
+    ChatResponseStream,
+    ConversationState,
+    UserInputMessage,
+};
+use crate::cli::chat::cli::editor::open_editor;
+use crate::cli::chat::cli::model::{
+    find_model,
+    get_available_models,
+};
+use crate::cli::chat::context::truncate_head_tail;
+use crate::cli::chat::git_context::git;
+use crate::cli::chat::tools::git::commit_message;
+use crate::database::settings::Setting;
+use crate::os::Os;
+
+/// The most tokens of the staged diff given to the model.
+const MAX_DIFF_TOKENS: usize = 20_000;
+
+/// The number of recent commit subjects given to the model as examples of the style of the
+/// repository.
+const RECENT_COMMITS: usize = 15;
+
+const COMMIT_MESSAGE_PROMPT: &str = "Write a commit message for the staged changes below, in the Conventional Commits format:
+
+<type>(<optional scope>): <subject>
+
+<optional body>
+
+- The type is one of feat, fix, docs, style, refactor, perf, test, build, ci, chore or revert.
+- The subject is in the imperative mood, lowercase, without a trailing period, and at most 72 characters with the type and scope.
+- Add a body, wrapped at 72 characters, only when the reason for the change is not obvious from the subject. Explain why, not how.
+- Mark breaking changes with a ! after the type or scope, and a BREAKING CHANGE: footer.
+- Follow the scopes of the recent commits when they fit.
+
+Reply with the commit message only, without quotes or code fences.";
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum GenerateSubcommand {
+    /// Write a conventional commit message for the staged changes
+    CommitMessage(CommitMessageArgs),
+}
+
+impl GenerateSubcommand {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        match self {
+            Self::CommitMessage(args) => args.execute(os).await,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Args)]
+#[command(
+    after_long_help = "To write the message of every commit made without -m, add a prepare-commit-msg hook:
+
+  #!/bin/sh
+  [ -z \"$2\" ] && q generate commit-message --output \"$1\""
+)]
+pub struct CommitMessageArgs {
+    /// Open the message in $EDITOR before using it
+    #[arg(long, short)]
+    pub edit: bool,
+    /// Commit the staged changes with the message
+    #[arg(long, short)]
+    pub commit: bool,
+    /// Write the message to this file instead of printing it
+    #[arg(long, short, conflicts_with = "commit")]
+    pub output: Option<PathBuf>,
+    /// The model to write the message with, the default model otherwise
+    #[arg(long)]
+    pub model: Option<String>,
+}
+
+impl CommitMessageArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let dir = os.env.current_dir()?;
+        let Some(diff) = staged_diff(&dir).await? else {
+            bail!("There are no staged changes, stage them with git add first");
+        };
+        let model_id = model_id(os, self.model.as_deref()).await?;
+        let mut message = generate_commit_message(os, &model_id, &dir, &diff).await?;
+        if self.edit {
+            message = open_editor(Some(message))?;
+            if message.is_empty() {
+                bail!("Aborting, the commit message is empty");
+            }
+        }
+
+        match (self.commit, self.output) {
+            (true, _) => println!("{}", commit(os, &dir, &message).await?.trim_end()),
+            (false, Some(path)) => os.fs.write(path, format!("{message}\n")).await?,
+            (false, None) => println!("{message}"),
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// The staged diff of the repository `dir` is in, or none if nothing is staged.
+pub async fn staged_diff(dir: &Path) -> Result<Option<String>> {
+    if git(dir, ["rev-parse", "--git-dir"]).await.is_err() {
+        bail!("The current directory is not in a git repository");
+    }
+    let diff = git(dir, ["diff", "--cached", "--no-color"]).await?;
+    Ok((!diff.trim().is_empty()).then_some(diff))
+}
+
+/// Writes a conventional commit message for `diff`, the staged diff of the repository `dir` is in.
+pub async fn generate_commit_message(os: &Os, model_id: &str, dir: &Path, diff: &str) -> Result<String> {
+    // Fails in a repository without commits yet.
+    let recent = git(dir, ["log", &format!("-{RECENT_COMMITS}"), "--format=%s"])
+        .await
+        .unwrap_or_default();
+    let mut prompt = COMMIT_MESSAGE_PROMPT.to_string();
+    if !recent.trim().is_empty() {
+        prompt.push_str(&format!("\n\nRecent commits:\n{}", recent.trim_end()));
+    }
+    prompt.push_str(&format!(
+        "\n\nStaged changes:\n{}",
+        truncate_head_tail(diff.trim_end(), MAX_DIFF_TOKENS)
+    ));
+
+    let message = clean_response(&complete(os, model_id, prompt).await?);
+    if message.is_empty() {
+        bail!("The model did not write a commit message");
+    }
+    Ok(message)
+}
+
+/// Commits the staged changes of the repository `dir` is in with `message`, in the commit template
+/// and with the `Co-authored-by` trailer the user set, returning the output of git.
+pub async fn commit(os: &Os, dir: &Path, message: &str) -> Result<String> {
+    git(dir, ["commit", "-m", &commit_message(os, message)]).await
+}
+
+/// The id of the model `requested`, or else of the default model of the user or the service.
+pub async fn model_id(os: &Os, requested: Option<&str>) -> Result<String> {
+    let (models, default_model) = get_available_models(os).await?;
+    if let Some(requested) = requested {
+        return match find_model(&models, requested) {
+            Some(model) => Ok(model.model_id.clone()),
+            None => bail!("Model '{requested}' is not available"),
+        };
+    }
+    Ok(os
+        .database
+        .settings
+        .get_string(Setting::ChatDefaultModel)
+        .and_then(|saved| find_model(&models, &saved).map(|model| model.model_id.clone()))
+        .unwrap_or(default_model.model_id))
+}
+
+/// Sends `prompt` to the model on its own, outside of any conversation, and returns the response.
+pub async fn complete(os: &Os, model_id: &str, prompt: String) -> Result<String> {
+    let mut output = os
+        .client
+        .send_message(ConversationState {
+            conversation_id: None,
+            user_input_message: UserInputMessage {
+                content: prompt,
+                user_input_message_context: None,
+                user_intent: None,
+                images: None,
+                model_id: Some(model_id.to_string()),
+            },
+            history: None,
+        })
+        .await?;
+
+    let mut response = String::new();
+    while let Some(event) = output.recv().await? {
+        if let ChatResponseStream::AssistantResponseEvent { content } = event {
+            response.push_str(&content);
+        }
+    }
+    Ok(response)
+}
+
+/// The response of the model without the code fences or quotes it may have put around it.
+pub fn clean_response(response: &str) -> String {
+    let mut response = response.trim();
+    if let Some(fenced) = response.strip_prefix("```") {
+        // Skips the language of the fence, if any.
+        let fenced = fenced.split_once('\n').map_or("", |(_, rest)| rest);
+        response = fenced.trim_end().strip_suffix("```").unwrap_or(fenced).trim();
+    }
+    response
+        .strip_prefix('"')
+        .and_then(|unquoted| unquoted.strip_suffix('"'))
+        .unwrap_or(response)
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_response() {
+        assert_eq!(
+            clean_response("feat(parser): add spans\n\nThe errors need them.\n"),
+            "feat(parser): add spans\n\nThe errors need them."
+        );
+        assert_eq!(
+            clean_response("```\nfix: handle empty input\n```"),
+            "fix: handle empty input"
+        );
+        assert_eq!(
+            clean_response("```text\nfix: handle empty input\n\nBody.\n```\n"),
+            "fix: handle empty input\n\nBody."
+        );
+        assert_eq!(clean_response("\"docs: fix typo\""), "docs: fix typo");
+    }
+}
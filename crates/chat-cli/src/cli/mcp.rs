@@ -5,6 +5,10 @@ use std::collections::{
 use std::io::Write;
 use std::path::PathBuf;
 use std::process::ExitCode;
+use std::time::{
+    Duration,
+    Instant,
+};
 
 use clap::{
     ArgAction,
@@ -27,6 +31,7 @@ use super::agent::{
     DEFAULT_AGENT_NAME,
     McpServerConfig,
 };
+use crate::cli::chat::server_messenger::ServerMessengerBuilder;
 use crate::cli::chat::tool_manager::{
     global_mcp_config_path,
     workspace_mcp_config_path,
@@ -35,6 +40,16 @@ use crate::cli::chat::tools::custom_tool::{
     CustomToolConfig,
     default_timeout,
 };
+use crate::mcp_client::stderr_log::{
+    EXCERPT_LINES,
+    server_log_path,
+    tail,
+};
+use crate::mcp_client::{
+    InitializedMcpClient,
+    InnerService,
+    McpClientService,
+};
 use crate::os::Os;
 use crate::util::directories;
 
@@ -68,6 +83,12 @@ pub enum McpSubcommand {
     Import(ImportArgs),
     /// Get the status of a configured server
     Status(StatusArgs),
+    /// Enable a configured server
+    Enable(ToggleArgs),
+    /// Disable a configured server, without removing its configuration
+    Disable(ToggleArgs),
+    /// Launch a configured server, and list its tools and prompts once it has initialized
+    Test(TestArgs),
 }
 
 impl McpSubcommand {
@@ -78,6 +99,9 @@ impl McpSubcommand {
             Self::List(args) => args.execute(os, output).await?,
             Self::Import(args) => args.execute(os, output).await?,
             Self::Status(args) => args.execute(os, output).await?,
+            Self::Enable(args) => args.execute(os, output, false).await?,
+            Self::Disable(args) => args.execute(os, output, true).await?,
+            Self::Test(args) => args.execute(os, output).await?,
         }
 
         output.flush()?;
@@ -109,9 +133,15 @@ pub struct AddArgs {
     /// Environment variables to use when launching the server
     #[arg(long, value_parser = parse_env_vars)]
     pub env: Vec<HashMap<String, String>>,
-    /// Server launch timeout, in milliseconds
+    /// The directory to launch the server in
+    #[arg(long)]
+    pub cwd: Option<String>,
+    /// Timeout for each request to the server, in milliseconds
     #[arg(long)]
     pub timeout: Option<u64>,
+    /// Server launch timeout, in milliseconds
+    #[arg(long)]
+    pub startup_timeout: Option<u64>,
     /// Whether the server should be disabled (not loaded)
     #[arg(long, default_value_t = false)]
     pub disabled: bool,
@@ -144,7 +174,9 @@ impl AddArgs {
                     "command": self.command,
                     "args": processed_args,
                     "env": merged_env,
+                    "cwd": self.cwd,
                     "timeout": self.timeout.unwrap_or(default_timeout()),
+                    "startupTimeout": self.startup_timeout,
                     "disabled": self.disabled,
                 }))?;
 
@@ -177,7 +209,9 @@ impl AddArgs {
                     "command": self.command,
                     "args": processed_args,
                     "env": merged_env,
+                    "cwd": self.cwd,
                     "timeout": self.timeout.unwrap_or(default_timeout()),
+                    "startupTimeout": self.startup_timeout,
                     "disabled": self.disabled,
                 }))?;
 
@@ -385,6 +419,9 @@ impl ImportArgs {
 pub struct StatusArgs {
     #[arg(long)]
     pub name: String,
+    /// How many of the last lines the server wrote to its stderr to show
+    #[arg(long, default_value_t = EXCERPT_LINES)]
+    pub lines: usize,
 }
 
 impl StatusArgs {
@@ -402,7 +439,13 @@ impl StatusArgs {
                         style::Print(format!("Scope   : {}\n", scope_display(&sc))),
                         style::Print(format!("Agent   : {}\n", name)),
                         style::Print(format!("Command : {}\n", cfg.command)),
+                        style::Print(format!("Cwd     : {}\n", cfg.cwd.as_deref().unwrap_or("(chat's)"))),
                         style::Print(format!("Timeout : {} ms\n", cfg.timeout)),
+                        style::Print(format!(
+                            "Startup : {}\n",
+                            cfg.startup_timeout
+                                .map_or_else(|| "(no timeout)".into(), |t| format!("{t} ms"))
+                        )),
                         style::Print(format!("Disabled: {}\n", cfg.disabled)),
                         style::Print(format!(
                             "Env Vars: {}\n",
@@ -425,10 +468,252 @@ impl StatusArgs {
             bail!("No MCP server named '{}' found in any agent\n", self.name);
         }
 
+        let log_path = server_log_path(&self.name)?;
+        let lines = tail(&log_path, self.lines).await?;
+        if lines.is_empty() {
+            writeln!(output, "The server has not written to its stderr.\n")?;
+        } else {
+            execute!(
+                output,
+                style::Print(format!("Last lines of stderr, from {}:\n", log_path.display()).bold()),
+                style::Print(format!("{}\n\n", lines.join("\n"))),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct ToggleArgs {
+    #[arg(long)]
+    pub name: String,
+    /// Scope. This parameter is only meaningful in the absence of agent name.
+    #[arg(long)]
+    pub scope: Option<Scope>,
+    /// The agent the server is configured in. If an agent name is not supplied, the changes shall
+    /// be made to the global mcp.json
+    #[arg(long)]
+    pub agent: Option<String>,
+}
+
+impl ToggleArgs {
+    pub async fn execute(self, os: &Os, output: &mut impl Write, disabled: bool) -> Result<()> {
+        let action = if disabled { "Disabled" } else { "Enabled" };
+        match self.agent.as_deref() {
+            Some(agent_name) => {
+                let (mut agent, config_path) = Agent::get_agent_by_name(os, agent_name).await?;
+                let Some(server) = agent.mcp_servers.mcp_servers.get_mut(&self.name) else {
+                    bail!("No MCP server named '{}' found in agent {}", self.name, agent_name);
+                };
+                server.disabled = disabled;
+
+                let json = agent.to_str_pretty()?;
+                os.fs.write(config_path, json).await?;
+                writeln!(
+                    output,
+                    "\n✓ {action} MCP server '{}' in agent {}\n",
+                    self.name, agent_name
+                )?;
+            },
+            None => {
+                let legacy_mcp_config_path = match self.scope {
+                    Some(Scope::Workspace) => directories::chat_legacy_workspace_mcp_config(os)?,
+                    _ => directories::chat_legacy_global_mcp_config(os)?,
+                };
+                let mut config = load_cfg(os, &legacy_mcp_config_path).await?;
+                let Some(server) = config.mcp_servers.get_mut(&self.name) else {
+                    bail!(
+                        "No MCP server named '{}' found in global config (path {})",
+                        self.name,
+                        legacy_mcp_config_path.display()
+                    );
+                };
+                server.disabled = disabled;
+
+                config.save_to_file(os, &legacy_mcp_config_path).await?;
+                writeln!(
+                    output,
+                    "\n✓ {action} MCP server '{}' in global config (path {})\n",
+                    self.name,
+                    legacy_mcp_config_path.display()
+                )?;
+            },
+        }
+
         Ok(())
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Args)]
+pub struct TestArgs {
+    #[arg(long)]
+    pub name: String,
+    /// The agent whose configuration of the server to test. Defaults to the first agent that
+    /// configures it, then to the mcp.json files.
+    #[arg(long)]
+    pub agent: Option<String>,
+}
+
+impl TestArgs {
+    pub async fn execute(self, os: &mut Os, output: &mut impl Write) -> Result<()> {
+        let config = match self.agent.as_deref() {
+            Some(agent_name) => {
+                let (agent, _) = Agent::get_agent_by_name(os, agent_name).await?;
+                match agent.mcp_servers.mcp_servers.get(&self.name) {
+                    Some(config) => config.clone(),
+                    None => bail!("No MCP server named '{}' found in agent {}", self.name, agent_name),
+                }
+            },
+            None => match find_server_config(os, &self.name).await? {
+                Some(config) => config,
+                None => bail!("No MCP server named '{}' found in any agent or mcp.json", self.name),
+            },
+        };
+
+        writeln!(output, "\nStarting MCP server '{}'...", self.name)?;
+        output.flush()?;
+        let probe = probe_server(os, &self.name, config).await?;
+        write!(output, "{}", format_probe(&self.name, &probe))?;
+
+        Ok(())
+    }
+}
+
+/// What [probe_server] found out about a server.
+#[derive(Debug, Default)]
+struct ServerProbe {
+    /// The time the server took to complete initialization.
+    startup: Duration,
+    /// The name and version the server reported.
+    server_info: Option<String>,
+    /// The tools and their descriptions, `None` if the server has no tools capability.
+    tools: Option<Vec<(String, String)>>,
+    /// The prompts and their descriptions, `None` if the server has no prompts capability.
+    prompts: Option<Vec<(String, String)>>,
+}
+
+/// Launches a server as q chat would, waits for it to complete initialization within its
+/// configured timeout, and lists its tools and prompts. The server is shut down when this returns.
+async fn probe_server(os: &Os, name: &str, config: CustomToolConfig) -> Result<ServerProbe> {
+    let timeout = Duration::from_millis(config.startup_timeout.unwrap_or(config.timeout));
+    // The client sends the lists it fetches on its own here, which are listed again below
+    // instead, as they are only sent for the capabilities the server has.
+    let (_update_event_receiver, messenger_builder) = ServerMessengerBuilder::new(20);
+    let messenger = messenger_builder.build_with_name(name.to_string());
+
+    let started = Instant::now();
+    let client = McpClientService::new(name.to_string(), config, messenger)
+        .init(os)
+        .await?;
+    let service = match client {
+        InitializedMcpClient::Pending(handle) => match tokio::time::timeout(timeout, handle).await {
+            Ok(service) => service??,
+            Err(_) => bail!(
+                "MCP server '{name}' did not complete initialization within {} ms",
+                timeout.as_millis()
+            ),
+        },
+        InitializedMcpClient::Ready(service) => service,
+    };
+    let startup = started.elapsed();
+
+    let peer = match &service.inner_service {
+        InnerService::Original(service) => service.peer().clone(),
+        InnerService::Peer(peer) => peer.clone(),
+    };
+    let Some(info) = peer.peer_info().cloned() else {
+        bail!("MCP server '{name}' did not send its initialization result");
+    };
+
+    let mut probe = ServerProbe {
+        startup,
+        server_info: Some(format!("{} {}", info.server_info.name, info.server_info.version)),
+        ..Default::default()
+    };
+    if info.capabilities.tools.is_some() {
+        let tools = tokio::time::timeout(timeout, peer.list_all_tools()).await??;
+        probe.tools = Some(
+            tools
+                .into_iter()
+                .map(|tool| {
+                    (
+                        tool.name.to_string(),
+                        tool.description.as_deref().unwrap_or_default().to_string(),
+                    )
+                })
+                .collect(),
+        );
+    }
+    if info.capabilities.prompts.is_some() {
+        let prompts = tokio::time::timeout(timeout, peer.list_all_prompts()).await??;
+        probe.prompts = Some(
+            prompts
+                .into_iter()
+                .map(|prompt| (prompt.name, prompt.description.unwrap_or_default()))
+                .collect(),
+        );
+    }
+
+    Ok(probe)
+}
+
+fn format_probe(name: &str, probe: &ServerProbe) -> String {
+    let mut out = format!(
+        "✓ {name} initialized in {:.2} s{}\n",
+        probe.startup.as_secs_f64(),
+        probe
+            .server_info
+            .as_ref()
+            .map(|info| format!(" ({info})"))
+            .unwrap_or_default()
+    );
+
+    for (title, items) in [("Tools", &probe.tools), ("Prompts", &probe.prompts)] {
+        match items {
+            Some(items) => {
+                out.push_str(&format!("\n{title} ({}):\n", items.len()));
+                let width = items.iter().map(|(name, _)| name.len()).max().unwrap_or_default();
+                for (name, description) in items {
+                    // Only the first line, as some descriptions are whole manuals.
+                    let description = description.lines().next().unwrap_or_default();
+                    let line = format!("  • {name:<width$}  {description}");
+                    out.push_str(line.trim_end());
+                    out.push('\n');
+                }
+            },
+            None => out.push_str(&format!("\n{title}: not supported by the server\n")),
+        }
+    }
+    out.push('\n');
+
+    out
+}
+
+/// The configuration of the server named `name` in the first agent that has one, or else in the
+/// workspace or global mcp.json.
+async fn find_server_config(os: &mut Os, name: &str) -> Result<Option<CustomToolConfig>> {
+    let configs = get_mcp_server_configs(os).await?;
+    let from_agents = configs
+        .into_values()
+        .flatten()
+        .find_map(|(_, config, _)| config.and_then(|c| c.mcp_servers.get(name).cloned()));
+    if from_agents.is_some() {
+        return Ok(from_agents);
+    }
+
+    for path in [
+        directories::chat_legacy_workspace_mcp_config(os)?,
+        directories::chat_legacy_global_mcp_config(os)?,
+    ] {
+        if let Some(config) = load_cfg(os, &path).await?.mcp_servers.remove(name) {
+            return Ok(Some(config));
+        }
+    }
+
+    Ok(None)
+}
+
 /// Returns a [BTreeMap] for consistent key iteration.
 async fn get_mcp_server_configs(os: &mut Os) -> Result<BTreeMap<Scope, Vec<(String, Option<McpServerConfig>, bool)>>> {
     let mut results = BTreeMap::new();
@@ -647,7 +932,9 @@ mod tests {
                 "--allow-sensitive-data-access".to_string(),
             ],
             env: vec![],
+            cwd: None,
             timeout: None,
+            startup_timeout: None,
             agent: None,
             disabled: false,
             force: false,
@@ -704,7 +991,9 @@ mod tests {
                     .into_iter()
                     .collect()
                 ],
+                cwd: None,
                 timeout: None,
+                startup_timeout: None,
                 disabled: false,
                 force: false,
             }))
@@ -739,7 +1028,10 @@ mod tests {
     fn test_mcp_subcommand_status_simple() {
         assert_parse!(
             ["mcp", "status", "--name", "aws"],
-            RootSubcommand::Mcp(McpSubcommand::Status(StatusArgs { name: "aws".into() }))
+            RootSubcommand::Mcp(McpSubcommand::Status(StatusArgs {
+                name: "aws".into(),
+                lines: EXCERPT_LINES,
+            }))
         );
     }
 
@@ -753,6 +1045,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mcp_subcommand_enable_disable() {
+        assert_parse!(
+            ["mcp", "disable", "--name", "git", "--agent", "backend"],
+            RootSubcommand::Mcp(McpSubcommand::Disable(ToggleArgs {
+                name: "git".into(),
+                scope: None,
+                agent: Some("backend".into()),
+            }))
+        );
+        assert_parse!(
+            ["mcp", "enable", "--name", "git", "--scope", "workspace"],
+            RootSubcommand::Mcp(McpSubcommand::Enable(ToggleArgs {
+                name: "git".into(),
+                scope: Some(Scope::Workspace),
+                agent: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_mcp_subcommand_test() {
+        assert_parse!(
+            ["mcp", "test", "--name", "git"],
+            RootSubcommand::Mcp(McpSubcommand::Test(TestArgs {
+                name: "git".into(),
+                agent: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_format_probe() {
+        let probe = ServerProbe {
+            startup: Duration::from_millis(1250),
+            server_info: Some("mcp-server-git 1.2.0".to_string()),
+            tools: Some(vec![
+                (
+                    "git_status".to_string(),
+                    "Shows the working tree status\nMore details".to_string(),
+                ),
+                ("git_log".to_string(), String::new()),
+            ]),
+            prompts: None,
+        };
+        assert_eq!(
+            format_probe("git", &probe),
+            "✓ git initialized in 1.25 s (mcp-server-git 1.2.0)\n\nTools (2):\n  • git_status  Shows the working tree status\n  • git_log\n\nPrompts: not supported by the server\n\n"
+        );
+    }
+
     #[test]
     fn test_parse_args_comma_separated() {
         let result = parse_args("arg1,arg2,arg3").unwrap();
@@ -1,12 +1,21 @@
 mod agent;
+mod audit;
+mod bench;
 pub mod chat;
+mod config_archive;
 mod debug;
 mod diagnostics;
 pub mod experiment;
 pub mod feed;
+mod generate;
 mod issue;
 mod mcp;
+mod policy;
+mod pr;
+mod provenance;
+mod purge;
 mod settings;
+mod telemetry;
 mod user;
 
 use std::fmt::Display;
@@ -43,14 +52,20 @@ use tracing::{
     debug,
 };
 
+use crate::cli::audit::AuditSubcommand;
 use crate::cli::chat::ChatArgs;
+use crate::cli::generate::GenerateSubcommand;
 use crate::cli::mcp::McpSubcommand;
+use crate::cli::policy::PolicySubcommand;
+use crate::cli::pr::PrSubcommand;
+use crate::cli::telemetry::TelemetrySubcommand;
 use crate::cli::user::{
     LoginArgs,
     WhoamiArgs,
 };
 use crate::logging::{
     LogArgs,
+    LogFormat,
     initialize_logging,
 };
 use crate::os::Os;
@@ -58,6 +73,7 @@ use crate::util::directories::logs_dir;
 use crate::util::{
     CLI_BINARY_NAME,
     GOV_REGIONS,
+    shutdown,
 };
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
@@ -122,6 +138,28 @@ pub enum RootSubcommand {
     /// Model Context Protocol (MCP)
     #[command(subcommand)]
     Mcp(McpSubcommand),
+    /// Test agent trust configurations
+    #[command(subcommand)]
+    Policy(PolicySubcommand),
+    /// Inspect the telemetry sent from this machine
+    #[command(subcommand)]
+    Telemetry(TelemetrySubcommand),
+    /// Review the tools the agent used, from the audit log
+    #[command(subcommand)]
+    Audit(AuditSubcommand),
+    /// Delete data past the retention settings
+    Purge(purge::PurgeArgs),
+    /// Show which lines of a file were written by the agent
+    Provenance(provenance::ProvenanceArgs),
+    /// Write text with the model for scripts and git hooks, such as commit messages
+    #[command(subcommand)]
+    Generate(GenerateSubcommand),
+    /// Describe or review the changes of a pull request
+    #[command(subcommand)]
+    Pr(PrSubcommand),
+    /// Benchmark the built-in tools on synthetic workspaces
+    #[command(name = "_bench", hide = true)]
+    Bench(bench::BenchArgs),
 }
 
 impl RootSubcommand {
@@ -133,7 +171,7 @@ impl RootSubcommand {
     }
 
     pub fn requires_auth(&self) -> bool {
-        matches!(self, Self::Chat(_) | Self::Profile)
+        matches!(self, Self::Chat(_) | Self::Profile | Self::Generate(_) | Self::Pr(_))
     }
 
     pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
@@ -168,8 +206,19 @@ impl RootSubcommand {
             Self::Settings(settings_args) => settings_args.execute(os).await,
             Self::Issue(args) => args.execute(os).await,
             Self::Version { changelog } => Cli::print_version(changelog),
-            Self::Chat(args) => args.execute(os).await,
+            Self::Chat(args) => {
+                purge::purge_on_startup(os).await;
+                args.execute(os).await
+            },
             Self::Mcp(args) => args.execute(os, &mut std::io::stderr()).await,
+            Self::Policy(args) => args.execute(os).await,
+            Self::Telemetry(args) => args.execute(os).await,
+            Self::Audit(subcommand) => subcommand.execute(os).await,
+            Self::Purge(args) => args.execute(os).await,
+            Self::Provenance(args) => args.execute(os).await,
+            Self::Generate(subcommand) => subcommand.execute(os).await,
+            Self::Pr(subcommand) => subcommand.execute(os).await,
+            Self::Bench(args) => args.execute(os).await,
         }
     }
 }
@@ -194,6 +243,14 @@ impl Display for RootSubcommand {
             Self::Issue(_) => "issue",
             Self::Version { .. } => "version",
             Self::Mcp(_) => "mcp",
+            Self::Policy(_) => "policy",
+            Self::Telemetry(_) => "telemetry",
+            Self::Audit(_) => "audit",
+            Self::Purge(_) => "purge",
+            Self::Provenance(_) => "provenance",
+            Self::Generate(_) => "generate",
+            Self::Pr(_) => "pr",
+            Self::Bench(_) => "_bench",
         };
 
         write!(f, "{name}")
@@ -237,6 +294,7 @@ impl Cli {
                 _ => None,
             },
             delete_old_log_file: false,
+            format: LogFormat::from_env(),
         });
 
         // Check for region support.
@@ -248,10 +306,15 @@ impl Cli {
 
         debug!(command =? std::env::args().collect::<Vec<_>>(), "Command being ran");
 
+        shutdown::install();
+        let cleanup_guard = shutdown::CleanupGuard;
         let mut os = Os::new().await?;
+        crate::telemetry::otel::init(&os.database.settings);
         let result = subcommand.execute(&mut os).await;
+        drop(cleanup_guard);
 
         let telemetry_result = os.telemetry.finish().await;
+        crate::telemetry::otel::shutdown();
         let exit_code = result?;
         telemetry_result?;
 
@@ -337,11 +400,17 @@ impl Cli {
 
 #[cfg(test)]
 mod test {
+    use chat::ChatSubcommand;
     use chat::WrapMode::{
         Always,
         Auto,
         Never,
     };
+    use chat::history::{
+        HistoryArgs,
+        HistorySubcommand,
+    };
+    use chat::json_output::ChatOutputFormat;
 
     use super::*;
     use crate::util::CHAT_BINARY_NAME;
@@ -383,6 +452,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })),
             verbose: 2,
             help_all: false,
@@ -423,6 +496,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
     }
@@ -440,6 +517,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
     }
@@ -457,6 +538,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
     }
@@ -474,6 +559,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: true,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
         assert_parse!(
@@ -487,6 +576,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: true,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
     }
@@ -504,6 +597,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
     }
@@ -521,6 +618,10 @@ mod test {
                 trust_tools: Some(vec!["".to_string()]),
                 no_interactive: false,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
     }
@@ -538,6 +639,10 @@ mod test {
                 trust_tools: Some(vec!["fs_read".to_string(), "fs_write".to_string()]),
                 no_interactive: false,
                 wrap: None,
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
     }
@@ -555,6 +660,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: Some(Never),
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
         assert_parse!(
@@ -568,6 +677,10 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: Some(Always),
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
             })
         );
         assert_parse!(
@@ -581,6 +694,244 @@ mod test {
                 trust_tools: None,
                 no_interactive: false,
                 wrap: Some(Auto),
+                output_format: ChatOutputFormat::Text,
+                max_turns: None,
+                contract: None,
+                subcommand: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_history_list() {
+        assert_parse!(
+            ["chat", "history", "list", "--tag", "aws", "--tag", "rust"],
+            RootSubcommand::Chat(ChatArgs {
+                subcommand: Some(ChatSubcommand::History(HistoryArgs {
+                    cmd: HistorySubcommand::List {
+                        tag: vec!["aws".to_string(), "rust".to_string()],
+                        limit: 20,
+                    },
+                })),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_history_purge() {
+        assert_parse!(
+            ["chat", "history", "purge", "--older-than", "30d"],
+            RootSubcommand::Chat(ChatArgs {
+                subcommand: Some(ChatSubcommand::History(HistoryArgs {
+                    cmd: HistorySubcommand::Purge {
+                        older_than: Some(std::time::Duration::from_secs(30 * 86400)),
+                        all: false,
+                    },
+                })),
+                ..Default::default()
+            })
+        );
+        assert_parse!(
+            ["chat", "history", "purge", "--all"],
+            RootSubcommand::Chat(ChatArgs {
+                subcommand: Some(ChatSubcommand::History(HistoryArgs {
+                    cmd: HistorySubcommand::Purge {
+                        older_than: None,
+                        all: true,
+                    },
+                })),
+                ..Default::default()
+            })
+        );
+        assert!(Cli::try_parse_from([CHAT_BINARY_NAME, "chat", "history", "purge"]).is_err());
+    }
+
+    #[test]
+    fn test_policy_test() {
+        assert_parse!(
+            [
+                "policy",
+                "test",
+                "request.json",
+                "--agent",
+                "reviewer",
+                "--trust-all-tools"
+            ],
+            RootSubcommand::Policy(PolicySubcommand::Test(policy::TestArgs {
+                request: "request.json".to_string(),
+                agent: Some("reviewer".to_string()),
+                config: None,
+                trust_all_tools: true,
+                format: OutputFormat::Plain,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_policy_show() {
+        assert_parse!(
+            ["policy", "show", "--format", "json"],
+            RootSubcommand::Policy(PolicySubcommand::Show {
+                format: OutputFormat::Json,
+            })
+        );
+    }
+
+    #[test]
+    fn test_telemetry_show() {
+        assert_parse!(
+            ["telemetry", "show", "--last", "5", "--format", "json"],
+            RootSubcommand::Telemetry(TelemetrySubcommand::Show(telemetry::ShowArgs {
+                last: 5,
+                format: OutputFormat::Json,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_audit() {
+        assert_parse!(
+            [
+                "audit",
+                "list",
+                "--tool",
+                "execute_bash",
+                "--since",
+                "7d",
+                "--last",
+                "10"
+            ],
+            RootSubcommand::Audit(AuditSubcommand::List(audit::ListArgs {
+                conversation: None,
+                tool: Some("execute_bash".to_string()),
+                since: Some(std::time::Duration::from_secs(7 * 86400)),
+                last: 10,
+                format: OutputFormat::Plain,
+            }))
+        );
+        assert_parse!(
+            ["audit", "show", "0123abcd", "-f", "json"],
+            RootSubcommand::Audit(AuditSubcommand::Show(audit::ShowArgs {
+                id: "0123abcd".to_string(),
+                format: OutputFormat::Json,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_purge() {
+        assert_parse!(
+            ["purge", "--dry-run", "-f", "json"],
+            RootSubcommand::Purge(purge::PurgeArgs {
+                dry_run: true,
+                format: OutputFormat::Json,
+            })
+        );
+    }
+
+    #[test]
+    fn test_provenance() {
+        assert_parse!(
+            ["provenance", "src/main.rs"],
+            RootSubcommand::Provenance(provenance::ProvenanceArgs {
+                path: std::path::PathBuf::from("src/main.rs"),
+                format: OutputFormat::Plain,
+            })
+        );
+    }
+
+    #[test]
+    fn test_generate_commit_message() {
+        assert_parse!(
+            [
+                "generate",
+                "commit-message",
+                "--edit",
+                "--output",
+                ".git/COMMIT_EDITMSG"
+            ],
+            RootSubcommand::Generate(GenerateSubcommand::CommitMessage(generate::CommitMessageArgs {
+                edit: true,
+                commit: false,
+                output: Some(std::path::PathBuf::from(".git/COMMIT_EDITMSG")),
+                model: None,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_pr() {
+        assert_parse!(
+            [
+                "pr",
+                "review",
+                "main..feature",
+                "--post",
+                "codecommit",
+                "--pull-request",
+                "42"
+            ],
+            RootSubcommand::Pr(PrSubcommand::Review(pr::PrArgs {
+                target: Some("main..feature".to_string()),
+                base: None,
+                post: Some(pr::PostTo::Codecommit),
+                pull_request: Some("42".to_string()),
+                model: None,
+                format: OutputFormat::Plain,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_chat_history_diff() {
+        assert_parse!(
+            ["chat", "history", "diff", "a.json", "b.json"],
+            RootSubcommand::Chat(ChatArgs {
+                subcommand: Some(ChatSubcommand::History(HistoryArgs {
+                    cmd: HistorySubcommand::Diff {
+                        first: "a.json".to_string(),
+                        second: "b.json".to_string(),
+                    },
+                })),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_output_format_json() {
+        assert_parse!(
+            ["chat", "--output-format", "json", "Hello"],
+            RootSubcommand::Chat(ChatArgs {
+                input: Some("Hello".to_string()),
+                output_format: ChatOutputFormat::Json,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_max_turns() {
+        assert_parse!(
+            ["chat", "--no-interactive", "--max-turns", "5", "Hello"],
+            RootSubcommand::Chat(ChatArgs {
+                no_interactive: true,
+                max_turns: Some(5),
+                input: Some("Hello".to_string()),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn test_chat_contract() {
+        assert_parse!(
+            ["chat", "--contract", "schema.json", "Summarize the changes"],
+            RootSubcommand::Chat(ChatArgs {
+                contract: Some(std::path::PathBuf::from("schema.json")),
+                input: Some("Summarize the changes".to_string()),
+                ..Default::default()
             })
         );
     }
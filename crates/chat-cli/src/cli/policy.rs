@@ -0,0 +1,555 @@
+//! Simulating what an agent's trust configuration does with a tool request, so guardrails can be
+//! checked before they are rolled out.
+
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::OutputFormat;
+use super::agent::{
+    Agent,
+    Agents,
+    ApprovalClass,
+    ManagedPolicy,
+    McpServerConfig,
+    PermissionEvalResult,
+    PolicyEvalResult,
+};
+use super::chat::tools::Tool;
+use super::chat::tools::cloudwatch_logs::CloudwatchLogs;
+use super::chat::tools::delegate::Delegate;
+use super::chat::tools::dependency_report::DependencyReport;
+use super::chat::tools::execute::ExecuteCommand;
+use super::chat::tools::fs_read::FsRead;
+use super::chat::tools::fs_write::FsWrite;
+use super::chat::tools::gh_issue::GhIssue;
+use super::chat::tools::git::Git;
+use super::chat::tools::introspect::Introspect;
+use super::chat::tools::issue_tracker::IssueTracker;
+#[cfg(feature = "knowledge")]
+use super::chat::tools::knowledge::Knowledge;
+use super::chat::tools::retrieve_output::RetrieveOutput;
+use super::chat::tools::security_scan::SecurityScan;
+#[cfg(feature = "knowledge")]
+use super::chat::tools::semantic_search::SemanticSearch;
+use super::chat::tools::thinking::Thinking;
+use super::chat::tools::todo::TodoList;
+use super::chat::tools::update_plan::UpdatePlan;
+use super::chat::tools::use_aws::UseAws;
+use crate::os::Os;
+use crate::util::MCP_SERVER_TOOL_DELIMITER;
+use crate::util::tool_permission_checker::is_tool_in_allowlist;
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum PolicySubcommand {
+    /// Report whether tool requests would be auto-approved, prompted or denied, and which rule
+    /// decides it
+    Test(TestArgs),
+    /// Show the managed policy an administrator installed on this machine
+    Show {
+        /// The format of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+}
+
+impl PolicySubcommand {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        match self {
+            Self::Test(args) => args.execute(os).await,
+            Self::Show { format } => {
+                let policy = ManagedPolicy::load(os).await?;
+                format.print(
+                    || match &policy {
+                        Some(policy) => format_managed_policy(policy),
+                        None => "No managed policy is installed".to_string(),
+                    },
+                    || &policy,
+                );
+                Ok(ExitCode::SUCCESS)
+            },
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Args)]
+#[command(after_long_help = "A request names the tool as the model would, with its input, e.g.
+
+  {\"name\": \"execute_bash\", \"input\": {\"command\": \"git push\"}}
+
+MCP tools are named @server/tool. The file can also hold a list of requests.")]
+pub struct TestArgs {
+    /// A JSON file with the tool request, or - to read it from stdin
+    pub request: String,
+    /// The agent whose configuration is tested. Defaults to the agent q chat would use
+    #[arg(long, conflicts_with = "config")]
+    pub agent: Option<String>,
+    /// An agent config file to test instead of an installed agent
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// Test as if the session was started with --trust-all-tools
+    #[arg(long)]
+    pub trust_all_tools: bool,
+    /// The format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl TestArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let content = if self.request == "-" {
+            std::io::read_to_string(std::io::stdin())?
+        } else {
+            os.fs.read_to_string(&self.request).await?
+        };
+        let requests = match serde_json::from_str::<serde_json::Value>(&content)? {
+            serde_json::Value::Array(requests) => requests,
+            request => vec![request],
+        }
+        .into_iter()
+        .map(serde_json::from_value::<ToolRequest>)
+        .collect::<Result<Vec<_>, _>>()?;
+
+        let mut stderr = std::io::stderr();
+        let managed_policy = ManagedPolicy::load(os).await?;
+        let mcp_enabled =
+            os.client.is_mcp_enabled().await.unwrap_or(true) && ManagedPolicy::mcp_enabled(managed_policy.as_ref());
+        let agent = match &self.config {
+            Some(path) => Agent::load(os, path, &mut None::<McpServerConfig>, mcp_enabled, &mut stderr)
+                .await
+                .map_err(|e| eyre!("Failed to load {}: {e}", path.display()))?,
+            None => {
+                let agents = Agents::load(os, self.agent.as_deref(), true, &mut stderr, mcp_enabled)
+                    .await
+                    .0;
+                match agents.get_active() {
+                    Some(agent) => agent.clone(),
+                    None => bail!("No agent to test"),
+                }
+            },
+        };
+
+        let decisions = requests
+            .into_iter()
+            .map(|request| evaluate(os, &agent, managed_policy.as_ref(), self.trust_all_tools, request))
+            .collect::<Result<Vec<_>>>()?;
+        self.format.print(
+            || {
+                decisions
+                    .iter()
+                    .map(|decision| decision.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            },
+            || &decisions,
+        );
+
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// A tool use as the model would request it.
+#[derive(Debug, Deserialize)]
+pub struct ToolRequest {
+    pub name: String,
+    #[serde(default, alias = "args")]
+    pub input: serde_json::Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Verdict {
+    AutoApproved,
+    Prompted,
+    Denied,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Decision {
+    pub tool: String,
+    pub verdict: Verdict,
+    /// The rule the verdict comes from.
+    pub rule: String,
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = match self.verdict {
+            Verdict::AutoApproved => "auto-approved",
+            Verdict::Prompted => "prompted",
+            Verdict::Denied => "denied",
+        };
+        write!(f, "{}: {verdict} ({})", self.tool, self.rule)
+    }
+}
+
+/// What a session running `agent` would do with `request`. Evaluated the same way as in chat:
+/// the managed policy comes first, denied rules win over --trust-all-tools, and infrastructure
+/// changes are always confirmed.
+pub fn evaluate(
+    os: &Os,
+    agent: &Agent,
+    managed_policy: Option<&ManagedPolicy>,
+    trust_all_tools: bool,
+    request: ToolRequest,
+) -> Result<Decision> {
+    let ToolRequest { name, input } = request;
+    let server_and_tool = name
+        .strip_prefix('@')
+        .and_then(|name| name.split_once(MCP_SERVER_TOOL_DELIMITER));
+
+    let (policy_result, result, infra_change) = match server_and_tool {
+        Some((server, tool)) => {
+            let policy_result = match managed_policy {
+                Some(policy) => match policy.eval_mcp(server, tool) {
+                    Some(rule) => PolicyEvalResult::Deny(rule),
+                    None if policy.require_approval.contains(&ApprovalClass::Mcp) => {
+                        PolicyEvalResult::RequireApproval(ApprovalClass::Mcp)
+                    },
+                    None => PolicyEvalResult::Unrestricted,
+                },
+                None => PolicyEvalResult::Unrestricted,
+            };
+            let result = if is_tool_in_allowlist(&agent.allowed_tools, tool, Some(server)) {
+                PermissionEvalResult::Allow
+            } else {
+                PermissionEvalResult::Ask
+            };
+            (policy_result, result, None)
+        },
+        None => {
+            let tool = parse_tool(&name, input)?;
+            let policy_result = managed_policy.map_or(PolicyEvalResult::Unrestricted, |policy| policy.eval(&tool));
+            (policy_result, tool.requires_acceptance(os, agent), tool.infra_change())
+        },
+    };
+
+    match policy_result {
+        PolicyEvalResult::Deny(_) => {
+            return Ok(Decision {
+                tool: name,
+                verdict: Verdict::Denied,
+                rule: policy_result.to_string(),
+            });
+        },
+        PolicyEvalResult::RequireApproval(_) => {
+            return Ok(Decision {
+                tool: name,
+                verdict: Verdict::Prompted,
+                rule: policy_result.to_string(),
+            });
+        },
+        PolicyEvalResult::Unrestricted => (),
+    }
+
+    let (verdict, rule) = match result {
+        PermissionEvalResult::Deny(rules) => (
+            Verdict::Denied,
+            format!("toolsSettings.{name} denies {}", rules.join(", ")),
+        ),
+        PermissionEvalResult::Allow => {
+            let mut entries = agent.allowed_tools.iter().collect::<Vec<_>>();
+            entries.sort();
+            let entry = entries.into_iter().find(|entry| {
+                let allowed_tools = HashSet::from([(*entry).clone()]);
+                match server_and_tool {
+                    Some((server, tool)) => is_tool_in_allowlist(&allowed_tools, tool, Some(server)),
+                    None => is_tool_in_allowlist(&allowed_tools, &name, None),
+                }
+            });
+            let rule = match entry {
+                Some(entry) => format!("allowedTools has \"{entry}\""),
+                None if agent.tools_settings.contains_key(name.as_str()) => format!("toolsSettings.{name} allows it"),
+                None => format!("{name} is trusted by default"),
+            };
+            (Verdict::AutoApproved, rule)
+        },
+        PermissionEvalResult::Ask if trust_all_tools => (Verdict::AutoApproved, "--trust-all-tools".to_string()),
+        PermissionEvalResult::Ask => (Verdict::Prompted, format!("no rule of agent {} allows it", agent.name)),
+    };
+    let (verdict, rule) = match infra_change {
+        Some(change) if verdict == Verdict::AutoApproved => (
+            Verdict::Prompted,
+            format!("{} is an infrastructure change, confirmed every time", change.action),
+        ),
+        _ => (verdict, rule),
+    };
+
+    Ok(Decision {
+        tool: name,
+        verdict,
+        rule,
+    })
+}
+
+fn format_managed_policy(policy: &ManagedPolicy) -> String {
+    let list = |items: Vec<String>| match items.is_empty() {
+        true => "none".to_string(),
+        false => items.join(", "),
+    };
+    let mut denied_tools = policy.denied_tools.iter().cloned().collect::<Vec<_>>();
+    denied_tools.sort();
+    let allowed_commands = match &policy.allowed_commands {
+        Some(commands) => list(commands.clone()),
+        None => "any".to_string(),
+    };
+    [
+        format!("source: {}", policy.source),
+        format!("denied tools: {}", list(denied_tools)),
+        format!("allowed commands: {allowed_commands}"),
+        format!("MCP: {}", if policy.disable_mcp { "disabled" } else { "enabled" }),
+        format!(
+            "approval required for: {}",
+            list(policy.require_approval.iter().map(|class| class.to_string()).collect())
+        ),
+    ]
+    .join("\n")
+}
+
+/// The built-in tool `name` with its input, like [super::chat::tool_manager::ToolManager] parses
+/// tool uses.
+fn parse_tool(name: &str, input: serde_json::Value) -> Result<Tool> {
+    let invalid = |e: serde_json::Error| eyre!("Invalid input for {name}: {e}");
+    Ok(match name {
+        "fs_read" => Tool::FsRead(serde_json::from_value::<FsRead>(input).map_err(invalid)?),
+        "fs_write" => Tool::FsWrite(serde_json::from_value::<FsWrite>(input).map_err(invalid)?),
+        #[cfg(windows)]
+        "execute_powershell" | "execute_cmd" | "execute_bash" => {
+            Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(input).map_err(invalid)?)
+        },
+        #[cfg(not(windows))]
+        "execute_bash" => Tool::ExecuteCommand(serde_json::from_value::<ExecuteCommand>(input).map_err(invalid)?),
+        "use_aws" => Tool::UseAws(serde_json::from_value::<UseAws>(input).map_err(invalid)?),
+        "cloudwatch_logs" => Tool::CloudwatchLogs(serde_json::from_value::<CloudwatchLogs>(input).map_err(invalid)?),
+        "issue_tracker" => Tool::IssueTracker(serde_json::from_value::<IssueTracker>(input).map_err(invalid)?),
+        "report_issue" => Tool::IssueTracker(IssueTracker::Report(
+            serde_json::from_value::<GhIssue>(input).map_err(invalid)?,
+        )),
+        "git" => Tool::Git(serde_json::from_value::<Git>(input).map_err(invalid)?),
+        "introspect" => Tool::Introspect(serde_json::from_value::<Introspect>(input).map_err(invalid)?),
+        "thinking" => Tool::Thinking(serde_json::from_value::<Thinking>(input).map_err(invalid)?),
+        #[cfg(feature = "knowledge")]
+        "knowledge" => Tool::Knowledge(serde_json::from_value::<Knowledge>(input).map_err(invalid)?),
+        #[cfg(feature = "knowledge")]
+        "semantic_search" => Tool::SemanticSearch(serde_json::from_value::<SemanticSearch>(input).map_err(invalid)?),
+        "todo_list" => Tool::Todo(serde_json::from_value::<TodoList>(input).map_err(invalid)?),
+        "update_plan" => Tool::UpdatePlan(serde_json::from_value::<UpdatePlan>(input).map_err(invalid)?),
+        "delegate" => Tool::Delegate(serde_json::from_value::<Delegate>(input).map_err(invalid)?),
+        "dependency_report" => {
+            Tool::DependencyReport(serde_json::from_value::<DependencyReport>(input).map_err(invalid)?)
+        },
+        "security_scan" => Tool::SecurityScan(serde_json::from_value::<SecurityScan>(input).map_err(invalid)?),
+        "retrieve_output" => Tool::RetrieveOutput(serde_json::from_value::<RetrieveOutput>(input).map_err(invalid)?),
+        name => bail!("Unknown tool {name}, MCP tools are named @server{MCP_SERVER_TOOL_DELIMITER}tool"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::cli::agent::ToolSettingTarget;
+
+    fn request(name: &str, input: serde_json::Value) -> ToolRequest {
+        ToolRequest {
+            name: name.to_string(),
+            input,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evaluate() {
+        let os = Os::new().await.unwrap();
+        let agent = Agent {
+            name: "guarded".to_string(),
+            allowed_tools: HashSet::from(["@git".to_string(), "use_aws".to_string()]),
+            tools_settings: HashMap::from([(
+                ToolSettingTarget("execute_bash".to_string()),
+                serde_json::json!({ "allowedCommands": ["cargo .*"], "deniedCommands": ["git push.*"] }),
+            )]),
+            ..Default::default()
+        };
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            None,
+            true,
+            request("execute_bash", serde_json::json!({ "command": "git push --force" })),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::Denied);
+        assert_eq!(decision.rule, "toolsSettings.execute_bash denies \\Agit push.*\\z");
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            None,
+            false,
+            request("execute_bash", serde_json::json!({ "command": "cargo test" })),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::AutoApproved);
+        assert_eq!(decision.rule, "toolsSettings.execute_bash allows it");
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            None,
+            false,
+            request("@git/git_status", serde_json::json!({})),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::AutoApproved);
+        assert_eq!(decision.rule, "allowedTools has \"@git\"");
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            None,
+            false,
+            request("@github/create_pr", serde_json::json!({})),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::Prompted);
+        assert_eq!(
+            decision.to_string(),
+            "@github/create_pr: prompted (no rule of agent guarded allows it)"
+        );
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            None,
+            true,
+            request("@github/create_pr", serde_json::json!({})),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::AutoApproved);
+
+        assert!(evaluate(&os, &agent, None, false, request("rm_rf", serde_json::json!({}))).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_infra_change() {
+        let os = Os::new().await.unwrap();
+        let agent = Agent {
+            allowed_tools: HashSet::from(["use_aws".to_string()]),
+            ..Default::default()
+        };
+        let deploy = serde_json::json!({
+            "service_name": "cloudformation",
+            "operation_name": "deploy",
+            "parameters": { "stack-name": "api", "template-file": "template.yaml" },
+            "region": "us-east-1",
+            "label": ""
+        });
+
+        let decision = evaluate(&os, &agent, None, true, request("use_aws", deploy)).unwrap();
+        assert_eq!(decision.verdict, Verdict::Prompted);
+        assert_eq!(
+            decision.rule,
+            "cloudformation deploy is an infrastructure change, confirmed every time"
+        );
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            None,
+            true,
+            request("execute_bash", serde_json::json!({ "command": "npx cdk deploy Api" })),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::Prompted);
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            None,
+            true,
+            request("execute_bash", serde_json::json!({ "command": "npx cdk diff Api" })),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::AutoApproved);
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_managed_policy() {
+        let os = Os::new().await.unwrap();
+        let agent = Agent {
+            name: "trusting".to_string(),
+            allowed_tools: HashSet::from(["*".to_string(), "@*".to_string()]),
+            ..Default::default()
+        };
+        let policy = ManagedPolicy::parse(
+            &serde_json::json!({
+                "deniedTools": ["fs_write"],
+                "allowedCommands": ["cargo .*"],
+                "requireApproval": ["mcp"]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            Some(&policy),
+            true,
+            request(
+                "fs_write",
+                serde_json::json!({ "command": "create", "path": "a.txt", "file_text": "a" }),
+            ),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::Denied);
+        assert_eq!(decision.rule, "the managed policy denies \"fs_write\"");
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            Some(&policy),
+            true,
+            request("execute_bash", serde_json::json!({ "command": "curl example.com" })),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::Denied);
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            Some(&policy),
+            true,
+            request("@github/create_pr", serde_json::json!({})),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::Prompted);
+        assert_eq!(decision.rule, "the managed policy requires approval of mcp tools");
+
+        let decision = evaluate(
+            &os,
+            &agent,
+            Some(&policy),
+            false,
+            request("execute_bash", serde_json::json!({ "command": "cargo build" })),
+        )
+        .unwrap();
+        assert_eq!(decision.verdict, Verdict::AutoApproved);
+    }
+}
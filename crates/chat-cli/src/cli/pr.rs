@@ -0,0 +1,510 @@
+//! `q pr`, which writes the description of a pull request, or reviews it, from the changes of a
+//! branch or a commit range, and can post the result to GitHub with `gh` or to CodeCommit.
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::ExitCode;
+
+use anstream::{
+    eprintln,
+    println,
+};
+use clap::{
+    Args,
+    Subcommand,
+    ValueEnum,
+};
+use eyre::{
+    Result,
+    bail,
+    eyre,
+};
+use serde::de::DeserializeOwned;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::OutputFormat;
+use super::generate::{
+    clean_response,
+    complete,
+    model_id,
+};
+use crate::cli::chat::context::truncate_head_tail;
+use crate::cli::chat::git_context::git;
+use crate::os::Os;
+use crate::util::dialoguer_theme;
+
+/// The most tokens of the diff given to the model.
+const MAX_DIFF_TOKENS: usize = 40_000;
+
+/// The branch changes are compared with when the remote has no default branch.
+const DEFAULT_BASE: &str = "main";
+
+const DESCRIBE_PROMPT: &str = "Write the description of a pull request with the changes below.
+
+The title is a short summary of the change, at most 72 characters. The body is markdown with:
+- a summary of what the change does and why, in a few sentences,
+- the notable changes, as a list,
+- how the change can be tested, when the diff shows it.
+
+Do not invent motivations or test results that the commits and the diff don't show.
+
+Reply with JSON only, in the form {\"title\": \"...\", \"body\": \"...\"}.";
+
+const REVIEW_PROMPT: &str = "Review the changes of a pull request below, as a careful senior engineer would.
+
+Look for bugs, security issues, missing error handling, race conditions, performance problems, missing tests, and code that is hard to maintain. Only comment on the changed code, and skip anything that is merely a matter of taste. Each comment has a severity:
+- critical: a bug, security issue or data loss that must be fixed before merging,
+- major: a problem that should be fixed before merging,
+- minor: an improvement worth making,
+- nit: a small style or naming suggestion.
+
+Reply with JSON only, in the form {\"summary\": \"overall assessment in a few sentences\", \"files\": [{\"path\": \"path/of/file\", \"comments\": [{\"line\": 12, \"severity\": \"major\", \"comment\": \"...\"}]}]}. The line is the line of the file after the change, or null when the comment is about the whole file. Leave out files without comments.";
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum PrSubcommand {
+    /// Write the description of a pull request for a branch or commit range
+    Describe(PrArgs),
+    /// Review the changes of a branch or commit range, by file and severity
+    Review(PrArgs),
+}
+
+#[derive(Debug, PartialEq, Args)]
+pub struct PrArgs {
+    /// A branch, compared with the base branch, or a commit range like main..feature. The current
+    /// branch by default
+    pub target: Option<String>,
+    /// The branch a branch is compared with. The default branch of origin by default
+    #[arg(long)]
+    pub base: Option<String>,
+    /// Post the result to the pull request, after confirming
+    #[arg(long, value_enum)]
+    pub post: Option<PostTo>,
+    /// The id of the CodeCommit pull request to post to. On GitHub, the pull request of the branch
+    /// is used by default
+    #[arg(long, required_if_eq("post", "codecommit"))]
+    pub pull_request: Option<String>,
+    /// The model to write with, the default model otherwise
+    #[arg(long)]
+    pub model: Option<String>,
+    /// The format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PostTo {
+    /// A GitHub pull request, with the gh CLI
+    Github,
+    /// An AWS CodeCommit pull request, with the AWS CLI
+    Codecommit,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Description {
+    title: String,
+    body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Review {
+    summary: String,
+    #[serde(default)]
+    files: Vec<FileReview>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FileReview {
+    path: String,
+    #[serde(default)]
+    comments: Vec<ReviewComment>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReviewComment {
+    #[serde(default)]
+    line: Option<u32>,
+    severity: Severity,
+    comment: String,
+}
+
+/// How important a review comment is, most important first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Critical,
+    Major,
+    Minor,
+    Nit,
+}
+
+/// The changes of a pull request.
+#[derive(Debug, PartialEq)]
+struct Changes {
+    /// The range of the diff, from the merge base for a branch.
+    diff_range: String,
+    /// The range of the commits.
+    log_range: String,
+    base: String,
+    head: String,
+}
+
+impl PrSubcommand {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        match self {
+            Self::Describe(args) => {
+                let prompt = args.prompt(os, DESCRIBE_PROMPT).await?;
+                let description = parse_response::<Description>(&args.complete(os, prompt).await?)?;
+                let markdown = format!("# {}\n\n{}", description.title.trim(), description.body.trim());
+                args.format.print(|| &markdown, || &description);
+                if let Some((changes, target)) = args.confirm_post(os).await? {
+                    post_description(&changes, &target, &description).await?;
+                    eprintln!("Posted to {target}");
+                }
+            },
+            Self::Review(args) => {
+                let prompt = args.prompt(os, REVIEW_PROMPT).await?;
+                let mut review = parse_response::<Review>(&args.complete(os, prompt).await?)?;
+                review.sort();
+                let markdown = review.markdown();
+                args.format.print(|| &markdown, || &review);
+                if let Some((_, target)) = args.confirm_post(os).await? {
+                    post_review(&target, &markdown).await?;
+                    eprintln!("Posted to {target}");
+                }
+            },
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+impl PrArgs {
+    async fn changes(&self, dir: &Path) -> Result<Changes> {
+        if git(dir, ["rev-parse", "--git-dir"]).await.is_err() {
+            bail!("The current directory is not in a git repository");
+        }
+        let base = match &self.base {
+            Some(base) => base.clone(),
+            None => git(dir, ["symbolic-ref", "--short", "refs/remotes/origin/HEAD"])
+                .await
+                .map(|base| base.trim().to_string())
+                .unwrap_or_else(|_| DEFAULT_BASE.to_string()),
+        };
+        let current = git(dir, ["rev-parse", "--abbrev-ref", "HEAD"]).await?;
+        Ok(resolve_changes(self.target.as_deref(), &base, current.trim()))
+    }
+
+    /// The prompt to the model, `instructions` followed by the commits and the diff of the changes.
+    async fn prompt(&self, os: &Os, instructions: &str) -> Result<String> {
+        let dir = os.env.current_dir()?;
+        let changes = self.changes(&dir).await?;
+        let diff = git(&dir, ["diff", "--no-color", &changes.diff_range]).await?;
+        if diff.trim().is_empty() {
+            bail!("There are no changes in {}", changes.diff_range);
+        }
+        let commits = git(&dir, ["log", "--format=%h %s%n%b", &changes.log_range]).await?;
+        let stat = git(&dir, ["diff", "--stat", &changes.diff_range]).await?;
+
+        eprintln!("Reading the changes of {} onto {}...", changes.head, changes.base);
+        Ok(format!(
+            "{instructions}\n\nCommits:\n{}\n\nChanged files:\n{}\n\nDiff:\n{}",
+            commits.trim_end(),
+            stat.trim_end(),
+            truncate_head_tail(diff.trim_end(), MAX_DIFF_TOKENS)
+        ))
+    }
+
+    async fn complete(&self, os: &Os, prompt: String) -> Result<String> {
+        let model_id = model_id(os, self.model.as_deref()).await?;
+        complete(os, &model_id, prompt).await
+    }
+
+    /// Where to post the result, if asked to and the user confirms.
+    async fn confirm_post(&self, os: &Os) -> Result<Option<(Changes, PostTarget)>> {
+        let Some(post_to) = self.post else {
+            return Ok(None);
+        };
+        let changes = self.changes(&os.env.current_dir()?).await?;
+        let target = match post_to {
+            PostTo::Github => PostTarget::Github {
+                pull_request: self.pull_request.clone().unwrap_or_else(|| changes.head.clone()),
+            },
+            PostTo::Codecommit => PostTarget::Codecommit {
+                pull_request: self
+                    .pull_request
+                    .clone()
+                    .ok_or_else(|| eyre!("--pull-request is required to post to CodeCommit"))?,
+            },
+        };
+
+        let confirmed = dialoguer::Confirm::with_theme(&dialoguer_theme())
+            .with_prompt(format!("Post this to {target}?"))
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            eprintln!("Nothing was posted");
+            return Ok(None);
+        }
+        Ok(Some((changes, target)))
+    }
+}
+
+/// Where a result is posted.
+enum PostTarget {
+    /// A GitHub pull request, by number, URL, or branch.
+    Github {
+        pull_request: String,
+    },
+    Codecommit {
+        pull_request: String,
+    },
+}
+
+impl std::fmt::Display for PostTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PostTarget::Github { pull_request } => write!(f, "the GitHub pull request of {pull_request}"),
+            PostTarget::Codecommit { pull_request } => write!(f, "CodeCommit pull request {pull_request}"),
+        }
+    }
+}
+
+impl Review {
+    fn sort(&mut self) {
+        for file in &mut self.files {
+            file.comments.sort_by_key(|comment| (comment.severity, comment.line));
+        }
+        self.files
+            .sort_by_key(|file| file.comments.iter().map(|comment| comment.severity).min());
+    }
+
+    fn markdown(&self) -> String {
+        let mut markdown = format!("## Review\n\n{}\n", self.summary.trim());
+        for file in self.files.iter().filter(|file| !file.comments.is_empty()) {
+            let _ = write!(markdown, "\n### `{}`\n\n", file.path);
+            for comment in &file.comments {
+                let severity = serde_json::to_value(comment.severity)
+                    .ok()
+                    .and_then(|severity| severity.as_str().map(str::to_string))
+                    .unwrap_or_default();
+                let _ = match comment.line {
+                    Some(line) => writeln!(markdown, "- **{severity}** (line {line}): {}", comment.comment.trim()),
+                    None => writeln!(markdown, "- **{severity}**: {}", comment.comment.trim()),
+                };
+            }
+        }
+        markdown
+    }
+}
+
+/// The changes of `target`: a commit range as it is, or a branch, the current one by default,
+/// from where it forked off `base`.
+fn resolve_changes(target: Option<&str>, base: &str, current: &str) -> Changes {
+    let target = target.unwrap_or(current);
+    if let Some((from, to)) = target.split_once("...") {
+        return Changes {
+            diff_range: target.to_string(),
+            log_range: format!("{from}..{to}"),
+            base: from.to_string(),
+            head: to.to_string(),
+        };
+    }
+    if let Some((from, to)) = target.split_once("..") {
+        return Changes {
+            diff_range: target.to_string(),
+            log_range: target.to_string(),
+            base: from.to_string(),
+            head: to.to_string(),
+        };
+    }
+    Changes {
+        diff_range: format!("{base}...{target}"),
+        log_range: format!("{base}..{target}"),
+        base: base.to_string(),
+        head: target.to_string(),
+    }
+}
+
+/// The JSON object in a response of the model, ignoring any text around it.
+fn parse_response<T: DeserializeOwned>(response: &str) -> Result<T> {
+    let response = clean_response(response);
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => bail!("The model did not reply with JSON: {response}"),
+    };
+    serde_json::from_str(json).map_err(|err| eyre!("The model replied with invalid JSON: {err}"))
+}
+
+async fn post_description(changes: &Changes, target: &PostTarget, description: &Description) -> Result<()> {
+    match target {
+        PostTarget::Github { pull_request } => {
+            let exists = run("gh", &["pr", "view", pull_request, "--json", "number"])
+                .await
+                .is_ok();
+            match exists {
+                true => {
+                    run("gh", &[
+                        "pr",
+                        "edit",
+                        pull_request,
+                        "--title",
+                        &description.title,
+                        "--body",
+                        &description.body,
+                    ])
+                    .await?
+                },
+                false => {
+                    let base = changes.base.strip_prefix("origin/").unwrap_or(&changes.base);
+                    run("gh", &[
+                        "pr",
+                        "create",
+                        "--head",
+                        &changes.head,
+                        "--base",
+                        base,
+                        "--title",
+                        &description.title,
+                        "--body",
+                        &description.body,
+                    ])
+                    .await?
+                },
+            };
+        },
+        PostTarget::Codecommit { pull_request } => {
+            run("aws", &[
+                "codecommit",
+                "update-pull-request-title",
+                "--pull-request-id",
+                pull_request,
+                "--title",
+                &description.title,
+            ])
+            .await?;
+            run("aws", &[
+                "codecommit",
+                "update-pull-request-description",
+                "--pull-request-id",
+                pull_request,
+                "--description",
+                &description.body,
+            ])
+            .await?;
+        },
+    }
+    Ok(())
+}
+
+async fn post_review(target: &PostTarget, markdown: &str) -> Result<()> {
+    match target {
+        PostTarget::Github { pull_request } => {
+            run("gh", &["pr", "review", pull_request, "--comment", "--body", markdown]).await?;
+        },
+        PostTarget::Codecommit { pull_request } => {
+            // Comments are made on the commits the pull request compares.
+            let pull_request_info = run("aws", &[
+                "codecommit",
+                "get-pull-request",
+                "--pull-request-id",
+                pull_request,
+                "--output",
+                "json",
+            ])
+            .await?;
+            let info = serde_json::from_str::<serde_json::Value>(&pull_request_info)?;
+            let target = &info["pullRequest"]["pullRequestTargets"][0];
+            let field = |name: &str| {
+                target[name]
+                    .as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| eyre!("CodeCommit pull request {pull_request} has no {name}"))
+            };
+            run("aws", &[
+                "codecommit",
+                "post-comment-for-pull-request",
+                "--pull-request-id",
+                pull_request,
+                "--repository-name",
+                &field("repositoryName")?,
+                "--before-commit-id",
+                &field("destinationCommit")?,
+                "--after-commit-id",
+                &field("sourceCommit")?,
+                "--content",
+                markdown,
+            ])
+            .await?;
+        },
+    }
+    Ok(())
+}
+
+/// Runs `program`, returning its output, or an error with it if it failed.
+async fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .output()
+        .await
+        .map_err(|err| eyre!("Failed to run {program}: {err}"))?;
+    if !output.status.success() {
+        bail!(
+            "{program} {} failed: {}",
+            args.iter().take(2).copied().collect::<Vec<_>>().join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_changes() {
+        assert_eq!(resolve_changes(None, "origin/main", "feature"), Changes {
+            diff_range: "origin/main...feature".to_string(),
+            log_range: "origin/main..feature".to_string(),
+            base: "origin/main".to_string(),
+            head: "feature".to_string(),
+        });
+        assert_eq!(resolve_changes(Some("v1.0..v1.1"), "origin/main", "feature"), Changes {
+            diff_range: "v1.0..v1.1".to_string(),
+            log_range: "v1.0..v1.1".to_string(),
+            base: "v1.0".to_string(),
+            head: "v1.1".to_string(),
+        });
+        assert_eq!(
+            resolve_changes(Some("main...fix"), "origin/main", "feature").log_range,
+            "main..fix"
+        );
+    }
+
+    #[test]
+    fn test_review() {
+        let mut review = parse_response::<Review>(
+            "Here is the review:\n```json\n{\"summary\": \"Mostly fine.\", \"files\": [\
+            {\"path\": \"src/a.rs\", \"comments\": [{\"line\": null, \"severity\": \"nit\", \"comment\": \"Rename it.\"}]},\
+            {\"path\": \"src/b.rs\", \"comments\": [\
+            {\"line\": 20, \"severity\": \"minor\", \"comment\": \"Add a test.\"},\
+            {\"line\": 4, \"severity\": \"critical\", \"comment\": \"This can panic.\"}]}]}\n```",
+        )
+        .unwrap();
+        review.sort();
+        assert_eq!(
+            review.markdown(),
+            "## Review\n\nMostly fine.\n\n\
+            ### `src/b.rs`\n\n\
+            - **critical** (line 4): This can panic.\n\
+            - **minor** (line 20): Add a test.\n\n\
+            ### `src/a.rs`\n\n\
+            - **nit**: Rename it.\n"
+        );
+
+        assert!(parse_response::<Review>("I could not review it.").is_err());
+    }
+}
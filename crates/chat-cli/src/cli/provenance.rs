@@ -0,0 +1,175 @@
+//! Which lines of a file were written by the agent, recorded from the `fs_write` tool uses of
+//! every chat turn, for teams with policies on attributing AI-authored code.
+
+use std::fmt::Write as _;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::ExitCode;
+
+use clap::Args;
+use eyre::Result;
+use serde::Serialize;
+use tracing::warn;
+
+use super::OutputFormat;
+use crate::cli::ConversationState;
+use crate::database::provenance::{
+    ProvenanceEntry,
+    hash_content,
+};
+use crate::os::Os;
+use crate::util::directories::canonicalizes_path;
+use crate::util::time::format_timestamp;
+
+#[derive(Debug, PartialEq, Args)]
+pub struct ProvenanceArgs {
+    /// The file to show the agent-authored lines of
+    pub path: PathBuf,
+    /// The format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    path: String,
+    /// Whether the file changed since the agent last wrote it, so that the lines of the entries
+    /// may have shifted.
+    changed_since: bool,
+    entries: Vec<ProvenanceEntry>,
+}
+
+impl ProvenanceArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let path = canonicalizes_path(os, &self.path.to_string_lossy())?;
+        let entries = os.database.get_provenance(&path)?;
+        let current_hash = os
+            .fs
+            .read_to_string(&path)
+            .await
+            .ok()
+            .map(|content| hash_content(&content));
+        let report = Report {
+            changed_since: entries
+                .last()
+                .is_some_and(|last| current_hash.as_deref() != Some(last.file_hash.as_str())),
+            path,
+            entries,
+        };
+
+        self.format.print(|| format_report(&report), || &report);
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Records the lines written by a successful `fs_write` tool use, without failing it.
+pub async fn record(
+    os: &Os,
+    conversation: &ConversationState,
+    path: &Path,
+    lines: Vec<(usize, usize)>,
+    tool_use_id: &str,
+) {
+    if lines.is_empty() {
+        return;
+    }
+
+    let entry = async {
+        Ok::<_, eyre::Report>(ProvenanceEntry {
+            path: canonicalizes_path(os, &path.to_string_lossy())?,
+            lines,
+            file_hash: hash_content(&os.fs.read_to_string(path).await?),
+            conversation_id: conversation.conversation_id().to_string(),
+            message_id: conversation.message_id().map(str::to_string),
+            tool_use_id: tool_use_id.to_string(),
+            model_id: conversation.model_info.as_ref().map(|model| model.model_id.clone()),
+            recorded_at: chrono::Utc::now(),
+        })
+    };
+    match entry.await {
+        Ok(entry) => {
+            if let Err(err) = os.database.record_provenance(&entry) {
+                warn!(?err, "failed to record the provenance of a write");
+            }
+        },
+        Err(err) => warn!(?err, ?path, "failed to record the provenance of a write"),
+    }
+}
+
+fn format_report(report: &Report) -> String {
+    if report.entries.is_empty() {
+        return format!("No lines of {} were written by the agent", report.path);
+    }
+
+    let mut out = format!("Lines of {} written by the agent:\n", report.path);
+    for entry in &report.entries {
+        let lines = entry
+            .lines
+            .iter()
+            .map(|(start, end)| match start == end {
+                true => start.to_string(),
+                false => format!("{start}-{end}"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(out, "\n  {lines}");
+        let _ = writeln!(out, "    at {}", format_timestamp(&entry.recorded_at));
+        let _ = writeln!(out, "    conversation {}", entry.conversation_id);
+        if let Some(message_id) = &entry.message_id {
+            let _ = writeln!(out, "    message {message_id}");
+        }
+        let _ = writeln!(out, "    tool use {}", entry.tool_use_id);
+        if let Some(model_id) = &entry.model_id {
+            let _ = writeln!(out, "    model {model_id}");
+        }
+    }
+
+    let _ = write!(out, "\nLines are numbered as they were right after each write.");
+    if report.changed_since {
+        let _ = write!(
+            out,
+            " The file has changed since the agent last wrote it, so they may have shifted."
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let mut report = Report {
+            path: "/repo/src/main.rs".to_string(),
+            changed_since: false,
+            entries: vec![],
+        };
+        assert_eq!(
+            format_report(&report),
+            "No lines of /repo/src/main.rs were written by the agent"
+        );
+
+        report.changed_since = true;
+        report.entries.push(ProvenanceEntry {
+            path: report.path.clone(),
+            lines: vec![(1, 3), (7, 7)],
+            file_hash: hash_content(""),
+            conversation_id: "conversation".to_string(),
+            message_id: None,
+            tool_use_id: "tooluse_1".to_string(),
+            model_id: Some("claude-sonnet-4".to_string()),
+            recorded_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        });
+        let out = format_report(&report);
+        assert!(out.contains("\n  1-3, 7\n"));
+        assert!(out.contains("tool use tooluse_1"));
+        assert!(!out.contains("message"));
+        assert!(out.ends_with("so they may have shifted."));
+    }
+}
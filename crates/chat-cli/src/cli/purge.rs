@@ -0,0 +1,472 @@
+//! Deleting the data kept on this machine past the limits of the `retention.*` settings: saved
+//! conversations, log files, checkpoints, and the telemetry events recorded for
+//! `q telemetry show`.
+//!
+//! A purge pass runs when a chat session starts, `q purge --dry-run` reports what it would delete.
+
+use std::fmt::Write as _;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+use clap::Args;
+use eyre::Result;
+use serde::Serialize;
+use tracing::{
+    debug,
+    warn,
+};
+
+use super::OutputFormat;
+use crate::cli::agent::registry::running_agents;
+use crate::cli::chat::checkpoint::{
+    dir_size_and_modified,
+    is_shadow_repo,
+};
+use crate::cli::chat::resource_monitor::format_bytes;
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+use crate::os::Os;
+use crate::telemetry::event_log;
+use crate::util::directories::{
+    logs_dir,
+    shadow_repos_dir,
+    telemetry_events_path,
+};
+
+const DAY: Duration = Duration::from_secs(86400);
+const MB: u64 = 1024 * 1024;
+
+/// Log files that are written to while q runs, and are capped in size when opened instead.
+const ACTIVE_LOG_FILES: &[&str] = &["qchat.log", "mcp.log"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DataKind {
+    Conversations,
+    Logs,
+    Checkpoints,
+    TelemetryEvents,
+}
+
+impl DataKind {
+    const ALL: [Self; 4] = [
+        Self::Conversations,
+        Self::Logs,
+        Self::Checkpoints,
+        Self::TelemetryEvents,
+    ];
+
+    fn policy(self, settings: &Settings) -> Policy {
+        let (max_age, max_size) = match self {
+            Self::Conversations => (
+                Setting::RetentionConversationsMaxAgeDays,
+                Some(Setting::RetentionConversationsMaxSizeMb),
+            ),
+            Self::Logs => (Setting::RetentionLogsMaxAgeDays, Some(Setting::RetentionLogsMaxSizeMb)),
+            // Their size is already capped by chat.checkpoint.maxSizeMb.
+            Self::Checkpoints => (Setting::RetentionCheckpointsMaxAgeDays, None),
+            // Their number is already capped by the event log.
+            Self::TelemetryEvents => (Setting::RetentionTelemetryMaxAgeDays, None),
+        };
+        // A limit too large to represent is no limit, rather than one that wraps around to a small
+        // one and deletes nearly everything.
+        let limit = |setting: Setting, unit: u64| {
+            let limit = settings.get_int(setting).filter(|limit| *limit > 0)?;
+            let limit = u64::try_from(limit).ok().and_then(|limit| limit.checked_mul(unit));
+            if limit.is_none() {
+                warn!(%setting, "Ignoring a retention limit that is out of range");
+            }
+            limit
+        };
+        Policy {
+            max_age: limit(max_age, DAY.as_secs()).map(Duration::from_secs),
+            max_size: max_size.and_then(|max_size| limit(max_size, MB)),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Conversations => "conversation",
+            Self::Logs => "log file",
+            Self::Checkpoints => "checkpoint",
+            Self::TelemetryEvents => "telemetry event",
+        }
+    }
+}
+
+/// How long, and how much, of a kind of data is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Policy {
+    max_age: Option<Duration>,
+    /// In bytes, for all the data of the kind.
+    max_size: Option<u64>,
+}
+
+/// A conversation, file or event that may be deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Candidate {
+    /// The path of the conversation, file or directory, or the type of the event.
+    id: String,
+    size: u64,
+    modified: SystemTime,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Reason {
+    /// Older than the maximum age.
+    Age,
+    /// Among the oldest data past the maximum size.
+    Size,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Expired {
+    pub kind: DataKind,
+    pub id: String,
+    pub size: u64,
+    pub reason: Reason,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurgeReport {
+    pub dry_run: bool,
+    /// Whether any retention setting is set.
+    pub enabled: bool,
+    pub expired: Vec<Expired>,
+}
+
+#[derive(Debug, PartialEq, Args)]
+pub struct PurgeArgs {
+    /// Report what would be deleted, without deleting it
+    #[arg(long)]
+    pub dry_run: bool,
+    /// The format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+impl PurgeArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let report = purge(os, self.dry_run).await?;
+        self.format.print(|| format_report(&report), || &report);
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// The purge pass run when a chat session starts, which never fails it.
+pub async fn purge_on_startup(os: &mut Os) {
+    match purge(os, false).await {
+        Ok(report) if !report.expired.is_empty() => debug!(?report, "purged data past its retention"),
+        Ok(_) => (),
+        Err(err) => warn!(?err, "failed to purge data past its retention"),
+    }
+}
+
+/// Deletes the data past the limits of the retention settings, unless `dry_run`.
+pub async fn purge(os: &mut Os, dry_run: bool) -> Result<PurgeReport> {
+    let now = SystemTime::now();
+    let mut report = PurgeReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    for kind in DataKind::ALL {
+        let policy = kind.policy(&os.database.settings);
+        if policy == Policy::default() {
+            continue;
+        }
+        report.enabled = true;
+
+        let expired = select(candidates(os, kind).await?, policy, now);
+        if !dry_run && !expired.is_empty() {
+            delete(os, kind, &expired, policy, now).await?;
+        }
+        report
+            .expired
+            .extend(expired.into_iter().map(|(candidate, reason)| Expired {
+                kind,
+                id: candidate.id,
+                size: candidate.size,
+                reason,
+            }));
+    }
+    Ok(report)
+}
+
+/// The candidates to delete under `policy`: those older than its maximum age, and then the
+/// oldest ones until the rest fit in its maximum size.
+fn select(mut candidates: Vec<Candidate>, policy: Policy, now: SystemTime) -> Vec<(Candidate, Reason)> {
+    candidates.sort_by_key(|candidate| candidate.modified);
+    let mut total = candidates.iter().map(|candidate| candidate.size).sum::<u64>();
+
+    let mut expired = Vec::new();
+    for candidate in candidates {
+        let age = now.duration_since(candidate.modified).unwrap_or_default();
+        let reason = match (
+            policy.max_age.is_some_and(|max_age| age > max_age),
+            policy.max_size.is_some_and(|max_size| total > max_size),
+        ) {
+            (true, _) => Reason::Age,
+            (false, true) => Reason::Size,
+            // The rest are newer, and fit.
+            (false, false) => break,
+        };
+        total -= candidate.size;
+        expired.push((candidate, reason));
+    }
+    expired
+}
+
+async fn candidates(os: &Os, kind: DataKind) -> Result<Vec<Candidate>> {
+    Ok(match kind {
+        DataKind::Conversations => os
+            .database
+            .get_all_conversations()?
+            .into_iter()
+            .map(|(path, conversation)| Candidate {
+                size: serde_json::to_string(&conversation).map_or(0, |json| json.len() as u64),
+                modified: conversation.last_activity_or_modified(&path),
+                id: path,
+            })
+            .collect(),
+        DataKind::Logs => {
+            let session_log = crate::logging::session_log_path();
+            // Session logs are named after their conversation, and are still written to while it
+            // runs in another process.
+            let running = running_agents()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|entry| entry.conversation_id)
+                .collect::<Vec<_>>();
+            walkdir::WalkDir::new(logs_dir()?)
+                .into_iter()
+                .flatten()
+                .filter(|entry| {
+                    entry.file_type().is_file()
+                        && !(entry.depth() == 1
+                            && ACTIVE_LOG_FILES.contains(&entry.file_name().to_string_lossy().as_ref()))
+                        && session_log.as_deref() != Some(entry.path())
+                        && !entry
+                            .path()
+                            .file_stem()
+                            .is_some_and(|stem| running.iter().any(|id| stem == id.as_str()))
+                })
+                .filter_map(|entry| {
+                    let metadata = entry.metadata().ok()?;
+                    Some(Candidate {
+                        id: entry.path().to_string_lossy().into_owned(),
+                        size: metadata.len(),
+                        modified: metadata.modified().ok()?,
+                    })
+                })
+                .collect()
+        },
+        // Laid out as `<root>/<workspace>/<conversation id>`.
+        DataKind::Checkpoints => walkdir::WalkDir::new(shadow_repos_dir(os)?)
+            .min_depth(2)
+            .max_depth(2)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_dir() && is_shadow_repo(entry.path()))
+            .map(|entry| {
+                let (size, modified) = dir_size_and_modified(entry.path());
+                Candidate {
+                    id: entry.path().to_string_lossy().into_owned(),
+                    size,
+                    modified,
+                }
+            })
+            .collect(),
+        DataKind::TelemetryEvents => event_log::read_last(&telemetry_events_path()?, usize::MAX)
+            .await?
+            .into_iter()
+            .map(|entry| Candidate {
+                id: entry.event_type().to_string(),
+                size: 0,
+                modified: entry.recorded_at.into(),
+            })
+            .collect(),
+    })
+}
+
+async fn delete(
+    os: &mut Os,
+    kind: DataKind,
+    expired: &[(Candidate, Reason)],
+    policy: Policy,
+    now: SystemTime,
+) -> Result<()> {
+    for (candidate, _) in expired {
+        match kind {
+            DataKind::Conversations => os.database.delete_conversation_by_path(&candidate.id)?,
+            DataKind::Logs => tokio::fs::remove_file(&candidate.id).await?,
+            DataKind::Checkpoints => {
+                let path = Path::new(&candidate.id);
+                if !is_shadow_repo(path) {
+                    continue;
+                }
+                tokio::fs::remove_dir_all(path).await?;
+                if let Some(workspace) = path.parent() {
+                    // Only succeeds once the workspace has no checkpoints left.
+                    let _ = tokio::fs::remove_dir(workspace).await;
+                }
+            },
+            // Events are dropped all at once, below.
+            DataKind::TelemetryEvents => (),
+        }
+    }
+
+    if let (DataKind::TelemetryEvents, Some(max_age)) = (kind, policy.max_age) {
+        if let Some(cutoff) = now.checked_sub(max_age) {
+            event_log::retain(&telemetry_events_path()?, |entry| {
+                SystemTime::from(entry.recorded_at) >= cutoff
+            })
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+fn format_report(report: &PurgeReport) -> String {
+    if !report.enabled {
+        return "No retention.* settings are set, so nothing is purged. Set one with, e.g.: q settings retention.logs.maxAgeDays 30"
+            .to_string();
+    }
+    if report.expired.is_empty() {
+        return "Nothing is past its retention".to_string();
+    }
+
+    let total = report.expired.iter().map(|expired| expired.size).sum::<u64>();
+    let mut out = format!(
+        "{} {} item(s), {}:\n",
+        if report.dry_run { "Would delete" } else { "Deleted" },
+        report.expired.len(),
+        format_bytes(total),
+    );
+    for expired in &report.expired {
+        let reason = match expired.reason {
+            Reason::Age => "too old",
+            Reason::Size => "over the size limit",
+        };
+        let _ = writeln!(out, "  {:<16} {}  ({reason})", expired.kind.name(), expired.id);
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: &str, size: u64, days_old: u64, now: SystemTime) -> Candidate {
+        Candidate {
+            id: id.to_string(),
+            size,
+            modified: now - DAY * days_old as u32,
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let now = SystemTime::now();
+        let candidates = vec![
+            candidate("new", 10, 1, now),
+            candidate("old", 10, 40, now),
+            candidate("middle", 10, 10, now),
+        ];
+
+        let ids = |policy| {
+            select(candidates.clone(), policy, now)
+                .into_iter()
+                .map(|(candidate, reason)| (candidate.id, reason))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(Policy::default()), vec![]);
+        assert_eq!(
+            ids(Policy {
+                max_age: Some(DAY * 30),
+                max_size: None,
+            }),
+            vec![("old".to_string(), Reason::Age)]
+        );
+        assert_eq!(
+            ids(Policy {
+                max_age: Some(DAY * 30),
+                max_size: Some(10),
+            }),
+            vec![("old".to_string(), Reason::Age), ("middle".to_string(), Reason::Size)]
+        );
+        assert_eq!(
+            ids(Policy {
+                max_age: None,
+                max_size: Some(25),
+            }),
+            vec![("old".to_string(), Reason::Size)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_policy() {
+        let mut os = Os::new().await.unwrap();
+        assert_eq!(DataKind::Logs.policy(&os.database.settings), Policy::default());
+
+        os.database
+            .settings
+            .set(Setting::RetentionLogsMaxAgeDays, 30)
+            .await
+            .unwrap();
+        os.database
+            .settings
+            .set(Setting::RetentionLogsMaxSizeMb, 5)
+            .await
+            .unwrap();
+        assert_eq!(DataKind::Logs.policy(&os.database.settings), Policy {
+            max_age: Some(DAY * 30),
+            max_size: Some(5 * MB),
+        });
+        assert_eq!(DataKind::Checkpoints.policy(&os.database.settings), Policy::default());
+
+        // Large limits aren't truncated to small ones, and out of range ones are no limits.
+        os.database
+            .settings
+            .set(Setting::RetentionConversationsMaxAgeDays, 4294967297_u64)
+            .await
+            .unwrap();
+        os.database
+            .settings
+            .set(Setting::RetentionConversationsMaxSizeMb, i64::MAX)
+            .await
+            .unwrap();
+        assert_eq!(DataKind::Conversations.policy(&os.database.settings), Policy {
+            max_age: Some(Duration::from_secs(4294967297 * 86400)),
+            max_size: None,
+        });
+    }
+
+    #[test]
+    fn test_format_report() {
+        assert!(format_report(&PurgeReport::default()).starts_with("No retention.* settings are set"));
+
+        let report = PurgeReport {
+            dry_run: true,
+            enabled: true,
+            expired: vec![Expired {
+                kind: DataKind::Conversations,
+                id: "/home/user/project".to_string(),
+                size: 2 * MB,
+                reason: Reason::Age,
+            }],
+        };
+        let out = format_report(&report);
+        assert!(out.starts_with("Would delete 1 item(s)"));
+        assert!(out.contains("conversation     /home/user/project  (too old)"));
+    }
+}
@@ -1,3 +1,5 @@
+use std::io::IsTerminal;
+use std::path::PathBuf;
 use std::process::ExitCode;
 
 use anstream::println;
@@ -12,10 +14,24 @@ use eyre::{
     bail,
 };
 use globset::Glob;
-use serde_json::json;
+use serde::Serialize;
+use serde_json::{
+    Value,
+    json,
+};
+use strum::{
+    EnumMessage,
+    IntoEnumIterator,
+};
 
-use super::OutputFormat;
-use crate::database::settings::Setting;
+use super::{
+    OutputFormat,
+    config_archive,
+};
+use crate::database::settings::{
+    Setting,
+    SettingType,
+};
 use crate::os::Os;
 use crate::util::directories;
 
@@ -32,6 +48,50 @@ pub enum SettingsSubcommands {
         #[arg(long, short, hide = true)]
         state: bool,
     },
+    /// List the settings that are set, with their type and default
+    List {
+        /// List every known setting, including those that are not set
+        #[arg(long, short)]
+        all: bool,
+        /// Format of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Check the settings file for unknown or deprecated keys and invalid values
+    Doctor {
+        /// Format of the output
+        #[arg(long, short, value_enum, default_value_t)]
+        format: OutputFormat,
+    },
+    /// Export the settings, global agents, prompts and MCP servers to an archive
+    Export {
+        /// The archive to write
+        file: PathBuf,
+        /// Encrypt the archive with a passphrase, read from Q_CONFIG_PASSPHRASE or asked for
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// Import an archive written by `q settings export`
+    Import {
+        /// The archive to read
+        file: PathBuf,
+        /// Replace the settings and files that already exist, and import agents and settings that
+        /// change what q runs or connects to without asking
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SettingEntry {
+    key: String,
+    /// `None` for keys that are not known settings.
+    #[serde(rename = "type")]
+    ty: Option<String>,
+    default: Option<Value>,
+    value: Option<Value>,
+    description: Option<&'static str>,
 }
 
 #[derive(Clone, Debug, Args, PartialEq, Eq)]
@@ -85,6 +145,93 @@ impl SettingsArgs {
 
                 Ok(ExitCode::SUCCESS)
             },
+            Some(SettingsSubcommands::List { all, format }) => {
+                let settings = os.database.settings.map();
+                let mut entries = Setting::iter()
+                    .filter(|setting| all || settings.contains_key(setting.as_ref()))
+                    .map(|setting| SettingEntry {
+                        key: setting.to_string(),
+                        ty: Some(setting.setting_type().to_string()),
+                        default: setting.default_value(),
+                        value: settings.get(setting.as_ref()).cloned(),
+                        description: setting.get_message(),
+                    })
+                    .collect::<Vec<_>>();
+                // Unknown keys are listed too, so that nothing in the file is hidden.
+                entries.extend(
+                    settings
+                        .iter()
+                        .filter(|(key, _)| Setting::try_from(key.as_str()).is_err())
+                        .map(|(key, value)| SettingEntry {
+                            key: key.clone(),
+                            ty: None,
+                            default: None,
+                            value: Some(value.clone()),
+                            description: None,
+                        }),
+                );
+                entries.sort_by(|a, b| a.key.cmp(&b.key));
+
+                format.print(|| format_entries(&entries), || &entries);
+                Ok(ExitCode::SUCCESS)
+            },
+            Some(SettingsSubcommands::Doctor { format }) => {
+                let issues = os.database.settings.diagnose();
+                format.print(
+                    || match issues.is_empty() {
+                        true => "No problems found in the settings".to_string(),
+                        false => issues
+                            .iter()
+                            .map(|issue| format!("- {issue}"))
+                            .collect::<Vec<_>>()
+                            .join("\n"),
+                    },
+                    || &issues,
+                );
+
+                Ok(match issues.is_empty() {
+                    true => ExitCode::SUCCESS,
+                    false => ExitCode::FAILURE,
+                })
+            },
+            Some(SettingsSubcommands::Export { ref file, encrypt }) => {
+                let count = config_archive::export(os, file, encrypt).await?;
+                println!("Exported {count} files to {}", file.display());
+                Ok(ExitCode::SUCCESS)
+            },
+            Some(SettingsSubcommands::Import { ref file, force }) => {
+                let summary = config_archive::import(os, file, force, |changes| {
+                    if !std::io::stdin().is_terminal() {
+                        return Ok(false);
+                    }
+                    println!("The archive changes what q runs or connects to:");
+                    for name in changes {
+                        println!("  {name}");
+                    }
+                    Ok(dialoguer::Confirm::with_theme(&crate::util::dialoguer_theme())
+                        .with_prompt("Import these?")
+                        .default(false)
+                        .interact()
+                        .unwrap_or(false))
+                })
+                .await?;
+                for name in &summary.imported {
+                    println!("Imported {name}");
+                }
+                for name in &summary.kept {
+                    println!("Kept the existing {name}");
+                }
+                for (name, reason) in &summary.skipped {
+                    println!("Skipped {name}: {reason}");
+                }
+                if summary.imported.is_empty() && summary.kept.is_empty() && summary.skipped.is_empty() {
+                    println!("Everything in {} is already imported", file.display());
+                }
+                if !summary.kept.is_empty() {
+                    println!("\nRun again with --force to replace what was kept");
+                }
+                Ok(ExitCode::SUCCESS)
+            },
             None => {
                 let Some(key) = &self.key else {
                     return Ok(ExitCode::SUCCESS);
@@ -113,7 +260,19 @@ impl SettingsArgs {
                         },
                     },
                     (Some(value_str), false) => {
-                        let value = serde_json::from_str(value_str).unwrap_or_else(|_| json!(value_str));
+                        let value = match serde_json::from_str(value_str) {
+                            // Strings don't need quoting, even when they would parse as another type.
+                            Ok(Value::String(value)) => json!(value),
+                            _ if matches!(
+                                key.setting_type(),
+                                SettingType::String | SettingType::Char | SettingType::Enum { .. }
+                            ) =>
+                            {
+                                json!(value_str)
+                            },
+                            Ok(value) => value,
+                            Err(_) => json!(value_str),
+                        };
                         os.database.settings.set(key, value).await?;
                         Ok(ExitCode::SUCCESS)
                     },
@@ -153,3 +312,43 @@ impl SettingsArgs {
         }
     }
 }
+
+/// One line per setting, with its type, default and value aligned in columns.
+fn format_entries(entries: &[SettingEntry]) -> String {
+    let rows = entries
+        .iter()
+        .map(|entry| {
+            let show = |value: &Option<Value>| value.as_ref().map_or("-".to_string(), Value::to_string);
+            [
+                entry.key.clone(),
+                entry.ty.clone().unwrap_or_else(|| "unknown".to_string()),
+                show(&entry.default),
+                show(&entry.value),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let header = ["KEY", "TYPE", "DEFAULT", "VALUE"].map(str::to_string);
+    let widths = (0..3)
+        .map(|i| rows.iter().chain([&header]).map(|row| row[i].len()).max().unwrap_or(0))
+        .collect::<Vec<_>>();
+    [header]
+        .iter()
+        .chain(&rows)
+        .map(|row| {
+            format!(
+                "{:w0$}  {:w1$}  {:w2$}  {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2]
+            )
+            .trim_end()
+            .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
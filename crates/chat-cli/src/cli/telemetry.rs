@@ -0,0 +1,143 @@
+//! Auditing the telemetry sent by this machine, from the local log of the most recent events.
+
+use std::fmt::Write as _;
+use std::process::ExitCode;
+
+use clap::{
+    Args,
+    Subcommand,
+};
+use eyre::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::OutputFormat;
+use crate::database::settings::Setting;
+use crate::os::Os;
+use crate::telemetry::event_log::{
+    self,
+    LoggedEvent,
+};
+use crate::util::directories;
+use crate::util::time::{
+    format_timestamp,
+    humanize,
+};
+
+#[derive(Debug, PartialEq, Subcommand)]
+pub enum TelemetrySubcommand {
+    /// Print the most recent telemetry events, and where they were sent
+    Show(ShowArgs),
+}
+
+impl TelemetrySubcommand {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        match self {
+            Self::Show(args) => args.execute(os).await,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Args)]
+pub struct ShowArgs {
+    /// How many events to print
+    #[arg(long, default_value_t = 20)]
+    pub last: usize,
+    /// The format of the output
+    #[arg(long, short, value_enum, default_value_t)]
+    pub format: OutputFormat,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    telemetry_enabled: bool,
+    events: Vec<LoggedEvent>,
+}
+
+impl ShowArgs {
+    pub async fn execute(self, os: &mut Os) -> Result<ExitCode> {
+        let report = Report {
+            telemetry_enabled: os.env.get_os("Q_DISABLE_TELEMETRY").is_none()
+                && os.database.settings.get_bool(Setting::TelemetryEnabled).unwrap_or(true),
+            events: event_log::read_last(&directories::telemetry_events_path()?, self.last).await?,
+        };
+        self.format.print(|| format_report(&report), || &report);
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn format_report(report: &Report) -> String {
+    let mut out = match report.telemetry_enabled {
+        true => "Telemetry is enabled, disable it with: q settings telemetry.enabled false, or a category of \
+                 events with telemetry.usage, telemetry.errors, telemetry.chatMetadata or telemetry.completions\n"
+            .to_string(),
+        false => "Telemetry is disabled\n".to_string(),
+    };
+    if report.events.is_empty() {
+        out.push_str("\nNo telemetry events were recorded yet");
+        return out;
+    }
+
+    for entry in &report.events {
+        let sent_to = match entry.sent_to.is_empty() {
+            true => "not sent".to_string(),
+            false => format!("sent to {}", entry.sent_to.join(", ")),
+        };
+        let category = entry
+            .category
+            .map(|category| format!(" [{category}]"))
+            .unwrap_or_default();
+        let _ = write!(
+            out,
+            "\n{} ({})  {}{category}  {sent_to}\n",
+            format_timestamp(&entry.recorded_at),
+            humanize(&entry.recorded_at),
+            entry.event_type(),
+        );
+        let Some(fields) = entry.event.as_object() else {
+            continue;
+        };
+        for (key, value) in fields {
+            if key == "type" || key == "createdTime" || value.is_null() {
+                continue;
+            }
+            let value = match value {
+                Value::String(s) => s.clone(),
+                value => value.to_string(),
+            };
+            let _ = writeln!(out, "  {key}: {value}");
+        }
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::EventType;
+    use crate::telemetry::core::Event;
+
+    #[test]
+    fn test_format_report() {
+        let event = Event::new(EventType::CliSubcommandExecuted {
+            subcommand: "chat".to_string(),
+        });
+        let report = Report {
+            telemetry_enabled: false,
+            events: vec![LoggedEvent::new(&event, vec![])],
+        };
+        let out = format_report(&report);
+        assert!(out.starts_with("Telemetry is disabled\n"));
+        assert!(out.contains("cliSubcommandExecuted [usage]  not sent\n"));
+        assert!(out.contains("  subcommand: chat"));
+        assert!(!out.contains("createdTime"));
+        assert!(!out.contains("credentialStartUrl"));
+
+        let report = Report {
+            telemetry_enabled: true,
+            events: vec![],
+        };
+        assert!(format_report(&report).ends_with("No telemetry events were recorded yet"));
+    }
+}
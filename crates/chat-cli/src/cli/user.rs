@@ -369,18 +369,27 @@ async fn select_profile_interactive(os: &mut Os, whoami: bool) -> Result<()> {
         .collect();
     let active_profile = os.database.get_auth_profile()?;
 
-    if let Some(default_idx) = active_profile
+    let active_idx = active_profile
         .as_ref()
-        .and_then(|active| profiles.iter().position(|p| p.arn == active.arn))
-    {
-        items[default_idx] = format!("{} (active)", items[default_idx].as_str());
+        .and_then(|active| profiles.iter().position(|p| p.arn == active.arn));
+    if let Some(active_idx) = active_idx {
+        items[active_idx] = format!("{} (active)", items[active_idx].as_str());
     }
 
+    // Without an active profile, preselect a profile in the region of the SSO session.
+    let default_idx = active_idx
+        .or_else(|| {
+            sso_region
+                .as_deref()
+                .and_then(|region| profiles.iter().position(|p| p.arn.split(':').nth(3) == Some(region)))
+        })
+        .unwrap_or(0);
+
     spinner.stop_with_message(String::new());
     let selected = Select::with_theme(&crate::util::dialoguer_theme())
         .with_prompt("Select an IAM Identity Center profile")
         .items(&items)
-        .default(0)
+        .default(default_idx)
         .interact_opt()?;
 
     match selected {
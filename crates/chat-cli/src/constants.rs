@@ -48,13 +48,14 @@ Learn how: https://docs.aws.amazon.com/amazonq/latest/qdeveloper-ug/qdev-mcp.htm
 <em>!{command}</em>          <black!>Quickly execute a command in your current session</black!>
 <em>Ctrl(^) + j</em>         <black!>Insert new-line to provide multi-line prompt</black!>
                     <black!>Alternatively, [Alt(⌥) + Enter(⏎)]</black!>
+                    <black!>Enter also adds a line inside an unclosed ``` code block</black!>
 <em>Ctrl(^) + s</em>         <black!>Fuzzy search commands and context files</black!>
                     <black!>Use Tab to select multiple items</black!>
                     <black!>Change the keybind using: q settings chat.skimCommandKey x</black!>
 <em>Ctrl(^) + t</em>         <black!>Toggle tangent mode for isolated conversations</black!>
                     <black!>Change the keybind using: q settings chat.tangentModeKey x</black!>
-<em>chat.editMode</em>       <black!>The prompt editing mode (vim or emacs)</black!>
-                    <black!>Change using: q settings chat.skimCommandKey x</black!>
+<em>chat.editMode</em>       <black!>The prompt editing mode (vi or emacs)</black!>
+                    <black!>Change using: q settings chat.editMode vi</black!>
 "};
 
     /// Welcome text with ASCII art logo for large screens
@@ -95,6 +96,8 @@ more accurate and helpful responses.
 
 Notes:
 • You can add specific files or use glob patterns (e.g., \"*.py\", \"src/**/*.js\")
+• Rules are re-resolved on every message, so new files matching a pattern are picked up automatically
+• Use --budget to cap the tokens a rule may use, and --watch to be notified when its matches change
 • Agent rules apply only to the current agent 
 • Context changes are NOT preserved between chat sessions. To make these changes permanent, edit the agent config file.", super::PRODUCT_NAME, super::PRODUCT_NAME)
     }
@@ -124,7 +127,7 @@ Notes:
 /// Tips and rotating messages
 pub mod tips {
     /// Array of rotating tips shown to users
-    pub const ROTATING_TIPS: [&str; 20] = [
+    pub const ROTATING_TIPS: [&str; 22] = [
         color_print::cstr! {"You can resume the last conversation from your current directory by launching with
         <green!>q chat --resume</green!>"},
         color_print::cstr! {"Get notified whenever Amazon Q CLI finishes responding.
@@ -132,6 +135,7 @@ pub mod tips {
         color_print::cstr! {"You can use
         <green!>/editor</green!> to edit your prompt with a vim-like experience"},
         color_print::cstr! {"<green!>/usage</green!> shows you a visual breakdown of your current context window usage"},
+        color_print::cstr! {"<green!>/cost</green!> shows the tokens used so far this session and an estimated cost"},
         color_print::cstr! {"Get notified whenever Amazon Q CLI finishes responding. Just run <green!>q settings
         chat.enableNotifications true</green!>"},
         color_print::cstr! {"You can execute bash commands by typing
@@ -159,6 +163,7 @@ pub mod tips {
         color_print::cstr! {"Use <green!>/tangent</green!> or <green!>ctrl + t</green!> (customizable) to start isolated conversations ( ↯ ) that don't affect your main chat history"},
         color_print::cstr! {"Ask me directly about my capabilities! Try questions like <green!>\"What can you do?\"</green!> or <green!>\"Can you save conversations?\"</green!>"},
         color_print::cstr! {"Stay up to date with the latest features and improvements! Use <green!>/changelog</green!> to see what's new in Amazon Q CLI"},
+        color_print::cstr! {"New here? Run <green!>/tour</green!> for a guided tour of context, tools, checkpoints and agents"},
         color_print::cstr! {"Enable workspace checkpoints to snapshot & restore changes. Just run <green!>q</green!> <green!>settings chat.enableCheckpoint true</green!>"},
     ];
 }
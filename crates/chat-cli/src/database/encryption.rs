@@ -0,0 +1,253 @@
+//! Encryption of saved conversations at rest, enabled with the `chat.encryptConversations`
+//! setting.
+//!
+//! Conversations are sealed with AES-256-GCM. The key is kept in the OS keychain, and where there
+//! is none, such as on a headless Linux host, it is derived from a passphrase instead.
+
+use std::io::IsTerminal;
+use std::num::NonZeroU32;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ring::aead::{
+    AES_256_GCM,
+    Aad,
+    LessSafeKey,
+    NONCE_LEN,
+    Nonce,
+    UnboundKey,
+};
+use ring::pbkdf2;
+use ring::rand::{
+    SecureRandom,
+    SystemRandom,
+};
+use tracing::warn;
+
+/// Marks an encrypted value, followed by the base64 of the nonce and the ciphertext.
+const ENCRYPTED_PREFIX: &str = "enc:v1:";
+/// Encrypted and saved when the key is first used, see [Cipher::verifier].
+const VERIFIER_PLAINTEXT: &str = "amazon-q-conversations";
+const KEYCHAIN_SERVICE: &str = "amazon-q";
+const KEYCHAIN_ACCOUNT: &str = "conversation-encryption-key";
+/// Read for the passphrase when there is no keychain, before asking for it.
+pub const PASSPHRASE_ENV: &str = "Q_CONVERSATION_PASSPHRASE";
+const PBKDF2_ITERATIONS: NonZeroU32 = NonZeroU32::new(600_000).unwrap();
+pub const KEY_LEN: usize = 32;
+pub const SALT_LEN: usize = 16;
+
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum EncryptionError {
+    #[error("There is no keychain to keep the encryption key in, set {PASSPHRASE_ENV} to use a passphrase instead")]
+    NoPassphrase,
+    #[error("Failed to decrypt, the key or passphrase is not the one the conversation was encrypted with")]
+    Decrypt,
+    #[error(
+        "The key or passphrase is not the one the saved conversations are encrypted with. If it is lost, run `q chat history purge` to start over"
+    )]
+    WrongKey,
+    #[error("The decrypted value is not UTF-8")]
+    NotUtf8(#[from] std::string::FromUtf8Error),
+    #[error("Failed to encrypt")]
+    Encrypt(#[source] ring::error::Unspecified),
+    #[error("Failed to generate random bytes")]
+    Random(#[source] ring::error::Unspecified),
+    #[error("Failed to access the keychain: {0}")]
+    Keychain(String),
+    #[error("Failed to read the passphrase: {0}")]
+    Passphrase(String),
+}
+
+/// Whether `value` was encrypted by a [Cipher].
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+pub struct Cipher {
+    key: LessSafeKey,
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cipher").finish()
+    }
+}
+
+impl Cipher {
+    pub fn from_key(key: &[u8; KEY_LEN]) -> Self {
+        Self {
+            key: LessSafeKey::new(UnboundKey::new(&AES_256_GCM, key).expect("key has the length of AES-256")),
+        }
+    }
+
+    pub fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        let mut key = [0; KEY_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            PBKDF2_ITERATIONS,
+            salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        Self::from_key(&key)
+    }
+
+    /// The cipher of the key in the keychain, which is created on first use. Falls back to a
+    /// passphrase, salted with `salt`, when there is no keychain. A passphrase typed for a `new`
+    /// key is asked for twice.
+    pub fn load(salt: impl FnOnce() -> Result<Vec<u8>, EncryptionError>, new: bool) -> Result<Self, EncryptionError> {
+        match keychain_key() {
+            Ok(key) => return Ok(Self::from_key(&key)),
+            Err(err) => warn!(%err, "Falling back to a passphrase to encrypt conversations"),
+        }
+        Ok(Self::from_passphrase(&passphrase(new)?, &salt()?))
+    }
+
+    /// A value to save along with the salt, so that [Self::verify] can tell whether a key loaded
+    /// later is the same one.
+    pub fn verifier(&self) -> Result<String, EncryptionError> {
+        self.encrypt(VERIFIER_PLAINTEXT)
+    }
+
+    /// Checks that this is the key `verifier` was created with, which a mistyped passphrase or
+    /// a keychain that went missing would not be.
+    pub fn verify(&self, verifier: &str) -> Result<(), EncryptionError> {
+        match self.decrypt(verifier) {
+            Ok(plaintext) if plaintext == VERIFIER_PLAINTEXT => Ok(()),
+            _ => Err(EncryptionError::WrongKey),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &str) -> Result<String, EncryptionError> {
+        let mut nonce = [0; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).map_err(EncryptionError::Random)?;
+
+        let mut sealed = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+            .map_err(EncryptionError::Encrypt)?;
+
+        let mut out = nonce.to_vec();
+        out.extend(sealed);
+        Ok(format!("{ENCRYPTED_PREFIX}{}", BASE64.encode(out)))
+    }
+
+    pub fn decrypt(&self, value: &str) -> Result<String, EncryptionError> {
+        let (nonce, mut sealed) = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .and_then(|data| BASE64.decode(data).ok())
+            .filter(|data| data.len() >= NONCE_LEN)
+            .and_then(|mut data| {
+                let sealed = data.split_off(NONCE_LEN);
+                Some((<[u8; NONCE_LEN]>::try_from(data).ok()?, sealed))
+            })
+            .ok_or(EncryptionError::Decrypt)?;
+
+        let plaintext = self
+            .key
+            .open_in_place(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut sealed)
+            // ring doesn't say why, but the only way to fail is for the value not to be
+            // authentic, i.e. sealed with another key.
+            .map_err(|ring::error::Unspecified| EncryptionError::Decrypt)?;
+        Ok(String::from_utf8(plaintext.to_vec())?)
+    }
+}
+
+/// A random salt for [Cipher::from_passphrase].
+pub fn new_salt() -> Result<Vec<u8>, EncryptionError> {
+    let mut salt = vec![0; SALT_LEN];
+    SystemRandom::new().fill(&mut salt).map_err(EncryptionError::Random)?;
+    Ok(salt)
+}
+
+fn keychain_key() -> Result<[u8; KEY_LEN], EncryptionError> {
+    let keychain_err = |err: keyring::Error| EncryptionError::Keychain(err.to_string());
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT).map_err(keychain_err)?;
+
+    let stored = match entry.get_password() {
+        Ok(stored) => Some(stored),
+        Err(keyring::Error::NoEntry) => None,
+        Err(err) => return Err(keychain_err(err)),
+    };
+    if let Some(key) = stored
+        .and_then(|stored| BASE64.decode(stored).ok())
+        .and_then(|key| <[u8; KEY_LEN]>::try_from(key).ok())
+    {
+        return Ok(key);
+    }
+
+    let mut key = [0; KEY_LEN];
+    SystemRandom::new().fill(&mut key).map_err(EncryptionError::Random)?;
+    entry.set_password(&BASE64.encode(key)).map_err(keychain_err)?;
+    Ok(key)
+}
+
+fn passphrase(confirm: bool) -> Result<String, EncryptionError> {
+    if let Ok(passphrase) = std::env::var(PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(EncryptionError::NoPassphrase);
+    }
+    let theme = crate::util::dialoguer_theme();
+    let mut prompt = dialoguer::Password::with_theme(&theme).with_prompt("Passphrase of the saved conversations");
+    if confirm {
+        prompt = prompt.with_confirmation("Repeat the passphrase", "The passphrases don't match");
+    }
+    prompt
+        .interact()
+        .map_err(|err| EncryptionError::Passphrase(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let cipher = Cipher::from_key(&[7; KEY_LEN]);
+        let encrypted = cipher.encrypt("{\"history\":[]}").unwrap();
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("history"));
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), "{\"history\":[]}");
+        // Each encryption uses a new nonce.
+        assert_ne!(cipher.encrypt("{\"history\":[]}").unwrap(), encrypted);
+
+        assert!(matches!(
+            Cipher::from_key(&[8; KEY_LEN]).decrypt(&encrypted),
+            Err(EncryptionError::Decrypt)
+        ));
+        assert!(cipher.decrypt("{\"history\":[]}").is_err());
+        assert!(cipher.decrypt("enc:v1:AAAA").is_err());
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let salt = new_salt().unwrap();
+        let encrypted = Cipher::from_passphrase("hunter2", &salt).encrypt("secret").unwrap();
+        assert_eq!(
+            Cipher::from_passphrase("hunter2", &salt).decrypt(&encrypted).unwrap(),
+            "secret"
+        );
+        assert!(Cipher::from_passphrase("hunter3", &salt).decrypt(&encrypted).is_err());
+        assert!(
+            Cipher::from_passphrase("hunter2", &new_salt().unwrap())
+                .decrypt(&encrypted)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let salt = new_salt().unwrap();
+        let verifier = Cipher::from_passphrase("hunter2", &salt).verifier().unwrap();
+        assert!(Cipher::from_passphrase("hunter2", &salt).verify(&verifier).is_ok());
+        assert!(matches!(
+            Cipher::from_passphrase("hunter3", &salt).verify(&verifier),
+            Err(EncryptionError::WrongKey)
+        ));
+        // An encrypted value that isn't a verifier doesn't verify either.
+        let cipher = Cipher::from_key(&[7; KEY_LEN]);
+        assert!(cipher.verify(&cipher.encrypt("secret").unwrap()).is_err());
+    }
+}
@@ -1,12 +1,24 @@
+pub mod encryption;
+pub mod provenance;
 pub mod settings;
 
 use std::ops::Deref;
 use std::path::Path;
 use std::str::FromStr;
-use std::sync::PoisonError;
+use std::sync::{
+    Arc,
+    OnceLock,
+    PoisonError,
+};
+use std::time::Duration;
 
 use aws_sdk_cognitoidentity::primitives::DateTimeFormat;
 use aws_sdk_cognitoidentity::types::Credentials;
+use base64::Engine;
+use encryption::{
+    Cipher,
+    EncryptionError,
+};
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::types::FromSql;
@@ -25,9 +37,13 @@ use serde_json::{
     Map,
     Value,
 };
-use settings::Settings;
+use settings::{
+    Setting,
+    Settings,
+};
 use thiserror::Error;
 use tracing::{
+    debug,
     error,
     info,
     trace,
@@ -35,6 +51,7 @@ use tracing::{
 use uuid::Uuid;
 
 use crate::cli::ConversationState;
+use crate::cli::chat::monthly_usage::MonthlyUsage;
 use crate::util::directories::{
     DirectoryError,
     database_path,
@@ -62,6 +79,11 @@ const IDC_REGION_KEY: &str = "auth.idc.region";
 const CUSTOMIZATION_STATE_KEY: &str = "api.selectedCustomization";
 const PROFILE_MIGRATION_KEY: &str = "profile.Migrated";
 const HEARTBEAT_DATE_KEY: &str = "telemetry.lastHeartbeatDate";
+const MONTHLY_USAGE_KEY: &str = "usage.monthlyRequests";
+const CACHE_KEY_PREFIX: &str = "cache.";
+const ENCRYPTION_SALT_KEY: &str = "chat.encryptionSalt";
+const ENCRYPTION_VERIFIER_KEY: &str = "chat.encryptionVerifier";
+const TRUSTED_WORKSPACE_CONFIG_KEY_PREFIX: &str = "workspaceConfig.trusted.";
 
 const MIGRATIONS: &[Migration] = migrations![
     "000_migration_table",
@@ -71,7 +93,8 @@ const MIGRATIONS: &[Migration] = migrations![
     "004_state_table",
     "005_auth_table",
     "006_make_state_blob",
-    "007_conversations_table"
+    "007_conversations_table",
+    "008_provenance_table"
 ];
 
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
@@ -82,6 +105,15 @@ pub struct CredentialsJson {
     pub expiration: Option<String>,
 }
 
+/// A value fetched from the service, kept between invocations so that short-lived commands don't
+/// each fetch it again.
+#[derive(Debug, Deserialize, Serialize)]
+struct CachedValue<T> {
+    /// Unix timestamp of when the value was fetched.
+    fetched_at: i64,
+    value: T,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AuthProfile {
     pub arn: String,
@@ -145,6 +177,10 @@ pub enum DatabaseError {
     StrFromUtf8(#[from] std::str::Utf8Error),
     #[error("`{}` is not a valid setting", .0)]
     InvalidSetting(String),
+    #[error("Invalid value for `{key}`: {reason}")]
+    InvalidSettingValue { key: String, reason: String },
+    #[error(transparent)]
+    Encryption(#[from] EncryptionError),
 }
 
 impl<T> From<PoisonError<T>> for DatabaseError {
@@ -183,6 +219,8 @@ struct Migration {
 pub struct Database {
     pool: Pool<SqliteConnectionManager>,
     pub settings: Settings,
+    /// The cipher of saved conversations, loaded when first needed.
+    cipher: Arc<OnceLock<Result<Cipher, EncryptionError>>>,
 }
 
 impl Database {
@@ -192,6 +230,7 @@ impl Database {
                 return Self {
                     pool: Pool::builder().build(SqliteConnectionManager::memory()).unwrap(),
                     settings: Settings::new().await?,
+                    cipher: Arc::default(),
                 }
                 .migrate();
             },
@@ -224,6 +263,7 @@ impl Database {
         Ok(Self {
             pool,
             settings: Settings::new().await?,
+            cipher: Arc::default(),
         }
         .migrate()
         .map_err(|e| DbOpenError(e.to_string()))?)
@@ -354,6 +394,66 @@ impl Database {
         Ok(())
     }
 
+    /// Get the requests sent during the month, used for usage alerts.
+    pub fn get_monthly_usage(&self) -> Result<Option<MonthlyUsage>, DatabaseError> {
+        self.get_json_entry(Table::State, MONTHLY_USAGE_KEY)
+    }
+
+    /// Set the requests sent during the month, used for usage alerts.
+    pub fn set_monthly_usage(&self, usage: &MonthlyUsage) -> Result<usize, DatabaseError> {
+        self.set_json_entry(Table::State, MONTHLY_USAGE_KEY, usage)
+    }
+
+    /// Get the digest of the workspace config the user trusted in the workspace at `path`.
+    pub fn get_trusted_workspace_config(&self, path: &str) -> Result<Option<String>, DatabaseError> {
+        self.get_entry::<String>(Table::State, format!("{TRUSTED_WORKSPACE_CONFIG_KEY_PREFIX}{path}"))
+    }
+
+    /// Set the digest of the workspace config the user trusted in the workspace at `path`.
+    pub fn set_trusted_workspace_config(&self, path: &str, digest: &str) -> Result<usize, DatabaseError> {
+        self.set_entry(
+            Table::State,
+            format!("{TRUSTED_WORKSPACE_CONFIG_KEY_PREFIX}{path}"),
+            digest,
+        )
+    }
+
+    /// Get a value stored with [Self::set_cached], unless it was fetched more than `ttl` ago.
+    /// Values that can't be read, e.g. because they were cached by another version, are misses.
+    pub fn get_cached<T: DeserializeOwned>(&self, key: &str, ttl: Duration) -> Option<T> {
+        let cached = match self.get_json_entry::<CachedValue<T>>(Table::State, format!("{CACHE_KEY_PREFIX}{key}")) {
+            Ok(cached) => cached?,
+            Err(err) => {
+                debug!(?err, key, "ignoring unreadable cached value");
+                return None;
+            },
+        };
+        let age = chrono::Utc::now().timestamp() - cached.fetched_at;
+        (0..ttl.as_secs() as i64).contains(&age).then_some(cached.value)
+    }
+
+    /// Cache a value fetched from the service, see [Self::get_cached].
+    pub fn set_cached(&self, key: &str, value: impl Serialize) -> Result<usize, DatabaseError> {
+        self.set_json_entry(Table::State, format!("{CACHE_KEY_PREFIX}{key}"), CachedValue {
+            fetched_at: chrono::Utc::now().timestamp(),
+            value,
+        })
+    }
+
+    pub fn clear_cached(&self, key: &str) -> Result<(), DatabaseError> {
+        self.delete_entry(Table::State, format!("{CACHE_KEY_PREFIX}{key}"))
+    }
+
+    /// Remove every cached value, e.g. when the user logs out.
+    pub fn clear_all_cached(&self) -> Result<(), DatabaseError> {
+        self.pool
+            .get()?
+            .execute(&format!("DELETE FROM {} WHERE key LIKE ?1", Table::State), [format!(
+                "{CACHE_KEY_PREFIX}%"
+            )])?;
+        Ok(())
+    }
+
     // /// Get the model id used for last conversation state.
     // pub fn get_last_used_model_id(&self) -> Result<Option<String>, DatabaseError> {
     //     self.get_json_entry::<String>(Table::State, LAST_USED_MODEL_ID)
@@ -380,7 +480,34 @@ impl Database {
             None => return Ok(None),
         };
 
-        self.get_json_entry(Table::Conversations, path)
+        match self.get_entry::<String>(Table::Conversations, path)? {
+            Some(value) => Ok(Some(serde_json::from_str(&self.decrypt_conversation(value)?)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get every saved chat conversation, by path. Conversations that fail to parse are skipped.
+    pub fn get_all_conversations(&self) -> Result<Vec<(String, ConversationState)>, DatabaseError> {
+        Ok(self
+            .all_entries(Table::Conversations)?
+            .into_iter()
+            .filter_map(|(path, value)| {
+                let value = match self.decrypt_conversation(value.as_str()?.to_string()) {
+                    Ok(value) => value,
+                    Err(err) => {
+                        warn!(?err, path, "skipping a conversation that failed to decrypt");
+                        return None;
+                    },
+                };
+                match serde_json::from_str(&value) {
+                    Ok(conversation) => Some((path, conversation)),
+                    Err(err) => {
+                        warn!(?err, path, "skipping a conversation that failed to parse");
+                        None
+                    },
+                }
+            })
+            .collect())
     }
 
     /// Set a chat conversation given a path to the conversation.
@@ -395,7 +522,35 @@ impl Database {
             None => return Ok(0),
         };
 
-        self.set_json_entry(Table::Conversations, path, state)
+        let mut value = serde_json::to_string(state)?;
+        if self
+            .settings
+            .get_bool(Setting::ChatEncryptConversations)
+            .unwrap_or(false)
+        {
+            value = self.cipher()?.encrypt(&value)?;
+        }
+        self.set_entry(Table::Conversations, path, value)
+    }
+
+    /// Delete the saved chat conversation of a path.
+    pub fn delete_conversation_by_path(&mut self, path: &str) -> Result<(), DatabaseError> {
+        self.delete_entry(Table::Conversations, path)
+    }
+
+    /// Delete every saved chat conversation, including those that fail to parse or decrypt. The
+    /// encryption verifier goes with them, so that a new key can be used from then on.
+    ///
+    /// # Returns
+    ///
+    /// How many conversations were deleted.
+    pub fn delete_all_conversations(&mut self) -> Result<usize, DatabaseError> {
+        let deleted = self
+            .pool
+            .get()?
+            .execute(&format!("DELETE FROM {}", Table::Conversations), [])?;
+        self.delete_entry(Table::State, ENCRYPTION_VERIFIER_KEY)?;
+        Ok(deleted)
     }
 
     pub async fn get_secret(&self, key: &str) -> Result<Option<Secret>, DatabaseError> {
@@ -416,6 +571,60 @@ impl Database {
 
     // Private functions. Do not expose.
 
+    fn cipher(&self) -> Result<&Cipher, DatabaseError> {
+        let cipher = self.cipher.get_or_init(|| {
+            let verifier = self
+                .get_entry::<String>(Table::State, ENCRYPTION_VERIFIER_KEY)
+                .ok()
+                .flatten();
+            Cipher::load(|| self.encryption_salt(), verifier.is_none())
+                .and_then(|cipher| self.check_cipher(cipher, verifier))
+        });
+        Ok(cipher.as_ref().map_err(Clone::clone)?)
+    }
+
+    /// Checks `cipher` against the `verifier` saved when conversations were first encrypted, so
+    /// that a wrong key is rejected rather than used to encrypt new conversations. Without a
+    /// verifier, one is saved for `cipher`.
+    fn check_cipher(&self, cipher: Cipher, verifier: Option<String>) -> Result<Cipher, EncryptionError> {
+        match verifier {
+            Some(verifier) => cipher.verify(&verifier)?,
+            None => {
+                if let Err(err) = self.set_entry(Table::State, ENCRYPTION_VERIFIER_KEY, cipher.verifier()?) {
+                    error!(?err, "failed to save the encryption verifier");
+                }
+            },
+        }
+        Ok(cipher)
+    }
+
+    /// The salt of the passphrase that conversations are encrypted with when there is no
+    /// keychain, created on first use.
+    fn encryption_salt(&self) -> Result<Vec<u8>, EncryptionError> {
+        let base64 = base64::engine::general_purpose::STANDARD;
+        if let Some(salt) = self
+            .get_entry::<String>(Table::State, ENCRYPTION_SALT_KEY)
+            .ok()
+            .flatten()
+            .and_then(|salt| base64.decode(salt).ok())
+        {
+            return Ok(salt);
+        }
+        let salt = encryption::new_salt()?;
+        if let Err(err) = self.set_entry(Table::State, ENCRYPTION_SALT_KEY, base64.encode(&salt)) {
+            error!(?err, "failed to save the encryption salt");
+        }
+        Ok(salt)
+    }
+
+    /// `value` as saved in the conversations table, decrypted if it is encrypted.
+    fn decrypt_conversation(&self, value: String) -> Result<String, DatabaseError> {
+        match encryption::is_encrypted(&value) {
+            true => Ok(self.cipher()?.decrypt(&value)?),
+            false => Ok(value),
+        }
+    }
+
     fn migrate(self) -> Result<Self, DatabaseError> {
         let mut conn = self.pool.get()?;
         let transaction = conn.transaction()?;
@@ -630,6 +839,98 @@ mod tests {
         assert!(db.get_entry::<bool>(Table::State, "bool").unwrap().is_some());
     }
 
+    #[tokio::test]
+    async fn test_cached_values() {
+        let db = Database::new().await.unwrap();
+        let ttl = Duration::from_secs(60);
+
+        db.set_cached("models", vec!["model-1"]).unwrap();
+        assert_eq!(
+            db.get_cached::<Vec<String>>("models", ttl),
+            Some(vec!["model-1".to_string()])
+        );
+        assert_eq!(db.get_cached::<Vec<String>>("models", Duration::ZERO), None);
+        // A value of another shape is a miss rather than an error.
+        assert_eq!(db.get_cached::<u32>("models", ttl), None);
+
+        db.set_json_entry(Table::State, "cache.profiles", CachedValue {
+            fetched_at: chrono::Utc::now().timestamp() - 120,
+            value: 1,
+        })
+        .unwrap();
+        assert_eq!(db.get_cached::<u32>("profiles", ttl), None);
+
+        db.clear_all_cached().unwrap();
+        assert!(db.get_cached::<Vec<String>>("models", ttl).is_none());
+        assert!(
+            db.get_entry::<String>(Table::State, "cache.profiles")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_conversations() {
+        let mut os = crate::os::Os::new().await.unwrap();
+        let mut tool_manager = crate::cli::chat::tool_manager::ToolManager::default();
+        let conversation = ConversationState::new(
+            "abc",
+            crate::cli::agent::Agents::default(),
+            tool_manager.load_tools(&mut os, &mut vec![]).await.unwrap(),
+            tool_manager,
+            None,
+            &os,
+            false,
+        )
+        .await;
+        let db = &mut os.database;
+        db.cipher.set(Ok(Cipher::from_key(&[1; encryption::KEY_LEN]))).unwrap();
+
+        db.set_conversation_by_path("/plain", &conversation).unwrap();
+        db.settings.set(Setting::ChatEncryptConversations, true).await.unwrap();
+        db.set_conversation_by_path("/encrypted", &conversation).unwrap();
+
+        let raw = db
+            .get_entry::<String>(Table::Conversations, "/encrypted")
+            .unwrap()
+            .unwrap();
+        assert!(encryption::is_encrypted(&raw));
+        assert!(!raw.contains("abc"));
+        // Conversations saved before encryption was enabled are still read.
+        for path in ["/plain", "/encrypted"] {
+            let read = db.get_conversation_by_path(path).unwrap().unwrap();
+            assert_eq!(read.conversation_id(), "abc");
+        }
+        assert_eq!(db.get_all_conversations().unwrap().len(), 2);
+
+        db.delete_conversation_by_path("/plain").unwrap();
+        assert!(db.get_conversation_by_path("/plain").unwrap().is_none());
+        assert_eq!(db.delete_all_conversations().unwrap(), 1);
+        assert!(db.get_all_conversations().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_cipher() {
+        let db = Database::new().await.unwrap();
+        let cipher = db
+            .check_cipher(Cipher::from_key(&[1; encryption::KEY_LEN]), None)
+            .unwrap();
+        let verifier = db
+            .get_entry::<String>(Table::State, ENCRYPTION_VERIFIER_KEY)
+            .unwrap()
+            .unwrap();
+        assert!(cipher.verify(&verifier).is_ok());
+
+        assert!(
+            db.check_cipher(Cipher::from_key(&[1; encryption::KEY_LEN]), Some(verifier.clone()))
+                .is_ok()
+        );
+        assert!(matches!(
+            db.check_cipher(Cipher::from_key(&[2; encryption::KEY_LEN]), Some(verifier)),
+            Err(EncryptionError::WrongKey)
+        ));
+    }
+
     #[tokio::test]
     #[ignore = "not on ci"]
     async fn test_set_password() {
@@ -0,0 +1,126 @@
+//! The ledger of the lines written by the agent, for `q provenance`.
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use rusqlite::params;
+use serde::Serialize;
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use super::{
+    Database,
+    DatabaseError,
+};
+
+/// The lines of a file written by a single tool use.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProvenanceEntry {
+    /// The absolute path of the file.
+    pub path: String,
+    /// 1-based inclusive ranges, numbered as the lines were right after the write.
+    pub lines: Vec<(usize, usize)>,
+    /// The [hash_content] of the file right after the write.
+    pub file_hash: String,
+    pub conversation_id: String,
+    /// The id of the assistant message of the turn.
+    pub message_id: Option<String>,
+    pub tool_use_id: String,
+    pub model_id: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// The hash of a file's content, to tell whether it changed since an entry was recorded.
+pub fn hash_content(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+impl Database {
+    pub fn record_provenance(&self, entry: &ProvenanceEntry) -> Result<(), DatabaseError> {
+        self.pool.get()?.execute(
+            "INSERT INTO provenance \
+             (path, lines, file_hash, conversation_id, message_id, tool_use_id, model_id, recorded_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                entry.path,
+                serde_json::to_string(&entry.lines)?,
+                entry.file_hash,
+                entry.conversation_id,
+                entry.message_id,
+                entry.tool_use_id,
+                entry.model_id,
+                entry.recorded_at.timestamp(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// The entries of a file, oldest first.
+    pub fn get_provenance(&self, path: &str) -> Result<Vec<ProvenanceEntry>, DatabaseError> {
+        let conn = self.pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT path, lines, file_hash, conversation_id, message_id, tool_use_id, model_id, recorded_at \
+             FROM provenance WHERE path = ?1 ORDER BY id",
+        )?;
+        let rows = stmt.query_map([path], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get(2)?,
+                row.get(3)?,
+                row.get(4)?,
+                row.get(5)?,
+                row.get(6)?,
+                row.get::<_, i64>(7)?,
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (path, lines, file_hash, conversation_id, message_id, tool_use_id, model_id, recorded_at) = row?;
+            entries.push(ProvenanceEntry {
+                path,
+                lines: serde_json::from_str(&lines)?,
+                file_hash,
+                conversation_id,
+                message_id,
+                tool_use_id,
+                model_id,
+                recorded_at: DateTime::from_timestamp(recorded_at, 0).unwrap_or_default(),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_provenance() {
+        let db = Database::new().await.unwrap();
+        let entry = |lines, tool_use_id: &str| ProvenanceEntry {
+            path: "/repo/src/main.rs".to_string(),
+            lines,
+            file_hash: hash_content("fn main() {}\n"),
+            conversation_id: "conversation".to_string(),
+            message_id: Some("message".to_string()),
+            tool_use_id: tool_use_id.to_string(),
+            model_id: None,
+            recorded_at: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        };
+
+        db.record_provenance(&entry(vec![(1, 3)], "tool1")).unwrap();
+        db.record_provenance(&entry(vec![(2, 2), (8, 10)], "tool2")).unwrap();
+        assert_eq!(db.get_provenance("/repo/src/main.rs").unwrap(), vec![
+            entry(vec![(1, 3)], "tool1"),
+            entry(vec![(2, 2), (8, 10)], "tool2"),
+        ]);
+        assert!(db.get_provenance("/repo/src/lib.rs").unwrap().is_empty());
+    }
+}
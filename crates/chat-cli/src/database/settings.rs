@@ -19,6 +19,28 @@ use super::DatabaseError;
 pub enum Setting {
     #[strum(message = "Enable/disable telemetry collection (boolean)")]
     TelemetryEnabled,
+    #[strum(message = "Send usage metrics, such as the commands used (boolean)")]
+    TelemetryUsage,
+    #[strum(message = "Send error reports (boolean)")]
+    TelemetryErrors,
+    #[strum(message = "Send chat metadata, such as message lengths and tools suggested (boolean)")]
+    TelemetryChatMetadata,
+    #[strum(message = "Send metrics about the code written by the agent (boolean)")]
+    TelemetryCompletions,
+    #[strum(message = "OTLP/HTTP endpoint to export spans of chat turns, tool uses and MCP requests to (string)")]
+    TelemetryOtlpEndpoint,
+    #[strum(message = "Delete saved conversations inactive for longer than this many days (number)")]
+    RetentionConversationsMaxAgeDays,
+    #[strum(message = "Delete the oldest saved conversations past this total size, in MB (number)")]
+    RetentionConversationsMaxSizeMb,
+    #[strum(message = "Delete log files older than this many days (number)")]
+    RetentionLogsMaxAgeDays,
+    #[strum(message = "Delete the oldest log files past this total size, in MB (number)")]
+    RetentionLogsMaxSizeMb,
+    #[strum(message = "Delete checkpoints older than this many days (number)")]
+    RetentionCheckpointsMaxAgeDays,
+    #[strum(message = "Drop telemetry events recorded for `q telemetry show` older than this many days (number)")]
+    RetentionTelemetryMaxAgeDays,
     #[strum(message = "Legacy client identifier for telemetry (string)")]
     OldClientId,
     #[strum(message = "Share content with CodeWhisperer service (boolean)")]
@@ -56,32 +78,132 @@ pub enum Setting {
     ChatGreetingEnabled,
     #[strum(message = "API request timeout in seconds (number)")]
     ApiTimeout,
-    #[strum(message = "Enable edit mode for chat interface (boolean)")]
+    #[strum(message = "Prompt editing mode, vi or emacs (string)")]
     ChatEditMode,
-    #[strum(message = "Enable desktop notifications (boolean)")]
+    #[strum(message = "Enable the terminal bell when attention is needed, see chat.notifications (boolean)")]
     ChatEnableNotifications,
-    #[strum(message = "CodeWhisperer service endpoint URL (string)")]
+    #[strum(
+        message = "How to notify when a tool needs approval or a long response ends: off, bell or desktop (string)"
+    )]
+    ChatNotifications,
+    #[strum(message = "How the reasoning of models is shown: collapsed, expanded or hidden (string)")]
+    ChatReasoningDisplay,
+    #[strum(message = "Maximum number of tool uses per minute, unlimited if unset (number)")]
+    ChatToolRateLimitPerMinute,
+    #[strum(message = "Maximum seconds spent running shell commands per prompt, unlimited if unset (number)")]
+    ChatToolRateLimitCommandSecondsPerTurn,
+    #[strum(message = "Memory of the session and its tools over which to warn, in MB, 0 to disable (number)")]
+    ChatResourceMonitorMaxMemoryMb,
+    #[strum(
+        message = "Sustained CPU usage of the session and its tools over which to warn, in percent of a core, 0 to disable (number)"
+    )]
+    ChatResourceMonitorMaxCpuPercent,
+    #[strum(message = "CodeWhisperer service endpoint, as {\"endpoint\": url, \"region\": region} (object)")]
     ApiCodeWhispererService,
+    #[strum(message = "Region pinned per profile, keyed by profile name or ARN (object)")]
+    ApiProfileRegions,
     #[strum(message = "Q service endpoint URL (string)")]
     ApiQService,
+    #[strum(message = "Extra headers sent with every API request (object)")]
+    ApiGatewayHeaders,
+    #[strum(message = "Command printing `Name: value` header lines to send with every API request (string)")]
+    ApiGatewayHeadersCommand,
+    #[strum(message = "Environment variable holding the HMAC-SHA256 key used to sign API requests (string)")]
+    ApiGatewaySigningKeyEnv,
+    #[strum(message = "Streaming (chat) API endpoint URL override, e.g. a VPC endpoint (string)")]
+    ApiStreamingEndpoint,
+    #[strum(message = "OIDC authentication endpoint URL override (string)")]
+    ApiAuthEndpoint,
+    #[strum(message = "Telemetry endpoint URL override (string)")]
+    ApiTelemetryEndpoint,
+    #[strum(
+        message = "Hostname to validate Q API server certificates against instead of the endpoint hostname (string)"
+    )]
+    ApiTlsServerName,
+    #[strum(message = "Path to a PEM bundle of additional trusted root certificates for the Q API (string)")]
+    ApiTlsCaBundle,
     #[strum(message = "MCP server initialization timeout (number)")]
     McpInitTimeout,
     #[strum(message = "Non-interactive MCP timeout (number)")]
     McpNoInteractiveTimeout,
     #[strum(message = "Track previously loaded MCP servers (boolean)")]
     McpLoadedBefore,
+    #[strum(
+        message = "Start MCP servers with cached tools only when one of their tools or prompts is first used (boolean)"
+    )]
+    McpLazyStartup,
     #[strum(message = "Show context usage percentage in prompt (boolean)")]
     EnabledContextUsageIndicator,
+    #[strum(message = "Maximum tokens used by context files, capped at 75% of the context window (number)")]
+    ContextMaxTokens,
+    #[strum(message = "Maximum tokens per context file before it is truncated (number)")]
+    ContextMaxFileTokens,
+    #[strum(message = "Only include the context files most relevant to each prompt, at most this many (number)")]
+    ContextRelevanceTopK,
+    #[strum(message = "Milliseconds hooks and context files have to be ready before each request (number)")]
+    ContextAssemblyTimeout,
+    #[strum(message = "Milliseconds the hooks of a tool use, stop and session end have to finish together (number)")]
+    HooksDeadline,
+    #[strum(message = "Maximum tokens of the staged and unstaged diffs in the @git context (number)")]
+    ContextGitDiffTokens,
+    #[strum(message = "Number of recent commit subjects in the @git context (number)")]
+    ContextGitCommits,
+    #[strum(
+        message = "Price per million tokens by model id, e.g. {\"claude-sonnet-4\": {\"input\": 3.0, \"output\": 15.0}} (object)"
+    )]
+    ChatPriceTable,
+    #[strum(message = "Directory for checkpoint shadow repositories (string)")]
+    ChatCheckpointDir,
+    #[strum(message = "Directory of OSV advisories (JSON) checked by the dependency_report tool (string)")]
+    ChatAdvisoryDbDir,
+    #[strum(
+        message = "Scanners run by the security_scan tool when none are asked for, out of semgrep, bandit, cargo-audit and codescan (array)"
+    )]
+    ChatSecurityScanScanners,
+    #[strum(
+        message = "Maximum total size of checkpoint shadow repositories in MB before old ones are removed (number)"
+    )]
+    ChatCheckpointMaxSizeMb,
+    #[strum(message = "Monthly request limit of your plan used for usage alerts, 0 disables the alerts (number)")]
+    ChatMonthlyRequestLimit,
+    #[strum(message = "Percentages of the monthly request limit at which to warn, e.g. [80, 90, 100] (array)")]
+    ChatUsageAlertThresholds,
+    #[strum(message = "Directories /cd may change to besides the one chat was started in (array)")]
+    ChatCdAllowedRoots,
+    #[strum(
+        message = "Segments shown before the chat prompt, out of agent, model, tokens, and edits, e.g. [\"agent\", \"model\"] (array)"
+    )]
+    ChatPromptSegments,
+    #[strum(message = "Offer to show the earlier answer when a prompt repeats one of the conversation (boolean)")]
+    ChatDetectDuplicatePrompts,
+    #[strum(
+        message = "Template of the commit messages of the git tool, with a {message} placeholder, e.g. \"[PROJ-123] {message}\" (string)"
+    )]
+    ChatGitCommitTemplate,
+    #[strum(message = "Co-authored-by trailer added to the commits of the git tool, e.g. \"Name <email>\" (string)")]
+    ChatGitCoAuthoredBy,
     #[strum(message = "Default AI model for conversations (string)")]
     ChatDefaultModel,
+    #[strum(
+        message = "Models to switch to, in order, when the selected one is throttled or out of capacity, e.g. [\"claude-3.7-sonnet\"] (array)"
+    )]
+    ChatFallbackModels,
     #[strum(message = "Disable markdown formatting in chat (boolean)")]
     ChatDisableMarkdownRendering,
+    #[strum(
+        message = "Command opening files cited in responses, with {file} and {line} placeholders, e.g. \"code -g {file}:{line}\" (string)"
+    )]
+    ChatEditorCommand,
     #[strum(message = "Default agent configuration (string)")]
     ChatDefaultAgent,
     #[strum(message = "Disable automatic conversation summarization (boolean)")]
     ChatDisableAutoCompaction,
+    #[strum(message = "Summarization prompt used when compacting, \"default\" or \"task_focused\" (string)")]
+    ChatCompactionPromptVariant,
     #[strum(message = "Show conversation history hints (boolean)")]
     ChatEnableHistoryHints,
+    #[strum(message = "Encrypt saved conversations, with a key kept in the OS keychain (boolean)")]
+    ChatEncryptConversations,
     #[strum(message = "Enable the todo list feature (boolean)")]
     EnabledTodoList,
     #[strum(message = "Enable the checkpoint feature (boolean)")]
@@ -94,6 +216,17 @@ impl AsRef<str> for Setting {
     fn as_ref(&self) -> &'static str {
         match self {
             Self::TelemetryEnabled => "telemetry.enabled",
+            Self::TelemetryUsage => "telemetry.usage",
+            Self::TelemetryErrors => "telemetry.errors",
+            Self::TelemetryChatMetadata => "telemetry.chatMetadata",
+            Self::TelemetryCompletions => "telemetry.completions",
+            Self::TelemetryOtlpEndpoint => "telemetry.otlpEndpoint",
+            Self::RetentionConversationsMaxAgeDays => "retention.conversations.maxAgeDays",
+            Self::RetentionConversationsMaxSizeMb => "retention.conversations.maxSizeMb",
+            Self::RetentionLogsMaxAgeDays => "retention.logs.maxAgeDays",
+            Self::RetentionLogsMaxSizeMb => "retention.logs.maxSizeMb",
+            Self::RetentionCheckpointsMaxAgeDays => "retention.checkpoints.maxAgeDays",
+            Self::RetentionTelemetryMaxAgeDays => "retention.telemetry.maxAgeDays",
             Self::OldClientId => "telemetryClientId",
             Self::ShareCodeWhispererContent => "codeWhisperer.shareCodeWhispererContentWithAWS",
             Self::EnabledThinking => "chat.enableThinking",
@@ -115,19 +248,58 @@ impl AsRef<str> for Setting {
             Self::ApiTimeout => "api.timeout",
             Self::ChatEditMode => "chat.editMode",
             Self::ChatEnableNotifications => "chat.enableNotifications",
+            Self::ChatNotifications => "chat.notifications",
+            Self::ChatReasoningDisplay => "chat.reasoningDisplay",
+            Self::ChatToolRateLimitPerMinute => "chat.toolRateLimit.perMinute",
+            Self::ChatToolRateLimitCommandSecondsPerTurn => "chat.toolRateLimit.commandSecondsPerTurn",
+            Self::ChatResourceMonitorMaxMemoryMb => "chat.resourceMonitor.maxMemoryMb",
+            Self::ChatResourceMonitorMaxCpuPercent => "chat.resourceMonitor.maxCpuPercent",
             Self::ApiCodeWhispererService => "api.codewhisperer.service",
+            Self::ApiProfileRegions => "api.profileRegions",
             Self::ApiQService => "api.q.service",
+            Self::ApiGatewayHeaders => "api.gateway.headers",
+            Self::ApiGatewayHeadersCommand => "api.gateway.headersCommand",
+            Self::ApiGatewaySigningKeyEnv => "api.gateway.signingKeyEnv",
+            Self::ApiStreamingEndpoint => "api.streaming.endpoint",
+            Self::ApiAuthEndpoint => "api.auth.endpoint",
+            Self::ApiTelemetryEndpoint => "api.telemetry.endpoint",
+            Self::ApiTlsServerName => "api.tls.serverName",
+            Self::ApiTlsCaBundle => "api.tls.caBundle",
             Self::McpInitTimeout => "mcp.initTimeout",
             Self::McpNoInteractiveTimeout => "mcp.noInteractiveTimeout",
             Self::McpLoadedBefore => "mcp.loadedBefore",
+            Self::McpLazyStartup => "mcp.lazyStartup",
+            Self::ChatGitCommitTemplate => "chat.git.commitTemplate",
+            Self::ChatGitCoAuthoredBy => "chat.git.coAuthoredBy",
             Self::ChatDefaultModel => "chat.defaultModel",
+            Self::ChatFallbackModels => "chat.fallbackModels",
             Self::ChatDisableMarkdownRendering => "chat.disableMarkdownRendering",
+            Self::ChatEditorCommand => "chat.editorCommand",
             Self::ChatDefaultAgent => "chat.defaultAgent",
             Self::ChatDisableAutoCompaction => "chat.disableAutoCompaction",
+            Self::ChatCompactionPromptVariant => "chat.compaction.promptVariant",
             Self::ChatEnableHistoryHints => "chat.enableHistoryHints",
+            Self::ChatEncryptConversations => "chat.encryptConversations",
             Self::EnabledTodoList => "chat.enableTodoList",
             Self::EnabledCheckpoint => "chat.enableCheckpoint",
             Self::EnabledContextUsageIndicator => "chat.enableContextUsageIndicator",
+            Self::ContextMaxTokens => "chat.context.maxTokens",
+            Self::ContextMaxFileTokens => "chat.context.maxFileTokens",
+            Self::ContextAssemblyTimeout => "chat.context.assemblyTimeoutMs",
+            Self::HooksDeadline => "chat.hooks.deadlineMs",
+            Self::ContextGitDiffTokens => "chat.context.git.diffTokens",
+            Self::ContextGitCommits => "chat.context.git.commits",
+            Self::ContextRelevanceTopK => "chat.context.relevanceTopK",
+            Self::ChatPriceTable => "chat.priceTable",
+            Self::ChatCheckpointDir => "chat.checkpoint.dir",
+            Self::ChatAdvisoryDbDir => "chat.advisoryDbDir",
+            Self::ChatSecurityScanScanners => "chat.securityScan.scanners",
+            Self::ChatCheckpointMaxSizeMb => "chat.checkpoint.maxSizeMb",
+            Self::ChatMonthlyRequestLimit => "chat.monthlyRequestLimit",
+            Self::ChatUsageAlertThresholds => "chat.usageAlertThresholds",
+            Self::ChatCdAllowedRoots => "chat.cdAllowedRoots",
+            Self::ChatPromptSegments => "chat.prompt.segments",
+            Self::ChatDetectDuplicatePrompts => "chat.detectDuplicatePrompts",
             Self::EnabledDelegate => "chat.enableDelegate",
         }
     }
@@ -145,6 +317,17 @@ impl TryFrom<&str> for Setting {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
             "telemetry.enabled" => Ok(Self::TelemetryEnabled),
+            "telemetry.usage" => Ok(Self::TelemetryUsage),
+            "telemetry.errors" => Ok(Self::TelemetryErrors),
+            "telemetry.chatMetadata" => Ok(Self::TelemetryChatMetadata),
+            "telemetry.completions" => Ok(Self::TelemetryCompletions),
+            "telemetry.otlpEndpoint" => Ok(Self::TelemetryOtlpEndpoint),
+            "retention.conversations.maxAgeDays" => Ok(Self::RetentionConversationsMaxAgeDays),
+            "retention.conversations.maxSizeMb" => Ok(Self::RetentionConversationsMaxSizeMb),
+            "retention.logs.maxAgeDays" => Ok(Self::RetentionLogsMaxAgeDays),
+            "retention.logs.maxSizeMb" => Ok(Self::RetentionLogsMaxSizeMb),
+            "retention.checkpoints.maxAgeDays" => Ok(Self::RetentionCheckpointsMaxAgeDays),
+            "retention.telemetry.maxAgeDays" => Ok(Self::RetentionTelemetryMaxAgeDays),
             "telemetryClientId" => Ok(Self::OldClientId),
             "codeWhisperer.shareCodeWhispererContentWithAWS" => Ok(Self::ShareCodeWhispererContent),
             "chat.enableThinking" => Ok(Self::EnabledThinking),
@@ -159,30 +342,330 @@ impl TryFrom<&str> for Setting {
             "chat.autocompletionKey" => Ok(Self::AutocompletionKey),
             "chat.enableTangentMode" => Ok(Self::EnabledTangentMode),
             "chat.tangentModeKey" => Ok(Self::TangentModeKey),
+            "chat.delegateModeKey" => Ok(Self::DelegateModeKey),
 
             "introspect.tangentMode" => Ok(Self::IntrospectTangentMode),
             "chat.greeting.enabled" => Ok(Self::ChatGreetingEnabled),
             "api.timeout" => Ok(Self::ApiTimeout),
             "chat.editMode" => Ok(Self::ChatEditMode),
             "chat.enableNotifications" => Ok(Self::ChatEnableNotifications),
+            "chat.notifications" => Ok(Self::ChatNotifications),
+            "chat.reasoningDisplay" => Ok(Self::ChatReasoningDisplay),
+            "chat.toolRateLimit.perMinute" => Ok(Self::ChatToolRateLimitPerMinute),
+            "chat.toolRateLimit.commandSecondsPerTurn" => Ok(Self::ChatToolRateLimitCommandSecondsPerTurn),
+            "chat.resourceMonitor.maxMemoryMb" => Ok(Self::ChatResourceMonitorMaxMemoryMb),
+            "chat.resourceMonitor.maxCpuPercent" => Ok(Self::ChatResourceMonitorMaxCpuPercent),
             "api.codewhisperer.service" => Ok(Self::ApiCodeWhispererService),
+            "api.profileRegions" => Ok(Self::ApiProfileRegions),
             "api.q.service" => Ok(Self::ApiQService),
+            "api.gateway.headers" => Ok(Self::ApiGatewayHeaders),
+            "api.gateway.headersCommand" => Ok(Self::ApiGatewayHeadersCommand),
+            "api.gateway.signingKeyEnv" => Ok(Self::ApiGatewaySigningKeyEnv),
+            "api.streaming.endpoint" => Ok(Self::ApiStreamingEndpoint),
+            "api.auth.endpoint" => Ok(Self::ApiAuthEndpoint),
+            "api.telemetry.endpoint" => Ok(Self::ApiTelemetryEndpoint),
+            "api.tls.serverName" => Ok(Self::ApiTlsServerName),
+            "api.tls.caBundle" => Ok(Self::ApiTlsCaBundle),
             "mcp.initTimeout" => Ok(Self::McpInitTimeout),
             "mcp.noInteractiveTimeout" => Ok(Self::McpNoInteractiveTimeout),
             "mcp.loadedBefore" => Ok(Self::McpLoadedBefore),
+            "mcp.lazyStartup" => Ok(Self::McpLazyStartup),
+            "chat.git.commitTemplate" => Ok(Self::ChatGitCommitTemplate),
+            "chat.git.coAuthoredBy" => Ok(Self::ChatGitCoAuthoredBy),
             "chat.defaultModel" => Ok(Self::ChatDefaultModel),
+            "chat.fallbackModels" => Ok(Self::ChatFallbackModels),
             "chat.disableMarkdownRendering" => Ok(Self::ChatDisableMarkdownRendering),
+            "chat.editorCommand" => Ok(Self::ChatEditorCommand),
             "chat.defaultAgent" => Ok(Self::ChatDefaultAgent),
             "chat.disableAutoCompaction" => Ok(Self::ChatDisableAutoCompaction),
+            "chat.compaction.promptVariant" => Ok(Self::ChatCompactionPromptVariant),
             "chat.enableHistoryHints" => Ok(Self::ChatEnableHistoryHints),
+            "chat.encryptConversations" => Ok(Self::ChatEncryptConversations),
             "chat.enableTodoList" => Ok(Self::EnabledTodoList),
             "chat.enableCheckpoint" => Ok(Self::EnabledCheckpoint),
             "chat.enableContextUsageIndicator" => Ok(Self::EnabledContextUsageIndicator),
+            "chat.context.maxTokens" => Ok(Self::ContextMaxTokens),
+            "chat.context.maxFileTokens" => Ok(Self::ContextMaxFileTokens),
+            "chat.context.assemblyTimeoutMs" => Ok(Self::ContextAssemblyTimeout),
+            "chat.hooks.deadlineMs" => Ok(Self::HooksDeadline),
+            "chat.context.git.diffTokens" => Ok(Self::ContextGitDiffTokens),
+            "chat.context.git.commits" => Ok(Self::ContextGitCommits),
+            "chat.context.relevanceTopK" => Ok(Self::ContextRelevanceTopK),
+            "chat.priceTable" => Ok(Self::ChatPriceTable),
+            "chat.checkpoint.dir" => Ok(Self::ChatCheckpointDir),
+            "chat.advisoryDbDir" => Ok(Self::ChatAdvisoryDbDir),
+            "chat.securityScan.scanners" => Ok(Self::ChatSecurityScanScanners),
+            "chat.checkpoint.maxSizeMb" => Ok(Self::ChatCheckpointMaxSizeMb),
+            "chat.monthlyRequestLimit" => Ok(Self::ChatMonthlyRequestLimit),
+            "chat.usageAlertThresholds" => Ok(Self::ChatUsageAlertThresholds),
+            "chat.cdAllowedRoots" => Ok(Self::ChatCdAllowedRoots),
+            "chat.prompt.segments" => Ok(Self::ChatPromptSegments),
+            "chat.detectDuplicatePrompts" => Ok(Self::ChatDetectDuplicatePrompts),
+            "chat.enableDelegate" => Ok(Self::EnabledDelegate),
             _ => Err(DatabaseError::InvalidSetting(value.to_string())),
         }
     }
 }
 
+/// The type of the values of a setting, checked when it is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingType {
+    Boolean,
+    /// An integer
+    Number,
+    String,
+    /// A string of a single character, for key bindings
+    Char,
+    /// A string out of a fixed set
+    Enum {
+        values: &'static [&'static str],
+        ignore_case: bool,
+    },
+    Array,
+    Object,
+}
+
+impl SettingType {
+    fn check(self, value: &Value) -> Result<(), String> {
+        let valid = match self {
+            Self::Boolean => value.is_boolean(),
+            Self::Number => value.is_i64(),
+            Self::String => value.is_string(),
+            Self::Char => value.as_str().is_some_and(|s| s.chars().count() == 1),
+            Self::Enum { values, ignore_case } => value.as_str().is_some_and(|s| {
+                values
+                    .iter()
+                    .any(|v| *v == s || (ignore_case && v.eq_ignore_ascii_case(s)))
+            }),
+            Self::Array => value.is_array(),
+            Self::Object => value.is_object(),
+        };
+        match valid {
+            true => Ok(()),
+            false => Err(format!("expected {self}, got {value}")),
+        }
+    }
+}
+
+impl Display for SettingType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Boolean => f.write_str("boolean"),
+            Self::Number => f.write_str("number"),
+            Self::String => f.write_str("string"),
+            Self::Char => f.write_str("single character"),
+            Self::Enum { values, .. } => write!(f, "one of {}", values.join(", ")),
+            Self::Array => f.write_str("array"),
+            Self::Object => f.write_str("object"),
+        }
+    }
+}
+
+impl Setting {
+    pub fn setting_type(&self) -> SettingType {
+        match self {
+            Self::TelemetryEnabled
+            | Self::TelemetryUsage
+            | Self::TelemetryErrors
+            | Self::TelemetryChatMetadata
+            | Self::TelemetryCompletions
+            | Self::ShareCodeWhispererContent
+            | Self::EnabledThinking
+            | Self::EnabledKnowledge
+            | Self::EnabledTangentMode
+            | Self::IntrospectTangentMode
+            | Self::ChatGreetingEnabled
+            | Self::ChatEnableNotifications
+            | Self::McpLoadedBefore
+            | Self::McpLazyStartup
+            | Self::EnabledContextUsageIndicator
+            | Self::ChatDetectDuplicatePrompts
+            | Self::ChatDisableMarkdownRendering
+            | Self::ChatDisableAutoCompaction
+            | Self::ChatEnableHistoryHints
+            | Self::ChatEncryptConversations
+            | Self::EnabledTodoList
+            | Self::EnabledCheckpoint
+            | Self::EnabledDelegate => SettingType::Boolean,
+            Self::RetentionConversationsMaxAgeDays
+            | Self::RetentionConversationsMaxSizeMb
+            | Self::RetentionLogsMaxAgeDays
+            | Self::RetentionLogsMaxSizeMb
+            | Self::RetentionCheckpointsMaxAgeDays
+            | Self::RetentionTelemetryMaxAgeDays
+            | Self::KnowledgeMaxFiles
+            | Self::KnowledgeChunkSize
+            | Self::KnowledgeChunkOverlap
+            | Self::ApiTimeout
+            | Self::ChatToolRateLimitPerMinute
+            | Self::ChatToolRateLimitCommandSecondsPerTurn
+            | Self::ChatResourceMonitorMaxMemoryMb
+            | Self::ChatResourceMonitorMaxCpuPercent
+            | Self::McpInitTimeout
+            | Self::McpNoInteractiveTimeout
+            | Self::ContextMaxTokens
+            | Self::ContextMaxFileTokens
+            | Self::ContextRelevanceTopK
+            | Self::ContextAssemblyTimeout
+            | Self::HooksDeadline
+            | Self::ContextGitDiffTokens
+            | Self::ContextGitCommits
+            | Self::ChatCheckpointMaxSizeMb
+            | Self::ChatMonthlyRequestLimit => SettingType::Number,
+            Self::TelemetryOtlpEndpoint
+            | Self::OldClientId
+            | Self::ApiQService
+            | Self::ApiGatewayHeadersCommand
+            | Self::ApiGatewaySigningKeyEnv
+            | Self::ApiStreamingEndpoint
+            | Self::ApiAuthEndpoint
+            | Self::ApiTelemetryEndpoint
+            | Self::ApiTlsServerName
+            | Self::ApiTlsCaBundle
+            | Self::ChatCheckpointDir
+            | Self::ChatAdvisoryDbDir
+            | Self::ChatGitCommitTemplate
+            | Self::ChatGitCoAuthoredBy
+            | Self::ChatDefaultModel
+            | Self::ChatEditorCommand
+            | Self::ChatDefaultAgent => SettingType::String,
+            Self::SkimCommandKey | Self::AutocompletionKey | Self::TangentModeKey | Self::DelegateModeKey => {
+                SettingType::Char
+            },
+            Self::KnowledgeIndexType => SettingType::Enum {
+                values: &["Fast", "Best"],
+                ignore_case: true,
+            },
+            Self::ChatEditMode => SettingType::Enum {
+                values: &["vi", "vim", "emacs"],
+                ignore_case: true,
+            },
+            Self::ChatNotifications => SettingType::Enum {
+                values: &["off", "bell", "desktop"],
+                ignore_case: false,
+            },
+            Self::ChatReasoningDisplay => SettingType::Enum {
+                values: &["collapsed", "expanded", "hidden"],
+                ignore_case: false,
+            },
+            Self::ChatCompactionPromptVariant => SettingType::Enum {
+                values: &["default", "task_focused"],
+                ignore_case: false,
+            },
+            Self::KnowledgeDefaultIncludePatterns
+            | Self::KnowledgeDefaultExcludePatterns
+            | Self::ChatUsageAlertThresholds
+            | Self::ChatCdAllowedRoots
+            | Self::ChatPromptSegments
+            | Self::ChatFallbackModels
+            | Self::ChatSecurityScanScanners => SettingType::Array,
+            Self::ApiCodeWhispererService
+            | Self::ApiProfileRegions
+            | Self::ApiGatewayHeaders
+            | Self::ChatPriceTable => SettingType::Object,
+        }
+    }
+
+    /// The value used when the setting is not set, `None` if there is no fixed one, such as when
+    /// the setting is off or the default depends on the platform.
+    pub fn default_value(&self) -> Option<Value> {
+        Some(match self {
+            Self::TelemetryEnabled
+            | Self::TelemetryUsage
+            | Self::TelemetryErrors
+            | Self::TelemetryChatMetadata
+            | Self::TelemetryCompletions
+            | Self::ShareCodeWhispererContent
+            | Self::ChatGreetingEnabled
+            | Self::ChatDetectDuplicatePrompts => Value::Bool(true),
+            Self::EnabledThinking
+            | Self::EnabledKnowledge
+            | Self::EnabledTangentMode
+            | Self::IntrospectTangentMode
+            | Self::ChatEnableNotifications
+            | Self::McpLoadedBefore
+            | Self::McpLazyStartup
+            | Self::EnabledContextUsageIndicator
+            | Self::ChatDisableMarkdownRendering
+            | Self::ChatDisableAutoCompaction
+            | Self::ChatEnableHistoryHints
+            | Self::ChatEncryptConversations
+            | Self::EnabledTodoList
+            | Self::EnabledCheckpoint
+            | Self::EnabledDelegate => Value::Bool(false),
+            Self::KnowledgeMaxFiles => 10_000.into(),
+            Self::KnowledgeChunkSize => 512.into(),
+            Self::KnowledgeChunkOverlap => 128.into(),
+            Self::ApiTimeout => 300_000.into(),
+            Self::ChatResourceMonitorMaxMemoryMb => 4096.into(),
+            Self::ChatResourceMonitorMaxCpuPercent => 300.into(),
+            Self::McpInitTimeout => 5000.into(),
+            Self::McpNoInteractiveTimeout => 30_000.into(),
+            Self::ContextAssemblyTimeout => 30_000.into(),
+            Self::HooksDeadline => 30_000.into(),
+            Self::ContextGitDiffTokens => 8_000.into(),
+            Self::ContextGitCommits => 10.into(),
+            Self::ChatCheckpointMaxSizeMb => 1024.into(),
+            Self::SkimCommandKey => "s".into(),
+            Self::AutocompletionKey => "g".into(),
+            Self::TangentModeKey => "t".into(),
+            Self::ChatEditMode => "emacs".into(),
+            Self::ChatNotifications => "off".into(),
+            Self::ChatReasoningDisplay => "collapsed".into(),
+            Self::ChatCompactionPromptVariant => "default".into(),
+            Self::ChatUsageAlertThresholds => Value::from(vec![80, 90, 100]),
+            Self::ChatSecurityScanScanners => Value::from(vec!["semgrep", "bandit", "cargo-audit"]),
+            _ => return None,
+        })
+    }
+
+    /// The setting that replaces this one, if it is deprecated.
+    pub fn replaced_by(&self) -> Option<Setting> {
+        match self {
+            Self::ChatEnableNotifications => Some(Self::ChatNotifications),
+            _ => None,
+        }
+    }
+
+    /// Checks that `value` is of the type of the setting.
+    pub fn validate(&self, value: &Value) -> Result<(), DatabaseError> {
+        self.setting_type()
+            .check(value)
+            .map_err(|reason| DatabaseError::InvalidSettingValue {
+                key: self.to_string(),
+                reason,
+            })
+    }
+}
+
+/// A problem with the settings file, found by [Settings::diagnose].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum SettingIssue {
+    /// The key is not a setting, so it has no effect.
+    Unknown {
+        key: String,
+    },
+    Deprecated {
+        key: String,
+        replaced_by: String,
+    },
+    Invalid {
+        key: String,
+        reason: String,
+    },
+}
+
+impl Display for SettingIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unknown { key } => write!(f, "{key} is not a known setting and has no effect"),
+            Self::Deprecated { key, replaced_by } => write!(f, "{key} is deprecated, use {replaced_by} instead"),
+            Self::Invalid { key, reason } => write!(f, "{key} has an invalid value: {reason}"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct Settings(Map<String, Value>);
 
@@ -225,7 +708,9 @@ impl Settings {
     }
 
     pub async fn set(&mut self, key: Setting, value: impl Into<serde_json::Value>) -> Result<(), DatabaseError> {
-        self.0.insert(key.to_string(), value.into());
+        let value = value.into();
+        key.validate(&value)?;
+        self.0.insert(key.to_string(), value);
         self.save_to_file().await
     }
 
@@ -251,6 +736,30 @@ impl Settings {
         self.get_int(key).map_or(default, |v| v as usize)
     }
 
+    /// The unknown and deprecated keys, and invalid values, of the settings file.
+    pub fn diagnose(&self) -> Vec<SettingIssue> {
+        let mut issues = Vec::new();
+        for (key, value) in &self.0 {
+            let Ok(setting) = Setting::try_from(key.as_str()) else {
+                issues.push(SettingIssue::Unknown { key: key.clone() });
+                continue;
+            };
+            if let Some(replacement) = setting.replaced_by() {
+                issues.push(SettingIssue::Deprecated {
+                    key: key.clone(),
+                    replaced_by: replacement.to_string(),
+                });
+            }
+            if let Err(reason) = setting.setting_type().check(value) {
+                issues.push(SettingIssue::Invalid {
+                    key: key.clone(),
+                    reason,
+                });
+            }
+        }
+        issues
+    }
+
     pub async fn save_to_file(&self) -> Result<(), DatabaseError> {
         if cfg!(test) {
             return Ok(());
@@ -356,4 +865,77 @@ mod test {
         assert_eq!(settings.get(Setting::ChatDisableMarkdownRendering), None);
         assert_eq!(settings.get(Setting::EnabledCheckpoint), None);
     }
+
+    #[test]
+    fn test_schema() {
+        use strum::{
+            EnumMessage,
+            IntoEnumIterator,
+        };
+
+        for setting in Setting::iter() {
+            assert!(
+                matches!(Setting::try_from(setting.as_ref()), Ok(s) if s.as_ref() == setting.as_ref()),
+                "{setting} doesn't round trip"
+            );
+
+            // The type in the description matches the schema.
+            let message = setting.get_message().unwrap();
+            let expected = match setting.setting_type() {
+                SettingType::Enum { .. } => "string".to_string(),
+                ty => ty.to_string(),
+            };
+            assert!(message.ends_with(&format!("({expected})")), "{setting}: {message}");
+
+            if let Some(default) = setting.default_value() {
+                setting.validate(&default).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let mut settings = Settings::new().await.unwrap();
+
+        assert!(settings.set(Setting::TelemetryEnabled, "yes").await.is_err());
+        assert!(settings.set(Setting::ApiTimeout, 1.5).await.is_err());
+        assert!(settings.set(Setting::TangentModeKey, "tt").await.is_err());
+        assert!(settings.set(Setting::ChatNotifications, "loud").await.is_err());
+        assert!(settings.set(Setting::ChatPromptSegments, "agent").await.is_err());
+        assert_eq!(settings.get(Setting::TelemetryEnabled), None);
+
+        settings.set(Setting::ChatEditMode, "Vi").await.unwrap();
+        settings.set(Setting::ChatEditMode, "vim").await.unwrap();
+        settings.set(Setting::TangentModeKey, "e").await.unwrap();
+        settings.set(Setting::ApiTimeout, 60_000).await.unwrap();
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let settings = Settings(
+            serde_json::json!({
+                "chat.defaultModell": "claude-sonnet-4",
+                "chat.editMode": "vi",
+                "chat.enableNotifications": true,
+                "chat.reasoningDisplay": "full",
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        );
+
+        assert_eq!(settings.diagnose(), vec![
+            SettingIssue::Unknown {
+                key: "chat.defaultModell".to_string()
+            },
+            SettingIssue::Deprecated {
+                key: "chat.enableNotifications".to_string(),
+                replaced_by: "chat.notifications".to_string()
+            },
+            SettingIssue::Invalid {
+                key: "chat.reasoningDisplay".to_string(),
+                reason: "expected one of collapsed, expanded, hidden, got \"full\"".to_string()
+            },
+        ]);
+    }
 }
@@ -0,0 +1,12 @@
+CREATE TABLE provenance (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    path TEXT NOT NULL,
+    lines TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    conversation_id TEXT NOT NULL,
+    message_id TEXT,
+    tool_use_id TEXT NOT NULL,
+    model_id TEXT,
+    recorded_at INTEGER NOT NULL
+);
+CREATE INDEX provenance_path ON provenance (path);
@@ -1,20 +1,33 @@
 use std::fs::File;
-use std::path::Path;
+use std::path::{
+    Path,
+    PathBuf,
+};
 use std::sync::Mutex;
 
 use thiserror::Error;
 use tracing::info;
 use tracing::level_filters::LevelFilter;
-use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::non_blocking::{
+    NonBlocking,
+    WorkerGuard,
+};
 use tracing_subscriber::filter::Directive;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::fmt::writer::OptionalWriter;
 use tracing_subscriber::prelude::*;
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::{
     EnvFilter,
+    Layer,
     Registry,
     fmt,
 };
 
-use crate::util::env_var::Q_LOG_LEVEL;
+use crate::util::env_var::{
+    Q_LOG_FORMAT,
+    Q_LOG_LEVEL,
+};
 
 const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
 const DEFAULT_FILTER: LevelFilter = LevelFilter::ERROR;
@@ -23,6 +36,10 @@ static Q_LOG_LEVEL_GLOBAL: Mutex<Option<String>> = Mutex::new(None);
 static MAX_LEVEL: Mutex<Option<LevelFilter>> = Mutex::new(None);
 static ENV_FILTER_RELOADABLE_HANDLE: Mutex<Option<tracing_subscriber::reload::Handle<EnvFilter, Registry>>> =
     Mutex::new(None);
+/// Where the log files of chat sessions go, set when logging for chat.
+static SESSION_LOGS_DIR: Mutex<Option<(PathBuf, LogFormat)>> = Mutex::new(None);
+/// The log file of the current chat session, along with its path.
+static SESSION_LOG: Mutex<Option<(PathBuf, NonBlocking, WorkerGuard)>> = Mutex::new(None);
 
 // A logging error
 #[derive(Debug, Error)]
@@ -31,6 +48,35 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error(transparent)]
     TracingReload(#[from] tracing_subscriber::reload::Error),
+    #[error("`{0}` is not a log level, such as info or debug, or a list of directives such as chat_cli=debug")]
+    InvalidLevel(String),
+}
+
+/// The format of log lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Lines for people to read.
+    #[default]
+    Text,
+    /// A JSON object per line, for tools to parse.
+    Json,
+}
+
+impl LogFormat {
+    /// The format set with `Q_LOG_FORMAT`, text unless it is `json`.
+    pub fn from_env() -> Self {
+        match std::env::var(Q_LOG_FORMAT) {
+            Ok(format) if format.eq_ignore_ascii_case("json") => Self::Json,
+            _ => Self::Text,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "log",
+            Self::Json => "jsonl",
+        }
+    }
 }
 
 /// Arguments to the initialize_logging function
@@ -44,6 +90,8 @@ pub struct LogArgs<T: AsRef<Path>> {
     pub log_file_path: Option<T>,
     /// Whether we should delete the log file at each launch.
     pub delete_old_log_file: bool,
+    /// The format of the log lines written to the log file and stdout.
+    pub format: LogFormat,
 }
 
 /// The log guard maintains tracing guards which send log information to other threads.
@@ -109,9 +157,7 @@ pub fn initialize_logging<T: AsRef<Path>>(args: LogArgs<T>) -> Result<LogGuard,
             }
 
             let (non_blocking, guard) = tracing_appender::non_blocking(file);
-            let file_layer = fmt::layer().with_line_number(true).with_writer(non_blocking);
-
-            (Some(file_layer), Some(guard))
+            (Some(format_layer(args.format, non_blocking)), Some(guard))
         },
         None => (None, None),
     };
@@ -119,12 +165,20 @@ pub fn initialize_logging<T: AsRef<Path>>(args: LogArgs<T>) -> Result<LogGuard,
     // If we log to stdout, we need to add this layer to our logger.
     let (stdout_layer, _stdout_guard) = if args.log_to_stdout {
         let (non_blocking, guard) = tracing_appender::non_blocking(std::io::stdout());
-        let stdout_layer = fmt::layer().with_line_number(true).with_writer(non_blocking);
-        (Some(stdout_layer), Some(guard))
+        (Some(format_layer(args.format, non_blocking)), Some(guard))
     } else {
         (None, None)
     };
 
+    // Chat sessions each also log to a file of their own, once they start.
+    let session_layer = mcp_path.as_ref().map(|parent| {
+        SESSION_LOGS_DIR
+            .lock()
+            .unwrap()
+            .replace((parent.join("sessions"), args.format));
+        format_layer(args.format, SessionLogWriter)
+    });
+
     // Set up for mcp servers layer if we are in chat
     let (mcp_server_layer, _mcp_file_guard) = if let Some(parent) = mcp_path {
         let mcp_path = parent.join("mcp.log");
@@ -165,7 +219,8 @@ pub fn initialize_logging<T: AsRef<Path>>(args: LogArgs<T>) -> Result<LogGuard,
     let subscriber = tracing_subscriber::registry()
         .with(reloadable_filter_layer)
         .with(file_layer)
-        .with(stdout_layer);
+        .with(stdout_layer)
+        .with(session_layer);
 
     if let Some(mcp_server_layer) = mcp_server_layer {
         subscriber.with(mcp_server_layer).init();
@@ -205,6 +260,9 @@ pub fn get_log_level() -> String {
 ///
 /// On success, returns the old log level.
 pub fn set_log_level(level: String) -> Result<String, Error> {
+    if EnvFilter::try_new(&level).is_err() {
+        return Err(Error::InvalidLevel(level));
+    }
     info!("Setting log level to {level:?}");
 
     let old_level = get_log_level();
@@ -240,6 +298,68 @@ pub fn get_log_level_max() -> LevelFilter {
     }
 }
 
+/// Starts writing the logs of the chat session `conversation_id` to a file of its own, named
+/// after it. Does nothing unless logging was initialized for chat.
+///
+/// # Returns
+///
+/// The path of the log file of the session, if there is one.
+pub fn start_session_log(conversation_id: &str) -> Result<Option<PathBuf>, Error> {
+    let Some((dir, format)) = SESSION_LOGS_DIR.lock().unwrap().clone() else {
+        return Ok(None);
+    };
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{conversation_id}.{}", format.extension()));
+    let file = File::options().append(true).create(true).open(&path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600)).ok();
+    }
+
+    let (non_blocking, guard) = tracing_appender::non_blocking(file);
+    SESSION_LOG.lock().unwrap().replace((path.clone(), non_blocking, guard));
+    Ok(Some(path))
+}
+
+/// Stops writing to the log file of the current chat session, flushing what is left of it.
+pub fn end_session_log() {
+    SESSION_LOG.lock().unwrap().take();
+}
+
+/// The log file of the current chat session, if one was started.
+pub fn session_log_path() -> Option<PathBuf> {
+    SESSION_LOG.lock().unwrap().as_ref().map(|(path, ..)| path.clone())
+}
+
+/// Writes to the log file of the current chat session, or nowhere before one is started.
+struct SessionLogWriter;
+
+impl<'a> MakeWriter<'a> for SessionLogWriter {
+    type Writer = OptionalWriter<NonBlocking>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        SESSION_LOG
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(_, writer, _)| writer.clone())
+            .into()
+    }
+}
+
+fn format_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = fmt::layer().with_line_number(true).with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
 fn create_filter_layer() -> EnvFilter {
     let directive = Directive::from(DEFAULT_FILTER);
 
@@ -283,6 +403,7 @@ mod tests {
             log_to_stdout: true,
             log_file_path: Some(&log_path),
             delete_old_log_file: true,
+            format: LogFormat::Text,
         })
         .unwrap();
 
@@ -296,6 +417,12 @@ mod tests {
         warn!("jkl");
         error!("mno");
 
+        assert!(matches!(
+            set_log_level("chat_cli=verbose".to_string()),
+            Err(Error::InvalidLevel(_))
+        ));
+        assert_eq!(get_log_level(), "trace");
+
         // Test that set log level functions as expected.
         // This also restores the default log level.
         set_log_level(DEFAULT_FILTER.to_string()).unwrap();
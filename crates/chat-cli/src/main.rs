@@ -34,6 +34,11 @@ fn main() -> Result<ExitCode> {
         },
     };
 
+    if let Err(err) = request::install_crypto_provider() {
+        eprintln!("{} {err}", "error:".bold().red());
+        return Ok(ExitCode::FAILURE);
+    }
+
     let verbose = parsed.verbose > 0;
     let runtime = tokio::runtime::Builder::new_multi_thread().enable_all().build()?;
     let result = runtime.block_on(parsed.execute());
@@ -1,11 +1,18 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::process::Stdio;
+use std::sync::{
+    Arc,
+    PoisonError,
+};
+use std::time::Duration;
 
 use regex::Regex;
 use rmcp::model::{
     CallToolRequestParam,
     CallToolResult,
+    CancelledNotificationParam,
+    ClientRequest,
     ClientResult,
     ErrorCode,
     GetPromptRequestParam,
@@ -13,17 +20,27 @@ use rmcp::model::{
     Implementation,
     InitializeRequestParam,
     ListPromptsResult,
+    ListResourcesResult,
     ListToolsResult,
     LoggingLevel,
     LoggingMessageNotificationParam,
     PaginatedRequestParam,
+    ProgressNotificationParam,
+    ProgressToken,
+    ReadResourceRequestParam,
+    ReadResourceResult,
+    Request,
+    RequestId,
     ServerNotification,
     ServerRequest,
+    ServerResult,
 };
 use rmcp::service::{
     ClientInitializeError,
     DynService,
     NotificationContext,
+    Peer,
+    PeerRequestOptions,
 };
 use rmcp::transport::{
     ConfigureCommandExt,
@@ -36,18 +53,20 @@ use rmcp::{
     ServiceError,
     ServiceExt,
 };
-use tokio::io::AsyncReadExt as _;
-use tokio::process::{
-    ChildStderr,
-    Command,
-};
+use tokio::process::Command;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use tracing::{
     error,
     info,
+    warn,
 };
 
 use super::messenger::Messenger;
+use super::stderr_log::{
+    StderrLog,
+    server_log_path,
+};
 use super::{
     AuthClientWrapper,
     HttpServiceBuilder,
@@ -59,7 +78,13 @@ use crate::cli::chat::tools::custom_tool::{
     TransportType,
 };
 use crate::os::Os;
+use crate::telemetry::otel::OtelSpan;
 use crate::util::directories::DirectoryError;
+use crate::util::shutdown;
+
+/// How long the stderr of a server that failed to start is waited for, before the failure is
+/// reported.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
 
 /// Fetches all pages of specified resources from a server
 macro_rules! paginated_fetch {
@@ -139,6 +164,13 @@ pub enum McpClientError {
     JoinError(#[from] tokio::task::JoinError),
     #[error("Client has not finished initializing")]
     NotReady,
+    #[error("The server did not start within {0} ms")]
+    StartupTimeout(u64),
+    #[error("{source}\nThe last lines the server wrote to its stderr:\n{stderr}")]
+    WithStderr {
+        source: Box<McpClientError>,
+        stderr: String,
+    },
     #[error(transparent)]
     Directory(#[from] DirectoryError),
     #[error(transparent)]
@@ -163,44 +195,48 @@ pub enum McpClientError {
 /// method that would allow us to configure what extra info to include in the token. If you find it,
 /// feel free to remove this. That would also enable us to simplify the definition of
 /// [RunningService])
+/// Tokens whose expiry is known, because we noted down when they were obtained, are also
+/// refreshed shortly before they expire rather than after a request fails.
+/// Requests are sent with [RunningService::send_request], so that a request the server doesn't
+/// answer in time is cancelled, and a timed out request is not retried.
 macro_rules! decorate_with_auth_retry {
-    ($param_type:ty, $method_name:ident, $return_type:ty) => {
+    ($param_type:ty, $method_name:ident, $request:ident, $return_type:ident) => {
         pub async fn $method_name(&self, param: $param_type) -> Result<$return_type, rmcp::ServiceError> {
-            let first_attempt = match &self.inner_service {
-                InnerService::Original(rs) => rs.$method_name(param.clone()).await,
-                InnerService::Peer(peer) => peer.$method_name(param.clone()).await,
+            let span = OtelSpan::start("mcp_request", &[(
+                "mcp.method",
+                stringify!($method_name).to_string(),
+            )]);
+            let send = |param: $param_type| async move {
+                match self
+                    .send_request(ClientRequest::$request(Request::new(param)))
+                    .await?
+                {
+                    ServerResult::$return_type(result) => Ok(result),
+                    _ => Err(rmcp::ServiceError::UnexpectedResponse),
+                }
             };
 
-            match first_attempt {
-                Ok(result) => Ok(result),
-                Err(e) => {
-                    // TODO: discern error type prior to retrying
-                    // Not entirely sure what is thrown when auth is required
-                    if let Some(auth_client) = self.auth_client.as_ref() {
-                        let refresh_result = auth_client.refresh_token().await;
-                        match refresh_result {
-                            Ok(_) => {
-                                info!("Token refreshed");
-                                // Retry the operation after token refresh
-                                match &self.inner_service {
-                                    InnerService::Original(rs) => rs.$method_name(param).await,
-                                    InnerService::Peer(peer) => peer.$method_name(param).await,
-                                }
-                            },
-                            Err(_) => {
-                                // If refresh fails, return the original error
-                                // Currently our event loop just does not allow us easy ways to
-                                // reauth entirely once a session starts since this would mean
-                                // swapping of transport (which also means swapping of client)
-                                Err(e)
-                            },
-                        }
-                    } else {
-                        // No auth client available, return original error
-                        Err(e)
-                    }
+            self.refresh_expiring_token().await;
+            let result = match send(param.clone()).await {
+                Err(rmcp::ServiceError::Timeout { timeout }) => Err(rmcp::ServiceError::Timeout { timeout }),
+                // TODO: discern error type prior to retrying
+                // Not entirely sure what is thrown when auth is required
+                Err(e) => match self.auth_client.as_ref() {
+                    Some(auth_client) if auth_client.refresh_token().await.is_ok() => {
+                        info!("Token refreshed");
+                        // Retry the operation after token refresh
+                        send(param).await
+                    },
+                    // If refresh fails, return the original error
+                    // Currently our event loop just does not allow us easy ways to
+                    // reauth entirely once a session starts since this would mean
+                    // swapping of transport (which also means swapping of client)
+                    _ => Err(e),
                 },
-            }
+                result => result,
+            };
+            span.end(result.as_ref().err().map(ToString::to_string).as_deref());
+            result
         }
     };
 }
@@ -224,6 +260,15 @@ impl std::fmt::Debug for InnerService {
     }
 }
 
+impl InnerService {
+    fn peer(&self) -> &Peer<RoleClient> {
+        match self {
+            InnerService::Original(rs) => &**rs,
+            InnerService::Peer(peer) => peer,
+        }
+    }
+}
+
 impl Clone for InnerService {
     fn clone(&self) -> Self {
         match self {
@@ -248,6 +293,10 @@ impl Clone for InnerService {
 pub struct RunningService {
     pub inner_service: InnerService,
     auth_client: Option<AuthClientWrapper>,
+    /// How long requests to the server are waited for
+    request_timeout: Duration,
+    progress: ProgressSubscribers,
+    stderr: StderrLog,
 }
 
 impl Clone for RunningService {
@@ -255,14 +304,200 @@ impl Clone for RunningService {
         RunningService {
             inner_service: self.inner_service.clone(),
             auth_client: self.auth_client.clone(),
+            request_timeout: self.request_timeout,
+            progress: self.progress.clone(),
+            stderr: self.stderr.clone(),
         }
     }
 }
 
 impl RunningService {
-    decorate_with_auth_retry!(CallToolRequestParam, call_tool, CallToolResult);
+    decorate_with_auth_retry!(CallToolRequestParam, call_tool, CallToolRequest, CallToolResult);
+
+    decorate_with_auth_retry!(GetPromptRequestParam, get_prompt, GetPromptRequest, GetPromptResult);
+
+    decorate_with_auth_retry!(
+        ReadResourceRequestParam,
+        read_resource,
+        ReadResourceRequest,
+        ReadResourceResult
+    );
+
+    /// The last lines the server wrote to its stderr, to explain errors of the transport. Only
+    /// stdio servers have any.
+    pub fn stderr_excerpt(&self) -> Option<String> {
+        self.stderr.excerpt()
+    }
+
+    /// Calls a tool like [Self::call_tool], sending the progress the server reports to
+    /// `progress`. If the returned future is dropped before the server responds, as it is when
+    /// the user interrupts the tool, the server is told to cancel the request.
+    pub async fn call_tool_with_progress(
+        &self,
+        param: CallToolRequestParam,
+        progress: watch::Sender<Option<ToolProgress>>,
+    ) -> Result<CallToolResult, rmcp::ServiceError> {
+        let span = OtelSpan::start("mcp_request", &[("mcp.method", "call_tool".to_string())]);
+        self.refresh_expiring_token().await;
+        let result = match self.send_call_tool(param.clone(), &progress).await {
+            Err(rmcp::ServiceError::Timeout { timeout }) => Err(rmcp::ServiceError::Timeout { timeout }),
+            Err(e) => match self.auth_client.as_ref() {
+                Some(auth_client) if auth_client.refresh_token().await.is_ok() => {
+                    info!("Token refreshed");
+                    self.send_call_tool(param, &progress).await
+                },
+                _ => Err(e),
+            },
+            result => result,
+        };
+        span.end(result.as_ref().err().map(ToString::to_string).as_deref());
+        result
+    }
+
+    async fn send_call_tool(
+        &self,
+        param: CallToolRequestParam,
+        progress: &watch::Sender<Option<ToolProgress>>,
+    ) -> Result<CallToolResult, rmcp::ServiceError> {
+        let peer = self.inner_service.peer();
+        let handle = peer
+            .send_request_with_option(
+                ClientRequest::CallToolRequest(Request::new(param)),
+                PeerRequestOptions {
+                    timeout: Some(self.request_timeout),
+                    meta: None,
+                },
+            )
+            .await?;
+        let _subscription = ProgressSubscription::new(&self.progress, handle.progress_token.clone(), progress.clone());
+        let mut cancel_guard = CancelOnDrop {
+            peer: peer.clone(),
+            request_id: Some(handle.id.clone()),
+        };
+
+        let response = handle.await_response().await;
+        cancel_guard.request_id = None;
+        match response? {
+            ServerResult::CallToolResult(result) => Ok(result),
+            _ => Err(rmcp::ServiceError::UnexpectedResponse),
+        }
+    }
+
+    /// Sends `request` to the server. If the server doesn't respond within the request timeout of
+    /// its config, it is told to cancel the request with `notifications/cancelled`.
+    async fn send_request(&self, request: ClientRequest) -> Result<ServerResult, rmcp::ServiceError> {
+        self.inner_service
+            .peer()
+            .send_request_with_option(request, PeerRequestOptions {
+                timeout: Some(self.request_timeout),
+                meta: None,
+            })
+            .await?
+            .await_response()
+            .await
+    }
+
+    async fn refresh_expiring_token(&self) {
+        if let Some(auth_client) = self.auth_client.as_ref() {
+            match auth_client.refresh_if_expiring().await {
+                Ok(true) => info!("Token refreshed ahead of its expiry"),
+                Ok(false) => {},
+                Err(err) => warn!(?err, "Failed to refresh a token that is about to expire"),
+            }
+        }
+    }
+}
+
+/// The progress a server reported for a request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolProgress {
+    pub progress: f64,
+    pub total: Option<f64>,
+    pub message: Option<String>,
+}
+
+impl ToolProgress {
+    /// A one line description, like `40% Indexing files`, or `12 Indexing files` when the total
+    /// is not known.
+    pub fn render(&self) -> String {
+        let amount = match self.total.filter(|total| *total > 0.0) {
+            Some(total) => format!("{:.0}%", (self.progress / total * 100.0).clamp(0.0, 100.0)),
+            None => format!("{}", self.progress),
+        };
+        match &self.message {
+            Some(message) => format!("{amount} {message}"),
+            None => amount,
+        }
+    }
+}
+
+impl From<ProgressNotificationParam> for ToolProgress {
+    fn from(value: ProgressNotificationParam) -> Self {
+        Self {
+            progress: f64::from(value.progress),
+            total: value.total.map(f64::from),
+            message: value.message,
+        }
+    }
+}
+
+/// Where the progress of the requests in flight is sent, by the progress token of the request.
+type ProgressSubscribers = Arc<std::sync::Mutex<HashMap<ProgressToken, watch::Sender<Option<ToolProgress>>>>>;
+
+/// Subscribes to the progress of a request for as long as it lives.
+struct ProgressSubscription {
+    subscribers: ProgressSubscribers,
+    token: ProgressToken,
+}
+
+impl ProgressSubscription {
+    fn new(
+        subscribers: &ProgressSubscribers,
+        token: ProgressToken,
+        sender: watch::Sender<Option<ToolProgress>>,
+    ) -> Self {
+        subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(token.clone(), sender);
+        Self {
+            subscribers: subscribers.clone(),
+            token,
+        }
+    }
+}
+
+impl Drop for ProgressSubscription {
+    fn drop(&mut self) {
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&self.token);
+    }
+}
 
-    decorate_with_auth_retry!(GetPromptRequestParam, get_prompt, GetPromptResult);
+/// Tells the server to cancel a request that is dropped before its response arrives.
+struct CancelOnDrop {
+    peer: Peer<RoleClient>,
+    request_id: Option<RequestId>,
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(request_id) = self.request_id.take() else {
+            return;
+        };
+        let peer = self.peer.clone();
+        tokio::spawn(async move {
+            let param = CancelledNotificationParam {
+                request_id,
+                reason: Some("The user interrupted the tool call".to_string()),
+            };
+            if let Err(err) = peer.notify_cancelled(param).await {
+                warn!(?err, "Failed to cancel an MCP request");
+            }
+        });
+    }
 }
 
 /// This struct implements the [Service] trait from rmcp. It is within this trait the logic of
@@ -273,6 +508,8 @@ pub struct McpClientService {
     pub config: CustomToolConfig,
     server_name: String,
     messenger: ServerMessenger,
+    progress: ProgressSubscribers,
+    stderr: StderrLog,
 }
 
 impl McpClientService {
@@ -281,6 +518,8 @@ impl McpClientService {
             server_name,
             config,
             messenger,
+            progress: Default::default(),
+            stderr: Default::default(),
         }
     }
 
@@ -290,10 +529,28 @@ impl McpClientService {
         let handle: JoinHandle<Result<RunningService, McpClientError>> = tokio::spawn(async move {
             let messenger_clone = self.messenger.clone();
             let server_name = self.server_name.clone();
-
-            let (service, child_stderr, auth_dropguard) = match self.into_service(&os_clone, &messenger_clone).await {
-                Ok((service, stderr, auth_dg)) => (service, stderr, auth_dg),
+            let request_timeout = Duration::from_millis(self.config.timeout);
+            let startup_timeout = self.config.startup_timeout;
+            let progress = self.progress.clone();
+            let stderr = self.stderr.clone();
+
+            let service = self.into_service(&os_clone, &messenger_clone);
+            let service = match startup_timeout {
+                Some(timeout) => tokio::time::timeout(Duration::from_millis(timeout), service)
+                    .await
+                    .unwrap_or(Err(McpClientError::StartupTimeout(timeout))),
+                None => service.await,
+            };
+            let (service, auth_dropguard) = match service {
+                Ok((service, auth_dg)) => (service, auth_dg),
                 Err(e) => {
+                    let e = match stderr.excerpt() {
+                        Some(excerpt) => McpClientError::WithStderr {
+                            source: Box::new(e),
+                            stderr: excerpt,
+                        },
+                        None => e,
+                    };
                     let msg = e.to_string();
                     let error_data = ErrorData {
                         code: ErrorCode::RESOURCE_NOT_FOUND,
@@ -310,28 +567,6 @@ impl McpClientService {
                 },
             };
 
-            if let Some(mut stderr) = child_stderr {
-                let server_name_clone = server_name.clone();
-                tokio::spawn(async move {
-                    let mut buf = [0u8; 1024];
-                    loop {
-                        match stderr.read(&mut buf).await {
-                            Ok(0) => {
-                                tracing::info!(target: "mcp", "{server_name_clone} stderr listening process exited due to EOF");
-                                break;
-                            },
-                            Ok(size) => {
-                                tracing::info!(target: "mcp", "{server_name_clone} logged to its stderr: {}", String::from_utf8_lossy(&buf[0..size]));
-                            },
-                            Err(e) => {
-                                tracing::info!(target: "mcp", "{server_name_clone} stderr listening process exited due to error: {e}");
-                                break; // Error reading
-                            },
-                        }
-                    }
-                });
-            }
-
             let service_clone = service.clone();
             tokio::spawn(async move {
                 let result: Result<(), Box<dyn std::error::Error + Send + Sync>> = async {
@@ -350,6 +585,19 @@ impl McpClientService {
                             };
                         }
 
+                        if init_result.capabilities.resources.is_some() {
+                            paginated_fetch! {
+                                final_result_type: ListResourcesResult,
+                                content_type: rmcp::model::Resource,
+                                service_method: list_resources,
+                                result_field: resources,
+                                messenger_method: send_resources_list_result,
+                                service: service_clone.clone(),
+                                messenger: messenger_clone,
+                                server_name: server_name
+                            };
+                        }
+
                         if init_result.capabilities.prompts.is_some() {
                             paginated_fetch! {
                                 final_result_type: ListPromptsResult,
@@ -375,6 +623,8 @@ impl McpClientService {
             Ok(RunningService {
                 inner_service: InnerService::Original(service),
                 auth_client: auth_dropguard,
+                request_timeout,
+                progress,
             })
         });
 
@@ -388,7 +638,6 @@ impl McpClientService {
     ) -> Result<
         (
             rmcp::service::RunningService<RoleClient, Box<dyn DynService<RoleClient>>>,
-            Option<ChildStderr>,
             Option<AuthClientWrapper>,
         ),
         McpClientError,
@@ -419,19 +668,29 @@ impl McpClientService {
                     command: command_as_str,
                     args,
                     env: config_envs,
+                    cwd,
                     ..
                 } = &mut self.config;
 
                 let context = |input: &str| Ok(os.env.get(input).ok());
                 let home_dir = || os.env.home().map(|p| p.to_string_lossy().to_string());
                 let expanded_cmd = shellexpand::full_with_context(command_as_str, home_dir, context)?;
+                let cwd = match cwd {
+                    Some(cwd) => Some(shellexpand::full_with_context(cwd, home_dir, context)?.into_owned()),
+                    None => None,
+                };
 
                 let command = Command::new(expanded_cmd.as_ref() as &str).configure(|cmd| {
+                    // The variables of the config take precedence over those chat runs with.
+                    cmd.envs(std::env::vars());
                     if let Some(envs) = config_envs {
                         process_env_vars(envs, &os.env);
                         cmd.envs(envs);
                     }
-                    cmd.envs(std::env::vars()).args(args);
+                    cmd.args(args);
+                    if let Some(cwd) = &cwd {
+                        cmd.current_dir(cwd);
+                    }
 
                     #[cfg(not(windows))]
                     cmd.process_group(0);
@@ -439,14 +698,38 @@ impl McpClientService {
 
                 let (tokio_child_process, child_stderr) =
                     TokioChildProcess::builder(command).stderr(Stdio::piped()).spawn()?;
+                let child_guard = tokio_child_process
+                    .id()
+                    .map(|pid| shutdown::track_child(pid, format!("MCP server {}", self.server_name)));
+                let capture = match child_stderr {
+                    Some(child_stderr) => {
+                        let log_path = server_log_path(&self.server_name)?;
+                        let capture = self.stderr.capture(self.server_name.clone(), log_path, child_stderr);
+                        // The server's stderr closes when it exits, after which there is nothing
+                        // left to kill on shutdown.
+                        Some(tokio::spawn(async move {
+                            capture.await.ok();
+                            drop(child_guard);
+                        }))
+                    },
+                    None => None,
+                };
 
-                let service = self
+                match self
                     .into_dyn()
                     .serve::<TokioChildProcess, _, _>(tokio_child_process)
                     .await
-                    .map_err(Box::new)?;
-
-                Ok((service, child_stderr, None))
+                {
+                    Ok(service) => Ok((service, None)),
+                    Err(e) => {
+                        // A server that fails the handshake has usually exited, so give its
+                        // last words a moment to arrive before they are reported.
+                        if let Some(capture) = capture {
+                            let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, capture).await;
+                        }
+                        Err(Box::new(e).into())
+                    },
+                }
             },
             TransportType::Http => {
                 let CustomToolConfig {
@@ -461,7 +744,7 @@ impl McpClientService {
 
                 let (service, auth_client_wrapper) = http_service_builder.try_build(&self).await?;
 
-                Ok((service, None, auth_client_wrapper))
+                Ok((service, auth_client_wrapper))
             },
         }
     }
@@ -494,6 +777,16 @@ impl McpClientService {
         }
     }
 
+    fn on_progress(&self, params: ProgressNotificationParam) {
+        let subscribers = self.progress.lock().unwrap_or_else(PoisonError::into_inner);
+        match subscribers.get(&params.progress_token) {
+            Some(sender) => {
+                sender.send_replace(Some(params.into()));
+            },
+            None => tracing::debug!(target: "mcp", "{}: progress of an unknown request", self.server_name),
+        }
+    }
+
     async fn on_tool_list_changed(&self, context: NotificationContext<RoleClient>) {
         let NotificationContext { peer, .. } = context;
 
@@ -523,6 +816,21 @@ impl McpClientService {
             server_name: self.server_name
         };
     }
+
+    async fn on_resource_list_changed(&self, context: NotificationContext<RoleClient>) {
+        let NotificationContext { peer, .. } = context;
+
+        paginated_fetch! {
+            final_result_type: ListResourcesResult,
+            content_type: rmcp::model::Resource,
+            service_method: list_resources,
+            result_field: resources,
+            messenger_method: send_resources_list_result,
+            service: peer,
+            messenger: self.messenger,
+            server_name: self.server_name
+        };
+    }
 }
 
 impl Service<RoleClient> for McpClientService {
@@ -556,11 +864,11 @@ impl Service<RoleClient> for McpClientService {
                 self.on_logging_message(notification.params, context).await;
             },
             ServerNotification::PromptListChangedNotification(_) => self.on_prompt_list_changed(context).await,
+            ServerNotification::ResourceListChangedNotification(_) => self.on_resource_list_changed(context).await,
+            ServerNotification::ProgressNotification(notification) => self.on_progress(notification.params),
             // TODO: support these
             ServerNotification::CancelledNotification(_) => (),
             ServerNotification::ResourceUpdatedNotification(_) => (),
-            ServerNotification::ResourceListChangedNotification(_) => (),
-            ServerNotification::ProgressNotification(_) => (),
         };
         Ok(())
     }
@@ -622,6 +930,21 @@ impl InitializedMcpClient {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tool_progress_render() {
+        let mut progress = ToolProgress {
+            progress: 2.0,
+            total: Some(5.0),
+            message: Some("Indexing files".to_string()),
+        };
+        assert_eq!(progress.render(), "40% Indexing files");
+        progress.total = None;
+        assert_eq!(progress.render(), "2 Indexing files");
+        progress.message = None;
+        progress.total = Some(1.0);
+        assert_eq!(progress.render(), "100%");
+    }
+
     #[tokio::test]
     async fn test_substitute_env_vars() {
         // Set a test environment variable
@@ -671,4 +994,73 @@ mod tests {
         assert_eq!(env_vars.get("KEY1").unwrap(), "Value is test_value");
         assert_eq!(env_vars.get("KEY2").unwrap(), "No substitution");
     }
+
+    #[test]
+    fn test_config_cwd_and_timeouts() {
+        let config: CustomToolConfig = serde_json::from_value(serde_json::json!({
+            "command": "server",
+            "cwd": "~/project",
+            "requestTimeout": 5000,
+            "startupTimeout": 1000
+        }))
+        .unwrap();
+        assert_eq!(config.cwd.as_deref(), Some("~/project"));
+        assert_eq!(config.timeout, 5000);
+        assert_eq!(config.startup_timeout, Some(1000));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_startup_timeout() {
+        use crate::cli::chat::server_messenger::ServerMessengerBuilder;
+
+        let os = Os::new().await.unwrap();
+        let config: CustomToolConfig = serde_json::from_value(serde_json::json!({
+            "command": "sleep",
+            "args": ["10"],
+            "startupTimeout": 100
+        }))
+        .unwrap();
+        let (_receiver, messenger_builder) = ServerMessengerBuilder::new(20);
+        let messenger = messenger_builder.build_with_name("sleepy".to_string());
+
+        let client = McpClientService::new("sleepy".to_string(), config, messenger)
+            .init(&os)
+            .await
+            .unwrap();
+        let InitializedMcpClient::Pending(handle) = client else {
+            panic!("the client should start in the background");
+        };
+        assert!(matches!(
+            handle.await.unwrap(),
+            Err(McpClientError::StartupTimeout(100))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failure_reports_stderr() {
+        use crate::cli::chat::server_messenger::ServerMessengerBuilder;
+
+        let os = Os::new().await.unwrap();
+        let config: CustomToolConfig = serde_json::from_value(serde_json::json!({
+            "command": "sh",
+            "args": ["-c", "echo 'missing API_KEY' >&2; exit 1"]
+        }))
+        .unwrap();
+        let (_receiver, messenger_builder) = ServerMessengerBuilder::new(20);
+        let messenger = messenger_builder.build_with_name("broken".to_string());
+
+        let client = McpClientService::new("broken".to_string(), config, messenger)
+            .init(&os)
+            .await
+            .unwrap();
+        let InitializedMcpClient::Pending(handle) = client else {
+            panic!("the client should start in the background");
+        };
+        match handle.await.unwrap() {
+            Err(McpClientError::WithStderr { stderr, .. }) => assert_eq!(stderr, "missing API_KEY"),
+            other => panic!("expected the error to carry stderr, got {other:?}"),
+        }
+    }
 }
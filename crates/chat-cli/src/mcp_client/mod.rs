@@ -1,6 +1,7 @@
 pub mod client;
 pub mod messenger;
 pub mod oauth_util;
+pub mod stderr_log;
 
 pub use client::*;
 pub use oauth_util::*;
@@ -3,7 +3,15 @@ use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{
+    Arc,
+    PoisonError,
+};
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
 
 use http::{
     HeaderMap,
@@ -38,6 +46,7 @@ use rmcp::{
     Service,
     serde_json,
 };
+use serde::de::DeserializeOwned;
 use serde::{
     Deserialize,
     Serialize,
@@ -56,6 +65,10 @@ use tracing::{
 use url::Url;
 
 use super::messenger::Messenger;
+use crate::database::{
+    Database,
+    DatabaseError,
+};
 use crate::os::Os;
 use crate::util::directories::{
     DirectoryError,
@@ -82,10 +95,12 @@ pub enum OauthUtilError {
     Directory(#[from] DirectoryError),
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
+    #[error(transparent)]
+    Request(#[from] crate::request::RequestError),
+    #[error(transparent)]
+    Database(#[from] DatabaseError),
     #[error("{0}")]
     Http(String),
-    #[error("Malformed directory")]
-    MalformDirectory,
     #[error("Missing credential")]
     MissingCredentials,
     #[error("Failed to create a running service after running through all fallbacks: {0}")]
@@ -136,22 +151,133 @@ impl From<OAuthClientConfig> for Registration {
     }
 }
 
+/// How long before it expires a token is refreshed, so that a request is not sent with a token
+/// that expires on the way.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// A token as kept in the secret store, with when it was obtained so that it can be refreshed
+/// before it expires.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct StoredToken {
+    pub token: OAuthTokenResponse,
+    /// Seconds since the epoch. Unknown for tokens read from the file cache.
+    pub obtained_at: Option<u64>,
+}
+
+impl StoredToken {
+    fn obtained_now(token: OAuthTokenResponse) -> Self {
+        Self {
+            token,
+            obtained_at: SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs()),
+        }
+    }
+
+    /// When the token expires, if both its lifetime and when it was obtained are known.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        // [OAuthTokenResponse::expires_in] needs a trait of oauth2, which we do not depend on.
+        let expires_in = serde_json::to_value(&self.token).ok()?.get("expires_in")?.as_u64()?;
+        Some(UNIX_EPOCH + Duration::from_secs(self.obtained_at? + expires_in))
+    }
+}
+
+/// The OAuth credentials of a remote server, kept in the secret store.
+///
+/// Credentials cached as files in [get_mcp_auth_dir], which is shared with the IDE, are still read
+/// so that servers authorized there need no new sign in. They are never written to.
+#[derive(Clone, Debug)]
+pub struct CredentialStore {
+    database: Database,
+    key: String,
+    file_cache_dir: PathBuf,
+}
+
+impl CredentialStore {
+    pub fn new(os: &Os, url: &Url) -> Result<Self, OauthUtilError> {
+        Ok(Self {
+            database: os.database.clone(),
+            key: compute_key(url),
+            file_cache_dir: get_mcp_auth_dir(os)?,
+        })
+    }
+
+    pub async fn load_token(&self) -> Result<Option<StoredToken>, OauthUtilError> {
+        if let Some(secret) = self.database.get_secret(&self.secret_key("token")).await? {
+            return Ok(Some(serde_json::from_str(&secret.0)?));
+        }
+        Ok(self
+            .read_file_cache::<OAuthTokenResponse>("token")
+            .await?
+            .map(|token| StoredToken {
+                token,
+                obtained_at: None,
+            }))
+    }
+
+    pub async fn save_token(&self, token: &StoredToken) -> Result<(), OauthUtilError> {
+        let token = serde_json::to_string(token)?;
+        Ok(self.database.set_secret(&self.secret_key("token"), &token).await?)
+    }
+
+    /// Forgets the token, so that the next connection goes through the authorization flow again.
+    pub async fn delete_token(&self) -> Result<(), OauthUtilError> {
+        self.database.delete_secret(&self.secret_key("token")).await?;
+        let cached = self.file_cache_path("token");
+        if cached.is_file() {
+            tokio::fs::remove_file(&cached).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn load_registration(&self) -> Result<Option<Registration>, OauthUtilError> {
+        if let Some(secret) = self.database.get_secret(&self.secret_key("registration")).await? {
+            return Ok(Some(serde_json::from_str(&secret.0)?));
+        }
+        self.read_file_cache("registration").await
+    }
+
+    pub async fn save_registration(&self, registration: &Registration) -> Result<(), OauthUtilError> {
+        let registration = serde_json::to_string(registration)?;
+        Ok(self
+            .database
+            .set_secret(&self.secret_key("registration"), &registration)
+            .await?)
+    }
+
+    fn secret_key(&self, kind: &str) -> String {
+        format!("mcp:oauth:{}:{kind}", self.key)
+    }
+
+    fn file_cache_path(&self, kind: &str) -> PathBuf {
+        self.file_cache_dir.join(format!("{}.{kind}.json", self.key))
+    }
+
+    async fn read_file_cache<T: DeserializeOwned>(&self, kind: &str) -> Result<Option<T>, OauthUtilError> {
+        match tokio::fs::read(self.file_cache_path(kind)).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
 /// A wrapper that manages an authenticated MCP client.
 ///
-/// This struct wraps an `AuthClient` and provides access to OAuth credentials
-/// for MCP server connections that require authentication. The credentials
-/// are managed separately from this wrapper's lifecycle.
+/// This struct wraps an `AuthClient` and keeps the OAuth credentials of the server in the
+/// [CredentialStore] up to date as the token is refreshed.
 #[derive(Clone, Debug)]
 pub struct AuthClientWrapper {
-    pub cred_full_path: PathBuf,
+    pub store: CredentialStore,
     pub auth_client: AuthClient<Client>,
+    /// When the current token expires, if known
+    expires_at: Arc<std::sync::Mutex<Option<SystemTime>>>,
 }
 
 impl AuthClientWrapper {
-    pub fn new(cred_full_path: PathBuf, auth_client: AuthClient<Client>) -> Self {
+    pub fn new(store: CredentialStore, auth_client: AuthClient<Client>, expires_at: Option<SystemTime>) -> Self {
         Self {
-            cred_full_path,
+            store,
             auth_client,
+            expires_at: Arc::new(std::sync::Mutex::new(expires_at)),
         }
     }
 
@@ -159,14 +285,25 @@ impl AuthClientWrapper {
     /// spawned. This also persists the retrieved token
     pub async fn refresh_token(&self) -> Result<(), OauthUtilError> {
         let cred = self.auth_client.auth_manager.lock().await.refresh_token().await?;
-        let parent_path = self.cred_full_path.parent().ok_or(OauthUtilError::MalformDirectory)?;
-        tokio::fs::create_dir_all(parent_path).await?;
-
-        let cred_as_bytes = serde_json::to_string_pretty(&cred)?;
-        tokio::fs::write(&self.cred_full_path, &cred_as_bytes).await?;
+        let token = StoredToken::obtained_now(cred);
+        self.store.save_token(&token).await?;
+        *self.expires_at.lock().unwrap_or_else(PoisonError::into_inner) = token.expires_at();
 
         Ok(())
     }
+
+    /// Refreshes the token if it expires within [TOKEN_REFRESH_MARGIN], returning whether it did.
+    pub async fn refresh_if_expiring(&self) -> Result<bool, OauthUtilError> {
+        let expiring = self
+            .expires_at
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_some_and(|expires_at| expires_at <= SystemTime::now() + TOKEN_REFRESH_MARGIN);
+        if expiring {
+            self.refresh_token().await?;
+        }
+        Ok(expiring)
+    }
 }
 
 pub fn get_default_scopes() -> &'static [&'static str] {
@@ -235,14 +372,13 @@ impl<'a> HttpServiceBuilder<'a> {
         } = self;
 
         let mut state = HttpServiceBuilderState::AttemptConnection(TransportType::Http, false);
-        let cred_dir = get_mcp_auth_dir(os)?;
         let url = Url::from_str(url)?;
-        let key = compute_key(&url);
-        let cred_full_path = cred_dir.join(format!("{key}.token.json"));
-        let reg_full_path = cred_dir.join(format!("{key}.registration.json"));
+        let store = CredentialStore::new(os, &url)?;
         let mut auth_client = None::<AuthClient<Client>>;
+        let mut expires_at = None::<SystemTime>;
 
-        let mut client_builder = reqwest::ClientBuilder::new().timeout(std::time::Duration::from_millis(timeout));
+        let mut client_builder =
+            crate::request::new_client_builder()?.timeout(std::time::Duration::from_millis(timeout));
         if !headers.is_empty() {
             let headers = HeaderMap::try_from(headers).map_err(|e| OauthUtilError::Http(e.to_string()))?;
             client_builder = client_builder.default_headers(headers);
@@ -287,14 +423,9 @@ impl<'a> HttpServiceBuilder<'a> {
                         let ac = match auth_client {
                             Some(ref auth_client) => auth_client.clone(),
                             None => {
-                                let am = get_auth_manager(
-                                    url.clone(),
-                                    cred_full_path.clone(),
-                                    reg_full_path.clone(),
-                                    scopes,
-                                    messenger,
-                                )
-                                .await?;
+                                let (am, token_expires_at) =
+                                    get_auth_manager(url.clone(), &store, scopes, messenger).await?;
+                                expires_at = token_expires_at;
 
                                 let ac = AuthClient::new(reqwest_client.clone(), am);
                                 auth_client.replace(ac.clone());
@@ -315,7 +446,7 @@ impl<'a> HttpServiceBuilder<'a> {
 
                                 match service.clone().into_dyn().serve(transport).await {
                                     Ok(service) => {
-                                        let auth_client_wrapper = AuthClientWrapper::new(cred_full_path, ac);
+                                        let auth_client_wrapper = AuthClientWrapper::new(store, ac, expires_at);
                                         return Ok((service, Some(auth_client_wrapper)));
                                     },
                                     Err(e) => {
@@ -347,7 +478,7 @@ impl<'a> HttpServiceBuilder<'a> {
 
                                 match service.clone().into_dyn().serve(transport).await {
                                     Ok(service) => {
-                                        let auth_client_wrapper = AuthClientWrapper::new(cred_full_path, ac);
+                                        let auth_client_wrapper = AuthClientWrapper::new(store, ac, expires_at);
                                         return Ok((service, Some(auth_client_wrapper)));
                                     },
                                     Err(e) => {
@@ -416,7 +547,8 @@ impl<'a> HttpServiceBuilder<'a> {
                 },
                 HttpServiceBuilderState::FailedBecauseTokenMightBeExpired => {
                     let auth_client_ref = auth_client.as_ref().ok_or(OauthUtilError::MissingAuthClient)?;
-                    let auth_client_wrapper = AuthClientWrapper::new(cred_full_path.clone(), auth_client_ref.clone());
+                    let auth_client_wrapper =
+                        AuthClientWrapper::new(store.clone(), auth_client_ref.clone(), expires_at);
                     let refresh_res = auth_client_wrapper.refresh_token().await;
 
                     if let Err(e) = refresh_res {
@@ -426,9 +558,7 @@ impl<'a> HttpServiceBuilder<'a> {
                         // case we would need to have user go through the auth flow
                         // again. We do this by deleting the cred
                         // and discarding the client to trigger a full auth flow
-                        if cred_full_path.is_file() {
-                            tokio::fs::remove_file(&cred_full_path).await?;
-                        }
+                        store.delete_token().await?;
 
                         // we'll also need to remove the auth client to force a reauth when we go
                         // back to attempt the first step again
@@ -447,29 +577,32 @@ impl<'a> HttpServiceBuilder<'a> {
     }
 }
 
+/// Returns the authorization manager of the server, with when its token expires if known. Stored
+/// credentials are used if there are any, otherwise the user is taken through the authorization
+/// flow.
 async fn get_auth_manager(
     url: Url,
-    cred_full_path: PathBuf,
-    reg_full_path: PathBuf,
+    store: &CredentialStore,
     scopes: &[String],
     messenger: &dyn Messenger,
-) -> Result<AuthorizationManager, OauthUtilError> {
-    let cred_as_bytes = tokio::fs::read(&cred_full_path).await;
-    let reg_as_bytes = tokio::fs::read(&reg_full_path).await;
+) -> Result<(AuthorizationManager, Option<SystemTime>), OauthUtilError> {
+    let token = store.load_token().await?;
+    let reg = store.load_registration().await?;
     let mut oauth_state = OAuthState::new(url, None).await?;
 
-    match (cred_as_bytes, reg_as_bytes) {
-        (Ok(cred_as_bytes), Ok(reg_as_bytes)) => {
-            let token = serde_json::from_slice::<OAuthTokenResponse>(&cred_as_bytes)?;
-            let reg = serde_json::from_slice::<Registration>(&reg_as_bytes)?;
-
-            oauth_state.set_credentials(&reg.client_id, token).await?;
+    match (token, reg) {
+        (Some(token), Some(reg)) => {
+            let expires_at = token.expires_at();
+            oauth_state.set_credentials(&reg.client_id, token.token).await?;
 
             debug!("## mcp: credentials set with cache");
 
-            Ok(oauth_state
-                .into_authorization_manager()
-                .ok_or(OauthUtilError::MissingAuthorizationManager)?)
+            Ok((
+                oauth_state
+                    .into_authorization_manager()
+                    .ok_or(OauthUtilError::MissingAuthorizationManager)?,
+                expires_at,
+            ))
         },
         _ => {
             info!("Error reading cached credentials");
@@ -489,19 +622,13 @@ async fn get_auth_manager(
                     .collect::<Vec<_>>(),
                 redirect_uri,
             };
-            let reg_as_str = serde_json::to_string_pretty(&reg)?;
-            let reg_parent_path = reg_full_path.parent().ok_or(OauthUtilError::MalformDirectory)?;
-            tokio::fs::create_dir_all(reg_parent_path).await?;
-            tokio::fs::write(reg_full_path, &reg_as_str).await?;
+            store.save_registration(&reg).await?;
 
             let credentials = credentials.ok_or(OauthUtilError::MissingCredentials)?;
+            let token = StoredToken::obtained_now(credentials);
+            store.save_token(&token).await?;
 
-            let cred_parent_path = cred_full_path.parent().ok_or(OauthUtilError::MalformDirectory)?;
-            tokio::fs::create_dir_all(cred_parent_path).await?;
-            let reg_as_str = serde_json::to_string_pretty(&credentials)?;
-            tokio::fs::write(cred_full_path, &reg_as_str).await?;
-
-            Ok(am)
+            Ok((am, token.expires_at()))
         },
     }
 }
@@ -717,3 +844,55 @@ async fn make_svc(
 
     Ok((actual_addr, dg))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_store(file_cache_dir: PathBuf) -> CredentialStore {
+        let os = Os::new().await.unwrap();
+        CredentialStore {
+            database: os.database.clone(),
+            key: compute_key(&Url::parse("https://example.com/mcp").unwrap()),
+            file_cache_dir,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_credential_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = test_store(dir.path().to_path_buf()).await;
+        assert!(store.load_token().await.unwrap().is_none());
+        assert!(store.load_registration().await.unwrap().is_none());
+
+        // Credentials cached as files are read, without a known expiry.
+        let token = get_stub_credentials().unwrap();
+        std::fs::write(store.file_cache_path("token"), serde_json::to_string(&token).unwrap()).unwrap();
+        let cached = store.load_token().await.unwrap().unwrap();
+        assert_eq!(cached.obtained_at, None);
+        assert_eq!(cached.expires_at(), None);
+
+        // The secret store takes precedence, and knows when the token expires.
+        let stored = StoredToken::obtained_now(token);
+        store.save_token(&stored).await.unwrap();
+        let loaded = store.load_token().await.unwrap().unwrap();
+        assert_eq!(loaded.obtained_at, stored.obtained_at);
+        assert_eq!(
+            loaded.expires_at(),
+            Some(UNIX_EPOCH + Duration::from_secs(stored.obtained_at.unwrap() + 3600))
+        );
+
+        let registration = Registration {
+            client_id: "client".to_string(),
+            client_secret: None,
+            scopes: vec![],
+            redirect_uri: "http://127.0.0.1:1234".to_string(),
+        };
+        store.save_registration(&registration).await.unwrap();
+        assert_eq!(store.load_registration().await.unwrap().unwrap().client_id, "client");
+
+        store.delete_token().await.unwrap();
+        assert!(store.load_token().await.unwrap().is_none());
+        assert!(!store.file_cache_path("token").exists());
+    }
+}
@@ -0,0 +1,217 @@
+//! Capturing what stdio MCP servers write to their stderr.
+//!
+//! The stderr of a server goes to a log file of its own under the log dir, which is rotated once it
+//! grows past [MAX_LOG_BYTES]. Its last lines are also kept in memory, so that errors of the
+//! server can say what it logged before it failed.
+
+use std::collections::VecDeque;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+    PoisonError,
+};
+
+use tokio::io::{
+    AsyncBufReadExt,
+    AsyncWriteExt,
+    BufReader,
+};
+use tokio::process::ChildStderr;
+use tokio::task::JoinHandle;
+use tracing::{
+    debug,
+    warn,
+};
+
+use crate::util::directories::{
+    DirectoryError,
+    logs_dir,
+};
+
+/// How many of the last lines of stderr are kept, and shown by default.
+pub const EXCERPT_LINES: usize = 20;
+
+/// The size past which a log file is rotated. The previous file is kept with a `.1` suffix.
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// The log file of the stderr of a server.
+pub fn server_log_path(server_name: &str) -> Result<PathBuf, DirectoryError> {
+    let file_name = server_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    Ok(logs_dir()?.join("mcp").join(format!("{file_name}.log")))
+}
+
+/// Returns the last `n` lines of the log file at `path`, or none if there is no such file.
+pub async fn tail(path: &Path, n: usize) -> std::io::Result<Vec<String>> {
+    let content = match tokio::fs::read(path).await {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let content = String::from_utf8_lossy(&content);
+    let lines = content.lines().collect::<Vec<_>>();
+    Ok(lines[lines.len().saturating_sub(n)..]
+        .iter()
+        .map(|line| (*line).to_string())
+        .collect())
+}
+
+/// The last lines a server wrote to its stderr.
+#[derive(Clone, Debug, Default)]
+pub struct StderrLog {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl StderrLog {
+    /// Reads `stderr` until the server closes it, appending it to the log file at `path` and
+    /// keeping its last lines. The returned task ends once stderr is closed.
+    pub fn capture(&self, server_name: String, path: PathBuf, stderr: ChildStderr) -> JoinHandle<()> {
+        let log = self.clone();
+        tokio::spawn(async move {
+            let mut writer = LogWriter::open(path).await;
+            let mut reader = BufReader::new(stderr);
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                match reader.read_until(b'\n', &mut line).await {
+                    Ok(0) => {
+                        debug!(target: "mcp", "{server_name} closed its stderr");
+                        break;
+                    },
+                    Ok(_) => {
+                        if let Some(writer) = writer.as_mut() {
+                            writer.write(&line).await;
+                        }
+                        log.push(String::from_utf8_lossy(&line).trim_end().to_string());
+                    },
+                    Err(err) => {
+                        debug!(target: "mcp", "Stopped reading the stderr of {server_name}: {err}");
+                        break;
+                    },
+                }
+            }
+        })
+    }
+
+    /// The last lines of stderr, or none if the server did not write to it.
+    pub fn excerpt(&self) -> Option<String> {
+        let lines = self.lines.lock().unwrap_or_else(PoisonError::into_inner);
+        (!lines.is_empty()).then(|| lines.iter().cloned().collect::<Vec<_>>().join("\n"))
+    }
+
+    fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap_or_else(PoisonError::into_inner);
+        if lines.len() == EXCERPT_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+}
+
+/// Appends to a log file, rotating it once it grows past [MAX_LOG_BYTES].
+struct LogWriter {
+    path: PathBuf,
+    file: tokio::fs::File,
+    len: u64,
+}
+
+impl LogWriter {
+    /// Opens the log file at `path`. Failing that, stderr is still kept in memory, so this only
+    /// warns.
+    async fn open(path: PathBuf) -> Option<Self> {
+        match Self::try_open(path).await {
+            Ok(writer) => Some(writer),
+            Err(err) => {
+                warn!(target: "mcp", ?err, "Failed to open the log file of an MCP server");
+                None
+            },
+        }
+    }
+
+    async fn try_open(path: PathBuf) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .await?;
+        let len = file.metadata().await?.len();
+        Ok(Self { path, file, len })
+    }
+
+    async fn write(&mut self, line: &[u8]) {
+        if self.len >= MAX_LOG_BYTES {
+            if let Err(err) = self.rotate().await {
+                warn!(target: "mcp", ?err, "Failed to rotate the log file of an MCP server");
+            }
+        }
+        match self.file.write_all(line).await {
+            Ok(()) => self.len += line.len() as u64,
+            Err(err) => warn!(target: "mcp", ?err, "Failed to write to the log file of an MCP server"),
+        }
+    }
+
+    async fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush().await?;
+        let mut rotated = self.path.clone().into_os_string();
+        rotated.push(".1");
+        tokio::fs::rename(&self.path, rotated).await?;
+        *self = Self::try_open(self.path.clone()).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_log_writer_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mcp").join("server.log");
+        let mut writer = LogWriter::try_open(path.clone()).await.unwrap();
+        writer.len = MAX_LOG_BYTES - 1;
+        writer.write(b"before\n").await;
+        writer.write(b"after\n").await;
+        writer.file.flush().await.unwrap();
+
+        assert_eq!(tail(&path, EXCERPT_LINES).await.unwrap(), vec!["after"]);
+        assert_eq!(
+            tail(&dir.path().join("mcp").join("server.log.1"), 1).await.unwrap(),
+            vec!["before"]
+        );
+        assert!(tail(&dir.path().join("missing.log"), 1).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_excerpt_keeps_last_lines() {
+        let log = StderrLog::default();
+        assert_eq!(log.excerpt(), None);
+        for i in 0..EXCERPT_LINES + 2 {
+            log.push(format!("line {i}"));
+        }
+        let excerpt = log.excerpt().unwrap();
+        assert!(excerpt.starts_with("line 2\n"));
+        assert!(excerpt.ends_with(&format!("line {}", EXCERPT_LINES + 1)));
+    }
+
+    #[test]
+    fn test_server_log_path() {
+        let path = server_log_path("my/server name").unwrap();
+        assert_eq!(path.file_name().unwrap(), "my_server_name.log");
+    }
+}
@@ -11,6 +11,7 @@ use time::OffsetDateTime;
 use time::format_description::well_known::Rfc3339;
 
 use crate::os::Env;
+use crate::os::package_health::PackageHealth;
 use crate::telemetry::InstallMethod;
 use crate::util::consts::build::HASH;
 use crate::util::system_info::{
@@ -203,6 +204,7 @@ pub struct Diagnostics {
     pub build_details: BuildDetails,
     pub system_info: SystemInfo,
     pub environment: CurrentEnvironment,
+    pub package_health: PackageHealth,
     #[serde(flatten)]
     pub environment_variables: EnvVarDiagnostic,
 }
@@ -213,6 +215,7 @@ impl Diagnostics {
             build_details: BuildDetails::new(),
             system_info: SystemInfo::new(),
             environment: CurrentEnvironment::new(env).await,
+            package_health: PackageHealth::new(env),
             environment_variables: EnvVarDiagnostic::new(),
         }
     }
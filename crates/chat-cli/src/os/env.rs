@@ -132,6 +132,18 @@ impl Env {
         }
     }
 
+    /// Changes the working directory of the process, or of the fake environment in tests.
+    pub fn set_current_dir(&self, path: impl Into<PathBuf>) -> Result<(), io::Error> {
+        use inner::Inner;
+        match &self.0 {
+            Inner::Real => std::env::set_current_dir(path.into()),
+            Inner::Fake(fake) => {
+                fake.lock().unwrap().cwd = path.into();
+                Ok(())
+            },
+        }
+    }
+
     pub fn set_current_dir_for_test(&self, path: PathBuf) {
         use inner::Inner;
         if let Inner::Fake(fake) = &self.0 {
@@ -3,16 +3,29 @@
 pub mod diagnostics;
 mod env;
 mod fs;
+pub mod package_health;
 mod sysinfo;
 
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{
+    SystemTime,
+    UNIX_EPOCH,
+};
+
 pub use env::Env;
 use eyre::Result;
 pub use fs::Fs;
 pub use sysinfo::SysInfo;
+use tokio::sync::OnceCell;
 
 use crate::api_client::ApiClient;
 use crate::database::Database;
 use crate::telemetry::TelemetryThread;
+use crate::util::{
+    directories,
+    shutdown,
+};
 
 const WINDOWS_USER_HOME: &str = "C:\\Users\\testuser";
 const UNIX_USER_HOME: &str = "/home/testuser";
@@ -38,6 +51,7 @@ pub struct Os {
     pub database: Database,
     pub client: ApiClient,
     pub telemetry: TelemetryThread,
+    session_temp_dir: Arc<OnceCell<PathBuf>>,
 }
 
 impl Os {
@@ -45,6 +59,7 @@ impl Os {
         let env = Env::new();
         let fs = Fs::new();
         let mut database = Database::new().await?;
+        crate::aws_common::endpoint_overrides::init(&database);
         let client = ApiClient::new(&env, &fs, &mut database, None).await?;
         let telemetry = TelemetryThread::new(&env, &fs, &mut database).await?;
 
@@ -55,14 +70,51 @@ impl Os {
             database,
             client,
             telemetry,
+            session_temp_dir: Arc::default(),
         })
     }
+
+    /// The temporary directory of this session, for the files tools produce on the side, like
+    /// spilled output or extracted archives. It is created on first use and removed when the
+    /// process exits. The directories of sessions that were killed are removed by the garbage
+    /// collection of the agent registry, which tells them apart by the pid and creation time in
+    /// their names.
+    pub async fn session_temp_dir(&self) -> Result<PathBuf> {
+        let path = self
+            .session_temp_dir
+            .get_or_try_init(|| async {
+                let created_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                let path = directories::session_temp_root()?.join(format!("{}-{created_at}", std::process::id()));
+                self.fs.create_dir_all(&path).await?;
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    self.fs
+                        .set_permissions(&path, std::fs::Permissions::from_mode(0o700))
+                        .await?;
+                }
+                shutdown::register_path(self.fs.chroot_path(&path));
+                Ok::<_, eyre::Report>(path)
+            })
+            .await?;
+        Ok(path.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_session_temp_dir() {
+        let os = Os::new().await.unwrap();
+        let dir = os.session_temp_dir().await.unwrap();
+        assert!(os.fs.exists(&dir));
+        assert_eq!(os.clone().session_temp_dir().await.unwrap(), dir);
+        let name = dir.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with(&format!("{}-", std::process::id())), "{name}");
+    }
+
     #[tokio::test]
     async fn test_context_builder_with_test_home() {
         let os = Os::new().await.unwrap();
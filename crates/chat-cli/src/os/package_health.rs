@@ -0,0 +1,247 @@
+//! Checking that the package q was installed with is intact, and how to repair it when it isn't.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process::Command;
+
+use serde::Serialize;
+
+use crate::os::Env;
+use crate::telemetry::{
+    InstallMethod,
+    get_install_method,
+};
+use crate::util::{
+    CHAT_BINARY_NAME,
+    CLI_BINARY_NAME,
+};
+
+const PACKAGE_NAME: &str = "amazon-q";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackageIssue {
+    pub problem: String,
+    /// The command repairing the install.
+    pub repair: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PackageHealth {
+    pub package_version: Option<String>,
+    pub binary_version: String,
+    pub issues: Vec<PackageIssue>,
+}
+
+impl PackageHealth {
+    pub fn new(env: &Env) -> Self {
+        let install_method = get_install_method();
+        let probe = Probe {
+            install_method,
+            package_version: package_version(install_method),
+            binary_version: env!("CARGO_PKG_VERSION").to_string(),
+            dangling_links: dangling_links(env),
+            completions_found: completions_found(install_method),
+        };
+        Self {
+            issues: probe.issues(),
+            package_version: probe.package_version,
+            binary_version: probe.binary_version,
+        }
+    }
+}
+
+/// What was found out about the install.
+#[derive(Debug)]
+struct Probe {
+    install_method: InstallMethod,
+    package_version: Option<String>,
+    binary_version: String,
+    /// Symlinks to q on the `PATH` whose target is gone.
+    dangling_links: Vec<PathBuf>,
+    /// Whether the package's shell completions are installed, `None` if it doesn't ship any.
+    completions_found: Option<bool>,
+}
+
+impl Probe {
+    fn issues(&self) -> Vec<PackageIssue> {
+        let method = self.install_method;
+        let reinstall = reinstall_command(method).map(str::to_string);
+        let mut issues = self
+            .dangling_links
+            .iter()
+            .map(|link| PackageIssue {
+                problem: format!("{} is a symlink to a file that doesn't exist", link.display()),
+                repair: Some(remove_command(link)),
+            })
+            .collect::<Vec<_>>();
+
+        if reinstall.is_some() {
+            match &self.package_version {
+                None => issues.push(PackageIssue {
+                    problem: format!("q looks installed with {method}, but the {PACKAGE_NAME} package can't be found"),
+                    repair: reinstall.clone(),
+                }),
+                Some(version) if *version != self.binary_version => issues.push(PackageIssue {
+                    problem: format!(
+                        "The {method} package is version {version}, but the running binary is version {}",
+                        self.binary_version
+                    ),
+                    repair: reinstall.clone(),
+                }),
+                Some(_) => {},
+            }
+        }
+
+        if self.completions_found == Some(false) {
+            issues.push(PackageIssue {
+                problem: format!("The shell completions of the {method} package are missing"),
+                repair: reinstall,
+            });
+        }
+        issues
+    }
+}
+
+fn reinstall_command(method: InstallMethod) -> Option<&'static str> {
+    match method {
+        InstallMethod::Brew => Some("brew reinstall amazon-q"),
+        InstallMethod::Apt => Some("sudo apt-get install --reinstall amazon-q"),
+        InstallMethod::Dnf => Some("sudo dnf reinstall amazon-q"),
+        InstallMethod::Toolbox | InstallMethod::Unknown => None,
+    }
+}
+
+fn remove_command(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let quoted = shlex::try_quote(&path).map_or_else(|_| format!("\"{path}\""), |quoted| quoted.into_owned());
+    match cfg!(windows) {
+        true => format!("del {quoted}"),
+        false => format!("rm {quoted}"),
+    }
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The version of the installed package, without the packaging revision.
+fn package_version(method: InstallMethod) -> Option<String> {
+    let version = match method {
+        // `amazon-q 1.12.0`
+        InstallMethod::Brew => command_output("brew", &["list", "--versions", PACKAGE_NAME])?
+            .split_whitespace()
+            .last()?
+            .to_string(),
+        InstallMethod::Apt => command_output("dpkg-query", &["-W", "-f=${Version}", PACKAGE_NAME])?,
+        InstallMethod::Dnf => command_output("rpm", &["-q", "--qf", "%{VERSION}", PACKAGE_NAME])?,
+        InstallMethod::Toolbox | InstallMethod::Unknown => return None,
+    };
+    Some(strip_revision(&version).to_string())
+}
+
+/// `1.12.0` from `1.12.0-1` or `1.12.0_1`.
+fn strip_revision(version: &str) -> &str {
+    version.split(['-', '_', '+', '~']).next().unwrap_or(version)
+}
+
+fn dangling_links(env: &Env) -> Vec<PathBuf> {
+    let Ok(path) = env.get("PATH") else {
+        return Vec::new();
+    };
+    std::env::split_paths(&path)
+        .flat_map(|dir| [dir.join(CLI_BINARY_NAME), dir.join(CHAT_BINARY_NAME)])
+        .filter(|path| path.symlink_metadata().is_ok_and(|m| m.file_type().is_symlink()) && path.metadata().is_err())
+        .collect()
+}
+
+fn completions_found(method: InstallMethod) -> Option<bool> {
+    let prefix = match method {
+        InstallMethod::Brew => PathBuf::from(command_output("brew", &["--prefix"])?),
+        InstallMethod::Apt | InstallMethod::Dnf => PathBuf::from("/usr"),
+        InstallMethod::Toolbox | InstallMethod::Unknown => return None,
+    };
+    Some(
+        [
+            "share/zsh/site-functions/_q",
+            "share/zsh/vendor-completions/_q",
+            "share/bash-completion/completions/q",
+            "etc/bash_completion.d/q",
+            "share/fish/vendor_completions.d/q.fish",
+        ]
+        .iter()
+        .any(|path| prefix.join(path).exists()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(install_method: InstallMethod) -> Probe {
+        Probe {
+            install_method,
+            package_version: Some("1.12.0".to_string()),
+            binary_version: "1.12.0".to_string(),
+            dangling_links: vec![],
+            completions_found: Some(true),
+        }
+    }
+
+    #[test]
+    fn test_healthy_install() {
+        assert!(probe(InstallMethod::Brew).issues().is_empty());
+        assert!(
+            Probe {
+                package_version: None,
+                completions_found: None,
+                ..probe(InstallMethod::Unknown)
+            }
+            .issues()
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_broken_install() {
+        let issues = Probe {
+            package_version: Some("1.11.0".to_string()),
+            dangling_links: vec![PathBuf::from("/usr/local/bin/q")],
+            completions_found: Some(false),
+            ..probe(InstallMethod::Apt)
+        }
+        .issues();
+        assert_eq!(issues.len(), 3);
+        assert_eq!(issues[0].repair, Some(remove_command(Path::new("/usr/local/bin/q"))));
+        assert_eq!(
+            issues[1].problem,
+            "The apt package is version 1.11.0, but the running binary is version 1.12.0"
+        );
+        assert_eq!(
+            issues[1].repair.as_deref(),
+            Some("sudo apt-get install --reinstall amazon-q")
+        );
+        assert!(issues[2].problem.contains("completions"));
+
+        let issues = Probe {
+            package_version: None,
+            ..probe(InstallMethod::Dnf)
+        }
+        .issues();
+        assert_eq!(issues[0].repair.as_deref(), Some("sudo dnf reinstall amazon-q"));
+    }
+
+    #[test]
+    fn test_strip_revision() {
+        assert_eq!(strip_revision("1.12.0-1"), "1.12.0");
+        assert_eq!(strip_revision("1.12.0_1"), "1.12.0");
+        assert_eq!(strip_revision("1.12.0"), "1.12.0");
+    }
+}
@@ -1,17 +1,38 @@
 use std::env::current_exe;
+use std::path::Path;
 use std::sync::{
     Arc,
     LazyLock,
 };
 
 use reqwest::Client;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{
+    HandshakeSignatureValid,
+    ServerCertVerified,
+    ServerCertVerifier,
+};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::{
+    CertificateDer,
+    ServerName,
+    UnixTime,
+};
 use rustls::{
     ClientConfig,
+    DigitallySignedStruct,
     RootCertStore,
+    SignatureScheme,
 };
 use thiserror::Error;
 use url::ParseError;
 
+use crate::aws_common::endpoint_overrides::{
+    EndpointOverrides,
+    endpoint_overrides,
+};
+use crate::util::consts::env_var::Q_FIPS_MODE;
+
 #[derive(Debug, Error)]
 pub enum RequestError {
     #[error(transparent)]
@@ -26,14 +47,77 @@ pub enum RequestError {
     Settings(#[from] crate::database::DatabaseError),
     #[error(transparent)]
     UrlParseError(#[from] ParseError),
+    #[error(
+        "FIPS compliance mode is enabled but the TLS provider is not FIPS-validated, use a build with the `fips` feature enabled"
+    )]
+    NonCompliantCryptoProvider,
+    #[error("Invalid TLS server name override '{0}'")]
+    InvalidTlsServerName(String),
+    #[error("Failed to load the TLS CA bundle: {0}")]
+    TlsCaBundle(String),
+}
+
+/// Whether FIPS compliance mode is enabled, either at build time with the `fips` feature or at
+/// runtime with [Q_FIPS_MODE].
+pub fn fips_mode_enabled() -> bool {
+    cfg!(feature = "fips") || std::env::var(Q_FIPS_MODE).is_ok_and(|v| matches!(v.as_str(), "1" | "true"))
+}
+
+/// Installs the process-wide TLS crypto provider used by every outbound client, including the
+/// ones created by dependencies.
+///
+/// In FIPS compliance mode this returns an error if the provider is not FIPS-validated, callers
+/// are expected to refuse to start.
+pub fn install_crypto_provider() -> Result<(), RequestError> {
+    // Ignore the error if a provider was already installed, what matters is the one in use.
+    let _ = default_crypto_provider().install_default();
+
+    if fips_mode_enabled() && !CryptoProvider::get_default().is_some_and(|provider| provider.fips()) {
+        return Err(RequestError::NonCompliantCryptoProvider);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "fips")]
+fn default_crypto_provider() -> CryptoProvider {
+    rustls::crypto::default_fips_provider()
+}
+
+#[cfg(not(feature = "fips"))]
+fn default_crypto_provider() -> CryptoProvider {
+    rustls::crypto::ring::default_provider()
 }
 
 pub fn new_client() -> Result<Client, RequestError> {
+    new_client_builder()?.build().map_err(Into::into)
+}
+
+/// A [reqwest::ClientBuilder] configured with the shared TLS config and user agent, for callers
+/// that need to customize the client further.
+pub fn new_client_builder() -> Result<reqwest::ClientBuilder, RequestError> {
+    client_builder(&EndpointOverrides::default())
+}
+
+/// A [reqwest::ClientBuilder] for the Q service clients, which also applies the TLS overrides
+/// (`api.tls.serverName` and `api.tls.caBundle`).
+///
+/// The overrides are meant for reaching Q through a VPC endpoint, so they must not apply to
+/// other hosts, such as MCP servers, whose certificates would fail the server name check.
+pub fn new_q_client_builder() -> Result<reqwest::ClientBuilder, RequestError> {
+    client_builder(endpoint_overrides())
+}
+
+fn client_builder(overrides: &EndpointOverrides) -> Result<reqwest::ClientBuilder, RequestError> {
+    let tls_config = client_config(overrides)?;
+    if fips_mode_enabled() && !tls_config.fips() {
+        return Err(RequestError::NonCompliantCryptoProvider);
+    }
+
     Ok(Client::builder()
-        .use_preconfigured_tls(client_config())
+        .use_preconfigured_tls(tls_config)
         .user_agent(USER_AGENT.chars().filter(|c| c.is_ascii_graphic()).collect::<String>())
-        .cookie_store(true)
-        .build()?)
+        .cookie_store(true))
 }
 
 pub fn create_default_root_cert_store() -> RootCertStore {
@@ -49,16 +133,103 @@ pub fn create_default_root_cert_store() -> RootCertStore {
     root_cert_store
 }
 
-fn client_config() -> ClientConfig {
+/// Checks that the TLS overrides of `overrides` can be used, i.e. that the CA bundle can be read
+/// and the server name is a valid hostname.
+pub fn check_tls_overrides(overrides: &EndpointOverrides) -> Result<(), RequestError> {
+    client_config(overrides).map(|_| ())
+}
+
+fn client_config(overrides: &EndpointOverrides) -> Result<ClientConfig, RequestError> {
     let provider = rustls::crypto::CryptoProvider::get_default()
         .cloned()
-        .unwrap_or_else(|| Arc::new(rustls::crypto::ring::default_provider()));
+        .unwrap_or_else(|| Arc::new(default_crypto_provider()));
 
-    ClientConfig::builder_with_provider(provider)
+    let mut root_cert_store = create_default_root_cert_store();
+    if let Some(path) = &overrides.tls_ca_bundle {
+        for cert in load_ca_bundle(path)? {
+            root_cert_store
+                .add(cert)
+                .map_err(|err| RequestError::TlsCaBundle(err.to_string()))?;
+        }
+    }
+
+    let builder = ClientConfig::builder_with_provider(provider.clone())
         .with_protocol_versions(rustls::DEFAULT_VERSIONS)
-        .expect("Failed to set supported TLS versions")
-        .with_root_certificates(create_default_root_cert_store())
-        .with_no_client_auth()
+        .expect("Failed to set supported TLS versions");
+
+    Ok(match &overrides.tls_server_name {
+        Some(server_name) => {
+            let verifier = ServerNameOverrideVerifier::new(root_cert_store, provider, server_name)?;
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(verifier))
+                .with_no_client_auth()
+        },
+        None => builder.with_root_certificates(root_cert_store).with_no_client_auth(),
+    })
+}
+
+fn load_ca_bundle(path: &Path) -> Result<Vec<CertificateDer<'static>>, RequestError> {
+    let file =
+        std::fs::File::open(path).map_err(|err| RequestError::TlsCaBundle(format!("{}: {err}", path.display())))?;
+    rustls_pemfile::certs(&mut std::io::BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| RequestError::TlsCaBundle(format!("{}: {err}", path.display())))
+}
+
+/// Validates server certificates against a fixed hostname rather than the hostname being
+/// connected to, for VPC endpoints whose DNS names are not on the service certificate.
+#[derive(Debug)]
+struct ServerNameOverrideVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    server_name: ServerName<'static>,
+}
+
+impl ServerNameOverrideVerifier {
+    fn new(roots: RootCertStore, provider: Arc<CryptoProvider>, server_name: &str) -> Result<Self, RequestError> {
+        let server_name = ServerName::try_from(server_name.to_string())
+            .map_err(|_err| RequestError::InvalidTlsServerName(server_name.to_string()))?;
+        let inner = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+            .build()
+            .map_err(|err| RequestError::TlsCaBundle(err.to_string()))?;
+        Ok(Self { inner, server_name })
+    }
+}
+
+impl ServerCertVerifier for ServerNameOverrideVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner
+            .verify_server_cert(end_entity, intermediates, &self.server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
 }
 
 static USER_AGENT: LazyLock<String> = LazyLock::new(|| {
@@ -83,6 +254,47 @@ mod tests {
         new_client().unwrap();
     }
 
+    #[test]
+    fn test_server_name_override_verifier() {
+        let provider = Arc::new(default_crypto_provider());
+        assert!(
+            ServerNameOverrideVerifier::new(
+                create_default_root_cert_store(),
+                provider.clone(),
+                "q.us-east-1.amazonaws.com"
+            )
+            .is_ok()
+        );
+        assert!(matches!(
+            ServerNameOverrideVerifier::new(create_default_root_cert_store(), provider, "not a hostname"),
+            Err(RequestError::InvalidTlsServerName(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_tls_overrides() {
+        assert!(check_tls_overrides(&EndpointOverrides::default()).is_ok());
+        assert!(matches!(
+            check_tls_overrides(&EndpointOverrides {
+                tls_ca_bundle: Some("/nonexistent/ca-bundle.pem".into()),
+                ..Default::default()
+            }),
+            Err(RequestError::TlsCaBundle(_))
+        ));
+        assert!(matches!(
+            check_tls_overrides(&EndpointOverrides {
+                tls_server_name: Some("not a hostname".into()),
+                ..Default::default()
+            }),
+            Err(RequestError::InvalidTlsServerName(_))
+        ));
+    }
+
+    #[test]
+    fn test_default_crypto_provider_fips() {
+        assert_eq!(default_crypto_provider().fips(), cfg!(feature = "fips"));
+    }
+
     #[tokio::test]
     async fn request_test() {
         let mut server = mockito::Server::new_async().await;
@@ -0,0 +1,98 @@
+//! The categories telemetry events fall in, each of which can be turned off with its
+//! `telemetry.*` setting.
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::core::EventType;
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, strum::Display, strum::EnumIter)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum TelemetryCategory {
+    /// Which commands and features are used.
+    Usage,
+    /// Failed requests.
+    Errors,
+    /// Messages and tool uses, without their content.
+    ChatMetadata,
+    /// The code written by the agent that was kept.
+    Completions,
+}
+
+impl TelemetryCategory {
+    pub fn setting(self) -> Setting {
+        match self {
+            Self::Usage => Setting::TelemetryUsage,
+            Self::Errors => Setting::TelemetryErrors,
+            Self::ChatMetadata => Setting::TelemetryChatMetadata,
+            Self::Completions => Setting::TelemetryCompletions,
+        }
+    }
+
+    /// Whether events of the category are sent, which they are unless turned off.
+    pub fn is_enabled(self, settings: &Settings) -> bool {
+        settings.get_bool(self.setting()).unwrap_or(true)
+    }
+}
+
+impl EventType {
+    pub fn category(&self) -> TelemetryCategory {
+        match self {
+            Self::UserLoggedIn {}
+            | Self::RefreshCredentials { .. }
+            | Self::CliSubcommandExecuted { .. }
+            | Self::ChatSlashCommandExecuted { .. }
+            | Self::ChatStart { .. }
+            | Self::ChatEnd { .. }
+            | Self::TangentModeSession { .. }
+            | Self::McpServerInit { .. }
+            | Self::AgentConfigInit { .. }
+            | Self::DidSelectProfile { .. }
+            | Self::ProfileState { .. }
+            | Self::DailyHeartbeat {}
+            | Self::CompactHistory { .. }
+            | Self::CompactionFeedback { .. } => TelemetryCategory::Usage,
+            Self::MessageResponseError { .. } => TelemetryCategory::Errors,
+            Self::ChatAddedMessage { .. } | Self::RecordUserTurnCompletion { .. } | Self::ToolUseSuggested { .. } => {
+                TelemetryCategory::ChatMetadata
+            },
+            Self::AgentContribution { .. } => TelemetryCategory::Completions,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    #[tokio::test]
+    async fn test_category_is_enabled() {
+        let mut database = Database::new().await.unwrap();
+        assert!(TelemetryCategory::Errors.is_enabled(&database.settings));
+
+        database.settings.set(Setting::TelemetryErrors, false).await.unwrap();
+        assert!(!TelemetryCategory::Errors.is_enabled(&database.settings));
+        assert!(TelemetryCategory::Usage.is_enabled(&database.settings));
+    }
+
+    #[test]
+    fn test_event_category() {
+        assert_eq!(
+            EventType::CliSubcommandExecuted {
+                subcommand: "chat".to_string()
+            }
+            .category(),
+            TelemetryCategory::Usage
+        );
+        assert_eq!(EventType::DailyHeartbeat {}.category(), TelemetryCategory::Usage);
+        assert_eq!(TelemetryCategory::ChatMetadata.to_string(), "chatMetadata");
+    }
+}
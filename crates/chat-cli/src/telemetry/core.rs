@@ -25,6 +25,8 @@ use crate::telemetry::definitions::metrics::{
     CodewhispererterminalAgentContribution,
     CodewhispererterminalChatSlashCommandExecuted,
     CodewhispererterminalCliSubcommandExecuted,
+    CodewhispererterminalCompactHistory,
+    CodewhispererterminalCompactionFeedback,
     CodewhispererterminalMcpServerInit,
     CodewhispererterminalRefreshCredentials,
     CodewhispererterminalToolUseSuggested,
@@ -508,6 +510,56 @@ impl Event {
                 }
                 .into_metric_datum(),
             ),
+            EventType::CompactHistory {
+                conversation_id,
+                result,
+                args:
+                    CompactHistoryArgs {
+                        request_id,
+                        model,
+                        tokens_before,
+                        tokens_after,
+                        latency_ms,
+                        prompt_variant,
+                    },
+            } => Some(
+                CodewhispererterminalCompactHistory {
+                    create_time: self.created_time,
+                    value: None,
+                    credential_start_url: self.credential_start_url.map(Into::into),
+                    sso_region: self.sso_region.map(Into::into),
+                    amazonq_conversation_id: Some(conversation_id.into()),
+                    request_id: request_id.map(Into::into),
+                    result: Some(result.to_string().into()),
+                    codewhispererterminal_model: model.map(Into::into),
+                    codewhispererterminal_compaction_tokens_before: Some(tokens_before.into()),
+                    codewhispererterminal_compaction_tokens_after: Some(tokens_after.into()),
+                    codewhispererterminal_compaction_latency_ms: Some(latency_ms.into()),
+                    codewhispererterminal_compaction_prompt_variant: Some(prompt_variant.into()),
+                }
+                .into_metric_datum(),
+            ),
+            EventType::CompactionFeedback {
+                conversation_id,
+                args:
+                    CompactionFeedbackArgs {
+                        request_id,
+                        prompt_variant,
+                        turns_since_compaction,
+                    },
+            } => Some(
+                CodewhispererterminalCompactionFeedback {
+                    create_time: self.created_time,
+                    value: None,
+                    credential_start_url: self.credential_start_url.map(Into::into),
+                    sso_region: self.sso_region.map(Into::into),
+                    amazonq_conversation_id: Some(conversation_id.into()),
+                    request_id: request_id.map(Into::into),
+                    codewhispererterminal_compaction_prompt_variant: Some(prompt_variant.into()),
+                    codewhispererterminal_turns_since_compaction: Some(turns_since_compaction.into()),
+                }
+                .into_metric_datum(),
+            ),
         }
     }
 }
@@ -580,6 +632,28 @@ pub struct RecordUserTurnCompletionArgs {
     pub message_meta_tags: Vec<MessageMetaTag>,
 }
 
+/// Fields for the compact history telemetry event.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
+pub struct CompactHistoryArgs {
+    pub request_id: Option<String>,
+    pub model: Option<String>,
+    pub tokens_before: i64,
+    pub tokens_after: i64,
+    /// Time taken to generate the summary
+    pub latency_ms: i64,
+    /// The summarization prompt used, so prompt changes can be compared
+    pub prompt_variant: String,
+}
+
+/// Fields for the compaction feedback telemetry event.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
+pub struct CompactionFeedbackArgs {
+    /// Request id of the compaction the feedback applies to
+    pub request_id: Option<String>,
+    pub prompt_variant: String,
+    pub turns_since_compaction: i64,
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
 pub struct AgentConfigInitArgs {
     pub agents_loaded_count: i64,
@@ -699,6 +773,15 @@ pub enum EventType {
         context_file_length: Option<usize>,
     },
     DailyHeartbeat {},
+    CompactHistory {
+        conversation_id: String,
+        result: TelemetryResult,
+        args: CompactHistoryArgs,
+    },
+    CompactionFeedback {
+        conversation_id: String,
+        args: CompactionFeedbackArgs,
+    },
 }
 
 #[derive(Debug)]
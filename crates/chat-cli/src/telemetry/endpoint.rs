@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 use amzn_toolkit_telemetry_client::config::endpoint::{
     Endpoint,
     EndpointFuture,
@@ -5,12 +7,12 @@ use amzn_toolkit_telemetry_client::config::endpoint::{
     ResolveEndpoint,
 };
 
-#[derive(Debug, Clone, Copy)]
-pub(crate) struct StaticEndpoint(pub &'static str);
+#[derive(Debug, Clone)]
+pub(crate) struct StaticEndpoint(pub Cow<'static, str>);
 
 impl ResolveEndpoint for StaticEndpoint {
     fn resolve_endpoint<'a>(&'a self, _params: &'a Params) -> EndpointFuture<'a> {
-        let endpoint = Endpoint::builder().url(self.0).build();
+        let endpoint = Endpoint::builder().url(self.0.as_ref()).build();
         tracing::info!(?endpoint, "Resolving endpoint");
         EndpointFuture::ready(Ok(endpoint))
     }
@@ -22,7 +24,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_static_endpoint() {
-        let endpoint = StaticEndpoint("https://example.com");
+        let endpoint = StaticEndpoint("https://example.com".into());
         let params = Params::builder().build().unwrap();
         let endpoint = endpoint.resolve_endpoint(&params).await.unwrap();
         assert_eq!(endpoint.url(), "https://example.com");
@@ -0,0 +1,152 @@
+//! A local log of the most recent telemetry events, so users can audit what is sent with
+//! `q telemetry show`.
+
+use std::path::Path;
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value;
+
+use super::category::TelemetryCategory;
+use super::core::Event;
+
+/// How many events the log keeps, older ones are dropped.
+pub const MAX_EVENTS: usize = 200;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoggedEvent {
+    pub recorded_at: DateTime<Utc>,
+    #[serde(default)]
+    pub category: Option<TelemetryCategory>,
+    /// Where the event was sent, empty if it wasn't.
+    pub sent_to: Vec<String>,
+    /// The event as it was when recorded, kept as JSON so that the events of other versions can
+    /// be read.
+    pub event: Value,
+}
+
+impl LoggedEvent {
+    pub fn new(event: &Event, sent_to: Vec<String>) -> Self {
+        Self {
+            recorded_at: Utc::now(),
+            category: Some(event.ty.category()),
+            sent_to,
+            event: serde_json::to_value(event).unwrap_or_default(),
+        }
+    }
+
+    /// The type of the event, such as `chatAddedMessage`.
+    pub fn event_type(&self) -> &str {
+        self.event.get("type").and_then(Value::as_str).unwrap_or("unknown")
+    }
+}
+
+/// Appends `entry` to the log at `path`, keeping the last [MAX_EVENTS] events.
+pub async fn record(path: &Path, entry: &LoggedEvent) -> std::io::Result<()> {
+    let mut lines = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content.lines().map(str::to_string).collect::<Vec<_>>(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err),
+    };
+    lines.push(serde_json::to_string(entry)?);
+    let start = lines.len().saturating_sub(MAX_EVENTS);
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    write_atomically(path, (lines[start..].join("\n") + "\n").into_bytes()).await
+}
+
+/// Keeps the events of the log at `path` for which `keep` returns true, dropping the others.
+pub async fn retain(path: &Path, keep: impl Fn(&LoggedEvent) -> bool) -> std::io::Result<()> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    let kept = content
+        .lines()
+        .filter(|line| serde_json::from_str::<LoggedEvent>(line).is_ok_and(|entry| keep(&entry)))
+        .map(|line| format!("{line}\n"))
+        .collect::<String>();
+
+    write_atomically(path, kept.into_bytes()).await
+}
+
+/// Replaces the content of `path` through a temporary file of its own, so that a reader never
+/// sees half of the log and concurrent sessions don't write to the same temporary file.
+async fn write_atomically(path: &Path, content: Vec<u8>) -> std::io::Result<()> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        use std::io::Write as _;
+
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        tmp.write_all(&content)?;
+        tmp.persist(&path).map_err(|err| err.error)?;
+        Ok(())
+    })
+    .await
+    .map_err(std::io::Error::other)?
+}
+
+/// The last `n` events of the log at `path`, oldest first.
+pub async fn read_last(path: &Path, n: usize) -> std::io::Result<Vec<LoggedEvent>> {
+    let content = match tokio::fs::read_to_string(path).await {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let events = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<LoggedEvent>(line).ok())
+        .collect::<Vec<_>>();
+    let start = events.len().saturating_sub(n);
+    Ok(events[start..].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::core::EventType;
+
+    #[tokio::test]
+    async fn test_record_and_read_last() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("telemetry-events.jsonl");
+        assert!(read_last(&path, 10).await.unwrap().is_empty());
+
+        for i in 0..MAX_EVENTS + 5 {
+            let event = Event::new(EventType::CliSubcommandExecuted {
+                subcommand: format!("command{i}"),
+            });
+            record(&path, &LoggedEvent::new(&event, vec!["toolkit".to_string()]))
+                .await
+                .unwrap();
+        }
+
+        let all = read_last(&path, usize::MAX).await.unwrap();
+        assert_eq!(all.len(), MAX_EVENTS);
+        assert_eq!(all[0].event["subcommand"], "command5");
+
+        let last = read_last(&path, 2).await.unwrap();
+        assert_eq!(last.len(), 2);
+        assert_eq!(last[1].event_type(), "cliSubcommandExecuted");
+        assert_eq!(last[1].event["subcommand"], format!("command{}", MAX_EVENTS + 4));
+        assert_eq!(last[1].sent_to, vec!["toolkit".to_string()]);
+
+        retain(&path, |entry| entry.event["subcommand"] == "command6")
+            .await
+            .unwrap();
+        let all = read_last(&path, usize::MAX).await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].event["subcommand"], "command6");
+    }
+}
@@ -17,6 +17,21 @@ static INSTALL_METHOD: LazyLock<InstallMethod> = LazyLock::new(|| {
         if current_exe.components().any(|c| c.as_os_str() == ".toolbox") {
             return InstallMethod::Toolbox;
         }
+
+        // Which package owns the binary, if any.
+        let owned_by = |program: &str, args: &[&str]| {
+            Command::new(program)
+                .args(args)
+                .arg(&current_exe)
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+        if owned_by("dpkg-query", &["-S"]) {
+            return InstallMethod::Apt;
+        }
+        if owned_by("rpm", &["-qf"]) {
+            return InstallMethod::Dnf;
+        }
     }
 
     InstallMethod::Unknown
@@ -27,6 +42,10 @@ static INSTALL_METHOD: LazyLock<InstallMethod> = LazyLock::new(|| {
 pub enum InstallMethod {
     Brew,
     Toolbox,
+    /// A deb package.
+    Apt,
+    /// An rpm package.
+    Dnf,
     Unknown,
 }
 
@@ -35,6 +54,8 @@ impl std::fmt::Display for InstallMethod {
         f.write_str(match self {
             InstallMethod::Brew => "brew",
             InstallMethod::Toolbox => "toolbox",
+            InstallMethod::Apt => "apt",
+            InstallMethod::Dnf => "dnf",
             InstallMethod::Unknown => "unknown",
         })
     }
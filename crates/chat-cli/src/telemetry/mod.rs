@@ -1,17 +1,24 @@
+pub mod category;
 pub mod cognito;
 pub mod core;
 pub mod definitions;
 pub mod endpoint;
+pub mod event_log;
 mod install_method;
+pub mod otel;
 
 use core::{
     AgentConfigInitArgs,
     ChatAddedMessageParams,
+    CompactHistoryArgs,
+    CompactionFeedbackArgs,
     RecordUserTurnCompletionArgs,
     TangentModeSessionArgs,
     ToolUseEventBuilder,
 };
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::time::Duration;
 
 use amzn_codewhisperer_client::types::{
     ChatAddMessageEvent,
@@ -33,12 +40,14 @@ use amzn_toolkit_telemetry_client::{
     Config,
 };
 use aws_credential_types::provider::SharedCredentialsProvider;
+use category::TelemetryCategory;
 use cognito::CognitoProvider;
 use endpoint::StaticEndpoint;
 pub use install_method::{
     InstallMethod,
     get_install_method,
 };
+use strum::IntoEnumIterator;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tokio::time::error::Elapsed;
@@ -58,6 +67,7 @@ use crate::api_client::{
 };
 use crate::auth::builder_id::get_start_url_and_region;
 use crate::aws_common::app_name;
+use crate::aws_common::endpoint_overrides::endpoint_overrides;
 use crate::cli::RootSubcommand;
 use crate::database::settings::Setting;
 use crate::database::{
@@ -91,6 +101,8 @@ pub enum TelemetryError {
     Database(#[from] DatabaseError),
     #[error(transparent)]
     Timeout(#[from] Elapsed),
+    #[error(transparent)]
+    Request(#[from] crate::request::RequestError),
 }
 
 impl From<amzn_toolkit_telemetry_client::operation::post_metrics::PostMetricsError> for TelemetryError {
@@ -146,19 +158,25 @@ impl TelemetryStage {
     }
 }
 
+/// The number of events queued for the telemetry thread, past which new events are dropped rather
+/// than delaying the code sending them.
+const TELEMETRY_QUEUE_CAPACITY: usize = 256;
+/// How long [TelemetryThread::finish] waits for the queued events to be sent before exiting.
+const FINISH_DEADLINE: Duration = Duration::from_millis(1000);
+
 #[derive(Debug)]
 enum TelemetrySender {
-    Strong(mpsc::UnboundedSender<Event>),
-    Weak(mpsc::WeakUnboundedSender<Event>),
+    Strong(mpsc::Sender<Event>),
+    Weak(mpsc::WeakSender<Event>),
 }
 
 impl TelemetrySender {
     fn send(&self, ev: Event) -> Result<(), Box<mpsc::error::SendError<Event>>> {
         match self {
-            Self::Strong(sender) => sender.send(ev).map_err(Box::new),
+            Self::Strong(sender) => Self::try_send(sender, ev),
             Self::Weak(sender) => {
                 if let Some(sender) = sender.upgrade() {
-                    sender.send(ev).map_err(Box::new)
+                    Self::try_send(&sender, ev)
                 } else {
                     tracing::error!(
                         "Attempted to send telemetry after telemetry thread has been dropped. Event attempted {:?}",
@@ -169,6 +187,18 @@ impl TelemetrySender {
             },
         }
     }
+
+    /// Queues `ev` without waiting, so that sending telemetry never blocks the caller.
+    fn try_send(sender: &mpsc::Sender<Event>, ev: Event) -> Result<(), Box<mpsc::error::SendError<Event>>> {
+        match sender.try_send(ev) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(ev)) => {
+                debug!(?ev.ty, "Telemetry queue is full, dropping the event");
+                Ok(())
+            },
+            Err(mpsc::error::TrySendError::Closed(ev)) => Err(Box::new(mpsc::error::SendError(ev))),
+        }
+    }
 }
 
 impl Clone for TelemetrySender {
@@ -198,7 +228,7 @@ impl Clone for TelemetryThread {
 impl TelemetryThread {
     pub async fn new(env: &Env, fs: &Fs, database: &mut Database) -> Result<Self, TelemetryError> {
         let telemetry_client = TelemetryClient::new(env, fs, database).await?;
-        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (tx, mut rx) = mpsc::channel(TELEMETRY_QUEUE_CAPACITY);
         let tx = TelemetrySender::Strong(tx);
         let handle = tokio::spawn(async move {
             while let Some(event) = rx.recv().await {
@@ -213,17 +243,20 @@ impl TelemetryThread {
         })
     }
 
+    /// Sends the queued events, for at most [FINISH_DEADLINE] so that slow telemetry can't delay
+    /// the exit. The events left after that are dropped.
     pub async fn finish(self) -> Result<(), TelemetryError> {
         drop(self.tx);
-        if let Some(handle) = self.handle {
-            match tokio::time::timeout(std::time::Duration::from_millis(1000), handle).await {
+        if let Some(mut handle) = self.handle {
+            match tokio::time::timeout(FINISH_DEADLINE, &mut handle).await {
                 Ok(result) => {
                     if let Err(e) = result {
                         return Err(TelemetryError::Join(e));
                     }
                 },
                 Err(_) => {
-                    // Ignore timeout errors
+                    debug!("Telemetry was not sent within {FINISH_DEADLINE:?}, dropping the remaining events");
+                    handle.abort();
                 },
             }
         }
@@ -345,6 +378,33 @@ impl TelemetryThread {
         Ok(self.tx.send(telemetry_event)?)
     }
 
+    pub async fn send_compact_history(
+        &self,
+        database: &Database,
+        conversation_id: String,
+        result: TelemetryResult,
+        args: CompactHistoryArgs,
+    ) -> Result<(), TelemetryError> {
+        let mut telemetry_event = Event::new(EventType::CompactHistory {
+            conversation_id,
+            result,
+            args,
+        });
+        set_event_metadata(database, &mut telemetry_event).await;
+        Ok(self.tx.send(telemetry_event)?)
+    }
+
+    pub async fn send_compaction_feedback(
+        &self,
+        database: &Database,
+        conversation_id: String,
+        args: CompactionFeedbackArgs,
+    ) -> Result<(), TelemetryError> {
+        let mut telemetry_event = Event::new(EventType::CompactionFeedback { conversation_id, args });
+        set_event_metadata(database, &mut telemetry_event).await;
+        Ok(self.tx.send(telemetry_event)?)
+    }
+
     pub async fn send_tool_use_suggested(
         &self,
         database: &Database,
@@ -495,6 +555,10 @@ struct TelemetryClient {
     telemetry_enabled: bool,
     codewhisperer_client: Option<ApiClient>,
     toolkit_telemetry_client: Option<ToolkitTelemetryClient>,
+    /// Where the events are logged for `q telemetry show`.
+    event_log: Option<PathBuf>,
+    /// The categories of events that aren't sent, from the `telemetry.*` settings.
+    disabled_categories: Vec<TelemetryCategory>,
 }
 
 impl TelemetryClient {
@@ -507,9 +571,14 @@ impl TelemetryClient {
         let toolkit_telemetry_client = if telemetry_enabled {
             Some(ToolkitTelemetryClient::from_conf(
                 Config::builder()
-                    .http_client(crate::aws_common::http_client::client())
+                    .http_client(crate::aws_common::http_client::client()?)
                     .behavior_version(BehaviorVersion::v2025_01_17())
-                    .endpoint_resolver(StaticEndpoint(TelemetryStage::EXTERNAL_PROD.endpoint))
+                    .endpoint_resolver(StaticEndpoint(
+                        endpoint_overrides()
+                            .telemetry
+                            .clone()
+                            .map_or(TelemetryStage::EXTERNAL_PROD.endpoint.into(), Into::into),
+                    ))
                     .app_name(app_name())
                     .region(TelemetryStage::EXTERNAL_PROD.region.clone())
                     .credentials_provider(SharedCredentialsProvider::new(CognitoProvider::new(
@@ -562,6 +631,14 @@ impl TelemetryClient {
             telemetry_enabled,
             toolkit_telemetry_client,
             codewhisperer_client,
+            // Tests shouldn't log to the user's data directory.
+            event_log: match cfg!(test) {
+                true => None,
+                false => crate::util::directories::telemetry_events_path().ok(),
+            },
+            disabled_categories: TelemetryCategory::iter()
+                .filter(|category| !category.is_enabled(&database.settings))
+                .collect(),
         })
     }
 
@@ -570,10 +647,47 @@ impl TelemetryClient {
     ///
     /// See [TelemetryClient::new] for which conditions the clients are created for.
     async fn send_event(&self, event: Event) {
+        let category_enabled = !self.disabled_categories.contains(&event.ty.category());
+        if let Some(path) = &self.event_log {
+            let destinations = match category_enabled {
+                true => self.destinations(&event),
+                false => Vec::new(),
+            };
+            let entry = event_log::LoggedEvent::new(&event, destinations);
+            if let Err(err) = event_log::record(path, &entry).await {
+                debug!(%err, "Failed to log telemetry event");
+            }
+        }
+
+        if !category_enabled {
+            trace!(category = %event.ty.category(), "not sending telemetry - category disabled");
+            return;
+        }
         self.send_cw_telemetry_event(&event).await;
         self.send_telemetry_toolkit_metric(event).await;
     }
 
+    /// Where `event` is sent, following the same conditions as the send functions.
+    fn destinations(&self, event: &Event) -> Vec<String> {
+        let mut destinations = Vec::new();
+        if self.codewhisperer_client.is_some()
+            && matches!(
+                event.ty,
+                EventType::ChatAddedMessage { .. } | EventType::AgentContribution { .. }
+            )
+        {
+            // These are sent even when telemetry is disabled, along with the opt-out preference.
+            destinations.push(match self.telemetry_enabled {
+                true => "codewhisperer".to_string(),
+                false => "codewhisperer (opted out)".to_string(),
+            });
+        }
+        if self.toolkit_telemetry_client.is_some() && event.clone().into_metric_datum().is_some() {
+            destinations.push("toolkit".to_string());
+        }
+        destinations
+    }
+
     async fn send_cw_telemetry_event(&self, event: &Event) {
         let Some(codewhisperer_client) = self.codewhisperer_client.clone() else {
             trace!("not sending cw metric - client does not exist");
@@ -782,6 +896,30 @@ mod test {
         assert_eq!(context.ide_version.as_deref(), Some(PRODUCT_VERSION));
     }
 
+    #[tokio::test]
+    async fn test_sender_drops_events_when_full() {
+        let (tx, mut rx) = mpsc::channel(1);
+        let sender = TelemetrySender::Strong(tx);
+        sender.send(Event::new(EventType::UserLoggedIn {})).unwrap();
+        // The queue is full, which doesn't block or fail the caller.
+        sender.send(Event::new(EventType::DailyHeartbeat {})).unwrap();
+        assert!(matches!(rx.recv().await.unwrap().ty, EventType::UserLoggedIn {}));
+        assert!(rx.try_recv().is_err());
+
+        // Clones are weak, and sending through them once the thread is gone is ignored.
+        let weak = sender.clone();
+        drop(sender);
+        weak.send(Event::new(EventType::UserLoggedIn {})).unwrap();
+
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        assert!(
+            TelemetrySender::Strong(tx)
+                .send(Event::new(EventType::UserLoggedIn {}))
+                .is_err()
+        );
+    }
+
     #[tracing_test::traced_test]
     #[tokio::test]
     #[ignore = "needs auth which is not in CI"]
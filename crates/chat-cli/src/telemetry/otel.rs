@@ -0,0 +1,166 @@
+//! Spans of chat turns, tool uses and MCP requests, exported over OTLP so that q activity can be
+//! followed in an existing observability stack.
+//!
+//! Spans are only exported by builds with the `otel` feature, when the `telemetry.otlpEndpoint`
+//! setting is set. Otherwise they do nothing.
+
+#[cfg(feature = "otel")]
+use std::sync::OnceLock;
+
+use crate::database::settings::{
+    Setting,
+    Settings,
+};
+
+#[cfg(feature = "otel")]
+static PROVIDER: OnceLock<opentelemetry_sdk::trace::SdkTracerProvider> = OnceLock::new();
+
+#[cfg(feature = "otel")]
+const TRACER_NAME: &str = "q-cli";
+
+/// Starts exporting spans to the endpoint of the `telemetry.otlpEndpoint` setting, if it is set.
+pub fn init(settings: &Settings) {
+    let Some(endpoint) = settings.get_string(Setting::TelemetryOtlpEndpoint) else {
+        return;
+    };
+
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry::KeyValue;
+        use opentelemetry_otlp::WithExportConfig;
+        use opentelemetry_sdk::Resource;
+        use opentelemetry_sdk::trace::SdkTracerProvider;
+
+        let exporter = match opentelemetry_otlp::SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&endpoint)
+            .build()
+        {
+            Ok(exporter) => exporter,
+            Err(err) => {
+                tracing::error!(?err, %endpoint, "Failed to create the OTLP span exporter");
+                return;
+            },
+        };
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(TRACER_NAME)
+                    .with_attribute(KeyValue::new("service.version", env!("CARGO_PKG_VERSION")))
+                    .build(),
+            )
+            .build();
+        opentelemetry::global::set_tracer_provider(provider.clone());
+        PROVIDER.set(provider).ok();
+    }
+
+    #[cfg(not(feature = "otel"))]
+    tracing::warn!(%endpoint, "telemetry.otlpEndpoint is set, but this build of q doesn't include the otel feature");
+}
+
+/// Exports the spans that are still buffered, called before exiting.
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    if let Some(provider) = PROVIDER.get() {
+        if let Err(err) = provider.shutdown() {
+            tracing::warn!(?err, "Failed to export the remaining spans");
+        }
+    }
+}
+
+/// An operation being timed, exported once [OtelSpan::end] is called.
+#[derive(Debug)]
+pub struct OtelSpan {
+    #[cfg(feature = "otel")]
+    cx: opentelemetry::Context,
+}
+
+impl OtelSpan {
+    pub fn start(name: &'static str, attributes: &[(&'static str, String)]) -> Self {
+        Self::start_in(None, name, attributes)
+    }
+
+    /// Starts a span nested in this one, such as the tool uses of a chat turn.
+    pub fn start_child(&self, name: &'static str, attributes: &[(&'static str, String)]) -> Self {
+        Self::start_in(Some(self), name, attributes)
+    }
+
+    fn start_in(parent: Option<&Self>, name: &'static str, attributes: &[(&'static str, String)]) -> Self {
+        #[cfg(feature = "otel")]
+        {
+            use opentelemetry::KeyValue;
+            use opentelemetry::trace::{
+                TraceContextExt,
+                Tracer,
+            };
+
+            let tracer = opentelemetry::global::tracer(TRACER_NAME);
+            let parent_cx = parent.map_or_else(opentelemetry::Context::new, |parent| parent.cx.clone());
+            let span = tracer
+                .span_builder(name)
+                .with_attributes(attributes.iter().map(|(key, value)| KeyValue::new(*key, value.clone())))
+                .start_with_context(&tracer, &parent_cx);
+            Self {
+                cx: parent_cx.with_span(span),
+            }
+        }
+
+        #[cfg(not(feature = "otel"))]
+        {
+            let _ = (parent, name, attributes);
+            Self {}
+        }
+    }
+
+    /// Ends the span, failed with `error` if there is one.
+    pub fn end(self, error: Option<&str>) {
+        #[cfg(feature = "otel")]
+        {
+            use opentelemetry::trace::{
+                Status,
+                TraceContextExt,
+            };
+
+            let span = self.cx.span();
+            span.set_status(match error {
+                Some(error) => Status::error(error.to_string()),
+                None => Status::Ok,
+            });
+            span.end();
+        }
+
+        #[cfg(not(feature = "otel"))]
+        let _ = error;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    #[tokio::test]
+    async fn test_spans_without_endpoint() {
+        let database = Database::new().await.unwrap();
+        assert_eq!(database.settings.get_string(Setting::TelemetryOtlpEndpoint), None);
+        init(&database.settings);
+        #[cfg(feature = "otel")]
+        assert!(PROVIDER.get().is_none(), "no exporter is installed without an endpoint");
+
+        let turn = OtelSpan::start("chat_turn", &[("conversation.id", "abc".to_string())]);
+        let tool = turn.start_child("tool_use", &[("tool.name", "fs_read".to_string())]);
+        // Without a tracer provider, spans are not recorded, so there is nothing to export.
+        #[cfg(feature = "otel")]
+        {
+            use opentelemetry::trace::TraceContextExt;
+
+            assert!(!turn.cx.span().is_recording());
+            assert!(!tool.cx.span().is_recording());
+            assert!(!tool.cx.span().span_context().is_valid());
+        }
+        tool.end(Some("file not found"));
+        turn.end(None);
+        shutdown();
+    }
+}
@@ -48,6 +48,9 @@ pub mod env_var {
         /// Sets the current log level
         Q_LOG_LEVEL = "Q_LOG_LEVEL",
 
+        /// Sets the format of log lines, `json` for a JSON object per line
+        Q_LOG_FORMAT = "Q_LOG_FORMAT",
+
         /// Overrides the ZDOTDIR environment variable
         Q_ZDOTDIR = "Q_ZDOTDIR",
 
@@ -67,7 +70,10 @@ pub mod env_var {
         Q_BUNDLE_METADATA_PATH = "Q_BUNDLE_METADATA_PATH",
 
         /// Identifier for the client application or service using the chat-cli
-        Q_CLI_CLIENT_APPLICATION = "Q_CLI_CLIENT_APPLICATION"
+        Q_CLI_CLIENT_APPLICATION = "Q_CLI_CLIENT_APPLICATION",
+
+        /// Enables FIPS compliance mode, refusing to start unless a FIPS-validated TLS provider is in use
+        Q_FIPS_MODE = "Q_FIPS_MODE"
     }
 }
 
@@ -1,5 +1,6 @@
 use std::env::VarError;
 use std::path::{
+    Path,
     PathBuf,
     StripPrefixError,
 };
@@ -8,9 +9,15 @@ use globset::{
     Glob,
     GlobSetBuilder,
 };
+use sha2::{
+    Digest,
+    Sha256,
+};
 use thiserror::Error;
 
+#[cfg(feature = "knowledge")]
 use crate::cli::DEFAULT_AGENT_NAME;
+use crate::database::settings::Setting;
 use crate::os::Os;
 
 #[derive(Debug, Error)]
@@ -43,7 +50,13 @@ pub enum DirectoryError {
 type Result<T, E = DirectoryError> = std::result::Result<T, E>;
 
 const WORKSPACE_AGENT_DIR_RELATIVE: &str = ".amazonq/cli-agents";
-const GLOBAL_SHADOW_REPO_DIR: &str = ".aws/amazonq/cli-checkpoints";
+const WORKSPACE_CONFIG_RELATIVE: &str = ".amazonq/config.toml";
+const SHADOW_REPOS_DIR_RELATIVE_TO_DATA_DIR: &str = "cli-checkpoints";
+const LEGACY_SHADOW_REPOS_DIR_RELATIVE_TO_HOME: &str = ".aws/amazonq/cli-checkpoints";
+const AGENT_REGISTRY_DIR_RELATIVE_TO_DATA_DIR: &str = "running-agents";
+const AGENT_COMPARE_DIR_RELATIVE_TO_DATA_DIR: &str = "agent-compare";
+const ADVISORY_DB_DIR_RELATIVE_TO_DATA_DIR: &str = "advisories";
+const MCP_CACHE_DIR_RELATIVE_TO_DATA_DIR: &str = "mcp-cache";
 const GLOBAL_AGENT_DIR_RELATIVE_TO_HOME: &str = ".aws/amazonq/cli-agents";
 const WORKSPACE_PROMPTS_DIR_RELATIVE: &str = ".amazonq/prompts";
 const GLOBAL_PROMPTS_DIR_RELATIVE_TO_HOME: &str = ".aws/amazonq/prompts";
@@ -137,6 +150,20 @@ pub fn runtime_dir() -> Result<PathBuf> {
     dir.ok_or(DirectoryError::NoRuntimeDirectory)
 }
 
+/// The temporary directories of chat sessions, one per session
+/// - Linux: `$XDG_RUNTIME_DIR/qsessions`
+/// - MacOS: `$TMPDIR/qsessions`
+/// - Windows: `%TEMP%\amazon-q\sessions`
+pub fn session_temp_root() -> Result<PathBuf> {
+    cfg_if::cfg_if! {
+        if #[cfg(unix)] {
+            Ok(runtime_dir()?.join("qsessions"))
+        } else if #[cfg(windows)] {
+            Ok(std::env::temp_dir().join("amazon-q").join("sessions"))
+        }
+    }
+}
+
 /// The directory to all the fig logs
 /// - Linux: `/tmp/fig/$USER/logs`
 /// - MacOS: `$TMPDIR/logs`
@@ -177,6 +204,12 @@ pub fn chat_global_agent_path(os: &Os) -> Result<PathBuf> {
     Ok(home_dir(os)?.join(GLOBAL_AGENT_DIR_RELATIVE_TO_HOME))
 }
 
+/// The checked-in config of the current workspace
+pub fn chat_workspace_config_path(os: &Os) -> Result<PathBuf> {
+    let cwd = os.env.current_dir()?;
+    Ok(cwd.join(WORKSPACE_CONFIG_RELATIVE))
+}
+
 /// The directory to the directory containing config for the `/context` feature in `q chat`.
 pub fn chat_local_agent_dir(os: &Os) -> Result<PathBuf> {
     let cwd = os.env.current_dir()?;
@@ -278,11 +311,13 @@ pub fn chat_profiles_dir(os: &Os) -> Result<PathBuf> {
 }
 
 /// The directory for knowledge base storage
+#[cfg(feature = "knowledge")]
 pub fn knowledge_bases_dir(os: &Os) -> Result<PathBuf> {
     Ok(home_dir(os)?.join(".aws").join("amazonq").join("knowledge_bases"))
 }
 
 /// The directory for agent-specific knowledge base storage
+#[cfg(feature = "knowledge")]
 pub fn agent_knowledge_dir(os: &Os, agent: Option<&crate::cli::Agent>) -> Result<PathBuf> {
     let unique_id = if let Some(agent) = agent {
         generate_agent_unique_id(agent)
@@ -301,11 +336,96 @@ pub fn get_mcp_auth_dir(os: &Os) -> Result<PathBuf> {
     Ok(home_dir(os)?.join(".aws").join("sso").join("cache"))
 }
 
+/// The root directory of the checkpoint shadow repositories
+///
+/// Configurable with `chat.checkpoint.dir`, defaults to `cli-checkpoints` under [fig_data_dir].
+pub fn shadow_repos_dir(os: &Os) -> Result<PathBuf> {
+    match os.database.settings.get_string(Setting::ChatCheckpointDir) {
+        Some(dir) => Ok(PathBuf::from(canonicalizes_path(os, &dir)?)),
+        None => Ok(fig_data_dir()?.join(SHADOW_REPOS_DIR_RELATIVE_TO_DATA_DIR)),
+    }
+}
+
+/// Where shadow repositories were kept before they moved to [shadow_repos_dir]
+///
+/// - `~/.aws/amazonq/cli-checkpoints`
+pub fn legacy_shadow_repos_dir(os: &Os) -> Result<PathBuf> {
+    Ok(home_dir(os)?.join(LEGACY_SHADOW_REPOS_DIR_RELATIVE_TO_HOME))
+}
+
+/// The directory of the OSV advisories checked by the `dependency_report` tool
+///
+/// Configurable with `chat.advisoryDbDir`, defaults to `advisories` under [fig_data_dir].
+pub fn advisory_db_dir(os: &Os) -> Result<PathBuf> {
+    match os.database.settings.get_string(Setting::ChatAdvisoryDbDir) {
+        Some(dir) => Ok(PathBuf::from(canonicalizes_path(os, &dir)?)),
+        None => Ok(fig_data_dir()?.join(ADVISORY_DB_DIR_RELATIVE_TO_DATA_DIR)),
+    }
+}
+
+/// The cache of the tools and prompts listed by MCP servers in earlier sessions
+///
+/// - `<data dir>/mcp-cache`
+pub fn mcp_cache_dir() -> Result<PathBuf> {
+    Ok(fig_data_dir()?.join(MCP_CACHE_DIR_RELATIVE_TO_DATA_DIR))
+}
+
+/// The registry of running chat sessions, with one entry per process
+///
+/// - `<data dir>/running-agents`
+pub fn agent_registry_dir() -> Result<PathBuf> {
+    Ok(fig_data_dir()?.join(AGENT_REGISTRY_DIR_RELATIVE_TO_DATA_DIR))
+}
+
+/// The worktrees and logs of the latest `q agent compare` run in a repository
+///
+/// - `<data dir>/agent-compare/<repository name>-<repository path hash>`
+pub fn agent_compare_dir(repository: &Path) -> Result<PathBuf> {
+    Ok(fig_data_dir()?
+        .join(AGENT_COMPARE_DIR_RELATIVE_TO_DATA_DIR)
+        .join(workspace_dir_name(repository)))
+}
+
+/// The shadow repository for a conversation, grouped by workspace
+///
+/// - `<shadow repos dir>/<workspace name>-<workspace path hash>/<conversation id>`
 pub fn get_shadow_repo_dir(os: &Os, conversation_id: String) -> Result<PathBuf> {
-    Ok(home_dir(os)?.join(GLOBAL_SHADOW_REPO_DIR).join(conversation_id))
+    let workspace = os.env.current_dir()?;
+    Ok(shadow_repos_dir(os)?
+        .join(workspace_dir_name(&workspace))
+        .join(conversation_id))
+}
+
+/// A stable directory name for a workspace, derived from a hash of its path. The workspace's
+/// name is kept as a prefix for readability.
+fn workspace_dir_name(workspace: &Path) -> String {
+    let workspace = workspace.canonicalize().unwrap_or_else(|_| workspace.to_path_buf());
+    let hash = hex::encode(Sha256::digest(workspace.to_string_lossy().as_bytes()));
+    let name = workspace
+        .file_name()
+        .map(|name| {
+            name.to_string_lossy()
+                .chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                        c
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+
+    if name.is_empty() {
+        hash[..16].to_string()
+    } else {
+        format!("{name}-{}", &hash[..16])
+    }
 }
 
 /// Generate a unique identifier for an agent based on its path and name
+#[cfg(feature = "knowledge")]
 fn generate_agent_unique_id(agent: &crate::cli::Agent) -> String {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{
@@ -337,6 +457,39 @@ pub fn database_path() -> Result<PathBuf> {
     Ok(fig_data_dir()?.join("data.sqlite3"))
 }
 
+/// The most recent telemetry events, for `q telemetry show`
+///
+/// - `<data dir>/telemetry-events.jsonl`
+pub fn telemetry_events_path() -> Result<PathBuf> {
+    Ok(fig_data_dir()?.join("telemetry-events.jsonl"))
+}
+
+/// The audit log of the actions of the agent
+///
+/// - `<data dir>/audit.jsonl`
+pub fn audit_log_path() -> Result<PathBuf> {
+    Ok(fig_data_dir()?.join("audit.jsonl"))
+}
+
+/// The policy an administrator installs to govern the tools of q chat on the machine. It can't be
+/// overridden, as users must not be able to turn it off.
+///
+/// - Linux: `/etc/amazon-q/policy.json`
+/// - MacOS: `/Library/Application Support/amazon-q/policy.json`
+/// - Windows: `%ProgramData%\amazon-q\policy.json`
+pub fn managed_policy_path(#[cfg_attr(not(windows), allow(unused_variables))] os: &Os) -> Result<PathBuf> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "macos")] {
+            Ok(PathBuf::from("/Library/Application Support/amazon-q/policy.json"))
+        } else if #[cfg(windows)] {
+            let program_data = os.env.get("ProgramData").unwrap_or_else(|_| r"C:\ProgramData".to_string());
+            Ok(PathBuf::from(program_data).join("amazon-q").join("policy.json"))
+        } else {
+            Ok(PathBuf::from("/etc/amazon-q/policy.json"))
+        }
+    }
+}
+
 #[cfg(test)]
 mod linux_tests {
     use super::*;
@@ -347,6 +500,15 @@ mod linux_tests {
         assert!(settings_path().is_ok());
     }
 
+    #[test]
+    fn test_workspace_dir_name() {
+        let name = workspace_dir_name(Path::new("/nonexistent/my project"));
+        assert!(name.starts_with("my_project-"));
+        assert_eq!(name, workspace_dir_name(Path::new("/nonexistent/my project")));
+        assert_ne!(name, workspace_dir_name(Path::new("/other/my project")));
+        assert_eq!(workspace_dir_name(Path::new("/")).len(), 16);
+    }
+
     #[test]
     fn test_add_gitignore_globs() {
         let direct_file = "/home/user/a.txt";
@@ -0,0 +1,185 @@
+//! Web pages and PDFs added to the knowledge base by URL.
+//!
+//! A document is fetched once and kept in the `documents` dir of the knowledge base of the agent:
+//! web pages converted to text, PDFs as they are. It is then indexed like any other file, with its
+//! URL as the source of its chunks, so that answers can cite the original document.
+
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::time::Duration;
+
+use reqwest::header::CONTENT_TYPE;
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use crate::cli::chat::util::truncate_safe;
+
+/// The largest document that is fetched.
+const MAX_DOCUMENT_BYTES: usize = 20 * 1024 * 1024;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The width web pages are wrapped at when converted to text.
+const TEXT_WIDTH: usize = 100;
+
+/// Whether a path given to `/knowledge add` is the URL of a document.
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("https://") || path.starts_with("http://")
+}
+
+/// The dir documents are kept in, in the knowledge base dir of an agent.
+pub fn documents_dir(agent_dir: &Path) -> PathBuf {
+    agent_dir.join("documents")
+}
+
+/// Fetches the document at `url` into `documents_dir`, replacing a previous copy, and returns the
+/// file it was saved to.
+pub async fn fetch(url: &str, documents_dir: &Path) -> Result<PathBuf, String> {
+    let client = crate::request::new_client_builder()
+        .and_then(|builder| Ok(builder.timeout(FETCH_TIMEOUT).build()?))
+        .map_err(|e| format!("Failed to create an HTTP client: {}", e))?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+
+    if response
+        .content_length()
+        .is_some_and(|len| len as usize > MAX_DOCUMENT_BYTES)
+    {
+        return Err(too_large(url));
+    }
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase())
+        .unwrap_or_default();
+    // Read in chunks, since the content length is only a hint and may be missing.
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?
+    {
+        if body.len() + chunk.len() > MAX_DOCUMENT_BYTES {
+            return Err(too_large(url));
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    let (extension, content) = convert(&content_type, &body)
+        .ok_or_else(|| format!("Cannot add {}: unsupported content type '{}'", url, content_type))??;
+
+    let path = document_path(documents_dir, url, extension);
+    tokio::fs::create_dir_all(documents_dir)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", documents_dir.display(), e))?;
+    tokio::fs::write(&path, content)
+        .await
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Removes the copy of a document, if the path of a context is one.
+pub async fn remove(documents_dir: &Path, path: &str) {
+    let path = Path::new(path);
+    let in_documents_dir = match (path.parent().map(Path::canonicalize), documents_dir.canonicalize()) {
+        (Some(Ok(parent)), Ok(documents_dir)) => parent == documents_dir,
+        _ => false,
+    };
+    if in_documents_dir {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+}
+
+/// The file extension and content a document is saved with, by its content type, or none if it
+/// cannot be indexed.
+fn convert(content_type: &str, body: &[u8]) -> Option<Result<(&'static str, Vec<u8>), String>> {
+    let converted = match content_type {
+        _ if content_type == "application/pdf" || body.starts_with(b"%PDF-") => ("pdf", body.to_vec()),
+        "text/html" | "application/xhtml+xml" => match html2text::from_read(body, TEXT_WIDTH) {
+            Ok(text) => ("md", text.into_bytes()),
+            Err(e) => return Some(Err(format!("Failed to convert the page to text: {}", e))),
+        },
+        "text/markdown" | "text/x-markdown" => ("md", body.to_vec()),
+        "application/json" => ("json", body.to_vec()),
+        "" | "text/plain" => ("txt", body.to_vec()),
+        _ if content_type.starts_with("text/") => ("txt", body.to_vec()),
+        _ => return None,
+    };
+    Some(Ok(converted))
+}
+
+/// The file a document is saved to: readable from its URL, and unique to it.
+fn document_path(documents_dir: &Path, url: &str, extension: &str) -> PathBuf {
+    let name = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['?', '#'])
+        .next()
+        .unwrap_or_default()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let name = truncate_safe(&name, 80);
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    documents_dir.join(format!("{}-{}.{}", name, &hash[..8], extension))
+}
+
+fn too_large(url: &str) -> String {
+    format!(
+        "Cannot add {}: documents larger than {} MB are not supported",
+        url,
+        MAX_DOCUMENT_BYTES / (1024 * 1024)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://example.com/guide"));
+        assert!(is_url("http://example.com/paper.pdf"));
+        assert!(!is_url("./docs"));
+        assert!(!is_url("/home/user/https://"));
+    }
+
+    #[test]
+    fn test_document_path() {
+        let dir = Path::new("/kb/documents");
+        let path = document_path(dir, "https://example.com/docs/Guide.html?lang=en#install", "md");
+        assert_eq!(path.parent(), Some(dir));
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("example-com-docs-Guide-html-"));
+        assert!(name.ends_with(".md"));
+        assert_ne!(path, document_path(dir, "https://example.com/docs/Guide.html", "md"));
+    }
+
+    #[test]
+    fn test_convert() {
+        let (extension, text) = convert("text/html", b"<html><body><h1>Title</h1><p>Body</p></body></html>")
+            .unwrap()
+            .unwrap();
+        assert_eq!(extension, "md");
+        let text = String::from_utf8(text).unwrap();
+        assert!(text.contains("Title") && text.contains("Body") && !text.contains("<p>"));
+
+        assert_eq!(
+            convert("application/octet-stream", b"%PDF-1.7").unwrap().unwrap().0,
+            "pdf"
+        );
+        assert_eq!(convert("text/csv", b"a,b").unwrap().unwrap().0, "txt");
+        assert!(convert("image/png", b"\x89PNG").is_none());
+    }
+}
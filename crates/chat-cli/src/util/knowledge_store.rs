@@ -1,8 +1,16 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf,
+};
 use std::sync::{
     Arc,
     LazyLock as Lazy,
 };
+use std::time::{
+    Duration,
+    Instant,
+};
 
 use eyre::Result;
 use semantic_search_client::KnowledgeContext;
@@ -13,11 +21,18 @@ use semantic_search_client::types::{
     SearchResult,
 };
 use tokio::sync::Mutex;
+use tracing::debug;
 use uuid::Uuid;
 
 use crate::cli::DEFAULT_AGENT_NAME;
 use crate::os::Os;
-use crate::util::directories;
+use crate::util::{
+    directories,
+    knowledge_documents,
+};
+
+/// How often a context searched with [KnowledgeStore::refresh] is checked for changed files.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
 
 /// Configuration for adding knowledge contexts
 #[derive(Default)]
@@ -108,6 +123,8 @@ impl std::error::Error for KnowledgeError {}
 pub struct KnowledgeStore {
     agent_client: AsyncSemanticSearchClient,
     agent_dir: PathBuf,
+    /// When contexts were last checked for changed files, by context ID
+    refreshed_at: HashMap<String, Instant>,
 }
 
 impl KnowledgeStore {
@@ -266,16 +283,31 @@ impl KnowledgeStore {
         let store = Self {
             agent_client,
             agent_dir,
+            refreshed_at: HashMap::new(),
         };
         Ok(store)
     }
 
     /// Add context with flexible options
+    ///
+    /// A URL is fetched into the knowledge base and indexed as a single document, whatever the
+    /// patterns, with the URL as the source of its chunks.
     pub async fn add(&mut self, name: &str, path_str: &str, options: AddOptions) -> Result<String, String> {
-        let path_buf = std::path::PathBuf::from(path_str);
-        let canonical_path = path_buf
-            .canonicalize()
-            .map_err(|_io_error| format!("❌ Path does not exist: {}", path_str))?;
+        let (canonical_path, source, options) = if knowledge_documents::is_url(path_str) {
+            let path = knowledge_documents::fetch(path_str, &self.documents_dir()).await?;
+            let options = AddOptions {
+                include_patterns: Vec::new(),
+                exclude_patterns: Vec::new(),
+                ..options
+            };
+            (path, Some(path_str.to_string()), options)
+        } else {
+            let path_buf = std::path::PathBuf::from(path_str);
+            let canonical_path = path_buf
+                .canonicalize()
+                .map_err(|_io_error| format!("❌ Path does not exist: {}", path_str))?;
+            (canonical_path, None, options)
+        };
 
         // Use provided description or generate default
         let description = options
@@ -318,6 +350,7 @@ impl KnowledgeStore {
                 },
                 None => None,
             },
+            source: source.clone(),
         };
 
         match self.agent_client.add_context(request).await {
@@ -328,6 +361,9 @@ impl KnowledgeStore {
                     canonical_path.display(),
                     &operation_id.to_string()[..8]
                 );
+                if let Some(source) = &source {
+                    message.push_str(&format!("\n🌐 Source: {}", source));
+                }
                 if !options.include_patterns.is_empty() || !options.exclude_patterns.is_empty() {
                     message.push_str("\n📋 Pattern filtering applied:");
                     if !options.include_patterns.is_empty() {
@@ -351,6 +387,48 @@ impl KnowledgeStore {
         }
     }
 
+    /// Add a directory, or update it with the files that changed if it was already added
+    pub async fn add_or_update(&mut self, name: &str, path_str: &str, options: AddOptions) -> Result<String, String> {
+        match self.context_by_path(path_str).await {
+            Some(context) => self.start_update(&context).await,
+            None => self.add(name, path_str, options).await,
+        }
+    }
+
+    /// The context that covers a workspace: the one added for the directory or its closest
+    /// parent
+    pub async fn workspace_context(&self, workspace: &Path) -> Option<KnowledgeContext> {
+        let workspace = workspace.canonicalize().ok()?;
+        self.agent_client
+            .get_contexts()
+            .await
+            .into_iter()
+            .filter_map(|context| {
+                let source_path = PathBuf::from(context.source_path.as_deref()?).canonicalize().ok()?;
+                workspace
+                    .starts_with(&source_path)
+                    .then(|| (source_path.components().count(), context))
+            })
+            .max_by_key(|(depth, _)| *depth)
+            .map(|(_, context)| context)
+    }
+
+    /// Start re-indexing the files of a context that changed, unless it was checked in the last
+    /// [REFRESH_INTERVAL]. Searches keep using the current index until the update completes.
+    pub async fn refresh(&mut self, context: &KnowledgeContext) {
+        if self
+            .refreshed_at
+            .get(&context.id)
+            .is_some_and(|at| at.elapsed() < REFRESH_INTERVAL)
+        {
+            return;
+        }
+        self.refreshed_at.insert(context.id.clone(), Instant::now());
+        if let Err(err) = self.agent_client.update_context(&context.id).await {
+            debug!(?err, "Did not refresh knowledge context {}", context.name);
+        }
+    }
+
     /// Get all contexts from agent client
     pub async fn get_all(&self) -> Result<Vec<KnowledgeContext>, String> {
         Ok(self.agent_client.get_contexts().await)
@@ -435,10 +513,13 @@ impl KnowledgeStore {
     /// Clear all contexts (background operation)
     pub async fn clear(&mut self) -> Result<String, String> {
         match self.agent_client.clear_all().await {
-            Ok((operation_id, _cancel_token)) => Ok(format!(
-                "🚀 Started clearing all contexts in background.\n📊 Use 'knowledge status' to check progress.\n🆔 Operation ID: {}",
-                &operation_id.to_string()[..8]
-            )),
+            Ok((operation_id, _cancel_token)) => {
+                let _ = tokio::fs::remove_dir_all(self.documents_dir()).await;
+                Ok(format!(
+                    "🚀 Started clearing all contexts in background.\n📊 Use 'knowledge status' to check progress.\n🆔 Operation ID: {}",
+                    &operation_id.to_string()[..8]
+                ))
+            },
             Err(e) => Err(format!("Failed to start clear operation: {}", e)),
         }
     }
@@ -446,18 +527,18 @@ impl KnowledgeStore {
     /// Clear all contexts immediately (synchronous operation)
     pub async fn clear_immediate(&mut self) -> Result<String, String> {
         match self.agent_client.clear_all_immediate().await {
-            Ok(count) => Ok(format!("✅ Successfully cleared {} knowledge base entries", count)),
+            Ok(count) => {
+                let _ = tokio::fs::remove_dir_all(self.documents_dir()).await;
+                Ok(format!("✅ Successfully cleared {} knowledge base entries", count))
+            },
             Err(e) => Err(format!("Failed to clear knowledge base: {}", e)),
         }
     }
 
     /// Remove context by path
     pub async fn remove_by_path(&mut self, path: &str) -> Result<(), String> {
-        if let Some(context) = self.agent_client.get_context_by_path(path).await {
-            self.agent_client
-                .remove_context_by_id(&context.id)
-                .await
-                .map_err(|e| e.to_string())
+        if let Some(context) = self.context_by_path(path).await {
+            self.remove_context(&context).await
         } else {
             Err(format!("No context found with path '{}'", path))
         }
@@ -466,10 +547,7 @@ impl KnowledgeStore {
     /// Remove context by name
     pub async fn remove_by_name(&mut self, name: &str) -> Result<(), String> {
         if let Some(context) = self.agent_client.get_context_by_name(name).await {
-            self.agent_client
-                .remove_context_by_id(&context.id)
-                .await
-                .map_err(|e| e.to_string())
+            self.remove_context(&context).await
         } else {
             Err(format!("No context found with name '{}'", name))
         }
@@ -477,29 +555,26 @@ impl KnowledgeStore {
 
     /// Remove context by ID
     pub async fn remove_by_id(&mut self, context_id: &str) -> Result<(), String> {
-        self.agent_client
-            .remove_context_by_id(context_id)
+        match self
+            .agent_client
+            .get_contexts()
             .await
-            .map_err(|e| e.to_string())
+            .into_iter()
+            .find(|c| c.id == context_id)
+        {
+            Some(context) => self.remove_context(&context).await,
+            None => self
+                .agent_client
+                .remove_context_by_id(context_id)
+                .await
+                .map_err(|e| e.to_string()),
+        }
     }
 
     /// Update context by path
     pub async fn update_by_path(&mut self, path_str: &str) -> Result<String, String> {
-        if let Some(context) = self.agent_client.get_context_by_path(path_str).await {
-            // Remove the existing context first
-            self.agent_client
-                .remove_context_by_id(&context.id)
-                .await
-                .map_err(|e| e.to_string())?;
-
-            // Then add it back with the same name and original patterns (agent scope)
-            let options = AddOptions {
-                description: None,
-                include_patterns: context.include_patterns.clone(),
-                exclude_patterns: context.exclude_patterns.clone(),
-                embedding_type: None,
-            };
-            self.add(&context.name, path_str, options).await
+        if let Some(context) = self.context_by_path(path_str).await {
+            self.start_update(&context).await
         } else {
             // Debug: List all available contexts
             let available_paths = self.agent_client.list_context_paths().await;
@@ -523,13 +598,14 @@ impl KnowledgeStore {
             .find(|c| c.id == context_id)
             .ok_or_else(|| format!("Context '{}' not found", context_id))?;
 
+        if Self::is_source_path(context, path_str) {
+            return self.start_update(context).await;
+        }
+
         let context_name = context.name.clone();
 
         // Remove the existing context first
-        self.agent_client
-            .remove_context_by_id(context_id)
-            .await
-            .map_err(|e| e.to_string())?;
+        self.remove_context(context).await?;
 
         // Then add it back with the same name and original patterns
         let options = AddOptions {
@@ -544,11 +620,12 @@ impl KnowledgeStore {
     /// Update context by name
     pub async fn update_context_by_name(&mut self, name: &str, path_str: &str) -> Result<String, String> {
         if let Some(context) = self.agent_client.get_context_by_name(name).await {
+            if Self::is_source_path(&context, path_str) {
+                return self.start_update(&context).await;
+            }
+
             // Remove the existing context first
-            self.agent_client
-                .remove_context_by_id(&context.id)
-                .await
-                .map_err(|e| e.to_string())?;
+            self.remove_context(&context).await?;
 
             // Then add it back with the same name and original patterns (agent scope)
             let options = AddOptions {
@@ -562,6 +639,67 @@ impl KnowledgeStore {
             Err(format!("Context with name '{}' not found", name))
         }
     }
+
+    /// Re-index the files of a context that changed since it was last indexed. A document
+    /// added by URL is fetched again first.
+    async fn start_update(&self, context: &KnowledgeContext) -> Result<String, String> {
+        if let Some(source) = &context.source {
+            knowledge_documents::fetch(source, &self.documents_dir()).await?;
+        }
+        let (operation_id, _) = self
+            .agent_client
+            .update_context(&context.id)
+            .await
+            .map_err(|e| format!("Failed to start updating: {}", e))?;
+        Ok(format!(
+            "🚀 Started updating '{}'\n📁 Path: {}\n🆔 Operation ID: {}\n✅ Only new or changed files will be re-indexed",
+            context.name,
+            context.source_path.as_deref().unwrap_or_default(),
+            &operation_id.to_string()[..8]
+        ))
+    }
+
+    /// Whether `path_str` is the path or URL a context was created from
+    fn is_source_path(context: &KnowledgeContext, path_str: &str) -> bool {
+        if knowledge_documents::is_url(path_str) {
+            return context.source.as_deref() == Some(path_str);
+        }
+        let canonical = |path: &str| PathBuf::from(path).canonicalize().ok();
+        context
+            .source_path
+            .as_deref()
+            .and_then(canonical)
+            .is_some_and(|source_path| Some(source_path) == canonical(path_str))
+    }
+
+    /// The context created from a path or URL
+    async fn context_by_path(&self, path_str: &str) -> Option<KnowledgeContext> {
+        if knowledge_documents::is_url(path_str) {
+            self.agent_client
+                .get_contexts()
+                .await
+                .into_iter()
+                .find(|context| context.source.as_deref() == Some(path_str))
+        } else {
+            self.agent_client.get_context_by_path(path_str).await
+        }
+    }
+
+    /// Remove a context, and the copy of its document if it was added by URL
+    async fn remove_context(&self, context: &KnowledgeContext) -> Result<(), String> {
+        self.agent_client
+            .remove_context_by_id(&context.id)
+            .await
+            .map_err(|e| e.to_string())?;
+        if let (Some(_), Some(path)) = (&context.source, &context.source_path) {
+            knowledge_documents::remove(&self.documents_dir(), path).await;
+        }
+        Ok(())
+    }
+
+    fn documents_dir(&self) -> PathBuf {
+        knowledge_documents::documents_dir(&self.agent_dir)
+    }
 }
 
 #[cfg(test)]
@@ -1,12 +1,17 @@
 pub mod consts;
 pub mod directories;
+#[cfg(feature = "knowledge")]
+pub mod knowledge_documents;
+#[cfg(feature = "knowledge")]
 pub mod knowledge_store;
 pub mod open;
 pub mod pattern_matching;
+pub mod shutdown;
 pub mod spinner;
 pub mod system_info;
 #[cfg(test)]
 pub mod test;
+pub mod time;
 pub mod tool_permission_checker;
 pub mod ui;
 
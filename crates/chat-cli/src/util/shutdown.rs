@@ -0,0 +1,241 @@
+//! Cleanup that has to happen however the process exits: normally, from SIGTERM/SIGHUP, or from a
+//! panic.
+//!
+//! Child processes and temporary files are registered here when they are created, so that they
+//! don't outlive a session that was killed. SIGINT is not handled here since chat treats ctrl+c
+//! as an interrupt, and exits through the normal path.
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::sync::{
+    LazyLock,
+    Once,
+};
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{
+    debug,
+    info,
+    warn,
+};
+
+/// How long a graceful shutdown may take after a termination signal before the process exits
+/// anyway.
+const FORCE_EXIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Exit code used when terminated by a signal, following the shell convention of 128 + SIGTERM.
+const TERMINATED_EXIT_CODE: i32 = 143;
+
+static SHUTDOWN: LazyLock<Shutdown> = LazyLock::new(Shutdown::default);
+
+#[derive(Debug, Default)]
+struct Shutdown {
+    /// Cancelled when a termination signal is received.
+    requested: CancellationToken,
+    /// Child processes to kill, by pid.
+    children: Mutex<HashMap<u32, String>>,
+    /// Files and directories to remove.
+    paths: Mutex<HashSet<PathBuf>>,
+    /// Set once [cleanup] has run.
+    done: AtomicBool,
+}
+
+/// Installs the termination signal handlers. Must be called from within a tokio runtime. Only the
+/// first call has any effect.
+pub fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        #[cfg(unix)]
+        tokio::spawn(handle_signals());
+    });
+}
+
+/// Runs [cleanup] when dropped, including while unwinding from a panic that was not caught.
+///
+/// Panics that are caught, such as those in spawned tasks, don't end the session and so don't
+/// clean up anything, unlike a panic hook which runs for every panic.
+#[derive(Debug)]
+pub struct CleanupGuard;
+
+impl Drop for CleanupGuard {
+    fn drop(&mut self) {
+        cleanup();
+    }
+}
+
+#[cfg(unix)]
+async fn handle_signals() {
+    use tokio::signal::unix::{
+        SignalKind,
+        signal,
+    };
+
+    let (Ok(mut terminate), Ok(mut hangup)) = (signal(SignalKind::terminate()), signal(SignalKind::hangup())) else {
+        warn!("failed to install the termination signal handlers");
+        return;
+    };
+
+    tokio::select! {
+        _ = terminate.recv() => info!("received SIGTERM, shutting down"),
+        _ = hangup.recv() => info!("received SIGHUP, shutting down"),
+    }
+    SHUTDOWN.requested.cancel();
+
+    // Give the session a chance to exit on its own, but don't wait forever or ignore a second
+    // signal.
+    tokio::select! {
+        _ = terminate.recv() => {},
+        _ = hangup.recv() => {},
+        _ = tokio::time::sleep(FORCE_EXIT_TIMEOUT) => warn!("graceful shutdown timed out"),
+    }
+    cleanup();
+    // The session didn't exit in time, so there is no caller left to return an exit code to.
+    #[allow(clippy::exit)]
+    std::process::exit(TERMINATED_EXIT_CODE);
+}
+
+/// Asks the session to exit as it does on a termination signal, but without forcing the exit if
+/// that takes long.
+pub fn request() {
+    info!("shutdown requested");
+    SHUTDOWN.requested.cancel();
+}
+
+/// Completes when a shutdown was requested by a termination signal or [request].
+pub async fn requested() {
+    SHUTDOWN.requested.cancelled().await;
+}
+
+/// Registers a child process to be killed on shutdown for as long as the returned guard is alive.
+/// Process group leaders are killed along with their group.
+pub fn track_child(pid: u32, name: impl Into<String>) -> ChildGuard {
+    SHUTDOWN.children.lock().insert(pid, name.into());
+    ChildGuard(pid)
+}
+
+/// Unregisters a child process registered with [track_child] when dropped.
+#[derive(Debug)]
+pub struct ChildGuard(u32);
+
+impl Drop for ChildGuard {
+    fn drop(&mut self) {
+        SHUTDOWN.children.lock().remove(&self.0);
+    }
+}
+
+/// Returns the registered child processes, by pid.
+pub fn children() -> Vec<(u32, String)> {
+    SHUTDOWN
+        .children
+        .lock()
+        .iter()
+        .map(|(pid, name)| (*pid, name.clone()))
+        .collect()
+}
+
+/// Registers a file or directory to be removed on shutdown, e.g. a socket or lock file.
+pub fn register_path(path: impl Into<PathBuf>) {
+    SHUTDOWN.paths.lock().insert(path.into());
+}
+
+pub fn unregister_path(path: &Path) {
+    SHUTDOWN.paths.lock().remove(path);
+}
+
+/// Kills the registered child processes, removes the registered paths, and restores the terminal.
+/// Only the first call has any effect.
+pub fn cleanup() {
+    if SHUTDOWN.done.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    // Avoid blocking forever if a panic happened while one of the locks was held.
+    if let Some(mut children) = SHUTDOWN.children.try_lock() {
+        for (pid, name) in children.drain() {
+            debug!(pid, name, "killing child process");
+            kill_process(pid);
+        }
+    }
+
+    if let Some(mut paths) = SHUTDOWN.paths.try_lock() {
+        for path in paths.drain() {
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            if let Err(err) = result {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    warn!(?err, ?path, "failed to remove path");
+                }
+            }
+        }
+    }
+
+    restore_terminal();
+}
+
+/// Terminates a process, along with its process group if it leads one.
+#[cfg(unix)]
+pub fn kill_process(pid: u32) {
+    use nix::sys::signal::{
+        Signal,
+        kill,
+        killpg,
+    };
+    use nix::unistd::Pid;
+
+    let pid = Pid::from_raw(pid as i32);
+    // Not every child leads its own process group, in which case only the process is killed.
+    if killpg(pid, Signal::SIGTERM).is_err() {
+        kill(pid, Signal::SIGTERM).ok();
+    }
+}
+
+/// Terminates a process along with its child processes.
+#[cfg(windows)]
+pub fn kill_process(pid: u32) {
+    std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .ok();
+}
+
+fn restore_terminal() {
+    use crossterm::{
+        cursor,
+        execute,
+        style,
+        terminal,
+    };
+
+    terminal::disable_raw_mode().ok();
+    execute!(std::io::stderr(), style::ResetColor, cursor::Show).ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_track_child() {
+        let guard = track_child(u32::MAX, "test");
+        assert!(SHUTDOWN.children.lock().contains_key(&u32::MAX));
+        drop(guard);
+        assert!(!SHUTDOWN.children.lock().contains_key(&u32::MAX));
+    }
+}
@@ -0,0 +1,196 @@
+//! Rendering timestamps and durations for people: in their timezone and date format, and
+//! relative to now when that is easier to read ("3m ago").
+
+use std::time::Duration;
+
+use chrono::{
+    DateTime,
+    Local,
+    TimeZone,
+    Utc,
+};
+
+/// Past this, a relative timestamp is less useful than the date.
+const RELATIVE_MAX_DAYS: i64 = 7;
+
+/// The date format of the user's locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeLocale {
+    /// `2025-03-14 16:05`, for the C locale or when it is unknown.
+    Iso,
+    /// `Mar 14, 2025 4:05 PM`
+    MonthFirst,
+    /// `14 Mar 2025 16:05`
+    DayFirst,
+}
+
+impl TimeLocale {
+    /// The locale from `LC_ALL`, `LC_TIME` or `LANG`, in the order they take precedence.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_TIME", "LANG"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()))
+            .map_or(Self::Iso, |locale| Self::from_locale(&locale))
+    }
+
+    /// The date format for a locale name such as `en_US.UTF-8`.
+    pub fn from_locale(locale: &str) -> Self {
+        let name = locale.split(['.', '@']).next().unwrap_or_default();
+        match name.split_once(['_', '-']) {
+            None => Self::Iso,
+            Some((_, "US" | "PH" | "FM" | "MH" | "PW")) => Self::MonthFirst,
+            Some(_) => Self::DayFirst,
+        }
+    }
+
+    fn format(self) -> &'static str {
+        match self {
+            Self::Iso => "%Y-%m-%d %H:%M",
+            Self::MonthFirst => "%b %-d, %Y %-I:%M %p",
+            Self::DayFirst => "%-d %b %Y %H:%M",
+        }
+    }
+}
+
+/// `time` in the local timezone and the user's date format.
+pub fn format_timestamp<Tz: TimeZone>(time: &DateTime<Tz>) -> String {
+    format_timestamp_in(time, TimeLocale::from_env())
+}
+
+pub fn format_timestamp_in<Tz: TimeZone>(time: &DateTime<Tz>, locale: TimeLocale) -> String {
+    time.with_timezone(&Local).format(locale.format()).to_string()
+}
+
+/// `time` relative to now if it's recent, such as `3m ago`, otherwise the date.
+pub fn humanize<Tz: TimeZone>(time: &DateTime<Tz>) -> String {
+    humanize_at(time, Utc::now(), TimeLocale::from_env())
+}
+
+pub fn humanize_at<Tz: TimeZone>(time: &DateTime<Tz>, now: DateTime<Utc>, locale: TimeLocale) -> String {
+    let elapsed = now.signed_duration_since(time.with_timezone(&Utc));
+    if elapsed.num_days().abs() >= RELATIVE_MAX_DAYS {
+        return format_timestamp_in(time, locale);
+    }
+    // Times in the future happen with clock skew between machines.
+    let (elapsed, future) = match elapsed.to_std() {
+        Ok(elapsed) => (elapsed, false),
+        Err(_) => ((-elapsed).to_std().unwrap_or_default(), true),
+    };
+    if elapsed < Duration::from_secs(60) {
+        "just now".to_string()
+    } else if future {
+        format!("in {}", format_duration_coarse(elapsed))
+    } else {
+        format!("{} ago", format_duration_coarse(elapsed))
+    }
+}
+
+/// A duration in its two largest units, such as `850ms`, `42s`, `3m 12s` or `2h 5m`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0 => format!("{}ms", duration.subsec_millis()),
+        1..60 => format!("{secs}s"),
+        60..3600 => join_units(secs / 60, "m", secs % 60, "s"),
+        3600..86400 => join_units(secs / 3600, "h", secs % 3600 / 60, "m"),
+        _ => join_units(secs / 86400, "d", secs % 86400 / 3600, "h"),
+    }
+}
+
+/// A duration in its largest unit, such as `3m` or `2h`.
+fn format_duration_coarse(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+/// Parses a duration written the way [format_duration] writes them, such as `30d`, `12h` or
+/// `2w`, for arguments such as `--older-than`.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let (amount, unit) = text.split_at(split);
+    let not_a_duration = || format!("`{text}` is not a duration, such as 30d or 12h");
+    let amount = amount
+        .parse::<u64>()
+        .map_err(|err| format!("{}: {err}", not_a_duration()))?;
+    let unit_secs = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86400,
+        "w" => 7 * 86400,
+        unit => return Err(format!("`{unit}` is not a unit of duration, use s, m, h, d or w")),
+    };
+    amount
+        .checked_mul(unit_secs)
+        .map(Duration::from_secs)
+        .ok_or_else(not_a_duration)
+}
+
+fn join_units(major: u64, major_unit: &str, minor: u64, minor_unit: &str) -> String {
+    match minor {
+        0 => format!("{major}{major_unit}"),
+        _ => format!("{major}{major_unit} {minor}{minor_unit}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+
+    #[test]
+    fn test_time_locale() {
+        assert_eq!(TimeLocale::from_locale("C"), TimeLocale::Iso);
+        assert_eq!(TimeLocale::from_locale("POSIX"), TimeLocale::Iso);
+        assert_eq!(TimeLocale::from_locale("en_US.UTF-8"), TimeLocale::MonthFirst);
+        assert_eq!(TimeLocale::from_locale("en-US"), TimeLocale::MonthFirst);
+        assert_eq!(TimeLocale::from_locale("en_GB.UTF-8"), TimeLocale::DayFirst);
+        assert_eq!(TimeLocale::from_locale("de_DE@euro"), TimeLocale::DayFirst);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(850)), "850ms");
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(192)), "3m 12s");
+        assert_eq!(format_duration(Duration::from_secs(180)), "3m");
+        assert_eq!(format_duration(Duration::from_secs(7500)), "2h 5m");
+        assert_eq!(format_duration(Duration::from_secs(3 * 86400 + 4 * 3600)), "3d 4h");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * 86400)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert!(parse_duration("30").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("99999999999999999w").is_err());
+    }
+
+    #[test]
+    fn test_humanize() {
+        let now = Utc::now();
+        let humanize = |delta| humanize_at(&(now - delta), now, TimeLocale::Iso);
+        assert_eq!(humanize(TimeDelta::seconds(20)), "just now");
+        assert_eq!(humanize(TimeDelta::minutes(3)), "3m ago");
+        assert_eq!(humanize(TimeDelta::hours(5)), "5h ago");
+        assert_eq!(humanize(TimeDelta::days(2)), "2d ago");
+        assert_eq!(humanize(TimeDelta::minutes(-10)), "in 10m");
+
+        let old = now - TimeDelta::days(30);
+        assert_eq!(
+            humanize_at(&old, now, TimeLocale::Iso),
+            old.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string()
+        );
+    }
+}
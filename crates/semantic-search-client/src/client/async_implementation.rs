@@ -257,6 +257,7 @@ impl AsyncSemanticSearchClient {
     ///     include_patterns: Some(vec!["*.txt".to_string(), "*.md".to_string()]),
     ///     exclude_patterns: Some(vec!["*.tmp".to_string()]),
     ///     embedding_type: None, // Use default
+    ///     source: None,
     /// };
     ///
     /// let (operation_id, cancel_token) = client.add_context(request).await?;
@@ -310,6 +311,66 @@ impl AsyncSemanticSearchClient {
             include_patterns: request.include_patterns.clone(),
             exclude_patterns: request.exclude_patterns.clone(),
             embedding_type: request.embedding_type,
+            source: request.source.clone(),
+        };
+
+        self.job_tx
+            .send(job)
+            .map_err(|_send_error| SemanticSearchError::OperationFailed("Background worker unavailable".to_string()))?;
+
+        Ok((operation_id, cancel_token))
+    }
+
+    /// Updates a context with the changes to the files it was created from.
+    ///
+    /// This method starts a background operation that re-indexes only the files that were added
+    /// or changed since the context was last indexed, and drops the files that were removed.
+    /// Contexts indexed before file hashes were kept are re-indexed in full once.
+    ///
+    /// # Arguments
+    ///
+    /// * `context_id` - The unique identifier of the context to update
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result<(Uuid, CancellationToken)>` for tracking the update operation.
+    pub async fn update_context(&self, context_id: &str) -> Result<(Uuid, CancellationToken)> {
+        let context = self
+            .context_manager
+            .get_contexts()
+            .await
+            .into_iter()
+            .find(|c| c.id == context_id)
+            .ok_or_else(|| SemanticSearchError::ContextNotFound(context_id.to_string()))?;
+        let source_path = context.source_path.clone().ok_or_else(|| {
+            SemanticSearchError::InvalidArgument(format!("Context '{}' was not created from a path", context.name))
+        })?;
+        let canonical_path = PathBuf::from(&source_path).canonicalize().map_err(|_e| {
+            SemanticSearchError::InvalidPath(format!("Path does not exist or is not accessible: {}", source_path))
+        })?;
+
+        self.context_manager
+            .check_not_indexing(&canonical_path, &self.operation_manager)
+            .await?;
+
+        let operation_id = Uuid::new_v4();
+        let cancel_token = CancellationToken::new();
+
+        self.operation_manager
+            .register_operation(
+                operation_id,
+                OperationType::Indexing {
+                    name: context.name.clone(),
+                    path: source_path,
+                },
+                cancel_token.clone(),
+            )
+            .await;
+
+        let job = IndexingJob::UpdateContext {
+            id: operation_id,
+            cancel: cancel_token.clone(),
+            context_id: context_id.to_string(),
         };
 
         self.job_tx
@@ -1,8 +1,13 @@
-use std::path::PathBuf;
+use std::collections::HashSet;
+use std::path::{
+    Path,
+    PathBuf,
+};
 use std::sync::Arc;
 
 use tokio::sync::{
     Semaphore,
+    SemaphorePermit,
     mpsc,
 };
 use tokio_util::sync::CancellationToken;
@@ -21,6 +26,10 @@ use crate::client::{
 };
 use crate::config::SemanticSearchConfig;
 use crate::embedding::TextEmbedderTrait;
+use crate::processing::{
+    FileManifest,
+    process_file_with_config,
+};
 use crate::types::*;
 
 const MAX_CONCURRENT_OPERATIONS: usize = 3;
@@ -80,6 +89,7 @@ impl BackgroundWorker {
                     include_patterns,
                     exclude_patterns,
                     embedding_type,
+                    source,
                 } => {
                     let params = IndexingParams {
                         path,
@@ -89,10 +99,14 @@ impl BackgroundWorker {
                         include_patterns,
                         exclude_patterns,
                         embedding_type,
+                        source,
                     };
 
                     self.process_add_directory(id, params, cancel).await;
                 },
+                IndexingJob::UpdateContext { id, cancel, context_id } => {
+                    self.process_update_context(id, context_id, cancel).await;
+                },
                 IndexingJob::Clear { id, cancel } => {
                     self.process_clear(id, cancel).await;
                 },
@@ -114,14 +128,33 @@ impl BackgroundWorker {
             return;
         }
 
+        let Some(_permit) = self.acquire_indexing_slot(operation_id).await else {
+            return;
+        };
+
+        let result = self.perform_indexing(operation_id, params, cancel_token).await;
+
+        match result {
+            Ok(context_id) => {
+                debug!("Successfully indexed context: {}", context_id);
+                self.mark_operation_completed(operation_id).await;
+            },
+            Err(e) => {
+                tracing::error!("Indexing failed: {}", e);
+                self.mark_operation_failed(operation_id, e).await;
+            },
+        }
+    }
+
+    async fn acquire_indexing_slot(&self, operation_id: Uuid) -> Option<SemaphorePermit<'_>> {
         self.update_operation_status(operation_id, "Waiting in queue...".to_string())
             .await;
 
-        let _permit = match self.indexing_semaphore.try_acquire() {
+        match self.indexing_semaphore.try_acquire() {
             Ok(permit) => {
                 self.update_operation_status(operation_id, "Acquired slot, starting indexing...".to_string())
                     .await;
-                permit
+                Some(permit)
             },
             Err(_) => {
                 self.update_operation_status(
@@ -136,28 +169,15 @@ impl BackgroundWorker {
                     Ok(permit) => {
                         self.update_operation_status(operation_id, "Acquired slot, starting indexing...".to_string())
                             .await;
-                        permit
+                        Some(permit)
                     },
                     Err(_) => {
                         self.mark_operation_failed(operation_id, "Semaphore unavailable".to_string())
                             .await;
-                        return;
+                        None
                     },
                 }
             },
-        };
-
-        let result = self.perform_indexing(operation_id, params, cancel_token).await;
-
-        match result {
-            Ok(context_id) => {
-                debug!("Successfully indexed context: {}", context_id);
-                self.mark_operation_completed(operation_id).await;
-            },
-            Err(e) => {
-                tracing::error!("Indexing failed: {}", e);
-                self.mark_operation_failed(operation_id, e).await;
-            },
         }
     }
 
@@ -221,7 +241,7 @@ impl BackgroundWorker {
             return Err("Failed: Operation was cancelled before file processing".to_string());
         }
 
-        let items = self
+        let mut items = self
             .file_processor
             .process_directory_files(
                 &params.path,
@@ -238,6 +258,8 @@ impl BackgroundWorker {
             return Err("Failed: Operation was cancelled before semantic context creation".to_string());
         }
 
+        Self::attribute_items(&mut items, params.source.as_deref());
+
         let effective_embedding_type = params.embedding_type.unwrap_or(self.config.embedding_type);
 
         self.context_creator
@@ -253,6 +275,10 @@ impl BackgroundWorker {
             )
             .await?;
 
+        if let Err(e) = Self::build_manifest(&items).await.save(&context_dir) {
+            tracing::warn!("Failed to save the file manifest of context {}: {}", context_id, e);
+        }
+
         self.store_context_metadata(
             &context_id,
             &params.name,
@@ -263,12 +289,262 @@ impl BackgroundWorker {
             &params.exclude_patterns,
             file_count,
             effective_embedding_type,
+            params.source.clone(),
         )
         .await?;
 
         Ok(context_id)
     }
 
+    async fn process_update_context(&self, operation_id: Uuid, context_id: String, cancel_token: CancellationToken) {
+        debug!("Processing UpdateContext job: {}", context_id);
+
+        if cancel_token.is_cancelled() {
+            self.mark_operation_cancelled(operation_id).await;
+            return;
+        }
+
+        let Some(_permit) = self.acquire_indexing_slot(operation_id).await else {
+            return;
+        };
+
+        match self.perform_update(operation_id, &context_id, cancel_token).await {
+            Ok(()) => {
+                debug!("Successfully updated context: {}", context_id);
+                self.mark_operation_completed(operation_id).await;
+            },
+            Err(e) => {
+                tracing::error!("Updating failed: {}", e);
+                self.mark_operation_failed(operation_id, e).await;
+            },
+        }
+    }
+
+    /// Re-index the files of a context that were added or changed since it was indexed, and drop
+    /// the files that were removed. The data points of unchanged files are kept as they are.
+    async fn perform_update(
+        &self,
+        operation_id: Uuid,
+        context_id: &str,
+        cancel_token: CancellationToken,
+    ) -> std::result::Result<(), String> {
+        let context = {
+            let contexts = self.context_manager.get_contexts_ref().read().await;
+            contexts.get(context_id).cloned()
+        }
+        .ok_or_else(|| format!("Context '{}' not found", context_id))?;
+        let source_path = context
+            .source_path
+            .as_deref()
+            .map(PathBuf::from)
+            .ok_or_else(|| format!("Context '{}' was not created from a path", context.name))?;
+        if !source_path.exists() {
+            return Err(format!("Path '{}' does not exist", source_path.display()));
+        }
+
+        let context_dir = if context.persistent {
+            self.base_dir.join(context_id)
+        } else {
+            std::env::temp_dir().join("semantic_search").join(context_id)
+        };
+        let include_patterns = (!context.include_patterns.is_empty()).then(|| context.include_patterns.clone());
+        let exclude_patterns = (!context.exclude_patterns.is_empty()).then(|| context.exclude_patterns.clone());
+
+        self.update_operation_status(operation_id, "Checking for changed files...".to_string())
+            .await;
+        let files = self
+            .file_processor
+            .list_files(&source_path, &include_patterns, &exclude_patterns)
+            .await?;
+        if files.len() > self.config.max_files {
+            return Err(format!(
+                "Failed: Directory contains {} files, which exceeds the maximum limit of {} files",
+                files.len(),
+                self.config.max_files
+            ));
+        }
+
+        let indexed = FileManifest::load(&context_dir);
+        let mut manifest = FileManifest::default();
+        let mut unchanged = HashSet::new();
+        let mut items = Vec::new();
+        for (i, path) in files.iter().enumerate() {
+            if cancel_token.is_cancelled() {
+                return Err("Operation was cancelled during file processing".to_string());
+            }
+
+            let Ok(content) = tokio::fs::read(path).await else {
+                continue;
+            };
+            let hash = FileManifest::hash(&content);
+            if indexed.is_unchanged(path, &hash) {
+                unchanged.insert(path.to_string_lossy().to_string());
+            } else {
+                match process_file_with_config(path, Some(self.config.chunk_size), Some(self.config.chunk_overlap)) {
+                    Ok(mut file_items) => items.append(&mut file_items),
+                    Err(_) => continue,
+                }
+            }
+            manifest.insert(path, hash);
+
+            if (i + 1) % 10 == 0 {
+                self.update_operation_progress(
+                    operation_id,
+                    (i + 1) as u64,
+                    files.len() as u64,
+                    format!("Checking files ({}/{})", i + 1, files.len()),
+                )
+                .await;
+            }
+        }
+
+        // Files that are new or changed, and indexed files that changed or were removed
+        Self::attribute_items(&mut items, context.source.as_deref());
+
+        let changed_files = manifest.len() - unchanged.len();
+        let stale_files = indexed.len() - unchanged.len();
+        if changed_files == 0 && stale_files == 0 {
+            debug!("Context {} is up to date", context_id);
+            return Ok(());
+        }
+
+        self.update_operation_status(
+            operation_id,
+            format!("Re-indexing {} new or changed files...", changed_files),
+        )
+        .await;
+        let is_unchanged = |payload: &std::collections::HashMap<String, serde_json::Value>| {
+            payload
+                .get("path")
+                .and_then(|path| path.as_str())
+                .is_some_and(|path| unchanged.contains(path))
+        };
+
+        if context.embedding_type.is_bm25() {
+            let bm25_context = {
+                let bm25_contexts = self.context_manager.get_bm25_contexts_ref().read().await;
+                bm25_contexts.get(context_id).cloned()
+            }
+            .ok_or_else(|| format!("Context '{}' is not loaded", context.name))?;
+
+            let mut data_points = bm25_context
+                .lock()
+                .await
+                .get_data_points()
+                .iter()
+                .filter(|point| is_unchanged(&point.payload))
+                .cloned()
+                .collect::<Vec<_>>();
+            for item in &items {
+                let data_point = ContextCreator::create_bm25_data_point_from_item(item, 0)
+                    .map_err(|e| format!("Failed to create BM25 data point: {}", e))?;
+                data_points.push(data_point);
+            }
+            for (id, point) in data_points.iter_mut().enumerate() {
+                point.id = id;
+            }
+
+            let mut bm25_context = bm25_context.lock().await;
+            bm25_context
+                .replace_data_points(data_points)
+                .map_err(|e| format!("Failed to add BM25 data points: {}", e))?;
+            let _ = bm25_context.save();
+        } else {
+            let semantic_context = {
+                let volatile_contexts = self.context_manager.get_volatile_contexts_ref().read().await;
+                volatile_contexts.get(context_id).cloned()
+            }
+            .ok_or_else(|| format!("Context '{}' is not loaded", context.name))?;
+
+            let mut data_points = semantic_context
+                .lock()
+                .await
+                .get_data_points()
+                .iter()
+                .filter(|point| is_unchanged(&point.payload))
+                .cloned()
+                .collect::<Vec<_>>();
+            for (i, item) in items.iter().enumerate() {
+                if cancel_token.is_cancelled() {
+                    return Err("Operation was cancelled during embedding generation".to_string());
+                }
+
+                if i % 10 == 0 {
+                    self.update_operation_progress(
+                        operation_id,
+                        i as u64,
+                        items.len() as u64,
+                        format!("Generating embeddings ({}/{})", i, items.len()),
+                    )
+                    .await;
+                }
+
+                let data_point = ContextCreator::create_data_point_from_item(item, 0, &*self.embedder)
+                    .map_err(|e| format!("Failed to create data point: {}", e))?;
+                data_points.push(data_point);
+            }
+            for (id, point) in data_points.iter_mut().enumerate() {
+                point.id = id;
+            }
+
+            self.update_operation_status(operation_id, "Building vector index...".to_string())
+                .await;
+            let mut semantic_context = semantic_context.lock().await;
+            semantic_context
+                .replace_data_points(data_points)
+                .map_err(|e| format!("Failed to add data points: {}", e))?;
+            let _ = semantic_context.save();
+        }
+
+        if let Err(e) = manifest.save(&context_dir) {
+            tracing::warn!("Failed to save the file manifest of context {}: {}", context_id, e);
+        }
+
+        {
+            let mut contexts = self.context_manager.get_contexts_ref().write().await;
+            if let Some(context) = contexts.get_mut(context_id) {
+                context.item_count = files.len();
+                context.updated_at = chrono::Utc::now();
+            }
+        }
+        if context.persistent {
+            self.context_manager
+                .save_contexts_metadata(&self.base_dir)
+                .await
+                .map_err(|e| format!("Failed to save contexts metadata: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    /// Record the original document of items, for attribution in search results
+    fn attribute_items(items: &mut [serde_json::Value], source: Option<&str>) {
+        let Some(source) = source else {
+            return;
+        };
+        for item in items {
+            if let serde_json::Value::Object(metadata) = item {
+                metadata.insert("source".to_string(), serde_json::Value::String(source.to_string()));
+            }
+        }
+    }
+
+    /// Hash the files that items were read from
+    async fn build_manifest(items: &[serde_json::Value]) -> FileManifest {
+        let paths = items
+            .iter()
+            .filter_map(|item| item.get("path").and_then(|path| path.as_str()))
+            .collect::<HashSet<_>>();
+
+        let mut manifest = FileManifest::default();
+        for path in paths {
+            if let Ok(content) = tokio::fs::read(path).await {
+                manifest.insert(Path::new(path), FileManifest::hash(&content));
+            }
+        }
+        manifest
+    }
+
     async fn process_clear(&self, operation_id: Uuid, cancel_token: CancellationToken) {
         debug!("Processing Clear job");
 
@@ -422,8 +698,9 @@ impl BackgroundWorker {
         exclude_patterns: &Option<Vec<String>>,
         item_count: usize,
         embedding_type: crate::embedding::EmbeddingType,
+        source: Option<String>,
     ) -> std::result::Result<(), String> {
-        let context = KnowledgeContext::new(
+        let mut context = KnowledgeContext::new(
             context_id.to_string(),
             name,
             description,
@@ -436,6 +713,7 @@ impl BackgroundWorker {
             item_count,
             embedding_type,
         );
+        context.source = source;
 
         {
             let mut contexts = self.context_manager.get_contexts_ref().write().await;
@@ -1,4 +1,7 @@
-use std::path::Path;
+use std::path::{
+    Path,
+    PathBuf,
+};
 
 use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
@@ -90,6 +93,40 @@ impl FileProcessor {
         }
     }
 
+    /// List the files of a directory that would be indexed, for updating a context
+    pub async fn list_files(
+        &self,
+        dir_path: &Path,
+        include_patterns: &Option<Vec<String>>,
+        exclude_patterns: &Option<Vec<String>>,
+    ) -> std::result::Result<Vec<PathBuf>, String> {
+        let dir_path = dir_path.to_path_buf();
+        let pattern_filter = Self::create_pattern_filter(include_patterns, exclude_patterns)?;
+
+        tokio::task::spawn_blocking(move || {
+            walkdir::WalkDir::new(&dir_path)
+                .follow_links(true)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+                .filter(|e| {
+                    !e.path()
+                        .file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|s| s.starts_with('.'))
+                })
+                .filter(|e| {
+                    pattern_filter
+                        .as_ref()
+                        .is_none_or(|filter| filter.should_include(e.path()))
+                })
+                .map(|e| e.into_path())
+                .collect()
+        })
+        .await
+        .map_err(|e| format!("File listing task failed: {}", e))
+    }
+
     /// Process directory files
     #[allow(clippy::too_many_arguments)]
     pub async fn process_directory_files(
@@ -95,6 +95,12 @@ impl BM25Context {
         Ok(count)
     }
 
+    /// Replace all data points of the context and rebuild its index
+    pub fn replace_data_points(&mut self, data_points: Vec<BM25DataPoint>) -> Result<()> {
+        self.data_points = data_points;
+        self.rebuild_index()
+    }
+
     /// Search the context
     pub fn search(&self, query: &str, limit: usize) -> Vec<(usize, f32)> {
         match &self.index {
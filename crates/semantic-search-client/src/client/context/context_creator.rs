@@ -223,7 +223,7 @@ impl ContextCreator {
         Ok(())
     }
 
-    fn create_bm25_data_point_from_item(item: &serde_json::Value, id: usize) -> Result<BM25DataPoint> {
+    pub(crate) fn create_bm25_data_point_from_item(item: &serde_json::Value, id: usize) -> Result<BM25DataPoint> {
         let text = item.get("text").and_then(|v| v.as_str()).unwrap_or("");
 
         let payload: HashMap<String, serde_json::Value> = if let serde_json::Value::Object(map) = item {
@@ -241,7 +241,7 @@ impl ContextCreator {
         })
     }
 
-    fn create_data_point_from_item(
+    pub(crate) fn create_data_point_from_item(
         item: &serde_json::Value,
         id: usize,
         embedder: &dyn TextEmbedderTrait,
@@ -190,6 +190,33 @@ impl ContextManager {
         operation_manager: &crate::client::operation::OperationManager,
     ) -> Result<()> {
         // First check if there's already an ACTIVE indexing operation for this exact path
+        self.check_not_indexing(canonical_path, operation_manager).await?;
+
+        // Then check if path already exists in knowledge base contexts
+        if let Ok(contexts_guard) = self.contexts.try_read() {
+            for context in contexts_guard.values() {
+                if let Some(existing_path) = &context.source_path {
+                    let existing_path_buf = PathBuf::from(existing_path);
+                    if let Ok(existing_canonical) = existing_path_buf.canonicalize() {
+                        if existing_canonical == *canonical_path {
+                            return Err(crate::error::SemanticSearchError::InvalidArgument(format!(
+                                "Path already exists in knowledge base: {} (Context: '{}')",
+                                existing_path, context.name
+                            )));
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that no active indexing operation is running for the path
+    pub async fn check_not_indexing(
+        &self,
+        canonical_path: &Path,
+        operation_manager: &crate::client::operation::OperationManager,
+    ) -> Result<()> {
         if let Ok(operations) = operation_manager.get_active_operations().try_read() {
             for handle in operations.values() {
                 if let crate::types::OperationType::Indexing { path, name } = &handle.operation_type {
@@ -214,23 +241,6 @@ impl ContextManager {
                 }
             }
         }
-
-        // Then check if path already exists in knowledge base contexts
-        if let Ok(contexts_guard) = self.contexts.try_read() {
-            for context in contexts_guard.values() {
-                if let Some(existing_path) = &context.source_path {
-                    let existing_path_buf = PathBuf::from(existing_path);
-                    if let Ok(existing_canonical) = existing_path_buf.canonicalize() {
-                        if existing_canonical == *canonical_path {
-                            return Err(crate::error::SemanticSearchError::InvalidArgument(format!(
-                                "Path already exists in knowledge base: {} (Context: '{}')",
-                                existing_path, context.name
-                            )));
-                        }
-                    }
-                }
-            }
-        }
         Ok(())
     }
 
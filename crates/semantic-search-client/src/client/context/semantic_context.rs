@@ -101,6 +101,12 @@ impl SemanticContext {
         Ok(count)
     }
 
+    /// Replace all data points of the context and rebuild its index
+    pub fn replace_data_points(&mut self, data_points: Vec<DataPoint>) -> Result<()> {
+        self.data_points = data_points;
+        self.rebuild_index()
+    }
+
     /// Update the index with data points in a specific range
     pub fn update_index_by_range(&mut self, start_idx: usize, end_idx: usize) -> Result<()> {
         // If we don't have an index yet, or if the index is small and we're adding many points,
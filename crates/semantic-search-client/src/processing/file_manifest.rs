@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+use crate::client::utils;
+use crate::error::Result;
+
+/// Name of the manifest file in a context directory
+pub const MANIFEST_FILE: &str = "files.json";
+
+/// Content hashes of the files a context was built from
+///
+/// The manifest is kept next to the data of a context, so that updating the context only
+/// re-processes and re-embeds the files whose content changed since it was last indexed.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct FileManifest {
+    files: HashMap<String, String>,
+}
+
+impl FileManifest {
+    /// Load the manifest of a context directory
+    ///
+    /// Contexts indexed before manifests were kept have none, in which case every file is
+    /// considered changed.
+    pub fn load(context_dir: &Path) -> Self {
+        utils::load_json_from_file(&context_dir.join(MANIFEST_FILE)).unwrap_or_default()
+    }
+
+    /// Save the manifest to a context directory
+    pub fn save(&self, context_dir: &Path) -> Result<()> {
+        utils::save_json_to_file(&context_dir.join(MANIFEST_FILE), self)
+    }
+
+    /// Hash the content of a file
+    pub fn hash(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    /// Record the hash of a file
+    pub fn insert(&mut self, path: &Path, hash: String) {
+        self.files.insert(path.to_string_lossy().to_string(), hash);
+    }
+
+    /// Whether a file was indexed with the same content
+    pub fn is_unchanged(&self, path: &Path, hash: &str) -> bool {
+        self.files
+            .get(path.to_string_lossy().as_ref())
+            .is_some_and(|indexed| indexed == hash)
+    }
+
+    /// Number of files in the manifest
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether the manifest has no files
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = Path::new("/project/src/main.rs");
+
+        let mut manifest = FileManifest::load(temp_dir.path());
+        assert!(manifest.is_empty());
+        assert!(!manifest.is_unchanged(path, &FileManifest::hash(b"fn main() {}")));
+
+        manifest.insert(path, FileManifest::hash(b"fn main() {}"));
+        manifest.save(temp_dir.path()).unwrap();
+
+        let manifest = FileManifest::load(temp_dir.path());
+        assert_eq!(manifest.len(), 1);
+        assert!(manifest.is_unchanged(path, &FileManifest::hash(b"fn main() {}")));
+        assert!(!manifest.is_unchanged(path, &FileManifest::hash(b"fn main() { todo!() }")));
+    }
+}
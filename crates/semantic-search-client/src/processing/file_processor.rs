@@ -42,6 +42,9 @@ pub fn get_file_type(path: &Path) -> FileType {
         // Web and markup formats (text-based)
         Some("svg") => FileType::Text,
 
+        // PDF documents, indexed by their text
+        Some("pdf") => FileType::Pdf,
+
         // Code file extensions
         Some("rs") => FileType::Code,
         Some("py") => FileType::Code,
@@ -71,7 +74,7 @@ pub fn get_file_type(path: &Path) -> FileType {
             _ => FileType::Unknown,
         },
 
-        // Default to unknown (includes office docs, PDFs, etc.)
+        // Default to unknown (includes office docs, etc.)
         _ => FileType::Unknown,
     }
 }
@@ -113,15 +116,19 @@ pub fn process_file_with_config(
     }
 
     let file_type = get_file_type(path);
-    let content = fs::read_to_string(path).map_err(|e| {
-        SemanticSearchError::IoError(std::io::Error::new(
-            e.kind(),
-            format!("Failed to read file {}: {}", path.display(), e),
-        ))
-    })?;
+    let content = if file_type == FileType::Pdf {
+        read_pdf_text(path)?
+    } else {
+        fs::read_to_string(path).map_err(|e| {
+            SemanticSearchError::IoError(std::io::Error::new(
+                e.kind(),
+                format!("Failed to read file {}: {}", path.display(), e),
+            ))
+        })?
+    };
 
     match file_type {
-        FileType::Text | FileType::Markdown | FileType::Code | FileType::Json => {
+        FileType::Text | FileType::Markdown | FileType::Code | FileType::Json | FileType::Pdf => {
             // For text-based files (including JSON), chunk the content and create multiple data points
             // Use the configured chunk size and overlap
             let chunks = chunk_text(&content, chunk_size, chunk_overlap);
@@ -179,6 +186,30 @@ pub fn process_file_with_config(
     }
 }
 
+/// Extract the text of a PDF document
+fn read_pdf_text(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).map_err(|e| {
+        SemanticSearchError::IoError(std::io::Error::new(
+            e.kind(),
+            format!("Failed to read file {}: {}", path.display(), e),
+        ))
+    })?;
+
+    // The PDF parser panics on some malformed documents, which must not take down indexing
+    match std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(&bytes)) {
+        Ok(Ok(text)) => Ok(text),
+        Ok(Err(e)) => Err(SemanticSearchError::OperationFailed(format!(
+            "Failed to extract the text of {}: {}",
+            path.display(),
+            e
+        ))),
+        Err(_) => Err(SemanticSearchError::OperationFailed(format!(
+            "Failed to extract the text of {}: malformed PDF",
+            path.display()
+        ))),
+    }
+}
+
 /// Process a directory and extract content from all files
 ///
 /// # Arguments
@@ -260,9 +291,11 @@ mod tests {
             ("Main.RS", FileType::Code),
             ("README.MD", FileType::Markdown),
             ("notes.TXT", FileType::Text),
+            // PDF documents
+            ("document.pdf", FileType::Pdf),
+            ("Paper.PDF", FileType::Pdf),
             // Unknown files
             ("image.png", FileType::Unknown),
-            ("document.pdf", FileType::Unknown),
             ("binary.exe", FileType::Unknown),
             ("unknown_file", FileType::Unknown),
         ];
@@ -281,7 +314,6 @@ mod tests {
     fn test_unknown_file_types() {
         // Binary files and unsupported formats
         assert_eq!(get_file_type(&PathBuf::from("image.png")), FileType::Unknown);
-        assert_eq!(get_file_type(&PathBuf::from("document.pdf")), FileType::Unknown);
         assert_eq!(get_file_type(&PathBuf::from("archive.zip")), FileType::Unknown);
         assert_eq!(get_file_type(&PathBuf::from("binary.exe")), FileType::Unknown);
         assert_eq!(get_file_type(&PathBuf::from("data.db")), FileType::Unknown);
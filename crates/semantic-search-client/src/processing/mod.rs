@@ -1,8 +1,11 @@
+/// Content hashes of indexed files, for incremental updates
+pub mod file_manifest;
 /// File processing utilities for handling different file types and extracting content
 pub mod file_processor;
 /// Text chunking utilities for breaking down text into manageable pieces for embedding
 pub mod text_chunker;
 
+pub use file_manifest::FileManifest;
 pub use file_processor::{
     get_file_type,
     process_directory,
@@ -34,6 +34,8 @@ pub struct AddContextRequest {
     pub exclude_patterns: Option<Vec<String>>,
     /// Optional embedding type override for this context
     pub embedding_type: Option<EmbeddingType>,
+    /// Original document the path was fetched from, such as the URL of a web page
+    pub source: Option<String>,
 }
 
 /// Parameters for indexing operations (internal use)
@@ -56,6 +58,8 @@ pub struct IndexingParams {
     pub exclude_patterns: Option<Vec<String>>,
     /// Optional embedding type override (uses client default if None)
     pub embedding_type: Option<EmbeddingType>,
+    /// Original document the path was fetched from, such as the URL of a web page
+    pub source: Option<String>,
 }
 
 use crate::client::context::SemanticContext;
@@ -107,6 +111,11 @@ pub struct KnowledgeContext {
     /// Embedding type used for this context
     #[serde(default)]
     pub embedding_type: EmbeddingType,
+
+    /// Original document the context was fetched from, such as the URL of a web page. Its
+    /// search results carry it as their `source`, so that answers can cite it.
+    #[serde(default)]
+    pub source: Option<String>,
 }
 
 impl KnowledgeContext {
@@ -135,6 +144,7 @@ impl KnowledgeContext {
             persistent,
             item_count,
             embedding_type,
+            source: None,
         }
     }
 }
@@ -198,6 +208,8 @@ pub enum FileType {
     Json,
     /// Source code file (programming languages)
     Code,
+    /// PDF document, indexed by its text
+    Pdf,
     /// Unknown file type
     Unknown,
 }
@@ -390,6 +402,17 @@ pub enum IndexingJob {
         exclude_patterns: Option<Vec<String>>,
         /// Embedding type
         embedding_type: Option<EmbeddingType>,
+        /// Original document the path was fetched from
+        source: Option<String>,
+    },
+    /// Re-index the files of an existing context that changed since it was indexed
+    UpdateContext {
+        /// Operation ID
+        id: Uuid,
+        /// Cancellation token
+        cancel: CancellationToken,
+        /// ID of the context to update
+        context_id: String,
     },
     /// Clear all contexts job
     Clear {
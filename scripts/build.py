@@ -8,7 +8,7 @@ import shutil
 import time
 from typing import Any, Mapping, Sequence, List, Optional
 from const import APPLE_TEAM_ID, CHAT_BINARY_NAME, CHAT_PACKAGE_NAME
-from util import debug, info, isDarwin, isLinux, run_cmd, run_cmd_output, warn
+from util import debug, info, isDarwin, isLinux, isFips, isMinimal, run_cmd, run_cmd_output, warn
 from rust import cargo_cmd_name, rust_env, rust_targets
 from importlib import import_module
 
@@ -73,6 +73,12 @@ def build_chat_bin(
 
     args = [cargo_cmd_name(), "build", "--locked", "--package", package]
 
+    if isMinimal():
+        args.append("--no-default-features")
+
+    if isFips():
+        args.extend(["--features", "fips"])
+
     for target in targets:
         args.extend(["--target", target])
 
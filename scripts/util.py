@@ -37,6 +37,22 @@ def isMusl() -> bool:
     return os.environ.get("AMAZON_Q_BUILD_MUSL") is not None
 
 
+@cache
+def isMinimal() -> bool:
+    """
+    Whether to build without the default cargo features, e.g. for static musl builds.
+    """
+    return os.environ.get("AMAZON_Q_BUILD_MINIMAL") is not None
+
+
+@cache
+def isFips() -> bool:
+    """
+    Whether to build with the FIPS-validated TLS provider.
+    """
+    return os.environ.get("AMAZON_Q_BUILD_FIPS") is not None
+
+
 @cache
 def version() -> str:
     output = run_cmd_output(